@@ -0,0 +1,101 @@
+//! キャッシュの圧縮形式ごとのファイルサイズとロード時間の比較
+//!
+//! ロード時間はファイル読み込み + 展開 + デシリアライズ + インデックス登録の合計。
+//! 直前に書き込んだファイルを読むためページキャッシュに載った状態の計測になり、
+//! 展開のコストだけ圧縮ありの方が遅く出る。ディスク I/O が律速になる環境
+//! (コールドスタート・ネットワークドライブ) ではサイズの縮小分が効く。
+//!
+//! ```sh
+//! cargo bench --bench cache_load
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
+use angularjs_lsp::cache::{CacheLoader, CacheWriter, FileMetadata};
+use angularjs_lsp::config::CacheCompression;
+use angularjs_lsp::index::Index;
+use tower_lsp::lsp_types::Url;
+
+const ITERATIONS: u32 = 10;
+const FILES: usize = 500;
+
+fn controller_js(file: usize, controllers: usize) -> String {
+    let mut source = String::from("angular.module('app')\n");
+    for i in 0..controllers {
+        source.push_str(&format!(
+            ".controller('Ctrl{file}_{i}', ['$scope', 'UserService', function($scope, UserService) {{\n\
+             \x20   $scope.items{i} = [];\n\
+             \x20   $scope.selected{i} = null;\n\
+             \x20   $scope.load{i} = function() {{ return UserService.fetch({i}); }};\n\
+             }}])\n"
+        ));
+    }
+    source.push_str(";\n");
+    source
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+                    Ok(meta) => meta.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn main() {
+    let workspace =
+        std::env::temp_dir().join(format!("angularjs-lsp-cache-bench-{}", std::process::id()));
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+    let mut file_metadata: HashMap<PathBuf, FileMetadata> = HashMap::new();
+    for file in 0..FILES {
+        let path = workspace.join(format!("src/ctrl{file}.js"));
+        let uri = Url::from_file_path(&path).unwrap();
+        let source = controller_js(file, 20);
+        analyzer.analyze_document(&uri, &source);
+        let meta = FileMetadata {
+            mtime: 0,
+            size: source.len() as u64,
+        };
+        file_metadata.insert(path, meta);
+    }
+    let valid_files: HashSet<PathBuf> = file_metadata.keys().cloned().collect();
+
+    let mut baseline: Option<Duration> = None;
+    for (label, compression) in [
+        ("none", CacheCompression::None),
+        ("gzip", CacheCompression::Gzip),
+        ("zstd", CacheCompression::Zstd),
+    ] {
+        let root = workspace.join(label);
+        CacheWriter::new(&root)
+            .with_compression(compression)
+            .save_full(&index, &file_metadata)
+            .unwrap();
+        let loader = CacheLoader::new(&root);
+        let size = dir_size(loader.cache_dir());
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            loader.load(&Index::new(), &valid_files).unwrap();
+        }
+        let load = start.elapsed() / ITERATIONS;
+        let speedup = baseline.map_or(1.0, |b| b.as_secs_f64() / load.as_secs_f64());
+        baseline.get_or_insert(load);
+        println!("{label:<5} {size:>10} bytes  load: {load:>10.2?}  ({speedup:.2}x)");
+    }
+
+    let _ = fs::remove_dir_all(&workspace);
+}
@@ -0,0 +1,111 @@
+//! 大きなファイルの 1 文字編集に対する、フルパースと差分パースの比較
+//!
+//! 解析 (AST 走査) 自体のコストは変わらないため、パース時間のみを計測する。
+//!
+//! ```sh
+//! cargo bench --bench incremental_parse
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use angularjs_lsp::analyzer::html::parser::HtmlParser;
+use angularjs_lsp::analyzer::incremental::SyntaxTreeCache;
+use angularjs_lsp::analyzer::js::JsParser;
+use tower_lsp::lsp_types::Url;
+
+const ITERATIONS: u32 = 50;
+
+fn large_js(controllers: usize) -> String {
+    let mut source = String::from("angular.module('app', [])\n");
+    for i in 0..controllers {
+        source.push_str(&format!(
+            ".controller('Ctrl{i}', ['$scope', 'UserService', function($scope, UserService) {{\n\
+             \x20   $scope.items{i} = [];\n\
+             \x20   $scope.load{i} = function() {{ return UserService.fetch({i}); }};\n\
+             }}])\n"
+        ));
+    }
+    source.push_str(";\n");
+    source
+}
+
+fn large_html(blocks: usize) -> String {
+    let mut source = String::from("<html><body>\n");
+    for i in 0..blocks {
+        source.push_str(&format!(
+            "<div ng-controller=\"Ctrl{i}\">\n\
+             \x20 <ul><li ng-repeat=\"item in items{i}\" ng-click=\"load{i}()\">{{{{ item.name }}}}</li></ul>\n\
+             </div>\n"
+        ));
+    }
+    source.push_str("</body></html>\n");
+    source
+}
+
+/// 中央付近の識別子に 1 文字挿入した版を作る
+fn edit_middle(source: &str, needle: &str) -> String {
+    let pos = source[source.len() / 2..]
+        .find(needle)
+        .map(|p| p + source.len() / 2 + needle.len())
+        .expect("needle not found");
+    let mut edited = source.to_string();
+    edited.insert(pos, 'x');
+    edited
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn report(label: &str, bytes: usize, full: Duration, incremental: Duration) {
+    println!(
+        "{label:<5} {bytes:>8} bytes  full: {full:>10.2?}  incremental: {incremental:>10.2?}  ({:.1}x)",
+        full.as_secs_f64() / incremental.as_secs_f64()
+    );
+}
+
+fn bench_js() {
+    let uri = Url::parse("file:///bench/app.js").unwrap();
+    let original = large_js(2000);
+    let edited = edit_middle(&original, "$scope.items");
+
+    let mut parser = JsParser::for_uri(&uri);
+    let cache = SyntaxTreeCache::new();
+    cache.insert(&uri, &original, &parser.parse(&original).unwrap());
+    let full = measure(|| {
+        black_box(parser.parse(&edited));
+    });
+    let incremental = measure(|| {
+        let old_tree = cache.edited_tree(&uri, &edited);
+        black_box(parser.parse_incremental(&edited, old_tree.as_ref()));
+    });
+    report("JS", original.len(), full, incremental);
+}
+
+fn bench_html() {
+    let uri = Url::parse("file:///bench/index.html").unwrap();
+    let original = large_html(2000);
+    let edited = edit_middle(&original, "item.name");
+
+    let mut parser = HtmlParser::new();
+    let cache = SyntaxTreeCache::new();
+    cache.insert(&uri, &original, &parser.parse(&original).unwrap());
+    let full = measure(|| {
+        black_box(parser.parse(&edited));
+    });
+    let incremental = measure(|| {
+        let old_tree = cache.edited_tree(&uri, &edited);
+        black_box(parser.parse_incremental(&edited, old_tree.as_ref()));
+    });
+    report("HTML", original.len(), full, incremental);
+}
+
+fn main() {
+    bench_js();
+    bench_html();
+}
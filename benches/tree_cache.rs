@@ -0,0 +1,75 @@
+//! inlay hints の応答時間: Tree キャッシュなし (毎回フルパース) と、
+//! デバウンス解析で更新されたキャッシュを再利用した場合の比較
+//!
+//! ```sh
+//! cargo bench --bench tree_cache
+//! ```
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use angularjs_lsp::analyzer::incremental::SyntaxTreeCache;
+use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
+use angularjs_lsp::handler::InlayHintsHandler;
+use angularjs_lsp::index::Index;
+use dashmap::DashMap;
+use tower_lsp::lsp_types::Url;
+
+const ITERATIONS: u32 = 50;
+
+fn large_js(controllers: usize) -> String {
+    let mut source = String::from("angular.module('app', [])\n");
+    for i in 0..controllers {
+        source.push_str(&format!(
+            ".controller('Ctrl{i}', ['$scope', 'UserService', function(s, u) {{\n\
+             \x20   s.items{i} = [];\n\
+             \x20   s.load{i} = function() {{ return u.fetch({i}); }};\n\
+             }}])\n"
+        ));
+    }
+    source.push_str(";\n");
+    source
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let uri = Url::parse("file:///bench/app.js").unwrap();
+    let source = large_js(2000);
+
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+    analyzer.analyze_document_incremental(&uri, &source);
+
+    let documents = Arc::new(DashMap::new());
+    documents.insert(uri.clone(), source.clone());
+
+    // キャッシュなし: リクエストのたびに空のキャッシュからフルパース
+    let cold = measure(|| {
+        let handler = InlayHintsHandler::new(
+            Arc::clone(&index),
+            Arc::clone(&documents),
+            Arc::new(SyntaxTreeCache::new()),
+        );
+        black_box(handler.inlay_hints(&uri, None));
+    });
+
+    // 解析済み Tree を共有: パースを省略
+    let handler = InlayHintsHandler::new(Arc::clone(&index), Arc::clone(&documents), analyzer.tree_cache());
+    let warm = measure(|| {
+        black_box(handler.inlay_hints(&uri, None));
+    });
+
+    println!(
+        "inlay hints {:>8} bytes  cold: {cold:>10.2?}  shared tree: {warm:>10.2?}  ({:.1}x)",
+        source.len(),
+        cold.as_secs_f64() / warm.as_secs_f64()
+    );
+}
@@ -3,10 +3,32 @@
 use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
-use crate::model::{HtmlControllerScope, Span, SymbolReference};
+use crate::model::{HtmlChildScope, HtmlControllerScope, Span, SymbolReference};
 
 use super::HtmlAngularJsAnalyzer;
 
+/// `ng-controller` 属性値の解析結果
+pub(super) struct NgControllerAttribute {
+    pub controller_name: String,
+    pub alias: Option<String>,
+    /// コントローラー名の位置（クォート除く）
+    pub name_span: Span,
+    /// alias 名の位置
+    pub alias_span: Option<Span>,
+}
+
+/// 子スコープを作る組み込みディレクティブ
+///
+/// `ng-switch` 自体はスコープを作らず、各 case (`ng-switch-when` / `ng-switch-default`) が作る
+const CHILD_SCOPE_DIRECTIVES: &[&str] = &[
+    "ng-if",
+    "ng-repeat",
+    "ng-repeat-start",
+    "ng-include",
+    "ng-switch-when",
+    "ng-switch-default",
+];
+
 /// コントローラースコープ情報（収集時に使用）
 #[derive(Clone, Debug)]
 pub(super) struct ControllerScopeInfo {
@@ -41,13 +63,12 @@ impl HtmlAngularJsAnalyzer {
             // 開始タグから属性を取得
             if let Some(start_tag) = self.find_child_by_kind(node, "start_tag") {
                 // ng-controllerをチェック（位置情報付き）
-                if let Some((controller_name, alias, name_start_line, name_start_col, name_end_line, name_end_col)) =
-                    self.get_ng_controller_attribute_with_position(start_tag, source)
-                {
+                if let Some(attr) = self.get_ng_controller_attribute_with_position(start_tag, source) {
                     // ng-controllerスコープを登録
                     let scope = HtmlControllerScope {
-                        controller_name: controller_name.clone(),
-                        alias,
+                        controller_name: attr.controller_name.clone(),
+                        alias: attr.alias,
+                        alias_span: attr.alias_span,
                         uri: uri.clone(),
                         start_line: scope_start_line,
                         end_line: scope_end_line,
@@ -56,17 +77,21 @@ impl HtmlAngularJsAnalyzer {
 
                     // コントローラー名への参照を登録（定義ジャンプ用）
                     let reference = SymbolReference {
-                        name: controller_name,
+                        name: attr.controller_name,
                         uri: uri.clone(),
-                        span: Span::new(
-                            name_start_line,
-                            name_start_col,
-                            name_end_line,
-                            name_end_col,
-                        ),
+                        span: attr.name_span,
                     };
                     self.index.definitions.add_reference(reference);
                 }
+
+                // ng-if / ng-repeat などの子スコープを登録
+                if let Some(expression_span) = self.child_scope_directive_span(start_tag, source) {
+                    self.index.controllers.add_html_child_scope(HtmlChildScope {
+                        uri: uri.clone(),
+                        span: self.node_span_utf16(node, source),
+                        expression_span,
+                    });
+                }
             }
 
             // 子要素を再帰的に処理
@@ -88,23 +113,21 @@ impl HtmlAngularJsAnalyzer {
     /// 例: "UserController as vm" -> ("UserController", Some("vm"))
     pub(super) fn get_ng_controller_attribute(&self, start_tag: Node, source: &str) -> Option<(String, Option<String>)> {
         self.get_ng_controller_attribute_with_position(start_tag, source)
-            .map(|(name, alias, _, _, _, _)| (name, alias))
+            .map(|attr| (attr.controller_name, attr.alias))
     }
 
-    /// ng-controller属性の値と位置を取得
-    /// 戻り値: (コントローラー名, alias, start_line, start_col, end_line, end_col)
-    /// 位置はコントローラー名の位置（クォート除く）
+    /// ng-controller属性の値と、コントローラー名・alias の位置を取得
     pub(super) fn get_ng_controller_attribute_with_position(
         &self,
         start_tag: Node,
         source: &str,
-    ) -> Option<(String, Option<String>, u32, u32, u32, u32)> {
+    ) -> Option<NgControllerAttribute> {
         let mut cursor = start_tag.walk();
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name, source);
-                    if attr_name == "ng-controller" || attr_name == "data-ng-controller" {
+                    let attr_name = self.directive_name_text(name, source);
+                    if attr_name == "ng-controller" {
                         if let Some(value_node) = self.find_child_by_kind(child, "quoted_attribute_value") {
                             let raw_value = self.node_text(value_node, source);
                             // クォートを除去
@@ -112,30 +135,45 @@ impl HtmlAngularJsAnalyzer {
                             // "Controller as alias"形式をパース
                             let parts: Vec<&str> = value.split_whitespace().collect();
                             let controller_name = parts.first().unwrap_or(&value).to_string();
-                            let alias = if parts.len() >= 3 && parts[1].eq_ignore_ascii_case("as") {
-                                Some(parts[2].to_string())
-                            } else {
-                                None
-                            };
+                            let alias_token = parts
+                                .get(2)
+                                .copied()
+                                .filter(|_| parts[1].eq_ignore_ascii_case("as"));
+                            let alias = alias_token.map(str::to_string);
 
-                            // コントローラー名の位置を計算（クォートの後から、UTF-16 単位）
+                            // 属性値の位置を計算（クォートの後から、UTF-16 単位）
                             // tree-sitter の column は UTF-8 byte なので、同一行に
                             // 多バイト文字が含まれる場合は変換しないと LSP 側でずれる。
                             let start_line = value_node.start_position().row as u32;
-                            let start_byte_col = value_node.start_position().column + 1; // クォート分
-                            let start_col = self.byte_col_to_utf16_col(
+                            let value_byte_col = value_node.start_position().column + 1; // クォート分
+                            let value_col = self.byte_col_to_utf16_col(
                                 source,
                                 start_line as usize,
-                                start_byte_col,
+                                value_byte_col,
                             );
-                            let end_line = start_line;
-                            let end_col = start_col
-                                + controller_name
-                                    .chars()
-                                    .map(|c| c.len_utf16())
-                                    .sum::<usize>() as u32;
-
-                            return Some((controller_name, alias, start_line, start_col, end_line, end_col));
+                            // コントローラー名は値の先頭の空白の後ろから
+                            let name_offset = value.find(controller_name.as_str()).unwrap_or(0);
+                            let start_col = value_col + utf16_len(&value[..name_offset]);
+                            let end_col = start_col + utf16_len(&controller_name);
+
+                            // alias は属性値の 3 番目のトークン。位置は `value` 内のバイトオフセットから
+                            let alias_span = alias_token.map(|alias| {
+                                let offset = alias.as_ptr() as usize - value.as_ptr() as usize;
+                                let alias_start = value_col + utf16_len(&value[..offset]);
+                                Span::new(
+                                    start_line,
+                                    alias_start,
+                                    start_line,
+                                    alias_start + utf16_len(alias),
+                                )
+                            });
+
+                            return Some(NgControllerAttribute {
+                                controller_name,
+                                alias,
+                                name_span: Span::new(start_line, start_col, start_line, end_col),
+                                alias_span,
+                            });
                         }
                     }
                 }
@@ -144,26 +182,60 @@ impl HtmlAngularJsAnalyzer {
         None
     }
 
+    /// 子スコープを作るディレクティブ属性の値の範囲を取得 (値がなければ属性全体)
+    fn child_scope_directive_span(&self, start_tag: Node, source: &str) -> Option<Span> {
+        let mut cursor = start_tag.walk();
+        let attr = start_tag.children(&mut cursor).find(|child| {
+            child.kind() == "attribute"
+                && self
+                    .find_child_by_kind(*child, "attribute_name")
+                    .is_some_and(|name| {
+                        CHILD_SCOPE_DIRECTIVES.contains(&self.directive_name_text(name, source).as_str())
+                    })
+        })?;
+        let value = self.find_child_by_kind(attr, "quoted_attribute_value").unwrap_or(attr);
+        Some(self.node_span_utf16(value, source))
+    }
+
+    /// ノードの範囲を UTF-16 の `Span` にする
+    fn node_span_utf16(&self, node: Node, source: &str) -> Span {
+        let start = node.start_position();
+        let end = node.end_position();
+        Span::new(
+            start.row as u32,
+            self.byte_col_to_utf16_col(source, start.row, start.column),
+            end.row as u32,
+            self.byte_col_to_utf16_col(source, end.row, end.column),
+        )
+    }
+
     /// ng-include属性またはsrc属性（<ng-include>要素用）の値を取得
-    pub(super) fn get_ng_include_attribute(&self, start_tag: Node, source: &str) -> Option<String> {
+    ///
+    /// パスと共に属性値ノード（`quoted_attribute_value`）を返す
+    pub(super) fn get_ng_include_attribute<'a>(
+        &self,
+        start_tag: Node<'a>,
+        source: &str,
+    ) -> Option<(String, Node<'a>)> {
         // タグ名を取得
         let tag_name_node = self.find_child_by_kind(start_tag, "tag_name");
-        let tag_name = tag_name_node.map(|n| self.node_text(n, source));
+        let tag_name = tag_name_node.map(|n| self.directive_name_text(n, source));
 
         let mut cursor = start_tag.walk();
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name, source);
+                    let attr_name = self.directive_name_text(name, source);
 
                     // ng-include属性または<ng-include>要素のsrc属性をチェック
-                    let is_ng_include = attr_name == "ng-include" || attr_name == "data-ng-include";
-                    let is_ng_include_src = (tag_name.as_deref() == Some("ng-include") || tag_name.as_deref() == Some("data-ng-include"))
-                        && attr_name == "src";
+                    let is_ng_include = attr_name == "ng-include";
+                    let is_ng_include_src = tag_name.as_deref() == Some("ng-include") && attr_name == "src";
 
                     if is_ng_include || is_ng_include_src {
-                        if let Some(value) = self.find_child_by_kind(child, "quoted_attribute_value") {
-                            let raw_value = self.node_text(value, source);
+                        if let Some(value_node) =
+                            self.find_child_by_kind(child, "quoted_attribute_value")
+                        {
+                            let raw_value = self.node_text(value_node, source);
                             // 外側のクォート（最初と最後の1文字）を除去
                             let value = if raw_value.len() >= 2 {
                                 &raw_value[1..raw_value.len() - 1]
@@ -172,7 +244,7 @@ impl HtmlAngularJsAnalyzer {
                             };
                             // 文字列リテラル部分を抽出
                             if let Some(template_path) = self.extract_string_literal(value) {
-                                return Some(template_path);
+                                return Some((template_path, value_node));
                             }
                         }
                     }
@@ -210,21 +282,21 @@ impl HtmlAngularJsAnalyzer {
     /// - `<ng-view>` / `<data-ng-view>` タグ
     /// - `ng-view` / `data-ng-view` 属性を持つ要素
     pub(super) fn is_ng_view_element(&self, start_tag: Node, source: &str) -> bool {
-        // タグ名をチェック（<ng-view>または<data-ng-view>）
+        // タグ名をチェック（<ng-view>。<data-ng-view> などの表記ゆれは正規化済み）
         if let Some(tag_name_node) = self.find_child_by_kind(start_tag, "tag_name") {
-            let tag_name = self.node_text(tag_name_node, source);
-            if tag_name == "ng-view" || tag_name == "data-ng-view" {
+            let tag_name = self.directive_name_text(tag_name_node, source);
+            if tag_name == "ng-view" {
                 return true;
             }
         }
 
-        // 属性をチェック（ng-viewまたはdata-ng-view属性）
+        // 属性をチェック（ng-view属性）
         let mut cursor = start_tag.walk();
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
-                    if attr_name == "ng-view" || attr_name == "data-ng-view" {
+                    let attr_name = self.directive_name_text(name_node, source);
+                    if attr_name == "ng-view" {
                         return true;
                     }
                 }
@@ -234,3 +306,8 @@ impl HtmlAngularJsAnalyzer {
         false
     }
 }
+
+/// 文字列の UTF-16 単位の長さ
+fn utf16_len(s: &str) -> u32 {
+    s.chars().map(|c| c.len_utf16()).sum::<usize>() as u32
+}
@@ -4,7 +4,7 @@ use phf::phf_set;
 use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
-use super::directives::is_ng_directive;
+use super::directives::{is_interpolation_directive, is_ng_directive};
 use super::HtmlAngularJsAnalyzer;
 use crate::model::{DirectiveUsageType, HtmlDirectiveReference};
 
@@ -141,6 +141,10 @@ impl HtmlAngularJsAnalyzer {
             if let Some(start_tag) = self.find_child_by_kind(node, "start_tag") {
                 self.extract_directive_from_tag(start_tag, source, uri);
             }
+            // 閉じタグ (`</user-card>`) の要素名も参照として扱う
+            if let Some(end_tag) = self.find_child_by_kind(node, "end_tag") {
+                self.extract_element_directive(end_tag, source, uri);
+            }
         }
 
         // 自己終了タグの場合
@@ -155,56 +159,59 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// タグ名が潜在的なカスタム要素なら Element 使用のディレクティブ参照として登録
+    ///
+    /// `.component('userList', ...)` の要素使用もここで 1 回だけ登録し、
+    /// コンポーネントの参照としては `Index::get_all_references` が引き当てる
+    fn extract_element_directive(&self, tag_node: Node, source: &str, uri: &Url) {
+        let Some(tag_name_node) = self.find_child_by_kind(tag_node, "tag_name") else {
+            return;
+        };
+        let tag_name = self.directive_name_text(tag_name_node, source);
+
+        // カスタム要素の可能性があるかチェック
+        if is_potential_custom_element(&tag_name) {
+            let camel_name = kebab_to_camel_case(&tag_name);
+            let start = tag_name_node.start_position();
+            let end = tag_name_node.end_position();
+
+            let reference = HtmlDirectiveReference {
+                directive_name: camel_name,
+                uri: uri.clone(),
+                start_line: start.row as u32,
+                start_col: self.byte_col_to_utf16_col(source, start.row, start.column),
+                end_line: end.row as u32,
+                end_col: self.byte_col_to_utf16_col(source, end.row, end.column),
+                usage_type: DirectiveUsageType::Element,
+            };
+            self.index.html.add_html_directive_reference(reference);
+        }
+    }
+
     /// タグからディレクティブ参照を抽出
     ///
     /// ハイフンを含む要素名・属性名を全て潜在的なカスタムディレクティブとして登録する。
     /// 定義の有無は定義ジャンプ時にチェックするため、解析順序に依存しない。
     fn extract_directive_from_tag(&self, tag_node: Node, source: &str, uri: &Url) {
         // 1. 要素名としてのディレクティブをチェック
-        if let Some(tag_name_node) = self.find_child_by_kind(tag_node, "tag_name") {
-            let tag_name = self.node_text(tag_name_node, source);
-
-            // カスタム要素の可能性があるかチェック
-            if is_potential_custom_element(&tag_name) {
-                let camel_name = kebab_to_camel_case(&tag_name);
-                let start = tag_name_node.start_position();
-                let end = tag_name_node.end_position();
-
-                let reference = HtmlDirectiveReference {
-                    directive_name: camel_name,
-                    uri: uri.clone(),
-                    start_line: start.row as u32,
-                    start_col: self.byte_col_to_utf16_col(source, start.row, start.column),
-                    end_line: end.row as u32,
-                    end_col: self.byte_col_to_utf16_col(source, end.row, end.column),
-                    usage_type: DirectiveUsageType::Element,
-                };
-                self.index.html.add_html_directive_reference(reference);
-            }
-        }
+        self.extract_element_directive(tag_node, source, uri);
 
         // 2. 属性としてのディレクティブをチェック
         let mut cursor = tag_node.walk();
         for child in tag_node.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
-
-                    // data- プレフィックスを除去
-                    let normalized_attr = if attr_name.starts_with("data-") {
-                        &attr_name[5..]
-                    } else {
-                        &attr_name
-                    };
+                    // `data-` / `x-` 接頭辞などの表記ゆれは正規化済み
+                    let attr_name = self.directive_name_text(name_node, source);
 
-                    // ビルトインng-*ディレクティブは除外
-                    if is_ng_directive(&attr_name) {
+                    // ビルトインng-*ディレクティブ (`ng-attr-*` を含む) は除外
+                    if is_ng_directive(&attr_name) || is_interpolation_directive(&attr_name) {
                         continue;
                     }
 
                     // カスタムディレクティブの可能性があるかチェック
-                    if is_potential_custom_directive(normalized_attr) {
-                        let camel_name = kebab_to_camel_case(normalized_attr);
+                    if is_potential_custom_directive(&attr_name) {
+                        let camel_name = kebab_to_camel_case(&attr_name);
                         let start = name_node.start_position();
                         let end = name_node.end_position();
 
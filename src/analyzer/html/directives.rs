@@ -1,67 +1,94 @@
 //! AngularJS directive definitions
 
+use std::borrow::Cow;
+
 use phf::phf_set;
 
+use crate::config::ExpressionAttributeMode;
+use crate::index::component_store::binding_attribute_name;
 use crate::index::Index;
-use crate::model::SymbolKind;
+use crate::model::{Symbol, SymbolKind};
 use crate::util::kebab_to_camel;
 
 /// AngularJS directive set (O(1) lookup)
+///
+/// 値を持つ AngularJS 公式ディレクティブ (`ng` / ngMessages / ngTouch) と既知ライブラリ。
+/// 値の解釈 (式全体 / 補間テキスト / リテラル) は [`is_literal_value_directive`] と
+/// [`is_interpolation_directive`] で分類する。`ng-controller` / `ng-include` /
+/// `ng-form` のように専用の解析がある属性は含まない
 static NG_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
     // Data binding
-    "ng-model", "data-ng-model",
-    "ng-bind", "data-ng-bind",
-    "ng-bind-html", "data-ng-bind-html",
-    "ng-value", "data-ng-value",
-    "ng-init", "data-ng-init",
+    "ng-model",
+    "ng-bind",
+    "ng-bind-html",
+    "ng-bind-template",
+    "ng-value",
+    "ng-init",
+    "ng-model-options",
+    "ng-list",
+    "ng-trim",
     // Conditionals & loops
-    "ng-if", "data-ng-if",
-    "ng-show", "data-ng-show",
-    "ng-hide", "data-ng-hide",
-    "ng-repeat", "data-ng-repeat",
-    "ng-switch", "data-ng-switch",
-    "ng-switch-when", "data-ng-switch-when",
+    "ng-if",
+    "ng-show",
+    "ng-hide",
+    "ng-repeat",
+    "ng-switch",
+    "ng-switch-when",
     // Style & class
-    "ng-class", "data-ng-class",
-    "ng-style", "data-ng-style",
-    // Form validation
-    "ng-disabled", "data-ng-disabled",
-    "ng-checked", "data-ng-checked",
-    "ng-selected", "data-ng-selected",
-    "ng-readonly", "data-ng-readonly",
-    "ng-required", "data-ng-required",
-    "ng-pattern", "data-ng-pattern",
-    "ng-minlength", "data-ng-minlength",
-    "ng-maxlength", "data-ng-maxlength",
+    "ng-class",
+    "ng-class-even",
+    "ng-class-odd",
+    "ng-style",
+    // Boolean attributes (式の真偽値で属性を付け外しする)
+    "ng-disabled",
+    "ng-checked",
+    "ng-selected",
+    "ng-readonly",
+    "ng-required",
+    "ng-open",
+    // Form validation (aliased attributes)
+    "ng-pattern",
+    "ng-minlength",
+    "ng-maxlength",
+    "ng-min",
+    "ng-max",
+    "ng-step",
+    // checkbox / radio の値 (`ng-true-value="'YES'"` のような定数式)
+    "ng-true-value",
+    "ng-false-value",
     // Event handlers
-    "ng-click", "data-ng-click",
-    "ng-dblclick", "data-ng-dblclick",
-    "ng-change", "data-ng-change",
-    "ng-submit", "data-ng-submit",
-    "ng-blur", "data-ng-blur",
-    "ng-focus", "data-ng-focus",
-    "ng-keydown", "data-ng-keydown",
-    "ng-keyup", "data-ng-keyup",
-    "ng-keypress", "data-ng-keypress",
-    "ng-mousedown", "data-ng-mousedown",
-    "ng-mouseup", "data-ng-mouseup",
-    "ng-mouseenter", "data-ng-mouseenter",
-    "ng-mouseleave", "data-ng-mouseleave",
-    "ng-mousemove", "data-ng-mousemove",
-    "ng-mouseover", "data-ng-mouseover",
-    "ng-copy", "data-ng-copy",
-    "ng-cut", "data-ng-cut",
-    "ng-paste", "data-ng-paste",
+    "ng-click",
+    "ng-dblclick",
+    "ng-change",
+    "ng-submit",
+    "ng-blur",
+    "ng-focus",
+    "ng-keydown",
+    "ng-keyup",
+    "ng-keypress",
+    "ng-mousedown",
+    "ng-mouseup",
+    "ng-mouseenter",
+    "ng-mouseleave",
+    "ng-mousemove",
+    "ng-mouseover",
+    "ng-copy",
+    "ng-cut",
+    "ng-paste",
+    // ngTouch
+    "ng-swipe-left",
+    "ng-swipe-right",
     // Select
-    "ng-options", "data-ng-options",
+    "ng-options",
     // href/src
-    "ng-href", "data-ng-href",
-    "ng-src", "data-ng-src",
-    "ng-srcset", "data-ng-srcset",
+    "ng-href",
+    "ng-src",
+    "ng-srcset",
     // ng-messages
-    "ng-messages", "data-ng-messages",
-    "ng-message", "data-ng-message",
-    "ng-messages-include", "data-ng-messages-include",
+    "ng-messages",
+    "ng-message",
+    "ng-message-exp",
+    "ng-messages-include",
     // angular-file-upload (ngf-*)
     "ngf-select", "ngf-drop", "ngf-drop-available",
     "ngf-multiple", "ngf-keep", "ngf-keep-distinct",
@@ -89,12 +116,181 @@ static NG_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
     "popover-placement", "popover-trigger", "popover-append-to-body",
 };
 
+/// 属性名 / 要素名を AngularJS の正規化規則に沿った kebab-case にそろえる
+///
+/// `$compile` の `directiveNormalize` と同じく `x-` / `data-` 接頭辞を外し、`:` / `_`
+/// 区切りを `-` にする。HTML の属性名は大文字小文字を区別しないので小文字にもそろえる。
+/// `data-ng-controller` / `x-ng-controller` / `ng:controller` / `NG-Controller` は
+/// すべて `ng-controller` になる。表記ゆれがなければ入力をそのまま借用して返す。
+///
+/// 小文字化と区切りの置換は新しい文字列を要するので `Option<&str>` ではなく `Cow` を返す。
+/// どの属性名も何らかの名前に正規化されるため、`None` にあたる場合はない
+pub fn normalize_directive_attr(name: &str) -> Cow<'_, str> {
+    let strip = |name: &str| -> usize {
+        ["data-", "x-"]
+            .iter()
+            .find(|prefix| name.starts_with(*prefix))
+            .map_or(0, |prefix| prefix.len())
+    };
+    if name.bytes().any(|b| b.is_ascii_uppercase() || b == b':' || b == b'_') {
+        let name = name.to_ascii_lowercase().replace([':', '_'], "-");
+        return Cow::Owned(name[strip(&name)..].to_string());
+    }
+    Cow::Borrowed(&name[strip(name)..])
+}
+
 /// Check if attribute name is a supported AngularJS directive
 pub fn is_ng_directive(attr_name: &str) -> bool {
-    NG_DIRECTIVE_SET.contains(attr_name)
+    NG_DIRECTIVE_SET.contains(normalize_directive_attr(attr_name).as_ref())
+}
+
+/// 値を読むだけの式を取る組み込みディレクティブ集合
+///
+/// これらの式の代入 (`ng-if="status = 'active'"`) は `==` の書き間違いとみなす。
+/// `ng-init` やイベントハンドラ、ライブラリのディレクティブ (`uib-tooltip` の補間文字列など)
+/// は代入が正当か判断できないので含めない
+static READ_ONLY_EXPRESSION_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
+    "ng-if",
+    "ng-show",
+    "ng-hide",
+    "ng-switch",
+    "ng-class",
+    "ng-class-odd",
+    "ng-class-even",
+    "ng-style",
+    "ng-disabled",
+    "ng-readonly",
+    "ng-required",
+    "ng-selected",
+    "ng-checked",
+    "ng-open",
+    "ng-bind",
+    "ng-bind-html",
+    "ng-value",
+};
+
+/// 式中の代入を誤用として診断すべき属性か判定
+/// ([`READ_ONLY_EXPRESSION_DIRECTIVE_SET`] のディレクティブ)
+pub fn disallows_assignment(attr_name: &str) -> bool {
+    READ_ONLY_EXPRESSION_DIRECTIVE_SET.contains(normalize_directive_attr(attr_name).as_ref())
+}
+
+/// ハンドラ式で `$event` (DOM / jQuery のイベントオブジェクト) を参照できるディレクティブ集合
+///
+/// `ng-change` は ngModel の値の変更で評価されるのでイベントオブジェクトを渡さない
+static EVENT_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
+    "ng-click",
+    "ng-dblclick",
+    "ng-submit",
+    "ng-blur",
+    "ng-focus",
+    "ng-keydown",
+    "ng-keyup",
+    "ng-keypress",
+    "ng-mousedown",
+    "ng-mouseup",
+    "ng-mouseenter",
+    "ng-mouseleave",
+    "ng-mousemove",
+    "ng-mouseover",
+    "ng-copy",
+    "ng-cut",
+    "ng-paste",
+    // ngTouch
+    "ng-swipe-left",
+    "ng-swipe-right",
+};
+
+/// ハンドラ式で `$event` を参照できるイベントディレクティブか判定
+pub fn provides_event(attr_name: &str) -> bool {
+    EVENT_DIRECTIVE_SET.contains(normalize_directive_attr(attr_name).as_ref())
 }
 
-/// 値が **Angular 式ではなくリテラル文字列 / 正規表現 / 補間テンプレート**
+/// 属性名補完で提示する AngularJS 1.x 組み込みディレクティブ
+/// (`ng` モジュール + ngRoute の `ng-view` + ngMessages)。
+///
+/// `NG_DIRECTIVE_SET` は「値を Angular 式として解析するか」の判定用なので、
+/// `ng-app` / `ng-cloak` のように値を持たない属性は含まない。補完ではそれらも
+/// 提示したいので別リストとして持つ。`data-` 接頭辞版は補完側で生成する。
+pub static NG_BUILTIN_ATTRIBUTE_DIRECTIVES: &[&str] = &[
+    "ng-app",
+    "ng-bind",
+    "ng-bind-html",
+    "ng-bind-template",
+    "ng-blur",
+    "ng-change",
+    "ng-checked",
+    "ng-class",
+    "ng-class-even",
+    "ng-class-odd",
+    "ng-click",
+    "ng-cloak",
+    "ng-controller",
+    "ng-copy",
+    "ng-csp",
+    "ng-cut",
+    "ng-dblclick",
+    "ng-disabled",
+    "ng-false-value",
+    "ng-focus",
+    "ng-form",
+    "ng-hide",
+    "ng-href",
+    "ng-if",
+    "ng-include",
+    "ng-init",
+    "ng-jq",
+    "ng-keydown",
+    "ng-keypress",
+    "ng-keyup",
+    "ng-list",
+    "ng-max",
+    "ng-maxlength",
+    "ng-message",
+    "ng-message-exp",
+    "ng-messages",
+    "ng-messages-include",
+    "ng-min",
+    "ng-minlength",
+    "ng-model",
+    "ng-model-options",
+    "ng-mousedown",
+    "ng-mouseenter",
+    "ng-mouseleave",
+    "ng-mousemove",
+    "ng-mouseover",
+    "ng-mouseup",
+    "ng-non-bindable",
+    "ng-open",
+    "ng-options",
+    "ng-paste",
+    "ng-pattern",
+    "ng-pluralize",
+    "ng-readonly",
+    "ng-ref",
+    "ng-repeat",
+    "ng-repeat-end",
+    "ng-repeat-start",
+    "ng-required",
+    "ng-selected",
+    "ng-show",
+    "ng-src",
+    "ng-srcset",
+    "ng-step",
+    "ng-strict-di",
+    "ng-style",
+    "ng-submit",
+    "ng-switch",
+    "ng-switch-default",
+    "ng-switch-when",
+    "ng-transclude",
+    "ng-trim",
+    "ng-true-value",
+    "ng-value",
+    "ng-view",
+];
+
+/// 値が **Angular 式ではなくリテラル文字列 / 正規表現**
 /// として解釈されるディレクティブ集合。
 ///
 /// これらはディレクティブ自体は AngularJS が認識するが、属性値はスコープ参照
@@ -103,46 +299,102 @@ pub fn is_ng_directive(attr_name: &str) -> bool {
 /// **literal string match 系:**
 /// - `ng-message="required"` — `$error.required` の検証キー名
 /// - `ng-messages-include="error-messages.html"` — テンプレート URL
-/// - `ng-switch-when="red"` — `ng-switch` の値との string match (case ラベル)
+/// - `ng-switch-when="red"` — `ng-switch` の値との string match (case ラベル)。
+///   ただしスコープ変数として定義済みの識別子は参照として扱う ([`ng_switch_when_variable`])
 ///
 /// **regex literal 系:**
 /// - `ng-pattern="/^\d+$/"` — 値は正規表現リテラルまたは正規表現文字列。
 ///   AngularJS が `$eval` の結果を `RegExp` として使う。一般的な使用は
 ///   インライン正規表現で scope 変数参照ではない
 ///
-/// **interpolation-only template 系:**
-/// - `ng-src="{{vm.imageUrl}}"` — 値は補間テンプレート (`{{}}` を含む文字列)。
-///   AngularJS は補間後の文字列を src 属性に設定する。bare expression として
-///   `ng-src="vm.imageUrl"` と書いても展開されないので、補間のみ抽出すれば足りる
+/// 補間テキストとして解釈されるディレクティブは [`INTERPOLATION_DIRECTIVE_SET`]。
 ///
 /// 参考: AngularJS source (`ngSwitchWhenDirective`) は `attrs.ngSwitchWhen` を
 /// `$eval` せず literal として `ctrl.cases['!' + value]` のキーに使っている。
 static LITERAL_VALUE_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
     // literal string match
-    "ng-message", "data-ng-message",
-    "ng-messages-include", "data-ng-messages-include",
-    "ng-switch-when", "data-ng-switch-when",
+    "ng-message",
+    "ng-messages-include",
+    "ng-switch-when",
     // regex literal
-    "ng-pattern", "data-ng-pattern",
-    // interpolation-only template
-    "ng-src", "data-ng-src",
+    "ng-pattern",
+    // ngList の区切り文字 / ngTrim の "false"
+    "ng-list",
+    "ng-trim",
+};
+
+/// 値が **補間テキスト** (`{{ }}` を含みうる文字列) として解釈されるディレクティブ集合。
+///
+/// AngularJS は `$interpolate` した結果の文字列を属性に設定するので、
+/// `ng-href="/users/{{ vm.id }}"` の `/users/` は式ではない。
+/// `ng-src="vm.imageUrl"` のように bare expression で書いても展開されないため、
+/// 補間部分だけをスコープ参照として抽出する。
+/// - `ng-href` / `ng-src` / `ng-srcset` — URL 属性
+/// - `ng-bind-template="{{a}} {{b}}"` — 内部の各補間が個別のスコープ参照になる
+/// - `ng-attr-*` (`ng-attr-width="{{ vm.width }}"`) は接頭辞で判定する
+static INTERPOLATION_DIRECTIVE_SET: phf::Set<&'static str> = phf_set! {
+    "ng-href",
+    "ng-src",
+    "ng-srcset",
+    "ng-bind-template",
 };
 
-/// 属性値が Angular 式ではなくリテラル文字列として解釈されるディレクティブか判定
-pub fn is_literal_value_directive(attr_name: &str) -> bool {
-    LITERAL_VALUE_DIRECTIVE_SET.contains(attr_name)
+/// 属性値が補間テキストとして解釈されるディレクティブか判定
+pub fn is_interpolation_directive(attr_name: &str) -> bool {
+    let name = normalize_directive_attr(attr_name);
+    INTERPOLATION_DIRECTIVE_SET.contains(name.as_ref()) || name.starts_with("ng-attr-")
+}
+
+/// 属性値が Angular 式として評価されないディレクティブか判定
+///
+/// リテラル文字列 / 正規表現として扱うものと、補間テキスト
+/// ([`is_interpolation_directive`]) として扱うものの両方を含む。
+/// ajsconfig.json の `expression_attributes` で `"mode": "literal"` を指定した属性も含む
+pub fn is_literal_value_directive(attr_name: &str, index: &Index) -> bool {
+    LITERAL_VALUE_DIRECTIVE_SET.contains(normalize_directive_attr(attr_name).as_ref())
+        || is_interpolation_directive(attr_name)
+        || index.html.expression_attribute_mode(attr_name) == Some(ExpressionAttributeMode::Literal)
+}
+
+/// `ng-switch-when` 属性か判定 (`data-` 接頭辞などの表記ゆれを含む)
+pub fn is_ng_switch_when(attr_name: &str) -> bool {
+    normalize_directive_attr(attr_name) == "ng-switch-when"
+}
+
+/// `ng-switch-when` の値が変数 (識別子のプロパティパス) ならそのパスを返す。
+///
+/// `ng-switch-when="'active'"` のような文字列リテラルや数値は `None`。
+/// `ng-switch-when="red"` のような裸の case ラベルも構文上は識別子なので、
+/// スコープ上の変数として扱うかは呼び出し側が定義の有無で判断する。
+/// `ng-switch-default` は値を持たないため対象外。
+pub fn ng_switch_when_variable(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let first = value.chars().next()?;
+    if !(first.is_ascii_alphabetic() || first == '_' || first == '$') {
+        return None;
+    }
+    let is_path = value
+        .split('.')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$'));
+    is_path.then_some(value)
 }
 
 /// 属性値を Angular 式として解析すべきか判定する。
 ///
 /// 以下のいずれかに当てはまる場合 `true` を返す:
 /// 1. ビルトイン or 既知ライブラリの ng-* / uib-* / ngf-* ディレクティブ
-///    (`is_ng_directive` の判定。`data-` 接頭辞は揺らぎを吸収)
+///    (`is_ng_directive` の判定。表記ゆれは [`normalize_directive_attr`] で吸収)、
+///    または ajsconfig.json の `expression_attributes` で登録された属性
 /// 2. JS 側で `.directive('name', ...)` 登録された custom directive
 ///    (kebab-case → camelCase で `SymbolKind::Directive` を index に検索)
 /// 3. `element_name` が `.component('name', ...)` 登録された component で、
-///    かつ属性名がその component の `bindings` の名前と一致
-///    (`SymbolKind::ComponentBinding` で `componentName.bindingName` を検索)
+///    かつ属性名がその component の `<` / `=` / `&` バインディングと一致
+///    (`ComponentStore` の `bindings`、なければ `SymbolKind::ComponentBinding` の
+///    `componentName.bindingName` を検索)。`@` バインディングは文字列なので `false`
+/// 4. `element_name` が `.directive('name', ...)` 登録された要素ディレクティブで、
+///    かつ属性名がその isolate scope バインディングと一致
+///    (`SymbolKind::DirectiveBinding` で `directiveName.$scope.bindingName` を検索)。
+///    属性値は親スコープの式として扱われる
 ///
 /// `element_name` は属性が属する要素のタグ名 (kebab-case)。
 /// `None` の場合は判定 (1) と (2) のみ行う (component bindings は判定不能)。
@@ -151,14 +403,13 @@ pub fn is_directive_attribute(
     element_name: Option<&str>,
     index: &Index,
 ) -> bool {
-    // 1. ビルトイン/既知ライブラリ
-    if is_ng_directive(attr_name) {
+    // 1. ビルトイン/既知ライブラリ/ユーザー設定
+    if is_ng_directive(attr_name) || index.html.expression_attribute_mode(attr_name).is_some() {
         return true;
     }
 
-    // data- 接頭辞を剥がしてから index 検索
-    let stripped = attr_name.strip_prefix("data-").unwrap_or(attr_name);
-    let camel = kebab_to_camel(stripped);
+    // 表記ゆれを正規化してから index 検索
+    let camel = kebab_to_camel(&normalize_directive_attr(attr_name));
 
     // 2. custom directive
     if index
@@ -170,20 +421,51 @@ pub fn is_directive_attribute(
 
     // 3. component binding (要素名が必要)
     if let Some(elem) = element_name {
-        let elem_camel = kebab_to_camel(elem);
+        let elem_camel = kebab_to_camel(&normalize_directive_attr(elem));
         if index
             .definitions
             .has_definition_of_kind(&elem_camel, SymbolKind::Component)
         {
-            let binding_name = format!("{}.{}", elem_camel, camel);
-            if index
-                .definitions
-                .has_definition_of_kind(&binding_name, SymbolKind::ComponentBinding)
+            if let Some(binding_type) = index
+                .components
+                .get_binding_type_for_attribute(&elem_camel, &camel)
             {
-                return true;
+                return !is_string_binding(&binding_type);
+            }
+
+            // キャッシュ読み込み直後は ComponentStore が空なので定義シンボルから判定
+            let bindings = index.definitions.get_component_bindings(&elem_camel);
+            if let Some(binding_type) = find_binding_type(bindings, &camel) {
+                return !is_string_binding(&binding_type);
             }
         }
+
+        // 4. 要素ディレクティブの isolate scope バインディング
+        let bindings = index
+            .definitions
+            .get_definitions_with_prefix(&format!("{}.$scope.", elem_camel))
+            .into_iter()
+            .filter(|s| s.kind == SymbolKind::DirectiveBinding)
+            .collect();
+        if find_binding_type(bindings, &camel).is_some() {
+            return true;
+        }
     }
 
     false
 }
+
+/// binding シンボルのうち、HTML 属性名 (別名指定を考慮) が `attribute` のものの値
+/// (値が文字列でなければ空文字列)
+fn find_binding_type(bindings: Vec<Symbol>, attribute: &str) -> Option<String> {
+    bindings.into_iter().find_map(|s| {
+        let binding_type = s.binding_type.unwrap_or_default();
+        let name = s.name.rsplit('.').next()?;
+        (binding_attribute_name(name, &binding_type) == attribute).then_some(binding_type)
+    })
+}
+
+/// `bindings` の種別が `@` (文字列バインディング) か
+fn is_string_binding(binding_type: &str) -> bool {
+    binding_type.trim_start().starts_with('@')
+}
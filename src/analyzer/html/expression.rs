@@ -1,8 +1,16 @@
 //! Angular式のパースとコンテキスト判定
 
-use super::directives::{is_directive_attribute, is_literal_value_directive};
+use super::directives::{
+    disallows_assignment, is_directive_attribute, is_literal_value_directive, provides_event,
+};
+use super::variable_parser::{parse_ng_repeat_expression, split_ng_repeat_expression};
 use super::HtmlAngularJsAnalyzer;
+use crate::model::{ExpressionAssignment, Span};
+use crate::util::position_to_offset;
 
+use std::borrow::Cow;
+
+use tower_lsp::lsp_types::{Position, Url};
 use tree_sitter::{Parser, Tree};
 
 /// JavaScriptパーサー（Angular式のパース用）
@@ -29,12 +37,18 @@ impl HtmlAngularJsAnalyzer {
     /// AngularJS式からプロパティパスを抽出（tree-sitter使用）
     pub(super) fn parse_angular_expression(&self, expr: &str, directive: &str) -> Vec<String> {
         let mut local_vars: Vec<String> = Vec::new();
+        let mut extra_expressions: Vec<&str> = Vec::new();
 
-        // ng-repeat / ng-options: "item in items" or "(key, value) in items" -> ローカル変数を抽出
+        // ng-repeat: "item in items | filter:q as filtered track by item.id"
+        // -> item / filtered はローカル変数、コレクション式と track by 式を解析
+        // ng-options: "item in items" or "(key, value) in items" -> ローカル変数を抽出
         // ng-options: "label for value in array" や "select as label for value in array" 形式もサポート
-        let expr_to_parse = if directive.contains("ng-repeat") || directive.contains("data-ng-repeat")
-            || directive.contains("ng-options") || directive.contains("data-ng-options")
-        {
+        let ng_repeat = split_ng_repeat_expression(expr).filter(|_| directive.contains("ng-repeat"));
+        let expr_to_parse = if let Some(clauses) = ng_repeat {
+            local_vars.extend(parse_ng_repeat_expression(expr).into_iter().map(|var| var.name));
+            extra_expressions.extend(clauses.track_by.map(|(track_by, _)| track_by));
+            clauses.collection.0
+        } else if directive.contains("ng-options") {
             if let Some(in_idx) = expr.find(" in ") {
                 let before_in = expr[..in_idx].trim();
 
@@ -67,8 +81,16 @@ impl HtmlAngularJsAnalyzer {
 
         // フィルター部分を除去（AngularJSフィルターはJS構文ではない）
         // 注意: || はJavaScriptの演算子なので、単独の | のみをフィルター区切りとして扱う
+        let filter_arguments = filter_argument_expressions(expr_to_parse);
         let expr_to_parse = self.remove_angular_filters(expr_to_parse);
 
+        // ワンタイムバインディング `::expr` の `::` は式の一部ではないので除去
+        // (参照位置は元の属性値から識別子を検索して求めるため、ここでの除去は位置に影響しない)
+        let expr_to_parse = strip_one_time_binding(expr_to_parse);
+
+        let wrapped = wrap_object_literal(expr_to_parse);
+        let expr_to_parse = wrapped.as_ref();
+
         // tree-sitter-javascriptで式をパース
         let mut parser = JsParser::new();
         let mut identifiers = Vec::new();
@@ -77,13 +99,47 @@ impl HtmlAngularJsAnalyzer {
             self.collect_identifiers_from_expr(tree.root_node(), expr_to_parse, &mut identifiers);
         }
 
-        // ローカル変数とAngularキーワードを除外
+        // フィルター引数 (`| filter: searchText | orderBy: sortKey`) もそれぞれ式として評価
+        for argument in filter_arguments.into_iter().chain(extra_expressions) {
+            if let Some(tree) = parser.parse(argument) {
+                self.collect_identifiers_from_expr(tree.root_node(), argument, &mut identifiers);
+            }
+        }
+
+        // ローカル変数 (`item` / `item.id`) とAngularキーワードを除外
         identifiers
             .into_iter()
-            .filter(|name| !local_vars.contains(name) && !self.is_angular_keyword(name))
+            .filter(|name| {
+                let base_name = name.split('.').next().unwrap_or(name);
+                !local_vars.iter().any(|var| var == base_name) && !self.is_angular_keyword(name)
+            })
             .collect()
     }
 
+    /// 値を読むだけのディレクティブ (`ng-if` など) の式にある代入を診断用に記録する
+    ///
+    /// 対象の属性は [`disallows_assignment`](super::directives::disallows_assignment) で判定する
+    pub(super) fn collect_expression_assignments(
+        &self,
+        uri: &Url,
+        attr_name: &str,
+        value: &str,
+        value_start_line: u32,
+        value_start_col: u32, // UTF-16コードユニット単位
+    ) {
+        if !disallows_assignment(attr_name) {
+            return;
+        }
+        for offset in assignment_operator_offsets(value) {
+            let (line, col) = self.position_in_text(value, offset, value_start_line, value_start_col);
+            self.index.diagnostics.add_expression_assignment(ExpressionAssignment {
+                uri: uri.clone(),
+                attribute: attr_name.to_string(),
+                span: Span::new(line, col, line, col + 1),
+            });
+        }
+    }
+
     /// 式のASTから識別子を収集
     fn collect_identifiers_from_expr(&self, node: tree_sitter::Node, source: &str, identifiers: &mut Vec<String>) {
         match node.kind() {
@@ -92,6 +148,13 @@ impl HtmlAngularJsAnalyzer {
             // - alias.property -> "alias.property" (controller as alias構文)
             // 両方の形式を収集し、参照解決時にaliasかどうかをチェック
             "member_expression" => {
+                // `$parent.$parent.user.name` -> "$parent.$parent.user" (親スコープのプロパティ)
+                if let Some(path) = self.parent_scope_path(node, source) {
+                    if !identifiers.contains(&path) {
+                        identifiers.push(path);
+                    }
+                    return;
+                }
                 if let Some(object) = node.child_by_field_name("object") {
                     // ネストしたmember_expression (a.b.c) の場合
                     if object.kind() == "member_expression" {
@@ -136,8 +199,8 @@ impl HtmlAngularJsAnalyzer {
                     self.collect_identifiers_from_expr(args, source, identifiers);
                 }
             }
-            // 単独の識別子
-            "identifier" => {
+            // 単独の識別子 (`{ height }` の省略記法のキーも値としての参照)
+            "identifier" | "shorthand_property_identifier" => {
                 let name = self.node_text(node, source);
                 if !identifiers.contains(&name) {
                     identifiers.push(name);
@@ -155,6 +218,29 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// `$parent` / `$root` で始まるメンバーチェーンを、辿るスコープとその直下のプロパティまでの
+    /// パスにする (`$root.a.b` -> `"$root.a"`, `$parent.$parent.a` -> `"$parent.$parent.a"`)
+    fn parent_scope_path(&self, node: tree_sitter::Node, source: &str) -> Option<String> {
+        let mut segments = Vec::new();
+        let mut current = node;
+        while current.kind() == "member_expression" {
+            segments.push(self.node_text(current.child_by_field_name("property")?, source));
+            current = current.child_by_field_name("object")?;
+        }
+        if current.kind() != "identifier" {
+            return None;
+        }
+        segments.push(self.node_text(current, source));
+        segments.reverse();
+
+        let depth = match segments[0].as_str() {
+            "$root" => 1,
+            "$parent" => segments.iter().take_while(|s| *s == "$parent").count(),
+            _ => return None,
+        };
+        (segments.len() > depth).then(|| segments[..=depth].join("."))
+    }
+
     /// AngularJSフィルターを除去（|| は演算子なので保持）
     fn remove_angular_filters<'a>(&self, expr: &'a str) -> &'a str {
         let bytes = expr.as_bytes();
@@ -175,12 +261,52 @@ impl HtmlAngularJsAnalyzer {
         expr.trim()
     }
 
+    /// 式中のフィルター名 (`x | name:arg` の `name`) とその式内バイトオフセットを列挙
+    ///
+    /// `||` (論理OR) と文字列リテラル内の `|` はフィルター区切りとして扱わない
+    pub(super) fn find_filter_names(&self, expr: &str) -> Vec<(String, usize)> {
+        let bytes = expr.as_bytes();
+        let mut result = Vec::new();
+        let mut quote: Option<u8> = None;
+        let mut i = 0;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if let Some(q) = quote {
+                if b == b'\\' {
+                    i += 1;
+                } else if b == q {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            match b {
+                b'\'' | b'"' => quote = Some(b),
+                b'|' if bytes.get(i + 1) == Some(&b'|') => i += 1,
+                b'|' => {
+                    let after = &expr[i + 1..];
+                    let name_start = i + 1 + (after.len() - after.trim_start().len());
+                    let name_len = expr[name_start..]
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(expr.len() - name_start);
+                    if name_len > 0 {
+                        result.push((expr[name_start..name_start + name_len].to_string(), name_start));
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        result
+    }
+
     /// AngularJSのキーワードかどうか
     fn is_angular_keyword(&self, name: &str) -> bool {
         matches!(
             name,
             "true" | "false" | "null" | "undefined" |
             "$index" | "$first" | "$last" | "$middle" | "$odd" | "$even" |
+            "$parent" | "$root" |
             "track" | "by" | "in" | "as" |
             // ng-repeatでよく使われるローカル変数名
             "item" | "key" | "value" | "i" | "idx" |
@@ -191,6 +317,8 @@ impl HtmlAngularJsAnalyzer {
 
     /// カーソル位置がAngularディレクティブまたはinterpolation内にあるかを判定
     /// 戻り値: true = Angular コンテキスト内（$scope補完が必要）
+    ///
+    /// `col` はバイト列 (LSP の UTF-16 列からは `utf16_col_to_byte_col` で変換する)
     pub fn is_in_angular_context(&self, source: &str, line: u32, col: u32) -> bool {
         let lines: Vec<&str> = source.lines().collect();
         if (line as usize) >= lines.len() {
@@ -229,7 +357,7 @@ impl HtmlAngularJsAnalyzer {
                     // ng-message / ng-messages-include は値が文字列リテラルなので
                     // AngularJS スコープ補完の対象外
                     if is_directive_attribute(attr_name, elem, &self.index)
-                        && !is_literal_value_directive(attr_name)
+                        && !is_literal_value_directive(attr_name, &self.index)
                     {
                         return true;
                     }
@@ -247,7 +375,7 @@ impl HtmlAngularJsAnalyzer {
                 if let Some(attr_name) = Self::extract_attr_name(before_eq) {
                     let elem = Self::extract_element_name_before(before_eq);
                     if is_directive_attribute(attr_name, elem, &self.index)
-                        && !is_literal_value_directive(attr_name)
+                        && !is_literal_value_directive(attr_name, &self.index)
                     {
                         return true;
                     }
@@ -258,6 +386,75 @@ impl HtmlAngularJsAnalyzer {
         false
     }
 
+    /// カーソル位置が `$event` を参照できるイベントディレクティブ
+    /// (`ng-click="onClick(<ここ>)"` など) の属性値内なら、その属性名を返す
+    ///
+    /// `col` は LSP の UTF-16 列。属性値が複数行にわたる場合は前の行の開きクォートまで遡る
+    pub fn event_directive_at(&self, source: &str, line: u32, col: u32) -> Option<String> {
+        let before_cursor = &source[..position_to_offset(source, Position::new(line, col))];
+
+        ["=\"", "='"].iter().find_map(|open| {
+            let eq_idx = before_cursor.rfind(open)?;
+            let quote = &open[1..];
+            if before_cursor[eq_idx + 2..].contains(quote) {
+                return None;
+            }
+            Self::extract_attr_name(&before_cursor[..eq_idx])
+                .filter(|attr_name| provides_event(attr_name))
+                .map(str::to_string)
+        })
+    }
+
+    /// カーソル位置がフィルター名の入力位置（`{{ x | <ここ> }}`）かを判定
+    ///
+    /// Angular コンテキスト内で、式の先頭からカーソルまでの最後の単独 `|`
+    /// (`||` は除く) の後ろが識別子の途中（または空白のみ）である場合に true。
+    /// `{{ amount | currency : '$' }}` のようにフィルター名の後の引数位置では false。
+    /// `col` は `is_in_angular_context` と同じくバイト列
+    pub fn is_in_filter_position(&self, source: &str, line: u32, col: u32) -> bool {
+        if !self.is_in_angular_context(source, line, col) {
+            return false;
+        }
+
+        let Some(before_cursor) = source
+            .lines()
+            .nth(line as usize)
+            .and_then(|l| l.get(..col as usize))
+        else {
+            return false;
+        };
+
+        // 式の開始位置（interpolation の開き記号または属性値の開きクォート）
+        let (start_symbol, _) = self.get_interpolate_symbols();
+        let expr_start = [
+            before_cursor.rfind(&start_symbol).map(|i| i + start_symbol.len()),
+            before_cursor.rfind("=\"").map(|i| i + 2),
+            before_cursor.rfind("='").map(|i| i + 2),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(0);
+        let expr = &before_cursor[expr_start..];
+
+        let bytes = expr.as_bytes();
+        let pipe_idx = (0..bytes.len()).rev().find(|&i| {
+            bytes[i] == b'|'
+                && bytes.get(i + 1) != Some(&b'|')
+                && (i == 0 || bytes[i - 1] != b'|')
+        });
+
+        match pipe_idx {
+            Some(i) => {
+                let after_pipe = expr[i + 1..].trim_start();
+                after_pipe
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+            }
+            None => false,
+        }
+    }
+
     /// 文字列の末尾位置から見て、現在開いている `<tag` の `tag` 名を抽出する
     /// (component bindings 判定に使う element_tag_name)
     fn extract_element_name_before(s: &str) -> Option<&str> {
@@ -307,6 +504,8 @@ impl HtmlAngularJsAnalyzer {
     /// - prefix: 入力中の文字列
     /// - is_tag_name: タグ名位置か（true なら element_tag_name は None）
     /// - element_tag_name: 属性名位置の場合、要素のタグ名（kebab-case のまま、空ならNone）
+    ///
+    /// `col` はバイト列
     pub fn get_directive_completion_context_with_tag(
         &self,
         source: &str,
@@ -389,4 +588,261 @@ impl HtmlAngularJsAnalyzer {
             Some((attr_part.to_string(), false, element_tag_name))
         }
     }
+
+    /// カーソルがタグの属性名位置にあり、入力中の属性名が `ng-` (または `data-ng-`)
+    /// で始まる場合に、組み込み `ng-*` 属性補完のコンテキストを返す。
+    ///
+    /// 戻り値: Some((prefix, existing_attrs))
+    /// - prefix: 入力中の属性名
+    /// - existing_attrs: 同じタグに既に書かれている属性名（入力中のものは除く）
+    ///
+    /// `col` は `get_directive_completion_context_with_tag` と同じくバイト列
+    pub fn get_ng_attribute_completion_context(
+        &self,
+        source: &str,
+        line: u32,
+        col: u32,
+    ) -> Option<(String, Vec<String>)> {
+        let (prefix, is_tag_name, _) =
+            self.get_directive_completion_context_with_tag(source, line, col)?;
+        if is_tag_name {
+            return None;
+        }
+        if !prefix.starts_with("ng-") && !prefix.starts_with("data-ng-") {
+            return None;
+        }
+
+        // get_directive_completion_context_with_tag が Some を返した時点で
+        // 行・列とタグ開始 `<` の存在は検証済み
+        let current_line = source.lines().nth(line as usize)?;
+        let col = col as usize;
+        let open_idx = current_line.get(..col)?.rfind('<')?;
+
+        // カーソル前: `<tag attr1 attr2="..." ng-` から入力中の prefix を除く
+        let before = current_line.get(open_idx + 1..col - prefix.len())?;
+        // カーソル後: 入力中の属性名の残りを読み飛ばし、タグ終端 `>` まで
+        let after = current_line.get(col..)?;
+        let after = after
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .map(|i| &after[i..])
+            .unwrap_or("");
+        let after = after.split('>').next().unwrap_or("");
+
+        let mut existing_attrs = Self::collect_attribute_names(before);
+        // 先頭トークンはタグ名
+        if !existing_attrs.is_empty() {
+            existing_attrs.remove(0);
+        }
+        existing_attrs.extend(Self::collect_attribute_names(after));
+
+        Some((prefix, existing_attrs))
+    }
+
+    /// タグ内テキストから属性名を抽出する（属性値はクォートの有無を問わず読み飛ばす）
+    fn collect_attribute_names(tag_text: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut chars = tag_text.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '/' || c == '>' {
+                chars.next();
+                continue;
+            }
+            if c == '=' {
+                chars.next();
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                match chars.peek() {
+                    Some(&q) if q == '"' || q == '\'' => {
+                        chars.next();
+                        for v in chars.by_ref() {
+                            if v == q {
+                                break;
+                            }
+                        }
+                    }
+                    _ => while chars.next_if(|c| !c.is_whitespace() && *c != '>').is_some() {},
+                }
+                continue;
+            }
+
+            let mut name = String::new();
+            while let Some(n) =
+                chars.next_if(|c| !c.is_whitespace() && !matches!(c, '=' | '/' | '>'))
+            {
+                name.push(n);
+            }
+            names.push(name);
+        }
+
+        names
+    }
+}
+
+/// 式中の文字列リテラルのバイト範囲 (クォートを含む) を列挙する
+///
+/// `ng-style="{ 'font-size': size }"` の `font-size` のように、文字列内の
+/// 識別子に見える部分をスコープ参照の位置から除くために使う
+pub(super) fn string_literal_ranges(expr: &str) -> Vec<(usize, usize)> {
+    let bytes = expr.as_bytes();
+    let mut ranges = Vec::new();
+    let mut start: Option<(usize, u8)> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match start {
+            Some(_) if b == b'\\' => i += 1,
+            Some((from, q)) if b == q => {
+                ranges.push((from, i + 1));
+                start = None;
+            }
+            None if b == b'\'' || b == b'"' => start = Some((i, b)),
+            _ => {}
+        }
+        i += 1;
+    }
+    // 閉じていない文字列は末尾まで
+    if let Some((from, _)) = start {
+        ranges.push((from, bytes.len()));
+    }
+    ranges
+}
+
+/// `ng-style="{ color: textColor }"` / `ng-class="{ active: isActive }"` の
+/// オブジェクトリテラルは、文として読むとブロックになるので括弧で囲んで式にする
+///
+/// 囲んだ場合、元の式の位置は 1 バイト後ろにずれる
+fn wrap_object_literal(expr: &str) -> Cow<'_, str> {
+    if expr.starts_with('{') {
+        Cow::Owned(format!("({})", expr))
+    } else {
+        Cow::Borrowed(expr)
+    }
+}
+
+/// 式のオブジェクトリテラルの値にある文字列リテラルの中身 (クォートを除く) のバイト範囲
+///
+/// `ng-style="{ color: '#f00', background: dark ? 'black' : 'white' }"` なら
+/// `#f00` / `black` / `white`。値が条件式ならその分岐の文字列も含める。
+/// キーの文字列 (`'font-size'`) や関数呼び出しの引数は含めない
+pub fn object_literal_string_values(expr: &str) -> Vec<(usize, usize)> {
+    let leading = expr.len() - expr.trim_start().len();
+    let trimmed = expr.trim_start();
+    let wrapped = wrap_object_literal(trimmed);
+    let shift = if matches!(wrapped, Cow::Owned(_)) { 1 } else { 0 };
+    let Some(tree) = JsParser::new().parse(&wrapped) else {
+        return Vec::new();
+    };
+
+    let mut ranges = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if let Some(value) = (node.kind() == "pair")
+            .then(|| node.child_by_field_name("value"))
+            .flatten()
+        {
+            collect_string_values(value, &mut ranges);
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    ranges.sort_unstable();
+    ranges
+        .into_iter()
+        .map(|(start, end)| (start - shift + leading, end - shift + leading))
+        .collect()
+}
+
+/// 値ノードが文字列なら中身の範囲を、条件式なら各分岐を辿って集める
+fn collect_string_values(node: tree_sitter::Node, ranges: &mut Vec<(usize, usize)>) {
+    match node.kind() {
+        "string" if node.end_byte() - node.start_byte() >= 2 => {
+            ranges.push((node.start_byte() + 1, node.end_byte() - 1));
+        }
+        "ternary_expression" => {
+            for field in ["consequence", "alternative"] {
+                if let Some(branch) = node.child_by_field_name(field) {
+                    collect_string_values(branch, ranges);
+                }
+            }
+        }
+        "parenthesized_expression" => {
+            if let Some(inner) = node.named_child(0) {
+                collect_string_values(inner, ranges);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 式中の代入演算子 `=` のバイト位置を列挙する
+///
+/// `==` / `===` / `!=` / `<=` / `>=` / `=>` と文字列リテラル内の `=` は除く
+pub(super) fn assignment_operator_offsets(expr: &str) -> Vec<usize> {
+    let bytes = expr.as_bytes();
+    let strings = string_literal_ranges(expr);
+    (0..bytes.len())
+        .filter(|&i| bytes[i] == b'=')
+        .filter(|&i| i == 0 || !matches!(bytes[i - 1], b'=' | b'!' | b'<' | b'>'))
+        .filter(|&i| !matches!(bytes.get(i + 1), Some(b'=' | b'>')))
+        .filter(|&i| !strings.iter().any(|&(start, end)| start <= i && i < end))
+        .collect()
+}
+
+/// 式の先頭のワンタイムバインディング記号 `::` を除去する
+///
+/// `{{ ::userName }}` / `ng-if="::isReady"` / `item in ::items` のように
+/// 先頭の空白を挟んでも認識する。
+fn strip_one_time_binding(expr: &str) -> &str {
+    let trimmed = expr.trim_start();
+    trimmed.strip_prefix("::").unwrap_or(trimmed)
+}
+
+/// 式中の各フィルターの引数部分 (`| name: arg1 : arg2` の `arg1`, `arg2`) を列挙する
+///
+/// 括弧・配列・オブジェクトリテラル内の `:` と文字列リテラル内の `|` / `:` は
+/// 区切りとして扱わない。`||` (論理OR) もフィルター区切りではない。
+fn filter_argument_expressions(expr: &str) -> Vec<&str> {
+    let bytes = expr.as_bytes();
+    let mut arguments = Vec::new();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0i32;
+    let mut in_filter = false;
+    let mut argument_start: Option<usize> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if b == b'\\' {
+                i += 1;
+            } else if b == q {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+        match b {
+            b'\'' | b'"' => quote = Some(b),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'|' if bytes.get(i + 1) == Some(&b'|') => i += 1,
+            b'|' if depth == 0 => {
+                if let Some(start) = argument_start.take() {
+                    arguments.push(&expr[start..i]);
+                }
+                in_filter = true;
+            }
+            b':' if depth == 0 && in_filter => {
+                if let Some(start) = argument_start {
+                    arguments.push(&expr[start..i]);
+                }
+                argument_start = Some(i + 1);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if let Some(start) = argument_start {
+        arguments.push(&expr[start..]);
+    }
+    arguments
 }
@@ -0,0 +1,100 @@
+//! AngularJS filter definitions
+
+/// 組み込みフィルターの名前とホバー用説明
+pub struct BuiltinFilterDoc {
+    pub name: &'static str,
+    /// テンプレートでの書式 (省略可能な引数は `[...]`)
+    pub syntax: &'static str,
+    pub description: &'static str,
+    /// 入力値以降の引数 (名前, 説明)。signature help 用
+    pub params: &'static [(&'static str, &'static str)],
+}
+
+/// AngularJS 1.x の `ng` モジュールに組み込まれているフィルター
+pub static NG_BUILTIN_FILTERS: &[BuiltinFilterDoc] = &[
+    BuiltinFilterDoc {
+        name: "currency",
+        syntax: "{{ amount | currency[:symbol[:fractionSize]] }}",
+        description: "Formats a number as a currency (e.g. `$1,234.56`). Uses the locale's currency symbol when `symbol` is omitted.",
+        params: &[
+            ("symbol", "Currency symbol or identifier (defaults to the locale's symbol)."),
+            ("fractionSize", "Number of decimal places (defaults to the locale's default)."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "date",
+        syntax: "{{ date | date[:format[:timezone]] }}",
+        description: "Formats a date (`Date`, milliseconds or ISO 8601 string) according to `format` (e.g. `'yyyy-MM-dd'`, `'short'`).",
+        params: &[
+            ("format", "Formatting rules, e.g. `'yyyy-MM-dd'` or a predefined format such as `'short'` (defaults to `'mediumDate'`)."),
+            ("timezone", "Timezone to format in, e.g. `'UTC'` or `'+0900'` (defaults to the browser's timezone)."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "filter",
+        syntax: "{{ array | filter:expression[:comparator[:anyPropertyKey]] }}",
+        description: "Selects a subset of items from `array` matching a string, object pattern or predicate function.",
+        params: &[
+            ("expression", "String, object pattern or predicate function used to select items."),
+            ("comparator", "Function or `true` (strict equality) deciding whether an item matches."),
+            ("anyPropertyKey", "Special property name matching against any property (defaults to `'$'`)."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "json",
+        syntax: "{{ object | json[:spacing] }}",
+        description: "Converts a JavaScript object into a JSON string. Mostly useful for debugging.",
+        params: &[
+            ("spacing", "Number of spaces to use per indentation (defaults to 2)."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "limitTo",
+        syntax: "{{ input | limitTo:limit[:begin] }}",
+        description: "Creates a new array or string containing only `limit` elements, taken from the beginning (or the end if negative).",
+        params: &[
+            ("limit", "Length of the returned array or string. Negative values take items from the end."),
+            ("begin", "Index at which to begin the limitation (defaults to 0)."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "lowercase",
+        syntax: "{{ string | lowercase }}",
+        description: "Converts a string to lowercase.",
+        params: &[],
+    },
+    BuiltinFilterDoc {
+        name: "number",
+        syntax: "{{ number | number[:fractionSize] }}",
+        description: "Formats a number as text with grouping separators, rounded to `fractionSize` decimal places.",
+        params: &[
+            ("fractionSize", "Number of decimal places to round the number to."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "orderBy",
+        syntax: "{{ array | orderBy[:expression[:reverse[:comparator]]] }}",
+        description: "Returns a copy of `array` sorted by `expression` (property name, getter function or an array of them).",
+        params: &[
+            ("expression", "Property name, getter function or an array of them used to sort the items."),
+            ("reverse", "Reverse the order when truthy."),
+            ("comparator", "Function used to compare two values."),
+        ],
+    },
+    BuiltinFilterDoc {
+        name: "uppercase",
+        syntax: "{{ string | uppercase }}",
+        description: "Converts a string to uppercase.",
+        params: &[],
+    },
+];
+
+/// 組み込みフィルターか判定
+pub fn is_builtin_filter(name: &str) -> bool {
+    builtin_filter_doc(name).is_some()
+}
+
+/// 組み込みフィルターの説明を取得
+pub fn builtin_filter_doc(name: &str) -> Option<&'static BuiltinFilterDoc> {
+    NG_BUILTIN_FILTERS.iter().find(|doc| doc.name == name)
+}
@@ -6,8 +6,12 @@ use tree_sitter::Node;
 use crate::model::HtmlFormBinding;
 
 use super::controller::ControllerScopeInfo;
+use super::variable_parser::is_valid_identifier;
 use super::HtmlAngularJsAnalyzer;
 
+/// `name` 属性があればフォームのコントロールになる要素
+const FORM_CONTROL_TAGS: &[&str] = &["input", "select", "textarea"];
+
 impl HtmlAngularJsAnalyzer {
     /// フォームバインディングのみを収集（Pass 2用）
     /// ng-controllerスコープが確定した後に呼び出される
@@ -66,6 +70,8 @@ impl HtmlAngularJsAnalyzer {
                         name_start_col: form_scope.name_start_col,
                         name_end_line: form_scope.name_end_line,
                         name_end_col: form_scope.name_end_col,
+                        field_names: form_scope.field_names,
+                        nested_form_names: form_scope.nested_form_names,
                     };
                     self.index.html.add_html_form_binding(binding);
                 }
@@ -89,4 +95,78 @@ impl HtmlAngularJsAnalyzer {
             }
         }
     }
+
+    /// フォーム要素配下のコントロール名を収集する
+    ///
+    /// `name` 属性を持つ `input` / `select` / `textarea` / `ng-model` 要素と、
+    /// 入れ子の `<form>` / `ng-form` がフォームのプロパティ (`myForm.email`) になる。
+    /// 入れ子フォームの内側のコントロールはそのフォームに属するため辿らない
+    pub(super) fn collect_form_field_names(
+        &self,
+        form_element: Node,
+        source: &str,
+    ) -> (Vec<String>, Vec<String>) {
+        let mut names = Vec::new();
+        let mut nested_forms = Vec::new();
+        let mut cursor = form_element.walk();
+        for child in form_element.named_children(&mut cursor) {
+            self.collect_form_field_names_recursive(child, source, &mut names, &mut nested_forms);
+        }
+        (names, nested_forms)
+    }
+
+    fn collect_form_field_names_recursive(
+        &self,
+        node: Node,
+        source: &str,
+        names: &mut Vec<String>,
+        nested_forms: &mut Vec<String>,
+    ) {
+        if node.kind() != "element" {
+            return;
+        }
+        let mut is_nested_form = false;
+        if let Some(start_tag) = self.find_child_by_kind(node, "start_tag") {
+            let tag_name = self
+                .find_child_by_kind(start_tag, "tag_name")
+                .map(|n| self.node_text(n, source))
+                .unwrap_or_default();
+            let mut name: Option<String> = None;
+            let mut has_ng_model = false;
+            let mut cursor = start_tag.walk();
+            for attr in start_tag.children(&mut cursor).filter(|c| c.kind() == "attribute") {
+                let Some(attr_name) = self.find_child_by_kind(attr, "attribute_name") else {
+                    continue;
+                };
+                match self.node_text(attr_name, source).as_str() {
+                    "name" => {
+                        name = self
+                            .find_child_by_kind(attr, "quoted_attribute_value")
+                            .or_else(|| self.find_child_by_kind(attr, "attribute_value"))
+                            .map(|v| self.node_text(v, source).trim_matches(|c| c == '"' || c == '\'').to_string());
+                    }
+                    "ng-model" => has_ng_model = true,
+                    "ng-form" => is_nested_form = true,
+                    _ => {}
+                }
+            }
+            is_nested_form |= matches!(tag_name.as_str(), "form" | "ng-form");
+
+            let is_control = is_nested_form || has_ng_model || FORM_CONTROL_TAGS.contains(&tag_name.as_str());
+            if let Some(name) = name.filter(|n| is_control && is_valid_identifier(n) && !names.contains(n)) {
+                if is_nested_form {
+                    nested_forms.push(name.clone());
+                }
+                names.push(name);
+            }
+        }
+        if is_nested_form {
+            return;
+        }
+
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.collect_form_field_names_recursive(child, source, names, nested_forms);
+        }
+    }
 }
@@ -0,0 +1,69 @@
+//! JS 内のインラインテンプレート (`template: '...'`) の解析
+
+use tower_lsp::lsp_types::Url;
+
+use super::controller::ControllerScopeInfo;
+use super::parser::HtmlParser;
+use super::HtmlAngularJsAnalyzer;
+use crate::model::{HtmlControllerScope, InlineTemplate};
+
+impl HtmlAngularJsAnalyzer {
+    /// JS ファイルに登録されたインラインテンプレートをすべて解析する
+    ///
+    /// JS の解析 (`clear_document` 済み) の後に 1 回だけ呼ぶ。ワークスペーススキャンでは
+    /// フィルターやディレクティブの定義が揃った HTML の Pass 3 の後に呼ぶ
+    pub fn analyze_inline_templates(&self, uri: &Url) {
+        self.index.clear_html_references(uri);
+        for template in self.index.templates.get_inline_templates(uri) {
+            self.analyze_inline_template(&template);
+        }
+    }
+
+    /// インラインテンプレートを HTML として解析し、参照を定義元 JS ファイルの位置で登録する
+    ///
+    /// テンプレートの前を開始行までの改行と開始列までの空白で埋めてからパースするので、
+    /// 各 Pass の位置計算がそのまま JS ファイル内の位置になる
+    pub fn analyze_inline_template(&self, template: &InlineTemplate) {
+        let uri = &template.uri;
+        let source = format!(
+            "{}{}{}",
+            "\n".repeat(template.line as usize),
+            " ".repeat(template.col as usize),
+            template.source
+        );
+        let Some(tree) = HtmlParser::new().parse(&source) else {
+            return;
+        };
+
+        // テンプレート全体をテンプレートのコントローラーのスコープとする
+        let start_line = template.line;
+        let end_line = start_line + template.source.matches('\n').count() as u32;
+        let mut scopes = Vec::new();
+        if let Some(name) = &template.scope_name {
+            scopes.push((name.clone(), None));
+        }
+        if let (Some(name), Some(alias)) = (&template.controller_name, &template.controller_as) {
+            scopes.push((name.clone(), Some(alias.clone())));
+        }
+        let mut controller_stack = Vec::new();
+        for (name, alias) in scopes {
+            self.index.controllers.add_html_controller_scope(HtmlControllerScope {
+                controller_name: name.clone(),
+                alias,
+                alias_span: None,
+                uri: uri.clone(),
+                start_line,
+                end_line,
+            });
+            controller_stack.push(ControllerScopeInfo {
+                name,
+                start_line,
+                end_line,
+            });
+        }
+
+        self.collect_controller_scopes_only_from_tree(tree.root_node(), &source, uri);
+        self.collect_form_bindings_from_tree(tree.root_node(), &source, uri, &mut controller_stack);
+        self.collect_references_with_tree(uri, &source, &tree);
+    }
+}
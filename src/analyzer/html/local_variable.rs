@@ -6,7 +6,9 @@ use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
 use super::directives::is_directive_attribute;
-use super::variable_parser::{parse_ng_init_expression, parse_ng_repeat_expression};
+use super::variable_parser::{
+    parse_ng_init_expression, parse_ng_repeat_expression, split_ng_repeat_expression,
+};
 use super::HtmlAngularJsAnalyzer;
 use crate::model::{HtmlLocalVariable, HtmlLocalVariableReference, HtmlLocalVariableSource};
 
@@ -29,27 +31,14 @@ impl HtmlAngularJsAnalyzer {
         };
 
         if let Some(tag) = tag_node {
-            // 要素のスコープ範囲
-            let scope_start_line = node.start_position().row as u32;
-            let scope_end_line = node.end_position().row as u32;
+            // ng-repeat の `as alias` は ng-repeat 要素の親スコープに公開される
+            let parent = node.parent().unwrap_or(node);
 
             // ng-repeatからローカル変数を抽出
-            self.extract_ng_repeat_variable_definitions(
-                tag,
-                source,
-                uri,
-                scope_start_line,
-                scope_end_line,
-            );
+            self.extract_ng_repeat_variable_definitions(tag, source, uri, node, parent);
 
             // ng-initからローカル変数を抽出
-            self.extract_ng_init_variable_definitions(
-                tag,
-                source,
-                uri,
-                scope_start_line,
-                scope_end_line,
-            );
+            self.extract_ng_init_variable_definitions(tag, source, uri, node);
         }
 
         // 子ノードを再帰的に処理
@@ -60,21 +49,25 @@ impl HtmlAngularJsAnalyzer {
     }
 
     /// ng-repeatから変数定義を抽出
+    ///
+    /// 変数のスコープは ng-repeat 要素自体、`as alias` だけはその親要素 (`parent`)
     fn extract_ng_repeat_variable_definitions(
         &self,
         start_tag: Node,
         source: &str,
         uri: &Url,
-        scope_start_line: u32,
-        scope_end_line: u32,
+        element: Node,
+        parent: Node,
     ) {
+        let scope_start_line = element.start_position().row as u32;
+        let scope_end_line = element.end_position().row as u32;
         let mut cursor = start_tag.walk();
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
-                    if attr_name == "ng-repeat" || attr_name == "data-ng-repeat" {
+                    if attr_name == "ng-repeat" {
                         if let Some(value_node) =
                             self.find_child_by_kind(child, "quoted_attribute_value")
                         {
@@ -108,6 +101,8 @@ impl HtmlAngularJsAnalyzer {
                                     uri: uri.clone(),
                                     scope_start_line,
                                     scope_end_line,
+                                    scope_start_byte: element.start_byte(),
+                                    scope_end_byte: element.end_byte(),
                                     name_start_line: attr_name_start_line,
                                     name_start_col: attr_name_start_col,
                                     name_end_line: attr_name_start_line,
@@ -136,12 +131,19 @@ impl HtmlAngularJsAnalyzer {
                                 let name_end_line = name_start_line;
                                 let name_end_col = name_start_col + var_text.chars().map(|c| c.len_utf16()).sum::<usize>() as u32;
 
+                                let scope = if var.source == HtmlLocalVariableSource::NgRepeatAlias {
+                                    parent
+                                } else {
+                                    element
+                                };
                                 let variable = HtmlLocalVariable {
                                     name: var.name,
                                     source: var.source,
                                     uri: uri.clone(),
-                                    scope_start_line,
-                                    scope_end_line,
+                                    scope_start_line: scope.start_position().row as u32,
+                                    scope_end_line: scope.end_position().row as u32,
+                                    scope_start_byte: scope.start_byte(),
+                                    scope_end_byte: scope.end_byte(),
                                     name_start_line,
                                     name_start_col,
                                     name_end_line,
@@ -162,16 +164,15 @@ impl HtmlAngularJsAnalyzer {
         start_tag: Node,
         source: &str,
         uri: &Url,
-        scope_start_line: u32,
-        scope_end_line: u32,
+        element: Node,
     ) {
         let mut cursor = start_tag.walk();
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
-                    if attr_name == "ng-init" || attr_name == "data-ng-init" {
+                    if attr_name == "ng-init" {
                         if let Some(value_node) =
                             self.find_child_by_kind(child, "quoted_attribute_value")
                         {
@@ -207,8 +208,10 @@ impl HtmlAngularJsAnalyzer {
                                     name: var.name,
                                     source: var.source,
                                     uri: uri.clone(),
-                                    scope_start_line,
-                                    scope_end_line,
+                                    scope_start_line: element.start_position().row as u32,
+                                    scope_end_line: element.end_position().row as u32,
+                                    scope_start_byte: element.start_byte(),
+                                    scope_end_byte: element.end_byte(),
                                     name_start_line,
                                     name_start_col,
                                     name_end_line,
@@ -311,16 +314,10 @@ impl HtmlAngularJsAnalyzer {
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
                     // ng-repeat, ng-options, ng-initは変数定義なのでスキップ（ただし右辺は参照としてチェック）
-                    if attr_name == "ng-repeat"
-                        || attr_name == "data-ng-repeat"
-                        || attr_name == "ng-options"
-                        || attr_name == "data-ng-options"
-                        || attr_name == "ng-init"
-                        || attr_name == "data-ng-init"
-                    {
+                    if matches!(attr_name.as_str(), "ng-repeat" | "ng-options" | "ng-init") {
                         // ng-repeat/ng-optionsの右辺（"in"の後）とng-initの右辺（=の後）のみチェック
                         if let Some(value_node) =
                             self.find_child_by_kind(child, "quoted_attribute_value")
@@ -331,9 +328,29 @@ impl HtmlAngularJsAnalyzer {
                             let value_byte_col = value_node.start_position().column + 1;
                             let value_start_col = self.byte_col_to_utf16_col(source, value_start_line, value_byte_col);
 
-                            // ng-repeat/ng-optionsの場合は"in"の後の部分のみ
+                            // ng-repeat はコレクション式 (フィルター引数を含む) と track by 式
+                            if attr_name.contains("ng-repeat") {
+                                let Some(clauses) = split_ng_repeat_expression(value) else {
+                                    continue;
+                                };
+                                let clauses_to_check =
+                                    std::iter::once(clauses.collection).chain(clauses.track_by);
+                                for (expr, offset) in clauses_to_check {
+                                    let utf16_offset = self.byte_offset_to_utf16_offset(value, offset);
+                                    self.check_and_register_local_var_references_utf16(
+                                        expr,
+                                        uri,
+                                        value_start_line as u32,
+                                        value_start_col + utf16_offset as u32,
+                                        active_scopes,
+                                    );
+                                }
+                                continue;
+                            }
+
+                            // ng-optionsの場合は"in"の後の部分のみ
                             let expr_to_check =
-                                if attr_name.contains("ng-repeat") || attr_name.contains("ng-options") {
+                                if attr_name.contains("ng-options") {
                                     if let Some(in_idx) = value.find(" in ") {
                                         // track byを除去
                                         let after_in = &value[in_idx + 4..];
@@ -1,23 +1,29 @@
 //! HTML内のAngularJSディレクティブを解析するモジュール
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use tower_lsp::lsp_types::Url;
 use tree_sitter::{Node, Tree};
 
+use crate::analyzer::incremental::SyntaxTreeCache;
 use crate::index::Index;
+use crate::model::AnalysisFailure;
 
 pub mod controller;
 pub mod directive_reference;
 pub mod directives;
 pub mod expression;
+pub mod filters;
 pub mod form;
+pub mod inline_template;
 pub mod local_variable;
 pub mod ng_include;
 pub mod ng_model;
 pub mod parser;
 pub mod scope_reference;
 pub mod script;
+pub mod syntax_error;
 pub mod ui_sref;
 pub mod variable_parser;
 
@@ -28,6 +34,8 @@ pub use script::EmbeddedScript;
 pub struct HtmlAngularJsAnalyzer {
     index: Arc<Index>,
     js_analyzer: Arc<crate::analyzer::js::AngularJsAnalyzer>,
+    /// 開いているドキュメントの前回 Tree（差分パース用）
+    tree_cache: SyntaxTreeCache,
 }
 
 impl HtmlAngularJsAnalyzer {
@@ -35,6 +43,7 @@ impl HtmlAngularJsAnalyzer {
         Self {
             index,
             js_analyzer,
+            tree_cache: SyntaxTreeCache::new(),
         }
     }
 
@@ -51,15 +60,19 @@ impl HtmlAngularJsAnalyzer {
     /// HTMLドキュメントを解析（単独ファイル解析用）
     /// 全パス（Pass 1, 1.5, 2, 3）を実行
     /// テストや単一ファイル更新時に使用
-    pub fn analyze_document(&self, uri: &Url, source: &str) {
+    ///
+    /// パースに失敗した場合は `false` (インデックスは前回の解析結果のまま)
+    pub fn analyze_document(&self, uri: &Url, source: &str) -> bool {
         let mut html_parser = parser::HtmlParser::new();
-        if let Some(tree) = html_parser.parse(source) {
-            self.analyze_document_with_tree(uri, source, &tree);
-        }
+        let Some(tree) = html_parser.parse(source) else {
+            return false;
+        };
+        self.analyze_document_with_tree(uri, source, &tree);
+        true
     }
 
     /// HTMLドキュメントを解析し、埋め込みスクリプトも抽出
-    /// on_change/on_openで使用（単一パースで両方の処理を実行）
+    /// ワークスペーススキャンで使用（単一パースで両方の処理を実行）
     pub fn analyze_document_and_extract_scripts(&self, uri: &Url, source: &str) -> Vec<EmbeddedScript> {
         let mut html_parser = parser::HtmlParser::new();
         if let Some(tree) = html_parser.parse(source) {
@@ -70,6 +83,36 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// エディタで編集中のHTMLを差分パースで解析し、埋め込みスクリプトも抽出
+    /// on_change/on_openで使用（前回の Tree を再利用して再パースを高速化）
+    ///
+    /// パースに失敗した場合は `None` (インデックスは前回の解析結果のまま)。
+    /// 失敗は診断用に `index.diagnostics` に記録し、次に成功したら消す
+    pub fn analyze_document_incremental(&self, uri: &Url, source: &str) -> Option<Vec<EmbeddedScript>> {
+        let old_tree = self.tree_cache.edited_tree(uri, source);
+        let mut html_parser = parser::HtmlParser::new();
+        let Some(tree) = html_parser.parse_incremental(source, old_tree.as_ref()) else {
+            self.index
+                .diagnostics
+                .set_analysis_failure(uri.clone(), AnalysisFailure::ParseFailed);
+            return None;
+        };
+        self.index.diagnostics.clear_analysis_failure(uri);
+        self.tree_cache.insert(uri, source, &tree);
+        self.analyze_document_with_tree(uri, source, &tree);
+        Some(Self::extract_scripts_from_tree(tree.root_node(), source))
+    }
+
+    /// `source` からパース済みの Tree がキャッシュにあれば返す
+    pub fn tree_for_source(&self, uri: &Url, source: &str) -> Option<Tree> {
+        self.tree_cache.tree_for_source(uri, source)
+    }
+
+    /// 差分パース用にキャッシュした Tree を破棄する（`did_close` 時）
+    pub fn forget_tree(&self, uri: &Url) {
+        self.tree_cache.remove(uri);
+    }
+
     /// 事前にパースしたTreeでHTMLドキュメントを解析
     fn analyze_document_with_tree(&self, uri: &Url, source: &str, tree: &Tree) {
         // 既存情報をクリア
@@ -103,7 +146,11 @@ impl HtmlAngularJsAnalyzer {
     pub fn analyze_document_references_only_with_tree(&self, uri: &Url, source: &str, tree: &Tree) {
         // Pass 3で収集する情報のみクリア（Pass 1, 1.5, 2の情報は保持）
         self.index.clear_html_references(uri);
+        self.collect_references_with_tree(uri, source, tree);
+    }
 
+    /// Pass 3 の参照収集本体（クリアはしない）
+    fn collect_references_with_tree(&self, uri: &Url, source: &str, tree: &Tree) {
         // ローカル変数定義を収集（ng-init, ng-repeat由来）
         // これをスコープ参照収集より先に行うことで、ローカル変数をフィルタリングできる
         self.collect_local_variable_definitions(tree.root_node(), source, uri);
@@ -133,6 +180,31 @@ impl HtmlAngularJsAnalyzer {
         );
     }
 
+    /// 複数ファイルの参照を並列に解析（Pass 3用、scan_workspaceから使用）
+    ///
+    /// `analyze_document_references_only_with_tree` はファイル単位で独立しており、
+    /// Index は DashMap ベースで並行書き込みできる。ワーカースレッドが共有の
+    /// インデックスから次のファイルを取り合う形で分配するので、大きなファイルが
+    /// 偏っても待ちが出にくい。`processed` には処理済みファイル数を加算する（進捗表示用）
+    pub fn analyze_references_parallel(&self, files: &[(&Url, &str, Tree)], processed: &AtomicUsize) {
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(files.len());
+        let next = AtomicUsize::new(0);
+
+        std::thread::scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| {
+                    while let Some((uri, source, tree)) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        self.analyze_document_references_only_with_tree(uri, source, tree);
+                        processed.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+    }
+
     /// 指定した種類の子ノードを検索
     pub(self) fn find_child_by_kind<'a>(&self, node: Node<'a>, kind: &str) -> Option<Node<'a>> {
         let mut cursor = node.walk();
@@ -149,6 +221,12 @@ impl HtmlAngularJsAnalyzer {
         source[node.byte_range()].to_string()
     }
 
+    /// 属性名 / タグ名ノードのテキストを [`directives::normalize_directive_attr`] で
+    /// 正規化して取得 (`data-ng-controller` / `ng:controller` → `ng-controller`)
+    pub(self) fn directive_name_text(&self, node: Node, source: &str) -> String {
+        directives::normalize_directive_attr(&source[node.byte_range()]).into_owned()
+    }
+
     /// 文字列ノードから値を取得（クォートを除去）
     pub(self) fn extract_string_value(&self, node: Node, source: &str) -> String {
         let text = self.node_text(node, source);
@@ -168,6 +246,8 @@ impl HtmlAngularJsAnalyzer {
     pub fn collect_controller_scopes_only_with_tree(&self, uri: &Url, source: &str, tree: &Tree) {
         // このHTMLファイルを解析済みとしてマーク
         self.index.mark_html_analyzed(uri);
+        // 構文エラーの位置を診断用に記録
+        self.collect_syntax_errors(uri, source, tree);
         // ng-controllerスコープのみを収集
         self.collect_controller_scopes_only_from_tree(tree.root_node(), source, uri);
     }
@@ -5,7 +5,7 @@ use tree_sitter::Node;
 
 use crate::model::{
     HtmlLocalVariableSource, InheritedFormBinding, InheritedLocalVariable,
-    NgIncludeBinding, NgViewBinding,
+    NgIncludeBinding, NgViewBinding, Span, TemplatePathUsage,
 };
 
 use super::variable_parser::is_valid_identifier;
@@ -41,6 +41,10 @@ pub(super) struct FormBindingScope {
     pub(super) name_start_col: u32,
     pub(super) name_end_line: u32,
     pub(super) name_end_col: u32,
+    /// フォーム配下のコントロール名
+    pub(super) field_names: Vec<String>,
+    /// `field_names` のうち入れ子フォームの名前
+    pub(super) nested_form_names: Vec<String>,
     /// このformが属するコントローラースタックの深さ
     /// コントローラースコープ終了時にまとめてpopするために使用
     pub(super) controller_depth: usize,
@@ -86,6 +90,8 @@ impl HtmlAngularJsAnalyzer {
                 name_start_col: f.name_start_col,
                 name_end_line: f.name_end_line,
                 name_end_col: f.name_end_col,
+                field_names: f.field_names,
+                nested_form_names: f.nested_form_names,
                 controller_depth: 0, // 継承されたものは最上位スコープ
             })
             .collect();
@@ -175,9 +181,24 @@ impl HtmlAngularJsAnalyzer {
             }
 
             // ng-includeをチェック
-            if let Some(template_path) = self.get_ng_include_attribute(start_tag, source) {
+            if let Some((template_path, value_node)) =
+                self.get_ng_include_attribute(start_tag, source)
+            {
                 let resolved_filename = crate::util::resolve_relative_path(uri, &template_path);
 
+                // 存在しないテンプレートファイルの診断用に属性値の位置を記録
+                let (start, end) = (value_node.start_position(), value_node.end_position());
+                self.index.diagnostics.add_template_path(TemplatePathUsage {
+                    uri: uri.clone(),
+                    path: template_path.clone(),
+                    span: Span::new(
+                        start.row as u32,
+                        self.byte_col_to_utf16_col(source, start.row, start.column),
+                        end.row as u32,
+                        self.byte_col_to_utf16_col(source, end.row, end.column),
+                    ),
+                });
+
                 // ローカル変数を継承情報に変換
                 // 元の定義元URIを保持（継承チェーンを通じて伝播するため）
                 let inherited_local_variables: Vec<InheritedLocalVariable> = local_var_stack
@@ -208,6 +229,8 @@ impl HtmlAngularJsAnalyzer {
                         name_start_col: f.name_start_col,
                         name_end_line: f.name_end_line,
                         name_end_col: f.name_end_col,
+                        field_names: f.field_names.clone(),
+                        nested_form_names: f.nested_form_names.clone(),
                     })
                     .collect();
 
@@ -257,6 +280,8 @@ impl HtmlAngularJsAnalyzer {
                         name_start_col: f.name_start_col,
                         name_end_line: f.name_end_line,
                         name_end_col: f.name_end_col,
+                        field_names: f.field_names.clone(),
+                        nested_form_names: f.nested_form_names.clone(),
                     })
                     .collect();
 
@@ -331,9 +356,9 @@ impl HtmlAngularJsAnalyzer {
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
-                    if attr_name == "ng-repeat" || attr_name == "data-ng-repeat" {
+                    if attr_name == "ng-repeat" {
                         if let Some(value_node) =
                             self.find_child_by_kind(child, "quoted_attribute_value")
                         {
@@ -403,9 +428,9 @@ impl HtmlAngularJsAnalyzer {
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
-                    if attr_name == "ng-init" || attr_name == "data-ng-init" {
+                    if attr_name == "ng-init" {
                         if let Some(value_node) =
                             self.find_child_by_kind(child, "quoted_attribute_value")
                         {
@@ -472,7 +497,7 @@ impl HtmlAngularJsAnalyzer {
     ) -> Option<FormBindingScope> {
         // タグ名を取得
         let tag_name_node = self.find_child_by_kind(start_tag, "tag_name")?;
-        let tag_name = self.node_text(tag_name_node, source);
+        let tag_name = self.directive_name_text(tag_name_node, source);
 
         // <form>タグのみ対象
         if tag_name != "form" {
@@ -484,7 +509,7 @@ impl HtmlAngularJsAnalyzer {
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
                     if attr_name == "name" {
                         if let Some(value_node) =
@@ -515,6 +540,10 @@ impl HtmlAngularJsAnalyzer {
                             let value_utf16_len: usize =
                                 value.chars().map(|c| c.len_utf16()).sum();
 
+                            let (field_names, nested_form_names) = start_tag
+                                .parent()
+                                .map(|form| self.collect_form_field_names(form, source))
+                                .unwrap_or_default();
                             return Some(FormBindingScope {
                                 name: value.to_string(),
                                 uri: uri.clone(),
@@ -524,6 +553,8 @@ impl HtmlAngularJsAnalyzer {
                                 name_start_col: value_start_col,
                                 name_end_line: value_start_line,
                                 name_end_col: value_start_col + value_utf16_len as u32,
+                                field_names,
+                                nested_form_names,
                                 controller_depth: 0, // 呼び出し元で設定
                             });
                         }
@@ -535,6 +566,53 @@ impl HtmlAngularJsAnalyzer {
     }
 }
 
+/// カーソル位置が `ng-include="'path'"` または `<ng-include src="'path'">` の
+/// パス文字列上にあれば、そのパス（クォートを除いた文字列）を返す
+///
+/// 定義ジャンプ用の行単位テキスト走査。`ng-include="vm.tpl"` のような
+/// 文字列リテラルでない式は対象外
+pub fn find_ng_include_path_at(source: &str, line: u32, col: u32) -> Option<String> {
+    let line_text = source.lines().nth(line as usize)?;
+    let col = col as usize;
+
+    for (attr_pos, attr) in line_text.match_indices("ng-include") {
+        let after_name = &line_text[attr_pos + attr.len()..];
+        let value_offset = if let Some(rest) = after_name.trim_start().strip_prefix('=') {
+            line_text.len() - rest.len()
+        } else if line_text[..attr_pos].ends_with('<') {
+            // 要素形式: 同じタグ内の src 属性
+            let tag_end = after_name.find('>').unwrap_or(after_name.len());
+            let src_pos = after_name[..tag_end].find("src")?;
+            let rest = after_name[src_pos + "src".len()..].trim_start().strip_prefix('=')?;
+            line_text.len() - rest.len()
+        } else {
+            continue;
+        };
+
+        let value = &line_text[value_offset..];
+        let trimmed = value.trim_start();
+        let value_start = value_offset + (value.len() - trimmed.len());
+        let mut chars = trimmed.chars();
+        let Some(outer) = chars.next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        let Some(inner) = chars.next().filter(|c| (*c == '"' || *c == '\'') && *c != outer)
+        else {
+            continue;
+        };
+
+        let path_start = value_start + 2;
+        let path_len = line_text[path_start..]
+            .find([inner, outer])
+            .unwrap_or(line_text.len() - path_start);
+        let path_end = path_start + path_len;
+        if (path_start..=path_end).contains(&col) {
+            return Some(line_text[path_start..path_end].to_string());
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
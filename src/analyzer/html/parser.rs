@@ -17,6 +17,11 @@ impl HtmlParser {
     pub fn parse(&mut self, source: &str) -> Option<Tree> {
         self.parser.parse(source, None)
     }
+
+    /// `Tree::edit` 済みの前回 Tree を使って差分パースする (`None` ならフルパース)
+    pub fn parse_incremental(&mut self, source: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        self.parser.parse(source, old_tree)
+    }
 }
 
 impl Default for HtmlParser {
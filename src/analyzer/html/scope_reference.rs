@@ -3,8 +3,11 @@
 use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
-use super::directives::{is_directive_attribute, is_literal_value_directive};
-use crate::model::HtmlScopeReference;
+use super::directives::{
+    is_directive_attribute, is_literal_value_directive, is_ng_switch_when, ng_switch_when_variable,
+};
+use super::expression::string_literal_ranges;
+use crate::model::{HtmlScopeReference, Span, SymbolReference};
 
 use super::HtmlAngularJsAnalyzer;
 
@@ -47,11 +50,12 @@ impl HtmlAngularJsAnalyzer {
         for child in start_tag.children(&mut cursor) {
             if child.kind() == "attribute" {
                 if let Some(name_node) = self.find_child_by_kind(child, "attribute_name") {
-                    let attr_name = self.node_text(name_node, source);
+                    let attr_name = self.directive_name_text(name_node, source);
 
                     if let Some(value_node) = self.find_child_by_kind(child, "quoted_attribute_value") {
                         let raw_value = self.node_text(value_node, source);
-                        let value = raw_value.trim_matches(|c| c == '"' || c == '\'');
+                        // 外側のクォートだけを除く (`ng-if="'a' + b"` の `'a'` は式の一部)
+                        let value = unquote_attribute_value(&raw_value);
 
                         // 属性値の開始位置（クォートの後）- UTF-16変換
                         let value_start_line = value_node.start_position().row as usize;
@@ -61,7 +65,7 @@ impl HtmlAngularJsAnalyzer {
                         // ui-router の `ui-sref="state[(...args)]"` は
                         // ディレクティブとしての扱いとは別に state 名参照として登録する。
                         // (`ui-sref-active` / `ui-sref-active-eq` は CSS class なので除外)
-                        if matches!(attr_name.as_str(), "ui-sref" | "data-ui-sref") {
+                        if matches!(attr_name.as_str(), "ui-sref") {
                             self.register_ui_sref_reference(
                                 uri,
                                 value,
@@ -74,7 +78,7 @@ impl HtmlAngularJsAnalyzer {
                             &attr_name,
                             element_tag_name.as_deref(),
                             &self.index,
-                        ) && !is_literal_value_directive(&attr_name)
+                        ) && !is_literal_value_directive(&attr_name, &self.index)
                         {
                             // ngディレクティブ または custom directive / component binding:
                             // 属性値全体をAngular式として解析
@@ -82,12 +86,20 @@ impl HtmlAngularJsAnalyzer {
                             //  リテラル文字列扱いのディレクティブは除外)
                             let property_paths = self.parse_angular_expression(value, &attr_name);
                             self.register_scope_references(uri, value, &property_paths, value_start_line as u32, value_start_col);
+                            self.register_filter_references(uri, value, value_start_line as u32, value_start_col);
+                            self.collect_expression_assignments(
+                                uri,
+                                &attr_name,
+                                value,
+                                value_start_line as u32,
+                                value_start_col,
+                            );
 
                             // ng-model="X" は $scope への暗黙的書き込みを生むので、
                             // テンプレート側で定義として記録する
                             // (controller 側で `$scope.X = ...` を書かなくても診断で
                             //  「未定義」と判定されないようにするため)
-                            if attr_name == "ng-model" || attr_name == "data-ng-model" {
+                            if attr_name == "ng-model" {
                                 self.register_ng_model_target(
                                     uri,
                                     value,
@@ -95,6 +107,15 @@ impl HtmlAngularJsAnalyzer {
                                     value_start_col,
                                 );
                             }
+                        } else if is_ng_switch_when(&attr_name)
+                            && ng_switch_when_variable(value)
+                                .is_some_and(|path| self.is_switch_when_scope_variable(uri, path, value_start_line as u32))
+                        {
+                            // `ng-switch-when="someVar"` はスコープ変数として定義されている
+                            // 場合に限り ng-if などと同じ式として解析する
+                            // (未定義の識別子は case ラベル)
+                            let property_paths = self.parse_angular_expression(value, &attr_name);
+                            self.register_scope_references(uri, value, &property_paths, value_start_line as u32, value_start_col);
                         } else {
                             // 非ディレクティブ属性 または リテラル値ディレクティブ:
                             // インターポレーションのみを抽出 (例: `ng-message="{{key}}"` のように
@@ -107,6 +128,51 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// スコープ参照として登録するプロパティパスか判定
+    ///
+    /// - `$parent.x` / `$parent.$parent.x` / `$root.x`: 辿った先のスコープで定義済みの場合のみ
+    /// - `alias.x`: alias がコントローラーエイリアスかフォーム名の場合のみ
+    /// - 単純な識別子: 常に登録
+    fn is_registrable_scope_path(&self, uri: &Url, property_path: &str, line: u32, col: u32) -> bool {
+        if property_path.starts_with("$parent.") || property_path.starts_with("$root.") {
+            return self
+                .index
+                .resolve_parent_scope_symbol(uri, line, col, property_path)
+                .is_some();
+        }
+        match property_path.split_once('.') {
+            Some((alias, _)) => {
+                self.index.resolve_controller_by_alias(uri, line, alias).is_some()
+                    || self.index.find_form_binding_definition(uri, alias, line).is_some()
+            }
+            None => true,
+        }
+    }
+
+    /// `ng-switch-when` の値がスコープ変数を指しているか判定
+    ///
+    /// `alias.prop` 形式はエイリアス検証を `register_scope_references` に任せる。
+    /// 単独の識別子は、その位置で有効なコントローラーの `$scope` プロパティ
+    /// または `$rootScope` プロパティとして定義されている場合のみ変数とみなす。
+    fn is_switch_when_scope_variable(&self, uri: &Url, path: &str, line: u32) -> bool {
+        if path.contains('.') {
+            return true;
+        }
+        self.index
+            .resolve_controllers_for_html(uri, line)
+            .iter()
+            .any(|controller| {
+                self.index
+                    .definitions
+                    .has_definition(&format!("{}.$scope.{}", controller, path))
+            })
+            || !self
+                .index
+                .definitions
+                .find_root_scope_definitions_by_property(path)
+                .is_empty()
+    }
+
     /// スコープ参照を登録（共通処理）- UTF-16対応
     fn register_scope_references(
         &self,
@@ -123,31 +189,20 @@ impl HtmlAngularJsAnalyzer {
                 continue;
             }
 
-            // alias.property形式の場合、aliasまたはフォームバインディングが有効かチェック
-            // どちらでもない場合はスキップ（単純な識別子だけを登録）
-            if property_path.contains('.') {
-                let parts: Vec<&str> = property_path.splitn(2, '.').collect();
-                if parts.len() == 2 {
-                    let potential_alias = parts[0];
-                    // このaliasが有効かチェック
-                    let is_alias = self.index.resolve_controller_by_alias(uri, value_start_line, potential_alias).is_some();
-                    // フォームバインディングかチェック
-                    let is_form_binding = self.index.find_form_binding_definition(uri, potential_alias, value_start_line).is_some();
-                    if !is_alias && !is_form_binding {
-                        // aliasでもフォームバインディングでもないのでスキップ
-                        continue;
-                    }
-                }
+            // alias.property / $parent.property 形式は解決できる場合のみ登録
+            // （単純な識別子はそのまま登録）
+            if !self.is_registrable_scope_path(uri, property_path, value_start_line, value_start_col) {
+                continue;
             }
 
             // 属性値内で識別子のすべての出現位置を検索
-            let positions = self.find_identifier_positions(value, property_path);
+            let positions = self.find_scope_identifier_positions(value, property_path);
 
             for (byte_offset, byte_len) in positions {
-                // alias.property 形式の場合、span は property 部分のみを覆うようにする。
+                // alias.property / $parent.property 形式の場合、span は property 部分のみを覆うようにする。
                 // (`alias` は別の単独 ref として登録されるため、両者を別位置にすることで
                 //  semantic tokens の overlap dedup で alias 部分が消えなくなる)
-                let (span_byte_offset, span_byte_len) = match property_path.find('.') {
+                let (span_byte_offset, span_byte_len) = match property_path.rfind('.') {
                     Some(dot_idx) => (byte_offset + dot_idx + 1, byte_len - dot_idx - 1),
                     None => (byte_offset, byte_len),
                 };
@@ -171,6 +226,29 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// 式中のフィルター名を `SymbolReference` として登録 - UTF-16対応
+    ///
+    /// `.filter('name', ...)` の定義と同じシンボル名で `index.definitions` に登録する
+    /// ことで、JS 側の定義ジャンプ・参照検索にそのまま乗る
+    fn register_filter_references(
+        &self,
+        uri: &Url,
+        expr: &str,
+        expr_start_line: u32,
+        expr_start_col: u32,  // UTF-16コードユニット単位
+    ) {
+        for (name, byte_offset) in self.find_filter_names(expr) {
+            let (start_line, start_col) =
+                self.position_in_text(expr, byte_offset, expr_start_line, expr_start_col);
+            let end_col = start_col + name.chars().map(|c| c.len_utf16()).sum::<usize>() as u32;
+            self.index.definitions.add_reference(SymbolReference {
+                name,
+                uri: uri.clone(),
+                span: Span::new(start_line, start_col, start_line, end_col),
+            });
+        }
+    }
+
     /// 属性値内のインターポレーションからスコープ参照を抽出（UTF-16対応）
     fn extract_interpolation_references_from_attribute(
         &self,
@@ -207,6 +285,7 @@ impl HtmlAngularJsAnalyzer {
                     value_start_line as u32,
                     value_start_col,
                 );
+                self.register_filter_references(uri, expr_trimmed, expr_line, expr_col);
 
                 // 式内でのプロパティパスの位置を登録
                 for property_path in &property_paths {
@@ -216,25 +295,17 @@ impl HtmlAngularJsAnalyzer {
                         continue;
                     }
 
-                    // alias.property形式の場合、aliasまたはフォームバインディングが有効かチェック
-                    if property_path.contains('.') {
-                        let parts: Vec<&str> = property_path.splitn(2, '.').collect();
-                        if parts.len() == 2 {
-                            let potential_alias = parts[0];
-                            let is_alias = self.index.resolve_controller_by_alias(uri, expr_line, potential_alias).is_some();
-                            let is_form_binding = self.index.find_form_binding_definition(uri, potential_alias, expr_line).is_some();
-                            if !is_alias && !is_form_binding {
-                                continue;
-                            }
-                        }
+                    // alias.property / $parent.property 形式は解決できる場合のみ登録
+                    if !self.is_registrable_scope_path(uri, property_path, expr_line, expr_col) {
+                        continue;
                     }
 
                     // 式内で識別子のすべての出現位置を検索
-                    let positions = self.find_identifier_positions(expr_trimmed, property_path);
+                    let positions = self.find_scope_identifier_positions(expr_trimmed, property_path);
 
                     for (byte_offset, byte_len) in positions {
-                        // alias.property は property 部分のみを span にする
-                        let (span_byte_offset, span_byte_len) = match property_path.find('.') {
+                        // alias.property / $parent.property は property 部分のみを span にする
+                        let (span_byte_offset, span_byte_len) = match property_path.rfind('.') {
                             Some(dot_idx) => (byte_offset + dot_idx + 1, byte_len - dot_idx - 1),
                             None => (byte_offset, byte_len),
                         };
@@ -264,6 +335,25 @@ impl HtmlAngularJsAnalyzer {
         }
     }
 
+    /// 式内でスコープ参照となる識別子の出現位置を検索
+    ///
+    /// `find_identifier_positions` の結果からフィルター名の位置
+    /// (`{{ orderBy | orderBy: orderBy }}` の 2 つ目はフィルター参照) と
+    /// 文字列リテラル内の位置 (`{ 'font-size': size }` の `font-size`) を除く
+    fn find_scope_identifier_positions(&self, text: &str, identifier: &str) -> Vec<(usize, usize)> {
+        let filter_offsets: Vec<usize> = self
+            .find_filter_names(text)
+            .into_iter()
+            .map(|(_, offset)| offset)
+            .collect();
+        let strings = string_literal_ranges(text);
+        self.find_identifier_positions(text, identifier)
+            .into_iter()
+            .filter(|(offset, _)| !filter_offsets.contains(offset))
+            .filter(|(offset, _)| !strings.iter().any(|(start, end)| (*start..*end).contains(offset)))
+            .collect()
+    }
+
     /// 文字列内で識別子のすべての出現位置を検索（単語境界を考慮）
     pub(super) fn find_identifier_positions(&self, text: &str, identifier: &str) -> Vec<(usize, usize)> {
         let mut positions = Vec::new();
@@ -412,6 +502,7 @@ impl HtmlAngularJsAnalyzer {
                     node_start_line as u32,
                     node_start_col,
                 );
+                self.register_filter_references(uri, expr_trimmed, expr_line, expr_col);
 
                 for property_path in property_paths {
                     // ローカル変数の場合はスキップ
@@ -420,29 +511,18 @@ impl HtmlAngularJsAnalyzer {
                         continue;
                     }
 
-                    // alias.property形式の場合、aliasまたはフォームバインディングが有効かチェック
-                    // どちらでもない場合はスキップ（単純な識別子だけを登録）
-                    if property_path.contains('.') {
-                        let parts: Vec<&str> = property_path.splitn(2, '.').collect();
-                        if parts.len() == 2 {
-                            let potential_alias = parts[0];
-                            // このaliasが有効かチェック
-                            let is_alias = self.index.resolve_controller_by_alias(uri, expr_line, potential_alias).is_some();
-                            // フォームバインディングかチェック
-                            let is_form_binding = self.index.find_form_binding_definition(uri, potential_alias, expr_line).is_some();
-                            if !is_alias && !is_form_binding {
-                                // aliasでもフォームバインディングでもないのでスキップ
-                                continue;
-                            }
-                        }
+                    // alias.property / $parent.property 形式は解決できる場合のみ登録
+                    // （単純な識別子はそのまま登録）
+                    if !self.is_registrable_scope_path(uri, &property_path, expr_line, expr_col) {
+                        continue;
                     }
 
                     // 式内で識別子のすべての出現位置を検索
-                    let positions = self.find_identifier_positions(expr_trimmed, &property_path);
+                    let positions = self.find_scope_identifier_positions(expr_trimmed, &property_path);
 
                     for (byte_offset, byte_len) in positions {
-                        // alias.property は property 部分のみを span にする
-                        let (span_byte_offset, span_byte_len) = match property_path.find('.') {
+                        // alias.property / $parent.property は property 部分のみを span にする
+                        let (span_byte_offset, span_byte_len) = match property_path.rfind('.') {
                             Some(dot_idx) => (byte_offset + dot_idx + 1, byte_len - dot_idx - 1),
                             None => (byte_offset, byte_len),
                         };
@@ -509,6 +589,16 @@ pub(super) fn position_in_text(
     (line, col)
 }
 
+/// `quoted_attribute_value` のテキストから外側のクォート 1 組を除く
+fn unquote_attribute_value(raw: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = raw.strip_prefix(quote).and_then(|r| r.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    raw
+}
+
 #[cfg(test)]
 mod position_in_text_tests {
     use super::{byte_offset_to_utf16_offset, position_in_text};
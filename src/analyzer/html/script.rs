@@ -1,14 +1,34 @@
 //! <script> tag JavaScript extraction
 
-use tree_sitter::Node;
+use tree_sitter::{Node, Tree};
 
 use super::HtmlAngularJsAnalyzer;
+use crate::util::utf16_col_to_byte_col;
 
 /// JavaScript code extracted from <script> tags in HTML files
 #[derive(Debug, Clone)]
 pub struct EmbeddedScript {
     pub source: String,
     pub line_offset: u32,
+    /// 先頭行で script の中身が始まるバイト列 (`<script>` の直後)
+    pub start_col: u32,
+}
+
+impl EmbeddedScript {
+    /// HTML 上の位置 (line, バイト列) がこの script 内なら、script ローカルの位置に変換する
+    pub fn to_local_position(&self, line: u32, col: u32) -> Option<(u32, u32)> {
+        let local_line = line.checked_sub(self.line_offset)?;
+        let local_col = if local_line == 0 {
+            col.checked_sub(self.start_col)?
+        } else {
+            col
+        };
+
+        // 末尾の改行の後 (`</script>` の行頭) も script 内として扱う
+        let mut lines = self.source.split('\n');
+        let line_text = lines.nth(local_line as usize)?;
+        (local_col as usize <= line_text.len()).then_some((local_line, local_col))
+    }
 }
 
 impl HtmlAngularJsAnalyzer {
@@ -24,6 +44,28 @@ impl HtmlAngularJsAnalyzer {
         scripts
     }
 
+    /// カーソル位置を含む <script> と、script ローカルの位置 (line, バイト列) を返す
+    ///
+    /// `character` は LSP の UTF-16 の列で、ここでバイト列に変換する。
+    /// `cached_tree` は `source` からパース済みの Tree (キャッシュヒット時のみ)。
+    /// 複数の <script> がある場合はカーソルを含むものを選ぶ
+    pub fn script_at(
+        source: &str,
+        cached_tree: Option<&Tree>,
+        line: u32,
+        character: u32,
+    ) -> Option<(EmbeddedScript, u32, u32)> {
+        let col = utf16_col_to_byte_col(source, line, character);
+        let scripts = match cached_tree {
+            Some(tree) => Self::extract_scripts_from_tree(tree.root_node(), source),
+            None => Self::extract_scripts(source),
+        };
+        scripts.into_iter().find_map(|script| {
+            let (local_line, local_col) = script.to_local_position(line, col)?;
+            Some((script, local_line, local_col))
+        })
+    }
+
     /// Extract JavaScript from a pre-parsed Tree
     pub fn extract_scripts_from_tree(root: Node, source: &str) -> Vec<EmbeddedScript> {
         let mut scripts = Vec::new();
@@ -42,6 +84,7 @@ impl HtmlAngularJsAnalyzer {
                     scripts.push(EmbeddedScript {
                         source: js_source,
                         line_offset,
+                        start_col: child.start_position().column as u32,
                     });
                 }
             }
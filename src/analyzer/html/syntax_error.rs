@@ -0,0 +1,49 @@
+//! HTML の構文エラー (tree-sitter の ERROR / MISSING ノード) の収集
+
+use tower_lsp::lsp_types::Url;
+use tree_sitter::{Node, Tree};
+
+use super::HtmlAngularJsAnalyzer;
+use crate::model::{HtmlSyntaxError, Span};
+
+impl HtmlAngularJsAnalyzer {
+    /// パースツリーの ERROR / MISSING ノードを診断用に記録する（Pass 1 で実行）
+    pub(super) fn collect_syntax_errors(&self, uri: &Url, source: &str, tree: &Tree) {
+        if tree.root_node().has_error() {
+            self.collect_syntax_errors_impl(tree.root_node(), source, uri);
+        }
+    }
+
+    /// ERROR ノードは中身をまとめて 1 件とし、子孫は辿らない
+    fn collect_syntax_errors_impl(&self, node: Node, source: &str, uri: &Url) {
+        if node.is_missing() || node.is_error() {
+            let start = node.start_position();
+            let end = node.end_position();
+            // 閉じタグの欠落などで後続の要素ごと ERROR になった場合は開始行だけを示す
+            let (end_row, end_col) = if end.row == start.row {
+                (end.row, end.column)
+            } else {
+                let line_len = source.lines().nth(start.row).map_or(0, |l| l.len());
+                (start.row, line_len)
+            };
+            self.index.diagnostics.add_html_syntax_error(HtmlSyntaxError {
+                uri: uri.clone(),
+                missing: node.is_missing().then(|| node.kind().to_string()),
+                span: Span::new(
+                    start.row as u32,
+                    self.byte_col_to_utf16_col(source, start.row, start.column),
+                    end_row as u32,
+                    self.byte_col_to_utf16_col(source, end_row, end_col),
+                ),
+            });
+            return;
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.has_error() {
+                self.collect_syntax_errors_impl(child, source, uri);
+            }
+        }
+    }
+}
@@ -13,17 +13,64 @@ pub struct ParsedVariable {
     pub len: usize,
 }
 
+/// ng-repeat expression split into its clauses
+/// e.g. "item in items | filter:q as filtered track by item.id"
+#[derive(Clone, Debug, PartialEq)]
+pub struct NgRepeatClauses<'a> {
+    /// Part before ` in ` ("item" / "(key, value)")
+    pub iterator: &'a str,
+    /// Collection expression including filters ("items | filter:q") with its byte offset
+    pub collection: (&'a str, usize),
+    /// `as` alias ("filtered") with its byte offset
+    pub alias: Option<(&'a str, usize)>,
+    /// `track by` expression ("item.id") with its byte offset
+    pub track_by: Option<(&'a str, usize)>,
+}
+
+/// Split ng-repeat expression into iterator / collection / `as` alias / `track by`
+///
+/// Follows the clause order of AngularJS's ngRepeat
+/// (`item in collection [as alias] [track by expr]`)
+pub fn split_ng_repeat_expression(expr: &str) -> Option<NgRepeatClauses<'_>> {
+    let in_idx = expr.find(" in ")?;
+    let rhs_start = in_idx + 4;
+
+    let track_idx = expr[rhs_start..].find(" track by ").map(|idx| rhs_start + idx);
+    let collection_end = track_idx.unwrap_or(expr.len());
+    let as_idx = expr[rhs_start..collection_end].find(" as ").map(|idx| rhs_start + idx);
+
+    let non_empty = |clause: &(&str, usize)| !clause.0.is_empty();
+    Some(NgRepeatClauses {
+        iterator: &expr[..in_idx],
+        collection: trimmed_with_offset(expr, rhs_start, as_idx.unwrap_or(collection_end)),
+        alias: as_idx
+            .map(|idx| trimmed_with_offset(expr, idx + 4, collection_end))
+            .filter(non_empty),
+        track_by: track_idx
+            .map(|idx| trimmed_with_offset(expr, idx + 10, expr.len()))
+            .filter(non_empty),
+    })
+}
+
+/// Trim `text[start..end]` and return it with its byte offset within `text`
+fn trimmed_with_offset(text: &str, start: usize, end: usize) -> (&str, usize) {
+    let part = &text[start..end];
+    let leading = part.len() - part.trim_start().len();
+    (part.trim(), start + leading)
+}
+
 /// Parse ng-repeat expression for variables
 /// e.g. "item in items" -> [ParsedVariable { name: "item", ... }]
 /// e.g. "(key, value) in obj" -> [ParsedVariable { name: "key", ... }, ParsedVariable { name: "value", ... }]
+/// e.g. "item in items | filter:q as filtered" -> [..., ParsedVariable { name: "filtered", ... }]
 pub fn parse_ng_repeat_expression(expr: &str) -> Vec<ParsedVariable> {
     let mut result = Vec::new();
 
-    let Some(in_idx) = expr.find(" in ") else {
+    let Some(clauses) = split_ng_repeat_expression(expr) else {
         return result;
     };
 
-    let iter_part = &expr[..in_idx];
+    let iter_part = clauses.iterator;
 
     if iter_part.trim().starts_with('(') {
         // (key, value) pattern
@@ -64,6 +111,15 @@ pub fn parse_ng_repeat_expression(expr: &str) -> Vec<ParsedVariable> {
         }
     }
 
+    if let Some((alias, offset)) = clauses.alias.filter(|(alias, _)| is_valid_identifier(alias)) {
+        result.push(ParsedVariable {
+            name: alias.to_string(),
+            source: HtmlLocalVariableSource::NgRepeatAlias,
+            offset,
+            len: alias.len(),
+        });
+    }
+
     result
 }
 
@@ -146,6 +202,32 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_split_ng_repeat_clauses() {
+        let expr = "item in items | filter:q as filtered track by item.id";
+        let clauses = split_ng_repeat_expression(expr).unwrap();
+        assert_eq!(clauses.iterator, "item");
+        assert_eq!(clauses.collection, ("items | filter:q", 8));
+        assert_eq!(clauses.alias, Some(("filtered", 28)));
+        assert_eq!(clauses.track_by, Some(("item.id", 46)));
+
+        let clauses = split_ng_repeat_expression("item in items track by $index").unwrap();
+        assert_eq!(clauses.collection, ("items", 8));
+        assert_eq!(clauses.alias, None);
+        assert_eq!(clauses.track_by, Some(("$index", 23)));
+    }
+
+    #[test]
+    fn test_parse_ng_repeat_alias() {
+        let vars = parse_ng_repeat_expression("item in items | filter:q as filtered track by item.id");
+        assert_eq!(vars.len(), 2);
+        assert_eq!(vars[0].name, "item");
+        assert_eq!(vars[1].name, "filtered");
+        assert_eq!(vars[1].offset, 28);
+        assert_eq!(vars[1].len, 8);
+        assert!(matches!(vars[1].source, HtmlLocalVariableSource::NgRepeatAlias));
+    }
+
     #[test]
     fn test_parse_ng_init_single() {
         let vars = parse_ng_init_expression("a = 1");
@@ -0,0 +1,189 @@
+//! tree-sitter の差分再パース (`Tree::edit` + 前回 Tree を渡した `parse`) 用のキャッシュ。
+//!
+//! `did_change` はクライアントから編集範囲付き (incremental sync) で届き、
+//! `apply_content_changes` でドキュメントに適用される。Tree の編集はその範囲からではなく、
+//! 適用後のソースと前回のソースの共通接頭辞/接尾辞から 1 つの [`InputEdit`] を
+//! 計算し直し、前回の Tree に適用してから再パースする。tree-sitter は変更されていない
+//! サブツリーを再利用するため、大きなファイルの 1 文字編集でもパースはほぼ一定時間で済む。
+//!
+//! `Tree` は `Send + Sync` なので URI ごとに `DashMap` に保持できる。キャッシュするのは
+//! エディタで開いているドキュメントだけ (ワークスペーススキャンは対象外) で、
+//! `did_close` で [`SyntaxTreeCache::remove`] して破棄する。
+//!
+//! JS のキャッシュは inlay hints とも共有し、デバウンス解析で更新された Tree を
+//! ソースが一致する限りそのまま再利用する ([`SyntaxTreeCache::tree_for_source`])。
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::Url;
+use tree_sitter::{InputEdit, Point, Tree};
+
+/// 前回パース時のソースと Tree
+struct CachedTree {
+    source: String,
+    tree: Tree,
+}
+
+/// URI ごとの前回 Tree キャッシュ
+#[derive(Default)]
+pub struct SyntaxTreeCache {
+    trees: DashMap<Url, CachedTree>,
+}
+
+impl SyntaxTreeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `source` に合わせて `edit` 済みの前回 Tree を返す
+    ///
+    /// キャッシュがない場合は `None` (フルパースになる)。ソースが前回と同一でも
+    /// 編集なしの Tree を返すので、そのまま `parse` に渡せる。
+    pub fn edited_tree(&self, uri: &Url, source: &str) -> Option<Tree> {
+        let cached = self.trees.get(uri)?;
+        let mut tree = cached.tree.clone();
+        if let Some(edit) = compute_input_edit(&cached.source, source) {
+            tree.edit(&edit);
+        }
+        Some(tree)
+    }
+
+    /// `source` と同じソースからパースした Tree がキャッシュにあれば返す
+    pub fn tree_for_source(&self, uri: &Url, source: &str) -> Option<Tree> {
+        self.trees
+            .get(uri)
+            .filter(|cached| cached.source == source)
+            .map(|cached| cached.tree.clone())
+    }
+
+    /// 今回のパース結果を次回の差分パース用に保存する
+    pub fn insert(&self, uri: &Url, source: &str, tree: &Tree) {
+        self.trees.insert(
+            uri.clone(),
+            CachedTree {
+                source: source.to_string(),
+                tree: tree.clone(),
+            },
+        );
+    }
+
+    pub fn remove(&self, uri: &Url) {
+        self.trees.remove(uri);
+    }
+}
+
+/// 前後のテキストの差分を 1 つの `InputEdit` として表す
+///
+/// 共通接頭辞と共通接尾辞を除いた中央部分を「置換された範囲」とみなす。
+/// 同一テキストなら `None`。境界は UTF-8 の文字境界に揃える。
+pub fn compute_input_edit(old: &str, new: &str) -> Option<InputEdit> {
+    if old == new {
+        return None;
+    }
+
+    let old_bytes = old.as_bytes();
+    let new_bytes = new.as_bytes();
+
+    let mut start = old_bytes
+        .iter()
+        .zip(new_bytes)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(start) || !new.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    // 接尾辞は接頭辞と重ならない範囲で数える
+    let max_suffix = old_bytes.len().min(new_bytes.len()) - start;
+    let mut suffix = old_bytes
+        .iter()
+        .rev()
+        .zip(new_bytes.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old_bytes.len() - suffix)
+        || !new.is_char_boundary(new_bytes.len() - suffix)
+    {
+        suffix -= 1;
+    }
+
+    let old_end = old_bytes.len() - suffix;
+    let new_end = new_bytes.len() - suffix;
+
+    Some(InputEdit {
+        start_byte: start,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old, start),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    })
+}
+
+/// バイトオフセットを tree-sitter の `Point` (行, 行内バイト列) に変換する
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text.as_bytes()[..byte];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    Point::new(row, byte - line_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    #[test]
+    fn identical_text_has_no_edit() {
+        assert!(compute_input_edit("abc", "abc").is_none());
+    }
+
+    #[test]
+    fn insertion_edit_spans_inserted_text() {
+        let edit = compute_input_edit("var a;\nvar b;\n", "var a;\nvar bc;\n").unwrap();
+        assert_eq!(edit.start_byte, 12);
+        assert_eq!(edit.old_end_byte, 12);
+        assert_eq!(edit.new_end_byte, 13);
+        assert_eq!(edit.start_position, Point::new(1, 5));
+        assert_eq!(edit.new_end_position, Point::new(1, 6));
+    }
+
+    #[test]
+    fn repeated_characters_do_not_overlap() {
+        // 接頭辞と接尾辞が重なり得るケース ("aa" → "aaa")
+        let edit = compute_input_edit("aa", "aaa").unwrap();
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (2, 2, 3));
+    }
+
+    #[test]
+    fn edit_respects_utf8_boundaries() {
+        let edit = compute_input_edit("// あ\n", "// い\n").unwrap();
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (3, 6, 6));
+    }
+
+    #[test]
+    fn incremental_parse_matches_full_parse() {
+        let uri = Url::parse("file:///app.js").unwrap();
+        let old = "angular.module('app').controller('A', function($scope) {\n  $scope.x = 1;\n});\n";
+        let new = "angular.module('app').controller('A', function($scope) {\n  $scope.xy = [1, 2];\n});\n";
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_javascript::LANGUAGE.into())
+            .unwrap();
+        let cache = SyntaxTreeCache::new();
+        cache.insert(&uri, old, &parser.parse(old, None).unwrap());
+
+        let edited = cache.edited_tree(&uri, new).unwrap();
+        let incremental = parser.parse(new, Some(&edited)).unwrap();
+        let full = parser.parse(new, None).unwrap();
+        assert_eq!(
+            incremental.root_node().to_sexp(),
+            full.root_node().to_sexp()
+        );
+    }
+}
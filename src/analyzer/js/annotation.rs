@@ -0,0 +1,303 @@
+//! 引数名からミニファイ安全な DI 注釈 (DI 配列 / `$inject`) を作る
+//!
+//! code action 用。カーソルを囲む DI 関数を探し、次のいずれかの挿入を返す。
+//!
+//! - `.controller('A', function($scope, UserService) {...})` → DI 配列で包む
+//! - `['$scope', function($scope, UserService) {...}]` → 足りない注入名を補う
+//! - `function A($scope, UserService) {...}` → `A.$inject = [...]` を追加
+//!   (`.controller('A', A)` のように DI 値として登録されているものだけ。
+//!   既に `A.$inject` があれば足りない注入名を補う)
+
+use tower_lsp::lsp_types::Position;
+use tree_sitter::{Node, Point, Tree};
+
+use super::AngularJsAnalyzer;
+use crate::util::{offset_to_position, utf16_col_to_byte_col};
+
+/// DI 関数を値に取る登録メソッド
+const INJECTABLE_METHODS: &[&str] = &[
+    "controller",
+    "service",
+    "factory",
+    "provider",
+    "directive",
+    "filter",
+    "config",
+    "run",
+    "decorator",
+    "animation",
+];
+
+/// DI 注釈の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiAnnotationKind {
+    /// 関数を DI 配列で包む
+    ConvertToArray,
+    /// 既存の DI 配列に足りない注入名を補う
+    SyncArray,
+    /// 関数宣言の後ろに `$inject` を追加する
+    AddInject,
+    /// 既存の `$inject` に足りない注入名を補う
+    SyncInject,
+}
+
+/// ソースへの挿入 (位置は LSP の UTF-16 列)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiAnnotationInsert {
+    pub line: u32,
+    pub col: u32,
+    pub text: String,
+}
+
+impl DiAnnotationInsert {
+    /// バイトオフセット `offset` への挿入
+    fn at(source: &str, offset: usize, text: String) -> Self {
+        let Position { line, character } = offset_to_position(source, offset);
+        Self { line, col: character, text }
+    }
+}
+
+/// DI 注釈の生成結果
+#[derive(Debug, Clone)]
+pub struct DiAnnotation {
+    pub kind: DiAnnotationKind,
+    pub inserts: Vec<DiAnnotationInsert>,
+}
+
+impl AngularJsAnalyzer {
+    /// カーソル位置を囲む DI 関数のうち最も内側のものについて、DI 注釈の挿入を返す
+    ///
+    /// `tree` は `source` をパースした Tree、`character` は LSP の UTF-16 列。
+    /// 引数に rest / 分割代入があるもの、注釈が既に引数と揃っているものは `None`
+    pub fn di_annotation_at(
+        &self,
+        source: &str,
+        tree: &Tree,
+        line: u32,
+        character: u32,
+    ) -> Option<DiAnnotation> {
+        let col = utf16_col_to_byte_col(source, line, character);
+        let point = Point::new(line as usize, col as usize);
+        let mut node = tree.root_node().descendant_for_point_range(point, point)?;
+        loop {
+            if let Some(annotation) = self.di_annotation_for(node, source) {
+                return Some(annotation);
+            }
+            node = node.parent()?;
+        }
+    }
+
+    fn di_annotation_for(&self, node: Node, source: &str) -> Option<DiAnnotation> {
+        match node.kind() {
+            "function_expression" | "arrow_function" | "class" => {
+                let parent = node.parent()?;
+                if parent.kind() == "array" && self.is_injectable_value(parent, source) {
+                    self.sync_di_array(parent, node, source)
+                } else if self.is_injectable_value(node, source) {
+                    self.convert_to_di_array(node, source)
+                } else {
+                    None
+                }
+            }
+            "function_declaration" | "class_declaration" => self.add_inject_annotation(node, source),
+            _ => None,
+        }
+    }
+
+    /// `name` が `.controller('A', name)` / `controller: name` のように DI 値として使われているか
+    fn is_registered_injectable(&self, node: Node, source: &str, name: &str) -> bool {
+        if node.kind() == "identifier"
+            && self.node_text(node, source) == name
+            && self.is_injectable_value(node, source)
+        {
+            return true;
+        }
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if self.is_registered_injectable(child, source, name) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// `.controller('A', <value>)` の登録値や、`controller:` / `resolve` の値の位置か
+    fn is_injectable_value(&self, node: Node, source: &str) -> bool {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        match parent.kind() {
+            "arguments" => {
+                let is_last = parent.named_child(parent.named_child_count().saturating_sub(1)) == Some(node);
+                is_last
+                    && parent
+                        .parent()
+                        .and_then(|call| call.child_by_field_name("function"))
+                        .filter(|callee| callee.kind() == "member_expression")
+                        .and_then(|callee| callee.child_by_field_name("property"))
+                        .is_some_and(|property| {
+                            INJECTABLE_METHODS.contains(&self.node_text(property, source).as_str())
+                        })
+            }
+            "pair" => {
+                let key_is = |pair: Node, name: &str| {
+                    pair.child_by_field_name("key")
+                        .is_some_and(|key| self.extract_string_value(key, source) == name)
+                };
+                // `resolve: { user: function(UserService) {...} }`
+                let in_resolve = parent
+                    .parent()
+                    .filter(|object| object.kind() == "object")
+                    .and_then(|object| object.parent())
+                    .is_some_and(|pair| pair.kind() == "pair" && key_is(pair, "resolve"));
+                key_is(parent, "controller") || in_resolve
+            }
+            _ => false,
+        }
+    }
+
+    fn convert_to_di_array(&self, func: Node, source: &str) -> Option<DiAnnotation> {
+        let params = self.injectable_param_names(func, source)?;
+        Some(DiAnnotation {
+            kind: DiAnnotationKind::ConvertToArray,
+            inserts: vec![
+                DiAnnotationInsert::at(source, func.start_byte(), format!("[{}, ", quoted_list(&params))),
+                DiAnnotationInsert::at(source, func.end_byte(), "]".to_string()),
+            ],
+        })
+    }
+
+    fn sync_di_array(&self, array: Node, func: Node, source: &str) -> Option<DiAnnotation> {
+        let params = self.injectable_param_names(func, source)?;
+        let insert = self.missing_dependencies_insert(array, &params, source)?;
+        Some(DiAnnotation {
+            kind: DiAnnotationKind::SyncArray,
+            inserts: vec![insert],
+        })
+    }
+
+    /// 関数宣言 / class 宣言の `$inject` を追加 (既にあれば足りない注入名を補う)
+    fn add_inject_annotation(&self, decl: Node, source: &str) -> Option<DiAnnotation> {
+        let name = self.node_text(decl.child_by_field_name("name")?, source);
+        let params = self.injectable_param_names(decl, source)?;
+
+        let root = {
+            let mut current = decl;
+            while let Some(parent) = current.parent() {
+                current = parent;
+            }
+            current
+        };
+        if let Some(array) = find_inject_array(root, source, &name) {
+            let insert = self.missing_dependencies_insert(array, &params, source)?;
+            return Some(DiAnnotation {
+                kind: DiAnnotationKind::SyncInject,
+                inserts: vec![insert],
+            });
+        }
+        // DI 値として登録されていない関数には `$inject` を付けない
+        if !self.is_registered_injectable(root, source, &name) {
+            return None;
+        }
+
+        // 宣言の次の行に宣言と同じインデントで追加する
+        let indent: String = source
+            .lines()
+            .nth(decl.start_position().row)
+            .unwrap_or("")
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        Some(DiAnnotation {
+            kind: DiAnnotationKind::AddInject,
+            inserts: vec![DiAnnotationInsert::at(
+                source,
+                decl.end_byte(),
+                format!("\n{}{}.$inject = [{}];", indent, name, quoted_list(&params)),
+            )],
+        })
+    }
+
+    /// DI 配列 / `$inject` に足りない注入名 (引数の後ろの方) を足す挿入
+    ///
+    /// 文字列以外の要素 (変数・スプレッドなど) を含む配列や、注入名が引数以上ある場合は `None`
+    fn missing_dependencies_insert(
+        &self,
+        array: Node,
+        params: &[String],
+        source: &str,
+    ) -> Option<DiAnnotationInsert> {
+        let mut strings = Vec::new();
+        let mut has_function = false;
+        let mut cursor = array.walk();
+        for child in array.named_children(&mut cursor) {
+            match child.kind() {
+                "string" => strings.push(child),
+                "function_expression" | "arrow_function" | "class" => has_function = true,
+                "comment" => {}
+                _ => return None,
+            }
+        }
+        let missing = params.get(strings.len()..).filter(|missing| !missing.is_empty())?;
+
+        Some(match strings.last() {
+            Some(last) => DiAnnotationInsert::at(source, last.end_byte(), format!(", {}", quoted_list(missing))),
+            None => DiAnnotationInsert::at(
+                source,
+                array.start_byte() + 1,
+                if has_function {
+                    format!("{}, ", quoted_list(missing))
+                } else {
+                    quoted_list(missing)
+                },
+            ),
+        })
+    }
+
+    /// 注入名として使える引数名 (rest / 分割代入を含む関数は `None`)
+    fn injectable_param_names(&self, func: Node, source: &str) -> Option<Vec<String>> {
+        let func = match func.kind() {
+            "class" | "class_declaration" => self.get_constructor_from_class(func, source)?,
+            _ => func,
+        };
+        let params = self.extract_function_params(func, source)?;
+        params
+            .iter()
+            .all(|param| !param.starts_with("...") && !param.starts_with(['{', '[']))
+            .then_some(params)
+    }
+}
+
+/// `name.$inject = [...]` の配列
+fn find_inject_array<'a>(node: Node<'a>, source: &str, name: &str) -> Option<Node<'a>> {
+    if node.kind() == "assignment_expression" {
+        let is_inject = node
+            .child_by_field_name("left")
+            .filter(|left| left.kind() == "member_expression")
+            .is_some_and(|left| {
+                let object = left.child_by_field_name("object");
+                let property = left.child_by_field_name("property");
+                object.is_some_and(|o| &source[o.byte_range()] == name)
+                    && property.is_some_and(|p| &source[p.byte_range()] == "$inject")
+            });
+        if is_inject {
+            return node.child_by_field_name("right").filter(|right| right.kind() == "array");
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if let Some(array) = find_inject_array(child, source, name) {
+            return Some(array);
+        }
+    }
+    None
+}
+
+/// `'a', 'b'`
+fn quoted_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
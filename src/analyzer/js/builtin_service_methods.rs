@@ -0,0 +1,238 @@
+//! AngularJS built-in service method definitions
+
+use tree_sitter::{Node, Point, Tree};
+
+use super::injected_params;
+
+/// 組み込みサービスのメソッド (名前, 呼び出し形式, 説明)
+///
+/// 呼び出し形式の省略可能な引数は `[...]`。プロパティは呼び出し形式に名前だけを書く
+pub type BuiltinMethod = (&'static str, &'static str, &'static str);
+
+/// 組み込みサービス名 → メソッド一覧
+static NG_BUILTIN_SERVICE_METHODS: &[(&str, &[BuiltinMethod])] = &[
+    (
+        "$http",
+        &[
+            ("get", "get(url, [config])", "Performs a GET request."),
+            ("post", "post(url, data, [config])", "Performs a POST request."),
+            ("put", "put(url, data, [config])", "Performs a PUT request."),
+            ("patch", "patch(url, data, [config])", "Performs a PATCH request."),
+            ("delete", "delete(url, [config])", "Performs a DELETE request."),
+            ("head", "head(url, [config])", "Performs a HEAD request."),
+            ("jsonp", "jsonp(url, [config])", "Performs a JSONP request. The URL must be trusted via `$sce`."),
+            ("defaults", "defaults", "Default request configuration (headers, transforms, cache)."),
+            ("pendingRequests", "pendingRequests", "Request configurations of the currently pending requests."),
+        ],
+    ),
+    (
+        "$q",
+        &[
+            ("defer", "defer()", "Creates a deferred object with `promise`, `resolve`, `reject` and `notify`."),
+            ("all", "all(promises)", "Resolves once all promises resolve; rejects on the first rejection."),
+            ("race", "race(promises)", "Settles with the first promise that resolves or rejects."),
+            ("resolve", "resolve(value, [successCallback], [errorCallback], [progressCallback])", "Wraps a value or third-party promise into a `$q` promise."),
+            ("when", "when(value, [successCallback], [errorCallback], [progressCallback])", "Alias of `resolve`."),
+            ("reject", "reject(reason)", "Creates a promise rejected with `reason`."),
+        ],
+    ),
+    (
+        "$timeout",
+        &[("cancel", "cancel([promise])", "Cancels a task scheduled by `$timeout`.")],
+    ),
+    (
+        "$interval",
+        &[("cancel", "cancel([promise])", "Cancels a task scheduled by `$interval`.")],
+    ),
+    (
+        "$location",
+        &[
+            ("absUrl", "absUrl()", "Returns the full URL."),
+            ("url", "url([url])", "Gets or sets the URL (path, search and hash)."),
+            ("protocol", "protocol()", "Returns the protocol of the current URL."),
+            ("host", "host()", "Returns the host of the current URL."),
+            ("port", "port()", "Returns the port of the current URL."),
+            ("path", "path([path])", "Gets or sets the path of the current URL."),
+            ("search", "search([search], [paramValue])", "Gets or sets the search part of the current URL."),
+            ("hash", "hash([hash])", "Gets or sets the hash fragment of the current URL."),
+            ("replace", "replace()", "Replaces the last history record instead of adding a new one."),
+            ("state", "state([state])", "Gets or sets the history state object (HTML5 mode only)."),
+        ],
+    ),
+    (
+        "$log",
+        &[
+            ("log", "log(...args)", "Writes a log message."),
+            ("info", "info(...args)", "Writes an information message."),
+            ("warn", "warn(...args)", "Writes a warning message."),
+            ("error", "error(...args)", "Writes an error message."),
+            ("debug", "debug(...args)", "Writes a debug message (suppressed when debug logging is disabled)."),
+        ],
+    ),
+    (
+        "$sce",
+        &[
+            ("trustAsHtml", "trustAsHtml(value)", "Marks a value as trusted HTML."),
+            ("trustAsUrl", "trustAsUrl(value)", "Marks a value as a trusted URL."),
+            ("trustAsResourceUrl", "trustAsResourceUrl(value)", "Marks a value as a trusted resource URL."),
+            ("trustAsJs", "trustAsJs(value)", "Marks a value as trusted JavaScript."),
+            ("trustAs", "trustAs(type, value)", "Marks a value as trusted for the given context."),
+            ("getTrustedHtml", "getTrustedHtml(value)", "Returns the trusted HTML value or throws."),
+            ("getTrustedResourceUrl", "getTrustedResourceUrl(value)", "Returns the trusted resource URL or throws."),
+            ("parseAsHtml", "parseAsHtml(expression)", "Parses an expression and returns the trusted HTML."),
+            ("isEnabled", "isEnabled()", "Returns whether Strict Contextual Escaping is enabled."),
+        ],
+    ),
+    (
+        "$templateCache",
+        &[
+            ("get", "get(key)", "Returns a cached template."),
+            ("put", "put(key, value)", "Adds a template to the cache."),
+            ("remove", "remove(key)", "Removes a template from the cache."),
+            ("removeAll", "removeAll()", "Clears the cache."),
+            ("info", "info()", "Returns the cache id and size."),
+        ],
+    ),
+    (
+        "$cacheFactory",
+        &[
+            ("get", "get(cacheId)", "Returns the cache created with `cacheId`."),
+            ("info", "info()", "Returns information about all caches."),
+        ],
+    ),
+    (
+        "$injector",
+        &[
+            ("get", "get(name, [caller])", "Returns a service instance."),
+            ("has", "has(name)", "Returns whether a service is registered."),
+            ("invoke", "invoke(fn, [self], [locals])", "Invokes a function, injecting its dependencies."),
+            ("instantiate", "instantiate(Type, [locals])", "Creates an instance of a constructor, injecting its dependencies."),
+            ("annotate", "annotate(fn, [strictDi])", "Returns the names of the services a function requests."),
+        ],
+    ),
+    (
+        "$animate",
+        &[
+            ("enter", "enter(element, parent, [after], [options])", "Inserts an element with an enter animation."),
+            ("leave", "leave(element, [options])", "Removes an element with a leave animation."),
+            ("move", "move(element, parent, [after], [options])", "Moves an element with a move animation."),
+            ("addClass", "addClass(element, className, [options])", "Adds a CSS class with an animation."),
+            ("removeClass", "removeClass(element, className, [options])", "Removes a CSS class with an animation."),
+            ("setClass", "setClass(element, add, remove, [options])", "Adds and removes CSS classes with an animation."),
+            ("animate", "animate(element, from, to, [className], [options])", "Performs an inline animation."),
+            ("cancel", "cancel(animationPromise)", "Cancels a running animation."),
+            ("enabled", "enabled([element], [enabled])", "Gets or sets whether animations are enabled."),
+            ("on", "on(event, container, callback)", "Registers an animation event callback."),
+            ("off", "off(event, [container], [callback])", "Deregisters an animation event callback."),
+            ("pin", "pin(element, parentElement)", "Associates an element outside the app with a parent for animations."),
+        ],
+    ),
+    (
+        "$route",
+        &[
+            ("reload", "reload()", "Reloads the current route."),
+            ("updateParams", "updateParams(newParams)", "Updates the current route parameters."),
+            ("current", "current", "The current route definition."),
+            ("routes", "routes", "All configured route definitions."),
+        ],
+    ),
+    (
+        "$cookies",
+        &[
+            ("get", "get(key)", "Returns the value of a cookie."),
+            ("getObject", "getObject(key)", "Returns the deserialized value of a cookie."),
+            ("getAll", "getAll()", "Returns all cookies as an object."),
+            ("put", "put(key, value, [options])", "Sets a cookie."),
+            ("putObject", "putObject(key, value, [options])", "Serializes and sets a cookie."),
+            ("remove", "remove(key, [options])", "Removes a cookie."),
+        ],
+    ),
+    (
+        "$state",
+        &[
+            ("go", "go(to, [params], [options])", "Transitions to a new state."),
+            ("transitionTo", "transitionTo(to, [toParams], [options])", "Low-level method for transitioning to a new state."),
+            ("href", "href(stateOrName, [params], [options])", "Generates a URL for a state."),
+            ("is", "is(stateOrName, [params], [options])", "Returns whether the current state is exactly the given state."),
+            ("includes", "includes(stateOrName, [params], [options])", "Returns whether the current state includes the given state."),
+            ("reload", "reload([state])", "Reloads the current state."),
+            ("get", "get([stateOrName], [base])", "Returns the registered state declaration(s)."),
+            ("current", "current", "The current state declaration."),
+            ("params", "params", "The parameters of the current state."),
+        ],
+    ),
+    (
+        "$uibModal",
+        &[("open", "open(options)", "Opens a modal and returns a modal instance.")],
+    ),
+    (
+        "$uibModalInstance",
+        &[
+            ("close", "close([result])", "Closes the modal, resolving its result promise."),
+            ("dismiss", "dismiss([reason])", "Dismisses the modal, rejecting its result promise."),
+        ],
+    ),
+    (
+        "$mdDialog",
+        &[
+            ("show", "show(optionsOrPreset)", "Shows a dialog."),
+            ("hide", "hide([response])", "Hides the dialog, resolving the promise returned by `show`."),
+            ("cancel", "cancel([response])", "Hides the dialog, rejecting the promise returned by `show`."),
+            ("alert", "alert()", "Builds a preset alert dialog."),
+            ("confirm", "confirm()", "Builds a preset confirm dialog."),
+            ("prompt", "prompt()", "Builds a preset prompt dialog."),
+        ],
+    ),
+    (
+        "$mdToast",
+        &[
+            ("show", "show(optionsOrPreset)", "Shows a toast."),
+            ("hide", "hide([response])", "Hides the current toast."),
+            ("cancel", "cancel([response])", "Hides the current toast, rejecting the promise returned by `show`."),
+            ("simple", "simple()", "Builds a preset simple toast."),
+        ],
+    ),
+];
+
+/// 組み込みサービスのメソッド一覧を返す (メソッドを持たないサービスは `None`)
+pub fn builtin_service_methods(service: &str) -> Option<&'static [BuiltinMethod]> {
+    NG_BUILTIN_SERVICE_METHODS
+        .iter()
+        .find(|(name, _)| *name == service)
+        .map(|(_, methods)| *methods)
+}
+
+/// カーソル位置を囲む関数のいずれかで `service` が DI されているか判定する
+///
+/// `service` を同じ名前のパラメータで受けている場合だけ注入済みとみなす。
+/// DI 配列 (`['$http', function(http) {...}]`) は位置でパラメータに対応させるので、
+/// 別名で受けた `$http.` は注入済みにならない。
+/// 組み込みサービスは `ControllerScope` の注入サービスとして記録されないため、
+/// 補完時にソースから判定する。`col` はバイト列
+pub fn is_service_injected_at(tree: &Tree, source: &str, line: u32, col: u32, service: &str) -> bool {
+    let point = Point::new(line as usize, (col as usize).saturating_sub(1));
+    let Some(mut node) = tree.root_node().descendant_for_point_range(point, point) else {
+        return false;
+    };
+
+    loop {
+        if matches!(
+            node.kind(),
+            "function_expression" | "arrow_function" | "function_declaration" | "method_definition"
+        ) && function_injects(node, source, service)
+        {
+            return true;
+        }
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        node = parent;
+    }
+}
+
+/// 関数が `service` を同名のパラメータで受けているか
+fn function_injects(func: Node, source: &str, service: &str) -> bool {
+    injected_params(func, source)
+        .into_iter()
+        .any(|(injected, param)| injected == service && param == service)
+}
@@ -2,11 +2,12 @@ use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
 use super::context::{AnalyzerContext, DiScope};
-use super::AngularJsAnalyzer;
+use super::{find_returned_node, AngularJsAnalyzer};
 use crate::model::{
-    BindingSource, ComponentTemplateUrl, ControllerScope, Span, SymbolBuilder, SymbolKind,
-    SymbolReference, TemplateBinding,
+    BindingSource, ComponentTemplateUrl, ControllerScope, InlineTemplate, Span, SymbolBuilder,
+    SymbolKind, SymbolReference, TemplateBinding, TemplatePathUsage,
 };
+use crate::util::is_html_file;
 
 impl AngularJsAnalyzer {
     /// AngularJSのコンポーネント定義呼び出しを解析する
@@ -270,6 +271,7 @@ impl AngularJsAnalyzer {
         let mut template_url: Option<String> = None;
         let mut template_url_line: Option<u32> = None;
         let mut template_url_col: Option<u32> = None;
+        let mut inline_template: Option<Node> = None;
         let mut controller_as: Option<String> = None;
 
         let mut cursor = obj_node.walk();
@@ -298,6 +300,7 @@ impl AngularJsAnalyzer {
                                     template_url_col = Some(value.start_position().column as u32);
                                 }
                             }
+                            "template" => inline_template = Some(value),
                             "controllerAs" => {
                                 if value.kind() == "string" {
                                     controller_as = Some(self.extract_string_value(value, source));
@@ -310,6 +313,18 @@ impl AngularJsAnalyzer {
             }
         }
 
+        // `template: '...'` はコントローラーの $scope (と controllerAs) で評価される
+        if let Some(template) = inline_template {
+            self.register_inline_template(
+                template,
+                source,
+                uri,
+                controller_name.clone(),
+                controller_name.clone(),
+                controller_as.clone(),
+            );
+        }
+
         // controller が文字列で指定されているなら参照を登録
         if let (Some(name), Some(value_node)) =
             (controller_name.as_ref(), controller_string_value_node)
@@ -369,6 +384,51 @@ impl AngularJsAnalyzer {
         }
     }
 
+    /// `template: '...'` / `` template: `...` `` の中身をインラインテンプレートとして登録する
+    ///
+    /// HTML としての解析は `HtmlAngularJsAnalyzer::analyze_inline_templates` が行う。
+    /// 文字列の連結や関数で組み立てたテンプレート、HTML 内 `<script>` のものは対象外
+    pub(super) fn register_inline_template(
+        &self,
+        value: Node,
+        source: &str,
+        uri: &Url,
+        scope_name: Option<String>,
+        controller_name: Option<String>,
+        controller_as: Option<String>,
+    ) {
+        if !matches!(value.kind(), "string" | "template_string") || is_html_file(uri) {
+            return;
+        }
+        let start = value.start_byte() + 1;
+        let end = value.end_byte().saturating_sub(1).max(start);
+        let mut template = source[start..end].to_string();
+
+        // `${...}` は改行以外を同じバイト数の空白にして位置を保つ
+        let mut cursor = value.walk();
+        for child in value.named_children(&mut cursor) {
+            if child.kind() == "template_substitution" {
+                let range = child.start_byte() - start..child.end_byte() - start;
+                let masked: String = template[range.clone()]
+                    .chars()
+                    .map(|c| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) })
+                    .collect();
+                template.replace_range(range, &masked);
+            }
+        }
+
+        let position = value.start_position();
+        self.index.templates.add_inline_template(InlineTemplate {
+            uri: uri.clone(),
+            source: template,
+            line: self.offset_line(position.row as u32),
+            col: position.column as u32 + 1,
+            scope_name,
+            controller_name,
+            controller_as,
+        });
+    }
+
     /// controller 値の形を判別して、controller_name と「this 抽出に使うノード」を分離する
     ///
     /// - 文字列なら `controller_name` のみ設定（参照登録対象）
@@ -379,7 +439,7 @@ impl AngularJsAnalyzer {
     /// - DI配列の最後の要素が識別子の場合は名前を抽出して controller_name に入れ、
     ///   func ノードとして識別子そのものを渡す
     ///   （extract_controller_methods の identifier ブランチに乗る）
-    fn classify_controller_value<'a>(
+    pub(super) fn classify_controller_value<'a>(
         &self,
         value: Node<'a>,
         source: &str,
@@ -554,8 +614,8 @@ impl AngularJsAnalyzer {
             self.index.controllers.add_controller_scope(ControllerScope {
                 name: component_name.to_string(),
                 uri: uri.clone(),
-                start_line: body_start,
-                end_line: body_end,
+                start_line: self.offset_line(body_start),
+                end_line: self.offset_line(body_end),
                 injected_services: di_info.injected_services.clone(),
             });
         }
@@ -687,6 +747,10 @@ impl AngularJsAnalyzer {
     /// angular.module('myApp', ['dep1', 'dep2'])
     /// angular.module('myApp')  // 既存モジュール参照
     /// ```
+    ///
+    /// setter 形式 (第2引数あり) のモジュール名を定義として登録し、依存配列内の
+    /// モジュール名は参照として登録する。getter 形式は参照として登録する
+    /// ([`Self::extract_module_getter_reference`])
     pub(super) fn extract_module_definition(&self, node: Node, source: &str, uri: &Url, ctx: &mut AnalyzerContext) {
         if let Some(args) = node.child_by_field_name("arguments") {
             if let Some(first_arg) = args.named_child(0) {
@@ -697,6 +761,11 @@ impl AngularJsAnalyzer {
                     // 現在のモジュール名をコンテキストに設定
                     ctx.set_current_module(name.clone());
 
+                    if args.named_child(1).is_none() {
+                        self.extract_module_getter_reference(first_arg, &name, uri);
+                        return;
+                    }
+
                     let docs = self.extract_jsdoc_for_line(start.row, source);
                     let span = self.span_of(first_arg);
 
@@ -707,13 +776,114 @@ impl AngularJsAnalyzer {
                     if let Some(docs_str) = docs {
                         builder = builder.docs(docs_str);
                     }
+                    if let Some(deps) = args.named_child(1).filter(|n| n.kind() == "array") {
+                        builder = builder.dependencies(self.module_dependency_names(deps, source));
+                    }
 
                     self.index.definitions.add_definition(builder.build());
                 }
             }
+            if let Some(deps) = args.named_child(1).filter(|n| n.kind() == "array") {
+                self.extract_module_dependencies(deps, source, uri);
+            }
         }
     }
 
+    /// 依存配列 `['dep1', 'dep2']` のモジュール名 (文字列リテラルのみ)
+    fn module_dependency_names(&self, deps: Node, source: &str) -> Vec<String> {
+        let mut cursor = deps.walk();
+        deps.named_children(&mut cursor)
+            .filter(|n| n.kind() == "string")
+            .map(|dep| self.extract_string_value(dep, source))
+            .collect()
+    }
+
+    /// `angular.module('app', ['dep1', 'dep2'])` の依存モジュール名を参照として登録する
+    fn extract_module_dependencies(&self, deps: Node, source: &str, uri: &Url) {
+        let mut cursor = deps.walk();
+        for dep in deps.named_children(&mut cursor).filter(|n| n.kind() == "string") {
+            self.index.definitions.add_reference(SymbolReference {
+                name: self.extract_string_value(dep, source),
+                uri: uri.clone(),
+                span: self.span_of(dep),
+            });
+        }
+    }
+
+    /// `.filter('x', ...)` 等の登録呼び出しが属するモジュール名を求める
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// angular.module('app').controller('A', ...).filter('b', ...)  // チェーンを辿って 'app'
+    /// var app = angular.module('app', []); app.filter('b', ...)   // 直前の angular.module
+    /// ```
+    ///
+    /// チェーンの場合、外側の呼び出しが先に訪問されるため `ctx` の
+    /// current_module はまだ更新されていない。レシーバを辿って
+    /// `angular.module(...)` 呼び出しを直接探し、見つからなければ `ctx` に頼る。
+    fn find_registering_module(&self, node: Node, source: &str, ctx: &AnalyzerContext) -> Option<String> {
+        let mut receiver = node
+            .child_by_field_name("function")
+            .and_then(|callee| callee.child_by_field_name("object"));
+
+        while let Some(current) = receiver {
+            if current.kind() != "call_expression" {
+                break;
+            }
+            let callee = current.child_by_field_name("function")?;
+            if self.node_text(callee, source) == "angular.module" {
+                let first_arg = current.child_by_field_name("arguments")?.named_child(0)?;
+                if first_arg.kind() != "string" {
+                    return None;
+                }
+                return Some(self.extract_string_value(first_arg, source));
+            }
+            receiver = callee.child_by_field_name("object");
+        }
+
+        ctx.get_current_module().cloned()
+    }
+
+    /// フィルターのファクトリーが返すフィルター関数の引数名を取得する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// .filter('truncate', function() { return function(input, length) { ... }; })
+    /// .filter('truncate', ['dep', function(dep) { return function(input, length) { ... }; }])
+    /// .filter('truncate', () => (input, length) => ...)
+    /// .filter('truncate', truncateFilter)  // function truncateFilter() { return function(...) {} }
+    /// ```
+    fn extract_filter_function_params(&self, factory: Node, source: &str) -> Option<Vec<String>> {
+        let factory_func = match factory.kind() {
+            "array" => {
+                let mut cursor = factory.walk();
+                factory
+                    .named_children(&mut cursor)
+                    .filter(|c| matches!(c.kind(), "function_expression" | "arrow_function"))
+                    .last()?
+            }
+            "function_expression" | "arrow_function" => factory,
+            "identifier" => {
+                let name = self.node_text(factory, source);
+                let mut root = factory;
+                while let Some(parent) = root.parent() {
+                    root = parent;
+                }
+                self.find_function_declaration(root, source, &name)?
+            }
+            _ => return None,
+        };
+
+        let body = factory_func.child_by_field_name("body")?;
+        let filter_func = match body.kind() {
+            "function_expression" | "arrow_function" => body,
+            _ => find_returned_node(body, |n| {
+                matches!(n.kind(), "function_expression" | "arrow_function")
+            })?,
+        };
+        self.extract_function_params(filter_func, source)
+    }
+
     /// コンポーネント（controller, service, factory等）の定義を抽出する
     ///
     /// 認識パターン:
@@ -733,10 +903,19 @@ impl AngularJsAnalyzer {
                     // シンボル名の位置（検索用）は常に文字列リテラルの位置
                     let name_span = self.span_of(first_arg);
 
+                    // フィルターはファクトリーが返す関数の引数 (入力値, フィルター引数...)
+                    let mut parameters = None;
+                    // ディレクティブ定義オブジェクトの restrict
+                    let mut restrict = None;
+
                     // 定義位置は関数定義を優先する
                     let (start, end, docs_line) = if let Some(second_arg) = args.named_child(1) {
                         self.extract_dependencies(second_arg, source, uri);
 
+                        if kind == SymbolKind::Filter {
+                            parameters = self.extract_filter_function_params(second_arg, source);
+                        }
+
                         // DIスコープを追加（配列・関数・class・識別子を統一的に処理、arity 不一致警告も発火）
                         let di_info = self.extract_di_info_with_diagnostics(second_arg, source, uri);
 
@@ -748,8 +927,8 @@ impl AngularJsAnalyzer {
                                     self.index.controllers.add_controller_scope(ControllerScope {
                                         name: component_name.clone(),
                                         uri: uri.clone(),
-                                        start_line: body_start,
-                                        end_line: body_end,
+                                        start_line: self.offset_line(body_start),
+                                        end_line: self.offset_line(body_end),
                                         injected_services: di_info.injected_services.clone(),
                                     });
                                 }
@@ -778,6 +957,11 @@ impl AngularJsAnalyzer {
                             self.extract_controller_methods(second_arg, source, uri, &component_name);
                         }
 
+                        // Directiveの場合は定義オブジェクト (scope / templateUrl 等) を解析
+                        if kind == SymbolKind::Directive {
+                            restrict = self.extract_directive_definition(second_arg, source, uri, &component_name);
+                        }
+
                         // 関数定義の位置を取得
                         if let Some((func_start, func_end)) = self.find_function_position(second_arg, source) {
                             // 関数定義の位置からJSDocを探す
@@ -807,6 +991,15 @@ impl AngularJsAnalyzer {
                     if let Some(docs_str) = docs {
                         builder = builder.docs(docs_str);
                     }
+                    if let Some(params) = parameters {
+                        builder = builder.parameters(params);
+                    }
+                    if let Some(restrict) = restrict {
+                        builder = builder.restrict(restrict);
+                    }
+                    if let Some(module_name) = self.find_registering_module(node, source, ctx) {
+                        builder = builder.module(module_name);
+                    }
 
                     self.index.definitions.add_definition(builder.build());
                 }
@@ -1146,6 +1339,7 @@ impl AngularJsAnalyzer {
         let mut template_path: Option<String> = None;
         let mut template_line: Option<u32> = None;
         let mut template_col: Option<u32> = None;
+        let mut inline_template: Option<Node> = None;
         let mut controller_name: Option<String> = None;
         let mut controller_as: Option<String> = None;
         let mut bindings_node: Option<Node> = None;
@@ -1167,6 +1361,7 @@ impl AngularJsAnalyzer {
                                     template_col = Some(start.column as u32);
                                 }
                             }
+                            "template" => inline_template = Some(value),
                             "controller" => {
                                 // controller: 'ControllerName' (文字列参照)
                                 if value.kind() == "string" {
@@ -1221,6 +1416,18 @@ impl AngularJsAnalyzer {
         // これにより $ctrl.xxx でバインディングにアクセス可能になる
         let effective_controller_name = controller_name.clone().or_else(|| component_name.map(|s| s.to_string()));
 
+        // インラインテンプレートは templateUrl と同じく controllerAs (既定 `$ctrl`) で評価される
+        if let Some(template) = inline_template {
+            self.register_inline_template(
+                template,
+                source,
+                uri,
+                None,
+                effective_controller_name.clone(),
+                Some(controller_as.clone().unwrap_or_else(|| "$ctrl".to_string())),
+            );
+        }
+
         // templateUrlが存在する場合のみ登録
         if let (Some(path), Some(line), Some(col)) = (template_path, template_line, template_col) {
             let template_url = ComponentTemplateUrl {
@@ -1237,7 +1444,7 @@ impl AngularJsAnalyzer {
 
         // bindings を抽出してシンボルとして登録
         if let (Some(bindings), Some(prefix)) = (bindings_node, effective_controller_name.as_deref()) {
-            self.extract_component_bindings(bindings, source, uri, prefix);
+            self.extract_component_bindings(bindings, source, uri, prefix, component_name);
         }
     }
 
@@ -1247,7 +1454,14 @@ impl AngularJsAnalyzer {
     /// ```javascript
     /// bindings: { users: '<', selected: '<', showDetails: '&onSelected' }
     /// ```
-    fn extract_component_bindings(&self, bindings_node: Node, source: &str, uri: &Url, controller_name: &str) {
+    fn extract_component_bindings(
+        &self,
+        bindings_node: Node,
+        source: &str,
+        uri: &Url,
+        controller_name: &str,
+        component_name: Option<&str>,
+    ) {
         let mut cursor = bindings_node.walk();
         for child in bindings_node.children(&mut cursor) {
             if child.kind() == "pair" {
@@ -1267,18 +1481,26 @@ impl AngularJsAnalyzer {
                         None
                     };
 
+                    // HTML 属性名補完用に component 名でも引けるよう登録
+                    if let Some(component) = component_name {
+                        self.index.components.add_component_binding(
+                            uri,
+                            component,
+                            binding_name,
+                            binding_type.as_deref().unwrap_or(""),
+                        );
+                    }
+
                     // ControllerName.bindingName として登録
                     let full_name = format!("{}.{}", controller_name, binding_name);
-                    let docs = binding_type.map(|t| format!("Component binding: {}", t));
-
                     let span = self.span_of(key);
 
                     let mut builder = SymbolBuilder::new(full_name, SymbolKind::ComponentBinding, uri.clone())
                         .definition_span(span)
                         .name_span(span);
 
-                    if let Some(docs_str) = docs {
-                        builder = builder.docs(docs_str);
+                    if let Some(t) = binding_type {
+                        builder = builder.docs(format!("Component binding: {}", t)).binding_type(t);
                     }
 
                     self.index.definitions.add_definition(builder.build());
@@ -1286,6 +1508,45 @@ impl AngularJsAnalyzer {
             }
         }
     }
+
+    /// `templateUrl: '...'` のプロパティから、存在しないテンプレートファイルの診断用に
+    /// パスと位置を記録する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// templateUrl: 'views/user.html'
+    /// templateUrl: 'views/user.html?v=' + version   // 先頭のリテラル部分で判定
+    /// templateUrl: baseDir + '/user.html'            // 完全に動的なので対象外
+    /// ```
+    pub(super) fn analyze_template_url_pair(&self, node: Node, source: &str, uri: &Url) {
+        let (Some(key), Some(value)) =
+            (node.child_by_field_name("key"), node.child_by_field_name("value"))
+        else {
+            return;
+        };
+        let key_text = self.node_text(key, source);
+        if key_text.trim_matches(|c| c == '"' || c == '\'') != "templateUrl" {
+            return;
+        }
+
+        // 文字列連結は左端のオペランドが文字列リテラルの場合のみ扱う
+        let mut literal = value;
+        while literal.kind() == "binary_expression" {
+            match literal.child_by_field_name("left") {
+                Some(left) => literal = left,
+                None => return,
+            }
+        }
+        if literal.kind() != "string" {
+            return;
+        }
+
+        self.index.diagnostics.add_template_path(TemplatePathUsage {
+            uri: uri.clone(),
+            path: self.extract_string_value(literal, source),
+            span: self.span_of(literal),
+        });
+    }
 }
 
 /// route / state の config オブジェクト解析時に variant ごとの差分を表現する。
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::model::Span;
+use crate::model::{ServiceAlias, Span};
 
 /// ローカル変数/関数の定義位置
 #[derive(Clone)]
@@ -92,6 +92,8 @@ pub(super) struct AnalyzerContext {
     pub(super) defined_root_scope_properties: HashMap<String, bool>,
     /// 現在のモジュール名
     pub(super) current_module: Option<String>,
+    /// `$injector.get('X')` を受けた変数 (`Index` にも同じものを登録する)
+    pub(super) service_aliases: Vec<ServiceAlias>,
 }
 
 impl AnalyzerContext {
@@ -105,6 +107,7 @@ impl AnalyzerContext {
             defined_scope_properties: HashMap::new(),
             defined_root_scope_properties: HashMap::new(),
             current_module: None,
+            service_aliases: Vec::new(),
         }
     }
 
@@ -139,6 +142,17 @@ impl AnalyzerContext {
         None
     }
 
+    /// 指定位置で変数 `name` が `$injector.get` で受けたサービス名を解決する
+    ///
+    /// 範囲が重なる場合は最も内側の別名を優先する。
+    pub(super) fn resolve_service_alias(&self, name: &str, line: u32) -> Option<&str> {
+        self.service_aliases
+            .iter()
+            .filter(|a| a.name == name && line >= a.start_line && line <= a.end_line)
+            .min_by_key(|a| a.end_line - a.start_line)
+            .map(|a| a.service.as_str())
+    }
+
     /// 指定位置でサービスがDIされているかどうかをチェック
     pub(super) fn is_injected_at(&self, service_name: &str, line: u32) -> bool {
         // 1. di_scopes から現在位置のスコープを探す（内側から外側へ）
@@ -1,11 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use tower_lsp::lsp_types::Url;
 use tree_sitter::Node;
 
 use super::context::{AnalyzerContext, DiInfo};
-use super::AngularJsAnalyzer;
-use crate::model::{ControllerScope, DiArityIssue, SymbolReference};
+use super::{param_identifier, AngularJsAnalyzer};
+use crate::model::{
+    ControllerScope, DiArityIssue, DiOrderIssue, InjectedParam, InjectedService, InjectionUsage,
+    InsertPoint, ServiceAlias, Span, SymbolReference,
+};
+use crate::util::offset_to_position;
 
 impl AngularJsAnalyzer {
     /// ES6 classノードからconstructorメソッドを取得する
@@ -97,7 +101,8 @@ impl AngularJsAnalyzer {
         }
     }
 
-    /// `extract_di_info` と DI 配列の arity 不一致チェックをセットで実行する。
+    /// `extract_di_info` と DI 配列の arity 不一致チェック・注入サービスの使用状況の
+    /// 記録をセットで実行する。
     ///
     /// すべての DI 値を解析するサイト (controller / service / factory / run / config / state など)
     /// はこちらを経由することで、警告呼び出しの追加忘れを防ぐ。
@@ -108,6 +113,7 @@ impl AngularJsAnalyzer {
         uri: &Url,
     ) -> DiInfo {
         self.check_di_arity_mismatch(node, source, uri);
+        self.collect_injection_usage(node, source, uri);
         self.extract_di_info(node, source)
     }
 
@@ -138,13 +144,19 @@ impl AngularJsAnalyzer {
 
     /// `$inject` 配列から依存サービスを抽出する
     ///
-    /// `$` で始まるAngular組み込みサービスはスキップ
+    /// 全要素を注入サービスとして診断用に記録し、
+    /// `$` で始まるAngular組み込みサービス以外を参照として登録する
     pub(super) fn extract_inject_dependencies(&self, node: Node, source: &str, uri: &Url) {
         if node.kind() == "array" {
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
                 if child.kind() == "string" {
                     let dep_name = self.extract_string_value(child, source);
+                    self.index.diagnostics.add_injected_service(InjectedService {
+                        uri: uri.clone(),
+                        name: dep_name.clone(),
+                        span: self.span_of(child),
+                    });
                     if !dep_name.starts_with('$') {
                         let reference = SymbolReference {
                             name: dep_name,
@@ -166,13 +178,19 @@ impl AngularJsAnalyzer {
     /// .controller('Ctrl', ['$scope', 'UserService', function(...) {}])
     /// ```
     ///
-    /// `$` で始まるAngular組み込みサービスはスキップ
+    /// 全要素を注入サービスとして診断用に記録し、
+    /// `$` で始まるAngular組み込みサービス以外を参照として登録する
     pub(super) fn extract_dependencies(&self, node: Node, source: &str, uri: &Url) {
         if node.kind() == "array" {
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
                 if child.kind() == "string" {
                     let dep_name = self.extract_string_value(child, source);
+                    self.index.diagnostics.add_injected_service(InjectedService {
+                        uri: uri.clone(),
+                        name: dep_name.clone(),
+                        span: self.span_of(child),
+                    });
                     if !dep_name.starts_with('$') {
                         let reference = SymbolReference {
                             name: dep_name,
@@ -243,6 +261,107 @@ impl AngularJsAnalyzer {
         false
     }
 
+    /// `$injector.get('X')` による動的注入を解析する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// var svc = $injector.get('UserService');   // svc を UserService の別名として記録
+    /// svc = $injector.get('UserService');
+    /// $injector.get('UserService').load();      // チェーンは analyze_member_access 側で解決
+    /// ```
+    ///
+    /// サービス名の文字列は DI 配列の要素と同様に注入サービス・参照として登録する。
+    pub(super) fn analyze_injector_get(
+        &self,
+        node: Node,
+        source: &str,
+        uri: &Url,
+        ctx: &mut AnalyzerContext,
+    ) {
+        let Some((service, string_node)) = self.injector_get_target(node, source, ctx) else {
+            return;
+        };
+
+        self.index.diagnostics.add_injected_service(InjectedService {
+            uri: uri.clone(),
+            name: service.clone(),
+            span: self.span_of(string_node),
+        });
+        if !service.starts_with('$') {
+            self.index.definitions.add_reference(SymbolReference {
+                name: service.clone(),
+                uri: uri.clone(),
+                span: self.span_of(string_node),
+            });
+        }
+
+        let Some(name) = self.injector_get_assignee(node, source) else {
+            return;
+        };
+        let (start_line, end_line) = enclosing_function_body_lines(node);
+        let alias = ServiceAlias {
+            name,
+            service,
+            uri: uri.clone(),
+            start_line,
+            end_line,
+        };
+        self.index.controllers.add_service_alias(alias.clone());
+        ctx.service_aliases.push(alias);
+    }
+
+    /// `$injector.get('X')` 呼び出しなら、サービス名とその文字列ノードを返す
+    ///
+    /// レシーバは `$injector` そのものか、DI で `$injector` を受けた引数
+    /// (`['$injector', function(inj) { inj.get('X'); }]`) を認める。
+    pub(super) fn injector_get_target<'a>(
+        &self,
+        node: Node<'a>,
+        source: &str,
+        ctx: &AnalyzerContext,
+    ) -> Option<(String, Node<'a>)> {
+        if node.kind() != "call_expression" {
+            return None;
+        }
+        let callee = node.child_by_field_name("function")?;
+        if callee.kind() != "member_expression" {
+            return None;
+        }
+        let object = callee.child_by_field_name("object")?;
+        let property = callee.child_by_field_name("property")?;
+        if object.kind() != "identifier" || self.node_text(property, source) != "get" {
+            return None;
+        }
+
+        let receiver = self.node_text(object, source);
+        let line = node.start_position().row as u32;
+        if receiver != "$injector" && ctx.resolve_di_param(&receiver, line) != Some("$injector") {
+            return None;
+        }
+
+        let first_arg = node.child_by_field_name("arguments")?.named_child(0)?;
+        if first_arg.kind() != "string" {
+            return None;
+        }
+        Some((self.extract_string_value(first_arg, source), first_arg))
+    }
+
+    /// `$injector.get(...)` の戻り値を受ける変数名を返す
+    /// (`var svc = ...` / `svc = ...` のみ。プロパティへの代入は対象外)
+    fn injector_get_assignee(&self, node: Node, source: &str) -> Option<String> {
+        let parent = node.parent()?;
+        let target = match parent.kind() {
+            "variable_declarator" if parent.child_by_field_name("value") == Some(node) => {
+                parent.child_by_field_name("name")?
+            }
+            "assignment_expression" if parent.child_by_field_name("right") == Some(node) => {
+                parent.child_by_field_name("left")?
+            }
+            _ => return None,
+        };
+        (target.kind() == "identifier").then(|| self.node_text(target, source))
+    }
+
     /// 関数パラメータに $scope が含まれているかチェックする
     ///
     /// 直接関数パターン用:
@@ -262,12 +381,10 @@ impl AngularJsAnalyzer {
         if let Some(func) = func_node {
             if let Some(params) = func.child_by_field_name("parameters") {
                 let mut cursor = params.walk();
-                for child in params.children(&mut cursor) {
-                    if child.kind() == "identifier" {
-                        let param_name = self.node_text(child, source);
-                        if param_name == "$scope" {
-                            return true;
-                        }
+                for child in params.children(&mut cursor).filter_map(param_identifier) {
+                    let param_name = self.node_text(child, source);
+                    if param_name == "$scope" {
+                        return true;
                     }
                 }
             }
@@ -294,12 +411,10 @@ impl AngularJsAnalyzer {
         if let Some(func) = func_node {
             if let Some(params) = func.child_by_field_name("parameters") {
                 let mut cursor = params.walk();
-                for child in params.children(&mut cursor) {
-                    if child.kind() == "identifier" {
-                        let param_name = self.node_text(child, source);
-                        if param_name == "$rootScope" {
-                            return true;
-                        }
+                for child in params.children(&mut cursor).filter_map(param_identifier) {
+                    let param_name = self.node_text(child, source);
+                    if param_name == "$rootScope" {
+                        return true;
                     }
                 }
             }
@@ -374,9 +489,11 @@ impl AngularJsAnalyzer {
 
     /// DI 配列の要素数と関数の引数数が一致しているかをチェックし、
     /// 不一致なら `DiArityIssue` として登録する。
+    /// 要素数が一致している場合は位置ずれ (`DiOrderIssue`) もチェックする。
     ///
     /// チェック対象は **DI 配列** (`['$scope', function(...) {}]`) のみ。
     /// 純粋な関数 / class 単独 (DI 配列なし) や、識別子経由の渡し方は対象外。
+    /// `$inject` パターンは `check_inject_params` で同じチェックを行う。
     ///
     /// 認識する不一致パターン:
     /// ```javascript
@@ -386,6 +503,8 @@ impl AngularJsAnalyzer {
     /// .controller('Ctrl', ['$scope', function($scope, $timeout) {}])
     /// // class constructor の場合
     /// .controller('Ctrl', ['s1', class { constructor(s1, s2) {} }])
+    /// // 位置ずれ (要素数は一致) → 警告
+    /// .controller('Ctrl', ['$scope', 'UserService', function(UserService, $scope) {}])
     /// ```
     ///
     /// rest / default / 分割代入などの複雑な引数パターンが含まれる場合は
@@ -397,54 +516,411 @@ impl AngularJsAnalyzer {
             return;
         }
 
-        // 配列内を走査して文字列要素数と関数 / class ノードを収集
-        let mut string_count: usize = 0;
+        // 配列末尾が識別子のケース (関数を別変数で受け渡し) は arity を
+        // 静的に確定できないので対象外
+        let (dep_names, Some(func)) = self.split_di_array(node, source) else {
+            return;
+        };
+
+        self.check_di_params(&dep_names, func, source, uri);
+    }
+
+    /// DI 配列を文字列要素と関数 / class ノードに分ける
+    fn split_di_array<'a>(&self, node: Node<'a>, source: &str) -> (Vec<String>, Option<Node<'a>>) {
+        let mut dep_names = Vec::new();
         let mut function_node: Option<Node> = None;
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
             match child.kind() {
-                "string" => string_count += 1,
+                "string" => dep_names.push(self.extract_string_value(child, source)),
                 "function_expression" | "arrow_function" | "class" => {
                     function_node = Some(child);
                 }
-                // 配列末尾が識別子のケース (関数を別変数で受け渡し) は arity を
-                // 静的に確定できないので対象外
                 _ => {}
             }
         }
+        (dep_names, function_node)
+    }
+
+    /// DI 値 (配列 / 関数 / class) の引数と本体での識別子の使用状況を記録する
+    ///
+    /// 未使用・注入漏れの判定は `DiagnosticsHandler` が診断時に行う。
+    /// 識別子経由の渡し方は `$inject` 側 (`check_inject_params`) で扱う。
+    fn collect_injection_usage(&self, node: Node, source: &str, uri: &Url) {
+        match node.kind() {
+            "array" => {
+                if let (dep_names, Some(func)) = self.split_di_array(node, source) {
+                    self.record_injection_usage(Some((&dep_names, node)), func, source, uri);
+                }
+            }
+            "function_expression" | "arrow_function" | "function_declaration" | "class"
+            | "class_declaration" => self.record_injection_usage(None, node, source, uri),
+            _ => {}
+        }
+    }
 
-        let Some(func) = function_node else {
+    /// 注入名リストと関数の引数を対応付け、本体に現れる識別子とあわせて記録する
+    ///
+    /// `deps` は注入名リストとその配列ノード (DI 配列 / `$inject`)。`None` なら暗黙 DI
+    /// (引数名がそのままサービス名)。引数に rest / default / 分割代入を含む関数は対象外。
+    /// TypeScript のアクセス修飾子付き引数 (`private $http`) は class のプロパティとして
+    /// 使われるので記録しない。本体で `arguments` を使う関数は引数を名前で参照しないので
+    /// 記録しない。
+    fn record_injection_usage(
+        &self,
+        deps: Option<(&[String], Node)>,
+        func: Node,
+        source: &str,
+        uri: &Url,
+    ) {
+        let Some(params) = self.simple_function_params(func, source) else {
             return;
         };
-
-        // rest / default / 分割代入があれば arity を確定できないので諦める
-        let Some(param_count) = self.count_simple_function_params(func, source) else {
+        let Some(body) = func.child_by_field_name("body") else {
             return;
         };
 
-        if param_count == string_count {
+        let mut used_identifiers = HashMap::new();
+        let mut declared_identifiers = HashSet::new();
+        self.collect_body_identifiers(body, source, &mut used_identifiers, &mut declared_identifiers);
+        if used_identifiers.contains_key("arguments") {
             return;
         }
+        self.collect_enclosing_declarations(func, source, &mut declared_identifiers);
 
-        // 警告位置: function は関数ノード自体、class は constructor (なければ class 全体)
-        let target = if func.kind() == "class" {
-            self.get_constructor_from_class(func, source).unwrap_or(func)
-        } else {
-            func
-        };
+        // 注入名と引数の数が合わなければ、末尾に足しても対応がずれるので挿入位置を出さない
+        let aligned = deps.is_none_or(|(dep_names, _)| dep_names.len() == params.len());
+        let dependency_insert = deps
+            .filter(|_| aligned)
+            .map(|(_, array)| self.dependency_insert_point(array, source));
+        let param_insert = self.param_insert_point(func, source).filter(|_| aligned);
+
+        let params = params
+            .into_iter()
+            .enumerate()
+            .filter(|(_, param)| !is_parameter_property(*param))
+            .filter_map(|(i, param)| {
+                let name = self.node_text(param, source);
+                let service = match deps {
+                    Some((dep_names, _)) => dep_names.get(i)?.clone(),
+                    None => name.clone(),
+                };
+                Some(InjectedParam {
+                    service,
+                    name,
+                    span: self.span_of(param),
+                })
+            })
+            .collect();
 
-        self.index.diagnostics.add_di_arity_issue(DiArityIssue {
+        self.index.diagnostics.add_injection_usage(InjectionUsage {
             uri: uri.clone(),
-            di_count: string_count,
-            param_count,
-            span: self.span_of(target),
+            params,
+            used_identifiers,
+            declared_identifiers,
+            dependency_insert,
+            param_insert,
+            span: self.span_of(func),
         });
     }
 
-    /// 関数 / arrow / class constructor の引数を全て単純識別子として数える。
+    /// 関数本体の識別子を、使用 (最初の出現位置) と宣言に分けて集める
+    ///
+    /// オブジェクトの省略記法 `{ UserService }` は使用として扱う
+    fn collect_body_identifiers(
+        &self,
+        node: Node,
+        source: &str,
+        used: &mut HashMap<String, Span>,
+        declared: &mut HashSet<String>,
+    ) {
+        match node.kind() {
+            "identifier" if is_declaration_name(node) => {
+                declared.insert(self.node_text(node, source));
+            }
+            "identifier" | "shorthand_property_identifier" => {
+                used.entry(self.node_text(node, source))
+                    .or_insert_with(|| self.span_of(node));
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_body_identifiers(child, source, used, declared);
+        }
+    }
+
+    /// `func` を囲むスコープで宣言されている名前を集める
+    ///
+    /// 外側の関数の名前・引数と、外側のブロック / ファイル直下の変数・関数・class 宣言、
+    /// `import` で束縛した名前 (`require` は変数宣言として拾う)。ブロック内にネストした
+    /// `var` の巻き上げまでは追わない
+    fn collect_enclosing_declarations(&self, func: Node, source: &str, declared: &mut HashSet<String>) {
+        let mut current = func;
+        while let Some(parent) = current.parent() {
+            match parent.kind() {
+                "program" | "statement_block" | "class_body" => {
+                    let mut cursor = parent.walk();
+                    for statement in parent.named_children(&mut cursor) {
+                        self.collect_statement_declarations(statement, source, declared);
+                    }
+                }
+                "function_expression" | "function_declaration" | "generator_function"
+                | "generator_function_declaration" | "arrow_function" | "method_definition" => {
+                    if let Some(name) = parent.child_by_field_name("name") {
+                        declared.insert(self.node_text(name, source));
+                    }
+                    let params = parent
+                        .child_by_field_name("parameters")
+                        .or_else(|| parent.child_by_field_name("parameter"));
+                    if let Some(params) = params {
+                        self.collect_pattern_names(params, source, declared);
+                    }
+                }
+                _ => {}
+            }
+            current = parent;
+        }
+    }
+
+    /// 文が宣言する名前 (変数・関数・class・import) を集める
+    fn collect_statement_declarations(&self, statement: Node, source: &str, declared: &mut HashSet<String>) {
+        match statement.kind() {
+            "lexical_declaration" | "variable_declaration" => {
+                let mut cursor = statement.walk();
+                for declarator in statement.named_children(&mut cursor) {
+                    if let Some(name) = declarator.child_by_field_name("name") {
+                        self.collect_pattern_names(name, source, declared);
+                    }
+                }
+            }
+            "function_declaration" | "generator_function_declaration" | "class_declaration" => {
+                if let Some(name) = statement.child_by_field_name("name") {
+                    declared.insert(self.node_text(name, source));
+                }
+            }
+            "import_statement" => {
+                let mut cursor = statement.walk();
+                for clause in statement.named_children(&mut cursor) {
+                    if clause.kind() == "import_clause" {
+                        self.collect_import_names(clause, source, declared);
+                    }
+                }
+            }
+            "export_statement" => {
+                if let Some(declaration) = statement.child_by_field_name("declaration") {
+                    self.collect_statement_declarations(declaration, source, declared);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `import` 句で束縛される名前 (default / `* as ns` / `{ a, b as c }`)
+    fn collect_import_names(&self, node: Node, source: &str, declared: &mut HashSet<String>) {
+        match node.kind() {
+            "identifier" => {
+                declared.insert(self.node_text(node, source));
+            }
+            "import_specifier" => {
+                if let Some(local) = node
+                    .child_by_field_name("alias")
+                    .or_else(|| node.child_by_field_name("name"))
+                {
+                    declared.insert(self.node_text(local, source));
+                }
+            }
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    self.collect_import_names(child, source, declared);
+                }
+            }
+        }
+    }
+
+    /// 引数リストや分割代入のパターンが束縛する名前 (既定値の式や型注釈は除く)
+    fn collect_pattern_names(&self, node: Node, source: &str, declared: &mut HashSet<String>) {
+        match node.kind() {
+            "identifier" | "shorthand_property_identifier_pattern" => {
+                declared.insert(self.node_text(node, source));
+            }
+            "assignment_pattern" | "object_assignment_pattern" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.collect_pattern_names(left, source, declared);
+                }
+            }
+            "pair_pattern" => {
+                if let Some(value) = node.child_by_field_name("value") {
+                    self.collect_pattern_names(value, source, declared);
+                }
+            }
+            "required_parameter" | "optional_parameter" => {
+                if let Some(pattern) = node.child_by_field_name("pattern") {
+                    self.collect_pattern_names(pattern, source, declared);
+                }
+            }
+            "type_annotation" => {}
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    self.collect_pattern_names(child, source, declared);
+                }
+            }
+        }
+    }
+
+    /// DI 配列 / `$inject` の末尾に注入名を足す位置
+    ///
+    /// 最後の文字列要素の後ろ。文字列要素がなければ `[` の直後 (関数が続くなら後ろに `, `)
+    fn dependency_insert_point(&self, array: Node, source: &str) -> InsertPoint {
+        let mut cursor = array.walk();
+        let children: Vec<Node> = array.named_children(&mut cursor).collect();
+        match children.iter().rev().find(|child| child.kind() == "string") {
+            Some(last) => self.insert_point_at(source, last.end_byte(), true, false),
+            None => self.insert_point_at(
+                source,
+                array.start_byte() + 1,
+                false,
+                children.iter().any(|child| child.kind() != "comment"),
+            ),
+        }
+    }
+
+    /// 関数 (class は constructor) の引数リストの末尾に引数を足す位置
+    ///
+    /// 括弧のない arrow function の引数 (`x => ...`) や constructor のない class は `None`
+    fn param_insert_point(&self, func: Node, source: &str) -> Option<InsertPoint> {
+        let func = match func.kind() {
+            "class" | "class_declaration" => self.get_constructor_from_class(func, source)?,
+            _ => func,
+        };
+        let params = func.child_by_field_name("parameters")?;
+        let mut cursor = params.walk();
+        let last = params
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() != "comment")
+            .last();
+        Some(match last {
+            Some(last) => self.insert_point_at(source, last.end_byte(), true, false),
+            None => self.insert_point_at(source, params.start_byte() + 1, false, false),
+        })
+    }
+
+    /// バイトオフセット `offset` への挿入位置
+    ///
+    /// quick fix の TextEdit にそのまま使うので、列は UTF-16 単位に変換する
+    fn insert_point_at(
+        &self,
+        source: &str,
+        offset: usize,
+        leading_comma: bool,
+        trailing_comma: bool,
+    ) -> InsertPoint {
+        let position = offset_to_position(source, offset);
+        InsertPoint {
+            line: self.offset_line(position.line),
+            col: position.character,
+            leading_comma,
+            trailing_comma,
+        }
+    }
+
+    /// `$inject` 配列と、同じファイル内の関数宣言 / class 宣言の引数を照合する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// MyController.$inject = ['$scope', 'UserService'];
+    /// function MyController($scope) {}   // → 要素数不一致
+    /// ```
+    ///
+    /// 文字列以外の要素 (変数・スプレッドなど) を含む配列や、宣言が見つからない
+    /// 場合は対象外。
+    fn check_inject_params(&self, array: Node, func_name: &str, source: &str, uri: &Url) {
+        if array.kind() != "array" {
+            return;
+        }
+
+        let mut dep_names = Vec::new();
+        let mut cursor = array.walk();
+        for child in array.named_children(&mut cursor) {
+            match child.kind() {
+                "string" => dep_names.push(self.extract_string_value(child, source)),
+                "comment" => {}
+                _ => return,
+            }
+        }
+
+        let root = {
+            let mut current = array;
+            while let Some(parent) = current.parent() {
+                current = parent;
+            }
+            current
+        };
+        let func = self
+            .find_function_declaration(root, source, func_name)
+            .or_else(|| self.find_class_declaration(root, source, func_name));
+        if let Some(func) = func {
+            self.check_di_params(&dep_names, func, source, uri);
+            self.record_injection_usage(Some((&dep_names, array)), func, source, uri);
+        }
+    }
+
+    /// 注入名リストと関数の引数を照合し、要素数不一致 / 位置ずれを登録する
+    ///
+    /// 位置ずれは「N 番目の引数名が別の位置の注入名と一致する (順序の入れ替え)」か
+    /// 「両方が `$` 始まりで名前が異なる」場合のみ報告する。ミニファイ対策で
+    /// 引数名を注入名と変えること自体は正当なので、それ以外は警告しない。
+    fn check_di_params(&self, dep_names: &[String], func: Node, source: &str, uri: &Url) {
+        // rest / default / 分割代入があれば arity を確定できないので諦める
+        let Some(params) = self.simple_function_params(func, source) else {
+            return;
+        };
+
+        if params.len() != dep_names.len() {
+            // 警告位置: function は関数ノード自体、class は constructor (なければ class 全体)
+            let target = if matches!(func.kind(), "class" | "class_declaration") {
+                self.get_constructor_from_class(func, source).unwrap_or(func)
+            } else {
+                func
+            };
+
+            self.index.diagnostics.add_di_arity_issue(DiArityIssue {
+                uri: uri.clone(),
+                di_count: dep_names.len(),
+                param_count: params.len(),
+                span: self.span_of(target),
+            });
+            return;
+        }
+
+        for (position, (dep_name, param)) in dep_names.iter().zip(&params).enumerate() {
+            let param_name = self.node_text(*param, source);
+            if param_name == *dep_name {
+                continue;
+            }
+            let swapped = dep_names
+                .iter()
+                .enumerate()
+                .any(|(i, name)| i != position && *name == param_name);
+            let both_dollar = dep_name.starts_with('$') && param_name.starts_with('$');
+            if swapped || both_dollar {
+                self.index.diagnostics.add_di_order_issue(DiOrderIssue {
+                    uri: uri.clone(),
+                    position,
+                    service_name: dep_name.clone(),
+                    param_name,
+                    span: self.span_of(*param),
+                });
+            }
+        }
+    }
+
+    /// 関数 / arrow / class constructor の引数ノードを全て単純識別子として返す。
     /// rest (`...rest`) / default (`x = 1`) / 分割代入 (`{a}` / `[a]`) などが
     /// 混じる場合は `None` を返す (静的に正確な arity を確定できないため)。
-    fn count_simple_function_params(&self, node: Node, source: &str) -> Option<usize> {
+    fn simple_function_params<'a>(&self, node: Node<'a>, source: &str) -> Option<Vec<Node<'a>>> {
         let func_node = match node.kind() {
             "function_expression" | "arrow_function" | "function_declaration"
             | "method_definition" => Some(node),
@@ -455,25 +931,30 @@ impl AngularJsAnalyzer {
         // arrow function 単一引数 (`x => ...`) は `parameter` フィールドを持つ
         if let Some(single) = func_node.child_by_field_name("parameter") {
             return if single.kind() == "identifier" {
-                Some(1)
+                Some(vec![single])
             } else {
                 None
             };
         }
 
         let params = func_node.child_by_field_name("parameters")?;
-        let mut count = 0;
+        let mut nodes = Vec::new();
         let mut cursor = params.walk();
         for child in params.children(&mut cursor) {
             match child.kind() {
-                "identifier" => count += 1,
+                "identifier" => nodes.push(child),
+                // TypeScript の型注釈・アクセス修飾子付き引数 (`private $http: ng.IHttpService`)。
+                // デフォルト値付きは arity を確定できないので諦める
+                "required_parameter" if child.child_by_field_name("value").is_none() => {
+                    nodes.push(param_identifier(child)?)
+                }
                 // 構文区切り・コメントは無視
                 "(" | ")" | "," | "comment" => {}
                 // rest_pattern / assignment_pattern / object_pattern / array_pattern などは諦める
                 _ => return None,
             }
         }
-        Some(count)
+        Some(nodes)
     }
 
     /// 関数 (式 / 宣言 / class constructor) のパラメータ識別子名を順番通りに返す
@@ -489,10 +970,8 @@ impl AngularJsAnalyzer {
         if let Some(func) = func_node {
             if let Some(params) = func.child_by_field_name("parameters") {
                 let mut cursor = params.walk();
-                for child in params.children(&mut cursor) {
-                    if child.kind() == "identifier" {
-                        names.push(self.node_text(child, source).to_string());
-                    }
+                for child in params.children(&mut cursor).filter_map(param_identifier) {
+                    names.push(self.node_text(child, source).to_string());
                 }
             }
         }
@@ -520,13 +999,11 @@ impl AngularJsAnalyzer {
         if let Some(func) = func_node {
             if let Some(params) = func.child_by_field_name("parameters") {
                 let mut cursor = params.walk();
-                for child in params.children(&mut cursor) {
-                    if child.kind() == "identifier" {
-                        let param_name = self.node_text(child, source);
-                        // $で始まらないパラメータをサービスとして収集
-                        if !param_name.starts_with('$') {
-                            services.push(param_name);
-                        }
+                for child in params.children(&mut cursor).filter_map(param_identifier) {
+                    let param_name = self.node_text(child, source);
+                    // $で始まらないパラメータをサービスとして収集
+                    if !param_name.starts_with('$') {
+                        services.push(param_name);
                     }
                 }
             }
@@ -670,6 +1147,7 @@ impl AngularJsAnalyzer {
                                     if prop_name == "$inject" {
                                         let func_name = self.node_text(object, source);
                                         if let Some(right) = expr.child_by_field_name("right") {
+                                            self.check_inject_params(right, &func_name, source, uri);
                                             let services = self.collect_injected_services(right, source);
                                             let has_scope = self.has_scope_in_di_array(right, source);
                                             let has_root_scope = self.has_root_scope_in_di_array(right, source);
@@ -681,8 +1159,8 @@ impl AngularJsAnalyzer {
                                                         self.index.controllers.add_controller_scope(ControllerScope {
                                                             name: func_name.clone(),
                                                             uri: uri.clone(),
-                                                            start_line: *start_line,
-                                                            end_line: *end_line,
+                                                            start_line: self.offset_line(*start_line),
+                                                            end_line: self.offset_line(*end_line),
                                                             injected_services: services.clone(),
                                                         });
                                                     }
@@ -709,3 +1187,56 @@ impl AngularJsAnalyzer {
         }
     }
 }
+
+/// ノードを含む最も内側の関数本体の行範囲を返す (関数外ならファイル全体)
+fn enclosing_function_body_lines(node: Node) -> (u32, u32) {
+    let mut current = node.parent();
+    while let Some(n) = current {
+        let body = n.child_by_field_name("body").filter(|_| {
+            matches!(
+                n.kind(),
+                "function_expression"
+                    | "function_declaration"
+                    | "arrow_function"
+                    | "method_definition"
+                    | "generator_function"
+                    | "generator_function_declaration"
+            )
+        });
+        if let Some(body) = body {
+            return (body.start_position().row as u32, body.end_position().row as u32);
+        }
+        current = n.parent();
+    }
+    (0, u32::MAX)
+}
+
+/// 識別子が変数・関数・class の名前や引数として宣言されている位置か
+fn is_declaration_name(node: Node) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    let field = match parent.kind() {
+        "formal_parameters" => return true,
+        "variable_declarator" | "function_declaration" | "function_expression"
+        | "generator_function_declaration" | "class_declaration" | "class" => "name",
+        "required_parameter" | "optional_parameter" => "pattern",
+        "arrow_function" | "catch_clause" => "parameter",
+        _ => return false,
+    };
+    parent.child_by_field_name(field) == Some(node)
+}
+
+/// TypeScript のアクセス修飾子 / `readonly` 付きの constructor 引数 (parameter property) か
+fn is_parameter_property(param: Node) -> bool {
+    let Some(parent) = param.parent().filter(|p| p.kind() == "required_parameter") else {
+        return false;
+    };
+    let mut cursor = parent.walk();
+    parent.children(&mut cursor).any(|child| {
+        matches!(
+            child.kind(),
+            "accessibility_modifier" | "override_modifier" | "readonly"
+        )
+    })
+}
@@ -0,0 +1,282 @@
+use tower_lsp::lsp_types::Url;
+use tree_sitter::Node;
+
+use super::{find_returned_node, AngularJsAnalyzer};
+use crate::model::{
+    BindingSource, ComponentTemplateUrl, SymbolBuilder, SymbolKind, SymbolReference,
+    TemplateBinding,
+};
+
+impl AngularJsAnalyzer {
+    /// ディレクティブのファクトリー関数が返す定義オブジェクト (DDO) を解析する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// .directive('userCard', function() {
+    ///     return {
+    ///         restrict: 'E',
+    ///         scope: { userName: '=', onSave: '&' },
+    ///         templateUrl: 'views/user-card.html',
+    ///         controller: function() { ... },
+    ///         controllerAs: 'vm',
+    ///         bindToController: { foo: '<' },
+    ///         require: { parent: '^parentDirective' }
+    ///     };
+    /// })
+    /// ```
+    ///
+    /// - `scope` のバインディングを `userCard.$scope.userName`
+    ///   (`SymbolKind::DirectiveBinding`) として登録
+    /// - `bindToController` のバインディング (または `bindToController: true` 時の
+    ///   `scope` バインディング) を controller 上のプロパティ `<controller>.foo` として登録
+    /// - `require` の各ディレクティブ名を参照として登録
+    /// - `templateUrl` のテンプレートをディレクティブ名にバインドし、テンプレート内の
+    ///   `userName` が isolate scope のバインディングへ解決されるようにする
+    ///
+    /// 戻り値は `restrict` の値 (文字列リテラルで指定されている場合のみ)
+    pub(super) fn extract_directive_definition(
+        &self,
+        factory: Node,
+        source: &str,
+        uri: &Url,
+        directive_name: &str,
+    ) -> Option<String> {
+        let ddo = self.find_directive_definition_object(factory, source)?;
+
+        let mut template_url: Option<(String, u32, u32)> = None;
+        let mut inline_template: Option<Node> = None;
+        let mut controller_as: Option<String> = None;
+        let mut controller_name: Option<String> = None;
+        let mut controller_string_value_node: Option<Node> = None;
+        let mut controller_function_node: Option<Node> = None;
+        let mut scope_bindings: Option<Node> = None;
+        let mut controller_bindings: Option<Node> = None;
+        let mut bind_to_controller = false;
+        let mut restrict: Option<String> = None;
+
+        let mut cursor = ddo.walk();
+        for child in ddo.children(&mut cursor) {
+            if child.kind() != "pair" {
+                continue;
+            }
+            let (Some(key), Some(value)) =
+                (child.child_by_field_name("key"), child.child_by_field_name("value"))
+            else {
+                continue;
+            };
+            let key_text = self.node_text(key, source);
+            match key_text.trim_matches(|c| c == '"' || c == '\'') {
+                "scope" if value.kind() == "object" => {
+                    self.extract_directive_scope_bindings(value, source, uri, directive_name);
+                    scope_bindings = Some(value);
+                }
+                "bindToController" => match value.kind() {
+                    "object" => controller_bindings = Some(value),
+                    "true" => bind_to_controller = true,
+                    _ => {}
+                },
+                "require" => self.extract_directive_require(value, source, uri),
+                "restrict" if value.kind() == "string" => {
+                    restrict = Some(self.extract_string_value(value, source));
+                }
+                "templateUrl" if value.kind() == "string" => {
+                    let start = value.start_position();
+                    template_url = Some((
+                        self.extract_string_value(value, source),
+                        self.offset_line(start.row as u32),
+                        start.column as u32,
+                    ));
+                }
+                "template" => inline_template = Some(value),
+                "controllerAs" if value.kind() == "string" => {
+                    controller_as = Some(self.extract_string_value(value, source));
+                }
+                "controller" => {
+                    self.classify_controller_value(
+                        value,
+                        source,
+                        &mut controller_name,
+                        &mut controller_string_value_node,
+                        &mut controller_function_node,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        // controller: 'UserCardCtrl' は既存コントローラーへの参照
+        if let (Some(name), Some(value_node)) = (controller_name.as_ref(), controller_string_value_node) {
+            self.index.definitions.add_reference(SymbolReference {
+                name: name.clone(),
+                uri: uri.clone(),
+                span: self.span_of(value_node),
+            });
+        }
+
+        // インライン/関数参照の controller は this.X を抽出する
+        // (無名関数ならディレクティブ名の下に登録)
+        let controller_name = controller_name.unwrap_or_else(|| directive_name.to_string());
+        if let Some(func_node) = controller_function_node {
+            self.extract_controller_methods(func_node, source, uri, &controller_name);
+        }
+
+        // bindToController のバインディングは controllerAs エイリアス上のプロパティになる
+        let controller_bindings =
+            controller_bindings.or(scope_bindings.filter(|_| bind_to_controller));
+        if let Some(bindings) = controller_bindings {
+            self.register_directive_bindings(bindings, source, uri, &controller_name);
+        }
+
+        // インラインテンプレートはディレクティブの scope と controllerAs で評価される
+        if let Some(template) = inline_template {
+            self.register_inline_template(
+                template,
+                source,
+                uri,
+                Some(directive_name.to_string()),
+                Some(controller_name.clone()),
+                controller_as.clone(),
+            );
+        }
+
+        let Some((template_path, line, col)) = template_url else {
+            return restrict;
+        };
+
+        // テンプレートの scope はディレクティブの isolate scope
+        self.index.templates.add_template_binding(TemplateBinding {
+            template_path: template_path.clone(),
+            controller_name: directive_name.to_string(),
+            source: BindingSource::Directive,
+            binding_uri: uri.clone(),
+            binding_line: line,
+        });
+
+        // controllerAs 指定時のみエイリアスを登録する
+        // (component と異なりディレクティブには既定の `$ctrl` がない)
+        if let Some(alias) = controller_as {
+            self.index.components.add_component_template_url(ComponentTemplateUrl {
+                uri: uri.clone(),
+                template_path,
+                line,
+                col,
+                controller_name: Some(controller_name),
+                controller_as: alias,
+            });
+        }
+
+        restrict
+    }
+
+    /// ファクトリー関数の `return { ... }` からディレクティブ定義オブジェクトを探す
+    ///
+    /// DI配列・関数式・アロー関数 (`() => ({ ... })`)・関数宣言への識別子参照に対応
+    fn find_directive_definition_object<'a>(&self, factory: Node<'a>, source: &str) -> Option<Node<'a>> {
+        let func_node = match factory.kind() {
+            "array" => {
+                let mut cursor = factory.walk();
+                let last = factory.named_children(&mut cursor).last()?;
+                return self.find_directive_definition_object(last, source);
+            }
+            "function_expression" | "arrow_function" | "function_declaration" => factory,
+            "identifier" => {
+                let name = self.node_text(factory, source);
+                let mut root = factory;
+                while let Some(parent) = root.parent() {
+                    root = parent;
+                }
+                self.find_function_declaration(root, source, &name)?
+            }
+            _ => return None,
+        };
+
+        let body = func_node.child_by_field_name("body")?;
+        match body.kind() {
+            "object" => Some(body),
+            "parenthesized_expression" => body.named_child(0).filter(|n| n.kind() == "object"),
+            _ => find_returned_node(body, |n| n.kind() == "object"),
+        }
+    }
+
+    /// `scope: { userName: '=', onSave: '&' }` のバインディングを登録する
+    fn extract_directive_scope_bindings(&self, scope_obj: Node, source: &str, uri: &Url, directive_name: &str) {
+        let prefix = format!("{}.$scope", directive_name);
+        self.register_directive_bindings(scope_obj, source, uri, &prefix);
+    }
+
+    /// `require` で指定された依存ディレクティブへの参照を登録する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// require: '^parentDirective'
+    /// require: ['^parentDirective', '?ngModel']
+    /// require: { parent: '^parentDirective' }
+    /// ```
+    fn extract_directive_require(&self, value: Node, source: &str, uri: &Url) {
+        match value.kind() {
+            "string" => {
+                let raw = self.extract_string_value(value, source);
+                let name = required_directive_name(&raw);
+                if name.is_empty() {
+                    return;
+                }
+                self.index.definitions.add_reference(SymbolReference {
+                    name: name.to_string(),
+                    uri: uri.clone(),
+                    span: self.span_of(value),
+                });
+            }
+            "array" => {
+                let mut cursor = value.walk();
+                for element in value.named_children(&mut cursor) {
+                    self.extract_directive_require(element, source, uri);
+                }
+            }
+            "object" => {
+                let mut cursor = value.walk();
+                for pair in value.named_children(&mut cursor).filter(|n| n.kind() == "pair") {
+                    if let Some(pair_value) = pair.child_by_field_name("value") {
+                        self.extract_directive_require(pair_value, source, uri);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// バインディング定義オブジェクトの各キーを `<prefix>.<key>` として登録する
+    fn register_directive_bindings(&self, bindings_obj: Node, source: &str, uri: &Url, prefix: &str) {
+        let mut cursor = bindings_obj.walk();
+        for child in bindings_obj.children(&mut cursor) {
+            if child.kind() != "pair" {
+                continue;
+            }
+            let Some(key) = child.child_by_field_name("key") else {
+                continue;
+            };
+            let key_text = self.node_text(key, source);
+            let binding_name = key_text.trim_matches(|c| c == '"' || c == '\'');
+            let binding_type = child
+                .child_by_field_name("value")
+                .filter(|v| v.kind() == "string")
+                .map(|v| self.extract_string_value(v, source));
+
+            let full_name = format!("{}.{}", prefix, binding_name);
+            let span = self.span_of(key);
+            let mut builder = SymbolBuilder::new(full_name, SymbolKind::DirectiveBinding, uri.clone())
+                .definition_span(span)
+                .name_span(span);
+            if let Some(t) = binding_type {
+                builder = builder.docs(format!("Directive binding: {}", t)).binding_type(t);
+            }
+            self.index.definitions.add_definition(builder.build());
+        }
+    }
+}
+
+/// `require` の値からディレクティブ名を取り出す
+///
+/// `'^parentDirective'` / `'?^^form'` のような接頭辞 (`?` / `^`) を除去する
+fn required_directive_name(value: &str) -> &str {
+    value.trim_start_matches(['?', '^'])
+}
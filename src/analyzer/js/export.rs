@@ -798,12 +798,19 @@ impl AngularJsAnalyzer {
 
         // bindings を抽出してシンボルとして登録
         if let (Some(bindings), Some(prefix)) = (bindings_node, effective_controller_name.as_deref()) {
-            self.extract_bindings_from_config(bindings, source, uri, prefix);
+            self.extract_bindings_from_config(bindings, source, uri, prefix, component_name);
         }
     }
 
     /// bindingsオブジェクトからバインディングを抽出してシンボルとして登録
-    fn extract_bindings_from_config(&self, bindings_node: Node, source: &str, uri: &Url, controller_name: &str) {
+    fn extract_bindings_from_config(
+        &self,
+        bindings_node: Node,
+        source: &str,
+        uri: &Url,
+        controller_name: &str,
+        component_name: Option<&str>,
+    ) {
         let mut cursor = bindings_node.walk();
         for child in bindings_node.children(&mut cursor) {
             if child.kind() == "pair" {
@@ -822,17 +829,25 @@ impl AngularJsAnalyzer {
                         None
                     };
 
-                    let full_name = format!("{}.{}", controller_name, binding_name);
-                    let docs = binding_type.map(|t| format!("Component binding: {}", t));
+                    // HTML 属性名補完用に component 名でも引けるよう登録
+                    if let Some(component) = component_name {
+                        self.index.components.add_component_binding(
+                            uri,
+                            component,
+                            binding_name,
+                            binding_type.as_deref().unwrap_or(""),
+                        );
+                    }
 
+                    let full_name = format!("{}.{}", controller_name, binding_name);
                     let span = self.span_of(key);
 
                     let mut builder = SymbolBuilder::new(full_name, SymbolKind::ComponentBinding, uri.clone())
                         .definition_span(span)
                         .name_span(span);
 
-                    if let Some(docs_str) = docs {
-                        builder = builder.docs(docs_str);
+                    if let Some(t) = binding_type {
+                        builder = builder.docs(format!("Component binding: {}", t)).binding_type(t);
                     }
 
                     self.index.definitions.add_definition(builder.build());
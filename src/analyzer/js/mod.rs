@@ -1,12 +1,19 @@
+mod annotation;
+pub mod builtin_service_methods;
 mod component;
 mod context;
 mod di;
+mod directive;
 mod export;
 mod module_chain;
 mod parser;
 mod reference;
 mod scope;
 mod service_method;
+pub mod services;
+
+pub use annotation::{DiAnnotation, DiAnnotationKind};
+pub use parser::JsParser;
 
 #[cfg(test)]
 mod tests;
@@ -17,16 +24,18 @@ use std::sync::Arc;
 use tower_lsp::lsp_types::Url;
 use tree_sitter::{Node, Tree};
 
+use crate::analyzer::incremental::SyntaxTreeCache;
 use crate::index::Index;
-use crate::model::Span;
+use crate::model::{AnalysisFailure, Span};
 use context::AnalyzerContext;
-use parser::JsParser;
 
 /// AngularJS 1.x のコードを解析し、シンボル定義と参照を抽出するアナライザー
 pub struct AngularJsAnalyzer {
     pub(crate) index: Arc<Index>,
     /// 行番号オフセット（HTML内のscriptタグ用）
     pub(crate) line_offset: AtomicU32,
+    /// 開いているドキュメントの前回 Tree（差分パース用）
+    tree_cache: Arc<SyntaxTreeCache>,
 }
 
 impl AngularJsAnalyzer {
@@ -34,14 +43,16 @@ impl AngularJsAnalyzer {
         Self {
             index,
             line_offset: AtomicU32::new(0),
+            tree_cache: Arc::new(SyntaxTreeCache::new()),
         }
     }
 
     /// ドキュメントを解析してシンボルをインデックスに追加する
     ///
-    /// 既存のドキュメント情報をクリアしてから解析を行う
-    pub fn analyze_document(&self, uri: &Url, source: &str) {
-        self.analyze_document_with_options(uri, source, true);
+    /// 既存のドキュメント情報をクリアしてから解析を行う。
+    /// パースに失敗した場合は `false` (インデックスは前回の解析結果のまま)
+    pub fn analyze_document(&self, uri: &Url, source: &str) -> bool {
+        self.analyze_document_with_options(uri, source, true)
     }
 
     /// ドキュメントを解析してシンボルをインデックスに追加する
@@ -50,9 +61,44 @@ impl AngularJsAnalyzer {
     /// * `uri` - ドキュメントのURI
     /// * `source` - ソースコード
     /// * `clear` - true: 既存情報をクリア, false: 追記モード（2パス目用）
-    pub fn analyze_document_with_options(&self, uri: &Url, source: &str, clear: bool) {
+    pub fn analyze_document_with_options(&self, uri: &Url, source: &str, clear: bool) -> bool {
+        self.analyze_internal(uri, source, clear, 0)
+    }
+
+    /// エディタで編集中のドキュメントを差分パースで解析する
+    ///
+    /// 前回の Tree を `Tree::edit` してから再パースするため、大きなファイルの
+    /// 小さな編集ではパースがほぼ一定時間で済む。解析結果・戻り値は `analyze_document` と同じ。
+    /// パースの失敗は診断用に `index.diagnostics` に記録し、次に成功したら消す
+    pub fn analyze_document_incremental(&self, uri: &Url, source: &str) -> bool {
         self.line_offset.store(0, Ordering::Relaxed);
-        self.analyze_internal(uri, source, clear);
+        let old_tree = self.tree_cache.edited_tree(uri, source);
+        let parsed = JsParser::for_uri(uri).parse_incremental(source, old_tree.as_ref());
+        self.analyze_parsed(uri, source, parsed)
+    }
+
+    /// 差分パースの結果を解析し、パースの成否を `index.diagnostics` に記録する
+    fn analyze_parsed(&self, uri: &Url, source: &str, parsed: Option<Tree>) -> bool {
+        let Some(tree) = parsed else {
+            self.index
+                .diagnostics
+                .set_analysis_failure(uri.clone(), AnalysisFailure::ParseFailed);
+            return false;
+        };
+        self.index.diagnostics.clear_analysis_failure(uri);
+        self.tree_cache.insert(uri, source, &tree);
+        self.analyze_tree(uri, source, &tree, true);
+        true
+    }
+
+    /// 差分パース用にキャッシュした Tree を破棄する（`did_close` 時）
+    pub fn forget_tree(&self, uri: &Url) {
+        self.tree_cache.remove(uri);
+    }
+
+    /// 直近の解析で使った Tree のキャッシュ（inlay hints など Tree を使うハンドラと共有）
+    pub fn tree_cache(&self) -> Arc<SyntaxTreeCache> {
+        Arc::clone(&self.tree_cache)
     }
 
     /// HTML内のscriptタグなど、埋め込みJSを解析する
@@ -61,30 +107,38 @@ impl AngularJsAnalyzer {
     /// * `uri` - ドキュメントのURI
     /// * `source` - ソースコード（scriptタグの中身）
     /// * `line_offset` - 行番号オフセット（scriptタグの開始行）
-    pub fn analyze_embedded_script(&self, uri: &Url, source: &str, line_offset: u32) {
+    pub fn analyze_embedded_script(&self, uri: &Url, source: &str, line_offset: u32) -> bool {
+        self.analyze_internal(uri, source, false, line_offset)
+    }
+
+    /// パースできて解析を行ったら `true`
+    ///
+    /// 解析中だけ `line_offset` を設定し、終わったら 0 に戻す
+    fn analyze_internal(&self, uri: &Url, source: &str, clear: bool, line_offset: u32) -> bool {
+        let Some(tree) = JsParser::for_uri(uri).parse(source) else {
+            return false;
+        };
         self.line_offset.store(line_offset, Ordering::Relaxed);
-        self.analyze_internal(uri, source, false);
+        self.analyze_tree(uri, source, &tree, clear);
         self.line_offset.store(0, Ordering::Relaxed);
+        true
     }
 
-    fn analyze_internal(&self, uri: &Url, source: &str, clear: bool) {
-        let mut parser = JsParser::new();
-
-        if let Some(tree) = parser.parse(source) {
-            if clear {
-                self.index.clear_document(uri);
-            }
-            let mut ctx = AnalyzerContext::new();
-            // 事前収集フェーズ:
-            // 1. $inject パターン用の関数宣言とclass宣言を収集
-            self.collect_function_declarations_for_inject(tree.root_node(), source, &mut ctx);
-            // 2. $inject パターンを収集
-            self.collect_inject_patterns(tree.root_node(), source, uri, &mut ctx);
-            // 3. 関数/class参照パターンのコンポーネント登録を収集（$inject なしでも $scope 追跡可能に）
-            self.collect_component_ref_scopes(tree.root_node(), source, uri, &mut ctx);
-            // 本解析
-            self.traverse_tree(&tree, source, uri, &mut ctx);
+    /// パース済みの Tree を解析してシンボルをインデックスに追加する
+    fn analyze_tree(&self, uri: &Url, source: &str, tree: &Tree, clear: bool) {
+        if clear {
+            self.index.clear_document(uri);
         }
+        let mut ctx = AnalyzerContext::new();
+        // 事前収集フェーズ:
+        // 1. $inject パターン用の関数宣言とclass宣言を収集
+        self.collect_function_declarations_for_inject(tree.root_node(), source, &mut ctx);
+        // 2. $inject パターンを収集
+        self.collect_inject_patterns(tree.root_node(), source, uri, &mut ctx);
+        // 3. 関数/class参照パターンのコンポーネント登録を収集（$inject なしでも $scope 追跡可能に）
+        self.collect_component_ref_scopes(tree.root_node(), source, uri, &mut ctx);
+        // 本解析
+        self.traverse_tree(tree, source, uri, &mut ctx);
     }
 
     /// AST全体を走査する
@@ -96,17 +150,22 @@ impl AngularJsAnalyzer {
     /// ASTノードを訪問し、種類に応じた解析を行う
     ///
     /// 認識するノード:
-    /// - `call_expression`: 関数呼び出し（angular.module(), .controller() 等）
+    /// - `call_expression`: 関数呼び出し（angular.module(), .controller(), $injector.get() 等）
     /// - `member_expression`: プロパティアクセス（Service.method, $scope.property）
     /// - `expression_statement`: 式文（$inject パターン）
     /// - `assignment_expression`: 代入式（$scope.property = value）
     /// - `identifier`: 識別子（サービス名等の参照）
     /// - `import_statement`: ES6 import文
+    /// - `pair`: オブジェクトのプロパティ（templateUrl）
     fn visit_node(&self, node: Node, source: &str, uri: &Url, ctx: &mut AnalyzerContext) {
         match node.kind() {
             "call_expression" => {
                 self.analyze_call_expression(node, source, uri, ctx);
                 self.analyze_method_call(node, source, uri, ctx);
+                self.analyze_injector_get(node, source, uri, ctx);
+                self.analyze_scope_watch(node, source, uri, ctx);
+                self.analyze_scope_event(node, source, uri);
+                self.analyze_scope_extend(node, source, uri, ctx);
             }
             "member_expression" => {
                 self.analyze_member_access(node, source, uri, ctx);
@@ -129,6 +188,9 @@ impl AngularJsAnalyzer {
             "import_statement" => {
                 self.analyze_import_statement(node, source, uri);
             }
+            "pair" => {
+                self.analyze_template_url_pair(node, source, uri);
+            }
             _ => {}
         }
 
@@ -200,6 +262,35 @@ impl AngularJsAnalyzer {
         )
     }
 
+    /// 代入の左辺 `obj.prop` / `obj['prop']` からオブジェクトノード・プロパティ名・
+    /// プロパティ名の範囲を取り出す
+    ///
+    /// ブラケット記法はキーが文字列リテラルの場合のみ対象 (`obj[key]` は `None`)。
+    /// 文字列キーの範囲はクォートを含まない。
+    pub(super) fn assignment_target<'a>(&self, left: Node<'a>, source: &str) -> Option<(Node<'a>, String, Span)> {
+        let object = left.child_by_field_name("object")?;
+        match left.kind() {
+            "member_expression" => {
+                let property = left.child_by_field_name("property")?;
+                Some((object, self.node_text(property, source), self.span_of(property)))
+            }
+            "subscript_expression" => {
+                let key = left.child_by_field_name("index").filter(|k| k.kind() == "string")?;
+                let name = self.extract_string_value(key, source);
+                if name.is_empty() {
+                    return None;
+                }
+                let span = self.span_of(key);
+                Some((
+                    object,
+                    name,
+                    Span::new(span.start_line, span.start_col + 1, span.end_line, span.end_col - 1),
+                ))
+            }
+            _ => None,
+        }
+    }
+
     /// ASTノードからソーステキストを取得する
     pub(super) fn node_text(&self, node: Node, source: &str) -> String {
         source[node.byte_range()].to_string()
@@ -280,9 +371,13 @@ impl AngularJsAnalyzer {
     /// 認識パターン:
     /// - function(param1, param2) {}
     /// - (param1, param2) => {}
+    /// - param => {} (括弧なしの単一引数)
+    /// - { method(param1, param2) {} } (オブジェクトのメソッド定義)
+    ///
+    /// デフォルト引数・分割代入・残余引数の表記は [`parameter_label`] を参照
     pub(super) fn extract_function_params(&self, node: Node, source: &str) -> Option<Vec<String>> {
         let func_node = match node.kind() {
-            "function_expression" | "arrow_function" | "function_declaration" => Some(node),
+            "function_expression" | "arrow_function" | "function_declaration" | "method_definition" => Some(node),
             "array" => {
                 // DI配列: ['$scope', function($scope) {}]
                 let mut cursor = node.walk();
@@ -292,16 +387,22 @@ impl AngularJsAnalyzer {
             _ => None,
         }?;
 
+        // `user => {...}` は parameters ではなく parameter フィールドを持つ
+        if let Some(param) = func_node.child_by_field_name("parameter") {
+            return parameter_label(param, source).map(|label| vec![label]);
+        }
+
         let params_node = func_node.child_by_field_name("parameters")?;
-        let mut params = Vec::new();
+        self.extract_params_from_node(params_node, source)
+    }
 
+    /// パラメータリストノード (`formal_parameters`) からパラメータ名のリストを抽出する
+    pub(super) fn extract_params_from_node(&self, params_node: Node, source: &str) -> Option<Vec<String>> {
         let mut cursor = params_node.walk();
-        for child in params_node.children(&mut cursor) {
-            if child.kind() == "identifier" {
-                let param_name = self.node_text(child, source);
-                params.push(param_name);
-            }
-        }
+        let params: Vec<String> = params_node
+            .named_children(&mut cursor)
+            .filter_map(|child| parameter_label(child, source))
+            .collect();
 
         if params.is_empty() {
             None
@@ -311,6 +412,106 @@ impl AngularJsAnalyzer {
     }
 }
 
+/// シグネチャ表示用のパラメータ名を取り出す
+///
+/// - `x` / `x = 1` (デフォルト引数) → `x`
+/// - `{ id, name }` / `[first, second]` (分割代入) → ソース上の表記 (空白は 1 つに詰める)
+/// - `...args` → `...args`
+/// - TypeScript の `required_parameter` / `optional_parameter` は `pattern` 部分
+///
+/// コメントなどパラメータ以外のノードは `None`
+fn parameter_label(node: Node, source: &str) -> Option<String> {
+    match node.kind() {
+        "identifier" | "object_pattern" | "array_pattern" | "rest_pattern" => Some(
+            source[node.byte_range()]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "assignment_pattern" => parameter_label(node.child_by_field_name("left")?, source),
+        "required_parameter" | "optional_parameter" => {
+            parameter_label(node.child_by_field_name("pattern")?, source)
+        }
+        _ => None,
+    }
+}
+
+/// 関数パラメータノードから引数名の識別子ノードを取り出す
+///
+/// JavaScript では `identifier` そのもの。TypeScript の `required_parameter` /
+/// `optional_parameter` は型注釈やアクセス修飾子 (`private $http: ng.IHttpService`)
+/// を含むので、`pattern` フィールドが識別子の場合にそれを返す。
+pub(crate) fn param_identifier(node: Node) -> Option<Node> {
+    match node.kind() {
+        "identifier" => Some(node),
+        "required_parameter" | "optional_parameter" => node
+            .child_by_field_name("pattern")
+            .filter(|pattern| pattern.kind() == "identifier"),
+        _ => None,
+    }
+}
+
+/// 関数が DI で受け取るサービス名と、それを受ける引数名の組 (サービス名, 引数名)
+///
+/// 関数を包む DI 配列 (`['$http', function(h) {...}]`) があれば配列の文字列と引数を
+/// 位置で対応させ、なければ引数名をそのままサービス名とみなす
+pub(crate) fn injected_params<'a>(func: Node, source: &'a str) -> Vec<(&'a str, &'a str)> {
+    let text = |node: Node| &source[node.byte_range()];
+    let params: Vec<&str> = match func.child_by_field_name("parameter") {
+        Some(param) => vec![text(param)],
+        None => func
+            .child_by_field_name("parameters")
+            .map(|params| {
+                let mut cursor = params.walk();
+                params
+                    .named_children(&mut cursor)
+                    .filter_map(param_identifier)
+                    .map(text)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    match func.parent().filter(|p| p.kind() == "array") {
+        Some(array) => {
+            let mut cursor = array.walk();
+            array
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "string")
+                .map(|s| text(s).trim_matches(|c| c == '"' || c == '\''))
+                .zip(params)
+                .collect()
+        }
+        None => params.into_iter().map(|param| (param, param)).collect(),
+    }
+}
+
+/// 関数本体直下 (入れ子の関数は除く) の `return` の値のうち `is_target` を満たすものを返す
+///
+/// directive の `return { ... }` や filter の `return function(...) {}` の取り出しに使う
+pub(super) fn find_returned_node<'a>(
+    node: Node<'a>,
+    is_target: fn(Node) -> bool,
+) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "return_statement" => {
+                if let Some(value) = child.named_child(0).filter(|n| is_target(*n)) {
+                    return Some(value);
+                }
+            }
+            "function_expression" | "arrow_function" | "function_declaration" | "class" => {}
+            _ => {
+                if let Some(value) = find_returned_node(child, is_target) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    None
+}
+
 /// JavaScriptの予約語・キーワードかどうかを判定する
 pub(super) fn is_common_keyword(name: &str) -> bool {
     matches!(
@@ -3,9 +3,27 @@ use tree_sitter::Node;
 
 use super::context::AnalyzerContext;
 use super::AngularJsAnalyzer;
-use crate::model::ControllerScope;
+use crate::model::{ControllerScope, SymbolReference};
 
 impl AngularJsAnalyzer {
+    /// getter 形式の `angular.module('app')` のモジュール名を既存モジュールへの参照として登録する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// angular.module('app').controller('MyController', ...);
+    /// var app = angular.module('app');
+    /// ```
+    ///
+    /// 第2引数 (依存配列) の有無で setter と区別する。setter は定義として
+    /// `extract_module_definition` で登録される
+    pub(super) fn extract_module_getter_reference(&self, name_node: Node, name: &str, uri: &Url) {
+        self.index.definitions.add_reference(SymbolReference {
+            name: name.to_string(),
+            uri: uri.clone(),
+            span: self.span_of(name_node),
+        });
+    }
+
     /// 関数/class参照パターンのコンポーネント登録を事前収集する
     ///
     /// 認識パターン:
@@ -74,8 +92,8 @@ impl AngularJsAnalyzer {
                                                     self.index.controllers.add_controller_scope(ControllerScope {
                                                         name: ref_name.clone(),
                                                         uri: uri.clone(),
-                                                        start_line,
-                                                        end_line,
+                                                        start_line: self.offset_line(start_line),
+                                                        end_line: self.offset_line(end_line),
                                                         injected_services: services.clone(),
                                                     });
                                                 }
@@ -1,4 +1,7 @@
-use tree_sitter::{Parser, Tree};
+use tower_lsp::lsp_types::Url;
+use tree_sitter::{Language, Parser, Tree};
+
+use crate::util::is_typescript_file;
 
 pub struct JsParser {
     parser: Parser,
@@ -14,9 +17,24 @@ impl JsParser {
         Self { parser }
     }
 
+    /// URI の拡張子に応じた文法 (JavaScript / TypeScript / TSX) でパーサーを作る
+    pub fn for_uri(uri: &Url) -> Self {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&js_language_for_uri(uri))
+            .expect("Failed to load JavaScript/TypeScript grammar");
+
+        Self { parser }
+    }
+
     pub fn parse(&mut self, source: &str) -> Option<Tree> {
         self.parser.parse(source, None)
     }
+
+    /// `Tree::edit` 済みの前回 Tree を使って差分パースする (`None` ならフルパース)
+    pub fn parse_incremental(&mut self, source: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        self.parser.parse(source, old_tree)
+    }
 }
 
 impl Default for JsParser {
@@ -24,3 +42,15 @@ impl Default for JsParser {
         Self::new()
     }
 }
+
+/// URI の拡張子から tree-sitter の文法を選ぶ (`.ts` / `.tsx` 以外は JavaScript)
+pub fn js_language_for_uri(uri: &Url) -> Language {
+    if !is_typescript_file(uri) {
+        return tree_sitter_javascript::LANGUAGE.into();
+    }
+    if uri.path().to_lowercase().ends_with(".tsx") {
+        tree_sitter_typescript::LANGUAGE_TSX.into()
+    } else {
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()
+    }
+}
@@ -81,6 +81,7 @@ impl AngularJsAnalyzer {
     /// Analyze member access (non-call) and register as references
     ///
     /// Pattern: var fn = UserService.getAll; callback(AuthService.login);
+    /// `$injector.get('UserService').getAll` や `$injector.get` で受けた変数経由も含む
     pub(super) fn analyze_member_access(
         &self,
         node: Node,
@@ -92,12 +93,34 @@ impl AngularJsAnalyzer {
             if let Some(property) = node.child_by_field_name("property") {
                 let obj_name = self.node_text(object, source);
                 let prop_name = self.node_text(property, source);
+                let current_line = node.start_position().row as u32;
+
+                // `$injector.get('X').method` / `$injector.get` で受けた変数経由のアクセス
+                let injected = match object.kind() {
+                    "call_expression" => self
+                        .injector_get_target(object, source, ctx)
+                        .map(|(service, _)| service),
+                    "identifier" => ctx
+                        .resolve_service_alias(&obj_name, current_line)
+                        .map(str::to_string),
+                    _ => None,
+                };
+                if let Some(service) = injected {
+                    let full_name = format!("{}.{}", service, prop_name);
+                    if self.index.definitions.has_definition(&full_name) {
+                        self.index.definitions.add_reference(SymbolReference {
+                            name: full_name,
+                            uri: uri.clone(),
+                            span: self.span_of(property),
+                        });
+                    }
+                    return;
+                }
 
                 if obj_name.starts_with('$') || obj_name == "this" || obj_name == "console" {
                     return;
                 }
 
-                let current_line = node.start_position().row as u32;
                 if !ctx.is_injected_at(&obj_name, current_line) {
                     return;
                 }
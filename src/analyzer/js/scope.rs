@@ -3,7 +3,7 @@ use tree_sitter::Node;
 
 use super::context::AnalyzerContext;
 use super::AngularJsAnalyzer;
-use crate::model::{SymbolBuilder, SymbolKind, SymbolReference};
+use crate::model::{Span, SymbolBuilder, SymbolKind, SymbolReference};
 
 impl AngularJsAnalyzer {
     /// $scope.property への代入を解析し、定義として登録する
@@ -12,91 +12,227 @@ impl AngularJsAnalyzer {
     /// ```javascript
     /// $scope.users = [];
     /// $scope.loadUsers = function() { ... };
+    /// $scope['dynamicName'] = value;   // 文字列リテラルキーのみ ($scope[key] は対象外)
     /// ```
     ///
     /// 一番最初の代入のみを定義として登録する
     /// 右辺が関数の場合は ScopeMethod、それ以外は ScopeProperty として登録
     pub(super) fn analyze_scope_assignment(&self, node: Node, source: &str, uri: &Url, ctx: &mut AnalyzerContext) {
-        // $scope.xxx = ... パターンを検出
-        if let Some(left) = node.child_by_field_name("left") {
-            if left.kind() == "member_expression" {
-                if let Some(object) = left.child_by_field_name("object") {
-                    let obj_name = self.node_text(object, source);
-                    if obj_name == "$scope" {
-                        // スコープ情報を取得（コントローラー名と$scopeのDI状態を同時に取得）
-                        let current_line = node.start_position().row as u32;
-                        let (controller_name, has_scope) = match ctx.get_scope_info_at(current_line) {
-                            Some((name, has_scope)) => (name, has_scope),
-                            None => return, // スコープが見つからない場合はスキップ
-                        };
+        // $scope.xxx = ... / $scope['xxx'] = ... パターンを検出
+        let Some((object, prop_name, prop_span)) = node
+            .child_by_field_name("left")
+            .and_then(|left| self.assignment_target(left, source))
+        else {
+            return;
+        };
+        if self.node_text(object, source) != "$scope" {
+            return;
+        }
 
-                        // $scope がDIされていない場合はスキップ
-                        if !has_scope {
-                            return;
-                        }
+        // スコープ情報を取得（コントローラー名と$scopeのDI状態を同時に取得）
+        let current_line = node.start_position().row as u32;
+        let (controller_name, has_scope) = match ctx.get_scope_info_at(current_line) {
+            Some((name, has_scope)) => (name, has_scope),
+            None => return, // スコープが見つからない場合はスキップ
+        };
 
-                        if let Some(property) = left.child_by_field_name("property") {
-                            let prop_name = self.node_text(property, source);
+        // $scope がDIされていない場合はスキップ
+        if !has_scope {
+            return;
+        }
 
-                            // シンボル名を生成（コントローラー名.$scope.プロパティ名）
-                            let full_name = format!("{}.$scope.{}", controller_name, prop_name);
+        // シンボル名を生成（コントローラー名.$scope.プロパティ名）
+        let full_name = format!("{}.$scope.{}", controller_name, prop_name);
 
-                            // 既に定義済みの場合は参照として登録
-                            if ctx.defined_scope_properties.contains_key(&full_name) {
-                                // 代入の左辺も参照としてカウント
-                                let reference = SymbolReference {
-                                    name: full_name,
-                                    uri: uri.clone(),
-                                    span: self.span_of(property),
-                                };
+        // `$scope.user = { name: '', address: { city: '' } }` のキーはネストプロパティ
+        // (`Ctrl.$scope.user.name`) として登録する
+        if let Some(object) = node.child_by_field_name("right").filter(|r| r.kind() == "object") {
+            self.register_scope_object_properties(&full_name, object, source, uri, ctx);
+        }
 
-                                self.index.definitions.add_reference(reference);
-                                return;
-                            }
-                            ctx.defined_scope_properties.insert(full_name.clone(), true);
+        // 既に定義済みの場合は参照として登録
+        if ctx.defined_scope_properties.contains_key(&full_name) {
+            // 代入の左辺も参照としてカウント
+            let reference = SymbolReference {
+                name: full_name,
+                uri: uri.clone(),
+                span: prop_span,
+            };
+
+            self.index.definitions.add_reference(reference);
+            return;
+        }
+        ctx.defined_scope_properties.insert(full_name.clone(), true);
+
+        // JSDocを探す
+        let docs = self.extract_jsdoc_for_line(node.start_position().row, source);
+
+        // 右辺が関数かどうかを判定し、パラメータを抽出
+        let (is_function, parameters) = if let Some(right) = node.child_by_field_name("right") {
+            let is_func = matches!(right.kind(), "function_expression" | "arrow_function");
+            let params = if is_func {
+                self.extract_function_params(right, source)
+            } else {
+                None
+            };
+            (is_func, params)
+        } else {
+            (false, None)
+        };
+
+        let kind = if is_function {
+            SymbolKind::ScopeMethod
+        } else {
+            SymbolKind::ScopeProperty
+        };
+
+        let mut builder = SymbolBuilder::new(full_name, kind, uri.clone())
+            .definition_span(prop_span)
+            .name_span(prop_span);
+
+        if let Some(docs_str) = docs {
+            builder = builder.docs(docs_str);
+        }
+        if let Some(params) = parameters {
+            builder = builder.parameters(params);
+        }
 
-                            // JSDocを探す
-                            let docs = self.extract_jsdoc_for_line(node.start_position().row, source);
+        self.index.definitions.add_definition(builder.build());
+    }
 
-                            // 右辺が関数かどうかを判定し、パラメータを抽出
-                            let (is_function, parameters) = if let Some(right) = node.child_by_field_name("right") {
-                                let is_func = matches!(right.kind(), "function_expression" | "arrow_function");
-                                let params = if is_func {
-                                    self.extract_function_params(right, source)
-                                } else {
-                                    None
-                                };
-                                (is_func, params)
-                            } else {
-                                (false, None)
-                            };
+    /// `angular.extend($scope, {...})` / `angular.merge($scope, {...})` のキーを
+    /// `$scope` プロパティ定義として登録する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// angular.extend($scope, { a: 1, load: function() {} });
+    /// angular.merge($scope, { user: { name: '', age: 0 } }); // user, user.name, user.age
+    /// ```
+    ///
+    /// 第2引数以降のオブジェクトリテラルが対象。値がオブジェクトリテラルのキーは
+    /// 展開し `Ctrl.$scope.user.name` として登録する。
+    /// 代入と同様、既に定義済みのキーは参照として登録する。
+    pub(super) fn analyze_scope_extend(&self, node: Node, source: &str, uri: &Url, ctx: &mut AnalyzerContext) {
+        let Some(callee) = node.child_by_field_name("function") else {
+            return;
+        };
+        if !matches!(self.node_text(callee, source).as_str(), "angular.extend" | "angular.merge") {
+            return;
+        }
+        let Some(args) = node.child_by_field_name("arguments") else {
+            return;
+        };
+        let mut cursor = args.walk();
+        let arguments: Vec<Node> = args.named_children(&mut cursor).collect();
+        let Some((target, sources)) = arguments.split_first() else {
+            return;
+        };
+        if self.node_text(*target, source) != "$scope" {
+            return;
+        }
 
-                            let kind = if is_function {
-                                SymbolKind::ScopeMethod
-                            } else {
-                                SymbolKind::ScopeProperty
-                            };
+        let current_line = node.start_position().row as u32;
+        let controller_name = match ctx.get_scope_info_at(current_line) {
+            Some((name, true)) => name,
+            _ => return,
+        };
 
-                            let def_span = self.span_of(property);
-                            let name_span = self.span_of(property);
+        let prefix = format!("{}.$scope", controller_name);
+        for object in sources.iter().filter(|n| n.kind() == "object") {
+            self.register_scope_object_properties(&prefix, *object, source, uri, ctx);
+        }
+    }
 
-                            let mut builder = SymbolBuilder::new(full_name, kind, uri.clone())
-                                .definition_span(def_span)
-                                .name_span(name_span);
+    /// オブジェクトリテラルのキーを `<prefix>.<key>` の `$scope` プロパティとして登録する
+    ///
+    /// 値がオブジェクトリテラルのキーは再帰的に展開する
+    /// (`{ user: { address: { city } } }` → `user`, `user.address`, `user.address.city`)
+    fn register_scope_object_properties(
+        &self,
+        prefix: &str,
+        object: Node,
+        source: &str,
+        uri: &Url,
+        ctx: &mut AnalyzerContext,
+    ) {
+        for (key, value) in self.object_entries(object) {
+            let full_name = format!("{}.{}", prefix, self.object_key_name(key, source));
+            self.register_extended_scope_property(&full_name, key, value, source, uri, ctx);
+            if let Some(nested) = value.filter(|v| v.kind() == "object") {
+                self.register_scope_object_properties(&full_name, nested, source, uri, ctx);
+            }
+        }
+    }
 
-                            if let Some(docs_str) = docs {
-                                builder = builder.docs(docs_str);
-                            }
-                            if let Some(params) = parameters {
-                                builder = builder.parameters(params);
-                            }
+    /// オブジェクトリテラルの各エントリを (キーノード, 値ノード) として列挙する
+    ///
+    /// `{ a: 1 }` / `{ a }` (shorthand) / `{ load() {} }` (メソッド定義) に対応。
+    /// shorthand の値は `None`、メソッド定義は自身を値とする。
+    pub(super) fn object_entries<'a>(&self, object: Node<'a>) -> Vec<(Node<'a>, Option<Node<'a>>)> {
+        let mut cursor = object.walk();
+        object
+            .named_children(&mut cursor)
+            .filter_map(|child| match child.kind() {
+                "pair" => Some((child.child_by_field_name("key")?, child.child_by_field_name("value"))),
+                "shorthand_property_identifier" => Some((child, None)),
+                "method_definition" => Some((child.child_by_field_name("name")?, Some(child))),
+                _ => None,
+            })
+            .collect()
+    }
 
-                            self.index.definitions.add_definition(builder.build());
-                        }
-                    }
-                }
-            }
+    /// オブジェクトのキー名 (クォート付き文字列キーはクォートを除去)
+    pub(super) fn object_key_name(&self, key: Node, source: &str) -> String {
+        self.node_text(key, source)
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string()
+    }
+
+    /// `angular.extend` やオブジェクトリテラルのキーで追加された `$scope` プロパティを登録する
+    ///
+    /// 値が関数なら ScopeMethod、それ以外は ScopeProperty
+    fn register_extended_scope_property(
+        &self,
+        full_name: &str,
+        key: Node,
+        value: Option<Node>,
+        source: &str,
+        uri: &Url,
+        ctx: &mut AnalyzerContext,
+    ) {
+        let span = self.span_of(key);
+        if ctx.defined_scope_properties.contains_key(full_name) {
+            self.index.definitions.add_reference(SymbolReference {
+                name: full_name.to_string(),
+                uri: uri.clone(),
+                span,
+            });
+            return;
+        }
+        ctx.defined_scope_properties.insert(full_name.to_string(), true);
+
+        let is_function = value.is_some_and(|v| {
+            matches!(v.kind(), "function_expression" | "arrow_function" | "method_definition")
+        });
+        let kind = if is_function {
+            SymbolKind::ScopeMethod
+        } else {
+            SymbolKind::ScopeProperty
+        };
+
+        let mut builder = SymbolBuilder::new(full_name.to_string(), kind, uri.clone())
+            .definition_span(span)
+            .name_span(span);
+        if let Some(docs) = self.extract_jsdoc_for_line(key.start_position().row, source) {
+            builder = builder.docs(docs);
+        }
+        if let Some(params) = value
+            .filter(|_| is_function)
+            .and_then(|v| self.extract_function_params(v, source))
+        {
+            builder = builder.parameters(params);
         }
+        self.index.definitions.add_definition(builder.build());
     }
 
     /// $scope.property への参照を解析し、参照として登録する
@@ -156,6 +292,126 @@ impl AngularJsAnalyzer {
         }
     }
 
+    /// `$scope.$watch('user.name', fn)` の文字列式を `$scope` プロパティ参照として登録する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// $scope.$watch('user.name', fn);            // → Ctrl.$scope.user
+    /// $scope.$watchCollection('items', fn);
+    /// $scope.$watchGroup(['first', 'last'], fn);
+    /// ```
+    ///
+    /// 式の先頭の識別子だけを参照とし、範囲は文字列内のその識別子に絞る。
+    pub(super) fn analyze_scope_watch(&self, node: Node, source: &str, uri: &Url, ctx: &AnalyzerContext) {
+        let Some((method, first_arg)) = self.scope_method_call(node, source, "$scope") else {
+            return;
+        };
+        let watch_strings: Vec<Node> = match (method.as_str(), first_arg.kind()) {
+            ("$watch" | "$watchCollection", "string") => vec![first_arg],
+            ("$watchGroup", "array") => {
+                let mut cursor = first_arg.walk();
+                first_arg
+                    .named_children(&mut cursor)
+                    .filter(|c| c.kind() == "string")
+                    .collect()
+            }
+            _ => return,
+        };
+
+        let current_line = node.start_position().row as u32;
+        let controller_name = match ctx.get_scope_info_at(current_line) {
+            Some((name, true)) => name,
+            _ => return,
+        };
+
+        for string_node in watch_strings {
+            let value = self.extract_string_value(string_node, source);
+            let prop_name: String = value
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+                .collect();
+            if prop_name.is_empty() || string_node.start_position().row != string_node.end_position().row {
+                continue;
+            }
+
+            // クォートの直後から識別子の長さ分
+            let start = string_node.start_position();
+            let line = self.offset_line(start.row as u32);
+            let start_col = start.column as u32 + 1;
+            self.index.definitions.add_reference(SymbolReference {
+                name: format!("{}.$scope.{}", controller_name, prop_name),
+                uri: uri.clone(),
+                span: Span::new(line, start_col, line, start_col + prop_name.len() as u32),
+            });
+        }
+    }
+
+    /// `$on` / `$emit` / `$broadcast` のイベント名文字列を解析する
+    ///
+    /// 認識パターン:
+    /// ```javascript
+    /// $scope.$on('userUpdated', fn);              // SymbolKind::Event の定義
+    /// $rootScope.$broadcast('userUpdated', user); // 参照
+    /// $scope.$emit('userUpdated');                // 参照
+    /// ```
+    ///
+    /// レシーバは問わない (`$scope` / `$rootScope` / directive の `scope` など)。
+    /// 購読側を定義にすることで、送信側から Go to Definition でハンドラへ飛べ、
+    /// References で同名イベントの送受信箇所を横断できる。
+    ///
+    /// `$destroy` / `$routeChangeSuccess` / `$stateChangeStart` など `$` で始まる
+    /// AngularJS / ルーターの組み込みイベントは登録しない。
+    pub(super) fn analyze_scope_event(&self, node: Node, source: &str, uri: &Url) {
+        let Some((method, first_arg)) = self.scope_method_call(node, source, "") else {
+            return;
+        };
+        if first_arg.kind() != "string" {
+            return;
+        }
+        let event_name = self.extract_string_value(first_arg, source);
+        if event_name.is_empty() || event_name.starts_with('$') {
+            return;
+        }
+        let span = self.span_of(first_arg);
+
+        match method.as_str() {
+            "$on" => {
+                let symbol = SymbolBuilder::new(event_name, SymbolKind::Event, uri.clone())
+                    .definition_span(span)
+                    .name_span(span)
+                    .build();
+                self.index.definitions.add_definition(symbol);
+            }
+            "$emit" | "$broadcast" => {
+                self.index.definitions.add_reference(SymbolReference {
+                    name: event_name,
+                    uri: uri.clone(),
+                    span,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// `<receiver>.<method>(<first_arg>, ...)` 形式の呼び出しからメソッド名と第1引数を取り出す
+    ///
+    /// `receiver` が空文字ならレシーバを問わない。
+    fn scope_method_call<'a>(&self, node: Node<'a>, source: &str, receiver: &str) -> Option<(String, Node<'a>)> {
+        let callee = node.child_by_field_name("function")?;
+        if callee.kind() != "member_expression" {
+            return None;
+        }
+        if !receiver.is_empty() {
+            let object = callee.child_by_field_name("object")?;
+            if self.node_text(object, source) != receiver {
+                return None;
+            }
+        }
+        let method = self.node_text(callee.child_by_field_name("property")?, source);
+        let first_arg = node.child_by_field_name("arguments")?.named_child(0)?;
+        Some((method, first_arg))
+    }
+
     /// $rootScope.property への代入を解析し、定義として登録する
     ///
     /// 認識パターン:
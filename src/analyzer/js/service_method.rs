@@ -5,7 +5,7 @@ use tree_sitter::Node;
 
 use super::context::LocalVarLocation;
 use super::AngularJsAnalyzer;
-use crate::model::{SymbolBuilder, SymbolKind};
+use crate::model::{SymbolBuilder, SymbolKind, SymbolReference};
 
 impl AngularJsAnalyzer {
     /// サービス/ファクトリーの実装関数からメソッドを抽出する
@@ -17,7 +17,11 @@ impl AngularJsAnalyzer {
     /// .factory('Svc', SvcFunction)  // 関数参照パターン
     /// .service('Svc', class { getData() { ... } })  // ES6 class式
     /// .service('Svc', MyServiceClass)  // ES6 class参照
+    /// .service('Svc', ['$http', SvcFunction])  // DI配列内の関数参照
     /// ```
+    ///
+    /// 関数参照の場合は `SvcFunction.prototype.getAll = function() {}` /
+    /// `SvcFunction.prototype = { ... }` のプロトタイプ定義もメソッドとして抽出する
     pub(super) fn extract_service_methods(&self, node: Node, source: &str, uri: &Url, service_name: &str) {
         if node.kind() == "array" {
             let mut cursor = node.walk();
@@ -27,6 +31,9 @@ impl AngularJsAnalyzer {
                 } else if child.kind() == "class" {
                     // ES6 class式: ['$http', class { ... }]
                     self.extract_methods_from_class(child, source, uri, service_name);
+                } else if child.kind() == "identifier" {
+                    // 関数参照: ['$http', SvcFunction]
+                    self.extract_service_methods(child, source, uri, service_name);
                 }
             }
         } else if node.kind() == "function_expression" || node.kind() == "arrow_function" {
@@ -49,6 +56,7 @@ impl AngularJsAnalyzer {
                 // まず関数宣言を探す
                 if let Some(func_decl) = self.find_function_declaration(root, source, &ref_name) {
                     self.extract_methods_from_function_decl(func_decl, source, uri, service_name);
+                    self.scan_for_prototype_methods(root, source, uri, service_name, &ref_name);
                 } else if let Some(class_decl) = self.find_class_declaration(root, source, &ref_name) {
                     // class宣言を探す
                     self.extract_methods_from_class(class_decl, source, uri, service_name);
@@ -57,6 +65,73 @@ impl AngularJsAnalyzer {
         }
     }
 
+    /// コンストラクタ関数のプロトタイプに定義されたメソッドを探す
+    ///
+    /// 認識パターン (TypeScript のトランスパイル結果など):
+    /// ```javascript
+    /// function UserService($http) { this.$http = $http; }
+    /// UserService.prototype.getAll = function() { ... };
+    /// UserService.prototype = { getById: function(id) { ... } };
+    /// angular.module('app').service('UserService', UserService);
+    /// ```
+    ///
+    /// 登録されたコンストラクタ名 (`constructor_name`) のプロトタイプのみ対象とし、
+    /// `UserService.getAll` のように `service_name` の下に登録する
+    fn scan_for_prototype_methods(&self, node: Node, source: &str, uri: &Url, service_name: &str, constructor_name: &str) {
+        if node.kind() == "assignment_expression" {
+            self.extract_prototype_method(node, source, uri, service_name, constructor_name);
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.scan_for_prototype_methods(child, source, uri, service_name, constructor_name);
+        }
+    }
+
+    /// `Ctor.prototype.method = ...` / `Ctor.prototype = { ... }` の代入を 1 つ解析する
+    fn extract_prototype_method(&self, assign_node: Node, source: &str, uri: &Url, service_name: &str, constructor_name: &str) {
+        let (Some(left), Some(right)) = (
+            assign_node.child_by_field_name("left"),
+            assign_node.child_by_field_name("right"),
+        ) else {
+            return;
+        };
+        let prototype = format!("{}.prototype", constructor_name);
+
+        // Ctor.prototype = { ... }
+        if self.node_text(left, source) == prototype {
+            if right.kind() == "object" {
+                self.extract_object_methods(right, source, uri, service_name, &HashMap::new());
+            }
+            return;
+        }
+
+        // Ctor.prototype.method = ...
+        let Some((object, method_name, span)) = self.assignment_target(left, source) else {
+            return;
+        };
+        if self.node_text(object, source) != prototype {
+            return;
+        }
+
+        let docs = self.extract_jsdoc_for_line(assign_node.start_position().row, source);
+        let parameters = self.extract_function_params(right, source);
+
+        let full_name = format!("{}.{}", service_name, method_name);
+        let mut builder = SymbolBuilder::new(full_name, SymbolKind::Method, uri.clone())
+            .definition_span(span)
+            .name_span(span);
+
+        if let Some(docs_str) = docs {
+            builder = builder.docs(docs_str);
+        }
+        if let Some(params) = parameters {
+            builder = builder.parameters(params);
+        }
+
+        self.index.definitions.add_definition(builder.build());
+    }
+
     /// ES6 classからService/Factory/Controllerのメソッドを抽出する
     ///
     /// 認識パターン:
@@ -111,22 +186,6 @@ impl AngularJsAnalyzer {
         }
     }
 
-    /// パラメータノードからパラメータ名のリストを抽出する
-    fn extract_params_from_node(&self, params_node: Node, source: &str) -> Option<Vec<String>> {
-        let mut params = Vec::new();
-        let mut cursor = params_node.walk();
-        for child in params_node.children(&mut cursor) {
-            if child.kind() == "identifier" {
-                params.push(self.node_text(child, source));
-            }
-        }
-        if params.is_empty() {
-            None
-        } else {
-            Some(params)
-        }
-    }
-
     /// 関数宣言を探す
     pub(super) fn find_function_declaration<'a>(&self, node: Node<'a>, source: &str, name: &str) -> Option<Node<'a>> {
         if node.kind() == "function_declaration" {
@@ -300,6 +359,9 @@ impl AngularJsAnalyzer {
                     }
                 }
             }
+            "call_expression" => {
+                self.extract_object_assign_methods(node, source, uri, service_name, local_vars, this_aliases);
+            }
             _ => {}
         }
 
@@ -309,6 +371,51 @@ impl AngularJsAnalyzer {
         }
     }
 
+    /// `Object.assign(this, { ... })` パターンからメソッドを抽出する
+    ///
+    /// ```javascript
+    /// .service('UserService', function($http) {
+    ///     Object.assign(this, {
+    ///         getAll: function() { ... },
+    ///         getById               // ローカル関数の shorthand
+    ///     });
+    /// })
+    /// ```
+    ///
+    /// 第1引数が `this` (または thisエイリアス) の場合のみ、第2引数以降の
+    /// オブジェクトリテラルを `return { ... }` と同様に解析する
+    fn extract_object_assign_methods(
+        &self,
+        call_node: Node,
+        source: &str,
+        uri: &Url,
+        service_name: &str,
+        local_vars: &HashMap<String, LocalVarLocation>,
+        this_aliases: &[String],
+    ) {
+        let (Some(callee), Some(args)) = (
+            call_node.child_by_field_name("function"),
+            call_node.child_by_field_name("arguments"),
+        ) else {
+            return;
+        };
+        if self.node_text(callee, source) != "Object.assign" {
+            return;
+        }
+        let mut cursor = args.walk();
+        let mut arguments = args.named_children(&mut cursor);
+        let Some(target) = arguments.next() else {
+            return;
+        };
+        let target_text = self.node_text(target, source);
+        if target_text != "this" && !this_aliases.contains(&target_text) {
+            return;
+        }
+        for object in arguments.filter(|arg| arg.kind() == "object") {
+            self.extract_object_methods(object, source, uri, service_name, local_vars);
+        }
+    }
+
     /// `return <identifier>;` パターンからreturnされる変数名を検出する
     ///
     /// factory内で `var service = {}; ... return service;` のように
@@ -451,6 +558,7 @@ impl AngularJsAnalyzer {
     /// ```
     ///
     /// `AuthService.login`, `AuthService.logout`, `AuthService.isLoggedIn` として登録
+    /// (`{ login(creds) { ... } }` のメソッド定義記法も同様)
     fn extract_object_methods(
         &self,
         obj_node: Node,
@@ -530,6 +638,29 @@ impl AngularJsAnalyzer {
                         }
                     }
                 }
+                // メソッド定義: { getAll() { ... } } (ES6)
+                "method_definition" => {
+                    let Some(name_node) = child.child_by_field_name("name") else {
+                        continue;
+                    };
+                    let full_name = format!("{}.{}", service_name, self.node_text(name_node, source));
+                    let span = self.span_of(name_node);
+                    let docs = self.extract_jsdoc_for_line(child.start_position().row, source);
+                    let parameters = self.extract_function_params(child, source);
+
+                    let mut builder = SymbolBuilder::new(full_name, SymbolKind::Method, uri.clone())
+                        .definition_span(span)
+                        .name_span(span);
+
+                    if let Some(docs_str) = docs {
+                        builder = builder.docs(docs_str);
+                    }
+                    if let Some(params) = parameters {
+                        builder = builder.parameters(params);
+                    }
+
+                    self.index.definitions.add_definition(builder.build());
+                }
                 // shorthand: { showNotify } (ES6)
                 "shorthand_property_identifier" => {
                     let method_name = self.node_text(child, source);
@@ -599,7 +730,7 @@ impl AngularJsAnalyzer {
                     self.extract_this_methods_from_controller(child, source, uri, controller_name);
                 } else if child.kind() == "class" {
                     // ES6 class式: ['$scope', class { submit() {} }]
-                    self.extract_methods_from_class(child, source, uri, controller_name);
+                    self.extract_controller_class_members(child, source, uri, controller_name);
                 }
             }
         } else if node.kind() == "function_expression" || node.kind() == "arrow_function" {
@@ -607,7 +738,7 @@ impl AngularJsAnalyzer {
             self.extract_this_methods_from_controller(node, source, uri, controller_name);
         } else if node.kind() == "class" {
             // ES6 class式: .controller('Ctrl', class { ... })
-            self.extract_methods_from_class(node, source, uri, controller_name);
+            self.extract_controller_class_members(node, source, uri, controller_name);
         } else if node.kind() == "identifier" {
             // 関数参照またはclass参照パターン: .controller('Ctrl', MyController)
             let ref_name = self.node_text(node, source);
@@ -626,12 +757,35 @@ impl AngularJsAnalyzer {
                     }
                 } else if let Some(class_decl) = self.find_class_declaration(root, source, &ref_name) {
                     // class宣言を探す
-                    self.extract_methods_from_class(class_decl, source, uri, controller_name);
+                    self.extract_controller_class_members(class_decl, source, uri, controller_name);
                 }
             }
         }
     }
 
+    /// コントローラーとして使われるES6 classのメンバーを抽出
+    ///
+    /// クラスメソッドに加え、constructor内の `this.users = [];` のような
+    /// プロパティ代入も `Ctrl.users` として登録する
+    /// (関数コントローラーの `this.users` と同じ扱いで、`vm.users` / `$ctrl.users` から解決可能)
+    fn extract_controller_class_members(&self, class_node: Node, source: &str, uri: &Url, controller_name: &str) {
+        self.extract_methods_from_class(class_node, source, uri, controller_name);
+
+        let Some(body) = class_node.child_by_field_name("body") else {
+            return;
+        };
+        let mut cursor = body.walk();
+        let constructor = body.children(&mut cursor).find(|child| {
+            child.kind() == "method_definition"
+                && child
+                    .child_by_field_name("name")
+                    .is_some_and(|name| self.node_text(name, source) == "constructor")
+        });
+        if let Some(ctor_body) = constructor.and_then(|c| c.child_by_field_name("body")) {
+            self.scan_for_this_methods(ctor_body, source, uri, controller_name);
+        }
+    }
+
     /// コントローラー関数本体からthis.methodを抽出
     fn extract_this_methods_from_controller(&self, func_node: Node, source: &str, uri: &Url, controller_name: &str) {
         if let Some(body) = func_node.child_by_field_name("body") {
@@ -639,6 +793,49 @@ impl AngularJsAnalyzer {
             let this_aliases = self.collect_this_aliases(body, source);
             // this.method と vm.method の両方をスキャン
             self.scan_for_this_methods_with_aliases(body, source, uri, controller_name, &this_aliases);
+            self.register_this_member_references(body, source, uri, controller_name, &this_aliases);
+        }
+    }
+
+    /// コントローラー内の `vm.userName` / `this.userName` の読み取りを参照として登録する
+    ///
+    /// 定義済みの `Ctrl.userName` に対してのみ、プロパティ名部分を span とする
+    /// (`vm.userName.first` でも `userName` だけ)。代入の左辺は定義として扱われるので除く
+    fn register_this_member_references(
+        &self,
+        node: Node,
+        source: &str,
+        uri: &Url,
+        controller_name: &str,
+        this_aliases: &[String],
+    ) {
+        if node.kind() == "member_expression" {
+            let is_assignment_target = node.parent().is_some_and(|parent| {
+                parent.kind() == "assignment_expression"
+                    && parent.child_by_field_name("left").is_some_and(|left| left.id() == node.id())
+            });
+            let object = node.child_by_field_name("object");
+            let property = node
+                .child_by_field_name("property")
+                .filter(|p| p.kind() == "property_identifier");
+            if let (false, Some(object), Some(property)) = (is_assignment_target, object, property) {
+                let obj_text = self.node_text(object, source);
+                let full_name = format!("{}.{}", controller_name, self.node_text(property, source));
+                if (obj_text == "this" || this_aliases.contains(&obj_text))
+                    && self.index.definitions.has_definition(&full_name)
+                {
+                    self.index.definitions.add_reference(SymbolReference {
+                        name: full_name,
+                        uri: uri.clone(),
+                        span: self.span_of(property),
+                    });
+                }
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.register_this_member_references(child, source, uri, controller_name, this_aliases);
         }
     }
 
@@ -707,6 +904,8 @@ impl AngularJsAnalyzer {
     }
 
     /// `this.method = ...` または `vm.method = ...` パターンからメソッドを抽出
+    ///
+    /// `this['method'] = ...` のような文字列リテラルキーのブラケット記法も対象
     fn extract_this_or_alias_method(
         &self,
         assign_node: Node,
@@ -715,43 +914,62 @@ impl AngularJsAnalyzer {
         controller_name: &str,
         this_aliases: &[String],
     ) {
-        if let Some(left) = assign_node.child_by_field_name("left") {
-            if left.kind() == "member_expression" {
-                if let Some(object) = left.child_by_field_name("object") {
-                    let obj_text = self.node_text(object, source);
+        let Some((object, method_name, span)) = assign_node
+            .child_by_field_name("left")
+            .and_then(|left| self.assignment_target(left, source))
+        else {
+            return;
+        };
+
+        // `this` または thisエイリアス（vm等）かどうかをチェック
+        let obj_text = self.node_text(object, source);
+        if obj_text != "this" && !this_aliases.contains(&obj_text) {
+            return;
+        }
 
-                    // `this` または thisエイリアス（vm等）かどうかをチェック
-                    let is_this_or_alias = obj_text == "this" || this_aliases.contains(&obj_text);
+        let docs = self.extract_jsdoc_for_line(assign_node.start_position().row, source);
 
-                    if is_this_or_alias {
-                        if let Some(property) = left.child_by_field_name("property") {
-                            let method_name = self.node_text(property, source);
+        // 右辺からパラメータを抽出
+        let parameters = assign_node
+            .child_by_field_name("right")
+            .and_then(|right| self.extract_function_params(right, source));
 
-                            let docs = self.extract_jsdoc_for_line(assign_node.start_position().row, source);
+        let full_name = format!("{}.{}", controller_name, method_name);
 
-                            // 右辺からパラメータを抽出
-                            let parameters = assign_node
-                                .child_by_field_name("right")
-                                .and_then(|right| self.extract_function_params(right, source));
+        // `vm.user = { name: '' }` のキーはネストプロパティ (`Ctrl.user.name`)
+        if let Some(object) = assign_node.child_by_field_name("right").filter(|r| r.kind() == "object") {
+            self.register_this_object_properties(&full_name, object, source, uri);
+        }
 
-                            let full_name = format!("{}.{}", controller_name, method_name);
-                            let span = self.span_of(property);
+        let mut builder = SymbolBuilder::new(full_name, SymbolKind::Method, uri.clone())
+            .definition_span(span)
+            .name_span(span);
 
-                            let mut builder = SymbolBuilder::new(full_name, SymbolKind::Method, uri.clone())
-                                .definition_span(span)
-                                .name_span(span);
+        if let Some(docs_str) = docs {
+            builder = builder.docs(docs_str);
+        }
+        if let Some(params) = parameters {
+            builder = builder.parameters(params);
+        }
 
-                            if let Some(docs_str) = docs {
-                                builder = builder.docs(docs_str);
-                            }
-                            if let Some(params) = parameters {
-                                builder = builder.parameters(params);
-                            }
+        self.index.definitions.add_definition(builder.build());
+    }
 
-                            self.index.definitions.add_definition(builder.build());
-                        }
-                    }
-                }
+    /// オブジェクトリテラルのキーを `<prefix>.<key>` として再帰的に登録する
+    fn register_this_object_properties(&self, prefix: &str, object: Node, source: &str, uri: &Url) {
+        for (key, value) in self.object_entries(object) {
+            let full_name = format!("{}.{}", prefix, self.object_key_name(key, source));
+            let span = self.span_of(key);
+            let mut builder = SymbolBuilder::new(full_name.clone(), SymbolKind::Method, uri.clone())
+                .definition_span(span)
+                .name_span(span);
+            if let Some(params) = value.and_then(|v| self.extract_function_params(v, source)) {
+                builder = builder.parameters(params);
+            }
+            self.index.definitions.add_definition(builder.build());
+
+            if let Some(nested) = value.filter(|v| v.kind() == "object") {
+                self.register_this_object_properties(&full_name, nested, source, uri);
             }
         }
     }
@@ -761,5 +979,6 @@ impl AngularJsAnalyzer {
         // thisエイリアスを収集して使用
         let this_aliases = self.collect_this_aliases(node, source);
         self.scan_for_this_methods_with_aliases(node, source, uri, controller_name, &this_aliases);
+        self.register_this_member_references(node, source, uri, controller_name, &this_aliases);
     }
 }
@@ -0,0 +1,86 @@
+//! AngularJS built-in service definitions
+
+/// AngularJS 1.x 本体と公式モジュール (ngRoute / ngAnimate / ngCookies /
+/// ngSanitize / ngResource / ngMessages / ngAria / ngTouch) が提供するサービス、
+/// およびテンプレート解析で対応している ui-router / ngMaterial / ui-bootstrap の
+/// サービス
+pub static NG_BUILTIN_SERVICES: &[&str] = &[
+    // ng
+    "$anchorScroll",
+    "$animate",
+    "$animateCss",
+    "$cacheFactory",
+    "$compile",
+    "$controller",
+    "$document",
+    "$exceptionHandler",
+    "$filter",
+    "$http",
+    "$httpBackend",
+    "$httpParamSerializer",
+    "$httpParamSerializerJQLike",
+    "$interpolate",
+    "$interval",
+    "$jsonpCallbacks",
+    "$locale",
+    "$location",
+    "$log",
+    "$parse",
+    "$q",
+    "$rootElement",
+    "$rootScope",
+    "$sce",
+    "$sceDelegate",
+    "$templateCache",
+    "$templateRequest",
+    "$timeout",
+    "$window",
+    "$xhrFactory",
+    "$injector",
+    "$provide",
+    // ディレクティブ / コントローラーのローカル注入
+    "$scope",
+    "$element",
+    "$attrs",
+    "$transclude",
+    // ngRoute
+    "$route",
+    "$routeParams",
+    // ngCookies
+    "$cookies",
+    // ngSanitize
+    "$sanitize",
+    // ngResource
+    "$resource",
+    // ngAria
+    "$aria",
+    // ngTouch
+    "$swipe",
+    // ui-router
+    "$state",
+    "$stateParams",
+    "$transitions",
+    "$urlRouter",
+    "$urlService",
+    "$uiRouter",
+    "$uiView",
+    // ngMaterial
+    "$mdBottomSheet",
+    "$mdDialog",
+    "$mdMedia",
+    "$mdPanel",
+    "$mdSidenav",
+    "$mdToast",
+    // ui-bootstrap
+    "$uibModal",
+    "$uibModalInstance",
+];
+
+/// 組み込みサービスか判定
+///
+/// `config()` ブロックで注入される `$httpProvider` のような provider 名は
+/// `Provider` を取り除いたサービス名で判定する。
+pub fn is_builtin_service(name: &str) -> bool {
+    let base = name.strip_suffix("Provider").unwrap_or(name);
+    NG_BUILTIN_SERVICES.contains(&base)
+}
@@ -208,6 +208,26 @@ angular.module('app', [])
     assert!(has_definition(&index, "app", SymbolKind::Module));
 }
 
+#[test]
+fn test_module_definition_records_dependencies() {
+    let index = analyze(
+        r#"
+angular.module('app', ['app.core', "ngRoute"]);
+angular.module('app.core', []);
+angular.module('app').controller('MainCtrl', function() {});
+"#,
+    );
+
+    let app = index.definitions.get_definitions("app");
+    assert_eq!(app.len(), 1);
+    assert_eq!(
+        app[0].dependencies.as_deref(),
+        Some(&["app.core".to_string(), "ngRoute".to_string()][..])
+    );
+    let core = index.definitions.get_definitions("app.core");
+    assert_eq!(core[0].dependencies.as_deref(), Some(&[][..]));
+}
+
 #[test]
 fn test_variable_holding_function_for_controller() {
     let index = analyze(
@@ -393,6 +413,103 @@ angular.module('app', [])
     assert!(has_definition(&index, "Fac1", SymbolKind::Factory));
 }
 
+#[test]
+fn test_module_getter_is_registered_as_reference() {
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+    let app_uri = Url::parse("file:///app.js").unwrap();
+    let ctrl_uri = Url::parse("file:///ctrl.js").unwrap();
+    analyzer.analyze_document(&app_uri, "angular.module('app', ['app.core']);\n");
+    analyzer.analyze_document(
+        &ctrl_uri,
+        "angular.module('app').controller('MainCtrl', function() {});\n",
+    );
+
+    // setter のみが定義、getter は別ファイルからの参照
+    let definitions = index.definitions.get_definitions("app");
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].uri, app_uri);
+    let references = index.definitions.get_references("app");
+    assert_eq!(references.len(), 1);
+    assert_eq!(references[0].uri, ctrl_uri);
+
+    // 依存配列内のモジュール名も参照
+    assert_eq!(index.definitions.get_references("app.core").len(), 1);
+
+    // getter 経由の登録もモジュールに紐付く
+    let ctrl = index.definitions.get_definitions("MainCtrl");
+    assert_eq!(ctrl[0].module.as_deref(), Some("app"));
+}
+
+#[test]
+fn test_angular_extend_scope_registers_properties() {
+    let index = analyze(
+        r#"
+angular.module('app', []).controller('ExtendCtrl', ['$scope', function($scope) {
+    $scope.title = 'x';
+    angular.extend($scope, {
+        count: 0,
+        title: 'y',
+        user: { name: '', age: 0 },
+        load: function(id) {},
+        save(item) {}
+    });
+    angular.merge(other, { ignored: 1 });
+}]);
+"#,
+    );
+
+    for name in ["count", "user", "user.name", "user.age"] {
+        assert!(
+            has_definition(&index, &format!("ExtendCtrl.$scope.{}", name), SymbolKind::ScopeProperty),
+            "{} should be a scope property",
+            name
+        );
+    }
+    assert!(has_definition(&index, "ExtendCtrl.$scope.load", SymbolKind::ScopeMethod));
+    assert!(has_definition(&index, "ExtendCtrl.$scope.save", SymbolKind::ScopeMethod));
+    let save = index.definitions.get_definitions("ExtendCtrl.$scope.save");
+    assert_eq!(save[0].parameters.as_deref(), Some(&["item".to_string()][..]));
+
+    // 既存の $scope.title はそのまま、extend 側のキーは参照になる
+    assert_eq!(index.definitions.get_definitions("ExtendCtrl.$scope.title").len(), 1);
+    assert_eq!(index.definitions.get_references("ExtendCtrl.$scope.title").len(), 1);
+
+    // 第1引数が $scope でなければ対象外
+    assert!(!index.definitions.has_definition("ExtendCtrl.$scope.ignored"));
+}
+
+#[test]
+fn test_bracket_assignment_with_string_key_is_definition() {
+    let index = analyze(
+        r#"
+angular.module('app', []).controller('BracketCtrl', ['$scope', function($scope) {
+    var vm = this;
+    var key = 'computed';
+    $scope['dynamicName'] = 1;
+    $scope["onSave"] = function(item) {};
+    $scope[key] = 2;
+    this['title'] = 'x';
+    vm['reload'] = function() {};
+}]);
+"#,
+    );
+
+    assert!(has_definition(&index, "BracketCtrl.$scope.dynamicName", SymbolKind::ScopeProperty));
+    assert!(has_definition(&index, "BracketCtrl.$scope.onSave", SymbolKind::ScopeMethod));
+    assert!(!index.definitions.has_definition("BracketCtrl.$scope.key"));
+    assert!(!index.definitions.has_definition("BracketCtrl.$scope.computed"));
+    assert!(has_definition(&index, "BracketCtrl.title", SymbolKind::Method));
+    assert!(has_definition(&index, "BracketCtrl.reload", SymbolKind::Method));
+
+    // 名前の範囲はクォートの内側
+    let dynamic = &index.definitions.get_definitions("BracketCtrl.$scope.dynamicName")[0];
+    assert_eq!(
+        (dynamic.name_span.start_line, dynamic.name_span.start_col, dynamic.name_span.end_col),
+        (4, 12, 23)
+    );
+}
+
 // ==========================================================================
 // $routeProvider.when() パターン
 // ==========================================================================
@@ -1260,3 +1377,29 @@ angular.module('myApp', ['ngRoute', 'myApp.services'])
         "UserService should be referenced from controllers"
     );
 }
+
+// ==========================================================================
+// パース失敗の記録
+// ==========================================================================
+
+#[test]
+fn test_parse_failure_is_recorded_and_cleared() {
+    let source = "angular.module('app', []).controller('MainCtrl', function($scope) {});";
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+    let uri = test_uri();
+    assert!(analyzer.analyze_document_incremental(&uri, source));
+    assert!(index.diagnostics.get_analysis_failure(&uri).is_none());
+
+    // パースに失敗したら記録し、前回の解析結果は残す
+    assert!(!analyzer.analyze_parsed(&uri, source, None));
+    assert_eq!(
+        index.diagnostics.get_analysis_failure(&uri),
+        Some(crate::model::AnalysisFailure::ParseFailed)
+    );
+    assert!(has_definition(&index, "MainCtrl", SymbolKind::Controller));
+
+    // 次に成功したら消える
+    assert!(analyzer.analyze_document_incremental(&uri, source));
+    assert!(index.diagnostics.get_analysis_failure(&uri).is_none());
+}
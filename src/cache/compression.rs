@@ -0,0 +1,154 @@
+//! Cache file header and compression
+//!
+//! Every binary cache file starts with a header:
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | 4     | magic `AJSC` |
+//! | 4     | schema version ([`CACHE_VERSION`], little endian) |
+//! | 1 + n | crate version (length-prefixed UTF-8) |
+//! | 1     | compression id |
+//!
+//! The loader rejects files written by another schema or crate version with
+//! [`CacheError::SchemaMismatch`] instead of deserializing them into a broken
+//! index, and detects the compression by itself so caches written with a
+//! different `cache.compression` setting still load.
+
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::config::CacheCompression;
+
+use super::error::CacheError;
+use super::metadata::CACHE_VERSION;
+
+const MAGIC: &[u8; 4] = b"AJSC";
+
+fn compression_id(compression: CacheCompression) -> u8 {
+    match compression {
+        CacheCompression::None => 0,
+        CacheCompression::Gzip => 1,
+        CacheCompression::Zstd => 2,
+    }
+}
+
+/// Prepend the header to `data`, compressing it with `compression`
+pub fn encode(data: &[u8], compression: CacheCompression) -> std::io::Result<Vec<u8>> {
+    encode_with_versions(data, compression, CACHE_VERSION, env!("CARGO_PKG_VERSION"))
+}
+
+fn encode_with_versions(
+    data: &[u8],
+    compression: CacheCompression,
+    schema_version: u32,
+    crate_version: &str,
+) -> std::io::Result<Vec<u8>> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&schema_version.to_le_bytes());
+    out.push(crate_version.len() as u8);
+    out.extend_from_slice(crate_version.as_bytes());
+    out.push(compression_id(compression));
+    match compression {
+        CacheCompression::None => out.extend_from_slice(data),
+        CacheCompression::Gzip => {
+            let mut encoder = GzEncoder::new(out, flate2::Compression::default());
+            encoder.write_all(data)?;
+            out = encoder.finish()?;
+        }
+        CacheCompression::Zstd => {
+            out.extend(zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?);
+        }
+    }
+    Ok(out)
+}
+
+/// Check the header and decompress according to the recorded compression
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, CacheError> {
+    let truncated = || CacheError::SchemaMismatch("missing or truncated cache header".to_string());
+    let rest = bytes.strip_prefix(MAGIC).ok_or_else(truncated)?;
+
+    let (schema_version, rest) = rest.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let schema_version = u32::from_le_bytes(*schema_version);
+    if schema_version != CACHE_VERSION {
+        return Err(CacheError::SchemaMismatch(format!(
+            "schema version {} (expected {})",
+            schema_version, CACHE_VERSION
+        )));
+    }
+
+    let (&version_len, rest) = rest.split_first().ok_or_else(truncated)?;
+    let (crate_version, rest) = rest
+        .split_at_checked(version_len as usize)
+        .ok_or_else(truncated)?;
+    if crate_version != env!("CARGO_PKG_VERSION").as_bytes() {
+        return Err(CacheError::SchemaMismatch(format!(
+            "written by version {} (running {})",
+            String::from_utf8_lossy(crate_version),
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+
+    let (&id, payload) = rest.split_first().ok_or_else(truncated)?;
+    match id {
+        0 => Ok(payload.to_vec()),
+        1 => {
+            let mut data = Vec::new();
+            GzDecoder::new(payload).read_to_end(&mut data)?;
+            Ok(data)
+        }
+        2 => Ok(zstd::decode_all(payload)?),
+        _ => Err(CacheError::Deserialize(format!("Unknown cache compression: {}", id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_with_each_compression() {
+        let data = b"angular.module('app').controller('Ctrl', function() {});".repeat(20);
+        for compression in [CacheCompression::None, CacheCompression::Gzip, CacheCompression::Zstd] {
+            let encoded = encode(&data, compression).unwrap();
+            assert_eq!(decode(&encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn compressed_data_is_smaller() {
+        let data = b"Ctrl.$scope.items".repeat(100);
+        let plain = encode(&data, CacheCompression::None).unwrap();
+        for compression in [CacheCompression::Gzip, CacheCompression::Zstd] {
+            assert!(encode(&data, compression).unwrap().len() < plain.len());
+        }
+    }
+
+    #[test]
+    fn missing_header_is_rejected() {
+        for bytes in [&b"\x01\x02\x03"[..], b"AJSC", b"AJSC\x01\x00"] {
+            assert!(matches!(decode(bytes), Err(CacheError::SchemaMismatch(_))));
+        }
+    }
+
+    #[test]
+    fn other_schema_or_crate_version_is_rejected() {
+        let version = env!("CARGO_PKG_VERSION");
+        let old_schema =
+            encode_with_versions(b"data", CacheCompression::None, CACHE_VERSION - 1, version).unwrap();
+        assert!(matches!(decode(&old_schema), Err(CacheError::SchemaMismatch(_))));
+
+        let old_crate =
+            encode_with_versions(b"data", CacheCompression::None, CACHE_VERSION, "0.0.1").unwrap();
+        assert!(matches!(decode(&old_crate), Err(CacheError::SchemaMismatch(_))));
+    }
+
+    #[test]
+    fn unknown_compression_is_rejected() {
+        let mut encoded = encode(b"data", CacheCompression::None).unwrap();
+        let id_pos = 4 + 4 + 1 + env!("CARGO_PKG_VERSION").len();
+        encoded[id_pos] = 9;
+        assert!(matches!(decode(&encoded), Err(CacheError::Deserialize(_))));
+    }
+}
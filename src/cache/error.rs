@@ -11,6 +11,12 @@ pub enum CacheError {
     #[error("Cache version mismatch")]
     VersionMismatch,
 
+    #[error("Cache was built with different analysis settings")]
+    ConfigMismatch,
+
+    #[error("Cache schema mismatch: {0}")]
+    SchemaMismatch(String),
+
     #[error("Cache not found")]
     NotFound,
 }
@@ -7,9 +7,11 @@ use tracing::{debug, info, warn};
 
 use crate::index::Index;
 
+use super::compression;
 use super::error::CacheError;
+use super::location::resolve_cache_dir;
 use super::metadata::{CacheMetadata, CACHE_VERSION};
-use super::schema::{CachedGlobalData, CachedSymbolData};
+use super::schema::{CachedGlobalData, CachedSymbolData, CHUNKS_DIR};
 
 /// Cache validation result
 pub struct CacheValidation {
@@ -20,19 +22,38 @@ pub struct CacheValidation {
 /// Cache loader
 pub struct CacheLoader {
     cache_dir: PathBuf,
+    config_hash: u64,
 }
 
 impl CacheLoader {
     pub fn new(workspace_root: &Path) -> Self {
+        Self::with_dir(resolve_cache_dir(workspace_root, None))
+    }
+
+    /// Read from `cache_dir` (see [`resolve_cache_dir`])
+    pub fn with_dir(cache_dir: PathBuf) -> Self {
         Self {
-            cache_dir: workspace_root.join(".angularjs-lsp/cache/v1"),
+            cache_dir,
+            config_hash: 0,
         }
     }
 
+    /// Treat the cache as stale unless it was written with `config_hash`
+    /// (see `AjsConfig::analysis_settings_hash`)
+    pub fn with_config_hash(mut self, config_hash: u64) -> Self {
+        self.config_hash = config_hash;
+        self
+    }
+
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
 
+    /// `cache_dir` にキャッシュが書き込まれているか
+    pub fn exists(&self) -> bool {
+        self.cache_dir.join("metadata.json").exists()
+    }
+
     /// Validate cache against current file metadata
     pub fn validate(
         &self,
@@ -54,6 +75,10 @@ impl CacheLoader {
             );
             return Err(CacheError::VersionMismatch);
         }
+        if metadata.config_hash != self.config_hash {
+            warn!("Cache was built with different analysis settings");
+            return Err(CacheError::ConfigMismatch);
+        }
 
         let mut valid_files = HashSet::new();
         let mut invalid_files = HashSet::new();
@@ -85,13 +110,24 @@ impl CacheLoader {
         index: &Index,
         valid_files: &HashSet<PathBuf>,
     ) -> Result<(), CacheError> {
-        let data_path = self.cache_dir.join("symbols.bin");
-        if !data_path.exists() {
+        let chunks_dir = self.cache_dir.join(CHUNKS_DIR);
+        if !chunks_dir.exists() {
             return Err(CacheError::NotFound);
         }
 
-        let data = fs::read(&data_path)?;
-        let cached_data: Vec<CachedSymbolData> = bincode::deserialize(&data)?;
+        let mut cached_data: Vec<CachedSymbolData> = Vec::new();
+        for chunk in fs::read_dir(&chunks_dir)? {
+            let path = chunk?.path();
+            // `*.bin.tmp` left behind by an interrupted write is not a chunk
+            if path.extension().is_none_or(|ext| ext != "bin") {
+                continue;
+            }
+            let data = compression::decode(&fs::read(&path)?)?;
+            cached_data.push(bincode::deserialize(&data)?);
+        }
+        // Read everything before touching the index so that a stale or corrupt
+        // file never leaves it half-populated
+        let global_data = self.read_global_data()?;
 
         let total_entries = cached_data.len();
         let total_definitions: usize = cached_data.iter().map(|e| e.definitions.len()).sum();
@@ -139,6 +175,10 @@ impl CacheLoader {
                 index.controllers.add_html_controller_scope(scope);
             }
 
+            for scope in entry.html_child_scopes {
+                index.controllers.add_html_child_scope(scope);
+            }
+
             for reference in entry.html_scope_references {
                 index.html.add_html_scope_reference(reference);
             }
@@ -158,10 +198,20 @@ impl CacheLoader {
             for reference in entry.html_directive_references {
                 index.html.add_html_directive_reference(reference);
             }
+
+            for target in entry.html_ng_model_targets {
+                index.html.add_ng_model_target(target);
+            }
+
+            for reference in entry.html_ui_sref_references {
+                index.html.add_ui_sref_reference(reference);
+            }
         }
 
         // Restore global data
-        self.load_global_data(index)?;
+        if let Some(global_data) = global_data {
+            Self::apply_global_data(index, global_data);
+        }
 
         info!(
             "Loaded {} definitions, {} html_scopes from cache (skipped {} entries, valid_files: {})",
@@ -170,15 +220,18 @@ impl CacheLoader {
         Ok(())
     }
 
-    fn load_global_data(&self, index: &Index) -> Result<(), CacheError> {
+    fn read_global_data(&self) -> Result<Option<CachedGlobalData>, CacheError> {
         let global_path = self.cache_dir.join("global.bin");
         if !global_path.exists() {
             debug!("No global cache file found");
-            return Ok(());
+            return Ok(None);
         }
 
-        let data = fs::read(&global_path)?;
-        let global_data: CachedGlobalData = bincode::deserialize(&data)?;
+        let data = compression::decode(&fs::read(&global_path)?)?;
+        Ok(Some(bincode::deserialize(&data)?))
+    }
+
+    fn apply_global_data(index: &Index, global_data: CachedGlobalData) {
 
         for binding in global_data.template_bindings {
             index.templates.add_template_binding(binding);
@@ -188,6 +241,10 @@ impl CacheLoader {
             index.templates.add_ng_include_binding_with_key(key, binding);
         }
 
+        for binding in global_data.ng_view_bindings {
+            index.templates.add_ng_view_binding(binding);
+        }
+
         let mut restored_interpolate = 0;
         for (uri_str, start, end) in global_data.interpolate_symbols {
             if let Ok(uri) = Url::parse(&uri_str) {
@@ -200,6 +257,5 @@ impl CacheLoader {
             "Loaded global data from cache ({} interpolate entries)",
             restored_interpolate
         );
-        Ok(())
     }
 }
@@ -0,0 +1,81 @@
+//! Cache directory resolution
+
+use std::path::{Path, PathBuf};
+
+use super::schema::stable_hash;
+
+/// Default cache directory, relative to the workspace root
+const DEFAULT_CACHE_DIR: &str = ".angularjs-lsp/cache/v1";
+
+/// Resolve the cache directory for a workspace
+///
+/// Without `configured` (ajsconfig.json `cache_dir`) the cache lives in the
+/// workspace. Otherwise `~` is expanded to the home directory, relative paths
+/// are resolved from the workspace root, and a hash of the root path is
+/// appended so several projects can share the same `cache_dir`.
+pub fn resolve_cache_dir(workspace_root: &Path, configured: Option<&str>) -> PathBuf {
+    let Some(configured) = configured else {
+        return workspace_root.join(DEFAULT_CACHE_DIR);
+    };
+    let base = expand_home(configured).unwrap_or_else(|| PathBuf::from(configured));
+    let base = if base.is_relative() {
+        workspace_root.join(base)
+    } else {
+        base
+    };
+    base.join(format!(
+        "{:016x}",
+        stable_hash(&workspace_root.to_string_lossy())
+    ))
+}
+
+/// Expand a leading `~` (`~` or `~/...`) to the home directory
+fn expand_home(path: &str) -> Option<PathBuf> {
+    let rest = path.strip_prefix('~')?;
+    if !(rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\')) {
+        return None;
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_dir_is_inside_workspace() {
+        let root = Path::new("/work/app");
+        assert_eq!(
+            resolve_cache_dir(root, None),
+            root.join(".angularjs-lsp/cache/v1")
+        );
+    }
+
+    #[test]
+    fn configured_dir_is_namespaced_by_root() {
+        let a = resolve_cache_dir(Path::new("/work/a"), Some("/var/cache/ajs"));
+        let b = resolve_cache_dir(Path::new("/work/b"), Some("/var/cache/ajs"));
+        assert!(a.starts_with("/var/cache/ajs"));
+        assert!(b.starts_with("/var/cache/ajs"));
+        assert_ne!(a, b);
+        assert_eq!(a, resolve_cache_dir(Path::new("/work/a"), Some("/var/cache/ajs")));
+    }
+
+    #[test]
+    fn relative_dir_is_resolved_from_root() {
+        let root = Path::new("/work/mono/packages/web");
+        let dir = resolve_cache_dir(root, Some("../../.cache"));
+        assert_eq!(dir.parent(), Some(root.join("../../.cache").as_path()));
+    }
+
+    #[test]
+    fn home_is_expanded() {
+        let Some(home) = std::env::var_os("HOME") else {
+            return;
+        };
+        let dir = resolve_cache_dir(Path::new("/work/app"), Some("~/.cache/angularjs-lsp"));
+        assert!(dir.starts_with(PathBuf::from(home).join(".cache/angularjs-lsp")));
+        assert_eq!(expand_home("~user/cache"), None);
+    }
+}
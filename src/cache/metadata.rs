@@ -4,13 +4,31 @@ use std::collections::HashMap;
 /// Cache format version
 /// v2: HTML cache support
 /// v3: `$interpolateProvider` 検出値の永続化 (CachedGlobalData.interpolate_symbols 追加)
-pub const CACHE_VERSION: u32 = 3;
+/// v4: Symbol.module (登録先モジュール名) 追加
+/// v5: ng-model ターゲット / ui-sref 参照 / ng-view バインディングの永続化
+/// v6: Symbol.restrict (ディレクティブの restrict 値) 追加
+/// v7: HtmlControllerScope.alias_span 追加
+/// v8: HtmlLocalVariableSource::NgRepeatAlias (ng-repeat の `as` エイリアス) 追加
+/// v9: HtmlFormBinding.field_names (フォーム配下のコントロール名) 追加
+/// v10: シンボルデータをファイル単位のチャンク (`chunks/*.bin`) に分割
+/// v11: バイナリファイル先頭に圧縮形式のヘッダを付与
+/// v12: バイナリファイルのヘッダにスキーマバージョンと crate バージョンを追加
+/// v13: Symbol.dependencies (モジュールの依存モジュール名) 追加
+/// v14: HtmlFormBinding.nested_form_names (入れ子フォームの名前) 追加
+/// v15: HtmlChildScope (ng-if / ng-repeat などが作る子スコープ) 追加
+/// v16: HtmlLocalVariable.scope_start_byte / scope_end_byte (スコープ要素のバイト範囲) 追加
+/// v17: Symbol.binding_type (component / ディレクティブのバインディングの値) 追加
+/// v18: CacheMetadata.config_hash (解析結果を左右する設定のハッシュ) 追加
+pub const CACHE_VERSION: u32 = 18;
 
 /// Cache metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheMetadata {
     pub version: u32,
     pub tool_version: String,
+    /// Hash of the config settings that affect analysis
+    /// (see `AjsConfig::analysis_settings_hash`)
+    pub config_hash: u64,
     pub files: HashMap<String, FileMetadata>,
 }
 
@@ -22,10 +40,11 @@ pub struct FileMetadata {
 }
 
 impl CacheMetadata {
-    pub fn new() -> Self {
+    pub fn new(config_hash: u64) -> Self {
         Self {
             version: CACHE_VERSION,
             tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash,
             files: HashMap::new(),
         }
     }
@@ -34,9 +53,3 @@ impl CacheMetadata {
         self.version == CACHE_VERSION && self.tool_version == env!("CARGO_PKG_VERSION")
     }
 }
-
-impl Default for CacheMetadata {
-    fn default() -> Self {
-        Self::new()
-    }
-}
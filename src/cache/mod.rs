@@ -1,9 +1,12 @@
+pub mod compression;
 pub mod error;
 pub mod loader;
+pub mod location;
 pub mod metadata;
 pub mod schema;
 pub mod writer;
 
 pub use loader::CacheLoader;
+pub use location::resolve_cache_dir;
 pub use metadata::FileMetadata;
 pub use writer::CacheWriter;
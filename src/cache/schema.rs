@@ -1,12 +1,18 @@
 use serde::{Deserialize, Serialize};
 
 use crate::model::{
-    HtmlControllerScope, HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable,
-    HtmlLocalVariableReference, HtmlScopeReference, NgIncludeBinding, Symbol, SymbolReference,
-    ControllerScope, TemplateBinding,
+    HtmlChildScope, HtmlControllerScope, HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable,
+    HtmlLocalVariableReference, HtmlNgModelTarget, HtmlScopeReference, HtmlUiSrefReference,
+    NgIncludeBinding, NgViewBinding, Symbol, SymbolReference, ControllerScope, TemplateBinding,
 };
 
+/// Directory (under the cache dir) holding one chunk file per source file
+pub const CHUNKS_DIR: &str = "chunks";
+
 /// Cached per-file symbol data
+///
+/// Each entry is stored in its own chunk file (see [`chunk_file_name`]) so that
+/// saving a single file only rewrites that file's chunk.
 #[derive(Serialize, Deserialize)]
 pub struct CachedSymbolData {
     pub uri: String,
@@ -16,6 +22,8 @@ pub struct CachedSymbolData {
     #[serde(default)]
     pub html_controller_scopes: Vec<HtmlControllerScope>,
     #[serde(default)]
+    pub html_child_scopes: Vec<HtmlChildScope>,
+    #[serde(default)]
     pub html_scope_references: Vec<HtmlScopeReference>,
     #[serde(default)]
     pub html_local_variables: Vec<HtmlLocalVariable>,
@@ -25,6 +33,10 @@ pub struct CachedSymbolData {
     pub html_form_bindings: Vec<HtmlFormBinding>,
     #[serde(default)]
     pub html_directive_references: Vec<HtmlDirectiveReference>,
+    #[serde(default)]
+    pub html_ng_model_targets: Vec<HtmlNgModelTarget>,
+    #[serde(default)]
+    pub html_ui_sref_references: Vec<HtmlUiSrefReference>,
 }
 
 /// Cached global data (not file-specific)
@@ -32,6 +44,10 @@ pub struct CachedSymbolData {
 pub struct CachedGlobalData {
     pub template_bindings: Vec<TemplateBinding>,
     pub ng_include_bindings: Vec<(String, NgIncludeBinding)>,
+    /// ng-view 位置の継承情報。`ng_include_bindings` に保存される
+    /// `ng-view##<template>` の仮想バインディングの元データで、
+    /// `$routeProvider` テンプレートの継承コントローラー解決に使う。
+    pub ng_view_bindings: Vec<NgViewBinding>,
     /// JS から検出された `$interpolateProvider.startSymbol/endSymbol` の値を
     /// URI 単位で永続化する。`(uri_str, start_symbol, end_symbol)` の Vec。
     /// 各 URI で start/end どちらか片方だけ宣言されているケースもあり得るので
@@ -42,3 +58,15 @@ pub struct CachedGlobalData {
     /// デフォルト `{{ }}` で動いてしまう。
     pub interpolate_symbols: Vec<(String, Option<String>, Option<String>)>,
 }
+
+/// Chunk file name for a source file URI
+pub fn chunk_file_name(uri: &str) -> String {
+    format!("{:016x}.bin", stable_hash(uri))
+}
+
+/// 64-bit FNV-1a hash, stable across runs and toolchains (unlike `DefaultHasher`)
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
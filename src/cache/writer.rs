@@ -1,26 +1,91 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 
+use tower_lsp::lsp_types::Url;
 use tracing::{debug, info};
 
+use crate::config::CacheCompression;
 use crate::index::Index;
 
+use super::compression;
+use super::location::resolve_cache_dir;
 use super::metadata::{CacheMetadata, FileMetadata};
-use super::schema::{CachedGlobalData, CachedSymbolData};
+use super::schema::{chunk_file_name, CachedGlobalData, CachedSymbolData, CHUNKS_DIR};
+
+/// Serializes every cache write in the process
+///
+/// Writers are created per save, so a full save (which removes the chunk
+/// directory) could otherwise run while an incremental save is rewriting
+/// the metadata and chunks of the same cache.
+static CACHE_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock_cache_writes() -> MutexGuard<'static, ()> {
+    CACHE_WRITE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Write `data` to a temp file next to `path` and rename it into place,
+/// so readers never see a partially written file
+fn write_atomic(path: &Path, data: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Remove temp files that an interrupted [`write_atomic`] left in `dir`
+///
+/// Must be called with the cache write lock held, so no write of this
+/// process is in progress.
+fn remove_stale_temp_files(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "tmp") {
+            debug!("Removing stale cache temp file: {}", path.display());
+            let _ = fs::remove_file(path);
+        }
+    }
+}
 
 /// Cache writer
 pub struct CacheWriter {
     cache_dir: PathBuf,
+    compression: CacheCompression,
+    config_hash: u64,
 }
 
 impl CacheWriter {
     pub fn new(workspace_root: &Path) -> Self {
+        Self::with_dir(resolve_cache_dir(workspace_root, None))
+    }
+
+    /// Write to `cache_dir` (see [`resolve_cache_dir`])
+    pub fn with_dir(cache_dir: PathBuf) -> Self {
         Self {
-            cache_dir: workspace_root.join(".angularjs-lsp/cache/v1"),
+            cache_dir,
+            compression: CacheCompression::None,
+            config_hash: 0,
         }
     }
 
+    /// Compress chunk and global files with `compression`
+    pub fn with_compression(mut self, compression: CacheCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Record `config_hash` (see `AjsConfig::analysis_settings_hash`) in the metadata
+    pub fn with_config_hash(mut self, config_hash: u64) -> Self {
+        self.config_hash = config_hash;
+        self
+    }
+
     fn ensure_cache_dir(&self) -> std::io::Result<()> {
         if !self.cache_dir.exists() {
             fs::create_dir_all(&self.cache_dir)?;
@@ -35,11 +100,14 @@ impl CacheWriter {
             references: Vec::new(),
             controller_scopes: Vec::new(),
             html_controller_scopes: Vec::new(),
+            html_child_scopes: Vec::new(),
             html_scope_references: Vec::new(),
             html_local_variables: Vec::new(),
             html_local_variable_references: Vec::new(),
             html_form_bindings: Vec::new(),
             html_directive_references: Vec::new(),
+            html_ng_model_targets: Vec::new(),
+            html_ui_sref_references: Vec::new(),
         }
     }
 
@@ -49,132 +117,240 @@ impl CacheWriter {
         index: &Index,
         file_metadata: &HashMap<PathBuf, FileMetadata>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let _guard = lock_cache_writes();
         self.ensure_cache_dir()?;
+        remove_stale_temp_files(&self.cache_dir);
 
         // Save metadata
-        let mut metadata = CacheMetadata::new();
+        let mut metadata = CacheMetadata::new(self.config_hash);
         for (path, meta) in file_metadata {
             metadata
                 .files
                 .insert(path.to_string_lossy().to_string(), meta.clone());
         }
+        self.write_metadata(&metadata)?;
+
+        // Rewrite all chunks (stale chunks of deleted files are dropped)
+        let chunks_dir = self.cache_dir.join(CHUNKS_DIR);
+        if chunks_dir.exists() {
+            fs::remove_dir_all(&chunks_dir)?;
+        }
+        fs::create_dir_all(&chunks_dir)?;
+        // Single-file format used before chunks (no longer read)
+        let legacy_path = self.cache_dir.join("symbols.bin");
+        if legacy_path.exists() {
+            fs::remove_file(legacy_path)?;
+        }
+
+        let cached_data: Vec<CachedSymbolData> =
+            Self::collect_file_data(index).into_values().collect();
+        let mut total_bytes = 0;
+        for entry in &cached_data {
+            total_bytes += self.write_chunk(entry)?;
+        }
+
+        // Save global data
+        self.save_global_data(index)?;
+
+        let html_scopes: usize = cached_data
+            .iter()
+            .map(|e| e.html_controller_scopes.len())
+            .sum();
+        let html_refs: usize = cached_data
+            .iter()
+            .map(|e| e.html_scope_references.len())
+            .sum();
+
+        info!(
+            "Saved cache: {} files, {} bytes, {} html_scopes, {} html_refs",
+            metadata.files.len(),
+            total_bytes,
+            html_scopes,
+            html_refs
+        );
+
+        Ok(())
+    }
+
+    /// Save a single file's entries to an existing cache
+    ///
+    /// Only the file's metadata entry and chunk are replaced (the chunk is
+    /// removed when the file no longer has any entries); global data is
+    /// rewritten since it is not split per file. Fails when no compatible
+    /// cache exists yet, in which case the caller should fall back to
+    /// [`save_full`](Self::save_full).
+    pub fn save_incremental(
+        &self,
+        uri: &Url,
+        index: &Index,
+        file_metadata: &FileMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = uri
+            .to_file_path()
+            .map_err(|_| format!("Not a file URI: {}", uri))?;
+
+        let _guard = lock_cache_writes();
+        remove_stale_temp_files(&self.cache_dir);
+        remove_stale_temp_files(&self.cache_dir.join(CHUNKS_DIR));
+        let metadata_content = fs::read_to_string(self.cache_dir.join("metadata.json"))?;
+        let mut metadata: CacheMetadata = serde_json::from_str(&metadata_content)?;
+        if !metadata.is_compatible() {
+            return Err("Cache version mismatch".into());
+        }
+        if metadata.config_hash != self.config_hash {
+            return Err("Cache was built with different analysis settings".into());
+        }
+        metadata
+            .files
+            .insert(path.to_string_lossy().to_string(), file_metadata.clone());
+
+        let uri_str = uri.to_string();
+        let chunks_dir = self.cache_dir.join(CHUNKS_DIR);
+        fs::create_dir_all(&chunks_dir)?;
+        match Self::collect_uri_data(index, uri) {
+            Some(entry) => {
+                self.write_chunk(&entry)?;
+            }
+            None => {
+                let chunk_path = chunks_dir.join(chunk_file_name(&uri_str));
+                if chunk_path.exists() {
+                    fs::remove_file(chunk_path)?;
+                }
+            }
+        }
+
+        self.save_global_data(index)?;
+        self.write_metadata(&metadata)?;
 
+        debug!("Saved cache incrementally: {}", uri_str);
+        Ok(())
+    }
+
+    fn write_metadata(&self, metadata: &CacheMetadata) -> Result<(), Box<dyn std::error::Error>> {
         let metadata_path = self.cache_dir.join("metadata.json");
-        let metadata_json = serde_json::to_string_pretty(&metadata)?;
-        fs::write(&metadata_path, metadata_json)?;
+        let metadata_json = serde_json::to_string_pretty(metadata)?;
+        write_atomic(&metadata_path, metadata_json)?;
+        Ok(())
+    }
+
+    /// Write one file's chunk and return its size in bytes
+    fn write_chunk(&self, entry: &CachedSymbolData) -> Result<usize, Box<dyn std::error::Error>> {
+        let data = compression::encode(&bincode::serialize(entry)?, self.compression)?;
+        let chunk_path = self
+            .cache_dir
+            .join(CHUNKS_DIR)
+            .join(chunk_file_name(&entry.uri));
+        write_atomic(&chunk_path, &data)?;
+        Ok(data.len())
+    }
+
+    /// Entry of `uri` in `file_data`
+    fn file_entry<'a>(
+        file_data: &'a mut HashMap<String, CachedSymbolData>,
+        uri: &Url,
+    ) -> &'a mut CachedSymbolData {
+        let uri_str = uri.to_string();
+        file_data
+            .entry(uri_str.clone())
+            .or_insert_with(|| Self::empty_cached_data(uri_str))
+    }
 
-        // Collect symbol data grouped by file
+    /// Collect the symbol data of a single file through the stores' per-URI
+    /// accessors, or `None` when the file has no entries
+    fn collect_uri_data(index: &Index, uri: &Url) -> Option<CachedSymbolData> {
+        let entry = CachedSymbolData {
+            uri: uri.to_string(),
+            definitions: index.definitions.get_definitions_for_uri(uri),
+            references: index.definitions.get_references_for_uri(uri),
+            controller_scopes: index.controllers.get_controller_scopes_for_uri(uri),
+            html_controller_scopes: index.controllers.get_all_html_controller_scopes(uri),
+            html_child_scopes: index.controllers.get_html_child_scopes_for_uri(uri),
+            html_scope_references: index.html.get_html_scope_references(uri),
+            html_local_variables: index.html.get_all_local_variables(uri),
+            html_local_variable_references: index
+                .html
+                .get_all_local_variable_references_for_uri(uri),
+            html_form_bindings: index.html.get_all_form_bindings(uri),
+            html_directive_references: index.html.get_all_directive_references_for_uri(uri),
+            html_ng_model_targets: index.html.get_ng_model_targets_for_uri(uri),
+            html_ui_sref_references: index.html.get_ui_sref_references_for_uri(uri),
+        };
+        let is_empty = entry.definitions.is_empty()
+            && entry.references.is_empty()
+            && entry.controller_scopes.is_empty()
+            && entry.html_controller_scopes.is_empty()
+            && entry.html_child_scopes.is_empty()
+            && entry.html_scope_references.is_empty()
+            && entry.html_local_variables.is_empty()
+            && entry.html_local_variable_references.is_empty()
+            && entry.html_form_bindings.is_empty()
+            && entry.html_directive_references.is_empty()
+            && entry.html_ng_model_targets.is_empty()
+            && entry.html_ui_sref_references.is_empty();
+        (!is_empty).then_some(entry)
+    }
+
+    /// Collect symbol data grouped by file
+    fn collect_file_data(index: &Index) -> HashMap<String, CachedSymbolData> {
         let mut file_data: HashMap<String, CachedSymbolData> = HashMap::new();
 
         for symbol in index.definitions.get_all_definitions() {
-            let uri_str = symbol.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .definitions
-                .push(symbol);
+            Self::file_entry(&mut file_data, &symbol.uri).definitions.push(symbol);
         }
 
         for symbol in index.definitions.get_all_definitions() {
             for reference in index.definitions.get_references(&symbol.name) {
-                let uri_str = reference.uri.to_string();
-                file_data
-                    .entry(uri_str.clone())
-                    .or_insert_with(|| Self::empty_cached_data(uri_str))
-                    .references
-                    .push(reference);
+                Self::file_entry(&mut file_data, &reference.uri).references.push(reference);
             }
         }
 
         for scope in index.controllers.get_all_controller_scopes() {
-            let uri_str = scope.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .controller_scopes
-                .push(scope);
+            Self::file_entry(&mut file_data, &scope.uri).controller_scopes.push(scope);
         }
 
         for scope in index.controllers.get_all_html_controller_scopes_for_cache() {
-            let uri_str = scope.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .html_controller_scopes
-                .push(scope);
+            Self::file_entry(&mut file_data, &scope.uri).html_controller_scopes.push(scope);
+        }
+
+        for scope in index.controllers.get_all_html_child_scopes_for_cache() {
+            Self::file_entry(&mut file_data, &scope.uri).html_child_scopes.push(scope);
         }
 
         for reference in index.html.get_all_html_scope_references_for_cache() {
-            let uri_str = reference.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .html_scope_references
-                .push(reference);
+            Self::file_entry(&mut file_data, &reference.uri).html_scope_references.push(reference);
         }
 
         for variable in index.html.get_all_html_local_variables_for_cache() {
-            let uri_str = variable.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .html_local_variables
-                .push(variable);
+            Self::file_entry(&mut file_data, &variable.uri).html_local_variables.push(variable);
         }
 
         for reference in index.html.get_all_html_local_variable_references_for_cache() {
-            let uri_str = reference.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
+            Self::file_entry(&mut file_data, &reference.uri)
                 .html_local_variable_references
                 .push(reference);
         }
 
         for binding in index.html.get_all_html_form_bindings_for_cache() {
-            let uri_str = binding.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
-                .html_form_bindings
-                .push(binding);
+            Self::file_entry(&mut file_data, &binding.uri).html_form_bindings.push(binding);
         }
 
         for reference in index.html.get_all_html_directive_references_for_cache() {
-            let uri_str = reference.uri.to_string();
-            file_data
-                .entry(uri_str.clone())
-                .or_insert_with(|| Self::empty_cached_data(uri_str))
+            Self::file_entry(&mut file_data, &reference.uri)
                 .html_directive_references
                 .push(reference);
         }
 
-        let cached_data: Vec<CachedSymbolData> = file_data.into_values().collect();
-        let data = bincode::serialize(&cached_data)?;
-        let data_path = self.cache_dir.join("symbols.bin");
-        fs::write(&data_path, &data)?;
-
-        // Save global data
-        self.save_global_data(index)?;
-
-        let html_scopes: usize = cached_data
-            .iter()
-            .map(|e| e.html_controller_scopes.len())
-            .sum();
-        let html_refs: usize = cached_data
-            .iter()
-            .map(|e| e.html_scope_references.len())
-            .sum();
+        for target in index.html.get_all_ng_model_targets_for_cache() {
+            Self::file_entry(&mut file_data, &target.uri).html_ng_model_targets.push(target);
+        }
 
-        info!(
-            "Saved cache: {} files, {} bytes, {} html_scopes, {} html_refs",
-            metadata.files.len(),
-            data.len(),
-            html_scopes,
-            html_refs
-        );
+        for reference in index.html.get_all_ui_sref_references_for_cache() {
+            Self::file_entry(&mut file_data, &reference.uri)
+                .html_ui_sref_references
+                .push(reference);
+        }
 
-        Ok(())
+        file_data
     }
 
     fn save_global_data(&self, index: &Index) -> Result<(), Box<dyn std::error::Error>> {
@@ -189,17 +365,19 @@ impl CacheWriter {
         let global_data = CachedGlobalData {
             template_bindings: index.templates.get_all_template_bindings(),
             ng_include_bindings: index.templates.get_all_ng_include_bindings(),
+            ng_view_bindings: index.templates.get_all_ng_view_bindings(),
             interpolate_symbols,
         };
 
-        let data = bincode::serialize(&global_data)?;
+        let data = compression::encode(&bincode::serialize(&global_data)?, self.compression)?;
         let global_path = self.cache_dir.join("global.bin");
-        fs::write(&global_path, data)?;
+        write_atomic(&global_path, data)?;
 
         debug!(
-            "Saved global cache: {} template_bindings, {} ng_include_bindings, {} interpolate_symbols",
+            "Saved global cache: {} template_bindings, {} ng_include_bindings, {} ng_view_bindings, {} interpolate_symbols",
             global_data.template_bindings.len(),
             global_data.ng_include_bindings.len(),
+            global_data.ng_view_bindings.len(),
             global_data.interpolate_symbols.len()
         );
 
@@ -212,9 +390,11 @@ mod tests {
     use super::*;
     use std::collections::HashSet;
     use tempfile::TempDir;
-    use tower_lsp::lsp_types::Url;
 
     use crate::cache::loader::CacheLoader;
+    use crate::model::{
+        HtmlNgModelTarget, HtmlUiSrefReference, NgViewBinding, SymbolBuilder, SymbolKind,
+    };
 
     /// `$interpolateProvider` 検出値を save → load で復元できることを確認。
     /// これがないとカスタム interpolate 記号を使うプロジェクトで cache hit 起動時に
@@ -287,4 +467,287 @@ mod tests {
             ("{{".to_string(), "}}".to_string())
         );
     }
+
+    /// Pass 3 でしか収集されない HTML データ (ng-model ターゲット / ui-sref 参照 /
+    /// ng-view バインディング) も save → load で復元され、cache hit 時に
+    /// HTML を再スキャンしなくて済むことを確認。
+    #[test]
+    fn html_pass_data_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let workspace_root = tmp.path();
+        let html_path = workspace_root.join("index.html");
+        let html_uri = Url::from_file_path(&html_path).unwrap();
+
+        let original = Index::new();
+        original.html.add_ng_model_target(HtmlNgModelTarget {
+            property_path: "vm.query".to_string(),
+            uri: html_uri.clone(),
+            start_line: 3,
+            start_col: 18,
+            end_line: 3,
+            end_col: 26,
+        });
+        original.html.add_ui_sref_reference(HtmlUiSrefReference {
+            state_name: "home.detail".to_string(),
+            uri: html_uri.clone(),
+            start_line: 5,
+            start_col: 16,
+            end_line: 5,
+            end_col: 27,
+        });
+        original.templates.add_ng_view_binding(NgViewBinding {
+            parent_uri: html_uri.clone(),
+            line: 7,
+            inherited_controllers: vec!["MainCtrl".to_string()],
+            inherited_local_variables: Vec::new(),
+            inherited_form_bindings: Vec::new(),
+        });
+
+        let writer = CacheWriter::new(workspace_root);
+        writer.save_full(&original, &HashMap::new()).unwrap();
+
+        let restored = Index::new();
+        let loader = CacheLoader::new(workspace_root);
+        let valid_files: HashSet<PathBuf> = [html_path].into_iter().collect();
+        loader.load(&restored, &valid_files).unwrap();
+
+        let targets = restored.html.get_ng_model_targets_for_uri(&html_uri);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].property_path, "vm.query");
+
+        let srefs = restored.html.get_ui_sref_references_by_state("home.detail");
+        assert_eq!(srefs.len(), 1);
+        assert_eq!(srefs[0].span(), original.html.get_ui_sref_references_for_uri(&html_uri)[0].span());
+
+        let views = restored.templates.get_all_ng_view_bindings();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].inherited_controllers, vec!["MainCtrl".to_string()]);
+    }
+
+    /// 保存時の増分更新は対象ファイルのチャンクとメタデータだけを差し替え、
+    /// 他のファイルのエントリはそのまま残ることを確認。
+    #[test]
+    fn incremental_save_replaces_only_saved_file() {
+        let tmp = TempDir::new().unwrap();
+        let workspace_root = tmp.path();
+        let path_a = workspace_root.join("a.js");
+        let path_b = workspace_root.join("b.js");
+        let uri_a = Url::from_file_path(&path_a).unwrap();
+        let uri_b = Url::from_file_path(&path_b).unwrap();
+        let meta = |mtime| FileMetadata { mtime, size: 10 };
+
+        let index = Index::new();
+        for (name, uri) in [("ACtrl", &uri_a), ("BCtrl", &uri_b)] {
+            index.definitions.add_definition(
+                SymbolBuilder::new(name.to_string(), SymbolKind::Controller, uri.clone()).build(),
+            );
+        }
+        let writer = CacheWriter::new(workspace_root);
+        let file_metadata: HashMap<PathBuf, FileMetadata> =
+            [(path_a.clone(), meta(1)), (path_b.clone(), meta(1))].into_iter().collect();
+        writer.save_full(&index, &file_metadata).unwrap();
+
+        // a.js を編集して保存した想定
+        index.definitions.clear_document(&uri_a);
+        index.definitions.add_definition(
+            SymbolBuilder::new("RenamedCtrl".to_string(), SymbolKind::Controller, uri_a.clone())
+                .build(),
+        );
+        writer.save_incremental(&uri_a, &index, &meta(2)).unwrap();
+
+        let loader = CacheLoader::new(workspace_root);
+        let validation = loader
+            .validate(&[(path_a.clone(), 2, 10), (path_b.clone(), 1, 10)])
+            .unwrap();
+        assert_eq!(validation.valid_files.len(), 2);
+
+        let restored = Index::new();
+        loader.load(&restored, &validation.valid_files).unwrap();
+        assert!(restored.definitions.has_definition("RenamedCtrl"));
+        assert!(!restored.definitions.has_definition("ACtrl"));
+        assert!(restored.definitions.has_definition("BCtrl"));
+
+        // 定義がなくなったファイルはチャンクごと削除される
+        index.definitions.clear_document(&uri_a);
+        writer.save_incremental(&uri_a, &index, &meta(3)).unwrap();
+        let restored = Index::new();
+        loader.load(&restored, &validation.valid_files).unwrap();
+        assert!(!restored.definitions.has_definition("RenamedCtrl"));
+        assert!(restored.definitions.has_definition("BCtrl"));
+    }
+
+    /// 圧縮形式はヘッダから自動判定されるため、設定と無関係にロードできることを確認。
+    #[test]
+    fn compressed_cache_round_trip() {
+        for compression in [CacheCompression::Gzip, CacheCompression::Zstd] {
+            let tmp = TempDir::new().unwrap();
+            let path = tmp.path().join("app.js");
+            let uri = Url::from_file_path(&path).unwrap();
+
+            let original = Index::new();
+            original.definitions.add_definition(
+                SymbolBuilder::new("MainCtrl".to_string(), SymbolKind::Controller, uri.clone())
+                    .build(),
+            );
+            original
+                .interpolate
+                .set_start_symbol(uri.clone(), "[[".to_string());
+            CacheWriter::new(tmp.path())
+                .with_compression(compression)
+                .save_full(&original, &HashMap::new())
+                .unwrap();
+
+            let restored = Index::new();
+            let valid_files: HashSet<PathBuf> = [path].into_iter().collect();
+            CacheLoader::new(tmp.path())
+                .load(&restored, &valid_files)
+                .unwrap();
+            assert!(restored.definitions.has_definition("MainCtrl"));
+            assert_eq!(restored.interpolate.resolved().0, "[[".to_string());
+        }
+    }
+
+    /// 旧スキーマのファイルが混ざっていたら SchemaMismatch で失敗し、
+    /// インデックスには何も読み込まれないことを確認。
+    #[test]
+    fn schema_mismatch_leaves_index_untouched() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("app.js");
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let original = Index::new();
+        original.definitions.add_definition(
+            SymbolBuilder::new("MainCtrl".to_string(), SymbolKind::Controller, uri).build(),
+        );
+        CacheWriter::new(tmp.path())
+            .save_full(&original, &HashMap::new())
+            .unwrap();
+
+        // 旧スキーマで書かれた global.bin に差し替える
+        let loader = CacheLoader::new(tmp.path());
+        let mut stale = b"AJSC".to_vec();
+        stale.extend_from_slice(&(crate::cache::metadata::CACHE_VERSION - 1).to_le_bytes());
+        fs::write(loader.cache_dir().join("global.bin"), stale).unwrap();
+
+        let restored = Index::new();
+        let valid_files: HashSet<PathBuf> = [path].into_iter().collect();
+        let result = loader.load(&restored, &valid_files);
+        assert!(matches!(result, Err(crate::cache::error::CacheError::SchemaMismatch(_))));
+        assert!(!restored.definitions.has_definition("MainCtrl"));
+    }
+
+    /// 全体保存と増分保存が並行しても書き込みは直列化され、
+    /// 一時ファイルを残さず読み込める状態で終わることを確認。
+    #[test]
+    fn concurrent_saves_leave_consistent_cache() {
+        let tmp = TempDir::new().unwrap();
+        let workspace_root = tmp.path();
+        let path = workspace_root.join("a.js");
+        let uri = Url::from_file_path(&path).unwrap();
+        let meta = FileMetadata { mtime: 1, size: 10 };
+
+        let index = Index::new();
+        index.definitions.add_definition(
+            SymbolBuilder::new("MainCtrl".to_string(), SymbolKind::Controller, uri.clone()).build(),
+        );
+        let file_metadata: HashMap<PathBuf, FileMetadata> =
+            [(path.clone(), meta.clone())].into_iter().collect();
+        CacheWriter::new(workspace_root)
+            .save_full(&index, &file_metadata)
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (index, uri, meta, file_metadata) = (&index, &uri, &meta, &file_metadata);
+                scope.spawn(move || {
+                    let writer = CacheWriter::new(workspace_root);
+                    if i % 2 == 0 {
+                        writer.save_full(index, file_metadata).unwrap();
+                    } else {
+                        writer.save_incremental(uri, index, meta).unwrap();
+                    }
+                });
+            }
+        });
+
+        let loader = CacheLoader::new(workspace_root);
+        let leftovers = fs::read_dir(loader.cache_dir())
+            .unwrap()
+            .chain(fs::read_dir(loader.cache_dir().join(CHUNKS_DIR)).unwrap())
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let validation = loader.validate(&[(path, 1, 10)]).unwrap();
+        let restored = Index::new();
+        loader.load(&restored, &validation.valid_files).unwrap();
+        assert!(restored.definitions.has_definition("MainCtrl"));
+    }
+
+    /// 中断された書き込みが残した `*.tmp` はロード時に無視され、
+    /// 次の保存で削除されることを確認。
+    #[test]
+    fn stale_temp_files_are_ignored_and_removed() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("a.js");
+        let uri = Url::from_file_path(&path).unwrap();
+        let meta = FileMetadata { mtime: 1, size: 10 };
+
+        let index = Index::new();
+        index.definitions.add_definition(
+            SymbolBuilder::new("MainCtrl".to_string(), SymbolKind::Controller, uri.clone()).build(),
+        );
+        let file_metadata: HashMap<PathBuf, FileMetadata> =
+            [(path.clone(), meta.clone())].into_iter().collect();
+        let writer = CacheWriter::new(tmp.path());
+        writer.save_full(&index, &file_metadata).unwrap();
+
+        let loader = CacheLoader::new(tmp.path());
+        let chunks_dir = loader.cache_dir().join(CHUNKS_DIR);
+        let stale_chunk = chunks_dir.join(format!("{}.tmp", chunk_file_name(uri.as_str())));
+        let stale_metadata = loader.cache_dir().join("metadata.json.tmp");
+        fs::write(&stale_chunk, b"partial").unwrap();
+        fs::write(&stale_metadata, b"{").unwrap();
+
+        let validation = loader.validate(&[(path, 1, 10)]).unwrap();
+        let restored = Index::new();
+        loader.load(&restored, &validation.valid_files).unwrap();
+        assert!(restored.definitions.has_definition("MainCtrl"));
+
+        writer.save_incremental(&uri, &index, &meta).unwrap();
+        assert!(!stale_chunk.exists());
+        assert!(!stale_metadata.exists());
+    }
+
+    /// 解析設定が変わっていたらキャッシュは無効になり、増分保存もできないことを確認。
+    #[test]
+    fn config_hash_mismatch_invalidates_cache() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("a.js");
+        let uri = Url::from_file_path(&path).unwrap();
+        CacheWriter::new(tmp.path())
+            .with_config_hash(1)
+            .save_full(&Index::new(), &HashMap::new())
+            .unwrap();
+
+        let files = [(path, 1, 10)];
+        let loader = CacheLoader::new(tmp.path());
+        assert!(loader.with_config_hash(1).validate(&files).is_ok());
+        let result = CacheLoader::new(tmp.path()).with_config_hash(2).validate(&files);
+        assert!(matches!(result, Err(crate::cache::error::CacheError::ConfigMismatch)));
+
+        let meta = FileMetadata { mtime: 1, size: 10 };
+        let writer = CacheWriter::new(tmp.path()).with_config_hash(2);
+        assert!(writer.save_incremental(&uri, &Index::new(), &meta).is_err());
+    }
+
+    #[test]
+    fn incremental_save_requires_existing_cache() {
+        let tmp = TempDir::new().unwrap();
+        let uri = Url::from_file_path(tmp.path().join("a.js")).unwrap();
+        let writer = CacheWriter::new(tmp.path());
+        let meta = FileMetadata { mtime: 1, size: 1 };
+        assert!(writer.save_incremental(&uri, &Index::new(), &meta).is_err());
+    }
 }
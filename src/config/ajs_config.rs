@@ -1,8 +1,12 @@
+use std::borrow::Cow;
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::Path;
 
 use serde::Deserialize;
 
+use super::file_limits::FileLimits;
+use crate::analyzer::html::directives::normalize_directive_attr;
 use super::path_matcher::PathMatcher;
 
 /// ajsconfig.json の設定
@@ -12,7 +16,7 @@ use super::path_matcher::PathMatcher;
 /// `.endSymbol(...)` から動的に解決するため当該フィールドは廃止した。
 /// 古い `ajsconfig.json` に `interpolate` フィールドが残っていても、`serde` の
 /// 標準動作で未知フィールドとして黙って無視される。
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct AjsConfig {
     /// 解析対象のglobパターン（空の場合は全ファイル対象）
     #[serde(default)]
@@ -20,16 +24,158 @@ pub struct AjsConfig {
     /// 除外対象のglobパターン
     #[serde(default = "default_exclude")]
     pub exclude: Vec<String>,
-    /// キャッシュ機能を有効にする（デフォルト: false）
+    /// 定義だけを収集し、参照は収集しないファイルのglobパターン
+    /// (巨大な legacy ディレクトリなどのインデックス軽量化用。開いたファイルは通常どおり解析する)
     #[serde(default)]
-    pub cache: bool,
+    pub definitions_only: Vec<String>,
+    /// キャッシュ設定（デフォルト: 無効）
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// キャッシュの保存先（未指定ならワークスペース直下の `.angularjs-lsp/cache/`）
+    /// 相対パスはワークスペースルート基準、`~` はホームディレクトリに展開する
+    #[serde(default)]
+    pub cache_dir: Option<String>,
     /// 診断（警告表示）設定
     #[serde(default)]
     pub diagnostics: DiagnosticsConfig,
+    /// CodeLens 設定
+    #[serde(default)]
+    pub codelens: CodeLensConfig,
+    /// ワークスペースシンボル検索の設定
+    #[serde(default)]
+    pub workspace_symbol: WorkspaceSymbolConfig,
+    /// typescript-language-server の実行ファイルパス（未指定なら PATH と node_modules/.bin から探す）
+    #[serde(default)]
+    pub tsserver_path: Option<String>,
+    /// typescript-language-server に `--stdio` の後で渡す追加引数
+    #[serde(default)]
+    pub tsserver_args: Vec<String>,
+    /// 値を Angular 式として解析するプロジェクト固有の属性
+    /// (組み込み / 既知ライブラリ / JS で登録されたディレクティブへの追加分)
+    #[serde(default)]
+    pub expression_attributes: Vec<ExpressionAttribute>,
+    /// これを超えるサイズ (バイト) のファイルは解析しない（未指定なら上限なし）
+    #[serde(default)]
+    pub max_file_size_bytes: Option<u64>,
+    /// これを超える行数のファイルは解析しない（未指定なら上限なし）
+    #[serde(default)]
+    pub max_file_lines: Option<u32>,
+}
+
+/// `expression_attributes` の各要素
+///
+/// 属性名だけの文字列 (`"my-validate"`) は式全体を解析する。
+/// `{ "name": "my-label", "mode": "literal" }` のように `mode` を指定すると
+/// 値を文字列リテラルとして扱い、`{{ }}` の補間のみを解析する。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ExpressionAttribute {
+    Name(String),
+    Detailed {
+        name: String,
+        #[serde(default)]
+        mode: ExpressionAttributeMode,
+    },
+}
+
+impl ExpressionAttribute {
+    /// 属性名 (`normalize_directive_attr` で正規化済み。`data-` / `x-` 接頭辞は除去)
+    pub fn name(&self) -> Cow<'_, str> {
+        let name = match self {
+            Self::Name(name) | Self::Detailed { name, .. } => name,
+        };
+        normalize_directive_attr(name)
+    }
+
+    pub fn mode(&self) -> ExpressionAttributeMode {
+        match self {
+            Self::Name(_) => ExpressionAttributeMode::Expression,
+            Self::Detailed { mode, .. } => *mode,
+        }
+    }
+}
+
+/// `cache` 設定
+///
+/// `true` / `false` だけの指定はキャッシュの有効/無効で、保存時の自動更新は既定の
+/// `on_save`、圧縮なし。`{ "enabled": true, "autosave": "off", "compression": "zstd" }`
+/// のように保存頻度や圧縮形式も指定できる。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum CacheConfig {
+    Enabled(bool),
+    Detailed {
+        #[serde(default = "default_true")]
+        enabled: bool,
+        #[serde(default)]
+        autosave: CacheAutosave,
+        #[serde(default)]
+        compression: CacheCompression,
+    },
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self::Enabled(false)
+    }
+}
+
+impl CacheConfig {
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Enabled(enabled) | Self::Detailed { enabled, .. } => *enabled,
+        }
+    }
+
+    pub fn autosave(&self) -> CacheAutosave {
+        match self {
+            Self::Enabled(_) => CacheAutosave::default(),
+            Self::Detailed { autosave, .. } => *autosave,
+        }
+    }
+
+    pub fn compression(&self) -> CacheCompression {
+        match self {
+            Self::Enabled(_) => CacheCompression::default(),
+            Self::Detailed { compression, .. } => *compression,
+        }
+    }
+}
+
+/// キャッシュの自動更新タイミング
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheAutosave {
+    /// ファイル保存時にそのファイル分だけキャッシュを書き換える
+    #[default]
+    OnSave,
+    /// 保存時には書き込まない (refreshIndex / 終了時の全体保存のみ)
+    Off,
+}
+
+/// キャッシュファイルの圧縮形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// 属性値の解析方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpressionAttributeMode {
+    /// 属性値全体を Angular 式として解析する
+    #[default]
+    Expression,
+    /// 属性値は文字列リテラル (`{{ }}` の補間のみ解析する)
+    Literal,
 }
 
 /// 診断（警告表示）設定
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct DiagnosticsConfig {
     /// 診断機能を有効にする（デフォルト: true）
     #[serde(default = "default_true")]
@@ -40,13 +186,73 @@ pub struct DiagnosticsConfig {
     /// 未使用スコープ変数の警告を有効にする（デフォルト: true）
     #[serde(default = "default_true")]
     pub unused_scope_variables: bool,
-    /// DI 配列の要素数と関数の引数数の不一致を警告する重要度
+    /// DI 配列 / `$inject` の要素数と関数の引数数の不一致、および位置ずれを警告する重要度
     /// "error", "warning", "hint", "information"（デフォルト: "warning"）
     /// 専用の severity を持たせるのは、本診断は誤検出のしようがない強い指摘
     /// (実行時に確実に undefined になる) のため、ユーザがプロジェクト方針に応じて
     /// error として扱えるようにするため。
     #[serde(default = "default_severity")]
     pub di_arity_severity: String,
+    /// HTML 上の未定義 `$scope` 参照 (`{{ userName }}` / `ng-model="vm.x"` 等) の重要度
+    /// 未指定の場合は `severity` を使う（従来の挙動を維持するため）
+    #[serde(default)]
+    pub undefined_scope_reference_severity: Option<String>,
+    /// 定義が無くても未定義サービスとして警告しないサービス名
+    /// (サードパーティライブラリが提供するサービスなど、組み込みサービスへの追加分)
+    #[serde(default)]
+    pub known_services: Vec<String>,
+    /// 登録先モジュールが異なる同名 controller / service / factory を重複定義として
+    /// 警告しない（デフォルト: false）
+    #[serde(default)]
+    pub allow_cross_module_duplicates: bool,
+    /// HTML の構文エラー (閉じタグ欠落・属性クォート不一致など) を報告する（デフォルト: true）
+    /// AngularJS 独自の記法で誤検出が多いプロジェクトでは false にする
+    #[serde(default = "default_true")]
+    pub html_syntax: bool,
+    /// HTML の構文エラーの重要度（デフォルト: "hint"）
+    #[serde(default = "default_hint_severity")]
+    pub html_syntax_severity: String,
+    /// DI で注入したが関数本体で使っていないサービスを hint で報告する（デフォルト: true）
+    #[serde(default = "default_true")]
+    pub unused_injection: bool,
+    /// DI 関数の本体で使っているが注入していないサービスを報告する（デフォルト: true）
+    #[serde(default = "default_true")]
+    pub missing_injection: bool,
+    /// ng-repeat の変数が外側の ng-repeat の変数やコントローラーの `$scope` プロパティを
+    /// 隠している場合に hint を出す（デフォルト: false）
+    /// ネストした ng-repeat での意図的なシャドーイングはよくあるため既定では無効
+    #[serde(default)]
+    pub ng_repeat_shadowing: bool,
+}
+
+/// CodeLens 設定
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CodeLensConfig {
+    /// 参照が 0 件のコントローラーメソッドに「unused」の CodeLens を表示する
+    /// （デフォルト: false。false の場合は 0 件のメソッドには CodeLens を出さない）
+    #[serde(default)]
+    pub show_unused: bool,
+    /// コントローラーの「Used in N templates」に ng-include / ng-view 経由で
+    /// コントローラーを継承しているテンプレートも含める（デフォルト: true）
+    #[serde(default = "default_true")]
+    pub include_inherited_templates: bool,
+}
+
+impl Default for CodeLensConfig {
+    fn default() -> Self {
+        Self {
+            show_unused: false,
+            include_inherited_templates: default_true(),
+        }
+    }
+}
+
+/// ワークスペースシンボル検索の設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct WorkspaceSymbolConfig {
+    /// 返す候補の上限（スコア上位から。未指定なら全件）
+    #[serde(default)]
+    pub max_results: Option<usize>,
 }
 
 fn default_true() -> bool {
@@ -57,6 +263,10 @@ fn default_severity() -> String {
     "warning".to_string()
 }
 
+fn default_hint_severity() -> String {
+    "hint".to_string()
+}
+
 impl Default for DiagnosticsConfig {
     fn default() -> Self {
         Self {
@@ -64,6 +274,14 @@ impl Default for DiagnosticsConfig {
             severity: default_severity(),
             unused_scope_variables: default_true(),
             di_arity_severity: default_severity(),
+            undefined_scope_reference_severity: None,
+            known_services: Vec::new(),
+            allow_cross_module_duplicates: false,
+            html_syntax: default_true(),
+            html_syntax_severity: default_hint_severity(),
+            unused_injection: default_true(),
+            missing_injection: default_true(),
+            ng_repeat_shadowing: false,
         }
     }
 }
@@ -86,8 +304,17 @@ impl Default for AjsConfig {
         Self {
             include: Vec::new(),
             exclude: default_exclude(),
-            cache: false,
+            definitions_only: Vec::new(),
+            cache: CacheConfig::default(),
+            cache_dir: None,
             diagnostics: DiagnosticsConfig::default(),
+            codelens: CodeLensConfig::default(),
+            workspace_symbol: WorkspaceSymbolConfig::default(),
+            tsserver_path: None,
+            tsserver_args: Vec::new(),
+            expression_attributes: Vec::new(),
+            max_file_size_bytes: None,
+            max_file_lines: None,
         }
     }
 }
@@ -122,7 +349,27 @@ impl AjsConfig {
 
     /// PathMatcherを作成
     pub fn create_path_matcher(&self) -> Result<PathMatcher, String> {
-        PathMatcher::new(&self.include, &self.exclude)
+        PathMatcher::new(&self.include, &self.exclude)?.with_definitions_only(&self.definitions_only)
+    }
+
+    /// 解析対象ファイルの大きさの上限
+    pub fn file_limits(&self) -> FileLimits {
+        FileLimits {
+            max_file_size_bytes: self.max_file_size_bytes,
+            max_file_lines: self.max_file_lines,
+        }
+    }
+
+    /// 解析結果を左右する設定 (`expression_attributes` / `definitions_only`) のハッシュ
+    ///
+    /// キャッシュのメタデータに保存し、変わっていたらキャッシュを無効にする
+    pub fn analysis_settings_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for attribute in &self.expression_attributes {
+            (attribute.name(), attribute.mode()).hash(&mut hasher);
+        }
+        self.definitions_only.hash(&mut hasher);
+        hasher.finish()
     }
 }
 
@@ -134,7 +381,7 @@ mod tests {
     fn test_default_config() {
         let config = AjsConfig::default();
         assert!(config.include.is_empty());
-        assert!(!config.cache);
+        assert!(!config.cache.enabled());
     }
 
     #[test]
@@ -150,7 +397,52 @@ mod tests {
             "cache": true
         }"#;
         let config: AjsConfig = serde_json::from_str(json).unwrap();
-        assert!(config.cache, "interpolate フィールドがあっても他フィールドは正しく読み込まれる");
+        assert!(config.cache.enabled(), "interpolate フィールドがあっても他フィールドは正しく読み込まれる");
+    }
+
+    #[test]
+    fn test_cache_settings() {
+        let config: AjsConfig = serde_json::from_str(r#"{ "cache": true }"#).unwrap();
+        assert!(config.cache.enabled());
+        assert_eq!(config.cache.autosave(), CacheAutosave::OnSave);
+
+        let json = r#"{ "cache": { "enabled": true, "autosave": "off" } }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert!(config.cache.enabled());
+        assert_eq!(config.cache.autosave(), CacheAutosave::Off);
+
+        let json = r#"{ "cache": { "autosave": "on_save" } }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert!(config.cache.enabled(), "オブジェクト指定では enabled の既定値は true");
+        assert_eq!(config.cache.autosave(), CacheAutosave::OnSave);
+        assert_eq!(config.cache.compression(), CacheCompression::None);
+
+        let json = r#"{ "cache": { "enabled": true, "compression": "zstd" } }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.cache.compression(), CacheCompression::Zstd);
+        assert!(config.cache_dir.is_none());
+
+        let json = r#"{ "cache": true, "cache_dir": "~/.cache/angularjs-lsp" }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.cache_dir.as_deref(), Some("~/.cache/angularjs-lsp"));
+    }
+
+    #[test]
+    fn test_tsserver_settings() {
+        let json = r#"{
+            "tsserver_path": "node_modules/.bin/typescript-language-server",
+            "tsserver_args": ["--log-level", "4"]
+        }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            config.tsserver_path.as_deref(),
+            Some("node_modules/.bin/typescript-language-server")
+        );
+        assert_eq!(config.tsserver_args, vec!["--log-level", "4"]);
+
+        let config = AjsConfig::default();
+        assert!(config.tsserver_path.is_none());
+        assert!(config.tsserver_args.is_empty());
     }
 
     #[test]
@@ -167,5 +459,95 @@ mod tests {
         assert_eq!(config.severity, "warning");
         assert!(config.unused_scope_variables);
         assert_eq!(config.di_arity_severity, "warning");
+        assert!(config.undefined_scope_reference_severity.is_none());
+        assert!(config.known_services.is_empty());
+        assert!(!config.allow_cross_module_duplicates);
+    }
+
+    #[test]
+    fn test_undefined_scope_reference_severity() {
+        let json = r#"{
+            "diagnostics": {
+                "undefined_scope_reference_severity": "error"
+            }
+        }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            config.diagnostics.undefined_scope_reference_severity.as_deref(),
+            Some("error")
+        );
+        assert_eq!(config.diagnostics.severity, "warning");
+    }
+
+    #[test]
+    fn test_analysis_settings_hash() {
+        let parse = |json: &str| serde_json::from_str::<AjsConfig>(json).unwrap();
+        let base = AjsConfig::default().analysis_settings_hash();
+        assert_eq!(parse(r#"{ "cache": true }"#).analysis_settings_hash(), base);
+        assert_ne!(
+            parse(r#"{ "definitions_only": ["legacy/**"] }"#).analysis_settings_hash(),
+            base
+        );
+        // 同じ属性は書き方によらず同じハッシュ
+        let short = parse(r#"{ "expression_attributes": ["my-validate"] }"#);
+        let detailed = parse(r#"{ "expression_attributes": [{ "name": "data-my-validate" }] }"#);
+        assert_ne!(short.analysis_settings_hash(), base);
+        assert_eq!(short.analysis_settings_hash(), detailed.analysis_settings_hash());
+    }
+
+    #[test]
+    fn test_expression_attributes() {
+        let json = r#"{
+            "expression_attributes": [
+                "my-validate",
+                { "name": "data-my-label", "mode": "literal" },
+                { "name": "x-my-options" }
+            ]
+        }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        let attributes: Vec<_> = config
+            .expression_attributes
+            .iter()
+            .map(|a| (a.name().into_owned(), a.mode()))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                ("my-validate", ExpressionAttributeMode::Expression),
+                ("my-label", ExpressionAttributeMode::Literal),
+                ("my-options", ExpressionAttributeMode::Expression),
+            ]
+            .map(|(name, mode)| (name.to_string(), mode))
+        );
+        assert!(AjsConfig::default().expression_attributes.is_empty());
+    }
+
+    #[test]
+    fn test_file_limits() {
+        let json = r#"{
+            "max_file_size_bytes": 1048576,
+            "max_file_lines": 20000
+        }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.file_limits().max_file_size_bytes, Some(1_048_576));
+        assert_eq!(config.file_limits().max_file_lines, Some(20_000));
+        assert_eq!(AjsConfig::default().file_limits(), FileLimits::default());
+    }
+
+    #[test]
+    fn test_codelens_show_unused() {
+        let json = r#"{ "codelens": { "show_unused": true } }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert!(config.codelens.show_unused);
+        assert!(config.codelens.include_inherited_templates);
+        assert!(!AjsConfig::default().codelens.show_unused);
+    }
+
+    #[test]
+    fn test_codelens_include_inherited_templates() {
+        let json = r#"{ "codelens": { "include_inherited_templates": false } }"#;
+        let config: AjsConfig = serde_json::from_str(json).unwrap();
+        assert!(!config.codelens.include_inherited_templates);
+        assert!(AjsConfig::default().codelens.include_inherited_templates);
     }
 }
@@ -0,0 +1,59 @@
+/// 解析対象にするファイルの大きさの上限
+/// (ajsconfig.json の `max_file_size_bytes` / `max_file_lines`)
+///
+/// バンドル済みの `vendor.js` や生成された巨大 HTML をスキャンして起動が
+/// 極端に遅くなるのを防ぐ。どちらも未指定なら上限なし。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileLimits {
+    pub max_file_size_bytes: Option<u64>,
+    pub max_file_lines: Option<u32>,
+}
+
+impl FileLimits {
+    /// ファイルサイズ (バイト数) が上限を超えるか
+    ///
+    /// 内容を読む前に `fs::metadata` のサイズで足切りするために使う
+    pub fn exceeds_size(&self, bytes: u64) -> bool {
+        self.max_file_size_bytes.is_some_and(|max| bytes > max)
+    }
+
+    /// 内容がサイズ・行数のいずれかの上限を超えるか
+    pub fn exceeds(&self, content: &str) -> bool {
+        self.exceeds_size(content.len() as u64)
+            || self
+                .max_file_lines
+                .is_some_and(|max| content.lines().count() > max as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_limits() {
+        let limits = FileLimits::default();
+        assert!(!limits.exceeds(&"x\n".repeat(100_000)));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let limits = FileLimits {
+            max_file_size_bytes: Some(10),
+            max_file_lines: None,
+        };
+        assert!(!limits.exceeds("0123456789"));
+        assert!(limits.exceeds("0123456789a"));
+        assert!(limits.exceeds_size(11));
+    }
+
+    #[test]
+    fn test_line_limit() {
+        let limits = FileLimits {
+            max_file_size_bytes: None,
+            max_file_lines: Some(2),
+        };
+        assert!(!limits.exceeds("a\nb\n"));
+        assert!(limits.exceeds("a\nb\nc"));
+    }
+}
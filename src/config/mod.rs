@@ -1,5 +1,10 @@
 pub mod ajs_config;
+pub mod file_limits;
 pub mod path_matcher;
 
-pub use ajs_config::{AjsConfig, DiagnosticsConfig};
+pub use ajs_config::{
+    AjsConfig, CacheAutosave, CacheCompression, CodeLensConfig, DiagnosticsConfig, ExpressionAttributeMode,
+    WorkspaceSymbolConfig,
+};
+pub use file_limits::FileLimits;
 pub use path_matcher::PathMatcher;
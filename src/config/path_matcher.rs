@@ -7,6 +7,8 @@ use globset::{Glob, GlobSet, GlobSetBuilder};
 pub struct PathMatcher {
     include: Option<GlobSet>,
     exclude: GlobSet,
+    /// 定義だけを収集し参照は収集しないファイル
+    definitions_only: Option<GlobSet>,
 }
 
 impl PathMatcher {
@@ -41,9 +43,30 @@ impl PathMatcher {
         Ok(Self {
             include: include_set,
             exclude: exclude_set,
+            definitions_only: None,
         })
     }
 
+    /// 定義だけを収集するファイルのパターンを設定
+    pub fn with_definitions_only(mut self, patterns: &[String]) -> Result<Self, String> {
+        if patterns.is_empty() {
+            self.definitions_only = None;
+            return Ok(self);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern)
+                .map_err(|e| format!("Invalid definitions_only pattern '{}': {}", pattern, e))?;
+            builder.add(glob);
+        }
+        self.definitions_only = Some(
+            builder
+                .build()
+                .map_err(|e| format!("Failed to build definitions_only set: {}", e))?,
+        );
+        Ok(self)
+    }
+
     /// ファイルが解析対象かどうかを判定
     pub fn should_include(&self, relative_path: &Path) -> bool {
         if self.exclude.is_match(relative_path) {
@@ -55,6 +78,13 @@ impl PathMatcher {
         }
     }
 
+    /// 参照を収集せず定義だけを収集するファイルかどうかを判定
+    pub fn is_definitions_only(&self, relative_path: &Path) -> bool {
+        self.definitions_only
+            .as_ref()
+            .is_some_and(|set| set.is_match(relative_path))
+    }
+
     /// ディレクトリを走査すべきかどうかを判定（excludeのみチェック）
     pub fn should_traverse_dir(&self, relative_path: &Path) -> bool {
         !self.exclude.is_match(relative_path)
@@ -94,6 +124,24 @@ mod tests {
         assert!(!matcher.should_include(Path::new("src/test/app.spec.js")));
     }
 
+    #[test]
+    fn test_definitions_only() {
+        let matcher = PathMatcher::new(&[], &[])
+            .unwrap()
+            .with_definitions_only(&["legacy/**".to_string()])
+            .unwrap();
+        assert!(matcher.is_definitions_only(Path::new("legacy/app.js")));
+        assert!(!matcher.is_definitions_only(Path::new("src/app.js")));
+        assert!(matcher.should_include(Path::new("legacy/app.js")));
+
+        let matcher = PathMatcher::new(&[], &[]).unwrap();
+        assert!(!matcher.is_definitions_only(Path::new("legacy/app.js")));
+        assert!(PathMatcher::new(&[], &[])
+            .unwrap()
+            .with_definitions_only(&["[".to_string()])
+            .is_err());
+    }
+
     #[test]
     fn test_should_traverse_dir() {
         let matcher = PathMatcher::new(
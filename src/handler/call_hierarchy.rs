@@ -0,0 +1,427 @@
+//! Call hierarchy handler.
+//!
+//! コントローラー / サービスのメソッドについて、呼び出し元 (incoming) と
+//! 呼び出し先 (outgoing) を返す。
+//!
+//! - incoming: 対象シンボルの参照 (JS + HTML) を集め、参照を囲む関数定義 (メソッド)
+//!   に丸める。メソッドの外 (コントローラー本体など) にある参照は登録元の
+//!   コンポーネントに、それも無ければ (HTML テンプレートを含め) ファイルにまとめる
+//! - outgoing: メソッド本体の呼び出し式を走査し、呼び出し位置の参照から解決する。
+//!   参照は解析時に DI を考慮して記録されているため、`UserService.getAll()` の
+//!   ような DI 境界をまたぐ呼び出しもサービス側の定義へ解決される
+//!
+//! 定義の `definition_span` はメソッド名の位置しか持たないため、関数の範囲は
+//! 構文木から求める。開いているドキュメントはエディタの内容と解析時にキャッシュした
+//! Tree を使い、パースし直すのはキャッシュにないファイル (未オープンのファイルなど)
+//! だけ。未オープンのファイルはディスク上の内容を読む。
+//!
+//! JS のインデックスの列は tree-sitter と同じバイト列、HTML とクライアントの列は
+//! UTF-16。比較の前にどちらかに揃える。
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
+    CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
+    Position, Range, Url,
+};
+use tree_sitter::{Node, Point, Tree};
+
+use crate::analyzer::incremental::SyntaxTreeCache;
+use crate::analyzer::js::JsParser;
+use crate::index::Index;
+use crate::model::{Symbol, SymbolKind};
+use crate::util::{is_js_file, utf16_col_to_byte_col};
+
+pub struct CallHierarchyHandler {
+    index: Arc<Index>,
+    documents: Arc<DashMap<Url, String>>,
+    /// JS の tree-sitter Tree キャッシュ (`AngularJsAnalyzer::tree_cache` と共有)
+    js_tree_cache: Arc<SyntaxTreeCache>,
+}
+
+impl CallHierarchyHandler {
+    pub fn new(
+        index: Arc<Index>,
+        documents: Arc<DashMap<Url, String>>,
+        js_tree_cache: Arc<SyntaxTreeCache>,
+    ) -> Self {
+        Self {
+            index,
+            documents,
+            js_tree_cache,
+        }
+    }
+
+    /// `textDocument/prepareCallHierarchy`
+    ///
+    /// カーソル位置のシンボル (定義・参照のどちらでもよい) がメソッドなら、
+    /// その定義を項目として返す
+    pub fn prepare(&self, params: CallHierarchyPrepareParams) -> Option<Vec<CallHierarchyItem>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        // JS のシンボル位置はバイト列で記録されている
+        let col = match self.source(&uri).filter(|_| is_js_file(&uri)) {
+            Some(source) => utf16_col_to_byte_col(&source, position.line, position.character),
+            None => position.character,
+        };
+        let name = self.index.definitions.find_symbol_at_position(&uri, position.line, col)?;
+        let items: Vec<_> = self
+            .index
+            .definitions
+            .get_definitions(&name)
+            .iter()
+            .filter(|symbol| is_callable(symbol.kind))
+            .map(symbol_item)
+            .collect();
+
+        if items.is_empty() { None } else { Some(items) }
+    }
+
+    /// `callHierarchy/incomingCalls`
+    pub fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Option<Vec<CallHierarchyIncomingCall>> {
+        let name = item_symbol_name(&params.item)?;
+        let references = self.index.get_all_references(&name);
+
+        // 呼び出し元ごとに呼び出し位置をまとめる (出現順を保つ)
+        let mut callers: Vec<(CallHierarchyItem, Vec<Range>)> = Vec::new();
+        let mut caller_slots: HashMap<(Url, String), usize> = HashMap::new();
+        let mut trees: HashMap<Url, Option<(String, Tree)>> = HashMap::new();
+
+        for reference in &references {
+            // JS の参照位置はバイト列なのでそのまま tree-sitter の Point にできる。
+            // HTML の参照 (UTF-16) はパースせずファイル単位にまとめる
+            let parsed = trees
+                .entry(reference.uri.clone())
+                .or_insert_with(|| self.parse(&reference.uri));
+            let caller = parsed
+                .as_ref()
+                .and_then(|(source, tree)| {
+                    let point = Point::new(
+                        reference.span.start_line as usize,
+                        reference.span.start_col as usize,
+                    );
+                    self.enclosing_caller(&reference.uri, tree, source, point)
+                })
+                .map(|symbol| symbol_item(&symbol))
+                .unwrap_or_else(|| file_item(&reference.uri));
+
+            let key = (caller.uri.clone(), caller.name.clone());
+            let slot = *caller_slots.entry(key).or_insert_with(|| {
+                callers.push((caller, Vec::new()));
+                callers.len() - 1
+            });
+            callers[slot].1.push(reference.span.to_lsp_range());
+        }
+
+        Some(
+            callers
+                .into_iter()
+                .map(|(from, from_ranges)| CallHierarchyIncomingCall { from, from_ranges })
+                .collect(),
+        )
+    }
+
+    /// `callHierarchy/outgoingCalls`
+    pub fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Option<Vec<CallHierarchyOutgoingCall>> {
+        let item = params.item;
+        let caller_name = item_symbol_name(&item)?;
+        let (_, tree) = self.parse(&item.uri)?;
+
+        let start = item.selection_range.start;
+        let point = Point::new(start.line as usize, start.character as usize);
+        let name_node = tree.root_node().descendant_for_point_range(point, point)?;
+        let function = function_for_name(name_node)?;
+        let body = function.child_by_field_name("body").unwrap_or(function);
+
+        let mut callees: Vec<(String, Vec<Range>)> = Vec::new();
+        let mut callee_slots: HashMap<String, usize> = HashMap::new();
+        let mut call_sites = Vec::new();
+        collect_call_sites(body, &mut call_sites);
+
+        for site in call_sites {
+            let start = site.start_position();
+            let Some(name) = self.index.definitions.find_symbol_at_position(
+                &item.uri,
+                start.row as u32,
+                start.column as u32,
+            ) else {
+                continue;
+            };
+            if name == caller_name || !self.has_callable_definition(&name) {
+                continue;
+            }
+            let slot = *callee_slots.entry(name.clone()).or_insert_with(|| {
+                callees.push((name, Vec::new()));
+                callees.len() - 1
+            });
+            callees[slot].1.push(node_range(site));
+        }
+
+        Some(
+            callees
+                .into_iter()
+                .filter_map(|(name, from_ranges)| {
+                    let definition = self
+                        .index
+                        .definitions
+                        .get_definitions(&name)
+                        .into_iter()
+                        .find(|symbol| is_callable(symbol.kind))?;
+                    Some(CallHierarchyOutgoingCall {
+                        to: symbol_item(&definition),
+                        from_ranges,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn has_callable_definition(&self, name: &str) -> bool {
+        self.index
+            .definitions
+            .get_definitions(name)
+            .iter()
+            .any(|symbol| is_callable(symbol.kind))
+    }
+
+    /// 開いているドキュメントはエディタの内容、それ以外はディスク上の内容
+    fn source(&self, uri: &Url) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(doc) => Some(doc.value().clone()),
+            None => fs::read_to_string(uri.to_file_path().ok()?).ok(),
+        }
+    }
+
+    /// JS ファイルのソースと Tree を取得する
+    ///
+    /// ソースが一致する Tree がキャッシュにあればそれを使い、無ければパースする
+    fn parse(&self, uri: &Url) -> Option<(String, Tree)> {
+        if !is_js_file(uri) {
+            return None;
+        }
+        let source = self.source(uri)?;
+        let tree = match self.js_tree_cache.tree_for_source(uri, &source) {
+            Some(tree) => tree,
+            None => JsParser::for_uri(uri).parse(&source)?,
+        };
+        Some((source, tree))
+    }
+
+    /// `point` を囲む関数のうち、インデックスに定義として登録されている最も内側のもの
+    ///
+    /// メソッドが見つからなければ、`.controller('Name', ...)` などの登録元を返す
+    fn enclosing_caller(&self, uri: &Url, tree: &Tree, source: &str, point: Point) -> Option<Symbol> {
+        let definitions = self.index.definitions.get_definitions_for_uri(uri);
+        let mut node = tree.root_node().descendant_for_point_range(point, point)?;
+        let mut registration: Option<Symbol> = None;
+
+        while let Some(parent) = node.parent() {
+            node = parent;
+            if let Some(name_node) = function_name_node(node) {
+                let method = definitions
+                    .iter()
+                    .filter(|symbol| is_callable(symbol.kind))
+                    .find(|symbol| span_within(symbol, name_node));
+                if let Some(symbol) = method {
+                    return Some(symbol.clone());
+                }
+            }
+            if registration.is_none() {
+                registration = registered_component(node, source, &definitions);
+            }
+        }
+
+        registration
+    }
+}
+
+/// call hierarchy の対象になるシンボル種別
+fn is_callable(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Method | SymbolKind::ScopeMethod | SymbolKind::RootScopeMethod
+    )
+}
+
+fn symbol_item(symbol: &Symbol) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: symbol.name.clone(),
+        kind: symbol.kind.to_lsp_symbol_kind(),
+        tags: None,
+        detail: Some(symbol.kind.as_str().to_string()),
+        uri: symbol.uri.clone(),
+        range: symbol.definition_span.to_lsp_range(),
+        selection_range: symbol.name_span.to_lsp_range(),
+        data: Some(serde_json::Value::String(symbol.name.clone())),
+    }
+}
+
+/// 関数の外 (モジュールの run / config ブロックや HTML) からの呼び出しをまとめる項目
+fn file_item(uri: &Url) -> CallHierarchyItem {
+    let file_name = uri
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or(uri.as_str())
+        .to_string();
+    let origin = Range::new(Position::new(0, 0), Position::new(0, 0));
+    CallHierarchyItem {
+        name: file_name,
+        kind: tower_lsp::lsp_types::SymbolKind::FILE,
+        tags: None,
+        detail: None,
+        uri: uri.clone(),
+        range: origin,
+        selection_range: origin,
+        data: None,
+    }
+}
+
+/// 項目に埋め込んだシンボル名 (`prepare` / 呼び出し元・先の項目のみ持つ)
+fn item_symbol_name(item: &CallHierarchyItem) -> Option<String> {
+    item.data.as_ref()?.as_str().map(str::to_string)
+}
+
+/// 定義の名前位置が `node` の範囲内にあるか (文字列キーはクォートを含まない)
+fn span_within(symbol: &Symbol, node: Node) -> bool {
+    let start = node.start_position();
+    let end = node.end_position();
+    let span = symbol.name_span;
+    (span.start_line, span.start_col) >= (start.row as u32, start.column as u32)
+        && (span.end_line, span.end_col) <= (end.row as u32, end.column as u32)
+}
+
+/// 関数ノードに名前を与えているノード
+///
+/// `method() {}` / `function name() {}` / `name: function() {}` /
+/// `this.name = function() {}` / `var name = function() {}` に対応
+fn function_name_node(node: Node) -> Option<Node> {
+    match node.kind() {
+        "method_definition" | "function_declaration" => node.child_by_field_name("name"),
+        "function_expression" | "arrow_function" => {
+            let parent = node.parent()?;
+            match parent.kind() {
+                "pair" => parent.child_by_field_name("key"),
+                "variable_declarator" => parent.child_by_field_name("name"),
+                "assignment_expression" => {
+                    let left = parent.child_by_field_name("left")?;
+                    match left.kind() {
+                        "member_expression" => left.child_by_field_name("property"),
+                        "subscript_expression" => left.child_by_field_name("index"),
+                        _ => Some(left),
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 名前ノードから、その名前が指す関数ノードを探す ([`function_name_node`] の逆)
+///
+/// `.controller('Name', function() {...})` のように登録名の文字列から
+/// 関数を指す場合は、同じ呼び出しの引数 (DI 配列を含む) から関数を探す
+fn function_for_name(name_node: Node) -> Option<Node> {
+    let mut node = name_node;
+    for _ in 0..4 {
+        let parent = node.parent()?;
+        let function = match parent.kind() {
+            "method_definition" | "function_declaration" => Some(parent),
+            "pair" => parent.child_by_field_name("value"),
+            "variable_declarator" => parent.child_by_field_name("value"),
+            "assignment_expression" => parent.child_by_field_name("right"),
+            "arguments" => last_function_argument(parent),
+            _ => None,
+        };
+        if let Some(function) = function.filter(|f| is_function(*f)) {
+            return Some(function);
+        }
+        node = parent;
+    }
+    None
+}
+
+fn is_function(node: Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_expression" | "arrow_function" | "function_declaration" | "method_definition"
+    )
+}
+
+/// 引数のうち最後の関数 (DI 配列 `['$http', function($http) {}]` の中も見る)
+fn last_function_argument(arguments: Node) -> Option<Node> {
+    let mut cursor = arguments.walk();
+    let last = arguments.named_children(&mut cursor).last()?;
+    if last.kind() == "array" {
+        let mut cursor = last.walk();
+        return last.named_children(&mut cursor).last().filter(|n| is_function(*n));
+    }
+    Some(last).filter(|n| is_function(*n))
+}
+
+/// `.controller('Name', ...)` などの登録呼び出しなら、その定義を返す
+fn registered_component(node: Node, source: &str, definitions: &[Symbol]) -> Option<Symbol> {
+    if node.kind() != "call_expression" {
+        return None;
+    }
+    let arguments = node.child_by_field_name("arguments")?;
+    let first = arguments.named_child(0).filter(|n| n.kind() == "string")?;
+    let name = source[first.byte_range()].trim_matches(|c| c == '"' || c == '\'' || c == '`');
+    definitions
+        .iter()
+        .find(|symbol| {
+            symbol.name == name
+                && matches!(
+                    symbol.kind,
+                    SymbolKind::Controller
+                        | SymbolKind::Service
+                        | SymbolKind::Factory
+                        | SymbolKind::Provider
+                        | SymbolKind::Directive
+                        | SymbolKind::Component
+                        | SymbolKind::Filter
+                )
+        })
+        .cloned()
+}
+
+/// 呼び出し式の呼び出し先名の位置 (`a.b()` の `b`、`f()` の `f`) を集める
+fn collect_call_sites<'a>(node: Node<'a>, sites: &mut Vec<Node<'a>>) {
+    if node.kind() == "call_expression" {
+        let callee = node.child_by_field_name("function");
+        let site = match callee {
+            Some(callee) if callee.kind() == "member_expression" => {
+                callee.child_by_field_name("property")
+            }
+            Some(callee) if callee.kind() == "identifier" => Some(callee),
+            _ => None,
+        };
+        if let Some(site) = site {
+            sites.push(site);
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_call_sites(child, sites);
+    }
+}
+
+fn node_range(node: Node) -> Range {
+    let start = node.start_position();
+    let end = node.end_position();
+    Range::new(
+        Position::new(start.row as u32, start.column as u32),
+        Position::new(end.row as u32, end.column as u32),
+    )
+}
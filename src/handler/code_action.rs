@@ -0,0 +1,333 @@
+//! Code action (quick fix) handler.
+//!
+//! 診断の `data` に埋めた修正情報 ([`QuickFixData`]) から `CodeAction` を作る。
+//! 診断ごとの判定は `DiagnosticsHandler` 側で済んでいるので、ここでは
+//! `data` をテキスト編集に変換するだけにする。
+//!
+//! - did-you-mean 候補への置換 (`didYouMean`)
+//! - 未定義のスコープ参照をコントローラーの `$scope` に追加 (`addScopeProperty`)
+//! - 使っているが注入していないサービスを DI 配列と引数に追加 (`addInjection`)
+//!
+//! 診断に紐づかない refactor として、カーソル位置の DI 関数の引数名から
+//! DI 配列 / `$inject` を生成・同期するアクションも返す (ng-annotate の代わり)。
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse,
+    Diagnostic, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use tree_sitter::Tree;
+
+use crate::analyzer::incremental::SyntaxTreeCache;
+use crate::analyzer::js::{injected_params, AngularJsAnalyzer, DiAnnotation, DiAnnotationKind, JsParser};
+use crate::index::Index;
+use crate::model::{ControllerScope, InsertPoint};
+use crate::util::is_js_file;
+
+/// 診断の `data` に入れるクイックフィックスの情報
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QuickFixData {
+    /// 参照を置き換える綴りの近い候補名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did_you_mean: Option<String>,
+    /// コントローラーの `$scope` に追加するプロパティ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add_scope_property: Option<AddScopeProperty>,
+    /// DI 配列と引数に追加するサービス
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add_injection: Option<AddInjection>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AddScopeProperty {
+    pub controller: String,
+    pub property: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AddInjection {
+    pub service: String,
+    /// DI 配列 / `$inject` への追加位置 (暗黙 DI では `None`)
+    pub dependency_insert: Option<InsertPoint>,
+    /// 引数リストへの追加位置
+    pub param_insert: InsertPoint,
+}
+
+impl QuickFixData {
+    pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        serde_json::from_value(diagnostic.data.clone()?).ok()
+    }
+
+    /// 診断の `data` に入れる値 (修正がなければ `None`)
+    pub fn into_value(self) -> Option<serde_json::Value> {
+        if self.did_you_mean.is_none()
+            && self.add_scope_property.is_none()
+            && self.add_injection.is_none()
+        {
+            return None;
+        }
+        serde_json::to_value(self).ok()
+    }
+}
+
+pub struct CodeActionHandler {
+    index: Arc<Index>,
+    documents: Arc<DashMap<Url, String>>,
+    /// JS の tree-sitter Tree キャッシュ (`AngularJsAnalyzer::tree_cache` と共有)
+    js_tree_cache: Arc<SyntaxTreeCache>,
+}
+
+impl CodeActionHandler {
+    pub fn new(
+        index: Arc<Index>,
+        documents: Arc<DashMap<Url, String>>,
+        js_tree_cache: Arc<SyntaxTreeCache>,
+    ) -> Self {
+        Self {
+            index,
+            documents,
+            js_tree_cache,
+        }
+    }
+
+    /// `textDocument/codeAction`
+    ///
+    /// リクエストに含まれる自サーバーの診断のうち、修正情報を持つものについて
+    /// quick fix を返す。JS ではカーソル位置の DI 注釈の refactor も返す
+    pub fn code_actions(&self, params: &CodeActionParams) -> Option<CodeActionResponse> {
+        let uri = &params.text_document.uri;
+        let mut actions = Vec::new();
+
+        for diagnostic in &params.context.diagnostics {
+            if diagnostic.source.as_deref() != Some("angularjs-lsp") {
+                continue;
+            }
+            let Some(data) = QuickFixData::from_diagnostic(diagnostic) else {
+                continue;
+            };
+
+            if let Some(name) = &data.did_you_mean {
+                let edit = single_file_edit(
+                    uri,
+                    vec![TextEdit {
+                        range: diagnostic.range,
+                        new_text: name.clone(),
+                    }],
+                );
+                actions.push(quick_fix(format!("Change to '{}'", name), diagnostic, edit, true));
+            }
+            let scope_fix = data.add_scope_property.as_ref().and_then(|fix| {
+                let edit = self.add_scope_property_edit(fix)?;
+                Some((fix, edit))
+            });
+            if let Some((fix, edit)) = scope_fix {
+                let title = format!(
+                    "Add '$scope.{}' to controller '{}'",
+                    fix.property, fix.controller
+                );
+                actions.push(quick_fix(title, diagnostic, edit, false));
+            }
+            if let Some(fix) = &data.add_injection {
+                let title = format!("Inject '{}'", fix.service);
+                actions.push(quick_fix(title, diagnostic, add_injection_edit(uri, fix), true));
+            }
+        }
+
+        if is_js_file(uri) && accepts_kind(params, &CodeActionKind::REFACTOR_REWRITE) {
+            actions.extend(self.di_annotation_action(uri, params.range.start));
+        }
+
+        if actions.is_empty() { None } else { Some(actions) }
+    }
+
+    /// カーソル位置の DI 関数の引数名から DI 配列 / `$inject` を生成・同期するアクション
+    fn di_annotation_action(&self, uri: &Url, position: Position) -> Option<CodeActionOrCommand> {
+        let source = self.document_source(uri)?;
+        let tree = self.js_tree(uri, &source)?;
+        let analyzer = AngularJsAnalyzer::new(Arc::clone(&self.index));
+        let DiAnnotation { kind, inserts } =
+            analyzer.di_annotation_at(&source, &tree, position.line, position.character)?;
+
+        let title = match kind {
+            DiAnnotationKind::ConvertToArray => "Convert to DI array",
+            DiAnnotationKind::SyncArray => "Sync DI array with parameters",
+            DiAnnotationKind::AddInject => "Add $inject annotation",
+            DiAnnotationKind::SyncInject => "Sync $inject with parameters",
+        };
+        let edits = inserts
+            .into_iter()
+            .map(|insert| {
+                let position = Position::new(insert.line, insert.col);
+                TextEdit {
+                    range: Range::new(position, position),
+                    new_text: insert.text,
+                }
+            })
+            .collect();
+        Some(CodeActionOrCommand::CodeAction(CodeAction {
+            title: title.to_string(),
+            kind: Some(CodeActionKind::REFACTOR_REWRITE),
+            edit: Some(single_file_edit(uri, edits)),
+            ..Default::default()
+        }))
+    }
+
+    /// 開いているドキュメントはエディタの内容、それ以外はディスク上の内容
+    fn document_source(&self, uri: &Url) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(doc) => Some(doc.value().clone()),
+            None => fs::read_to_string(uri.to_file_path().ok()?).ok(),
+        }
+    }
+
+    /// `source` の Tree (キャッシュにソースが一致する Tree がなければパースする)
+    fn js_tree(&self, uri: &Url, source: &str) -> Option<Tree> {
+        self.js_tree_cache
+            .tree_for_source(uri, source)
+            .or_else(|| JsParser::for_uri(uri).parse(source))
+    }
+
+    /// コントローラー本体の先頭に `$scope.{property} = null;` を挿入する編集
+    ///
+    /// 本体が 1 行に収まっている関数は挿入位置の整形が難しいので対象外
+    fn add_scope_property_edit(&self, fix: &AddScopeProperty) -> Option<WorkspaceEdit> {
+        let scope = self.index.controllers.find_controller_scope(&fix.controller)?;
+        if scope.end_line <= scope.start_line {
+            return None;
+        }
+        let source = self.document_source(&scope.uri)?;
+
+        // 本体の最初の空でない行に揃える (なければ `{` の行 + 4 スペース)
+        let lines: Vec<&str> = source.lines().collect();
+        let indent = lines
+            .iter()
+            .take(scope.end_line as usize + 1)
+            .skip(scope.start_line as usize + 1)
+            .find(|line| !line.trim().is_empty())
+            .filter(|line| !line.trim_start().starts_with('}'))
+            .map(|line| leading_whitespace(line).to_string())
+            .unwrap_or_else(|| {
+                let open = lines.get(scope.start_line as usize).copied().unwrap_or("");
+                format!("{}    ", leading_whitespace(open))
+            });
+
+        let scope_param = self
+            .js_tree(&scope.uri, &source)
+            .and_then(|tree| scope_param_name(&source, &tree, &scope))
+            .unwrap_or("$scope");
+        let position = Position::new(scope.start_line + 1, 0);
+        Some(single_file_edit(
+            &scope.uri,
+            vec![TextEdit {
+                range: Range::new(position, position),
+                new_text: format!("{}{}.{} = null;\n", indent, scope_param, fix.property),
+            }],
+        ))
+    }
+}
+
+/// コントローラー関数で `$scope` を受けている引数名 (`['$scope', function(s) {` なら `s`)
+///
+/// 本体の行範囲が `scope` と一致する関数を探す
+fn scope_param_name<'a>(source: &'a str, tree: &Tree, scope: &ControllerScope) -> Option<&'a str> {
+    fn find<'t>(node: tree_sitter::Node<'t>, scope: &ControllerScope) -> Option<tree_sitter::Node<'t>> {
+        if (node.start_position().row as u32) > scope.start_line
+            || (node.end_position().row as u32) < scope.end_line
+        {
+            return None;
+        }
+        let body_matches = node.child_by_field_name("body").is_some_and(|body| {
+            body.start_position().row as u32 == scope.start_line
+                && body.end_position().row as u32 == scope.end_line
+        });
+        if body_matches
+            && matches!(node.kind(), "function_expression" | "function_declaration" | "arrow_function")
+        {
+            return Some(node);
+        }
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.named_children(&mut cursor).collect();
+        children.into_iter().find_map(|child| find(child, scope))
+    }
+
+    let func = find(tree.root_node(), scope)?;
+    injected_params(func, source)
+        .into_iter()
+        .find(|(service, _)| *service == "$scope")
+        .map(|(_, param)| param)
+}
+
+/// リクエストの `only` (指定があれば) が `kind` を含むか
+fn accepts_kind(params: &CodeActionParams, kind: &CodeActionKind) -> bool {
+    params.context.only.as_ref().is_none_or(|only| {
+        only.iter().any(|requested| {
+            kind.as_str() == requested.as_str()
+                || kind.as_str().starts_with(&format!("{}.", requested.as_str()))
+        })
+    })
+}
+
+/// DI 配列 (あれば) と引数リストの末尾にサービスを追加する編集
+fn add_injection_edit(uri: &Url, fix: &AddInjection) -> WorkspaceEdit {
+    let mut edits = Vec::new();
+    if let Some(point) = &fix.dependency_insert {
+        edits.push(insert_at(point, &format!("'{}'", fix.service)));
+    }
+    edits.push(insert_at(&fix.param_insert, &fix.service));
+    single_file_edit(uri, edits)
+}
+
+/// 挿入位置の前後に必要なカンマを付けて `text` を挿入する
+fn insert_at(point: &InsertPoint, text: &str) -> TextEdit {
+    let position = Position::new(point.line, point.col);
+    let mut new_text = String::new();
+    if point.leading_comma {
+        new_text.push_str(", ");
+    }
+    new_text.push_str(text);
+    if point.trailing_comma {
+        new_text.push_str(", ");
+    }
+    TextEdit {
+        range: Range::new(position, position),
+        new_text,
+    }
+}
+
+fn single_file_edit(uri: &Url, edits: Vec<TextEdit>) -> WorkspaceEdit {
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), edits);
+    WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }
+}
+
+fn quick_fix(
+    title: String,
+    diagnostic: &Diagnostic,
+    edit: WorkspaceEdit,
+    is_preferred: bool,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(edit),
+        is_preferred: Some(is_preferred),
+        ..Default::default()
+    })
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
@@ -3,17 +3,19 @@ use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
 
+use crate::config::CodeLensConfig;
 use crate::index::Index;
-use crate::model::{BindingSource, ComponentTemplateUrl, SymbolKind, TemplateBinding};
+use crate::model::{BindingSource, ComponentTemplateUrl, Symbol, SymbolKind, TemplateBinding};
 use crate::util::{is_html_file, is_js_file};
 
 pub struct CodeLensHandler {
     index: Arc<Index>,
+    config: CodeLensConfig,
 }
 
 impl CodeLensHandler {
-    pub fn new(index: Arc<Index>) -> Self {
-        Self { index }
+    pub fn new(index: Arc<Index>, config: CodeLensConfig) -> Self {
+        Self { index, config }
     }
 
     pub fn code_lens(&self, uri: &Url) -> Option<Vec<CodeLens>> {
@@ -93,16 +95,9 @@ impl CodeLensHandler {
 
         // 7. <script>タグ内のコントローラー定義（JSファイルと同様の処理）
         let symbols = self.index.get_document_symbols(uri);
-        for symbol in symbols {
-            if symbol.kind == SymbolKind::Controller {
-                let templates = self.index.get_templates_for_controller(&symbol.name);
-                if !templates.is_empty() {
-                    lenses.push(self.create_template_lens(
-                        &symbol.name,
-                        symbol.name_span.start_line,
-                        &templates,
-                    ));
-                }
+        for symbol in symbols.iter().filter(|s| s.kind == SymbolKind::Controller) {
+            if let Some(lens) = self.create_template_lens(symbol) {
+                lenses.push(lens);
             }
         }
 
@@ -125,16 +120,16 @@ impl CodeLensHandler {
 
         // このファイル内のコントローラー定義を取得
         let symbols = self.index.get_document_symbols(uri);
-        for symbol in symbols {
-            if symbol.kind == SymbolKind::Controller {
-                let templates = self.index.get_templates_for_controller(&symbol.name);
-                if !templates.is_empty() {
-                    lenses.push(self.create_template_lens(
-                        &symbol.name,
-                        symbol.name_span.start_line,
-                        &templates,
-                    ));
-                }
+        for symbol in symbols.iter().filter(|s| s.kind == SymbolKind::Controller) {
+            if let Some(lens) = self.create_template_lens(symbol) {
+                lenses.push(lens);
+            }
+        }
+
+        // コントローラーメソッド ($scope.method / this.method) の参照数
+        for symbol in symbols.iter().filter(|s| self.is_controller_method(s)) {
+            if let Some(lens) = self.create_references_lens(symbol) {
+                lenses.push(lens);
             }
         }
 
@@ -157,6 +152,81 @@ impl CodeLensHandler {
         }
     }
 
+    /// `$scope.method` / `this.method` で定義されたコントローラーのメソッドか
+    ///
+    /// `this.method` は `Service.method` と同じ `SymbolKind::Method` なので、
+    /// 所有者がコントローラー (component / directive の controller を含む) のものに限る
+    fn is_controller_method(&self, symbol: &Symbol) -> bool {
+        match symbol.kind {
+            SymbolKind::ScopeMethod => true,
+            SymbolKind::Method => self
+                .index
+                .parse_controller_method_name(&symbol.name)
+                .is_some_and(|(owner, _)| {
+                    [SymbolKind::Controller, SymbolKind::Component, SymbolKind::Directive]
+                        .into_iter()
+                        .any(|kind| self.index.definitions.has_definition_of_kind(&owner, kind))
+                }),
+            _ => false,
+        }
+    }
+
+    /// メソッド定義行の「N references」CodeLens
+    ///
+    /// HTML / JS 双方の参照を集計し、クリックで参照一覧を開く
+    /// (`angularjs.showReferences` はクライアント側で `editor.action.showReferences` に変換する)。
+    /// 参照が 0 件の場合は `codelens.show_unused` が有効なときだけ「unused」と表示する。
+    fn create_references_lens(&self, symbol: &Symbol) -> Option<CodeLens> {
+        let mut locations: Vec<Location> = Vec::new();
+        for reference in self.index.get_all_references(&symbol.name) {
+            let location = Location {
+                uri: reference.uri,
+                range: reference.span.to_lsp_range(),
+            };
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+
+        let command = match locations.len() {
+            0 if !self.config.show_unused => return None,
+            0 => Command {
+                title: "unused".to_string(),
+                command: "".to_string(),
+                arguments: None,
+            },
+            count => {
+                let position = Position {
+                    line: symbol.name_span.start_line,
+                    character: symbol.name_span.start_col,
+                };
+                Command {
+                    title: if count == 1 {
+                        "1 reference".to_string()
+                    } else {
+                        format!("{} references", count)
+                    },
+                    command: "angularjs.showReferences".to_string(),
+                    arguments: Some(vec![
+                        serde_json::json!(symbol.uri.to_string()),
+                        serde_json::json!(position),
+                        serde_json::json!(locations),
+                    ]),
+                }
+            }
+        };
+
+        let line = symbol.name_span.start_line;
+        Some(CodeLens {
+            range: Range {
+                start: Position { line, character: 0 },
+                end: Position { line, character: 0 },
+            },
+            command: Some(command),
+            data: None,
+        })
+    }
+
     fn create_controller_lens(
         &self,
         controller_name: &str,
@@ -211,40 +281,46 @@ impl CodeLensHandler {
         }
     }
 
-    /// JSコントローラー用のCodeLens（バインドされたHTMLテンプレートを表示）
-    fn create_template_lens(
-        &self,
-        _controller_name: &str,
-        line: u32,
-        templates: &[String],
-    ) -> CodeLens {
-        // テンプレートパスからファイル名のみを抽出して表示
-        let template_names: Vec<String> = templates
+    /// コントローラー定義行の「Used in N templates」CodeLens
+    ///
+    /// バインドされている HTML テンプレート (ng-controller / route / modal、設定により
+    /// ng-include / ng-view 継承も) を集計し、クリックで Location 一覧を開く。
+    /// ファイルに解決できない templateUrl は "(not resolved)" として件数に含める
+    fn create_template_lens(&self, symbol: &Symbol) -> Option<CodeLens> {
+        let templates = self
+            .index
+            .get_templates_for_controller(&symbol.name, self.config.include_inherited_templates);
+        let unresolved = self
+            .index
+            .templates
+            .get_templates_for_controller(&symbol.name)
             .iter()
-            .map(|t| t.rsplit('/').next().unwrap_or(t).to_string())
-            .collect();
-
-        let title = if template_names.len() == 1 {
-            format!("Template: {}", template_names[0])
-        } else {
-            format!("Templates: {}", template_names.join(", "))
-        };
+            .filter(|path| self.index.resolve_template_uri(path).is_none())
+            .count();
+        if templates.is_empty() && unresolved == 0 {
+            return None;
+        }
 
-        // テンプレートURIを取得してLocationの配列を作成
-        let locations: Vec<serde_json::Value> = templates
-            .iter()
-            .filter_map(|template_path| {
-                self.index.resolve_template_uri(template_path).map(|uri| {
-                    serde_json::json!({
-                        "uri": uri.to_string(),
-                        "range": {
-                            "start": { "line": 0, "character": 0 },
-                            "end": { "line": 0, "character": 0 }
-                        }
-                    })
-                })
+        let locations: Vec<Location> = templates
+            .into_iter()
+            .map(|(uri, line)| Location {
+                uri,
+                range: Range {
+                    start: Position { line, character: 0 },
+                    end: Position { line, character: 0 },
+                },
             })
             .collect();
+        let count = locations.len() + unresolved;
+        let title = if count == 1 {
+            "Used in 1 template".to_string()
+        } else {
+            format!("Used in {} templates", count)
+        };
+        let position = Position {
+            line: symbol.name_span.start_line,
+            character: symbol.name_span.start_col,
+        };
 
         let command = if locations.is_empty() {
             Command {
@@ -253,27 +329,31 @@ impl CodeLensHandler {
                 arguments: None,
             }
         } else {
+            let title = if unresolved > 0 {
+                format!("{} ({} not resolved)", title, unresolved)
+            } else {
+                title
+            };
             Command {
                 title,
-                command: "angularjs.openLocation".to_string(),
-                arguments: Some(vec![serde_json::json!(locations)]),
+                command: "angularjs.showReferences".to_string(),
+                arguments: Some(vec![
+                    serde_json::json!(symbol.uri.to_string()),
+                    serde_json::json!(position),
+                    serde_json::json!(locations),
+                ]),
             }
         };
 
-        CodeLens {
+        let line = symbol.name_span.start_line;
+        Some(CodeLens {
             range: Range {
-                start: Position {
-                    line,
-                    character: 0,
-                },
-                end: Position {
-                    line,
-                    character: 0,
-                },
+                start: Position { line, character: 0 },
+                end: Position { line, character: 0 },
             },
             command: Some(command),
             data: None,
-        }
+        })
     }
 
     /// 呼び出し元（親ファイル）へのCodeLens（ファイル先頭に表示）
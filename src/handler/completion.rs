@@ -1,12 +1,101 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 
+use serde_json::{json, Value};
 use tower_lsp::lsp_types::*;
 
+use crate::analyzer::html::directives::{normalize_directive_attr, NG_BUILTIN_ATTRIBUTE_DIRECTIVES};
+use crate::analyzer::html::filters::NG_BUILTIN_FILTERS;
+use crate::analyzer::js::builtin_service_methods::builtin_service_methods;
 use crate::index::Index;
-use crate::model::SymbolKind;
+use crate::model::{HtmlFormBinding, HtmlLocalVariableSource, SymbolKind};
 use crate::util::{camel_to_kebab, kebab_to_camel};
 
+/// AngularJS 1.x 公式の組み込みモジュール (`angular.module` の依存配列で使う)
+const NG_BUILTIN_MODULES: &[&str] = &[
+    "ngAnimate",
+    "ngAria",
+    "ngCookies",
+    "ngMessageFormat",
+    "ngMessages",
+    "ngMock",
+    "ngParseExt",
+    "ngResource",
+    "ngRoute",
+    "ngSanitize",
+    "ngTouch",
+];
+
+/// `FormController` の状態プロパティ (`myForm.$valid` など)
+const NG_FORM_STATE_PROPERTIES: &[(&str, &str)] = &[
+    ("$valid", "True if all controls are valid"),
+    ("$invalid", "True if at least one control is invalid"),
+    ("$pristine", "True if the user has not interacted with the form yet"),
+    ("$dirty", "True if the user has changed at least one control"),
+    ("$submitted", "True if the form has been submitted"),
+    ("$pending", "Controls with pending asynchronous validations"),
+    ("$error", "Map of validation error names to the controls that failed them"),
+    ("$name", "The form's name attribute"),
+];
+
+/// `NgModelController` の状態プロパティ (`myForm.email.$touched` など)
+const NG_MODEL_STATE_PROPERTIES: &[(&str, &str)] = &[
+    ("$valid", "True if there are no validation errors"),
+    ("$invalid", "True if there is at least one validation error"),
+    ("$pristine", "True if the user has not changed the value yet"),
+    ("$dirty", "True if the user has changed the value"),
+    ("$touched", "True if the control has lost focus"),
+    ("$untouched", "True if the control has not lost focus yet"),
+    ("$pending", "Validations that are still pending"),
+    ("$error", "Map of failing validation names to true"),
+    ("$viewValue", "Actual string value in the view"),
+    ("$modelValue", "The value in the model that the control is bound to"),
+    ("$name", "The control's name attribute"),
+];
+
+/// `bindings` の値（`'<?alias'` など）を (モード文字, optional か, 別名) に分解する
+fn parse_binding_type(binding_type: &str) -> (Option<char>, bool, Option<&str>) {
+    let trimmed = binding_type.trim();
+    let mut chars = trimmed.chars();
+    let mode = chars.next().filter(|c| matches!(c, '<' | '=' | '@' | '&'));
+    if mode.is_none() {
+        return (None, false, None);
+    }
+    let rest = chars.as_str();
+    let optional = rest.starts_with('?');
+    let alias = rest.trim_start_matches('?').trim();
+    (mode, optional, (!alias.is_empty()).then_some(alias))
+}
+
+/// バインディングモードの説明
+fn binding_mode_description(mode: Option<char>) -> Option<&'static str> {
+    match mode? {
+        '<' => Some("one-way binding"),
+        '=' => Some("two-way binding"),
+        '@' => Some("string binding"),
+        '&' => Some("expression callback"),
+        _ => None,
+    }
+}
+
+/// AngularJS 由来の補完候補であることを示す `CompletionItem.data` の `origin` 値
+const ANGULARJS_COMPLETION_ORIGIN: &str = "angularjs";
+
+/// AngularJS 由来の補完候補に付ける `data`。ドキュメントは `completionItem/resolve`
+/// で `symbol` (インデックス上の完全名) から引いて埋める
+fn angularjs_completion_data(symbol_name: &str) -> Value {
+    json!({ "origin": ANGULARJS_COMPLETION_ORIGIN, "symbol": symbol_name })
+}
+
+/// `data` が AngularJS 由来の補完候補を示していれば、そのシンボル名を返す
+pub fn angularjs_completion_symbol(item: &CompletionItem) -> Option<&str> {
+    let data = item.data.as_ref()?;
+    if data.get("origin")?.as_str()? != ANGULARJS_COMPLETION_ORIGIN {
+        return None;
+    }
+    data.get("symbol")?.as_str()
+}
+
 /// HTML補完候補のラベル重複を避けつつ追加するヘルパー
 fn push_unique(items: &mut Vec<CompletionItem>, seen: &mut HashSet<String>, item: CompletionItem) {
     if seen.insert(item.label.clone()) {
@@ -18,6 +107,48 @@ pub struct CompletionHandler {
     index: Arc<Index>,
 }
 
+/// `<form name="myForm">` のプロパティ補完
+///
+/// - `myForm.` → 配下のコントロール名とフォームの状態プロパティ
+/// - `myForm.email.` → コントロールの状態プロパティ (入れ子フォームの名前は対象外)
+fn complete_form_chain(form: &HtmlFormBinding, rest: Option<&str>) -> Vec<CompletionItem> {
+    let state_item = |(name, description): &(&str, &str), detail: String| CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::PROPERTY),
+        detail: Some(detail),
+        documentation: Some(Documentation::String(description.to_string())),
+        ..Default::default()
+    };
+
+    match rest {
+        None => form
+            .field_names
+            .iter()
+            .map(|field| CompletionItem {
+                label: field.clone(),
+                kind: Some(CompletionItemKind::FIELD),
+                detail: Some(format!("{} (form control)", form.name)),
+                ..Default::default()
+            })
+            .chain(
+                NG_FORM_STATE_PROPERTIES
+                    .iter()
+                    .map(|prop| state_item(prop, format!("{} (form state)", form.name))),
+            )
+            .collect(),
+        Some(field)
+            if form.field_names.iter().any(|f| f == field)
+                && !form.nested_form_names.iter().any(|f| f == field) =>
+        {
+            NG_MODEL_STATE_PROPERTIES
+                .iter()
+                .map(|prop| state_item(prop, format!("{}.{} (ngModel state)", form.name, field)))
+                .collect()
+        }
+        Some(_) => Vec::new(),
+    }
+}
+
 impl CompletionHandler {
     pub fn new(index: Arc<Index>) -> Self {
         Self { index }
@@ -26,21 +157,28 @@ impl CompletionHandler {
     /// サービスプレフィックスに基づいて補完候補を返す
     /// service_prefix: "ServiceName" の場合、"ServiceName.xxx" のメソッドのみ返す
     /// service_prefix: "$scope" の場合、current_controller の $scope プロパティを返す
+    /// service_prefix: "$scope.user" の場合、`$scope.user` の子プロパティを返す
+    /// service_prefix: "$http" などの組み込みサービスの場合、DIされていれば静的なメソッド一覧を返す
     /// injected_services: 現在のコントローラーでDIされているサービス（優先表示）
+    ///
+    /// プレフィックスがあって AngularJS 側の候補が見つからなければ `None`
+    /// (`$scope.items.` のような配列・文字列のメンバーは tsserver に任せる)
     pub fn complete_with_context(
         &self,
         service_prefix: Option<&str>,
         current_controller: Option<&str>,
         injected_services: &[String],
     ) -> Option<CompletionResponse> {
-        let definitions = self.index.definitions.get_all_definitions();
-
         let items: Vec<CompletionItem> = if let Some(prefix) = service_prefix {
             if prefix == "$rootScope" {
                 // $rootScope. の場合、全モジュールの $rootScope プロパティを返す
                 let mut seen_props: HashSet<String> = HashSet::new();
                 let mut items: Vec<CompletionItem> = Vec::new();
 
+                let definitions = self
+                    .index
+                    .definitions
+                    .get_definitions_matching(|name| name.contains(".$rootScope."));
                 for symbol in definitions.iter().filter(|s| {
                     s.kind == SymbolKind::RootScopeProperty
                         || s.kind == SymbolKind::RootScopeMethod
@@ -71,12 +209,7 @@ impl CompletionHandler {
                                 "{} ($rootScope {})",
                                 module_name, type_str
                             )),
-                            documentation: symbol.docs.clone().map(|docs| {
-                                Documentation::MarkupContent(MarkupContent {
-                                    kind: MarkupKind::Markdown,
-                                    value: docs,
-                                })
-                            }),
+                            data: Some(angularjs_completion_data(&symbol.name)),
                             ..Default::default()
                         });
                     }
@@ -89,6 +222,16 @@ impl CompletionHandler {
                 let mut items: Vec<CompletionItem> = Vec::new();
 
                 // 定義からプロパティを収集
+                let definitions = match current_controller {
+                    Some(current) => self
+                        .index
+                        .definitions
+                        .get_definitions_with_prefix(&format!("{}.$scope.", current)),
+                    None => self
+                        .index
+                        .definitions
+                        .get_definitions_matching(|name| name.contains(".$scope.")),
+                };
                 for symbol in definitions.iter().filter(|s| {
                     s.kind == SymbolKind::ScopeProperty || s.kind == SymbolKind::ScopeMethod
                 }) {
@@ -98,6 +241,12 @@ impl CompletionHandler {
                         let controller_name = parts[0];
                         let prop_name = parts[1].to_string();
 
+                        // `angular.extend` で展開したネストプロパティ (`user.name`) は
+                        // `$scope.` 直下の候補ではない
+                        if prop_name.contains('.') {
+                            continue;
+                        }
+
                         // 現在のコントローラーが指定されている場合、それ以外はスキップ
                         if let Some(current) = current_controller {
                             if controller_name != current {
@@ -126,12 +275,7 @@ impl CompletionHandler {
                                 "{} (scope {})",
                                 controller_name, type_str
                             )),
-                            documentation: symbol.docs.clone().map(|docs| {
-                                Documentation::MarkupContent(MarkupContent {
-                                    kind: MarkupKind::Markdown,
-                                    value: docs,
-                                })
-                            }),
+                            data: Some(angularjs_completion_data(&symbol.name)),
                             ..Default::default()
                         });
                     }
@@ -177,6 +321,7 @@ impl CompletionHandler {
                                     "{} (scope property, reference only)",
                                     controller_name
                                 )),
+                                data: Some(angularjs_completion_data(&ref_name)),
                                 ..Default::default()
                             });
                         }
@@ -184,6 +329,40 @@ impl CompletionHandler {
                 }
 
                 items
+            } else if let Some(path) = prefix.strip_prefix("$scope.") {
+                // $scope.user. の場合、Ctrl.$scope.user の子プロパティを返す
+                let parents: Vec<String> = match current_controller {
+                    Some(controller) => vec![format!("{}.$scope.{}", controller, path)],
+                    None => {
+                        let suffix = format!(".$scope.{}", path);
+                        self.index
+                            .definitions
+                            .get_definitions_matching(|name| name.ends_with(&suffix))
+                            .into_iter()
+                            .map(|s| s.name)
+                            .collect()
+                    }
+                };
+                self.complete_member_chain(&parents)
+            } else if let Some(methods) = builtin_service_methods(prefix) {
+                // 注入されていない組み込みサービスは候補に出さない
+                if !injected_services.iter().any(|s| s == prefix) {
+                    return Some(CompletionResponse::Array(Vec::new()));
+                }
+                methods
+                    .iter()
+                    .map(|(name, signature, description)| CompletionItem {
+                        label: name.to_string(),
+                        kind: Some(if signature.contains('(') {
+                            CompletionItemKind::METHOD
+                        } else {
+                            CompletionItemKind::PROPERTY
+                        }),
+                        detail: Some(format!("{}.{}", prefix, signature)),
+                        documentation: Some(Documentation::String(description.to_string())),
+                        ..Default::default()
+                    })
+                    .collect()
             } else {
                 // サービス名/コントローラー名が指定された場合、そのプレフィックスを持つ
                 // メソッド (service の `.method()` や controller の `this.X`) のみを返す。
@@ -193,11 +372,17 @@ impl CompletionHandler {
                 // プレフィックス分岐で扱う候補なので、ここでは除外する
                 // (HTML 補完で `update` (Function) と `$scope.update` (Method) が
                 //  同時に出る重複の原因となるため)。
+                //
+                // ネストプロパティ (`MyCtrl.user.name`) は直下の候補ではないので除外する
                 let method_prefix = format!("{}.", prefix);
-                definitions
+                self.index
+                    .definitions
+                    .get_definitions_with_prefix(&method_prefix)
                     .into_iter()
                     .filter(|s| {
-                        s.name.starts_with(&method_prefix)
+                        s.name
+                            .strip_prefix(&method_prefix)
+                            .is_some_and(|rest| !rest.contains('.'))
                             && !matches!(
                                 s.kind,
                                 SymbolKind::ScopeProperty
@@ -222,12 +407,7 @@ impl CompletionHandler {
                                 prefix,
                                 symbol.kind.as_str()
                             )),
-                            documentation: symbol.docs.map(|docs| {
-                                Documentation::MarkupContent(MarkupContent {
-                                    kind: MarkupKind::Markdown,
-                                    value: docs,
-                                })
-                            }),
+                            data: Some(angularjs_completion_data(&symbol.name)),
                             ..Default::default()
                         }
                     })
@@ -240,13 +420,16 @@ impl CompletionHandler {
             let injected_set: HashSet<&str> =
                 injected_services.iter().map(|s| s.as_str()).collect();
 
-            definitions
+            self.index
+                .definitions
+                .get_all_definitions()
                 .into_iter()
                 .filter(|s| {
                     s.kind != SymbolKind::Method
                         && s.kind != SymbolKind::ScopeProperty
                         && s.kind != SymbolKind::ScopeMethod
                         && s.kind != SymbolKind::Controller
+                        && s.kind != SymbolKind::Event
                 })
                 .map(|symbol| {
                     let kind = self.symbol_kind_to_completion_kind(symbol.kind);
@@ -268,21 +451,107 @@ impl CompletionHandler {
                         kind: Some(kind),
                         detail: Some(detail),
                         sort_text: Some(sort_text),
-                        documentation: symbol.docs.map(|docs| {
-                            Documentation::MarkupContent(MarkupContent {
-                                kind: MarkupKind::Markdown,
-                                value: docs,
-                            })
-                        }),
+                        data: Some(angularjs_completion_data(&symbol.name)),
                         ..Default::default()
                     }
                 })
                 .collect()
         };
 
+        if service_prefix.is_some() && items.is_empty() {
+            return None;
+        }
         Some(CompletionResponse::Array(items))
     }
 
+    /// `parents` の各シンボル直下の子プロパティを補完候補で返す
+    ///
+    /// 例: `Ctrl.$scope.user` → `Ctrl.$scope.user.name` / `Ctrl.$scope.user.address` から
+    /// `name`, `address` を返す。`user.address.city` のような深いネストも 1 階層ずつ辿る。
+    pub fn complete_member_chain(&self, parents: &[String]) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for parent in parents {
+            let child_prefix = format!("{}.", parent);
+            for symbol in self.index.definitions.get_definitions_with_prefix(&child_prefix) {
+                let child = &symbol.name[child_prefix.len()..];
+                if child.contains('.') {
+                    continue;
+                }
+
+                let is_function =
+                    symbol.kind == SymbolKind::ScopeMethod || symbol.parameters.is_some();
+                let (item_kind, type_str) = if is_function {
+                    (CompletionItemKind::FUNCTION, "function")
+                } else {
+                    (CompletionItemKind::PROPERTY, "property")
+                };
+                push_unique(
+                    &mut items,
+                    &mut seen,
+                    CompletionItem {
+                        label: child.to_string(),
+                        kind: Some(item_kind),
+                        detail: Some(format!("{} ({})", parent, type_str)),
+                        data: Some(angularjs_completion_data(&symbol.name)),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        items
+    }
+
+    /// HTML の Angular 式で `vm.user.` / `user.` のようなメンバーチェーンの子プロパティを返す
+    ///
+    /// `chain` はカーソル直前の `.` を除いたチェーン (`vm.user`)。先頭がフォーム名なら
+    /// コントロール名/状態プロパティ ([`complete_form_chain`])。コントローラーの
+    /// エイリアスなら `Ctrl.user` / `Ctrl.$scope.user`、それ以外はスコープ内コントローラーの
+    /// `Ctrl.$scope.<chain>` の子を返す。エイリアス単体 (`vm.`) は対象外
+    pub fn complete_html_member_chain(&self, uri: &Url, line: u32, chain: &str) -> Vec<CompletionItem> {
+        let (head, rest) = match chain.split_once('.') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (chain, None),
+        };
+
+        if let Some(form) = self.index.find_form_binding_definition(uri, head, line) {
+            return complete_form_chain(&form, rest);
+        }
+
+        let mut aliases = self.index.controllers.get_html_alias_mappings(uri, line);
+        // component template の controllerAs エイリアス ($ctrl)
+        if let Some((alias, controller_name)) = self
+            .index
+            .components
+            .get_component_binding_for_template(uri)
+            .and_then(|binding| Some((binding.controller_as, binding.controller_name?)))
+        {
+            aliases.entry(alias).or_insert(controller_name);
+        }
+
+        let parents: Vec<String> = match aliases.get(head) {
+            Some(controller_name) => {
+                let Some(path) = rest else {
+                    return Vec::new();
+                };
+                vec![
+                    format!("{}.{}", controller_name, path),
+                    format!("{}.$scope.{}", controller_name, path),
+                ]
+            }
+            None => self
+                .index
+                .resolve_controllers_for_html(uri, line)
+                .iter()
+                .map(|controller_name| format!("{}.$scope.{}", controller_name, chain))
+                .collect(),
+        };
+
+        self.complete_member_chain(&parents)
+    }
+
     /// HTMLテンプレート内のAngular式コンテキスト（{{ ... }} や ng-* 属性値内）の補完候補を返す
     ///
     /// 含まれる候補:
@@ -292,6 +561,7 @@ impl CompletionHandler {
     /// - フォームバインディングと継承されたフォームバインディング
     /// - ng-controller の "as" エイリアス
     /// - component template の controllerAs エイリアス（デフォルト $ctrl）
+    /// - 全テンプレート共通の $rootScope プロパティ/メソッド
     pub fn complete_in_html_angular_context(
         &self,
         uri: &Url,
@@ -427,6 +697,62 @@ impl CompletionHandler {
             );
         }
 
+        // $rootScope プロパティはどのテンプレートからも参照できる
+        // (同名の $scope プロパティやローカル変数があればそちらを優先)
+        for symbol in self.index.definitions.get_all_definitions().iter().filter(|s| {
+            s.kind == SymbolKind::RootScopeProperty || s.kind == SymbolKind::RootScopeMethod
+        }) {
+            let Some((module_name, prop_name)) = symbol.name.split_once(".$rootScope.") else {
+                continue;
+            };
+            let item_kind = if symbol.kind == SymbolKind::RootScopeMethod {
+                CompletionItemKind::FUNCTION
+            } else {
+                CompletionItemKind::PROPERTY
+            };
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: prop_name.to_string(),
+                    kind: Some(item_kind),
+                    detail: Some(format!("{} ($rootScope)", module_name)),
+                    data: Some(angularjs_completion_data(&symbol.name)),
+                    ..Default::default()
+                },
+            );
+        }
+
+        items
+    }
+
+    /// イベントディレクティブ (`ng-click` など) のハンドラ式で使える特殊変数
+    ///
+    /// `$event` と、ng-repeat 内なら `$index`。引数位置で使うことが多いので先頭に並べる
+    pub fn complete_event_locals(&self, uri: &Url, line: u32) -> Vec<CompletionItem> {
+        let mut items = vec![CompletionItem {
+            label: "$event".to_string(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some("event object ($event)".to_string()),
+            sort_text: Some("0$event".to_string()),
+            ..Default::default()
+        }];
+
+        let in_ng_repeat = self
+            .index
+            .html
+            .get_local_variables_at(uri, line)
+            .iter()
+            .any(|var| var.name == "$index" && var.source == HtmlLocalVariableSource::NgRepeatSpecial);
+        if in_ng_repeat {
+            items.push(CompletionItem {
+                label: "$index".to_string(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some("ng-repeat index ($index)".to_string()),
+                sort_text: Some("0$index".to_string()),
+                ..Default::default()
+            });
+        }
         items
     }
 
@@ -443,7 +769,6 @@ impl CompletionHandler {
         prefix: &str,
     ) -> Vec<CompletionItem> {
         let camel_name = kebab_to_camel(element_tag_name);
-        let prefix_str = format!("{}.", camel_name);
 
         // 当該componentが存在しない場合は空（誤った要素名で binding を提案しない）
         let component_exists = self
@@ -456,34 +781,227 @@ impl CompletionHandler {
             return Vec::new();
         }
 
-        self.index
+        let mut bindings = self.index.components.get_bindings(&camel_name);
+        if bindings.is_empty() {
+            // キャッシュ読み込み直後は ComponentStore が空なので、
+            // component 定義内の binding シンボルから復元する
+            bindings = self
+                .index
+                .definitions
+                .get_component_bindings(&camel_name)
+                .into_iter()
+                .filter_map(|s| {
+                    let name = s.name.rsplit('.').next()?.to_string();
+                    Some((name, s.binding_type.unwrap_or_default()))
+                })
+                .collect();
+        }
+
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+        for (binding_name, binding_type) in bindings {
+            let (mode, optional, alias) = parse_binding_type(&binding_type);
+            // `onSelect: '&onSelected'` のように別名指定があれば HTML 属性名は別名側
+            let attr_name = camel_to_kebab(alias.unwrap_or(&binding_name));
+            if !prefix.is_empty() && !attr_name.starts_with(prefix) {
+                continue;
+            }
+
+            let mut detail = format!("{} binding", camel_name);
+            if !binding_type.is_empty() {
+                detail.push_str(&format!(": {}", binding_type));
+            }
+            if let Some(description) = binding_mode_description(mode) {
+                detail.push_str(&format!(" ({})", description));
+            }
+            if optional {
+                detail.push_str(" [optional]");
+            }
+
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: attr_name,
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    detail: Some(detail),
+                    documentation: Some(Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: format!("`{}.{}`", camel_name, binding_name),
+                    })),
+                    ..Default::default()
+                },
+            );
+        }
+        items
+    }
+
+    /// タグ内の属性名位置で AngularJS 組み込みディレクティブ属性 (`ng-*`) を補完する
+    ///
+    /// prefix: 入力中の属性名 (`ng-` または `data-ng-` で始まる)
+    /// existing_attrs: 同じタグに既に書かれている属性名。`ng-model` と
+    /// `data-ng-model` / `ng:model` は同一ディレクティブなので、どれかがあれば全て除外する
+    pub fn complete_ng_attributes(
+        &self,
+        prefix: &str,
+        existing_attrs: &[String],
+    ) -> Vec<CompletionItem> {
+        let existing: HashSet<_> = existing_attrs
+            .iter()
+            .map(|a| normalize_directive_attr(a))
+            .collect();
+        let data_prefixed = prefix.starts_with("data-");
+
+        NG_BUILTIN_ATTRIBUTE_DIRECTIVES
+            .iter()
+            .filter(|name| !existing.contains(**name))
+            .map(|name| {
+                if data_prefixed {
+                    format!("data-{}", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .filter(|label| label.starts_with(prefix))
+            .map(|label| CompletionItem {
+                label,
+                kind: Some(CompletionItemKind::PROPERTY),
+                detail: Some("builtin directive".to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// `{{ x | <ここ> }}` のフィルター名位置で、インデックス済みのフィルター定義と
+    /// AngularJS 組み込みフィルターを補完候補として返す
+    ///
+    /// ユーザー定義フィルターは `detail` に登録先モジュール名を、組み込みフィルターは
+    /// `"builtin filter"` を付ける。同名のユーザー定義がある場合はそちらを優先する。
+    pub fn complete_filters(&self) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for symbol in self
+            .index
             .definitions
             .get_all_definitions()
             .into_iter()
-            .filter(|s| s.kind == SymbolKind::ComponentBinding && s.name.starts_with(&prefix_str))
-            .filter_map(|s| {
-                let binding_name = s.name.strip_prefix(&prefix_str)?.to_string();
-                let kebab_binding = camel_to_kebab(&binding_name);
-                if !prefix.is_empty() && !kebab_binding.starts_with(prefix) {
-                    return None;
-                }
-                let detail = s
-                    .docs
-                    .clone()
-                    .map(|d| format!("{} ({})", camel_name, d))
-                    .unwrap_or_else(|| format!("{} binding", camel_name));
-                Some(CompletionItem {
-                    label: kebab_binding,
-                    kind: Some(CompletionItemKind::PROPERTY),
+            .filter(|s| s.kind == SymbolKind::Filter)
+        {
+            let detail = match symbol.module {
+                Some(ref module_name) => format!("{} (filter)", module_name),
+                None => "filter".to_string(),
+            };
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: symbol.name.clone(),
+                    kind: Some(CompletionItemKind::FUNCTION),
                     detail: Some(detail),
-                    documentation: s.docs.clone().map(|docs| {
-                        Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: docs,
-                        })
-                    }),
+                    data: Some(angularjs_completion_data(&symbol.name)),
                     ..Default::default()
-                })
+                },
+            );
+        }
+
+        for filter in NG_BUILTIN_FILTERS {
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: filter.name.to_string(),
+                    kind: Some(CompletionItemKind::FUNCTION),
+                    detail: Some("builtin filter".to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        items
+    }
+
+    /// `angular.module('app', ['<ここ>'])` の依存配列内でモジュール名を補完する
+    ///
+    /// ワークスペースで定義済みのモジュールと組み込みモジュールを返す。
+    /// prefix: 入力中の文字列リテラルの内容（空ならフィルタなし）
+    /// excluded: 定義中のモジュール自身と既に列挙済みの依存（候補から除外）
+    pub fn complete_module_dependencies(
+        &self,
+        prefix: &str,
+        excluded: &[String],
+    ) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = Vec::new();
+        let mut seen: HashSet<String> = excluded.iter().cloned().collect();
+
+        for symbol in self
+            .index
+            .definitions
+            .get_all_definitions()
+            .into_iter()
+            .filter(|s| s.kind == SymbolKind::Module && s.name.starts_with(prefix))
+        {
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: symbol.name.clone(),
+                    kind: Some(CompletionItemKind::MODULE),
+                    detail: Some("module".to_string()),
+                    data: Some(angularjs_completion_data(&symbol.name)),
+                    ..Default::default()
+                },
+            );
+        }
+
+        for name in NG_BUILTIN_MODULES.iter().filter(|n| n.starts_with(prefix)) {
+            push_unique(
+                &mut items,
+                &mut seen,
+                CompletionItem {
+                    label: name.to_string(),
+                    kind: Some(CompletionItemKind::MODULE),
+                    detail: Some("builtin module".to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        items
+    }
+
+    /// templateUrl / ng-include のパス文字列内でテンプレートファイルを補完する
+    ///
+    /// entries: `list_template_path_entries` の結果 (名前, ディレクトリか)
+    /// replace_range: 入力中パスの最後の `/` 以降 (置換対象のファイル名部分)
+    ///
+    /// ディレクトリは `views/` のように末尾 `/` 付きで返し、続けて `/` トリガーで
+    /// その下の階層を補完できるようにする
+    pub fn complete_template_paths(
+        &self,
+        entries: &[(String, bool)],
+        replace_range: Range,
+    ) -> Vec<CompletionItem> {
+        entries
+            .iter()
+            .map(|(name, is_dir)| {
+                let (label, kind, detail) = if *is_dir {
+                    (format!("{}/", name), CompletionItemKind::FOLDER, "directory")
+                } else {
+                    (name.clone(), CompletionItemKind::FILE, "template")
+                };
+                CompletionItem {
+                    label: label.clone(),
+                    kind: Some(kind),
+                    detail: Some(detail.to_string()),
+                    // ディレクトリを先に並べる
+                    sort_text: Some(format!("{}{}", if *is_dir { 0 } else { 1 }, label)),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                        range: replace_range,
+                        new_text: label,
+                    })),
+                    ..Default::default()
+                }
             })
             .collect()
     }
@@ -535,12 +1053,7 @@ impl CompletionHandler {
                     label: kebab_name,
                     kind: Some(CompletionItemKind::CLASS),
                     detail: Some(detail),
-                    documentation: symbol.docs.map(|docs| {
-                        Documentation::MarkupContent(MarkupContent {
-                            kind: MarkupKind::Markdown,
-                            value: docs,
-                        })
-                    }),
+                    data: Some(angularjs_completion_data(&symbol.name)),
                     ..Default::default()
                 })
             })
@@ -553,6 +1066,31 @@ impl CompletionHandler {
         }
     }
 
+    /// `completionItem/resolve`: AngularJS 由来の候補にシンボルの JSDoc を埋める
+    ///
+    /// 同名の定義が複数ある場合はドキュメントを持つ最初の定義を使う。
+    pub fn resolve(&self, mut item: CompletionItem) -> CompletionItem {
+        if item.documentation.is_some() {
+            return item;
+        }
+        let Some(symbol_name) = angularjs_completion_symbol(&item) else {
+            return item;
+        };
+        item.documentation = self
+            .index
+            .definitions
+            .get_definitions(symbol_name)
+            .into_iter()
+            .find_map(|symbol| symbol.docs)
+            .map(|docs| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: docs,
+                })
+            });
+        item
+    }
+
     fn symbol_kind_to_completion_kind(&self, kind: SymbolKind) -> CompletionItemKind {
         match kind {
             SymbolKind::Module => CompletionItemKind::MODULE,
@@ -573,7 +1111,9 @@ impl CompletionHandler {
             SymbolKind::FormBinding => CompletionItemKind::VARIABLE,
             SymbolKind::ExportedComponent => CompletionItemKind::CLASS,
             SymbolKind::ComponentBinding => CompletionItemKind::PROPERTY,
+            SymbolKind::DirectiveBinding => CompletionItemKind::PROPERTY,
             SymbolKind::UiRouterState => CompletionItemKind::EVENT,
+            SymbolKind::Event => CompletionItemKind::EVENT,
         }
     }
 }
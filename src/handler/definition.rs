@@ -3,9 +3,11 @@ use std::sync::Arc;
 use tower_lsp::lsp_types::*;
 use tracing::debug;
 
+use crate::analyzer::html::ng_include::find_ng_include_path_at;
+use super::resolve::{locate_symbol_at, LocatedSymbol};
 use crate::index::{HtmlResolution, Index};
-use crate::model::{HtmlDirectiveReference, HtmlUiSrefReference, SymbolKind};
-use crate::util::is_html_file;
+use crate::model::{DirectiveUsageType, HtmlDirectiveReference, HtmlUiSrefReference, SymbolKind};
+use crate::util::{is_html_file, utf16_col_to_byte_col};
 
 pub struct DefinitionHandler {
     index: Arc<Index>,
@@ -24,22 +26,39 @@ impl DefinitionHandler {
         &self,
         params: GotoDefinitionParams,
         source: Option<&str>,
+    ) -> Option<GotoDefinitionResponse> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let located = locate_symbol_at(&self.index, uri, position.line, position.character, source);
+        self.goto_definition_with_located(params, source, located.as_ref())
+    }
+
+    /// `located` はカーソル位置で [`locate_symbol_at`] (`source` 付き) した結果
+    pub fn goto_definition_with_located(
+        &self,
+        params: GotoDefinitionParams,
+        source: Option<&str>,
+        located: Option<&LocatedSymbol>,
     ) -> Option<GotoDefinitionResponse> {
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
 
         // HTMLファイルの場合は専用の処理
         if is_html_file(&uri) {
-            return self.goto_definition_from_html(&uri, position, source);
+            return self.goto_definition_from_html(&uri, position, source, located);
         }
 
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
-            position.line,
-            position.character,
-        )?;
+        // templateUrl の文字列上 → テンプレート HTML へ
+        if let Some(template_path) =
+            source.and_then(|s| find_template_url_path_at(s, position.line, position.character))
+        {
+            let targets = self.index.templates.resolve_template_targets(&template_path);
+            if !targets.is_empty() {
+                return Some(file_locations(targets));
+            }
+        }
 
-        let definitions = self.index.definitions.get_definitions(&symbol_name);
+        let definitions = self.index.definitions.get_definitions(&located?.name);
 
         if definitions.is_empty() {
             return None;
@@ -65,10 +84,29 @@ impl DefinitionHandler {
         uri: &Url,
         position: Position,
         source: Option<&str>,
+        located: Option<&LocatedSymbol>,
     ) -> Option<GotoDefinitionResponse> {
-        match self.index.resolve_html_position(uri, position, source)? {
+        // ng-include のパス文字列上 → 対象 HTML ファイルへ
+        if let Some(template_path) =
+            source.and_then(|s| find_ng_include_path_at(s, position.line, position.character))
+        {
+            return self.build_for_ng_include(uri, &template_path);
+        }
+
+        let Some(resolution) = located.and_then(|located| located.html.clone()) else {
+            // 先頭行で他に解決できるものがなければ、テンプレートにバインドされた
+            // コントローラー定義へ
+            if position.line == 0 {
+                return self.goto_template_controllers(uri);
+            }
+            return None;
+        };
+
+        match resolution {
             HtmlResolution::UiSref(r) => self.build_for_ui_sref(&r),
             HtmlResolution::Directive(r) => self.build_for_directive(&r),
+            HtmlResolution::Filter(name) => self.build_for_filter(&name),
+            HtmlResolution::Controller(name) => self.build_for_controller(&name),
             HtmlResolution::LocalVarDef(v) | HtmlResolution::LocalVarRef(v) => {
                 Some(scalar(&v.uri, v.name_span().to_lsp_range()))
             }
@@ -107,6 +145,98 @@ impl DefinitionHandler {
         Some(GotoDefinitionResponse::Array(locations))
     }
 
+    /// テンプレート HTML にバインドされたコントローラーの定義位置を返す
+    ///
+    /// `$routeProvider` / `$uibModal` など複数箇所からバインドされている場合は
+    /// 全コントローラーを返す。コンポーネントテンプレートはコンポーネントの
+    /// controller (未指定ならコンポーネント自身) を返す
+    pub fn goto_template_controllers(&self, uri: &Url) -> Option<GotoDefinitionResponse> {
+        let mut controller_names: Vec<String> = Vec::new();
+        for (controller_name, _, _, _) in self.index.templates.get_all_template_binding_sources(uri)
+        {
+            if !controller_names.contains(&controller_name) {
+                controller_names.push(controller_name);
+            }
+        }
+        let component_controller = self
+            .index
+            .components
+            .get_component_binding_for_template(uri)
+            .and_then(|binding| binding.controller_name)
+            .filter(|name| !controller_names.contains(name));
+        controller_names.extend(component_controller);
+
+        let locations: Vec<Location> = controller_names
+            .iter()
+            .flat_map(|name| self.index.definitions.get_definitions(name))
+            .filter(|d| d.kind == SymbolKind::Controller || d.kind == SymbolKind::Component)
+            .map(|def| Location {
+                uri: def.uri.clone(),
+                range: def.name_span.to_lsp_range(),
+            })
+            .collect();
+        if locations.is_empty() {
+            return None;
+        }
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+
+    fn build_for_ng_include(
+        &self,
+        parent_uri: &Url,
+        template_path: &str,
+    ) -> Option<GotoDefinitionResponse> {
+        let targets = self
+            .index
+            .templates
+            .resolve_ng_include_targets(parent_uri, template_path);
+        if targets.is_empty() {
+            debug!(
+                "goto_definition_from_html: ng-include '{}' did not resolve to a workspace file",
+                template_path
+            );
+            return None;
+        }
+        Some(file_locations(targets))
+    }
+
+    fn build_for_filter(&self, filter_name: &str) -> Option<GotoDefinitionResponse> {
+        // 組み込みフィルターは定義がないので None (tsserver にも解決できない)
+        let locations: Vec<Location> = self
+            .index
+            .definitions
+            .get_definitions(filter_name)
+            .into_iter()
+            .filter(|d| d.kind == SymbolKind::Filter)
+            .map(|def| Location {
+                uri: def.uri.clone(),
+                range: def.definition_span.to_lsp_range(),
+            })
+            .collect();
+        if locations.is_empty() {
+            return None;
+        }
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+
+    fn build_for_controller(&self, controller_name: &str) -> Option<GotoDefinitionResponse> {
+        let locations: Vec<Location> = self
+            .index
+            .definitions
+            .get_definitions(controller_name)
+            .into_iter()
+            .filter(|d| d.kind == SymbolKind::Controller)
+            .map(|def| Location {
+                uri: def.uri.clone(),
+                range: def.definition_span.to_lsp_range(),
+            })
+            .collect();
+        if locations.is_empty() {
+            return None;
+        }
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+
     fn build_for_directive(
         &self,
         directive_ref: &HtmlDirectiveReference,
@@ -115,9 +245,15 @@ impl DefinitionHandler {
             .index
             .definitions
             .get_definitions(&directive_ref.directive_name);
+        // component は要素としてしか使えないので、属性使用なら directive のみ
         let directive_defs: Vec<_> = definitions
             .into_iter()
-            .filter(|d| d.kind == SymbolKind::Directive || d.kind == SymbolKind::Component)
+            .filter(|d| match directive_ref.usage_type {
+                DirectiveUsageType::Element => {
+                    d.kind == SymbolKind::Directive || d.kind == SymbolKind::Component
+                }
+                DirectiveUsageType::Attribute => d.kind == SymbolKind::Directive,
+            })
             .collect();
         if directive_defs.is_empty() {
             return None;
@@ -223,3 +359,61 @@ fn scalar(uri: &Url, range: Range) -> GotoDefinitionResponse {
         range,
     })
 }
+
+/// ファイル先頭を指す Location の一覧 (テンプレートファイルへのジャンプ用)
+fn file_locations(uris: Vec<Url>) -> GotoDefinitionResponse {
+    GotoDefinitionResponse::Array(
+        uris.into_iter()
+            .map(|uri| Location {
+                uri,
+                range: Range::default(),
+            })
+            .collect(),
+    )
+}
+
+/// カーソル位置が `templateUrl: '...'` の文字列リテラル上にあれば、その値を返す
+///
+/// `col` は LSP の UTF-16 列。行内のバイト位置と比べるためにバイト列へ変換する
+fn find_template_url_path_at(source: &str, line: u32, col: u32) -> Option<String> {
+    let line_text = source.lines().nth(line as usize)?;
+    let col = utf16_col_to_byte_col(source, line, col) as usize;
+
+    // 行内の文字列リテラル (開始クォート位置, 終了クォート位置) を列挙
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (i, c) in line_text.char_indices() {
+        match open {
+            Some((start, q)) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    open = None;
+                    if start < col && col <= i && is_template_url_key(&line_text[..start]) {
+                        return Some(line_text[start + 1..i].to_string());
+                    }
+                }
+            }
+            None if c == '\'' || c == '"' || c == '`' => open = Some((i, c)),
+            None => {}
+        }
+    }
+    None
+}
+
+/// 文字列リテラル直前のテキストが `templateUrl:` (キーのクォート有無は問わない) か
+fn is_template_url_key(before_literal: &str) -> bool {
+    let Some(key_part) = before_literal.trim_end().strip_suffix(':') else {
+        return false;
+    };
+    let key = key_part.trim_end().trim_end_matches(['\'', '"']);
+    match key.strip_suffix("templateUrl") {
+        Some(rest) => !rest
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$'),
+        None => false,
+    }
+}
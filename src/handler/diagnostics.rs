@@ -1,10 +1,24 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, Position, Range, Url};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    Position, Range, Url,
+};
 use tracing::debug;
 
+use super::code_action::{AddInjection, AddScopeProperty, QuickFixData};
+use crate::analyzer::js::services::is_builtin_service;
 use crate::config::DiagnosticsConfig;
 use crate::index::Index;
+use crate::model::{HtmlLocalVariable, HtmlLocalVariableSource, Symbol, SymbolKind};
+use crate::util::{levenshtein_distance, resolve_relative_path};
+
+/// 使っていなくても未使用の注入として報告しないサービス
+///
+/// `$scope` はテンプレートとの結び付けのため、`$element` / `$attrs` / `$transclude` は
+/// ディレクティブのコントローラーの定型として注入されることが多い
+const UNUSED_INJECTION_ALLOWLIST: &[&str] = &["$scope", "$element", "$attrs", "$transclude"];
 
 /// 診断ハンドラー
 pub struct DiagnosticsHandler {
@@ -41,12 +55,31 @@ impl DiagnosticsHandler {
 
         let mut diagnostics = Vec::new();
 
+        // 解析に失敗したファイル (インデックスは前回の解析結果のまま)
+        diagnostics.extend(self.check_analysis_failure(uri));
+
         // スコープ参照のチェック
         diagnostics.extend(self.check_scope_references(uri));
 
         // ローカル変数参照のチェック
         diagnostics.extend(self.check_local_variable_references(uri));
 
+        // ng-repeat の変数によるシャドーイング
+        if self.config.ng_repeat_shadowing {
+            diagnostics.extend(self.check_ng_repeat_shadowing(uri));
+        }
+
+        // ng-include のテンプレートファイル存在チェック
+        diagnostics.extend(self.check_missing_template_files(uri));
+
+        // 値を読むだけの式の中の代入
+        diagnostics.extend(self.check_expression_assignments(uri));
+
+        // HTML の構文エラー
+        if self.config.html_syntax {
+            diagnostics.extend(self.check_html_syntax_errors(uri));
+        }
+
         diagnostics
     }
 
@@ -58,6 +91,9 @@ impl DiagnosticsHandler {
 
         let mut diagnostics = Vec::new();
 
+        // 解析に失敗したファイル (インデックスは前回の解析結果のまま)
+        diagnostics.extend(self.check_analysis_failure(uri));
+
         // 未使用スコープ変数のチェック
         if self.config.unused_scope_variables {
             diagnostics.extend(self.check_unused_scope_variables(uri));
@@ -66,9 +102,376 @@ impl DiagnosticsHandler {
         // DI 配列の要素数と関数の引数数の不一致チェック
         diagnostics.extend(self.check_di_arity_mismatch(uri));
 
+        // DI 配列と関数の引数の位置ずれチェック
+        diagnostics.extend(self.check_di_order_mismatch(uri));
+
+        // 定義の見つからない注入サービスのチェック
+        diagnostics.extend(self.check_undefined_injected_services(uri));
+
+        // 注入したが使っていないサービスのチェック
+        if self.config.unused_injection {
+            diagnostics.extend(self.check_unused_injections(uri));
+        }
+
+        // 使っているが注入していないサービスのチェック
+        if self.config.missing_injection {
+            diagnostics.extend(self.check_missing_injections(uri));
+        }
+
+        // 同名 controller / service / factory の重複定義チェック
+        diagnostics.extend(self.check_duplicate_definitions(uri));
+
+        // templateUrl のテンプレートファイル存在チェック
+        diagnostics.extend(self.check_missing_template_files(uri));
+
         diagnostics
     }
 
+    /// 直近の解析に失敗していれば、ファイル先頭に info 診断を出す
+    ///
+    /// 補完や診断が効かない理由をユーザーが確認できるようにする
+    fn check_analysis_failure(&self, uri: &Url) -> Option<Diagnostic> {
+        let failure = self.index.diagnostics.get_analysis_failure(uri)?;
+        Some(Diagnostic {
+            range: Range::default(),
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            source: Some("angularjs-lsp".to_string()),
+            message: format!("AngularJS analysis skipped: {}", failure.reason()),
+            ..Default::default()
+        })
+    }
+
+    /// DI 配列と関数の引数の位置ずれを診断する
+    ///
+    /// 重要度は要素数不一致と同じ `di_arity_severity` を使う。
+    /// 検出ロジックの詳細は `AngularJsAnalyzer::check_di_params` を参照。
+    fn check_di_order_mismatch(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = Self::severity_from_str(&self.config.di_arity_severity);
+
+        self.index
+            .diagnostics
+            .get_di_order_issues(uri)
+            .into_iter()
+            .map(|issue| Diagnostic {
+                range: issue.span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: format!(
+                    "DI array injects '{}' at position {} but the parameter is named '{}'; the dependency order does not match the function parameters",
+                    issue.service_name,
+                    issue.position + 1,
+                    issue.param_name
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    /// `templateUrl` / `ng-include` のパスがワークスペース内のファイルに解決できるかを診断する
+    ///
+    /// `resolve_relative_path` で取り出したファイル名がワークスペーススキャンで見つかった
+    /// ファイルに無ければ警告する。動的連結は先頭の文字列リテラル部分で判定し、
+    /// ファイル名まで確定しないもの (`'views/' + name`) はスキップする。
+    /// ワークスペースが未スキャンの場合は判定できないので何もしない。
+    fn check_missing_template_files(&self, uri: &Url) -> Vec<Diagnostic> {
+        if !self.index.templates.has_workspace_files() {
+            return Vec::new();
+        }
+        let severity = self.parse_severity();
+
+        self.index
+            .diagnostics
+            .get_template_paths(uri)
+            .into_iter()
+            .filter(|usage| {
+                let file_name = resolve_relative_path(uri, &usage.path);
+                (file_name.ends_with(".html") || file_name.ends_with(".htm"))
+                    && !self.index.templates.workspace_file_exists(&file_name)
+            })
+            .map(|usage| Diagnostic {
+                range: usage.span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: format!(
+                    "Template file '{}' was not found in the workspace",
+                    usage.path
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    /// `ng-if="status = 'active'"` のように、値を読むだけの式に書かれた代入を診断する
+    ///
+    /// 代入が正当な属性 (`ng-init` / イベントハンドラ) は解析時に除外済み
+    fn check_expression_assignments(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = self.parse_severity();
+
+        self.index
+            .diagnostics
+            .get_expression_assignments(uri)
+            .into_iter()
+            .map(|assignment| Diagnostic {
+                range: assignment.span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: format!(
+                    "Assignment in '{}' expression; did you mean '=='?",
+                    assignment.attribute
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    /// 解析時に記録した HTML の構文エラー (tree-sitter の ERROR / MISSING ノード) を診断する
+    ///
+    /// エラー回復後のツリーでも AngularJS の解析は続くため、重要度は `html_syntax_severity`
+    /// (既定は hint) で控えめに出す
+    fn check_html_syntax_errors(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = Self::severity_from_str(&self.config.html_syntax_severity);
+
+        self.index
+            .diagnostics
+            .get_html_syntax_errors(uri)
+            .into_iter()
+            .map(|error| Diagnostic {
+                range: error.span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: match error.missing {
+                    Some(token) => format!("HTML syntax error: missing '{}'", token),
+                    None => "HTML syntax error".to_string(),
+                },
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    /// ワークスペース全体で同名の controller / service / factory が複数登録されて
+    /// いないかを診断する
+    ///
+    /// 診断はこのファイル内の各定義箇所に出し、他の定義箇所を related information
+    /// として付ける。service と factory は同じ injector 名前空間を共有するため
+    /// 相互に重複とみなす。`allow_cross_module_duplicates` が有効な場合は
+    /// 登録先モジュールが同じものだけを重複とする。
+    fn check_duplicate_definitions(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = self.parse_severity();
+        let mut diagnostics = Vec::new();
+
+        let local_defs = self
+            .index
+            .definitions
+            .get_definitions_for_uri(uri)
+            .into_iter()
+            .filter(|s| duplicate_namespace(s.kind).is_some());
+
+        for symbol in local_defs {
+            let others: Vec<Symbol> = self
+                .index
+                .definitions
+                .get_definitions(&symbol.name)
+                .into_iter()
+                .filter(|other| {
+                    duplicate_namespace(other.kind) == duplicate_namespace(symbol.kind)
+                        && !(other.uri == symbol.uri && other.name_span == symbol.name_span)
+                        && (!self.config.allow_cross_module_duplicates
+                            || other.module == symbol.module)
+                })
+                .collect();
+            if others.is_empty() {
+                continue;
+            }
+
+            let related_information = others
+                .iter()
+                .map(|other| DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: other.uri.clone(),
+                        range: other.name_span.to_lsp_range(),
+                    },
+                    message: format!(
+                        "Other definition: {}:{}",
+                        other.uri.path_segments().and_then(|mut s| s.next_back()).unwrap_or(""),
+                        other.name_span.start_line + 1
+                    ),
+                })
+                .collect();
+
+            diagnostics.push(Diagnostic {
+                range: symbol.name_span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: format!(
+                    "{} '{}' is defined {} times in the workspace",
+                    symbol.kind.as_str(),
+                    symbol.name,
+                    others.len() + 1
+                ),
+                related_information: Some(related_information),
+                tags: None,
+                data: None,
+            });
+        }
+
+        diagnostics
+    }
+
+    /// DI 配列 / `$inject` で注入しているサービスの定義が存在するかを診断する
+    ///
+    /// 組み込みサービスと `known_services` に列挙されたサービスは除外する。
+    /// `config()` で注入する `UserServiceProvider` は provider `UserService` の
+    /// 定義があれば解決済みとみなす。
+    fn check_undefined_injected_services(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = self.parse_severity();
+
+        self.index
+            .diagnostics
+            .get_injected_services(uri)
+            .into_iter()
+            .filter(|service| !self.is_known_service(&service.name))
+            .map(|service| Diagnostic {
+                range: service.span.to_lsp_range(),
+                severity: Some(severity),
+                code: None,
+                code_description: None,
+                source: Some("angularjs-lsp".to_string()),
+                message: format!(
+                    "Injected service '{}' is not defined in the workspace",
+                    service.name
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    /// DI で注入したが関数本体で一度も使っていないサービスを診断する
+    ///
+    /// 解析時に記録した引数と本体の識別子の差分を hint として出す。`$scope` のように
+    /// 使わなくても注入しておくのが妥当なものは `UNUSED_INJECTION_ALLOWLIST` で除外する
+    fn check_unused_injections(&self, uri: &Url) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for usage in self.index.diagnostics.get_injection_usages(uri) {
+            let unused = usage.params.iter().filter(|param| {
+                !usage.used_identifiers.contains_key(&param.name)
+                    && !UNUSED_INJECTION_ALLOWLIST.contains(&param.service.as_str())
+            });
+            for param in unused {
+                diagnostics.push(Diagnostic {
+                    range: param.span.to_lsp_range(),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    code: None,
+                    code_description: None,
+                    source: Some("angularjs-lsp".to_string()),
+                    message: format!("Injected service '{}' is never used", param.service),
+                    related_information: None,
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    data: None,
+                });
+            }
+        }
+
+        diagnostics
+    }
+
+    /// DI 関数の本体で使っているが注入していないサービスを診断する
+    ///
+    /// 本体に現れる識別子のうち、ワークスペースで service / factory として定義された名前で、
+    /// 引数にも本体内や外側のスコープ (ファイル直下の `import` / `require` を含む) の宣言にも
+    /// ないもの。注入位置が分かれば
+    /// `data` に DI 配列と引数リストへの追加位置を入れる
+    fn check_missing_injections(&self, uri: &Url) -> Vec<Diagnostic> {
+        let severity = self.parse_severity();
+        let mut diagnostics = Vec::new();
+
+        for usage in self.index.diagnostics.get_injection_usages(uri) {
+            let mut missing: Vec<_> = usage
+                .used_identifiers
+                .iter()
+                .filter(|(name, _)| {
+                    !usage.declared_identifiers.contains(*name)
+                        && !usage.params.iter().any(|param| &param.name == *name)
+                        && self.index.definitions.get_definitions(name).iter().any(|s| {
+                            matches!(s.kind, SymbolKind::Service | SymbolKind::Factory)
+                        })
+                })
+                .collect();
+            missing.sort_by_key(|(_, span)| (span.start_line, span.start_col));
+
+            for (name, span) in missing {
+                let data = usage.param_insert.clone().map(|param_insert| QuickFixData {
+                    add_injection: Some(AddInjection {
+                        service: name.clone(),
+                        dependency_insert: usage.dependency_insert.clone(),
+                        param_insert,
+                    }),
+                    ..Default::default()
+                });
+                diagnostics.push(Diagnostic {
+                    range: span.to_lsp_range(),
+                    severity: Some(severity),
+                    code: None,
+                    code_description: None,
+                    source: Some("angularjs-lsp".to_string()),
+                    message: format!("Service '{}' is used but not injected", name),
+                    related_information: None,
+                    tags: None,
+                    data: data.and_then(QuickFixData::into_value),
+                });
+            }
+        }
+
+        diagnostics
+    }
+
+    /// 注入可能なサービスとして解決できる名前か
+    fn is_known_service(&self, name: &str) -> bool {
+        if is_builtin_service(name) || self.config.known_services.iter().any(|s| s == name) {
+            return true;
+        }
+
+        let definitions = &self.index.definitions;
+        let is_injectable = |name: &str| {
+            definitions.get_definitions(name).iter().any(|s| {
+                matches!(
+                    s.kind,
+                    SymbolKind::Service
+                        | SymbolKind::Factory
+                        | SymbolKind::Provider
+                        | SymbolKind::Value
+                        | SymbolKind::Constant
+                )
+            })
+        };
+
+        if is_injectable(name) {
+            return true;
+        }
+        name.strip_suffix("Provider")
+            .is_some_and(|base| definitions.has_definition_of_kind(base, SymbolKind::Provider))
+    }
+
     /// DI 配列の要素数と関数の引数数の不一致を診断する
     ///
     /// アナライザーが解析時に収集した `DiArityIssue` を読み出して LSP 診断に変換する。
@@ -124,6 +527,11 @@ impl DiagnosticsHandler {
                 continue;
             };
 
+            // ネストしたプロパティ (`user.name`) の使用状況は親プロパティで判断する
+            if symbol.name.contains(".$scope.") && property_name.contains('.') {
+                continue;
+            }
+
             // HTML内での参照があるかチェック
             let is_referenced_in_html =
                 self.index.is_scope_variable_referenced(&symbol.name);
@@ -191,12 +599,20 @@ impl DiagnosticsHandler {
     }
 
     /// スコープ参照（vm.xxx, $scope.xxx）のチェック
+    ///
+    /// ローカル変数・フォームバインディング（継承分を含む）は除外し、
+    /// コントローラーが JS 側で一つも解決できない場合は警告を出さない。
     fn check_scope_references(&self, uri: &Url) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
-        let severity = self.parse_severity();
+        let severity = match &self.config.undefined_scope_reference_severity {
+            Some(s) => Self::severity_from_str(s),
+            None => self.parse_severity(),
+        };
 
         // 全スコープ参照を取得
         let references = self.index.html.get_html_scope_references(uri);
+        // did-you-mean の候補は接頭辞 (`Ctrl.$scope.` / `Ctrl.`) ごとに一度だけ集める
+        let mut candidates = HashMap::new();
 
         for reference in references {
             // 動的式（配列アクセス）はスキップ
@@ -291,7 +707,13 @@ impl DiagnosticsHandler {
                     }
 
                     // 定義が見つからない場合は警告
-                    diagnostics.push(Diagnostic {
+                    let suggestion = self.suggest_scope_property(
+                        &mut candidates,
+                        std::slice::from_ref(&controller_name),
+                        property,
+                        true,
+                    );
+                    diagnostics.push(with_suggestion(Diagnostic {
                         range: Range {
                             start: Position {
                                 line: reference.start_line,
@@ -313,7 +735,7 @@ impl DiagnosticsHandler {
                         related_information: None,
                         tags: None,
                         data: None,
-                    });
+                    }, suggestion));
                 }
                 // aliasが解決できない場合（コントローラーが見つからない）は警告を出さない
             } else {
@@ -380,7 +802,19 @@ impl DiagnosticsHandler {
 
                 // コントローラーのJS定義が存在する場合のみ警告
                 if !found && any_controller_defined {
-                    diagnostics.push(Diagnostic {
+                    let suggestion =
+                        self.suggest_scope_property(&mut candidates, &controllers, property, false);
+                    // `$scope` に追加するクイックフィックスは最も内側の JS 定義のあるコントローラーに
+                    let add_scope_property = controllers
+                        .iter()
+                        .rev()
+                        .find(|c| self.index.controllers.find_controller_scope(c).is_some())
+                        .filter(|_| !property.contains('.'))
+                        .map(|controller| AddScopeProperty {
+                            controller: controller.clone(),
+                            property: property.to_string(),
+                        });
+                    let mut diagnostic = with_suggestion(Diagnostic {
                         range: Range {
                             start: Position {
                                 line: reference.start_line,
@@ -402,7 +836,13 @@ impl DiagnosticsHandler {
                         related_information: None,
                         tags: None,
                         data: None,
-                    });
+                    }, suggestion);
+                    if add_scope_property.is_some() {
+                        let mut data = QuickFixData::from_diagnostic(&diagnostic).unwrap_or_default();
+                        data.add_scope_property = add_scope_property;
+                        diagnostic.data = data.into_value();
+                    }
+                    diagnostics.push(diagnostic);
                 }
             }
         }
@@ -410,6 +850,59 @@ impl DiagnosticsHandler {
         diagnostics
     }
 
+    /// 未定義のスコープ参照 `property` に綴りの近い、同じコントローラーのプロパティを探す
+    ///
+    /// 大文字小文字を無視した編集距離が 1〜2 (4 文字以下の名前は 1) のもののうち
+    /// 最も近いもの。大文字小文字だけの違いも候補にする。`include_this` なら
+    /// controller as 構文の `this.x` (`{ctrl}.x`) も候補にする。
+    ///
+    /// `candidates` は接頭辞ごとの候補 (接頭辞を除いた名前と定義) のキャッシュで、
+    /// 診断 1 回分の間使い回す
+    fn suggest_scope_property(
+        &self,
+        candidates: &mut HashMap<String, Vec<(String, Symbol)>>,
+        controllers: &[String],
+        property: &str,
+        include_this: bool,
+    ) -> Option<Symbol> {
+        if property.contains('.') {
+            return None;
+        }
+        let max_distance = if property.chars().count() <= 4 { 1 } else { 2 };
+        let lower = property.to_lowercase();
+
+        let mut prefixes = Vec::new();
+        for controller in controllers {
+            prefixes.push(format!("{}.$scope.", controller));
+            if include_this {
+                prefixes.push(format!("{}.", controller));
+            }
+        }
+
+        for prefix in &prefixes {
+            if !candidates.contains_key(prefix) {
+                let symbols = self
+                    .index
+                    .definitions
+                    .get_definitions_with_prefix(prefix)
+                    .into_iter()
+                    .map(|symbol| (symbol.name[prefix.len()..].to_string(), symbol))
+                    .filter(|(name, _)| !name.contains('.') && !name.starts_with('$'))
+                    .collect();
+                candidates.insert(prefix.clone(), symbols);
+            }
+        }
+
+        prefixes
+            .iter()
+            .flat_map(|prefix| &candidates[prefix])
+            .filter(|(name, _)| name != property)
+            .map(|(name, symbol)| (levenshtein_distance(&lower, &name.to_lowercase()), name, symbol))
+            .filter(|(distance, _, _)| *distance <= max_distance)
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+            .map(|(_, _, symbol)| symbol.clone())
+    }
+
     /// ローカル変数参照のチェック
     fn check_local_variable_references(&self, uri: &Url) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
@@ -468,4 +961,135 @@ impl DiagnosticsHandler {
 
         diagnostics
     }
+
+    /// ng-repeat の変数 (`item` / `(key, value)`) が、外側のローカル変数や
+    /// コントローラーの `$scope` プロパティと同名で、それらを隠していないかを診断する
+    ///
+    /// 外側の変数は、スコープの要素が内側の変数のスコープの要素を真に含み、定義位置が前にあるもの。
+    /// `$index` などの暗黙の変数はネストすれば必ず重なるので対象外
+    fn check_ng_repeat_shadowing(&self, uri: &Url) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let variables = self.index.html.get_all_local_variables(uri);
+        let is_repeat_variable = |v: &HtmlLocalVariable| {
+            matches!(
+                v.source,
+                HtmlLocalVariableSource::NgRepeatIterator | HtmlLocalVariableSource::NgRepeatKeyValue
+            )
+        };
+
+        for variable in variables.iter().filter(|v| is_repeat_variable(v)) {
+            let name_span = variable.name_span();
+            let outer = variables
+                .iter()
+                .filter(|o| {
+                    o.name == variable.name
+                        && o.source != HtmlLocalVariableSource::NgRepeatSpecial
+                        && o.scope_start_byte <= variable.scope_start_byte
+                        && o.scope_end_byte >= variable.scope_end_byte
+                        && (o.scope_start_byte, o.scope_end_byte)
+                            != (variable.scope_start_byte, variable.scope_end_byte)
+                        && (o.name_start_line, o.name_start_col)
+                            < (name_span.start_line, name_span.start_col)
+                })
+                .max_by_key(|o| (o.name_start_line, o.name_start_col));
+
+            if let Some(outer) = outer {
+                diagnostics.push(Diagnostic {
+                    range: name_span.to_lsp_range(),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    code: None,
+                    code_description: None,
+                    source: Some("angularjs-lsp".to_string()),
+                    message: format!(
+                        "ng-repeat variable '{}' shadows the {} variable on line {}",
+                        variable.name,
+                        outer.source.as_str(),
+                        outer.name_start_line + 1
+                    ),
+                    related_information: Some(vec![DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: uri.clone(),
+                            range: outer.name_span().to_lsp_range(),
+                        },
+                        message: "Shadowed variable".to_string(),
+                    }]),
+                    tags: None,
+                    data: None,
+                });
+                continue;
+            }
+
+            let scope_property = self
+                .index
+                .resolve_controllers_for_html(uri, variable.scope_start_line)
+                .into_iter()
+                .rev()
+                .find_map(|controller| {
+                    let symbol_name = format!("{}.$scope.{}", controller, variable.name);
+                    let symbol = self.index.definitions.get_definitions(&symbol_name).into_iter().next()?;
+                    Some((controller, symbol))
+                });
+
+            if let Some((controller, symbol)) = scope_property {
+                diagnostics.push(Diagnostic {
+                    range: name_span.to_lsp_range(),
+                    severity: Some(DiagnosticSeverity::HINT),
+                    code: None,
+                    code_description: None,
+                    source: Some("angularjs-lsp".to_string()),
+                    message: format!(
+                        "ng-repeat variable '{}' hides the $scope property '{}' of controller '{}'",
+                        variable.name, variable.name, controller
+                    ),
+                    related_information: Some(vec![DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: symbol.uri.clone(),
+                            range: symbol.name_span.to_lsp_range(),
+                        },
+                        message: "Hidden $scope property".to_string(),
+                    }]),
+                    tags: None,
+                    data: None,
+                });
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// 未定義参照の診断に綴りの近い候補を付ける
+///
+/// 候補の定義位置を related information に、候補名を `data` の `didYouMean` に入れる。
+/// `data` は `CodeActionHandler` が参照を候補名に置き換えるのに使う
+fn with_suggestion(mut diagnostic: Diagnostic, suggestion: Option<Symbol>) -> Diagnostic {
+    let Some(symbol) = suggestion else {
+        return diagnostic;
+    };
+    let name = symbol.name.rsplit('.').next().unwrap_or(&symbol.name).to_string();
+    diagnostic.message = format!("{}; did you mean '{}'?", diagnostic.message, name);
+    diagnostic.related_information = Some(vec![DiagnosticRelatedInformation {
+        location: Location {
+            uri: symbol.uri.clone(),
+            range: symbol.name_span.to_lsp_range(),
+        },
+        message: format!("Did you mean '{}'?", name),
+    }]);
+    diagnostic.data = QuickFixData {
+        did_you_mean: Some(name),
+        ..Default::default()
+    }
+    .into_value();
+    diagnostic
+}
+
+/// 重複定義チェックで同じ名前空間とみなす kind のグループ
+///
+/// controller は `$controller`、service / factory は `$injector` に登録される。
+fn duplicate_namespace(kind: SymbolKind) -> Option<u8> {
+    match kind {
+        SymbolKind::Controller => Some(0),
+        SymbolKind::Service | SymbolKind::Factory => Some(1),
+        _ => None,
+    }
 }
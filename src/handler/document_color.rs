@@ -0,0 +1,463 @@
+//! Document color handler.
+//!
+//! HTML テンプレート中の CSS カラーを色プレビューとして返す。
+//!
+//! - `ng-style="{ color: '#ff0000', border: '1px solid rgb(0, 0, 0)' }"` の値の文字列
+//!   (オブジェクトリテラルの解析は [`object_literal_string_values`] を使う)
+//! - `<style>` 内のクラス定義などの宣言値 (`.error { color: red; }`)
+//!
+//! 認識する表記は `#rgb` / `#rgba` / `#rrggbb` / `#rrggbbaa`、`rgb()` / `rgba()`、
+//! 基本色の名前。色の変更時は元の表記 (hex / rgb / 名前) を優先した書き換えを返す。
+
+use tower_lsp::lsp_types::{Color, ColorInformation, ColorPresentation, Range, TextEdit, Url};
+use tree_sitter::{Node, Tree};
+
+use crate::analyzer::html::directives::normalize_directive_attr;
+use crate::analyzer::html::expression::object_literal_string_values;
+use crate::analyzer::html::parser::HtmlParser;
+use crate::util::{is_html_file, offset_to_position, position_to_offset};
+
+/// 名前で認識する基本色 (CSS 2.1 の 17 色と、よく使われる別名)
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0, 0, 0)),
+    ("silver", (192, 192, 192)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("white", (255, 255, 255)),
+    ("maroon", (128, 0, 0)),
+    ("red", (255, 0, 0)),
+    ("purple", (128, 0, 128)),
+    ("fuchsia", (255, 0, 255)),
+    ("magenta", (255, 0, 255)),
+    ("green", (0, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("olive", (128, 128, 0)),
+    ("yellow", (255, 255, 0)),
+    ("navy", (0, 0, 128)),
+    ("blue", (0, 0, 255)),
+    ("teal", (0, 128, 128)),
+    ("aqua", (0, 255, 255)),
+    ("cyan", (0, 255, 255)),
+    ("orange", (255, 165, 0)),
+];
+
+#[derive(Default)]
+pub struct DocumentColorHandler;
+
+impl DocumentColorHandler {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// `textDocument/documentColor` を処理する
+    ///
+    /// `cached_tree` は `source` からパース済みの Tree (キャッシュヒット時のみ)
+    pub fn document_colors(
+        &self,
+        uri: &Url,
+        source: &str,
+        cached_tree: Option<Tree>,
+    ) -> Option<Vec<ColorInformation>> {
+        if !is_html_file(uri) {
+            return None;
+        }
+        let tree = cached_tree.or_else(|| HtmlParser::new().parse(source))?;
+
+        let mut spans = Vec::new();
+        collect_color_spans(tree.root_node(), source, &mut spans);
+        Some(
+            spans
+                .into_iter()
+                .filter_map(|(start, end)| {
+                    Some(ColorInformation {
+                        range: Range::new(
+                            offset_to_position(source, start),
+                            offset_to_position(source, end),
+                        ),
+                        color: parse_color(&source[start..end])?,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// `textDocument/colorPresentation` を処理する
+    ///
+    /// `range` にある現在の表記 (hex / rgb / 名前) を先頭にする
+    pub fn color_presentations(&self, source: &str, color: Color, range: Range) -> Vec<ColorPresentation> {
+        let original = text_in_range(source, range);
+        let hex = hex_label(color);
+        let rgb = rgb_label(color);
+        let name = color_name(color);
+
+        let mut labels = Vec::new();
+        let original = original.trim().to_ascii_lowercase();
+        if original.starts_with("rgb") {
+            labels.push(rgb.clone());
+        } else if !original.starts_with('#') {
+            labels.extend(name.map(str::to_string));
+        }
+        labels.extend([hex, rgb]);
+        labels.extend(name.map(str::to_string));
+        let mut seen = Vec::new();
+        labels.retain(|label| {
+            let first = !seen.contains(label);
+            seen.push(label.clone());
+            first
+        });
+
+        labels
+            .into_iter()
+            .map(|label| ColorPresentation {
+                text_edit: Some(TextEdit {
+                    range,
+                    new_text: label.clone(),
+                }),
+                label,
+                additional_text_edits: None,
+            })
+            .collect()
+    }
+}
+
+/// 色の候補になるバイト範囲を集める
+fn collect_color_spans(node: Node, source: &str, spans: &mut Vec<(usize, usize)>) {
+    match node.kind() {
+        "attribute" => {
+            let is_ng_style = node
+                .named_child(0)
+                .is_some_and(|name| normalize_directive_attr(&source[name.byte_range()]) == "ng-style");
+            if let Some(value) = is_ng_style.then(|| attribute_value(node)).flatten() {
+                let expr = &source[value.byte_range()];
+                for (start, end) in object_literal_string_values(expr) {
+                    let base = value.start_byte() + start;
+                    spans.extend(
+                        css_color_tokens(&expr[start..end])
+                            .into_iter()
+                            .map(|(s, e)| (base + s, base + e)),
+                    );
+                }
+            }
+            return;
+        }
+        "raw_text" if node.parent().is_some_and(|p| p.kind() == "style_element") => {
+            let base = node.start_byte();
+            for (start, end) in css_declaration_values(&source[node.byte_range()]) {
+                let value = &source[base + start..base + end];
+                spans.extend(
+                    css_color_tokens(value)
+                        .into_iter()
+                        .map(|(s, e)| (base + start + s, base + start + e)),
+                );
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_color_spans(child, source, spans);
+    }
+}
+
+/// 属性値のノード (クォートの内側)
+fn attribute_value(attribute: Node) -> Option<Node> {
+    let value = attribute.named_child(1)?;
+    match value.kind() {
+        "quoted_attribute_value" => value.named_child(0),
+        _ => Some(value),
+    }
+}
+
+/// CSS の宣言値 (`{ ... }` の中の `:` から `;` / `}` まで) のバイト範囲
+///
+/// セレクタの `a:hover` などを値として読まないよう、波括弧の内側だけを見る
+fn css_declaration_values(css: &str) -> Vec<(usize, usize)> {
+    let bytes = css.as_bytes();
+    let mut values = Vec::new();
+    let mut depth = 0;
+    let mut value_start: Option<usize> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            b'{' => depth += 1,
+            b'}' => {
+                if let Some(start) = value_start.take() {
+                    values.push((start, i));
+                }
+                depth -= 1;
+            }
+            b':' if depth > 0 && value_start.is_none() => value_start = Some(i + 1),
+            b';' => {
+                if let Some(start) = value_start.take() {
+                    values.push((start, i));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    values
+}
+
+/// CSS の値の中の色の表記 (`#hex` / `rgb()` / `rgba()` / 色名) のバイト範囲
+///
+/// 前後が区切り文字 (空白・`,` `(` `)` `;` `:` `!` クォート) の語だけを色とみなし、
+/// `url(...)` の中身 (`url(red.png)` など) は読み飛ばす
+fn css_color_tokens(value: &str) -> Vec<(usize, usize)> {
+    let bytes = value.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'_';
+    let is_delimiter = |b: u8| {
+        b.is_ascii_whitespace() || matches!(b, b',' | b'(' | b')' | b';' | b':' | b'!' | b'\'' | b'"')
+    };
+    let bounded = |start: usize, end: usize| {
+        (start == 0 || is_delimiter(bytes[start - 1]))
+            && bytes.get(end).is_none_or(|&b| is_delimiter(b))
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'#' {
+            let end = (i + 1..bytes.len()).find(|&j| !is_word(bytes[j])).unwrap_or(bytes.len());
+            if bounded(i, end) && parse_color(&value[i..end]).is_some() {
+                tokens.push((i, end));
+            }
+            i = end;
+            continue;
+        }
+        if is_word(bytes[i]) {
+            let end = (i..bytes.len()).find(|&j| !is_word(bytes[j])).unwrap_or(bytes.len());
+            let word = value[i..end].to_ascii_lowercase();
+            let close = (bytes.get(end) == Some(&b'('))
+                .then(|| value[end..].find(')').map(|close| end + close + 1))
+                .flatten();
+            match (word.as_str(), close) {
+                ("rgb" | "rgba", Some(close)) => {
+                    if bounded(i, close) && parse_color(&value[i..close]).is_some() {
+                        tokens.push((i, close));
+                    }
+                    i = close;
+                    continue;
+                }
+                // URL の中身は色として読まない
+                ("url", close) => {
+                    i = close.unwrap_or(bytes.len());
+                    continue;
+                }
+                _ => {}
+            }
+            if bounded(i, end) && parse_color(&word).is_some() {
+                tokens.push((i, end));
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// CSS カラーの表記を `Color` にする
+fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(hex) = text.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    if let Some(args) = text
+        .strip_prefix("rgba(")
+        .or_else(|| text.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        return parse_rgb_args(args);
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == text)
+        .map(|(_, (r, g, b))| rgba(*r, *g, *b, 1.0))
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some(rgba(digit(0)?, digit(1)?, digit(2)?, 1.0)),
+        4 => Some(rgba(digit(0)?, digit(1)?, digit(2)?, digit(3)? as f32 / 255.0)),
+        6 => Some(rgba(pair(0)?, pair(2)?, pair(4)?, 1.0)),
+        8 => Some(rgba(pair(0)?, pair(2)?, pair(4)?, pair(6)? as f32 / 255.0)),
+        _ => None,
+    }
+}
+
+/// `255, 0, 0` / `255 0 0 / 50%` / `100%, 0%, 0%, 0.5`
+fn parse_rgb_args(args: &str) -> Option<Color> {
+    let parts: Vec<&str> = args
+        .split([',', ' ', '/'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    if !(3..=4).contains(&parts.len()) {
+        return None;
+    }
+    let channel = |part: &str| -> Option<f32> {
+        let value = match part.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => part.parse::<f32>().ok()? / 255.0,
+        };
+        Some(value.clamp(0.0, 1.0))
+    };
+    let alpha = match parts.get(3) {
+        Some(part) => match part.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => part.parse::<f32>().ok()?,
+        }
+        .clamp(0.0, 1.0),
+        None => 1.0,
+    };
+    Some(Color {
+        red: channel(parts[0])?,
+        green: channel(parts[1])?,
+        blue: channel(parts[2])?,
+        alpha,
+    })
+}
+
+fn rgba(r: u8, g: u8, b: u8, alpha: f32) -> Color {
+    Color {
+        red: r as f32 / 255.0,
+        green: g as f32 / 255.0,
+        blue: b as f32 / 255.0,
+        alpha,
+    }
+}
+
+fn to_u8(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn hex_label(color: Color) -> String {
+    let rgb = format!(
+        "#{:02x}{:02x}{:02x}",
+        to_u8(color.red),
+        to_u8(color.green),
+        to_u8(color.blue)
+    );
+    if color.alpha < 1.0 {
+        format!("{}{:02x}", rgb, to_u8(color.alpha))
+    } else {
+        rgb
+    }
+}
+
+fn rgb_label(color: Color) -> String {
+    let (r, g, b) = (to_u8(color.red), to_u8(color.green), to_u8(color.blue));
+    if color.alpha < 1.0 {
+        format!("rgba({}, {}, {}, {})", r, g, b, (color.alpha * 100.0).round() / 100.0)
+    } else {
+        format!("rgb({}, {}, {})", r, g, b)
+    }
+}
+
+/// 不透明で基本色と一致すればその名前
+fn color_name(color: Color) -> Option<&'static str> {
+    if color.alpha < 1.0 {
+        return None;
+    }
+    let rgb = (to_u8(color.red), to_u8(color.green), to_u8(color.blue));
+    NAMED_COLORS.iter().find(|(_, c)| *c == rgb).map(|(name, _)| *name)
+}
+
+fn text_in_range(source: &str, range: Range) -> &str {
+    let (start, end) = (position_to_offset(source, range.start), position_to_offset(source, range.end));
+    source.get(start..end).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Position;
+
+    fn colors(source: &str) -> Vec<(String, [u8; 4])> {
+        let uri = Url::parse("file:///a.html").unwrap();
+        let lines: Vec<&str> = source.lines().collect();
+        DocumentColorHandler::new()
+            .document_colors(&uri, source, None)
+            .unwrap()
+            .into_iter()
+            .map(|info| {
+                let line: Vec<u16> = lines[info.range.start.line as usize].encode_utf16().collect();
+                let text = String::from_utf16(
+                    &line[info.range.start.character as usize..info.range.end.character as usize],
+                )
+                .unwrap();
+                let c = info.color;
+                (text, [to_u8(c.red), to_u8(c.green), to_u8(c.blue), to_u8(c.alpha)])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ng_style_string_values_are_colors() {
+        let source = r#"<p ng-style="{ color: '#f00', 'border': '1px solid rgba(0, 0, 255, 0.5)', background: dark ? 'black' : 'white', width: '10px' }">日本</p>"#;
+        assert_eq!(
+            colors(source),
+            vec![
+                ("#f00".to_string(), [255, 0, 0, 255]),
+                ("rgba(0, 0, 255, 0.5)".to_string(), [0, 0, 255, 128]),
+                ("black".to_string(), [0, 0, 0, 255]),
+                ("white".to_string(), [255, 255, 255, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn style_element_declarations_are_colors() {
+        let source = "<style>\n  a:hover { color: Teal; }\n  /* red */ .error { border: 1px solid #00ff0080 }\n</style>\n<p style=\"color: red\">x</p>";
+        assert_eq!(
+            colors(source),
+            vec![
+                ("Teal".to_string(), [0, 128, 128, 255]),
+                ("#00ff0080".to_string(), [0, 255, 0, 128]),
+            ]
+        );
+    }
+
+    #[test]
+    fn urls_and_partial_words_are_not_colors() {
+        let source = r#"<p ng-style="{ background: 'url(img/red.png) no-repeat', border: '1px solid theme.red', color: 'red-ish', outline: 'thin solid linear-gradient(#fff, navy)' }">x</p>"#;
+        assert_eq!(
+            colors(source),
+            vec![
+                ("#fff".to_string(), [255, 255, 255, 255]),
+                ("navy".to_string(), [0, 0, 128, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn presentations_prefer_original_notation() {
+        let handler = DocumentColorHandler::new();
+        let labels = |color, original: &str| -> Vec<String> {
+            let range = Range::new(Position::new(0, 0), Position::new(0, original.len() as u32));
+            handler
+                .color_presentations(original, color, range)
+                .into_iter()
+                .map(|p| p.label)
+                .collect()
+        };
+        let red = rgba(255, 0, 0, 1.0);
+        assert_eq!(labels(red, "red"), vec!["red", "#ff0000", "rgb(255, 0, 0)"]);
+        assert_eq!(labels(red, "rgb(1, 2, 3)"), vec!["rgb(255, 0, 0)", "#ff0000", "red"]);
+        assert_eq!(
+            labels(rgba(0, 0, 255, 0.5), "#00f"),
+            vec!["#0000ff80", "rgba(0, 0, 255, 0.5)"]
+        );
+    }
+}
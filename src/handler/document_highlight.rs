@@ -15,6 +15,7 @@ use std::sync::Arc;
 use tower_lsp::lsp_types::*;
 use tracing::debug;
 
+use super::resolve::locate_symbol_at;
 use crate::index::{HtmlResolution, Index};
 use crate::model::{HtmlFormBinding, HtmlLocalVariable, HtmlUiSrefReference, SymbolKind};
 use crate::util::is_html_file;
@@ -40,22 +41,20 @@ impl DocumentHighlightHandler {
         }
 
         // JS ファイル: シンボル名を取り出し、同 URI のみフィルタして返す
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
-            position.line,
-            position.character,
-        )?;
-
-        self.collect_symbol_highlights_in_uri(&uri, &symbol_name)
+        let located = locate_symbol_at(&self.index, &uri, position.line, position.character, None)?;
+        self.collect_symbol_highlights_in_uri(&uri, &located.name)
     }
 
     /// HTML ファイルのカーソル位置を解決し、同 URI 内の参照のみハイライト
     fn highlight_from_html(&self, uri: &Url, position: Position) -> Option<Vec<DocumentHighlight>> {
-        match self.index.resolve_html_position(uri, position, None)? {
+        match locate_symbol_at(&self.index, uri, position.line, position.character, None)?.html? {
             HtmlResolution::UiSref(r) => self.highlight_for_ui_sref(uri, &r),
             HtmlResolution::Directive(r) => {
                 self.highlight_for_directive(uri, &r.directive_name)
             }
+            HtmlResolution::Filter(name) | HtmlResolution::Controller(name) => {
+                self.collect_symbol_highlights_in_uri(uri, &name)
+            }
             HtmlResolution::LocalVarDef(v) | HtmlResolution::LocalVarRef(v) => {
                 self.highlight_for_local_variable(uri, &v)
             }
@@ -1,18 +1,62 @@
+use std::cmp::Reverse;
 use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
+use tree_sitter::{Node, Tree};
 
+use crate::analyzer::html::directives::normalize_directive_attr;
+use crate::analyzer::html::parser::HtmlParser;
 use crate::index::Index;
+use crate::model::{HtmlLocalVariable, HtmlLocalVariableSource, Span, Symbol, SymbolKind};
+use crate::util::offset_to_position;
 
 pub struct DocumentSymbolHandler {
     index: Arc<Index>,
 }
 
+/// 階層を組み立てる途中のノード
+struct SymbolNode {
+    symbol: DocumentSymbol,
+    children: Vec<SymbolNode>,
+}
+
+impl SymbolNode {
+    /// 子ノードを `DocumentSymbol::children` に畳み込む
+    ///
+    /// LSP は子の range が親の range に含まれることを要求するため、
+    /// 親の range を子の range まで広げる (モジュール定義は名前部分しか持たない等)
+    fn into_document_symbol(self) -> DocumentSymbol {
+        let mut symbol = self.symbol;
+        if self.children.is_empty() {
+            return symbol;
+        }
+        let mut children: Vec<DocumentSymbol> = self
+            .children
+            .into_iter()
+            .map(SymbolNode::into_document_symbol)
+            .collect();
+        children.sort_by_key(|c| c.range.start);
+        for child in &children {
+            symbol.range.start = symbol.range.start.min(child.range.start);
+            symbol.range.end = symbol.range.end.max(child.range.end);
+        }
+        symbol.children = Some(children);
+        symbol
+    }
+}
+
 impl DocumentSymbolHandler {
     pub fn new(index: Arc<Index>) -> Self {
         Self { index }
     }
 
+    /// ドキュメントのアウトラインを階層構造で返す
+    ///
+    /// - Module → そのモジュールに登録された controller / service / filter 等
+    /// - Controller / Service 等 → `$scope.*` プロパティ・メソッド、`this.*` / service メソッド
+    ///
+    /// メンバーの所属は `ControllerScope` の行範囲 (関数本体) で判定し、
+    /// 範囲がなければシンボル名の接頭辞 (`MainCtrl.$scope.save` の `MainCtrl`) で判定する
     pub fn document_symbols(&self, uri: &Url) -> Option<DocumentSymbolResponse> {
         let symbols = self.index.get_document_symbols(uri);
 
@@ -20,60 +64,399 @@ impl DocumentSymbolHandler {
             return None;
         }
 
-        let document_symbols: Vec<DocumentSymbol> = symbols
+        let mut document_symbols: Vec<DocumentSymbol> = self
+            .nest_symbols(uri, &symbols)
             .into_iter()
-            .map(|s| {
-                // Ensure selection_range is contained within range
-                // LSP requires: range.start <= selection_range.start && selection_range.end <= range.end
-                let range_start_line = s
-                    .definition_span
-                    .start_line
-                    .min(s.name_span.start_line);
-                let range_start_col = if range_start_line == s.definition_span.start_line
-                    && range_start_line == s.name_span.start_line
-                {
-                    s.definition_span.start_col.min(s.name_span.start_col)
-                } else if range_start_line == s.definition_span.start_line {
-                    s.definition_span.start_col
-                } else {
-                    s.name_span.start_col
-                };
-                let range_end_line =
-                    s.definition_span.end_line.max(s.name_span.end_line);
-                let range_end_col = if range_end_line == s.definition_span.end_line
-                    && range_end_line == s.name_span.end_line
-                {
-                    s.definition_span.end_col.max(s.name_span.end_col)
-                } else if range_end_line == s.definition_span.end_line {
-                    s.definition_span.end_col
-                } else {
-                    s.name_span.end_col
-                };
+            .map(SymbolNode::into_document_symbol)
+            .collect();
+        document_symbols.sort_by_key(|s| s.range.start);
+
+        Some(DocumentSymbolResponse::Nested(document_symbols))
+    }
+
+    /// HTML テンプレートのアウトライン
+    ///
+    /// ng-controller スコープ・ng-repeat ブロック・`<form name="x">` を行範囲の包含関係で
+    /// 入れ子にする。`<script>` 内の定義は JS と同じ階層で並べる
+    ///
+    /// ブロックの range は要素の実際の範囲 (`cached_tree` は `source` からパース済みの
+    /// Tree、キャッシュヒット時のみ)
+    pub fn document_symbols_html(
+        &self,
+        uri: &Url,
+        source: &str,
+        cached_tree: Option<Tree>,
+    ) -> Option<DocumentSymbolResponse> {
+        let tree = cached_tree.or_else(|| HtmlParser::new().parse(source));
+        let element_range = |directive: &str, start_line: u32, end_line: u32| {
+            let lines = Range::new(Position::new(start_line, 0), Position::new(end_line, 0));
+            tree.as_ref()
+                .and_then(|tree| {
+                    find_directive_element(tree.root_node(), source, directive, start_line, end_line)
+                })
+                .map_or(lines, |node| {
+                    Range::new(
+                        offset_to_position(source, node.start_byte()),
+                        offset_to_position(source, node.end_byte()),
+                    )
+                })
+        };
+        let mut blocks: Vec<(u8, SymbolNode)> = Vec::new();
+
+        for scope in self.index.controllers.get_all_html_controller_scopes(uri) {
+            let name = match &scope.alias {
+                Some(alias) => format!("{} as {}", scope.controller_name, alias),
+                None => scope.controller_name.clone(),
+            };
+            let range = element_range("ng-controller", scope.start_line, scope.end_line);
+            blocks.push((
+                0,
+                html_block(
+                    name,
+                    "ng-controller",
+                    SymbolKind::Controller.to_lsp_symbol_kind(),
+                    range,
+                    range,
+                ),
+            ));
+        }
+
+        // ng-repeat は同じ要素の変数 ((key, value) 等) を 1 ブロックにまとめる
+        let mut repeats: Vec<(u32, u32, Vec<HtmlLocalVariable>)> = Vec::new();
+        for var in self.index.html.get_all_local_variables(uri) {
+            if !matches!(
+                var.source,
+                HtmlLocalVariableSource::NgRepeatIterator
+                    | HtmlLocalVariableSource::NgRepeatKeyValue
+            ) {
+                continue;
+            }
+            match repeats.iter_mut().find(|(start, end, _)| {
+                *start == var.scope_start_line && *end == var.scope_end_line
+            }) {
+                Some((_, _, vars)) => vars.push(var),
+                None => repeats.push((var.scope_start_line, var.scope_end_line, vec![var])),
+            }
+        }
+        for (start_line, end_line, mut vars) in repeats {
+            vars.sort_by_key(|v| (v.name_start_line, v.name_start_col));
+            let name = if vars.len() == 1 {
+                vars[0].name.clone()
+            } else {
+                let names: Vec<&str> = vars.iter().map(|v| v.name.as_str()).collect();
+                format!("({})", names.join(", "))
+            };
+            let selection_range = vars[0].name_span().to_lsp_range();
+            // LSP は range が selection_range を含むことを要求する
+            let range = union_range(element_range("ng-repeat", start_line, end_line), selection_range);
+            blocks.push((
+                1,
+                html_block(
+                    name,
+                    "ng-repeat",
+                    tower_lsp::lsp_types::SymbolKind::ARRAY,
+                    range,
+                    selection_range,
+                ),
+            ));
+        }
+
+        for form in self.index.html.get_all_form_bindings(uri) {
+            let range = Span::new(
+                form.name_start_line,
+                form.name_start_col,
+                form.name_end_line,
+                form.name_end_col,
+            )
+            .to_lsp_range();
+            blocks.push((
+                2,
+                html_block(
+                    form.name.clone(),
+                    "form",
+                    SymbolKind::FormBinding.to_lsp_symbol_kind(),
+                    range,
+                    range,
+                ),
+            ));
+        }
+
+        // 外側のブロックから順に入れる (同じ行範囲なら ng-controller → ng-repeat → form)
+        blocks.sort_by_key(|(rank, node)| {
+            (
+                node.symbol.range.start.line,
+                Reverse(node.symbol.range.end.line),
+                *rank,
+            )
+        });
+        let mut roots: Vec<SymbolNode> = Vec::new();
+        for (_, node) in blocks {
+            insert_by_line_range(&mut roots, node);
+        }
+
+        // <script> 内の定義
+        let script_symbols = self.index.definitions.get_definitions_for_uri(uri);
+        roots.extend(self.nest_symbols(uri, &script_symbols));
+
+        if roots.is_empty() {
+            return None;
+        }
+        let mut document_symbols: Vec<DocumentSymbol> = roots
+            .into_iter()
+            .map(SymbolNode::into_document_symbol)
+            .collect();
+        document_symbols.sort_by_key(|s| s.range.start);
 
-                #[allow(deprecated)]
-                DocumentSymbol {
-                    name: s.name.clone(),
-                    detail: Some(s.kind.as_str().to_string()),
-                    kind: s.kind.to_lsp_symbol_kind(),
-                    tags: None,
-                    deprecated: None,
-                    range: Range {
-                        start: Position {
-                            line: range_start_line,
-                            character: range_start_col,
-                        },
-                        end: Position {
-                            line: range_end_line,
-                            character: range_end_col,
-                        },
-                    },
-                    selection_range: s.name_span.to_lsp_range(),
-                    children: None,
+        Some(DocumentSymbolResponse::Nested(document_symbols))
+    }
+
+    /// AngularJS の定義を Module → 登録コンポーネント → メンバーの階層にする
+    fn nest_symbols(&self, uri: &Url, symbols: &[Symbol]) -> Vec<SymbolNode> {
+        let controller_scopes = self.index.controllers.get_controller_scopes_for_uri(uri);
+        let is_container = |name: &str| {
+            symbols
+                .iter()
+                .any(|s| is_member_container(s.kind) && s.name == name)
+        };
+
+        // 1. メンバーの所属先を決める
+        let owners: Vec<Option<String>> = symbols
+            .iter()
+            .map(|s| {
+                if !is_member(s.kind) {
+                    return None;
                 }
+                let line = s.name_span.start_line;
+                let scope_owner = controller_scopes
+                    .iter()
+                    .filter(|scope| scope.start_line <= line && line <= scope.end_line)
+                    .min_by_key(|scope| scope.end_line - scope.start_line)
+                    .map(|scope| scope.name.clone())
+                    .filter(|name| is_container(name));
+                scope_owner.or_else(|| {
+                    s.name
+                        .split('.')
+                        .next()
+                        .filter(|prefix| *prefix != s.name && is_container(prefix))
+                        .map(|prefix| prefix.to_string())
+                })
             })
             .collect();
 
-        Some(DocumentSymbolResponse::Nested(document_symbols))
+        // 2. コンテナ (controller 等) に所属メンバーを入れる
+        let mut containers: Vec<(usize, SymbolNode)> = Vec::new();
+        let mut top_level: Vec<(usize, SymbolNode)> = Vec::new();
+        for (i, s) in symbols.iter().enumerate() {
+            if owners[i].is_some() {
+                continue;
+            }
+            let node = SymbolNode {
+                symbol: to_document_symbol(s, s.name.clone()),
+                children: Vec::new(),
+            };
+            if is_member_container(s.kind) {
+                containers.push((i, node));
+            } else {
+                top_level.push((i, node));
+            }
+        }
+        for (s, owner) in symbols.iter().zip(&owners) {
+            let Some(owner) = owner else {
+                continue;
+            };
+            let Some((_, container)) = containers
+                .iter_mut()
+                .find(|(i, _)| &symbols[*i].name == owner)
+            else {
+                continue;
+            };
+            let name = s
+                .name
+                .strip_prefix(owner.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+                .unwrap_or(&s.name)
+                .to_string();
+            container.children.push(SymbolNode {
+                symbol: to_document_symbol(s, name),
+                children: Vec::new(),
+            });
+        }
+        top_level.extend(containers);
+
+        // 3. 登録先モジュールの下に入れる
+        let (mut modules, others): (Vec<_>, Vec<_>) = top_level
+            .into_iter()
+            .partition(|(i, _)| symbols[*i].kind == SymbolKind::Module);
+        let mut roots: Vec<SymbolNode> = Vec::new();
+        for (i, node) in others {
+            let registered = &symbols[i];
+            let module_index = registered.module.as_ref().and_then(|module| {
+                let line = registered.name_span.start_line;
+                let same_name = || {
+                    modules
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (m, _))| &symbols[*m].name == module)
+                };
+                // 同名モジュールが複数回 `angular.module(...)` されていれば直前のものに入れる
+                same_name()
+                    .filter(|(_, (m, _))| symbols[*m].name_span.start_line <= line)
+                    .max_by_key(|(_, (m, _))| symbols[*m].name_span.start_line)
+                    .or_else(|| same_name().next())
+                    .map(|(index, _)| index)
+            });
+            match module_index {
+                Some(index) => modules[index].1.children.push(node),
+                None => roots.push(node),
+            }
+        }
+        roots.extend(modules.into_iter().map(|(_, node)| node));
+        roots
+    }
+}
+
+/// 行範囲が `node` を含む最も内側のブロックの子として入れる
+///
+/// `nodes` は開始行順 (同じ開始行なら外側が先) に入れていく前提
+fn insert_by_line_range(nodes: &mut Vec<SymbolNode>, node: SymbolNode) {
+    let range = node.symbol.range;
+    let parent = nodes.iter_mut().rev().find(|n| {
+        let outer = n.symbol.range;
+        outer.start.line <= range.start.line && range.end.line <= outer.end.line
+    });
+    match parent {
+        Some(parent) => insert_by_line_range(&mut parent.children, node),
+        None => nodes.push(node),
+    }
+}
+
+/// 開始行・終了行が一致し、開始タグに `directive` 属性を持つ要素
+fn find_directive_element<'a>(
+    node: Node<'a>,
+    source: &str,
+    directive: &str,
+    start_line: u32,
+    end_line: u32,
+) -> Option<Node<'a>> {
+    let (start_row, end_row) = (node.start_position().row as u32, node.end_position().row as u32);
+    if start_row > start_line || end_row < end_line {
+        return None;
+    }
+    if node.kind() == "element" && start_row == start_line && end_row == end_line {
+        let has_directive = node.child(0).filter(|tag| tag.kind() == "start_tag").is_some_and(|tag| {
+            let mut cursor = tag.walk();
+            tag.named_children(&mut cursor)
+                .filter(|attr| attr.kind() == "attribute")
+                .filter_map(|attr| attr.named_child(0))
+                .filter(|name| name.kind() == "attribute_name")
+                .any(|name| normalize_directive_attr(&source[name.byte_range()]) == directive)
+        });
+        if has_directive {
+            return Some(node);
+        }
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node<'a>> = node.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_directive_element(child, source, directive, start_line, end_line))
+}
+
+/// 両方の範囲を含む最小の範囲
+fn union_range(a: Range, b: Range) -> Range {
+    Range::new(a.start.min(b.start), a.end.max(b.end))
+}
+
+fn html_block(
+    name: String,
+    detail: &str,
+    kind: tower_lsp::lsp_types::SymbolKind,
+    range: Range,
+    selection_range: Range,
+) -> SymbolNode {
+    #[allow(deprecated)]
+    let symbol = DocumentSymbol {
+        name,
+        detail: Some(detail.to_string()),
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: None,
+    };
+    SymbolNode {
+        symbol,
+        children: Vec::new(),
     }
+}
+
+/// `$scope.*` / `this.*` 等のメンバーを子に持つシンボルか
+fn is_member_container(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Controller
+            | SymbolKind::Service
+            | SymbolKind::Factory
+            | SymbolKind::Directive
+            | SymbolKind::Component
+            | SymbolKind::Provider
+    )
+}
 
+/// コントローラー等に所属するメンバーか
+fn is_member(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Method
+            | SymbolKind::ScopeProperty
+            | SymbolKind::ScopeMethod
+            | SymbolKind::ComponentBinding
+            | SymbolKind::DirectiveBinding
+    )
+}
+
+fn to_document_symbol(s: &Symbol, name: String) -> DocumentSymbol {
+    // Ensure selection_range is contained within range
+    // LSP requires: range.start <= selection_range.start && selection_range.end <= range.end
+    let range_start_line = s.definition_span.start_line.min(s.name_span.start_line);
+    let range_start_col = if range_start_line == s.definition_span.start_line
+        && range_start_line == s.name_span.start_line
+    {
+        s.definition_span.start_col.min(s.name_span.start_col)
+    } else if range_start_line == s.definition_span.start_line {
+        s.definition_span.start_col
+    } else {
+        s.name_span.start_col
+    };
+    let range_end_line = s.definition_span.end_line.max(s.name_span.end_line);
+    let range_end_col =
+        if range_end_line == s.definition_span.end_line && range_end_line == s.name_span.end_line {
+            s.definition_span.end_col.max(s.name_span.end_col)
+        } else if range_end_line == s.definition_span.end_line {
+            s.definition_span.end_col
+        } else {
+            s.name_span.end_col
+        };
+
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name,
+        detail: Some(s.kind.as_str().to_string()),
+        kind: s.kind.to_lsp_symbol_kind(),
+        tags: None,
+        deprecated: None,
+        range: Range {
+            start: Position {
+                line: range_start_line,
+                character: range_start_col,
+            },
+            end: Position {
+                line: range_end_line,
+                character: range_end_col,
+            },
+        },
+        selection_range: s.name_span.to_lsp_range(),
+        children: None,
+    }
 }
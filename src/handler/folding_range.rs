@@ -0,0 +1,244 @@
+//! Folding range handler.
+//!
+//! tree-sitter のノードの開始行・終了行から折りたたみ範囲を作る。
+//!
+//! - JS: `.controller('A', ...)` などの登録呼び出し、関数、クラス本体、複数行コメント
+//! - HTML: 複数行の要素 (`ng-repeat` の要素は折りたたみ時に繰り返し式を表示)、
+//!   `<script>` タグ、コメント。`<script>` の中身は JS として関数単位でも折りたたむ
+//!
+//! 閉じ括弧 / 閉じタグの行は表示したままにするため、範囲は終了行の 1 行前までとする。
+//! エディタで開いているファイルはデバウンス解析でキャッシュ済みの Tree を受け取り、
+//! ソースが一致すれば再パースしない。
+
+use std::collections::HashSet;
+
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind, Url};
+use tree_sitter::{Node, Tree};
+
+use crate::analyzer::html::directives::normalize_directive_attr;
+use crate::analyzer::html::parser::HtmlParser;
+use crate::analyzer::js::JsParser;
+use crate::util::{is_html_file, is_js_file};
+
+/// 折りたたみ対象にする `angular.module(...)` の登録メソッド
+const REGISTRATION_METHODS: &[&str] = &[
+    "controller",
+    "service",
+    "factory",
+    "provider",
+    "directive",
+    "component",
+    "filter",
+    "config",
+    "run",
+];
+
+#[derive(Default)]
+pub struct FoldingRangeHandler;
+
+impl FoldingRangeHandler {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// `textDocument/foldingRange` を処理する
+    ///
+    /// `cached_tree` は `source` からパース済みの Tree (キャッシュヒット時のみ)
+    pub fn folding_ranges(
+        &self,
+        uri: &Url,
+        source: &str,
+        cached_tree: Option<Tree>,
+    ) -> Option<Vec<FoldingRange>> {
+        let mut folds = Folds::default();
+        if is_html_file(uri) {
+            let tree = cached_tree.or_else(|| HtmlParser::new().parse(source))?;
+            collect_html_folds(tree.root_node(), source, &mut folds);
+        } else if is_js_file(uri) {
+            let tree = cached_tree.or_else(|| JsParser::for_uri(uri).parse(source))?;
+            collect_js_folds(tree.root_node(), source, 0, &mut folds);
+        } else {
+            return None;
+        }
+        Some(folds.ranges)
+    }
+}
+
+/// 折りたたみ範囲の集合 (同じ開始行は外側のノードを優先して 1 つだけ)
+#[derive(Default)]
+struct Folds {
+    ranges: Vec<FoldingRange>,
+    start_lines: HashSet<u32>,
+}
+
+impl Folds {
+    fn push(
+        &mut self,
+        node: Node,
+        line_offset: u32,
+        kind: Option<FoldingRangeKind>,
+        collapsed_text: Option<String>,
+    ) {
+        let start_line = node.start_position().row as u32 + line_offset;
+        let end_line = node.end_position().row as u32 + line_offset;
+        // コメントは最終行まで、それ以外は閉じ括弧 / 閉じタグの行を残す
+        let end_line = if kind == Some(FoldingRangeKind::Comment) {
+            end_line
+        } else {
+            end_line.saturating_sub(1)
+        };
+        if end_line <= start_line || !self.start_lines.insert(start_line) {
+            return;
+        }
+        self.ranges.push(FoldingRange {
+            start_line,
+            start_character: None,
+            end_line,
+            end_character: None,
+            kind,
+            collapsed_text,
+        });
+    }
+}
+
+fn collect_js_folds(node: Node, source: &str, line_offset: u32, folds: &mut Folds) {
+    match node.kind() {
+        "call_expression" if is_registration_call(node, source) => {
+            folds.push(node, line_offset, Some(FoldingRangeKind::Region), None);
+        }
+        "function_expression" | "function_declaration" | "generator_function_declaration"
+        | "method_definition" | "class_body" | "object" | "array" => {
+            folds.push(node, line_offset, None, None);
+        }
+        "arrow_function" => {
+            if let Some(body) = node.child_by_field_name("body") {
+                folds.push(body, line_offset, None, None);
+            }
+        }
+        "comment" => folds.push(node, line_offset, Some(FoldingRangeKind::Comment), None),
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_js_folds(child, source, line_offset, folds);
+    }
+}
+
+/// `.controller('Name', ...)` のような登録呼び出しか
+fn is_registration_call(node: Node, source: &str) -> bool {
+    node.child_by_field_name("function")
+        .filter(|callee| callee.kind() == "member_expression")
+        .and_then(|callee| callee.child_by_field_name("property"))
+        .is_some_and(|property| REGISTRATION_METHODS.contains(&&source[property.byte_range()]))
+}
+
+fn collect_html_folds(node: Node, source: &str, folds: &mut Folds) {
+    match node.kind() {
+        "element" => {
+            let collapsed_text = ng_repeat_expression(node, source)
+                .map(|expression| format!("ng-repeat=\"{}\"", expression));
+            folds.push(node, 0, None, collapsed_text);
+        }
+        "script_element" | "style_element" => {
+            folds.push(node, 0, None, None);
+        }
+        "comment" => folds.push(node, 0, Some(FoldingRangeKind::Comment), None),
+        "raw_text" if node.parent().is_some_and(|p| p.kind() == "script_element") => {
+            // 埋め込み JS は関数単位でも折りたたむ
+            let script = &source[node.byte_range()];
+            if let Some(tree) = JsParser::new().parse(script) {
+                let line_offset = node.start_position().row as u32;
+                collect_js_folds(tree.root_node(), script, line_offset, folds);
+            }
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_html_folds(child, source, folds);
+    }
+}
+
+/// 要素の開始タグにある `ng-repeat` (`data-ng-repeat` などの表記ゆれを含む) の値
+fn ng_repeat_expression<'a>(element: Node, source: &'a str) -> Option<&'a str> {
+    let start_tag = element.named_child(0).filter(|n| n.kind() == "start_tag")?;
+    let mut cursor = start_tag.walk();
+    let attribute = start_tag
+        .named_children(&mut cursor)
+        .filter(|n| n.kind() == "attribute")
+        .find(|attr| {
+            attr.named_child(0).is_some_and(|name| {
+                matches!(
+                    normalize_directive_attr(&source[name.byte_range()]).as_ref(),
+                    "ng-repeat" | "ng-repeat-start"
+                )
+            })
+        })?;
+    let value = attribute.named_child(1)?;
+    let value = match value.kind() {
+        "quoted_attribute_value" => value.named_child(0)?,
+        _ => value,
+    };
+    Some(source[value.byte_range()].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folds(uri: &str, source: &str) -> Vec<(u32, u32, Option<String>)> {
+        let uri = Url::parse(uri).unwrap();
+        FoldingRangeHandler::new()
+            .folding_ranges(&uri, source, None)
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.start_line, f.end_line, f.collapsed_text))
+            .collect()
+    }
+
+    #[test]
+    fn js_folds_registration_blocks_and_functions() {
+        let source = "\
+angular.module('app')
+.controller('MainCtrl', function($scope) {
+    $scope.save = function() {
+        return 1;
+    };
+});
+";
+        let ranges = folds("file:///a.js", source);
+        // モジュールチェーン全体 (0-4)、controller 関数 (1-4)、save (2-3)
+        assert_eq!(
+            ranges,
+            vec![(0, 4, None), (1, 4, None), (2, 3, None)]
+        );
+    }
+
+    #[test]
+    fn html_folds_ng_repeat_and_script_contents() {
+        let source = "\
+<ul>
+  <li ng-repeat=\"item in vm.items\">
+    {{ item.name }}
+  </li>
+</ul>
+<script>
+  function load() {
+    return 1;
+  }
+</script>
+";
+        let ranges = folds("file:///a.html", source);
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 3, None),
+                (1, 2, Some("ng-repeat=\"item in vm.items\"".to_string())),
+                (5, 8, None),
+                (6, 7, None),
+            ]
+        );
+    }
+}
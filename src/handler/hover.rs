@@ -2,6 +2,8 @@ use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
 
+use crate::analyzer::html::filters::builtin_filter_doc;
+use super::resolve::{locate_symbol_at, LocatedSymbol};
 use crate::index::{HtmlResolution, Index};
 use crate::model::{
     DirectiveUsageType, HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable,
@@ -19,31 +21,39 @@ impl HoverHandler {
     }
 
     pub fn hover(&self, params: HoverParams) -> Option<Hover> {
-        let uri = params.text_document_position_params.text_document.uri;
+        let uri = &params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
+        let located = locate_symbol_at(&self.index, uri, position.line, position.character, None);
+        self.hover_with_located(params, located.as_ref())
+    }
+
+    /// `located` はカーソル位置で [`locate_symbol_at`] した結果
+    pub fn hover_with_located(
+        &self,
+        params: HoverParams,
+        located: Option<&LocatedSymbol>,
+    ) -> Option<Hover> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let located = located?;
 
         // HTMLファイルの場合は専用の処理
         if is_html_file(&uri) {
-            return self.hover_from_html(&uri, position);
+            return self.hover_from_html(&uri, located.html.clone()?);
         }
 
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
-            position.line,
-            position.character,
-        )?;
-
-        self.build_hover_for_symbol(&symbol_name)
+        self.build_hover_for_symbol(&located.name)
     }
 
     /// HTMLファイルからのホバー
     ///
     /// 解決優先順位は [`Index::resolve_html_position`] に集約 (issue #49)。
     /// ここではその結果を `Hover` にマッピングするだけ。
-    fn hover_from_html(&self, uri: &Url, position: Position) -> Option<Hover> {
-        match self.index.resolve_html_position(uri, position, None)? {
+    fn hover_from_html(&self, uri: &Url, resolution: HtmlResolution) -> Option<Hover> {
+        match resolution {
             HtmlResolution::UiSref(r) => self.build_for_ui_sref(&r),
             HtmlResolution::Directive(r) => self.build_hover_for_directive(&r),
+            HtmlResolution::Filter(name) => self.build_hover_for_filter(&name),
+            HtmlResolution::Controller(name) => self.build_hover_for_symbol(&name),
             HtmlResolution::LocalVarDef(v) | HtmlResolution::LocalVarRef(v) => {
                 self.build_hover_for_local_variable(&v)
             }
@@ -170,6 +180,10 @@ impl HoverHandler {
             content.push_str("\n\n---\n\n");
         }
 
+        if def.kind == SymbolKind::Controller {
+            content.push_str(&self.format_injected_services(symbol_name));
+        }
+
         content.push_str(&format!(
             "Defined in: `{}:{}`\n",
             file_name,
@@ -189,6 +203,115 @@ impl HoverHandler {
         })
     }
 
+    /// フィルター (`{{ amount | currency }}`) のホバー情報を構築
+    ///
+    /// ユーザー定義フィルターは定義位置・JSDoc・フィルター関数の引数から書式を、
+    /// 組み込みフィルターは静的な説明を表示する
+    fn build_hover_for_filter(&self, name: &str) -> Option<Hover> {
+        let definition = self
+            .index
+            .definitions
+            .get_definitions(name)
+            .into_iter()
+            .find(|d| d.kind == SymbolKind::Filter);
+
+        let content = if let Some(def) = definition {
+            let file_name = def
+                .uri
+                .to_file_path()
+                .ok()
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| def.uri.to_string());
+
+            // 第1引数は入力値、残りが `:` 区切りのフィルター引数
+            let params = def.parameters.clone().unwrap_or_default();
+            let input = params.first().map(String::as_str).unwrap_or("value");
+            let args: String = params.iter().skip(1).map(|p| format!(":{}", p)).collect();
+
+            let mut content = format!(
+                "**{}** (*filter*)\n\n```\n{{{{ {} | {}{} }}}}\n```\n\n",
+                name, input, name, args
+            );
+            if let Some(ref docs) = def.docs {
+                content.push_str(docs);
+                content.push_str("\n\n---\n\n");
+            }
+            content.push_str(&format!(
+                "Defined in: `{}:{}`\n",
+                file_name,
+                def.definition_span.start_line + 1
+            ));
+
+            let reference_count = self.index.get_all_references(name).len();
+            if reference_count > 0 {
+                content.push_str(&format!("\nReferences: {}", reference_count));
+            }
+            content
+        } else {
+            let doc = builtin_filter_doc(name)?;
+            format!(
+                "**{}** (*built-in filter*)\n\n```\n{}\n```\n\n{}",
+                doc.name, doc.syntax, doc.description
+            )
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: content,
+            }),
+            range: None,
+        })
+    }
+
+    /// コントローラーに注入されているサービスの一覧 (markdown)
+    ///
+    /// ワークスペースに定義があるサービスは定義位置へのリンク、ないものは未定義として示す。
+    /// `$` で始まる組み込みサービスは `ControllerScope` に記録されないため含まれない
+    fn format_injected_services(&self, controller_name: &str) -> String {
+        let services = self
+            .index
+            .controllers
+            .get_injected_services_for_controller(controller_name);
+        if services.is_empty() {
+            return String::new();
+        }
+
+        let mut content = String::from("Injected services:\n\n");
+        for service in &services {
+            let definition = self
+                .index
+                .definitions
+                .get_definitions(service)
+                .into_iter()
+                .next();
+            let line = match definition {
+                Some(def) => {
+                    let file_name = def
+                        .uri
+                        .to_file_path()
+                        .ok()
+                        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                        .unwrap_or_else(|| def.uri.to_string());
+                    let line = def.definition_span.start_line + 1;
+                    format!(
+                        "- [`{}`]({}#L{}) (*{}*) — `{}:{}`\n",
+                        service,
+                        def.uri,
+                        line,
+                        def.kind.as_str(),
+                        file_name,
+                        line
+                    )
+                }
+                None => format!("- `{}` (*not found in workspace*)\n", service),
+            };
+            content.push_str(&line);
+        }
+        content.push_str("\n---\n\n");
+        content
+    }
+
     /// ローカル変数用のホバー情報を構築
     fn build_hover_for_local_variable(&self, var_def: &HtmlLocalVariable) -> Option<Hover> {
         let source_str = match var_def.source {
@@ -196,6 +319,7 @@ impl HoverHandler {
             HtmlLocalVariableSource::NgRepeatIterator => "ng-repeat iterator",
             HtmlLocalVariableSource::NgRepeatKeyValue => "ng-repeat key/value",
             HtmlLocalVariableSource::NgRepeatSpecial => "ng-repeat special",
+            HtmlLocalVariableSource::NgRepeatAlias => "ng-repeat alias",
         };
 
         let reference_count = self
@@ -266,7 +390,11 @@ impl HoverHandler {
             return None;
         }
 
-        let def = &definitions[0];
+        // 同名の別種シンボルより directive / component の定義を優先する
+        let def = definitions
+            .iter()
+            .find(|d| matches!(d.kind, SymbolKind::Directive | SymbolKind::Component))
+            .unwrap_or(&definitions[0]);
 
         // ファイル名を取得
         let file_name = def
@@ -298,6 +426,13 @@ impl HoverHandler {
             content.push_str("\n\n---\n\n");
         }
 
+        if def.kind == SymbolKind::Directive {
+            content.push_str(&self.format_directive_definition(
+                &directive_ref.directive_name,
+                def.restrict.as_deref(),
+            ));
+        }
+
         content.push_str(&format!(
             "Defined in: `{}:{}`\n",
             file_name,
@@ -316,4 +451,70 @@ impl HoverHandler {
             range: None,
         })
     }
+
+    /// ディレクティブ定義オブジェクトの restrict / isolate scope / templateUrl (markdown)
+    fn format_directive_definition(&self, directive_name: &str, restrict: Option<&str>) -> String {
+        // AngularJS 1.3 以降、restrict 未指定時の既定値は 'EA'
+        let (restrict_value, is_default) = match restrict {
+            Some(r) => (r, false),
+            None => ("EA", true),
+        };
+        let usages = restrict_usages(restrict_value);
+
+        let mut content = format!("Restrict: `{}`", restrict_value);
+        if is_default {
+            content.push_str(" (default)");
+        }
+        if !usages.is_empty() {
+            content.push_str(&format!(" — {}", usages.join(", ")));
+        }
+        content.push_str("\n\n");
+        if usages.contains(&"element") && usages.contains(&"attribute") {
+            content.push_str("Usable as both an element and an attribute\n\n");
+        }
+
+        // scope: { ... } のバインディングは `<directive>.$scope.<name>` として登録されている
+        let prefix = format!("{}.$scope.", directive_name);
+        let mut bindings: Vec<(String, String)> = self
+            .index
+            .definitions
+            .get_definitions_with_prefix(&prefix)
+            .into_iter()
+            .filter(|s| s.kind == SymbolKind::DirectiveBinding)
+            .filter_map(|s| {
+                let name = s.name.strip_prefix(&prefix)?.to_string();
+                Some((name, s.binding_type.unwrap_or_default()))
+            })
+            .collect();
+        bindings.sort();
+        bindings.dedup();
+        if !bindings.is_empty() {
+            content.push_str("| Binding | Type |\n|---|---|\n");
+            for (name, binding_type) in &bindings {
+                content.push_str(&format!("| `{}` | `{}` |\n", name, binding_type));
+            }
+            content.push('\n');
+        }
+
+        for template in self.index.templates.get_templates_for_controller(directive_name) {
+            content.push_str(&format!("Template: `{}`\n\n", template));
+        }
+
+        content.push_str("---\n\n");
+        content
+    }
+}
+
+/// restrict 値の各文字 (E/A/C/M) を使用形態名に変換する
+fn restrict_usages(restrict: &str) -> Vec<&'static str> {
+    restrict
+        .chars()
+        .filter_map(|c| match c {
+            'E' => Some("element"),
+            'A' => Some("attribute"),
+            'C' => Some("class"),
+            'M' => Some("comment"),
+            _ => None,
+        })
+        .collect()
 }
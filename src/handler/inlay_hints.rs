@@ -4,61 +4,52 @@
 //! コントローラーか」が syntactic に切り離されているため、ジャンプしないと
 //! 対応関係が分からない。Inlay hints でこれを inline 表示する。
 //!
-//! 3 種類のヒント:
+//! 5 種類のヒント:
 //!
 //! 1. **DI rename hint** (JS):
 //!    `['$scope', '$timeout', function(s, t)]` の `s` の右に `: $scope` を表示
-//! 2. **controller as alias hint** (HTML):
+//! 2. **注入サービスの出所 hint** (JS):
+//!    DI 関数の引数 `UserService` の右に `: service (users.service.js)` を表示
+//!    (ワークスペースで定義されたサービスのみ。rename hint とは 1 つにまとめる)
+//! 3. **controller as alias hint** (HTML):
 //!    `<div ng-controller="MainCtrl as vm">{{ vm.foo }}` の `vm` の右に
 //!    `: MainCtrl` を表示
-//! 3. **`$ctrl` alias hint** (HTML):
+//! 4. **`$ctrl` alias hint** (HTML):
 //!    component template 内の `{{ $ctrl.bar }}` の `$ctrl` の右に
 //!    `: <componentName>` を表示
+//! 5. **ng-repeat 変数の出所 hint** (HTML):
+//!    `ng-repeat="item in vm.items"` の内側で参照される `item` の右に
+//!    `: vm.items[]` を表示 (定義と同じ行の参照には出さない)
 //!
+//! いずれも要求された範囲 (可視領域) と重なる部分だけを計算する。
 //! issue #66 参照。
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use dashmap::DashMap;
 use tower_lsp::lsp_types::{
     InlayHint, InlayHintKind, InlayHintLabel, Position, Range, Url,
 };
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Tree};
 
+use crate::analyzer::incremental::SyntaxTreeCache;
+use crate::analyzer::js::{param_identifier, JsParser};
 use crate::index::Index;
+use crate::model::{HtmlLocalVariableSource, SymbolKind};
 use crate::util::{is_html_file, is_js_file};
 
-/// JS の tree-sitter Tree を URI ごとにキャッシュする。
-///
-/// LSP の `textDocument/inlayHint` はクライアントのスクロール毎に呼ばれるが、
-/// その間にソースが変わっていなければ Tree を使い回せる。
-/// `source_hash` を毎回比較し、ソースが変化したときだけ再パースする。
-pub struct CachedJsTree {
-    source_hash: u64,
-    tree: Tree,
-}
-
-/// `Backend` から共有する JS Tree キャッシュ。`Backend::new` で生成して、
-/// `InlayHintsHandler::new` に渡す。
-pub type JsTreeCache = DashMap<Url, CachedJsTree>;
-
-pub fn new_js_tree_cache() -> Arc<JsTreeCache> {
-    Arc::new(DashMap::new())
-}
-
 pub struct InlayHintsHandler {
     index: Arc<Index>,
     documents: Arc<DashMap<Url, String>>,
-    js_tree_cache: Arc<JsTreeCache>,
+    /// JS の tree-sitter Tree キャッシュ (`AngularJsAnalyzer::tree_cache` と共有)
+    js_tree_cache: Arc<SyntaxTreeCache>,
 }
 
 impl InlayHintsHandler {
     pub fn new(
         index: Arc<Index>,
         documents: Arc<DashMap<Url, String>>,
-        js_tree_cache: Arc<JsTreeCache>,
+        js_tree_cache: Arc<SyntaxTreeCache>,
     ) -> Self {
         Self {
             index,
@@ -74,9 +65,9 @@ impl InlayHintsHandler {
     /// フィルタを適用しておく。
     pub fn inlay_hints(&self, uri: &Url, range: Option<Range>) -> Option<Vec<InlayHint>> {
         let hints = if is_js_file(uri) {
-            self.collect_js_hints(uri)
+            self.collect_js_hints(uri, range)
         } else if is_html_file(uri) {
-            self.collect_html_hints(uri)
+            self.collect_html_hints(uri, range)
         } else {
             return None;
         };
@@ -106,9 +97,9 @@ impl InlayHintsHandler {
     /// は array 注釈と違って関数定義と DI が syntactic に離れているため
     /// 別 issue として future work。array 形式のみ対応する。
     ///
-    /// パフォーマンス: ソースの hash を URI ごとに保存し、変化していない
-    /// 場合は前回パースした Tree を再利用する (フルパース回避)。
-    fn collect_js_hints(&self, uri: &Url) -> Vec<InlayHint> {
+    /// パフォーマンス: デバウンス解析で更新された Tree をソースが一致する限り
+    /// 再利用する (フルパース回避)。`range` と重ならない部分木は辿らない。
+    fn collect_js_hints(&self, uri: &Url, range: Option<Range>) -> Vec<InlayHint> {
         let source = match self.documents.get(uri) {
             Some(doc) => doc.value().clone(),
             None => return Vec::new(),
@@ -120,35 +111,89 @@ impl InlayHintsHandler {
         };
 
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), &source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), &source, range, &mut hints);
+        self.add_service_origin_hints(uri, &source, range, &mut hints);
         hints
     }
 
-    /// `js_tree_cache` から URI 対応の Tree を取得 (hash 一致時)、または
-    /// 再パースしてキャッシュ更新する。`Tree::clone` は内部 Arc 参照のため
-    /// 安価。
-    fn get_or_parse_js_tree(&self, uri: &Url, source: &str) -> Option<Tree> {
-        let new_hash = hash_source(source);
-
-        if let Some(entry) = self.js_tree_cache.get(uri) {
-            if entry.source_hash == new_hash {
-                return Some(entry.tree.clone());
+    /// DI 関数の引数に、注入されるサービスの種類と定義元ファイルを表示する
+    ///
+    /// 引数と注入名の対応は解析時に記録した `InjectionUsage` を使うので、DI 配列・
+    /// 暗黙 DI・`$inject` のいずれにも対応する。同じ位置に rename hint があれば
+    /// `: UserService — service (users.service.js)` のように 1 つにまとめる
+    fn add_service_origin_hints(
+        &self,
+        uri: &Url,
+        source: &str,
+        range: Option<Range>,
+        hints: &mut Vec<InlayHint>,
+    ) {
+        let lines: Vec<&str> = source.split('\n').collect();
+        for usage in self.index.diagnostics.get_injection_usages(uri) {
+            if !lines_overlap(range, usage.span.start_line, usage.span.end_line) {
+                continue;
+            }
+            for param in &usage.params {
+                let Some(origin) = self.service_origin(&param.service) else {
+                    continue;
+                };
+                let Some(line) = lines.get(param.span.end_line as usize) else {
+                    continue;
+                };
+                let Some(prefix) = line.get(..param.span.end_col as usize) else {
+                    continue;
+                };
+                let position = Position {
+                    line: param.span.end_line,
+                    character: prefix.encode_utf16().count() as u32,
+                };
+
+                match hints.iter_mut().find(|h| h.position == position) {
+                    Some(hint) => {
+                        if let InlayHintLabel::String(label) = &mut hint.label {
+                            label.push_str(&format!(" — {}", origin));
+                        }
+                    }
+                    None => hints.push(make_hint(position, format!(": {}", origin))),
+                }
             }
         }
+    }
 
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_javascript::LANGUAGE.into())
-            .ok()?;
-        let tree = parser.parse(source, None)?;
+    /// `service (users.service.js)` (ワークスペースで定義されていなければ `None`)
+    fn service_origin(&self, service: &str) -> Option<String> {
+        let symbol = self
+            .index
+            .definitions
+            .get_definitions(service)
+            .into_iter()
+            .find(|s| {
+                matches!(
+                    s.kind,
+                    SymbolKind::Service
+                        | SymbolKind::Factory
+                        | SymbolKind::Provider
+                        | SymbolKind::Value
+                        | SymbolKind::Constant
+                )
+            })?;
+        let file = symbol.uri.path_segments().and_then(|mut s| s.next_back())?;
+        Some(format!("{} ({})", symbol.kind.as_str(), file))
+    }
 
-        self.js_tree_cache.insert(
-            uri.clone(),
-            CachedJsTree {
-                source_hash: new_hash,
-                tree: tree.clone(),
-            },
-        );
+    /// `js_tree_cache` から現在のソースに対応する Tree を取得する。
+    ///
+    /// キャッシュミス時 (解析がまだ追いついていない等) だけ再パースする。
+    /// その場合も前回の Tree があれば差分パースし、結果をキャッシュに戻す。
+    /// `Tree::clone` は内部 Arc 参照のため安価。
+    fn get_or_parse_js_tree(&self, uri: &Url, source: &str) -> Option<Tree> {
+        if let Some(tree) = self.js_tree_cache.tree_for_source(uri, source) {
+            return Some(tree);
+        }
+
+        let old_tree = self.js_tree_cache.edited_tree(uri, source);
+        let tree = JsParser::for_uri(uri).parse_incremental(source, old_tree.as_ref())?;
+        self.js_tree_cache.insert(uri, source, &tree);
 
         Some(tree)
     }
@@ -168,7 +213,7 @@ impl InlayHintsHandler {
     /// 対して 1 回計算すれば足りる (ref ごとに変わらない)。ここでは
     /// `controllers` (line 依存) を ref ごとに、component binding を URI ごとに
     /// 1 度だけ取得して per-ref のオーバーヘッドを抑える。
-    fn collect_html_hints(&self, uri: &Url) -> Vec<InlayHint> {
+    fn collect_html_hints(&self, uri: &Url, range: Option<Range>) -> Vec<InlayHint> {
         let mut hints = Vec::new();
 
         // URI に対して 1 回だけ component binding を取得 (ref ごとには変わらない)
@@ -179,6 +224,9 @@ impl InlayHintsHandler {
 
         let refs = self.index.html.get_html_scope_references(uri);
         for scope_ref in refs {
+            if !lines_overlap(range, scope_ref.start_line, scope_ref.end_line) {
+                continue;
+            }
             let Some((alias, _rest)) = split_alias(&scope_ref.property_path) else {
                 continue;
             };
@@ -222,8 +270,82 @@ impl InlayHintsHandler {
             ));
         }
 
+        self.add_ng_repeat_origin_hints(uri, range, &mut hints);
         hints
     }
+
+    /// ng-repeat の繰り返し変数の参照に、繰り返し元のコレクションを表示する
+    ///
+    /// コレクションは定義位置の直後 (`item in vm.items | ...`) をソースから読む。
+    /// 定義と同じ行の参照は繰り返し式が見えているので出さない
+    fn add_ng_repeat_origin_hints(&self, uri: &Url, range: Option<Range>, hints: &mut Vec<InlayHint>) {
+        let Some(source) = self.documents.get(uri).map(|doc| doc.value().clone()) else {
+            return;
+        };
+        let lines: Vec<&str> = source.split('\n').collect();
+
+        for reference in self.index.html.get_all_local_variable_references_for_uri(uri) {
+            if !lines_overlap(range, reference.start_line, reference.end_line) {
+                continue;
+            }
+            let Some(variable) = self.index.html.find_local_variable_definition(
+                uri,
+                &reference.variable_name,
+                reference.start_line,
+            ) else {
+                continue;
+            };
+            if variable.source != HtmlLocalVariableSource::NgRepeatIterator
+                || variable.name_start_line == reference.start_line
+            {
+                continue;
+            }
+            let Some(collection) = lines
+                .get(variable.name_end_line as usize)
+                .and_then(|line| ng_repeat_collection(line, variable.name_end_col))
+            else {
+                continue;
+            };
+            hints.push(make_hint(
+                Position {
+                    line: reference.end_line,
+                    character: reference.end_col,
+                },
+                format!(": {}[]", collection),
+            ));
+        }
+    }
+}
+
+/// ng-repeat の繰り返し変数の直後 (UTF-16 列 `name_end_col`) から `in <collection>` を読む
+///
+/// コレクションはフィルター (`|`)・`track by`・`as`・属性値の終わりまで
+fn ng_repeat_collection(line: &str, name_end_col: u32) -> Option<&str> {
+    let mut utf16 = 0;
+    let mut start = line.len();
+    for (i, c) in line.char_indices() {
+        if utf16 >= name_end_col as usize {
+            start = i;
+            break;
+        }
+        utf16 += c.len_utf16();
+    }
+    let rest = line[start..].trim_start().strip_prefix("in")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let end = [rest.find('|'), rest.find('"'), rest.find('\''), rest.find(" track by "), rest.find(" as ")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(rest.len());
+    let collection = rest[..end].trim();
+    (!collection.is_empty()).then_some(collection)
+}
+
+/// `range` (指定があれば) が `start_line`〜`end_line` と重なるか
+fn lines_overlap(range: Option<Range>, start_line: u32, end_line: u32) -> bool {
+    range.is_none_or(|r| start_line <= r.end.line && end_line >= r.start.line)
 }
 
 /// `vm.foo` → `Some(("vm", "foo"))`、`vm` → `None`、`vm.user.name` →
@@ -282,14 +404,19 @@ fn position_in_range(pos: Position, range: Range) -> bool {
 // ============================================================
 
 /// AST を再帰的に walk して DI rename hint を集める。
-fn collect_di_rename_hints(node: Node, source: &str, hints: &mut Vec<InlayHint>) {
+///
+/// `range` と重ならないノードは子孫ごと飛ばす。
+fn collect_di_rename_hints(node: Node, source: &str, range: Option<Range>, hints: &mut Vec<InlayHint>) {
+    if !lines_overlap(range, node.start_position().row as u32, node.end_position().row as u32) {
+        return;
+    }
     if let Some((services, function_node)) = parse_di_array(node, source) {
         emit_di_hints_for_function(function_node, source, &services, hints);
     }
 
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        collect_di_rename_hints(child, source, hints);
+        collect_di_rename_hints(child, source, range, hints);
     }
 }
 
@@ -329,18 +456,10 @@ fn emit_di_hints_for_function(
         return;
     };
 
-    let mut idx: usize = 0;
     let mut cursor = params.walk();
-    for child in params.children(&mut cursor) {
-        if child.kind() != "identifier" {
-            continue;
-        }
-        if idx >= services.len() {
-            break;
-        }
+    let param_nodes = params.children(&mut cursor).filter_map(param_identifier);
+    for (child, service) in param_nodes.zip(services) {
         let param_name = node_text(child, source);
-        let service = &services[idx];
-        idx += 1;
 
         // param 名 == service 名 のときは情報量がないので hint を出さない
         if param_name == service.as_str() {
@@ -363,14 +482,6 @@ fn emit_di_hints_for_function(
     }
 }
 
-/// JS ソースのキャッシュキー用ハッシュ。`DefaultHasher` はプロセス内で
-/// 決定的なので、同一プロセスで「ソースが変わったか」の判定に使える。
-fn hash_source(s: &str) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    hasher.finish()
-}
-
 /// ソース全体の絶対バイト offset から「その行内での UTF-16 code unit 数」を
 /// 計算する。LSP は UTF-16 列を要求するため、tree-sitter のバイト列との
 /// 変換にこのヘルパーを使う。
@@ -411,7 +522,7 @@ mod tests {
     use super::*;
 
     fn parse_js(source: &str) -> tree_sitter::Tree {
-        let mut parser = Parser::new();
+        let mut parser = tree_sitter::Parser::new();
         parser
             .set_language(&tree_sitter_javascript::LANGUAGE.into())
             .unwrap();
@@ -453,7 +564,7 @@ angular.module('app').controller('Main', ['$scope', '$timeout', function(s, t) {
 }]);\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
 
         // 2 つの hint が出る (`s` の後ろと `t` の後ろ)
         assert_eq!(hints.len(), 2);
@@ -478,7 +589,7 @@ angular.module('app').controller('Main', ['$scope', function($scope) {\n\
 }]);\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
         assert!(hints.is_empty(), "expected no hints, got {:?}", hints);
     }
 
@@ -491,7 +602,7 @@ angular.module('app').controller('Main', ['$scope', '$timeout', function($scope,
 }]);\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
         assert_eq!(hints.len(), 1);
         if let InlayHintLabel::String(label) = &hints[0].label {
             assert_eq!(label, ": $timeout");
@@ -506,7 +617,7 @@ angular.module('app').controller('Main', ['$scope', '$timeout', function($scope,
         let source = "var d = ['$scope', function(s) {}];\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
         assert_eq!(hints.len(), 1);
         // `s` は line 0, col 28 にある (`var d = ['$scope', function(` の長さ)
         assert_eq!(hints[0].position.line, 0);
@@ -519,7 +630,7 @@ angular.module('app').controller('Main', ['$scope', '$timeout', function($scope,
         let source = "var deps = ['$scope', '$timeout'];\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
         assert!(hints.is_empty());
     }
 
@@ -532,7 +643,7 @@ angular.module('app').config(['$routeProvider', function(rp) {\n\
 }]);\n";
         let tree = parse_js(source);
         let mut hints = Vec::new();
-        collect_di_rename_hints(tree.root_node(), source, &mut hints);
+        collect_di_rename_hints(tree.root_node(), source, None, &mut hints);
         assert_eq!(hints.len(), 1);
         if let InlayHintLabel::String(label) = &hints[0].label {
             assert_eq!(label, ": $routeProvider");
@@ -560,7 +671,7 @@ angular.module('app').config(['$routeProvider', function(rp) {\n\
         index: Arc<Index>,
         documents: Arc<DashMap<Url, String>>,
     ) -> InlayHintsHandler {
-        InlayHintsHandler::new(index, documents, new_js_tree_cache())
+        InlayHintsHandler::new(index, documents, Arc::new(SyntaxTreeCache::new()))
     }
 
     #[test]
@@ -633,6 +744,7 @@ angular.module('app').controller('Main', ['$scope', '$timeout', function(s, t) {
             .add_html_controller_scope(HtmlControllerScope {
                 controller_name: "MainCtrl".to_string(),
                 alias: Some("vm".to_string()),
+                alias_span: None,
                 uri: uri.clone(),
                 start_line: 0,
                 end_line: 10,
@@ -744,13 +856,12 @@ var b = ['$timeout', function(t) { t.cancel(); }];\n";
 
     #[test]
     fn js_tree_cache_reuses_tree_when_source_unchanged() {
-        // 同じソースで 2 回呼ぶと、2 回目は cache から Tree が再利用され、
-        // cache のサイズは 1 のまま、source_hash も等しい。
+        // 同じソースで 2 回呼ぶと、2 回目は cache から同じ Tree が再利用される。
         let uri = js_url();
         let source = "var d = ['$scope', function(s) { s.foo = 1; }];\n";
         let documents = Arc::new(DashMap::new());
         documents.insert(uri.clone(), source.to_string());
-        let cache = new_js_tree_cache();
+        let cache = Arc::new(SyntaxTreeCache::new());
 
         let handler = InlayHintsHandler::new(
             Arc::new(Index::new()),
@@ -760,26 +871,50 @@ var b = ['$timeout', function(t) { t.cancel(); }];\n";
 
         let first = handler.inlay_hints(&uri, None).unwrap();
         assert_eq!(first.len(), 1);
-        assert_eq!(cache.len(), 1);
-        let hash_after_first = cache.get(&uri).unwrap().source_hash;
+        let tree_after_first = cache.tree_for_source(&uri, source).unwrap();
 
-        // 2 回目: 同じソース → 同じ hash で再利用
+        // 2 回目: 同じソース → 同じ Tree を再利用
+        // (root の id は Tree の clone ごとに変わるため、共有される子サブツリーで比較)
         let second = handler.inlay_hints(&uri, None).unwrap();
         assert_eq!(second.len(), 1);
-        assert_eq!(cache.len(), 1);
-        assert_eq!(cache.get(&uri).unwrap().source_hash, hash_after_first);
+        let tree_after_second = cache.tree_for_source(&uri, source).unwrap();
+        assert_eq!(
+            tree_after_first.root_node().child(0).unwrap().id(),
+            tree_after_second.root_node().child(0).unwrap().id()
+        );
+    }
+
+    #[test]
+    fn js_tree_cache_reuses_tree_from_analysis() {
+        // デバウンス解析で更新された Tree を inlay hints がそのまま使う
+        let uri = js_url();
+        let source = "var d = ['$scope', function(s) { s.foo = 1; }];\n";
+        let documents = Arc::new(DashMap::new());
+        documents.insert(uri.clone(), source.to_string());
+        let index = Arc::new(Index::new());
+        let analyzer = crate::analyzer::js::AngularJsAnalyzer::new(Arc::clone(&index));
+        analyzer.analyze_document_incremental(&uri, source);
+        let analyzed = analyzer.tree_cache().tree_for_source(&uri, source).unwrap();
+
+        let handler = InlayHintsHandler::new(index, documents, analyzer.tree_cache());
+        assert_eq!(handler.inlay_hints(&uri, None).unwrap().len(), 1);
+        let used = analyzer.tree_cache().tree_for_source(&uri, source).unwrap();
+        assert_eq!(
+            analyzed.root_node().child(0).unwrap().id(),
+            used.root_node().child(0).unwrap().id()
+        );
     }
 
     #[test]
     fn js_tree_cache_invalidates_on_source_change() {
-        // ソースが変わると hash が変わり、再パース + cache 更新される。
+        // ソースが変わると再パース + cache 更新される。
         let uri = js_url();
         let documents = Arc::new(DashMap::new());
         documents.insert(
             uri.clone(),
             "var d = ['$scope', function(s) { s.foo = 1; }];\n".to_string(),
         );
-        let cache = new_js_tree_cache();
+        let cache = Arc::new(SyntaxTreeCache::new());
         let handler = InlayHintsHandler::new(
             Arc::new(Index::new()),
             documents.clone(),
@@ -788,7 +923,6 @@ var b = ['$timeout', function(t) { t.cancel(); }];\n";
 
         let first = handler.inlay_hints(&uri, None).unwrap();
         assert_eq!(first.len(), 1);
-        let hash1 = cache.get(&uri).unwrap().source_hash;
 
         // ドキュメントを差し替え
         documents.insert(
@@ -798,8 +932,12 @@ var b = ['$timeout', function(t) { t.cancel(); }];\n";
 
         let second = handler.inlay_hints(&uri, None).unwrap();
         assert_eq!(second.len(), 1);
-        let hash2 = cache.get(&uri).unwrap().source_hash;
-        assert_ne!(hash1, hash2, "hash should change after source edit");
+        assert!(
+            cache
+                .tree_for_source(&uri, "var d = ['$timeout', function(t) { t.cancel(); }];\n")
+                .is_some(),
+            "cache should hold the tree for the edited source"
+        );
 
         if let InlayHintLabel::String(label) = &second[0].label {
             assert_eq!(label, ": $timeout");
@@ -807,4 +945,88 @@ var b = ['$timeout', function(t) { t.cancel(); }];\n";
             panic!("expected String label");
         }
     }
+
+    fn labels(hints: &[InlayHint]) -> Vec<(u32, String)> {
+        hints
+            .iter()
+            .filter_map(|h| match &h.label {
+                InlayHintLabel::String(s) => Some((h.position.line, s.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inlay_hints_show_origin_of_injected_services() {
+        let index = Arc::new(Index::new());
+        let analyzer = crate::analyzer::js::AngularJsAnalyzer::new(Arc::clone(&index));
+        let service_uri = Url::parse("file:///src/users.service.js").unwrap();
+        analyzer.analyze_document(
+            &service_uri,
+            "angular.module('app').factory('UserService', function() { return {}; });\n",
+        );
+
+        let uri = js_url();
+        let source = "\
+angular.module('app')\n\
+.controller('A', function($scope, UserService) { $scope.u = UserService; })\n\
+.controller('B', ['UserService', function(users) { return users; }]);\n";
+        analyzer.analyze_document(&uri, source);
+        let documents = Arc::new(DashMap::new());
+        documents.insert(uri.clone(), source.to_string());
+
+        let handler = make_handler(index, documents);
+        let hints = handler.inlay_hints(&uri, None).unwrap();
+        let mut labels = labels(&hints);
+        labels.sort();
+        // 組み込みサービス ($scope) には出所を出さず、rename hint とは 1 つにまとめる
+        assert_eq!(
+            labels,
+            vec![
+                (1, ": factory (users.service.js)".to_string()),
+                (2, ": UserService — factory (users.service.js)".to_string()),
+            ]
+        );
+
+        // 可視範囲外の DI 関数は計算しない
+        let range = Range::new(Position::new(2, 0), Position::new(2, 80));
+        let visible = handler.inlay_hints(&uri, Some(range)).unwrap();
+        let lines: Vec<u32> = visible.iter().map(|h| h.position.line).collect();
+        assert_eq!(lines, vec![2]);
+    }
+
+    #[test]
+    fn inlay_hints_show_ng_repeat_collection_on_references() {
+        let index = Arc::new(Index::new());
+        let js_analyzer = Arc::new(crate::analyzer::js::AngularJsAnalyzer::new(Arc::clone(&index)));
+        let html_analyzer =
+            crate::analyzer::html::HtmlAngularJsAnalyzer::new(Arc::clone(&index), js_analyzer);
+        let uri = html_url();
+        let source = "\
+<ul>\n\
+  <li ng-repeat=\"item in vm.items | orderBy:'name' track by item.id\">\n\
+    {{ item.name }}\n\
+  </li>\n\
+</ul>\n";
+        html_analyzer.analyze_document(&uri, source);
+        let documents = Arc::new(DashMap::new());
+        documents.insert(uri.clone(), source.to_string());
+
+        let handler = make_handler(index, documents);
+        let hints = handler.inlay_hints(&uri, None).unwrap();
+        // 定義と同じ行の `track by item.id` には出さない
+        assert_eq!(labels(&hints), vec![(2, ": vm.items[]".to_string())]);
+        // 行継続 `\` で先頭の空白は詰まるので `{{ item` の `item` は 3〜7
+        assert_eq!(hints[0].position.character, 7);
+    }
+
+    #[test]
+    fn ng_repeat_collection_reads_expression_after_variable() {
+        assert_eq!(ng_repeat_collection("ng-repeat=\"item in items\"", 15), Some("items"));
+        assert_eq!(
+            ng_repeat_collection("ng-repeat=\"row   in  vm.rows as shown\"", 14),
+            Some("vm.rows")
+        );
+        assert_eq!(ng_repeat_collection("ng-repeat=\"item inside\"", 15), None);
+    }
 }
@@ -0,0 +1,120 @@
+//! Linked editing range handler.
+//!
+//! HTML の開始タグ名と終了タグ名を同時に編集できるよう、カーソル位置の要素の
+//! 両方のタグ名の範囲を返す。`<user-list>` のようなカスタム要素も同じ扱い。
+//! 自己終了タグ (`<img />`) や終了タグのない要素では何も返さない。
+
+use tower_lsp::lsp_types::{LinkedEditingRanges, Position, Range, Url};
+use tree_sitter::{Node, Tree};
+
+use crate::analyzer::html::parser::HtmlParser;
+use crate::util::{is_html_file, offset_to_position, position_to_offset};
+
+/// タグ名として編集中に受け付ける文字 (カスタム要素のハイフン・名前空間のコロンを含む)
+const TAG_NAME_PATTERN: &str = "[A-Za-z][A-Za-z0-9_.:-]*";
+
+#[derive(Default)]
+pub struct LinkedEditingRangeHandler;
+
+impl LinkedEditingRangeHandler {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// `textDocument/linkedEditingRange` を処理する
+    ///
+    /// `cached_tree` は `source` からパース済みの Tree (キャッシュヒット時のみ)
+    pub fn linked_editing_ranges(
+        &self,
+        uri: &Url,
+        source: &str,
+        position: Position,
+        cached_tree: Option<Tree>,
+    ) -> Option<LinkedEditingRanges> {
+        if !is_html_file(uri) {
+            return None;
+        }
+        let tree = cached_tree.or_else(|| HtmlParser::new().parse(source))?;
+        let offset = position_to_offset(source, position);
+
+        // タグ名の直後 (`<div|>`) にカーソルがある場合も対象にする
+        let tag_name = tag_name_at(&tree, offset)
+            .or_else(|| offset.checked_sub(1).and_then(|offset| tag_name_at(&tree, offset)))?;
+        let element = tag_name.parent()?.parent()?;
+
+        let mut cursor = element.walk();
+        let mut start_name = None;
+        let mut end_name = None;
+        for child in element.named_children(&mut cursor) {
+            match child.kind() {
+                "start_tag" => start_name = child.named_child(0).filter(|n| n.kind() == "tag_name"),
+                "end_tag" => end_name = child.named_child(0).filter(|n| n.kind() == "tag_name"),
+                _ => {}
+            }
+        }
+        let (start_name, end_name) = (start_name?, end_name?);
+
+        Some(LinkedEditingRanges {
+            ranges: vec![node_range(source, start_name), node_range(source, end_name)],
+            word_pattern: Some(TAG_NAME_PATTERN.to_string()),
+        })
+    }
+}
+
+/// `offset` にある開始タグ / 終了タグのタグ名ノード
+fn tag_name_at(tree: &Tree, offset: usize) -> Option<Node<'_>> {
+    let node = tree.root_node().descendant_for_byte_range(offset, offset)?;
+    let is_paired_tag = node
+        .parent()
+        .is_some_and(|parent| matches!(parent.kind(), "start_tag" | "end_tag"));
+    (node.kind() == "tag_name" && is_paired_tag).then_some(node)
+}
+
+fn node_range(source: &str, node: Node) -> Range {
+    Range::new(
+        offset_to_position(source, node.start_byte()),
+        offset_to_position(source, node.end_byte()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(source: &str, line: u32, character: u32) -> Option<Vec<(u32, u32, u32)>> {
+        let uri = Url::parse("file:///a.html").unwrap();
+        LinkedEditingRangeHandler::new()
+            .linked_editing_ranges(&uri, source, Position::new(line, character), None)
+            .map(|r| {
+                r.ranges
+                    .into_iter()
+                    .map(|range| (range.start.line, range.start.character, range.end.character))
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn start_and_end_tag_names_are_linked() {
+        let source = "<div>\n  <user-list items=\"vm.users\"></user-list>\n</div>\n";
+        // 開始タグ名の中
+        assert_eq!(ranges(source, 1, 5), Some(vec![(1, 3, 12), (1, 32, 41)]));
+        // 終了タグ名の直後
+        assert_eq!(ranges(source, 1, 41), Some(vec![(1, 3, 12), (1, 32, 41)]));
+        // 外側の要素
+        assert_eq!(ranges(source, 2, 3), Some(vec![(0, 1, 4), (2, 2, 5)]));
+    }
+
+    #[test]
+    fn positions_use_utf16_columns() {
+        let source = "<p>日本語</p><span>x</span>";
+        assert_eq!(ranges(source, 0, 12), Some(vec![(0, 11, 15), (0, 19, 23)]));
+    }
+
+    #[test]
+    fn self_closing_and_unclosed_tags_return_nothing() {
+        assert_eq!(ranges("<user-card name=\"a\" />\n", 0, 3), None);
+        assert_eq!(ranges("<input type=\"text\">\n", 0, 3), None);
+        // タグ名以外 (属性) にカーソルがある場合も対象外
+        assert_eq!(ranges("<div class=\"a\"></div>\n", 0, 7), None);
+    }
+}
@@ -1,28 +1,45 @@
+mod call_hierarchy;
+mod code_action;
 mod codelens;
 mod completion;
 mod definition;
 mod diagnostics;
+mod document_color;
 mod document_highlight;
 mod document_symbol;
+mod folding_range;
 mod hover;
 pub mod inlay_hints;
+mod linked_editing_range;
+mod on_type_formatting;
 mod references;
 mod rename;
 pub mod resolve;
+mod selection_range;
 mod semantic_tokens;
 mod signature_help;
 mod workspace_symbol;
 
+pub use call_hierarchy::CallHierarchyHandler;
+pub use code_action::CodeActionHandler;
 pub use codelens::CodeLensHandler;
-pub use completion::CompletionHandler;
+pub use completion::{angularjs_completion_symbol, CompletionHandler};
 pub use definition::DefinitionHandler;
 pub use diagnostics::DiagnosticsHandler;
+pub use document_color::DocumentColorHandler;
 pub use document_highlight::DocumentHighlightHandler;
 pub use document_symbol::DocumentSymbolHandler;
+pub use folding_range::FoldingRangeHandler;
 pub use hover::HoverHandler;
-pub use inlay_hints::{new_js_tree_cache, InlayHintsHandler, JsTreeCache};
+pub use inlay_hints::InlayHintsHandler;
+pub use linked_editing_range::LinkedEditingRangeHandler;
+pub use on_type_formatting::{
+    OnTypeFormattingHandler, FIRST_TRIGGER_CHARACTER, MORE_TRIGGER_CHARACTERS,
+};
 pub use references::ReferencesHandler;
 pub use rename::RenameHandler;
+pub use resolve::{locate_symbol_at, LocatedSymbol};
+pub use selection_range::SelectionRangeHandler;
 pub use semantic_tokens::SemanticTokensHandler;
 pub use signature_help::SignatureHelpHandler;
 pub use workspace_symbol::WorkspaceSymbolHandler;
@@ -0,0 +1,123 @@
+//! On-type formatting handler.
+//!
+//! HTML で補間の開始記号 (`{{`、`$interpolateProvider` 設定時は `[[` など) を
+//! 入力したとき、対応する終了記号をカーソル位置に挿入する。
+//! 同じ行のカーソル以降に閉じ記号が既にあれば (`{{| name }}`) 挿入しない。
+
+use std::sync::Arc;
+
+use tower_lsp::lsp_types::{Position, Range, TextEdit, Url};
+
+use crate::index::Index;
+use crate::util::{is_html_file, utf16_col_to_byte_col};
+
+/// `document_on_type_formatting_provider` に登録するトリガー文字
+///
+/// 初期化時点では `$interpolateProvider` の設定が分からないので、
+/// よく使われるカスタム記号 (`[[`) の分も登録しておく
+pub const FIRST_TRIGGER_CHARACTER: &str = "{";
+pub const MORE_TRIGGER_CHARACTERS: &[&str] = &["["];
+
+pub struct OnTypeFormattingHandler {
+    index: Arc<Index>,
+}
+
+impl OnTypeFormattingHandler {
+    pub fn new(index: Arc<Index>) -> Self {
+        Self { index }
+    }
+
+    /// `textDocument/onTypeFormatting` を処理する
+    ///
+    /// `position` は入力された文字 `ch` の直後
+    pub fn on_type_formatting(
+        &self,
+        uri: &Url,
+        source: &str,
+        position: Position,
+        ch: &str,
+    ) -> Option<Vec<TextEdit>> {
+        if !is_html_file(uri) {
+            return None;
+        }
+        let (start_symbol, end_symbol) = self.index.interpolate.resolved();
+        if start_symbol.is_empty() || !start_symbol.ends_with(ch) {
+            return None;
+        }
+
+        let line = source.lines().nth(position.line as usize)?;
+        let cursor = utf16_col_to_byte_col(source, position.line, position.character) as usize;
+        let (before, after) = line.split_at(cursor);
+        if !before.ends_with(start_symbol.as_str()) {
+            return None;
+        }
+
+        // 閉じ記号が次の開始記号より前にあれば、既に閉じている
+        let next_end = after.find(end_symbol.as_str());
+        let next_start = after.find(start_symbol.as_str());
+        let already_closed = match (next_end, next_start) {
+            (Some(end), Some(start)) => end < start,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if already_closed {
+            return None;
+        }
+
+        Some(vec![TextEdit {
+            range: Range::new(position, position),
+            new_text: end_symbol,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edits(index: Arc<Index>, source: &str, line: u32, character: u32, ch: &str) -> Option<Vec<String>> {
+        let uri = Url::parse("file:///a.html").unwrap();
+        OnTypeFormattingHandler::new(index)
+            .on_type_formatting(&uri, source, Position::new(line, character), ch)
+            .map(|edits| edits.into_iter().map(|edit| edit.new_text).collect())
+    }
+
+    #[test]
+    fn closes_default_interpolation() {
+        let index = Arc::new(Index::new());
+        assert_eq!(
+            edits(Arc::clone(&index), "<p>\n  日本 {{\n</p>", 1, 7, "{"),
+            Some(vec!["}}".to_string()])
+        );
+        // 1 文字目の `{` では閉じない
+        assert_eq!(edits(Arc::clone(&index), "<p>{</p>", 0, 4, "{"), None);
+        // 既に閉じ記号がある
+        assert_eq!(edits(Arc::clone(&index), "<p>{{}}</p>", 0, 5, "{"), None);
+        assert_eq!(edits(Arc::clone(&index), "<p>{{ name }}</p>", 0, 5, "{"), None);
+        // 後ろにあるのは別の補間の閉じ記号
+        assert_eq!(
+            edits(Arc::clone(&index), "<p>{{ {{ b }}</p>", 0, 5, "{"),
+            Some(vec!["}}".to_string()])
+        );
+        // JS ファイルは対象外
+        let uri = Url::parse("file:///a.js").unwrap();
+        assert_eq!(
+            OnTypeFormattingHandler::new(index).on_type_formatting(&uri, "{{", Position::new(0, 2), "{"),
+            None
+        );
+    }
+
+    #[test]
+    fn closes_custom_interpolation_symbols() {
+        let index = Arc::new(Index::new());
+        let js = Url::parse("file:///app.js").unwrap();
+        index.interpolate.set_start_symbol(js.clone(), "[[".to_string());
+        index.interpolate.set_end_symbol(js, "]]".to_string());
+
+        assert_eq!(
+            edits(Arc::clone(&index), "<p>[[</p>", 0, 5, "["),
+            Some(vec!["]]".to_string()])
+        );
+        assert_eq!(edits(index, "<p>{{</p>", 0, 5, "{"), None);
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tower_lsp::lsp_types::*;
 use tracing::debug;
 
+use super::resolve::{locate_symbol_at, LocatedSymbol};
 use crate::index::{HtmlResolution, Index};
 use crate::model::{HtmlFormBinding, HtmlLocalVariable, HtmlUiSrefReference, SymbolKind};
 use crate::util::is_html_file;
@@ -17,31 +18,37 @@ impl ReferencesHandler {
     }
 
     pub fn find_references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
-        let uri = params.text_document_position.text_document.uri;
+        let uri = &params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
+        let located = locate_symbol_at(&self.index, uri, position.line, position.character, None);
+        self.find_references_with_located(params, located.as_ref())
+    }
+
+    /// `located` はカーソル位置で [`locate_symbol_at`] した結果
+    pub fn find_references_with_located(
+        &self,
+        params: ReferenceParams,
+        located: Option<&LocatedSymbol>,
+    ) -> Option<Vec<Location>> {
+        let uri = params.text_document_position.text_document.uri;
         let include_declaration = params.context.include_declaration;
+        let located = located?;
 
         // HTMLファイルの場合は専用の処理
         if is_html_file(&uri) {
-            return self.find_references_from_html(&uri, position, include_declaration);
+            return self.find_references_from_html(located.html.clone()?, include_declaration);
         }
 
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
-            position.line,
-            position.character,
-        )?;
-
         // シンボルがディレクティブまたはコンポーネントの場合、HTML参照も収集
-        let definitions = self.index.definitions.get_definitions(&symbol_name);
+        let definitions = self.index.definitions.get_definitions(&located.name);
         if definitions
             .iter()
             .any(|d| d.kind == SymbolKind::Directive || d.kind == SymbolKind::Component)
         {
-            return self.collect_directive_all_references(&symbol_name, include_declaration);
+            return self.collect_directive_all_references(&located.name, include_declaration);
         }
 
-        self.collect_references(&symbol_name, include_declaration)
+        self.collect_references(&located.name, include_declaration)
     }
 
     /// HTMLファイルからの参照検索
@@ -50,15 +57,17 @@ impl ReferencesHandler {
     /// ここではその結果を `Vec<Location>` にマッピングするだけ。
     fn find_references_from_html(
         &self,
-        uri: &Url,
-        position: Position,
+        resolution: HtmlResolution,
         include_declaration: bool,
     ) -> Option<Vec<Location>> {
-        match self.index.resolve_html_position(uri, position, None)? {
+        match resolution {
             HtmlResolution::UiSref(r) => self.build_for_ui_sref(&r, include_declaration),
             HtmlResolution::Directive(r) => {
                 self.collect_directive_all_references(&r.directive_name, include_declaration)
             }
+            HtmlResolution::Filter(name) | HtmlResolution::Controller(name) => {
+                self.collect_references(&name, include_declaration)
+            }
             HtmlResolution::LocalVarDef(v) | HtmlResolution::LocalVarRef(v) => {
                 self.collect_local_variable_references(&v, include_declaration)
             }
@@ -230,8 +239,8 @@ impl ReferencesHandler {
             });
         }
 
-        // JS内の参照も追加
-        for reference in self.index.get_all_references(directive_name) {
+        // JS内の参照も追加 (`require: '^userCard'` など)
+        for reference in self.index.definitions.get_references(directive_name) {
             locations.push(Location {
                 uri: reference.uri.clone(),
                 range: reference.span.to_lsp_range(),
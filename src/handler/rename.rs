@@ -1,28 +1,66 @@
 use std::collections::HashMap;
+use std::fs;
 use std::sync::Arc;
 
+use dashmap::DashMap;
 use tower_lsp::lsp_types::*;
-
-use crate::index::Index;
-use crate::model::{HtmlFormBinding, HtmlLocalVariable};
-use crate::util::is_html_file;
+use tree_sitter::{Point, Tree};
+
+use crate::analyzer::html::parser::HtmlParser;
+use crate::analyzer::js::JsParser;
+use crate::analyzer::html::variable_parser::is_valid_identifier;
+use super::resolve::{locate_symbol_at, LocatedSymbol};
+use crate::index::{HtmlResolution, Index};
+use crate::model::{HtmlControllerScope, HtmlFormBinding, HtmlLocalVariable, Span, SymbolKind};
+use crate::util::{
+    byte_col_to_utf16_col, camel_to_kebab, is_html_file, is_js_file, kebab_to_camel,
+    position_to_offset,
+};
 
 pub struct RenameHandler {
     index: Arc<Index>,
+    /// 開いているドキュメントの内容 (HTML 属性名の接頭辞の判定に使う)
+    documents: Arc<DashMap<Url, String>>,
 }
 
 impl RenameHandler {
-    pub fn new(index: Arc<Index>) -> Self {
-        Self { index }
+    pub fn new(index: Arc<Index>, documents: Arc<DashMap<Url, String>>) -> Self {
+        Self { index, documents }
     }
 
     pub fn rename(&self, params: RenameParams) -> Option<WorkspaceEdit> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let located = locate_symbol_at(&self.index, uri, position.line, position.character, None);
+        self.rename_with_located(params, located.as_ref())
+    }
+
+    /// `located` はカーソル位置で [`locate_symbol_at`] した結果
+    pub fn rename_with_located(
+        &self,
+        params: RenameParams,
+        located: Option<&LocatedSymbol>,
+    ) -> Option<WorkspaceEdit> {
         let uri = params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
         let new_name = params.new_name;
 
+        // フィルター / ディレクティブは JS の登録名と HTML の使用箇所を横断してリネーム
+        if let Some((name, kind)) = located.and_then(|located| self.registered_name(located)) {
+            return match kind {
+                SymbolKind::Filter => self.collect_filter_edits(&name, &new_name),
+                SymbolKind::Module => self.collect_module_edits(&name, &new_name),
+                _ => self.collect_directive_edits(&name, &new_name),
+            };
+        }
+
         // HTMLファイルの場合は専用の処理
         if is_html_file(&uri) {
+            // controller as のエイリアス（ng-controller 内の定義、または `vm.xxx` の `vm`）
+            if let Some(alias_scope) = self.find_controller_alias_at(&uri, position) {
+                return self.collect_controller_alias_edits(&alias_scope, &new_name);
+            }
+
             // まずローカル変数をチェック（定義位置にカーソルがある場合）
             if let Some(local_var_def) = self.index.html.find_html_local_variable_definition_at(
                 &uri,
@@ -69,10 +107,14 @@ impl RenameHandler {
                     .unwrap_or(&html_ref.property_path);
 
                 // フォームバインディング参照かどうかをチェック
+                // (`userForm.email` の `email` 部分はフィールド名なので対象外)
                 if let Some(form_binding) =
                     self.index
                         .find_form_binding_definition(&uri, base_name, position.line)
                 {
+                    if html_ref.property_path.contains('.') {
+                        return None;
+                    }
                     return self.collect_form_binding_edits(&uri, &form_binding, &new_name);
                 }
 
@@ -90,13 +132,298 @@ impl RenameHandler {
             return self.collect_edits(&symbol_name, &new_name);
         }
 
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
+        self.collect_edits(&located?.name, &new_name)
+    }
+
+    /// 新しい名前が不正ならエラーメッセージを返す
+    ///
+    /// フィルター名は JS 識別子、ディレクティブ名は camelCase (`userCard`) または
+    /// kebab-case (`user-card` / `data-user-card`) で指定できる。
+    /// それ以外のシンボルはここでは検証しない
+    pub fn validate_new_name(&self, params: &RenameParams) -> std::result::Result<(), String> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        if self.find_controller_alias_at(uri, position).is_some() {
+            return if is_valid_identifier(&params.new_name) {
+                Ok(())
+            } else {
+                Err(format!("'{}' is not a valid controller alias", params.new_name))
+            };
+        }
+        let Some((_, kind)) = self.find_registered_name_at(uri, position) else {
+            return Ok(());
+        };
+        let valid = match kind {
+            SymbolKind::Filter => is_valid_identifier(&params.new_name),
+            SymbolKind::Module => is_valid_module_name(&params.new_name),
+            _ => normalize_directive_name(&params.new_name).is_some(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' is not a valid {} name",
+                params.new_name,
+                kind.as_str()
+            ))
+        }
+    }
+
+    /// カーソル位置のフィルター / ディレクティブ (コンポーネント) / モジュール名を取得
+    ///
+    /// HTML では `| myFilter` のフィルター参照と要素・属性のディレクティブ参照、
+    /// JS では登録名の文字列リテラルや `require`・モジュール依存配列等の参照が対象。
+    /// 戻り値の kind はコンポーネントも `Directive` として扱う
+    fn find_registered_name_at(&self, uri: &Url, position: Position) -> Option<(String, SymbolKind)> {
+        let located = locate_symbol_at(&self.index, uri, position.line, position.character, None)?;
+        self.registered_name(&located)
+    }
+
+    /// `located` がフィルター / ディレクティブ (コンポーネント) / モジュール名なら、その名前と kind
+    fn registered_name(&self, located: &LocatedSymbol) -> Option<(String, SymbolKind)> {
+        let name = match &located.html {
+            Some(HtmlResolution::Filter(name)) => name.clone(),
+            Some(HtmlResolution::Directive(directive_ref)) => directive_ref.directive_name.clone(),
+            Some(_) => return None,
+            None => located.name.clone(),
+        };
+
+        let definitions = &self.index.definitions;
+        if definitions.has_definition_of_kind(&name, SymbolKind::Filter) {
+            Some((name, SymbolKind::Filter))
+        } else if definitions.has_definition_of_kind(&name, SymbolKind::Directive)
+            || definitions.has_definition_of_kind(&name, SymbolKind::Component)
+        {
+            Some((name, SymbolKind::Directive))
+        } else if definitions.has_definition_of_kind(&name, SymbolKind::Module) {
+            Some((name, SymbolKind::Module))
+        } else {
+            None
+        }
+    }
+
+    /// カーソル位置の controller as エイリアスを定義している ng-controller スコープを取得
+    ///
+    /// `ng-controller="UserController as vm"` の `vm` と、`vm.xxx` 参照の `vm` 部分が対象
+    fn find_controller_alias_at(&self, uri: &Url, position: Position) -> Option<HtmlControllerScope> {
+        if !is_html_file(uri) {
+            return None;
+        }
+
+        let scopes = self.index.controllers.get_all_html_controller_scopes(uri);
+        if let Some(scope) = scopes.into_iter().find(|s| {
+            s.alias_span
+                .is_some_and(|span| span.contains(position.line, position.character))
+        }) {
+            return Some(scope);
+        }
+
+        let html_ref = self.index.html.find_html_scope_reference_at(
+            uri,
             position.line,
             position.character,
         )?;
+        // `vm.name` は `vm` 単独の参照と `name` 部分の参照に分けて登録されている
+        if html_ref.property_path.contains('.') {
+            return None;
+        }
+        self.index
+            .controllers
+            .find_html_alias_scope_at(uri, html_ref.start_line, &html_ref.property_path)
+    }
+
+    /// エイリアスの定義と、そのスコープ内の `alias.xxx` の `alias` 部分の編集を収集
+    ///
+    /// ネストした同名エイリアスのスコープ内の参照は内側の定義を指すため除外する。
+    /// エイリアスはテンプレートごとに閉じているので ng-include 先は対象外
+    fn collect_controller_alias_edits(
+        &self,
+        alias_scope: &HtmlControllerScope,
+        new_name: &str,
+    ) -> Option<WorkspaceEdit> {
+        if !is_valid_identifier(new_name) {
+            return None;
+        }
+        let alias = alias_scope.alias.as_deref()?;
+        let uri = &alias_scope.uri;
+
+        let mut edits = Vec::new();
+        if let Some(span) = alias_scope.alias_span {
+            edits.push(TextEdit {
+                range: span.to_lsp_range(),
+                new_text: new_name.to_string(),
+            });
+        }
+
+        for reference in self.index.html.get_html_scope_references(uri) {
+            if reference.property_path != alias {
+                continue;
+            }
+            let resolves_here = self
+                .index
+                .controllers
+                .find_html_alias_scope_at(uri, reference.start_line, alias)
+                .is_some_and(|s| {
+                    s.start_line == alias_scope.start_line && s.end_line == alias_scope.end_line
+                });
+            if !resolves_here {
+                continue;
+            }
+            edits.push(TextEdit {
+                range: reference.span().to_lsp_range(),
+                new_text: new_name.to_string(),
+            });
+        }
 
-        self.collect_edits(&symbol_name, &new_name)
+        if edits.is_empty() {
+            return None;
+        }
+        let mut changes = HashMap::new();
+        changes.insert(uri.clone(), edits);
+        Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        })
+    }
+
+    /// フィルターの定義 (`.filter('name', ...)`) と全参照 (`| name`, `$filter('name')`) の編集を収集
+    fn collect_filter_edits(&self, filter_name: &str, new_name: &str) -> Option<WorkspaceEdit> {
+        if !is_valid_identifier(new_name) {
+            return None;
+        }
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for def in self.index.definitions.get_definitions(filter_name) {
+            if def.kind != SymbolKind::Filter {
+                continue;
+            }
+            changes.entry(def.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&def.name_span, filter_name),
+                new_text: new_name.to_string(),
+            });
+        }
+        for reference in self.index.definitions.get_references(filter_name) {
+            changes.entry(reference.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&reference.span, filter_name),
+                new_text: new_name.to_string(),
+            });
+        }
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// モジュール名の編集を収集
+    ///
+    /// `angular.module('name', [...])` (setter) の定義と、`angular.module('name')` (getter)
+    /// および他モジュールの依存配列 `['name', ...]` 内の参照が対象。
+    /// 名前の完全一致で引くため `name.sub` のような別モジュールは置換しない
+    fn collect_module_edits(&self, module_name: &str, new_name: &str) -> Option<WorkspaceEdit> {
+        if !is_valid_module_name(new_name) {
+            return None;
+        }
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for def in self.index.definitions.get_definitions(module_name) {
+            if def.kind != SymbolKind::Module {
+                continue;
+            }
+            changes.entry(def.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&def.name_span, module_name),
+                new_text: new_name.to_string(),
+            });
+        }
+        for reference in self.index.definitions.get_references(module_name) {
+            changes.entry(reference.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&reference.span, module_name),
+                new_text: new_name.to_string(),
+            });
+        }
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// ディレクティブの編集を収集
+    ///
+    /// JS の登録名・`require` 参照は camelCase、HTML の要素名・属性名は kebab-case に
+    /// 変換して置き換える。`data-` / `x-` 接頭辞は残す
+    fn collect_directive_edits(&self, directive_name: &str, new_name: &str) -> Option<WorkspaceEdit> {
+        let new_camel = normalize_directive_name(new_name)?;
+        let new_kebab = camel_to_kebab(&new_camel);
+        let old_kebab = camel_to_kebab(directive_name);
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for def in self.index.definitions.get_definitions(directive_name) {
+            if !matches!(def.kind, SymbolKind::Directive | SymbolKind::Component) {
+                continue;
+            }
+            changes.entry(def.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&def.name_span, directive_name),
+                new_text: new_camel.clone(),
+            });
+        }
+        for reference in self.index.definitions.get_references(directive_name) {
+            changes.entry(reference.uri.clone()).or_default().push(TextEdit {
+                range: name_range_in_span(&reference.span, directive_name),
+                new_text: new_camel.clone(),
+            });
+        }
+        let mut sources: HashMap<Url, Option<String>> = HashMap::new();
+        for reference in self.index.html.get_html_directive_references(directive_name) {
+            // 属性名の `data-` / `x-` 接頭辞は実際の属性名から判断する
+            let span = reference.span();
+            let source = sources
+                .entry(reference.uri.clone())
+                .or_insert_with(|| self.source_of(&reference.uri));
+            let written = source.as_deref().map_or("", |source| {
+                let range = span.to_lsp_range();
+                let start = position_to_offset(source, range.start);
+                let end = position_to_offset(source, range.end).max(start);
+                &source[start..end]
+            });
+            let prefix = ["data-", "x-"]
+                .into_iter()
+                .find(|prefix| {
+                    written
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.eq_ignore_ascii_case(&old_kebab))
+                })
+                .unwrap_or("");
+            changes.entry(reference.uri.clone()).or_default().push(TextEdit {
+                range: span.to_lsp_range(),
+                new_text: format!("{}{}", prefix, new_kebab),
+            });
+        }
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// 開いているドキュメント、なければディスク上のファイルの内容
+    fn source_of(&self, uri: &Url) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(doc) => Some(doc.value().clone()),
+            None => fs::read_to_string(uri.to_file_path().ok()?).ok(),
+        }
     }
 
     /// HTMLファイルからシンボル名を解決
@@ -137,44 +464,41 @@ impl RenameHandler {
                 .resolve_controller_for_html(uri, position.line)?
         };
 
-        // 4. シンボル名を構築 "ControllerName.$scope.property"
-        let scope_symbol = format!("{}.$scope.{}", controller_name, property_path);
-
-        // まず$scope形式で定義が見つかるか確認
-        if !self
-            .index
-            .definitions
-            .get_definitions(&scope_symbol)
-            .is_empty()
-        {
-            return Some(scope_symbol);
-        }
-
-        // 5. controller as 構文の場合、ControllerName.method 形式も試す
+        // 4. controller as 構文 (`vm.userName`) はコントローラーインスタンスのプロパティ
+        //    "ControllerName.property" を優先する (同名の `$scope.userName` とは別物)
         if resolved_controller.is_some() {
             let method_symbol = format!("{}.{}", controller_name, property_path);
-            if !self
-                .index
-                .definitions
-                .get_definitions(&method_symbol)
-                .is_empty()
-            {
+            if self.index.definitions.has_definition(&method_symbol) {
                 return Some(method_symbol);
             }
         }
 
-        // デフォルトで$scope形式を返す
-        Some(scope_symbol)
+        // 5. シンボル名を構築 "ControllerName.$scope.property"
+        Some(format!("{}.$scope.{}", controller_name, property_path))
     }
 
     /// シンボル名から編集を収集
     fn collect_edits(&self, symbol_name: &str, new_name: &str) -> Option<WorkspaceEdit> {
         let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        // 文字列リテラルの判定に使う JS ファイルの Tree (ファイルごとに 1 回だけパースする)
+        let mut trees: HashMap<Url, Option<Tree>> = HashMap::new();
+        let mut edit_range = |uri: &Url, span: &Span| {
+            let tree = trees.entry(uri.clone()).or_insert_with(|| {
+                if !is_js_file(uri) {
+                    return None;
+                }
+                JsParser::for_uri(uri).parse(&self.source_of(uri)?)
+            });
+            tree.as_ref()
+                .and_then(|tree| string_literal_content(tree, span))
+                .unwrap_or(*span)
+                .to_lsp_range()
+        };
 
         // Collect definition locations (use name_span for accurate renaming)
         for def in self.index.definitions.get_definitions(symbol_name) {
             let edit = TextEdit {
-                range: def.name_span.to_lsp_range(),
+                range: edit_range(&def.uri, &def.name_span),
                 new_text: new_name.to_string(),
             };
             changes.entry(def.uri.clone()).or_default().push(edit);
@@ -183,7 +507,7 @@ impl RenameHandler {
         // Collect reference locations
         for reference in self.index.get_all_references(symbol_name) {
             let edit = TextEdit {
-                range: reference.span.to_lsp_range(),
+                range: edit_range(&reference.uri, &reference.span),
                 new_text: new_name.to_string(),
             };
             changes
@@ -315,42 +639,145 @@ impl RenameHandler {
         }
     }
 
+    /// `textDocument/prepareRename` を処理する
+    ///
+    /// カーソル位置で実際に書き換わる範囲と、その現在のテキストをプレースホルダーとして返す。
+    /// `vm.userName` では `vm` 上ならエイリアス、`userName` 上ならプロパティだけが対象。
+    /// 組み込みディレクティブ名や HTML 式中の文字列リテラルなどリネームできない位置は `None`
+    ///
+    /// `cached_tree` は `source` をパースした Tree (HTML なら HTML、JS なら JS)。
+    /// なければその場でパースする
     pub fn prepare_rename(
         &self,
         params: TextDocumentPositionParams,
+        source: &str,
+        cached_tree: Option<Tree>,
     ) -> Option<PrepareRenameResponse> {
         let uri = params.text_document.uri;
         let position = params.position;
 
-        // HTMLファイルの場合は専用の処理
-        if is_html_file(&uri) {
-            return self.prepare_rename_from_html(&uri, position);
+        let range = if let Some((name, kind)) = self.find_registered_name_at(&uri, position) {
+            // フィルター / ディレクティブは名前部分 (クォートや `data-` を除く) を範囲とする
+            self.prepare_registered_name_range(&uri, position, &name, kind)?
+        } else if is_html_file(&uri) {
+            // `ng-if="mode == 'vm'"` の `'vm'` はスコープ参照ではない
+            let tree = cached_tree.or_else(|| HtmlParser::new().parse(source));
+            let start_symbol = &self.index.interpolate.resolved().0;
+            if tree.is_some_and(|tree| is_in_html_string_literal(&tree, source, position, start_symbol)) {
+                return None;
+            }
+            match self.find_controller_alias_at(&uri, position) {
+                Some(alias_scope) => {
+                    self.prepare_controller_alias_range(&uri, position, &alias_scope)?
+                }
+                None => self.prepare_rename_from_html(&uri, position)?,
+            }
+        } else {
+            let located =
+                locate_symbol_at(&self.index, &uri, position.line, position.character, None)?;
+            let span = self.find_symbol_span_at_position(&located.name, &uri, position)?;
+            let span = cached_tree
+                .or_else(|| JsParser::for_uri(&uri).parse(source))
+                .and_then(|tree| string_literal_content(&tree, &span))
+                .unwrap_or(span);
+            // JS のインデックスの列はバイト列なので、クライアントの UTF-16 に揃える
+            Range::new(
+                Position::new(
+                    span.start_line,
+                    byte_col_to_utf16_col(source, span.start_line, span.start_col),
+                ),
+                Position::new(
+                    span.end_line,
+                    byte_col_to_utf16_col(source, span.end_line, span.end_col),
+                ),
+            )
+        };
+
+        Some(match text_in_range(source, range) {
+            Some(placeholder) => PrepareRenameResponse::RangeWithPlaceholder { range, placeholder },
+            None => PrepareRenameResponse::Range(range),
+        })
+    }
+
+    /// フィルター / ディレクティブ名の prepare_rename 範囲
+    fn prepare_registered_name_range(
+        &self,
+        uri: &Url,
+        position: Position,
+        name: &str,
+        kind: SymbolKind,
+    ) -> Option<Range> {
+        let contains = |span: &Span| span.contains(position.line, position.character);
+
+        // HTML の要素名・属性名は kebab-case 部分のみ
+        if kind == SymbolKind::Directive {
+            let kebab = camel_to_kebab(name);
+            if let Some(reference) = self
+                .index
+                .html
+                .find_html_directive_reference_at(uri, position.line, position.character)
+            {
+                let range = reference.span().to_lsp_range();
+                let start = Position {
+                    line: range.end.line,
+                    character: range.end.character.saturating_sub(kebab.len() as u32),
+                };
+                return Some(Range { start, end: range.end });
+            }
         }
 
-        let symbol_name = self.index.definitions.find_symbol_at_position(
-            &uri,
+        let definition_spans = self
+            .index
+            .definitions
+            .get_definitions(name)
+            .into_iter()
+            .filter(|d| d.uri == *uri)
+            .map(|d| d.name_span);
+        let reference_spans = self
+            .index
+            .definitions
+            .get_references(name)
+            .into_iter()
+            .filter(|r| r.uri == *uri)
+            .map(|r| r.span);
+        definition_spans
+            .chain(reference_spans)
+            .find(contains)
+            .map(|span| name_range_in_span(&span, name))
+    }
+
+    /// controller as エイリアスの prepare_rename 範囲
+    fn prepare_controller_alias_range(
+        &self,
+        uri: &Url,
+        position: Position,
+        alias_scope: &HtmlControllerScope,
+    ) -> Option<Range> {
+        if let Some(span) = alias_scope
+            .alias_span
+            .filter(|span| span.contains(position.line, position.character))
+        {
+            return Some(span.to_lsp_range());
+        }
+        let reference = self.index.html.find_html_scope_reference_at(
+            uri,
             position.line,
             position.character,
         )?;
-
-        self.find_symbol_range_at_position(&symbol_name, &uri, position)
+        Some(reference.span().to_lsp_range())
     }
 
     /// HTMLファイルからのprepare_rename
-    fn prepare_rename_from_html(
-        &self,
-        uri: &Url,
-        position: Position,
-    ) -> Option<PrepareRenameResponse> {
+    ///
+    /// 範囲は常にカーソル位置の定義 / 参照自身 (継承元の定義位置ではない)
+    fn prepare_rename_from_html(&self, uri: &Url, position: Position) -> Option<Range> {
         // まずローカル変数定義をチェック
         if let Some(local_var_def) = self.index.html.find_html_local_variable_definition_at(
             uri,
             position.line,
             position.character,
         ) {
-            return Some(PrepareRenameResponse::Range(
-                local_var_def.name_span().to_lsp_range(),
-            ));
+            return Some(local_var_def.name_span().to_lsp_range());
         }
 
         // ローカル変数参照をチェック
@@ -359,9 +786,7 @@ impl RenameHandler {
             position.line,
             position.character,
         ) {
-            return Some(PrepareRenameResponse::Range(
-                local_var_ref.span().to_lsp_range(),
-            ));
+            return Some(local_var_ref.span().to_lsp_range());
         }
 
         // フォームバインディング定義をチェック
@@ -370,9 +795,7 @@ impl RenameHandler {
             position.line,
             position.character,
         ) {
-            return Some(PrepareRenameResponse::Range(
-                form_binding.name_span().to_lsp_range(),
-            ));
+            return Some(form_binding.name_span().to_lsp_range());
         }
 
         // HTMLスコープ参照を取得
@@ -382,45 +805,30 @@ impl RenameHandler {
             position.character,
         )?;
 
-        let base_name = html_ref
+        // `userForm.email` の `email` 部分はフォームのフィールド名なのでリネーム対象外
+        // (`userForm` 部分は単独の参照として登録されている)
+        let is_form_field = html_ref
             .property_path
-            .split('.')
-            .next()
-            .unwrap_or(&html_ref.property_path);
-
-        // フォームバインディング参照かどうかをチェック
-        if let Some(form_binding) =
-            self.index
-                .find_form_binding_definition(uri, base_name, position.line)
-        {
-            return Some(PrepareRenameResponse::Range(
-                form_binding.name_span().to_lsp_range(),
-            ));
-        }
-
-        // 継承されたローカル変数かどうかをチェック
-        if let Some(var_def) =
-            self.index
-                .find_local_variable_definition(uri, base_name, position.line)
-        {
-            return Some(PrepareRenameResponse::Range(
-                var_def.name_span().to_lsp_range(),
-            ));
+            .split_once('.')
+            .is_some_and(|(base_name, _)| {
+                self.index
+                    .find_form_binding_definition(uri, base_name, position.line)
+                    .is_some()
+            });
+        if is_form_field {
+            return None;
         }
 
-        // 通常のスコープ参照の範囲を返す
-        Some(PrepareRenameResponse::Range(
-            html_ref.span().to_lsp_range(),
-        ))
+        Some(html_ref.span().to_lsp_range())
     }
 
-    /// シンボルの範囲を見つける
-    fn find_symbol_range_at_position(
+    /// カーソル位置にあるシンボルの定義 / 参照の span を見つける
+    fn find_symbol_span_at_position(
         &self,
         symbol_name: &str,
         uri: &Url,
         position: Position,
-    ) -> Option<PrepareRenameResponse> {
+    ) -> Option<Span> {
         // First check definitions
         for def in self.index.definitions.get_definitions(symbol_name) {
             if def.uri == *uri && def.name_span.contains_line(position.line) {
@@ -431,9 +839,7 @@ impl RenameHandler {
                     true
                 };
                 if in_range {
-                    return Some(PrepareRenameResponse::Range(
-                        def.name_span.to_lsp_range(),
-                    ));
+                    return Some(def.name_span);
                 }
             }
         }
@@ -448,9 +854,7 @@ impl RenameHandler {
                     true
                 };
                 if in_range {
-                    return Some(PrepareRenameResponse::Range(
-                        reference.span.to_lsp_range(),
-                    ));
+                    return Some(reference.span);
                 }
             }
         }
@@ -458,3 +862,103 @@ impl RenameHandler {
         None
     }
 }
+
+/// span 内の名前部分の範囲
+///
+/// JS の文字列リテラル (`'myFilter'`, `'^parentDirective'`) は span にクォートや
+/// 接頭辞を含むため、閉じクォート直前の名前部分だけを返す。span が名前と同じ長さなら
+/// (HTML のフィルター参照など) そのまま返す
+fn name_range_in_span(span: &Span, name: &str) -> Range {
+    let name_len = name.chars().count() as u32;
+    if span.start_line != span.end_line || span.end_col - span.start_col <= name_len {
+        return span.to_lsp_range();
+    }
+    let end_col = span.end_col - 1;
+    Span::new(span.start_line, end_col - name_len, span.end_line, end_col).to_lsp_range()
+}
+
+/// `span` が JS の文字列リテラル (`'MainCtrl'`) そのものなら、クォートを除いた中身の span
+fn string_literal_content(tree: &Tree, span: &Span) -> Option<Span> {
+    let start = Point::new(span.start_line as usize, span.start_col as usize);
+    let end = Point::new(span.end_line as usize, span.end_col as usize);
+    let node = tree.root_node().descendant_for_point_range(start, end)?;
+    if node.kind() != "string" || node.start_position() != start || node.end_position() != end {
+        return None;
+    }
+    // 先頭と末尾の子はクォート
+    let open = node.child(0)?.end_position();
+    let close = node.child(node.child_count().checked_sub(1)?)?.start_position();
+    Some(Span::new(open.row as u32, open.column as u32, close.row as u32, close.column as u32))
+}
+
+/// `range` のテキスト (1 行内の範囲のみ。列は UTF-16 コードユニット)
+fn text_in_range(source: &str, range: Range) -> Option<String> {
+    if range.start.line != range.end.line {
+        return None;
+    }
+    let start = position_to_offset(source, range.start);
+    let end = position_to_offset(source, range.end);
+    (start < end).then(|| source[start..end].to_string())
+}
+
+/// HTML の AngularJS 式 (ディレクティブ属性値 / 補間) の文字列リテラル内にカーソルがあるか
+///
+/// 式の先頭からカーソルまでのクォートの対応を数えて判定する
+fn is_in_html_string_literal(tree: &Tree, source: &str, position: Position, start_symbol: &str) -> bool {
+    let offset = position_to_offset(source, position);
+    let Some(node) = tree.root_node().descendant_for_byte_range(offset, offset) else {
+        return false;
+    };
+    let expression_start = match node.kind() {
+        "attribute_value" => node.start_byte(),
+        "text" => match source[node.start_byte()..offset].rfind(start_symbol) {
+            Some(open) => node.start_byte() + open + start_symbol.len(),
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in source[expression_start..offset].chars() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            None if c == '\'' || c == '"' => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
+/// 新しいディレクティブ名を camelCase に正規化する
+///
+/// `userCard` / `user-card` / `data-user-card` / `x-user-card` を受け付け、
+/// 小文字で始まる英数字の名前でなければ `None`
+fn normalize_directive_name(new_name: &str) -> Option<String> {
+    let name = new_name
+        .strip_prefix("data-")
+        .or_else(|| new_name.strip_prefix("x-"))
+        .unwrap_or(new_name);
+    if name.contains('-') {
+        let valid_kebab = name
+            .split('-')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        if !valid_kebab {
+            return None;
+        }
+    }
+    let camel = kebab_to_camel(name);
+    let mut chars = camel.chars();
+    let starts_lowercase = chars.next().is_some_and(|c| c.is_ascii_lowercase());
+    (starts_lowercase && chars.all(|c| c.is_ascii_alphanumeric())).then_some(camel)
+}
+
+/// モジュール名として使える文字列か (空でなく、空白・クォート・バックスラッシュを含まない)
+fn is_valid_module_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '`' | '\\'))
+}
@@ -1,163 +1,175 @@
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{Position, Range, Url};
 
-use crate::index::Index;
-use crate::model::{
-    HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable,
-};
+use crate::index::{HtmlResolution, Index};
+use crate::model::SymbolKind;
+use crate::util::is_html_file;
 
-/// HTML内のカーソル位置で解決されたシンボルの種類
-pub enum HtmlSymbolAtPosition {
-    /// カスタムディレクティブ参照（<my-directive> や属性として使用）
-    Directive(HtmlDirectiveReference),
-    /// ローカル変数の定義位置（ng-repeat, ng-init の定義行にカーソルがある）
-    LocalVariableDefinition(HtmlLocalVariable),
-    /// ローカル変数の参照位置（参照箇所にカーソルがある）
-    LocalVariableReference {
-        variable_name: String,
-        definition: HtmlLocalVariable,
-    },
-    /// フォームバインディングの定義位置（<form name="x"> の name にカーソルがある）
-    FormBindingDefinition(HtmlFormBinding),
-    /// フォームバインディングの参照位置（スコープ参照としてフォーム名が参照されている）
-    FormBindingReference(HtmlFormBinding),
-    /// スコープシンボル（$scope.xxx または controller as 構文のプロパティ）
-    ScopeSymbol {
-        symbol_name: String,
-        controller: String,
-        is_controller_as: bool,
-    },
-    /// $rootScopeシンボル
-    RootScopeSymbol {
-        symbol_name: String,
-    },
-    /// 継承されたローカル変数（ng-include経由で親テンプレートから継承）
-    InheritedLocalVariable(HtmlLocalVariable),
+/// カーソル下のシンボル
+///
+/// hover / definition / references / document highlight がカーソル位置の特定に使う。
+/// HTML では [`Index::resolve_html_position`] の結果を `html` に持ち、各ハンドラは
+/// その variant ごとの後段処理だけを実装する。
+#[derive(Debug, Clone)]
+pub struct LocatedSymbol {
+    /// シンボルの種別 (HTML のローカル変数などインデックスに定義がないものは `None`)
+    pub kind: Option<SymbolKind>,
+    /// インデックスのシンボル名
+    ///
+    /// HTML のスコープ参照は定義が見つかったシンボル名 (`Ctrl.$scope.x` など)、
+    /// 見つからなければプロパティパス。ローカル変数・フォーム名は変数名
+    pub name: String,
+    /// スコープ参照の解決済みコントローラー (HTML のスコープ参照以外は空)
+    pub controllers: Vec<String>,
+    /// カーソル下のトークンの範囲 (登録済みの参照がない位置では `None`)
+    pub range: Option<Range>,
+    /// AngularJS のシンボルとして解決できたか。`false` なら tsserver にフォールバックする
+    pub is_angularjs: bool,
+    /// HTML 上の解決結果 (JS では `None`)
+    pub html: Option<HtmlResolution>,
 }
 
-/// HTML内のカーソル位置からシンボル情報を解決する
-///
-/// 複数のハンドラー（hover, references, rename, definition）で共通して使用される
-/// 位置からのシンボル解決ロジックを一元化する。
+/// カーソル位置のシンボルを HTML / JS を問わず特定する
 ///
-/// 解決の優先順位:
-/// 1. カスタムディレクティブ参照
-/// 2. ローカル変数定義位置
-/// 3. ローカル変数参照位置
-/// 4. フォームバインディング定義位置
-/// 5. スコープ参照 -> フォームバインディング参照、継承ローカル変数、エイリアス解決、$scope、$rootScope
-pub fn resolve_html_position(
+/// JS では定義名・参照のうちカーソルを含む最小の範囲のシンボル、HTML では
+/// [`Index::resolve_html_position`] の解決順に従う。`source` は HTML の
+/// 継承された var / form binding を識別子だけで引く最終フォールバックでのみ使う
+pub fn locate_symbol_at(
     index: &Index,
     uri: &Url,
     line: u32,
     col: u32,
-) -> Option<HtmlSymbolAtPosition> {
-    // 1. カスタムディレクティブ参照をチェック
-    if let Some(directive_ref) = index.html.find_html_directive_reference_at(uri, line, col) {
-        return Some(HtmlSymbolAtPosition::Directive(directive_ref));
-    }
-
-    // 2. ローカル変数定義位置をチェック
-    if let Some(local_var_def) = index.html.find_html_local_variable_definition_at(uri, line, col)
-    {
-        return Some(HtmlSymbolAtPosition::LocalVariableDefinition(local_var_def));
+    source: Option<&str>,
+) -> Option<LocatedSymbol> {
+    if is_html_file(uri) {
+        return locate_html_symbol(index, uri, line, col, source);
     }
 
-    // 3. ローカル変数参照をチェック
-    if let Some(local_var_ref) = index.html.find_html_local_variable_at(uri, line, col) {
-        if let Some(var_def) =
-            index.find_local_variable_definition(uri, &local_var_ref.variable_name, line)
-        {
-            return Some(HtmlSymbolAtPosition::LocalVariableReference {
-                variable_name: local_var_ref.variable_name.clone(),
-                definition: var_def,
-            });
-        }
-    }
-
-    // 4. フォームバインディング定義位置をチェック
-    if let Some(form_binding) = index.html.find_html_form_binding_at(uri, line, col) {
-        return Some(HtmlSymbolAtPosition::FormBindingDefinition(form_binding));
-    }
-
-    // 5. スコープ参照を取得して解析
-    let html_ref = index.html.find_html_scope_reference_at(uri, line, col)?;
-
-    // 5a. フォームバインディング参照かどうかをチェック
-    let base_name = html_ref
-        .property_path
-        .split('.')
-        .next()
-        .unwrap_or(&html_ref.property_path);
-    if let Some(form_binding) = index.find_form_binding_definition(uri, base_name, line) {
-        return Some(HtmlSymbolAtPosition::FormBindingReference(form_binding));
-    }
-
-    // 5b. 継承されたローカル変数かどうかをチェック
-    if let Some(var_def) = index.find_local_variable_definition(uri, base_name, line) {
-        return Some(HtmlSymbolAtPosition::InheritedLocalVariable(var_def));
-    }
+    let (name, span) = index.definitions.find_symbol_span_at_position(uri, line, col)?;
+    let kind = index.definitions.get_definitions(&name).first().map(|d| d.kind);
+    Some(LocatedSymbol {
+        kind,
+        // 定義がない参照 (未登録のサービス名など) は tsserver に任せる
+        is_angularjs: kind.is_some(),
+        name,
+        controllers: Vec::new(),
+        range: Some(span.to_lsp_range()),
+        html: None,
+    })
+}
 
-    // 5c. alias.property 形式をチェック（controller as alias 構文）
-    let (resolved_controller, property_path) = if html_ref.property_path.contains('.') {
-        let parts: Vec<&str> = html_ref.property_path.splitn(2, '.').collect();
-        if parts.len() == 2 {
-            let alias = parts[0];
-            let prop = parts[1];
-            if let Some(controller) = index.resolve_controller_by_alias(uri, line, alias) {
-                (Some(controller), prop.to_string())
-            } else {
-                (None, html_ref.property_path.clone())
-            }
-        } else {
-            (None, html_ref.property_path.clone())
-        }
-    } else {
-        (None, html_ref.property_path.clone())
+fn locate_html_symbol(
+    index: &Index,
+    uri: &Url,
+    line: u32,
+    col: u32,
+    source: Option<&str>,
+) -> Option<LocatedSymbol> {
+    let resolution = index.resolve_html_position(uri, Position::new(line, col), source)?;
+    let symbol_span = || {
+        index
+            .definitions
+            .find_symbol_span_at_position(uri, line, col)
+            .map(|(_, span)| span)
     };
-
-    // コントローラーを解決
-    let is_controller_as = resolved_controller.is_some();
-    let controllers = if let Some(controller) = resolved_controller {
-        vec![controller]
-    } else {
-        index.resolve_controllers_for_html(uri, line)
+    let scope_ref_span = || {
+        index
+            .html
+            .find_html_scope_reference_at(uri, line, col)
+            .map(|r| r.span())
     };
 
-    // 各コントローラーを順番に試して、定義が見つかったものを返す
-    for controller_name in &controllers {
-        let symbol_name = format!("{}.$scope.{}", controller_name, property_path);
-        if index.definitions.has_definition(&symbol_name) {
-            return Some(HtmlSymbolAtPosition::ScopeSymbol {
-                symbol_name,
-                controller: controller_name.clone(),
-                is_controller_as,
-            });
+    let (kind, name, controllers, span) = match &resolution {
+        HtmlResolution::UiSref(r) => (
+            Some(SymbolKind::UiRouterState),
+            r.state_name.clone(),
+            Vec::new(),
+            Some(r.span()),
+        ),
+        HtmlResolution::Directive(r) => (
+            index
+                .definitions
+                .get_definitions(&r.directive_name)
+                .first()
+                .map(|d| d.kind),
+            r.directive_name.clone(),
+            Vec::new(),
+            Some(r.span()),
+        ),
+        HtmlResolution::Filter(name) => {
+            (Some(SymbolKind::Filter), name.clone(), Vec::new(), symbol_span())
         }
-    }
-
-    // controller as 構文の場合、ControllerName.method 形式も検索
-    if is_controller_as {
-        for controller_name in &controllers {
-            let symbol_name = format!("{}.{}", controller_name, property_path);
-            if index.definitions.has_definition(&symbol_name) {
-                return Some(HtmlSymbolAtPosition::ScopeSymbol {
-                    symbol_name,
-                    controller: controller_name.clone(),
-                    is_controller_as,
-                });
-            }
+        HtmlResolution::Controller(name) => {
+            (Some(SymbolKind::Controller), name.clone(), Vec::new(), symbol_span())
         }
-    }
+        HtmlResolution::LocalVarDef(v) => (None, v.name.clone(), Vec::new(), Some(v.name_span())),
+        HtmlResolution::LocalVarRef(v) => {
+            let span = index
+                .html
+                .find_html_local_variable_at(uri, line, col)
+                .map(|r| r.span());
+            (None, v.name.clone(), Vec::new(), span)
+        }
+        HtmlResolution::InheritedLocalVar(v) => (None, v.name.clone(), Vec::new(), scope_ref_span()),
+        HtmlResolution::FormBindingDef(f) => (
+            Some(SymbolKind::FormBinding),
+            f.name.clone(),
+            Vec::new(),
+            Some(f.name_span()),
+        ),
+        HtmlResolution::InheritedFormBinding(f) => (
+            Some(SymbolKind::FormBinding),
+            f.name.clone(),
+            Vec::new(),
+            scope_ref_span(),
+        ),
+        HtmlResolution::Scope {
+            controllers,
+            property_path,
+            is_alias,
+        } => {
+            let symbol = resolve_scope_symbol(index, controllers, property_path, *is_alias);
+            let kind = symbol
+                .as_ref()
+                .and_then(|s| index.definitions.get_definitions(s).first().map(|d| d.kind));
+            (
+                kind,
+                symbol.unwrap_or_else(|| property_path.clone()),
+                controllers.clone(),
+                scope_ref_span(),
+            )
+        }
+    };
 
-    // $rootScope からのグローバル参照を検索
-    if let Some(root_scope_symbol) = index
-        .definitions
-        .find_root_scope_symbol_name_by_property(&property_path)
-    {
-        return Some(HtmlSymbolAtPosition::RootScopeSymbol {
-            symbol_name: root_scope_symbol,
-        });
-    }
+    Some(LocatedSymbol {
+        kind,
+        name,
+        controllers,
+        range: span.map(|s| s.to_lsp_range()),
+        // HTML の AngularJS 式は tsserver では解決できないので常に自前で扱う
+        is_angularjs: true,
+        html: Some(resolution),
+    })
+}
 
-    None
+/// スコープ参照の定義シンボル名:
+/// `{ctrl}.$scope.{prop}` → (alias なら) `{ctrl}.{prop}` → `$rootScope` の `{prop}`
+fn resolve_scope_symbol(
+    index: &Index,
+    controllers: &[String],
+    property_path: &str,
+    is_alias: bool,
+) -> Option<String> {
+    let scope_symbol = controllers
+        .iter()
+        .map(|c| format!("{}.$scope.{}", c, property_path))
+        .find(|s| index.definitions.has_definition(s));
+    let alias_symbol = || {
+        controllers
+            .iter()
+            .map(|c| format!("{}.{}", c, property_path))
+            .find(|s| is_alias && index.definitions.has_definition(s))
+    };
+    scope_symbol
+        .or_else(alias_symbol)
+        .or_else(|| index.definitions.find_root_scope_symbol_name_by_property(property_path))
 }
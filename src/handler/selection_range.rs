@@ -0,0 +1,291 @@
+//! Selection range (スマート選択) handler.
+//!
+//! tree-sitter の AST ノード階層をそのまま選択段階にする。JS では
+//! `$scope.user.address.city` の `user` から `$scope.user` → `$scope.user.address` → …
+//! と member_expression の入れ子に沿って広がる。
+//!
+//! HTML の AngularJS 式は tree-sitter-html では属性値 / テキストの 1 ノードなので、
+//! 式の中は文字列として区切る: 識別子 → プロパティパス (`.` 区切り) →
+//! パイプ区間 (`|` 区切り) → 式全体 → `{{ }}` を含む補間 → 属性値 → 属性 → 要素
+
+use std::sync::Arc;
+
+use tower_lsp::lsp_types::{Position, Range, SelectionRange, Url};
+use tree_sitter::{Node, Tree};
+
+use crate::analyzer::html::parser::HtmlParser;
+use crate::analyzer::js::JsParser;
+use crate::index::Index;
+use crate::util::{is_html_file, is_js_file, offset_to_position, position_to_offset};
+
+pub struct SelectionRangeHandler {
+    index: Arc<Index>,
+}
+
+impl SelectionRangeHandler {
+    pub fn new(index: Arc<Index>) -> Self {
+        Self { index }
+    }
+
+    /// `textDocument/selectionRange` を処理する
+    ///
+    /// 位置ごとに 1 つの `SelectionRange` (内側から外側への入れ子) を返す。
+    /// 広げる段階がない位置は、その位置の空範囲だけを返す。
+    /// `cached_tree` は `source` からパース済みの Tree (キャッシュヒット時のみ)
+    pub fn selection_ranges(
+        &self,
+        uri: &Url,
+        source: &str,
+        positions: &[Position],
+        cached_tree: Option<Tree>,
+    ) -> Option<Vec<SelectionRange>> {
+        let html = is_html_file(uri);
+        let tree = if html {
+            cached_tree.or_else(|| HtmlParser::new().parse(source))?
+        } else if is_js_file(uri) {
+            cached_tree.or_else(|| JsParser::for_uri(uri).parse(source))?
+        } else {
+            return None;
+        };
+
+        let ranges = positions
+            .iter()
+            .map(|position| {
+                let offset = position_to_offset(source, *position);
+                let stages = if html {
+                    self.html_stages(&tree, source, offset)
+                } else {
+                    node_stages(&tree, offset)
+                };
+                nest(source, *position, stages)
+            })
+            .collect();
+        Some(ranges)
+    }
+
+    /// HTML の選択段階 (バイト範囲、内側から順)
+    fn html_stages(&self, tree: &Tree, source: &str, offset: usize) -> Vec<(usize, usize)> {
+        let Some(node) = tree.root_node().descendant_for_byte_range(offset, offset) else {
+            return Vec::new();
+        };
+
+        let mut stages = Vec::new();
+        match node.kind() {
+            "attribute_value" => {
+                stages.extend(expression_stages(source, node.start_byte(), node.end_byte(), offset));
+            }
+            "text" => {
+                let (start_symbol, end_symbol) = self.index.interpolate.resolved();
+                if let Some((open, close)) =
+                    find_interpolation(source, node, offset, &start_symbol, &end_symbol)
+                {
+                    let inner = (open + start_symbol.len(), close);
+                    stages.extend(expression_stages(source, inner.0, inner.1, offset));
+                    stages.push((open, close + end_symbol.len()));
+                }
+            }
+            _ => {}
+        }
+        stages.extend(node_stages(tree, offset));
+        stages
+    }
+}
+
+/// `offset` を含む最小ノードから根までの範囲
+fn node_stages(tree: &Tree, offset: usize) -> Vec<(usize, usize)> {
+    let mut stages = Vec::new();
+    let mut node = tree.root_node().descendant_for_byte_range(offset, offset);
+    while let Some(current) = node {
+        stages.push((current.start_byte(), current.end_byte()));
+        node = current.parent();
+    }
+    stages
+}
+
+/// AngularJS 式 `source[start..end]` 内の選択段階
+///
+/// 識別子 → プロパティパス → パイプ区間 → 式全体 (前後の空白は除く)
+fn expression_stages(source: &str, start: usize, end: usize, offset: usize) -> Vec<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$';
+    let is_path = |b: u8| is_ident(b) || b == b'.';
+    let expand = |pred: &dyn Fn(u8) -> bool| {
+        let mut from = offset;
+        while from > start && pred(bytes[from - 1]) {
+            from -= 1;
+        }
+        let mut to = offset;
+        while to < end && pred(bytes[to]) {
+            to += 1;
+        }
+        (from, to)
+    };
+
+    let mut stages = vec![expand(&is_ident), expand(&is_path)];
+
+    // パイプ区間 (`||` は論理演算子なので区切りにしない)
+    let mut segment_start = start;
+    let mut segment_end = end;
+    let mut i = start;
+    while i < end {
+        let is_pipe = bytes[i] == b'|'
+            && bytes.get(i + 1) != Some(&b'|')
+            && (i == start || bytes[i - 1] != b'|');
+        if is_pipe {
+            if i < offset {
+                segment_start = i + 1;
+            } else {
+                segment_end = i;
+                break;
+            }
+        }
+        i += 1;
+    }
+    stages.push(trim(source, segment_start, segment_end));
+    stages.push(trim(source, start, end));
+    stages
+}
+
+/// 範囲の前後の空白を除く
+fn trim(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let text = &source[start..end];
+    let leading = text.len() - text.trim_start().len();
+    let trailing = text.len() - text.trim_end().len();
+    if leading == text.len() {
+        return (start, end);
+    }
+    (start + leading, end - trailing)
+}
+
+/// テキストノード内で `offset` を囲む補間 `{{ ... }}` の開始・終了記号の位置
+fn find_interpolation(
+    source: &str,
+    node: Node,
+    offset: usize,
+    start_symbol: &str,
+    end_symbol: &str,
+) -> Option<(usize, usize)> {
+    let node_start = node.start_byte();
+    let before = &source[node_start..offset];
+    let open = node_start + before.rfind(start_symbol)?;
+    // 直前の補間が既に閉じていれば対象外
+    if before[open - node_start..].contains(end_symbol) {
+        return None;
+    }
+    let after = &source[open + start_symbol.len()..node.end_byte()];
+    let close = open + start_symbol.len() + after.find(end_symbol)?;
+    (offset <= close).then_some((open, close))
+}
+
+/// 段階を内側から順に入れ子の `SelectionRange` にする
+///
+/// 直前の段階を含まない範囲や同じ範囲は飛ばす
+fn nest(source: &str, position: Position, stages: Vec<(usize, usize)>) -> SelectionRange {
+    let mut accepted: Vec<(usize, usize)> = Vec::new();
+    for stage in stages {
+        let grows = accepted
+            .last()
+            .is_none_or(|last| stage != *last && stage.0 <= last.0 && stage.1 >= last.1);
+        if grows {
+            accepted.push(stage);
+        }
+    }
+
+    let mut result: Option<SelectionRange> = None;
+    for (start, end) in accepted.into_iter().rev() {
+        result = Some(SelectionRange {
+            range: Range::new(offset_to_position(source, start), offset_to_position(source, end)),
+            parent: result.map(Box::new),
+        });
+    }
+    result.unwrap_or(SelectionRange {
+        range: Range::new(position, position),
+        parent: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str, range: &SelectionRange) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut current = Some(range);
+        while let Some(r) = current {
+            let start = position_to_offset(source, r.range.start);
+            let end = position_to_offset(source, r.range.end);
+            result.push(source[start..end].to_string());
+            current = r.parent.as_deref();
+        }
+        result
+    }
+
+    fn select(uri: &str, source: &str, needle: &str) -> Vec<String> {
+        let offset = source.find(needle).unwrap();
+        let handler = SelectionRangeHandler::new(Arc::new(Index::new()));
+        let uri = Url::parse(uri).unwrap();
+        let ranges = handler
+            .selection_ranges(&uri, source, &[offset_to_position(source, offset)], None)
+            .unwrap();
+        texts(source, &ranges[0])
+    }
+
+    #[test]
+    fn js_selection_follows_member_expressions() {
+        let source = "function f($scope) {\n  $scope.user.address.city = 1;\n}\n";
+        let stages = select("file:///a.js", source, "user");
+        assert_eq!(
+            stages[..4],
+            ["user", "$scope.user", "$scope.user.address", "$scope.user.address.city"]
+        );
+        assert_eq!(stages.last().unwrap(), source);
+    }
+
+    #[test]
+    fn html_interpolation_expands_through_expression_boundaries() {
+        let source = "<div>\n  <p>{{ vm.user.name | uppercase }}</p>\n</div>\n";
+        let stages = select("file:///a.html", source, "user");
+        assert_eq!(
+            stages[..6],
+            [
+                "user",
+                "vm.user.name",
+                "vm.user.name | uppercase",
+                "{{ vm.user.name | uppercase }}",
+                "<p>{{ vm.user.name | uppercase }}</p>",
+                "<div>\n  <p>{{ vm.user.name | uppercase }}</p>\n</div>",
+            ]
+        );
+    }
+
+    #[test]
+    fn html_attribute_expression_expands_to_attribute_and_element() {
+        let source = r#"<button ng-click="vm.save(item) || vm.cancel()">Save</button>"#;
+        let stages = select("file:///a.html", source, "save");
+        assert_eq!(
+            stages[..5],
+            [
+                "save",
+                "vm.save",
+                "vm.save(item) || vm.cancel()",
+                "\"vm.save(item) || vm.cancel()\"",
+                "ng-click=\"vm.save(item) || vm.cancel()\"",
+            ]
+        );
+        assert_eq!(stages.last().unwrap(), source);
+    }
+
+    #[test]
+    fn positions_use_utf16_columns() {
+        let source = "<p title=\"日本語\">{{ vm.user.name }}</p>\n";
+        let offset = source.find("user").unwrap();
+        let position = offset_to_position(source, offset);
+        assert_eq!(position, Position::new(0, 21));
+
+        let handler = SelectionRangeHandler::new(Arc::new(Index::new()));
+        let uri = Url::parse("file:///a.html").unwrap();
+        let ranges = handler.selection_ranges(&uri, source, &[position], None).unwrap();
+        assert_eq!(ranges[0].range, Range::new(Position::new(0, 21), Position::new(0, 25)));
+        assert_eq!(texts(source, &ranges[0])[..2], ["user", "vm.user.name"]);
+    }
+}
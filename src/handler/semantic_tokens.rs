@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
 
+use crate::analyzer::html::filters::is_builtin_filter;
 use crate::index::Index;
 use crate::model::{HtmlScopeReference, SymbolKind};
 use crate::util::is_html_file;
@@ -11,11 +12,17 @@ const TOKEN_TYPE_PROPERTY: u32 = 0;
 const TOKEN_TYPE_METHOD: u32 = 1;
 const TOKEN_TYPE_VARIABLE: u32 = 2;
 const TOKEN_TYPE_MACRO: u32 = 3; // directive
+const TOKEN_TYPE_FUNCTION: u32 = 4; // filter
 
 /// Token modifiers (bit flags)
 const TOKEN_MOD_READONLY: u32 = 1 << 0;
 const TOKEN_MOD_STATIC: u32 = 1 << 1;
 const TOKEN_MOD_DECLARATION: u32 = 1 << 2;
+const TOKEN_MOD_DEFAULT_LIBRARY: u32 = 1 << 3;
+const TOKEN_MOD_LOCAL: u32 = 1 << 4;
+
+/// Custom modifier for template-local variables (ng-repeat, ng-init, ...)
+const LOCAL_MODIFIER: &str = "local";
 
 /// Raw token with absolute positions (before encoding)
 struct RawSemanticToken {
@@ -43,11 +50,14 @@ impl SemanticTokensHandler {
                 SemanticTokenType::METHOD,   // 1: scope method
                 SemanticTokenType::VARIABLE, // 2: local variable, form binding
                 SemanticTokenType::MACRO,    // 3: directive
+                SemanticTokenType::FUNCTION, // 4: filter
             ],
             token_modifiers: vec![
-                SemanticTokenModifier::READONLY,    // 0: for form bindings
+                SemanticTokenModifier::READONLY,    // 0: for form bindings, inherited local variables
                 SemanticTokenModifier::STATIC,      // 1: for $rootScope
                 SemanticTokenModifier::DECLARATION, // 2: for definitions
+                SemanticTokenModifier::DEFAULT_LIBRARY, // 3: for built-in filters
+                SemanticTokenModifier::new(LOCAL_MODIFIER), // 4: for local variables
             ],
         }
     }
@@ -87,6 +97,9 @@ impl SemanticTokensHandler {
         // 5. Directive references
         self.collect_directive_reference_tokens(uri, &mut raw_tokens);
 
+        // 6. Filter references (`{{ x | myFilter }}`)
+        self.collect_filter_reference_tokens(uri, &mut raw_tokens);
+
         raw_tokens
     }
 
@@ -95,6 +108,11 @@ impl SemanticTokensHandler {
         let refs = self.index.html.get_html_scope_references(uri);
 
         for scope_ref in refs {
+            if let Some(token) = self.inherited_local_variable_token(uri, &scope_ref) {
+                tokens.push(token);
+                continue;
+            }
+
             let (token_type, token_modifiers) =
                 self.determine_scope_token_type(uri, &scope_ref);
 
@@ -108,6 +126,33 @@ impl SemanticTokensHandler {
         }
     }
 
+    /// Token for a scope reference whose base name is a local variable inherited
+    /// from a parent template (ng-include), e.g. `item` in `{{ item.name }}`
+    ///
+    /// Only the base name is highlighted as a variable.
+    fn inherited_local_variable_token(
+        &self,
+        uri: &Url,
+        scope_ref: &HtmlScopeReference,
+    ) -> Option<RawSemanticToken> {
+        let base_name = scope_ref
+            .property_path
+            .split('.')
+            .next()
+            .unwrap_or(&scope_ref.property_path);
+        self.index
+            .find_local_variable_definition(uri, base_name, scope_ref.start_line)
+            .filter(|var| &var.uri != uri)?;
+
+        Some(RawSemanticToken {
+            line: scope_ref.start_line,
+            start_col: scope_ref.start_col,
+            length: base_name.encode_utf16().count() as u32,
+            token_type: TOKEN_TYPE_VARIABLE,
+            token_modifiers: TOKEN_MOD_LOCAL | TOKEN_MOD_READONLY,
+        })
+    }
+
     /// Determine token type for a scope reference by resolving its definition
     fn determine_scope_token_type(
         &self,
@@ -201,7 +246,7 @@ impl SemanticTokensHandler {
                 start_col: var.name_start_col,
                 length: (var.name_end_col - var.name_start_col),
                 token_type: TOKEN_TYPE_VARIABLE,
-                token_modifiers: TOKEN_MOD_DECLARATION,
+                token_modifiers: TOKEN_MOD_LOCAL | TOKEN_MOD_DECLARATION,
             });
         }
     }
@@ -218,12 +263,23 @@ impl SemanticTokensHandler {
             .get_all_local_variable_references_for_uri(uri);
 
         for var_ref in refs {
+            // Variables inherited from a parent template are defined in another file
+            let inherited = self
+                .index
+                .find_local_variable_definition(uri, &var_ref.variable_name, var_ref.start_line)
+                .is_some_and(|var| &var.uri != uri);
+            let token_modifiers = if inherited {
+                TOKEN_MOD_LOCAL | TOKEN_MOD_READONLY
+            } else {
+                TOKEN_MOD_LOCAL
+            };
+
             tokens.push(RawSemanticToken {
                 line: var_ref.start_line,
                 start_col: var_ref.start_col,
                 length: (var_ref.end_col - var_ref.start_col),
                 token_type: TOKEN_TYPE_VARIABLE,
-                token_modifiers: 0,
+                token_modifiers,
             });
         }
     }
@@ -262,6 +318,36 @@ impl SemanticTokensHandler {
         }
     }
 
+    /// Collect tokens from filter references
+    ///
+    /// User-defined filters (`.filter('name', ...)`) have no modifier;
+    /// built-in filters (`date`, `currency`, ...) get `defaultLibrary`.
+    fn collect_filter_reference_tokens(&self, uri: &Url, tokens: &mut Vec<RawSemanticToken>) {
+        let refs = self.index.definitions.get_references_for_uri(uri);
+
+        for filter_ref in refs.iter().filter(|r| r.span.start_line == r.span.end_line) {
+            let token_modifiers = if self
+                .index
+                .definitions
+                .has_definition_of_kind(&filter_ref.name, SymbolKind::Filter)
+            {
+                0
+            } else if is_builtin_filter(&filter_ref.name) {
+                TOKEN_MOD_DEFAULT_LIBRARY
+            } else {
+                continue;
+            };
+
+            tokens.push(RawSemanticToken {
+                line: filter_ref.span.start_line,
+                start_col: filter_ref.span.start_col,
+                length: (filter_ref.span.end_col - filter_ref.span.start_col),
+                token_type: TOKEN_TYPE_FUNCTION,
+                token_modifiers,
+            });
+        }
+    }
+
     /// Encode raw tokens as delta-encoded SemanticTokens
     ///
     /// LSP semantic tokens spec の制約:
@@ -270,7 +356,7 @@ impl SemanticTokensHandler {
     /// - length > 0 でなければならない
     ///
     /// この LSP は複数のソース (scope refs / local vars / form bindings /
-    /// directive refs) からトークンを集めるため、同一スパンの重複や
+    /// directive refs / filter refs) からトークンを集めるため、同一スパンの重複や
     /// 隣接トークンのオーバーラップが発生し得る。クライアント (VS Code 等) は
     /// オーバーラップ等の不正データを検出すると **ファイル全体の semantic
     /// tokens を破棄して何も表示しない** ため、ここで防御的に弾く。
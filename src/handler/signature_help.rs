@@ -1,10 +1,14 @@
 use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
+use tree_sitter::Tree;
 
+use crate::analyzer::html::filters::builtin_filter_doc;
+use crate::analyzer::html::HtmlAngularJsAnalyzer;
+use super::resolve::locate_symbol_at;
 use crate::index::Index;
-use crate::model::Symbol;
-use crate::util::is_html_file;
+use crate::model::{Symbol, SymbolKind};
+use crate::util::{byte_col_to_utf16_col, is_html_file, utf16_col_to_byte_col};
 
 pub struct SignatureHelpHandler {
     index: Arc<Index>,
@@ -16,19 +20,48 @@ impl SignatureHelpHandler {
     }
 
     /// signatureHelpリクエストを処理する
+    ///
+    /// `col` は LSP の UTF-16 の列。`cached_tree` は HTML の `source` からパース済みの
+    /// Tree (キャッシュヒット時のみ、<script> の抽出に使う)
     pub fn signature_help(
         &self,
         uri: &Url,
         line: u32,
         col: u32,
         source: &str,
+        cached_tree: Option<&Tree>,
     ) -> Option<SignatureHelp> {
+        // HTML 内の <script> は script ローカルの位置に変換して JS として扱う
+        // (インデックスの位置は解析時に line_offset を足した HTML 上の行のまま)
+        let embedded = is_html_file(uri)
+            .then(|| HtmlAngularJsAnalyzer::script_at(source, cached_tree, line, col))
+            .flatten();
+        let col = utf16_col_to_byte_col(source, line, col);
+        let in_template = is_html_file(uri) && embedded.is_none();
+
+        // 0. HTML 式中のパイプフィルター引数 (`value | myFilter:<ここ>`)
+        if let Some(filter_context) =
+            find_filter_context(source, line, col).filter(|_| in_template)
+        {
+            return self.build_filter_signature_help(&filter_context);
+        }
+
         // 1. カーソル位置から関数呼び出しコンテキストを取得
-        let call_context = self.find_call_context(source, line, col)?;
+        let call_context = match &embedded {
+            Some((script, local_line, local_col)) => {
+                self.find_call_context(&script.source, *local_line, *local_col)?
+            }
+            None => self.find_call_context(source, line, col)?,
+        };
 
-        // 2. シンボル定義を取得
-        let symbol =
-            self.find_symbol_definition(uri, line, &call_context.function_name)?;
+        // 2. シンボル定義を取得 (<script> 内はインデックスの列と対応しないので名前だけで引く)
+        let located = embedded
+            .is_none()
+            .then(|| self.locate_callee(uri, line, source, call_context.name_col))
+            .flatten();
+        let symbol = located.or_else(|| {
+            self.find_symbol_definition(uri, line, &call_context.function_name, in_template)
+        })?;
 
         // 3. SignatureHelpを構築
         self.build_signature_help(
@@ -75,6 +108,7 @@ impl SignatureHelpHandler {
         // 開き括弧の前の識別子を取得（関数名またはメソッド名）
         let before_paren = &before_cursor[..paren_pos];
         let function_name = self.extract_function_name(before_paren)?;
+        let name_col = before_paren.trim_end().char_indices().last()?.0 as u32;
 
         // アクティブなパラメータを計算（カンマの数をカウント）
         let inside_parens = &before_cursor[paren_pos + 1..];
@@ -83,6 +117,7 @@ impl SignatureHelpHandler {
         Some(CallContext {
             function_name,
             active_parameter,
+            name_col,
         })
     }
 
@@ -144,12 +179,35 @@ impl SignatureHelpHandler {
         count
     }
 
-    /// シンボル定義を検索
+    /// 呼び出される関数名の位置にあるシンボルの定義
+    ///
+    /// hover / definition と同じく [`locate_symbol_at`] で引くので、DI 名や
+    /// `controller as` のエイリアスも同じ規則で解決される。`name_col` はバイト列
+    fn locate_callee(&self, uri: &Url, line: u32, source: &str, name_col: u32) -> Option<Symbol> {
+        // HTML のインデックスの列は UTF-16、JS はバイト列
+        let col = if is_html_file(uri) {
+            byte_col_to_utf16_col(source, line, name_col)
+        } else {
+            name_col
+        };
+        let located = locate_symbol_at(&self.index, uri, line, col, Some(source))?;
+        self.index
+            .definitions
+            .get_definitions(&located.name)
+            .into_iter()
+            .next()
+    }
+
+    /// 関数名からシンボル定義を検索する
+    ///
+    /// 参照がまだインデックスにない位置 (入力直後で解析が追いついていない呼び出しや
+    /// <script> 内) 用。`in_template` は HTML テンプレートの式 (<script> の外) か
     fn find_symbol_definition(
         &self,
         uri: &Url,
         line: u32,
         function_name: &str,
+        in_template: bool,
     ) -> Option<Symbol> {
         // 1. まず完全な名前で検索（ServiceName.methodName）
         let definitions = self.index.definitions.get_definitions(function_name);
@@ -172,19 +230,24 @@ impl SignatureHelpHandler {
             }
         }
 
-        // 3. HTMLファイルの場合
-        if is_html_file(uri) {
+        // 3. HTMLテンプレートの式の場合
+        if in_template {
             // 3a. alias.method 形式をコントローラー名に解決 (例: ctrl.doSomething)
             let alias_mappings =
                 self.index.controllers.get_html_alias_mappings(uri, line);
             for (alias, controller_name) in &alias_mappings {
                 if function_name.starts_with(&format!("{}.", alias)) {
                     let method_part = &function_name[alias.len() + 1..];
-                    let full_name =
-                        format!("{}.$scope.{}", controller_name, method_part);
-                    let defs = self.index.definitions.get_definitions(&full_name);
-                    if let Some(def) = defs.first() {
-                        return Some(def.clone());
+                    // controller as 構文の this.method は ControllerName.method
+                    let candidates = [
+                        format!("{}.$scope.{}", controller_name, method_part),
+                        format!("{}.{}", controller_name, method_part),
+                    ];
+                    for full_name in &candidates {
+                        let defs = self.index.definitions.get_definitions(full_name);
+                        if let Some(def) = defs.first() {
+                            return Some(def.clone());
+                        }
                     }
                 }
             }
@@ -208,6 +271,95 @@ impl SignatureHelpHandler {
         None
     }
 
+    /// フィルター引数の SignatureHelp を構築
+    ///
+    /// フィルター関数の第1引数は入力値なので、2番目以降の引数をパラメータとして示す。
+    /// ユーザー定義フィルターは関数の引数名と JSDoc `@param`、組み込みフィルターは
+    /// 静的定義から引数情報を取る
+    fn build_filter_signature_help(&self, context: &FilterContext) -> Option<SignatureHelp> {
+        let name = &context.filter_name;
+        let user_defined = self
+            .index
+            .definitions
+            .get_definitions(name)
+            .into_iter()
+            .find(|d| d.kind == SymbolKind::Filter);
+
+        let (params, documentation): (Vec<(String, Option<String>)>, Option<String>) =
+            if let Some(def) = user_defined {
+                let docs = def.docs.as_deref().unwrap_or("");
+                let param_docs = parse_jsdoc_params(docs);
+                let names: Vec<String> = match def.parameters {
+                    Some(params) => params.into_iter().skip(1).collect(),
+                    None => param_docs.iter().skip(1).map(|(n, _)| n.clone()).collect(),
+                };
+                let params = names
+                    .into_iter()
+                    .map(|n| {
+                        let doc = param_docs
+                            .iter()
+                            .find(|(pn, _)| *pn == n)
+                            .map(|(_, d)| d.clone())
+                            .filter(|d| !d.is_empty());
+                        (n, doc)
+                    })
+                    .collect();
+                let description = docs
+                    .lines()
+                    .filter(|l| !l.starts_with('@'))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (params, Some(description).filter(|d| !d.is_empty()))
+            } else {
+                let doc = builtin_filter_doc(name)?;
+                let params = doc
+                    .params
+                    .iter()
+                    .map(|(n, d)| (n.to_string(), Some(d.to_string())))
+                    .collect();
+                (params, Some(doc.description.to_string()))
+            };
+
+        if params.is_empty() {
+            return None;
+        }
+
+        let label = format!(
+            "input | {}:{}",
+            name,
+            params.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(":")
+        );
+        let parameter_info = params
+            .into_iter()
+            .map(|(n, doc)| ParameterInformation {
+                label: ParameterLabel::Simple(n),
+                documentation: doc.map(|d| {
+                    Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: d,
+                    })
+                }),
+            })
+            .collect();
+        let documentation = documentation.map(|d| {
+            Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: d,
+            })
+        });
+
+        Some(SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label,
+                documentation,
+                parameters: Some(parameter_info),
+                active_parameter: Some(context.active_parameter),
+            }],
+            active_signature: Some(0),
+            active_parameter: Some(context.active_parameter),
+        })
+    }
+
     /// SignatureHelpレスポンスを構築
     fn build_signature_help(
         &self,
@@ -263,4 +415,106 @@ struct CallContext {
     function_name: String,
     /// アクティブなパラメータのインデックス（0始まり）
     active_parameter: u32,
+    /// 関数名の最後の文字の列 (行内のバイト列)
+    name_col: u32,
+}
+
+/// パイプフィルターの引数位置のコンテキスト情報
+struct FilterContext {
+    /// フィルター名
+    filter_name: String,
+    /// アクティブな引数のインデックス（入力値を除いて0始まり）
+    active_parameter: u32,
+}
+
+/// カーソル位置がパイプフィルターの引数内 (`value | name:a:<ここ>`) なら
+/// フィルター名と引数インデックスを返す
+///
+/// 式の開始は現在行の直前の `{{` (閉じられていないもの) か、属性値の開始 `"` とする
+fn find_filter_context(source: &str, line: u32, col: u32) -> Option<FilterContext> {
+    let current_line = source.lines().nth(line as usize)?;
+    let col = (col as usize).min(current_line.len());
+    let before_cursor = current_line.get(..col)?;
+
+    let expr_start = match before_cursor.rfind("{{") {
+        Some(pos) if !before_cursor[pos..].contains("}}") => pos + 2,
+        _ => before_cursor.rfind('"').map(|pos| pos + 1)?,
+    };
+    let expr = &before_cursor[expr_start..];
+
+    // トップレベルの最後の `|` (`||` を除く) とそれ以降の `:` の数を求める
+    let mut depth: i32 = 0;
+    let mut quote: Option<char> = None;
+    let mut pipe_pos: Option<usize> = None;
+    let mut colons: u32 = 0;
+    let mut prev: Option<char> = None;
+    let chars: Vec<(usize, char)> = expr.char_indices().collect();
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if let Some(q) = quote {
+            if c == q && prev != Some('\\') {
+                quote = None;
+            }
+            prev = Some(c);
+            continue;
+        }
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '|' if depth == 0 => {
+                let next = chars.get(i + 1).map(|&(_, n)| n);
+                if prev != Some('|') && next != Some('|') {
+                    pipe_pos = Some(pos);
+                    colons = 0;
+                }
+            }
+            ':' if depth == 0 && pipe_pos.is_some() => colons += 1,
+            _ => {}
+        }
+        prev = Some(c);
+    }
+
+    if colons == 0 {
+        return None;
+    }
+    let after_pipe = expr[pipe_pos? + 1..].trim_start();
+    let filter_name: String = after_pipe
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    if filter_name.is_empty() {
+        return None;
+    }
+
+    Some(FilterContext {
+        filter_name,
+        active_parameter: colons - 1,
+    })
+}
+
+/// JSDoc の `@param {type} name 説明` 行から (引数名, 説明) を取り出す
+///
+/// `[name]` / `[name=default]` 形式の省略可能引数にも対応する
+fn parse_jsdoc_params(docs: &str) -> Vec<(String, String)> {
+    docs.lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("@param")?.trim_start();
+            let rest = match rest.strip_prefix('{') {
+                Some(typed) => typed.split_once('}')?.1.trim_start(),
+                None => rest,
+            };
+            let (name, description) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let name = name
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split('=')
+                .next()
+                .unwrap_or("");
+            if name.is_empty() {
+                return None;
+            }
+            let description = description.trim_start().trim_start_matches("- ");
+            Some((name.to_string(), description.trim().to_string()))
+        })
+        .collect()
 }
@@ -2,29 +2,54 @@ use std::sync::Arc;
 
 use tower_lsp::lsp_types::*;
 
+use crate::config::WorkspaceSymbolConfig;
 use crate::index::Index;
 use crate::model::SymbolKind as AngularSymbolKind;
 
+/// `kind:query` 構文で指定できるシンボル種別
+const KIND_FILTERS: &[AngularSymbolKind] = &[
+    AngularSymbolKind::Module,
+    AngularSymbolKind::Controller,
+    AngularSymbolKind::Service,
+    AngularSymbolKind::Factory,
+    AngularSymbolKind::Directive,
+    AngularSymbolKind::Component,
+    AngularSymbolKind::Provider,
+    AngularSymbolKind::Filter,
+    AngularSymbolKind::Constant,
+    AngularSymbolKind::Value,
+];
+
 pub struct WorkspaceSymbolHandler {
     index: Arc<Index>,
+    config: WorkspaceSymbolConfig,
 }
 
 impl WorkspaceSymbolHandler {
-    pub fn new(index: Arc<Index>) -> Self {
-        Self { index }
+    pub fn new(index: Arc<Index>, config: WorkspaceSymbolConfig) -> Self {
+        Self { index, config }
     }
 
+    /// ワークスペースシンボル検索
+    ///
+    /// - `controller:User` のように種別名の接頭辞で種別を絞り込む
+    /// - クエリ本体は部分一致を最優先し、それ以外は部分文字列シーケンスの
+    ///   ファジーマッチでスコア順に並べる
+    /// - `workspace_symbol.max_results` が指定されていれば上位 N 件に制限する
     pub fn handle(&self, query: &str) -> Vec<SymbolInformation> {
-        let all_definitions = self.index.definitions.get_all_definitions();
-        let query_lower = query.to_lowercase();
+        let (kind_filter, query) = parse_query(query);
 
-        all_definitions
+        let mut scored: Vec<(i64, SymbolInformation)> = self
+            .index
+            .definitions
+            .get_all_definitions()
             .into_iter()
             .filter(|sym| self.is_top_level_symbol(sym.kind))
-            .filter(|sym| query.is_empty() || sym.name.to_lowercase().contains(&query_lower))
-            .map(|sym| {
+            .filter(|sym| kind_filter.is_none_or(|kind| sym.kind == kind))
+            .filter_map(|sym| {
+                let score = fuzzy_score(&sym.name, query)?;
                 #[allow(deprecated)]
-                SymbolInformation {
+                let info = SymbolInformation {
                     name: sym.name.clone(),
                     kind: sym.kind.to_lsp_symbol_kind(),
                     tags: None,
@@ -34,24 +59,127 @@ impl WorkspaceSymbolHandler {
                         range: sym.definition_span.to_lsp_range(),
                     },
                     container_name: Some(sym.kind.as_str().to_string()),
-                }
+                };
+                Some((score, info))
             })
-            .collect()
+            .collect();
+
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score).then_with(|| a.name.cmp(&b.name))
+        });
+        if let Some(max_results) = self.config.max_results {
+            scored.truncate(max_results);
+        }
+
+        scored.into_iter().map(|(_, info)| info).collect()
     }
 
     fn is_top_level_symbol(&self, kind: AngularSymbolKind) -> bool {
-        matches!(
-            kind,
-            AngularSymbolKind::Module
-                | AngularSymbolKind::Controller
-                | AngularSymbolKind::Service
-                | AngularSymbolKind::Factory
-                | AngularSymbolKind::Directive
-                | AngularSymbolKind::Component
-                | AngularSymbolKind::Provider
-                | AngularSymbolKind::Filter
-                | AngularSymbolKind::Constant
-                | AngularSymbolKind::Value
-        )
+        KIND_FILTERS.contains(&kind)
+    }
+}
+
+/// `kind:query` の種別接頭辞を取り出す
+///
+/// 接頭辞が既知の種別名でなければクエリ全体を検索語として扱う
+fn parse_query(query: &str) -> (Option<AngularSymbolKind>, &str) {
+    let Some((prefix, rest)) = query.split_once(':') else {
+        return (None, query);
+    };
+    let prefix = prefix.trim().to_lowercase();
+    match KIND_FILTERS.iter().find(|kind| kind.as_str() == prefix) {
+        Some(kind) => (Some(*kind), rest.trim()),
+        None => (None, query),
+    }
+}
+
+/// シンボル名とクエリのマッチスコア (大きいほど上位、マッチしなければ `None`)
+///
+/// 大文字小文字を区別しない部分一致 (完全一致 > 前方一致 > 部分一致) を最優先し、
+/// 部分一致しない場合はクエリの文字が順に現れるか (`uctrl` → `UserController`) で判定する。
+/// ファジーマッチでは連続一致と単語境界 (camelCase の大文字、`_` / `-` / `.` の直後) を加点する
+fn fuzzy_score(name: &str, query: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let name_lower = name.to_lowercase();
+    let query_lower = query.to_lowercase();
+    let length_penalty = name.chars().count() as i64;
+    if name_lower == query_lower {
+        return Some(3000);
+    }
+    if name_lower.starts_with(&query_lower) {
+        return Some(2000 - length_penalty);
+    }
+    if name_lower.contains(&query_lower) {
+        return Some(1000 - length_penalty);
+    }
+
+    let name_chars: Vec<char> = name.chars().collect();
+    let mut query_chars = query_lower.chars().peekable();
+    let mut score = 0;
+    let mut prev_match: Option<usize> = None;
+    for (i, c) in name_chars.iter().enumerate() {
+        let Some(&q) = query_chars.peek() else {
+            break;
+        };
+        if !c.to_lowercase().eq(q.to_lowercase()) {
+            continue;
+        }
+        query_chars.next();
+        score += 10;
+        match prev_match {
+            Some(prev) if prev + 1 == i => score += 15,
+            Some(prev) => score -= (i - prev - 1) as i64,
+            None => score -= i as i64,
+        }
+        let at_boundary = i == 0
+            || matches!(name_chars[i - 1], '_' | '-' | '.' | '$')
+            || (c.is_uppercase() && name_chars[i - 1].is_lowercase());
+        if at_boundary {
+            score += 20;
+        }
+        prev_match = Some(i);
+    }
+
+    query_chars.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_query_extracts_kind_prefix() {
+        assert_eq!(
+            parse_query("controller:User"),
+            (Some(AngularSymbolKind::Controller), "User")
+        );
+        assert_eq!(parse_query("Service: "), (Some(AngularSymbolKind::Service), ""));
+        // 種別名でない接頭辞はクエリの一部
+        assert_eq!(parse_query("foo:bar"), (None, "foo:bar"));
+    }
+
+    #[test]
+    fn substring_match_ranks_above_fuzzy_match() {
+        let exact = fuzzy_score("user", "user").unwrap();
+        let prefix = fuzzy_score("UserService", "user").unwrap();
+        let substring = fuzzy_score("CurrentUser", "user").unwrap();
+        let fuzzy = fuzzy_score("UnusedServiceRegistry", "user").unwrap();
+        assert!(exact > prefix && prefix > substring && substring > fuzzy);
+    }
+
+    #[test]
+    fn fuzzy_match_requires_ordered_subsequence() {
+        assert!(fuzzy_score("UserController", "uctrl").is_some());
+        assert!(fuzzy_score("UserController", "ctrlu").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_word_boundaries() {
+        let boundary = fuzzy_score("UserListController", "ulc").unwrap();
+        let scattered = fuzzy_score("ultimateRecord", "ulc").unwrap();
+        assert!(boundary > scattered);
     }
 }
@@ -4,12 +4,25 @@ use tower_lsp::lsp_types::Url;
 use crate::model::ComponentTemplateUrl;
 use crate::util::normalize_template_path;
 
+/// binding に対応する HTML 属性名 (camelCase)
+///
+/// `onSelect: '&onSelected'` のように別名指定があれば別名、なければ binding 名
+pub fn binding_attribute_name<'a>(binding_name: &'a str, binding_type: &'a str) -> &'a str {
+    let alias = binding_type
+        .trim()
+        .trim_start_matches(['<', '=', '@', '&', '*', '?'])
+        .trim();
+    if alias.is_empty() { binding_name } else { alias }
+}
+
 /// コンポーネントテンプレートの管理ストア
 pub struct ComponentStore {
     /// コンポーネントのtemplateUrl情報（URI -> Vec<ComponentTemplateUrl>）
     component_template_urls: DashMap<Url, Vec<ComponentTemplateUrl>>,
     /// コンポーネントテンプレートバインディング逆引き（normalized_path -> ComponentTemplateUrl）
     component_template_bindings: DashMap<String, ComponentTemplateUrl>,
+    /// コンポーネントのbindings（component名 -> Vec<(定義元URI, binding名, バインディング種別)>）
+    component_bindings: DashMap<String, Vec<(Url, String, String)>>,
 }
 
 impl ComponentStore {
@@ -17,6 +30,7 @@ impl ComponentStore {
         Self {
             component_template_urls: DashMap::new(),
             component_template_bindings: DashMap::new(),
+            component_bindings: DashMap::new(),
         }
     }
 
@@ -66,6 +80,50 @@ impl ComponentStore {
         None
     }
 
+    /// コンポーネントの binding を登録
+    ///
+    /// binding_type は `bindings` の値そのまま（`'<'`, `'=?'`, `'&onSelect'` など）。
+    /// 値が文字列でない場合は空文字列
+    pub fn add_component_binding(
+        &self,
+        uri: &Url,
+        component_name: &str,
+        binding_name: &str,
+        binding_type: &str,
+    ) {
+        self.component_bindings
+            .entry(component_name.to_string())
+            .or_default()
+            .push((uri.clone(), binding_name.to_string(), binding_type.to_string()));
+    }
+
+    /// コンポーネント名（camelCase）から (binding名, バインディング種別) の一覧を取得
+    pub fn get_bindings(&self, component_name: &str) -> Vec<(String, String)> {
+        self.component_bindings
+            .get(component_name)
+            .map(|v| {
+                v.iter()
+                    .map(|(_, name, binding_type)| (name.clone(), binding_type.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// HTML 属性名 (camelCase) に対応する binding の種別を取得
+    ///
+    /// `onSelect: '&onSelected'` のように別名指定があれば属性名は別名側で照合する
+    pub fn get_binding_type_for_attribute(
+        &self,
+        component_name: &str,
+        attribute_name: &str,
+    ) -> Option<String> {
+        let bindings = self.component_bindings.get(component_name)?;
+        bindings
+            .iter()
+            .find(|(_, name, binding_type)| binding_attribute_name(name, binding_type) == attribute_name)
+            .map(|(_, _, binding_type)| binding_type.clone())
+    }
+
     pub fn clear_document(&self, uri: &Url) {
         if let Some(templates) = self.component_template_urls.get(uri) {
             for template in templates.iter() {
@@ -75,11 +133,17 @@ impl ComponentStore {
             }
         }
         self.component_template_urls.remove(uri);
+
+        for mut entry in self.component_bindings.iter_mut() {
+            entry.value_mut().retain(|(u, _, _)| u != uri);
+        }
+        self.component_bindings.retain(|_, v| !v.is_empty());
     }
 
     pub fn clear_all(&self) {
         self.component_template_urls.clear();
         self.component_template_bindings.clear();
+        self.component_bindings.clear();
     }
 }
 
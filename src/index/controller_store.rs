@@ -1,7 +1,9 @@
+use std::cmp::Reverse;
+
 use dashmap::DashMap;
 use tower_lsp::lsp_types::Url;
 
-use crate::model::{ControllerScope, HtmlControllerScope};
+use crate::model::{ControllerScope, HtmlChildScope, HtmlControllerScope, ServiceAlias};
 
 /// JS/HTMLコントローラースコープの管理ストア
 pub struct ControllerStore {
@@ -9,6 +11,12 @@ pub struct ControllerStore {
     controller_scopes: DashMap<Url, Vec<ControllerScope>>,
     /// HTML内のng-controllerスコープ（URI -> Vec<HtmlControllerScope>）
     html_controller_scopes: DashMap<Url, Vec<HtmlControllerScope>>,
+    /// HTML内の子スコープを作る要素（URI -> Vec<HtmlChildScope>）
+    html_child_scopes: DashMap<Url, Vec<HtmlChildScope>>,
+    /// `$injector.get('X')` を受けた変数（URI -> Vec<ServiceAlias>）
+    service_aliases: DashMap<Url, Vec<ServiceAlias>>,
+    /// コントローラー名 -> JS スコープを登録したファイル（`find_controller_scope` 用）
+    controller_scope_uris: DashMap<String, Vec<Url>>,
 }
 
 impl ControllerStore {
@@ -16,6 +24,9 @@ impl ControllerStore {
         Self {
             controller_scopes: DashMap::new(),
             html_controller_scopes: DashMap::new(),
+            html_child_scopes: DashMap::new(),
+            service_aliases: DashMap::new(),
+            controller_scope_uris: DashMap::new(),
         }
     }
 
@@ -23,6 +34,11 @@ impl ControllerStore {
 
     pub fn add_controller_scope(&self, scope: ControllerScope) {
         let uri = scope.uri.clone();
+        let mut uris = self.controller_scope_uris.entry(scope.name.clone()).or_default();
+        if !uris.contains(&uri) {
+            uris.push(uri.clone());
+        }
+        drop(uris);
         self.controller_scopes.entry(uri).or_default().push(scope);
     }
 
@@ -38,6 +54,34 @@ impl ControllerStore {
         None
     }
 
+    /// コントローラー名から JS 側のスコープ (`$scope` を注入した関数本体) を取得
+    pub fn find_controller_scope(&self, controller_name: &str) -> Option<ControllerScope> {
+        let uri = self.controller_scope_uris.get(controller_name)?.first()?.clone();
+        self.controller_scopes
+            .get(&uri)?
+            .iter()
+            .find(|scope| scope.name == controller_name)
+            .cloned()
+    }
+
+    /// 指定URIのコントローラースコープを取得
+    pub fn get_controller_scopes_for_uri(&self, uri: &Url) -> Vec<ControllerScope> {
+        self.controller_scopes
+            .get(uri)
+            .map(|scopes| scopes.clone())
+            .unwrap_or_default()
+    }
+
+    /// コントローラー名からDIされているサービスを取得
+    pub fn get_injected_services_for_controller(&self, controller_name: &str) -> Vec<String> {
+        self.controller_scopes
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .find(|scope| scope.name == controller_name)
+            .map(|scope| scope.injected_services)
+            .unwrap_or_default()
+    }
+
     /// 指定位置のコントローラーでDIされているサービスを取得
     pub fn get_injected_services_at(&self, uri: &Url, line: u32) -> Vec<String> {
         if let Some(scopes) = self.controller_scopes.get(uri) {
@@ -50,6 +94,30 @@ impl ControllerStore {
         Vec::new()
     }
 
+    pub fn add_service_alias(&self, alias: ServiceAlias) {
+        let uri = alias.uri.clone();
+        let mut aliases = self.service_aliases.entry(uri).or_default();
+        // ワークスペーススキャンでは同じファイルを 2 回解析するため重複を避ける
+        let exists = aliases.iter().any(|a| {
+            a.name == alias.name && a.start_line == alias.start_line && a.end_line == alias.end_line
+        });
+        if !exists {
+            aliases.push(alias);
+        }
+    }
+
+    /// 指定位置で変数 `name` が `$injector.get` で受けたサービス名を取得
+    ///
+    /// 範囲が重なる場合は最も内側 (行数の少ない) 範囲の別名を優先する。
+    pub fn resolve_service_alias(&self, uri: &Url, name: &str, line: u32) -> Option<String> {
+        let aliases = self.service_aliases.get(uri)?;
+        aliases
+            .iter()
+            .filter(|a| a.name == name && line >= a.start_line && line <= a.end_line)
+            .min_by_key(|a| a.end_line - a.start_line)
+            .map(|a| a.service.clone())
+    }
+
     /// 全コントローラースコープを取得（キャッシュ用）
     pub fn get_all_controller_scopes(&self) -> Vec<ControllerScope> {
         self.controller_scopes
@@ -129,30 +197,36 @@ impl ControllerStore {
         line: u32,
         alias: &str,
     ) -> Option<String> {
-        if let Some(scopes) = self.html_controller_scopes.get(uri) {
-            let mut best_match: Option<&HtmlControllerScope> = None;
-            for scope in scopes.iter() {
-                if line >= scope.start_line && line <= scope.end_line {
-                    if let Some(ref scope_alias) = scope.alias {
-                        if scope_alias == alias {
-                            if let Some(current_best) = best_match {
-                                if scope.start_line >= current_best.start_line
-                                    && scope.end_line <= current_best.end_line
-                                {
-                                    best_match = Some(scope);
-                                }
-                            } else {
-                                best_match = Some(scope);
-                            }
-                        }
-                    }
-                }
+        self.find_html_alias_scope_at(uri, line, alias)
+            .map(|scope| scope.controller_name)
+    }
+
+    /// 指定位置で `alias` を定義している最も内側の ng-controller スコープを取得
+    ///
+    /// 同名 alias がネストしている場合は内側のスコープがシャドーイングする
+    pub fn find_html_alias_scope_at(
+        &self,
+        uri: &Url,
+        line: u32,
+        alias: &str,
+    ) -> Option<HtmlControllerScope> {
+        let scopes = self.html_controller_scopes.get(uri)?;
+        let mut best_match: Option<&HtmlControllerScope> = None;
+        for scope in scopes.iter() {
+            if line < scope.start_line || line > scope.end_line {
+                continue;
+            }
+            if scope.alias.as_deref() != Some(alias) {
+                continue;
             }
-            if let Some(matched) = best_match {
-                return Some(matched.controller_name.clone());
+            match best_match {
+                Some(current_best)
+                    if !(scope.start_line >= current_best.start_line
+                        && scope.end_line <= current_best.end_line) => {}
+                _ => best_match = Some(scope),
             }
         }
-        None
+        best_match.cloned()
     }
 
     /// 指定位置のHTML内の全aliasマッピングを取得
@@ -174,17 +248,19 @@ impl ControllerStore {
         mappings
     }
 
-    /// コントローラー名からバインドされているHTMLテンプレートのパスを取得
-    pub fn get_html_templates_for_controller(&self, controller_name: &str) -> Vec<String> {
+    /// コントローラー名から ng-controller でバインドしている HTML ファイルと
+    /// 最初の ng-controller の行を取得
+    pub fn get_html_templates_for_controller(&self, controller_name: &str) -> Vec<(Url, u32)> {
         let mut templates = Vec::new();
         for entry in self.html_controller_scopes.iter() {
-            for scope in entry.value() {
-                if scope.controller_name == controller_name {
-                    let path = entry.key().path().to_string();
-                    if !templates.contains(&path) {
-                        templates.push(path);
-                    }
-                }
+            let first_line = entry
+                .value()
+                .iter()
+                .filter(|scope| scope.controller_name == controller_name)
+                .map(|scope| scope.start_line)
+                .min();
+            if let Some(line) = first_line {
+                templates.push((entry.key().clone(), line));
             }
         }
         templates
@@ -198,6 +274,69 @@ impl ControllerStore {
             .collect()
     }
 
+    // ========== HTML Child Scopes ==========
+
+    pub fn add_html_child_scope(&self, scope: HtmlChildScope) {
+        let uri = scope.uri.clone();
+        self.html_child_scopes.entry(uri).or_default().push(scope);
+    }
+
+    /// 指定位置を囲む HTML 内のスコープを外側から内側への順で返す
+    ///
+    /// ng-controller は `Some(コントローラー名)`、子スコープを作る要素は `None`。
+    /// ng-controller の範囲は行単位なので、開始行の先頭から始まるものとして並べる
+    pub fn get_html_scopes_at(&self, uri: &Url, line: u32, col: u32) -> Vec<Option<String>> {
+        let mut scopes = Vec::new();
+
+        if let Some(controllers) = self.html_controller_scopes.get(uri) {
+            scopes.extend(
+                controllers
+                    .iter()
+                    .filter(|scope| line >= scope.start_line && line <= scope.end_line)
+                    .map(|scope| {
+                        (
+                            (scope.start_line, 0),
+                            Reverse((scope.end_line, u32::MAX)),
+                            Some(scope.controller_name.clone()),
+                        )
+                    }),
+            );
+        }
+        if let Some(children) = self.html_child_scopes.get(uri) {
+            scopes.extend(
+                children
+                    .iter()
+                    .filter(|scope| scope.contains(line, col))
+                    .map(|scope| {
+                        (
+                            (scope.span.start_line, scope.span.start_col),
+                            Reverse((scope.span.end_line, scope.span.end_col)),
+                            None,
+                        )
+                    }),
+            );
+        }
+
+        scopes.sort_by_key(|(start, end, _)| (*start, *end));
+        scopes.into_iter().map(|(_, _, controller)| controller).collect()
+    }
+
+    /// 指定URIのHTML子スコープを取得
+    pub fn get_html_child_scopes_for_uri(&self, uri: &Url) -> Vec<HtmlChildScope> {
+        self.html_child_scopes
+            .get(uri)
+            .map(|scopes| scopes.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 全HTML子スコープを取得（キャッシュ用）
+    pub fn get_all_html_child_scopes_for_cache(&self) -> Vec<HtmlChildScope> {
+        self.html_child_scopes
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .collect()
+    }
+
     /// URIのHTML Controller Scopeキーをイテレート（テンプレートURI解決用）
     pub fn html_controller_scope_uris(&self) -> Vec<Url> {
         self.html_controller_scopes
@@ -207,13 +346,25 @@ impl ControllerStore {
     }
 
     pub fn clear_document(&self, uri: &Url) {
-        self.controller_scopes.remove(uri);
+        if let Some((_, scopes)) = self.controller_scopes.remove(uri) {
+            for scope in scopes {
+                if let Some(mut uris) = self.controller_scope_uris.get_mut(&scope.name) {
+                    uris.retain(|registered| registered != uri);
+                }
+                self.controller_scope_uris.remove_if(&scope.name, |_, uris| uris.is_empty());
+            }
+        }
         self.html_controller_scopes.remove(uri);
+        self.html_child_scopes.remove(uri);
+        self.service_aliases.remove(uri);
     }
 
     pub fn clear_all(&self) {
         self.controller_scopes.clear();
+        self.controller_scope_uris.clear();
         self.html_controller_scopes.clear();
+        self.html_child_scopes.clear();
+        self.service_aliases.clear();
     }
 }
 
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use dashmap::DashMap;
 use tower_lsp::lsp_types::Url;
 
-use crate::model::{Symbol, SymbolKind, SymbolReference};
+use crate::model::{Span, Symbol, SymbolKind, SymbolReference};
 
 /// シンボル定義・参照の管理ストア
 pub struct DefinitionStore {
@@ -13,6 +13,25 @@ pub struct DefinitionStore {
     /// `get_definitions_for_uri` 等の URI 逆引きを O(該当ドキュメントのシンボル数)
     /// で行うためのインデックス。重複登録を避けるため HashSet で保持する。
     document_symbols: DashMap<Url, HashSet<String>>,
+    /// 参照を記録しない URI（値は `without_references` のネスト数）
+    suppressed_references: DashMap<Url, usize>,
+}
+
+/// `without_references` の抑止を解除するガード（パニック時も解除する）
+struct SuppressionGuard<'a> {
+    store: &'a DefinitionStore,
+    uri: &'a Url,
+}
+
+impl Drop for SuppressionGuard<'_> {
+    fn drop(&mut self) {
+        self.store
+            .suppressed_references
+            .remove_if_mut(self.uri, |_, depth| {
+                *depth -= 1;
+                *depth == 0
+            });
+    }
 }
 
 impl DefinitionStore {
@@ -21,6 +40,7 @@ impl DefinitionStore {
             definitions: DashMap::new(),
             references: DashMap::new(),
             document_symbols: DashMap::new(),
+            suppressed_references: DashMap::new(),
         }
     }
 
@@ -41,6 +61,9 @@ impl DefinitionStore {
     }
 
     pub fn add_reference(&self, reference: SymbolReference) {
+        if self.suppressed_references.contains_key(&reference.uri) {
+            return;
+        }
         let name = reference.name.clone();
         let uri = reference.uri.clone();
 
@@ -56,6 +79,16 @@ impl DefinitionStore {
         }
     }
 
+    /// `f` を実行する間、`uri` の参照を記録しない（定義は記録する）
+    ///
+    /// `definitions_only` のファイルの解析に使う。参照を集めてから消すと
+    /// 消すまでの間に参照が見えてしまうため、記録の時点で捨てる
+    pub fn without_references<R>(&self, uri: &Url, f: impl FnOnce() -> R) -> R {
+        *self.suppressed_references.entry(uri.clone()).or_insert(0) += 1;
+        let _guard = SuppressionGuard { store: self, uri };
+        f()
+    }
+
     pub fn get_definitions(&self, name: &str) -> Vec<Symbol> {
         self.definitions
             .get(name)
@@ -116,6 +149,20 @@ impl DefinitionStore {
             .collect()
     }
 
+    /// 名前が `prefix` で始まる定義をすべて取得
+    pub fn get_definitions_with_prefix(&self, prefix: &str) -> Vec<Symbol> {
+        self.get_definitions_matching(|name| name.starts_with(prefix))
+    }
+
+    /// 名前が条件を満たす定義だけを取得 (一致しない定義は clone しない)
+    pub fn get_definitions_matching<F: Fn(&str) -> bool>(&self, matches: F) -> Vec<Symbol> {
+        self.definitions
+            .iter()
+            .filter(|entry| matches(entry.key()))
+            .flat_map(|entry| entry.value().clone())
+            .collect()
+    }
+
     /// 指定した名前がService/Factoryかどうかを判定
     pub fn is_service_or_factory(&self, name: &str) -> bool {
         if let Some(symbols) = self.definitions.get(name) {
@@ -194,19 +241,30 @@ impl DefinitionStore {
     /// 全シンボル/参照を走査して O(N) だったが、本実装は O(該当 URI のシンボル
     /// 数 + 参照数) に絞られる。
     pub fn find_symbol_at_position(&self, uri: &Url, line: u32, col: u32) -> Option<String> {
+        self.find_symbol_span_at_position(uri, line, col)
+            .map(|(name, _)| name)
+    }
+
+    /// 位置からシンボルを検索し、シンボル名とカーソル下の定義名 / 参照の範囲を返す
+    pub fn find_symbol_span_at_position(
+        &self,
+        uri: &Url,
+        line: u32,
+        col: u32,
+    ) -> Option<(String, Span)> {
         let Some(names) = self.document_symbols.get(uri) else {
             return None;
         };
 
-        let mut best_match: Option<(String, u32)> = None;
+        let mut best_match: Option<(String, Span, u32)> = None;
 
         for name in names.value() {
             if let Some(entry) = self.definitions.get(name) {
                 for symbol in entry.value() {
                     if &symbol.uri == uri && symbol.name_span.contains(line, col) {
                         let size = symbol.name_span.range_size();
-                        if best_match.is_none() || size < best_match.as_ref().unwrap().1 {
-                            best_match = Some((symbol.name.clone(), size));
+                        if best_match.is_none() || size < best_match.as_ref().unwrap().2 {
+                            best_match = Some((symbol.name.clone(), symbol.name_span, size));
                         }
                     }
                 }
@@ -216,15 +274,15 @@ impl DefinitionStore {
                 for reference in entry.value() {
                     if &reference.uri == uri && reference.span.contains(line, col) {
                         let size = reference.span.range_size();
-                        if best_match.is_none() || size < best_match.as_ref().unwrap().1 {
-                            best_match = Some((reference.name.clone(), size));
+                        if best_match.is_none() || size < best_match.as_ref().unwrap().2 {
+                            best_match = Some((reference.name.clone(), reference.span, size));
                         }
                     }
                 }
             }
         }
 
-        best_match.map(|(name, _)| name)
+        best_match.map(|(name, span, _)| (name, span))
     }
 
     /// 指定URIのドキュメント内定義を取得
@@ -232,6 +290,23 @@ impl DefinitionStore {
         self.collect_definitions_for_uri(uri, |_| true)
     }
 
+    /// component 定義 (config オブジェクト) 内で登録された binding シンボル
+    ///
+    /// binding は `<コントローラー名>.<binding名>` で登録されるため、名前ではなく定義位置で引く
+    pub fn get_component_bindings(&self, component_name: &str) -> Vec<Symbol> {
+        self.get_definitions(component_name)
+            .into_iter()
+            .filter(|component| component.kind == SymbolKind::Component)
+            .flat_map(|component| {
+                let span = component.definition_span;
+                self.collect_definitions_for_uri(&component.uri, |s| {
+                    s.kind == SymbolKind::ComponentBinding
+                        && span.contains(s.name_span.start_line, s.name_span.start_col)
+                })
+            })
+            .collect()
+    }
+
     /// `document_symbols` の URI 逆引きを使い、`definitions` を全件走査せずに
     /// 当該ドキュメントの定義だけ取り出す。`predicate` が true の Symbol のみ
     /// 返す。
@@ -255,6 +330,20 @@ impl DefinitionStore {
         result
     }
 
+    /// 指定URIのドキュメント内参照を取得
+    pub fn get_references_for_uri(&self, uri: &Url) -> Vec<SymbolReference> {
+        let Some(names) = self.document_symbols.get(uri) else {
+            return Vec::new();
+        };
+        let mut result = Vec::new();
+        for name in names.value() {
+            if let Some(entry) = self.references.get(name) {
+                result.extend(entry.value().iter().filter(|r| &r.uri == uri).cloned());
+            }
+        }
+        result
+    }
+
     /// 指定 URI から参照されているシンボル名集合を取得
     /// (HTML 埋め込みスクリプトが書き込んだ参照を URI 単位で逆引きするのに使う)
     ///
@@ -321,6 +410,7 @@ impl DefinitionStore {
         }
     }
 
+
     pub fn clear_all(&self) {
         self.definitions.clear();
         self.references.clear();
@@ -413,6 +503,27 @@ mod tests {
         assert_eq!(store.get_references("Ctrl.$scope.shared").len(), 1);
     }
 
+    #[test]
+    fn without_references_records_definitions_only() {
+        let store = DefinitionStore::new();
+        let uri_a = Url::parse("file:///a.js").unwrap();
+        let uri_b = Url::parse("file:///b.js").unwrap();
+
+        store.without_references(&uri_a, || {
+            store.add_definition(make_definition("Ctrl.$scope.items", &uri_a));
+            store.add_reference(make_reference("Ctrl.$scope.items", &uri_a));
+            store.add_reference(make_reference("Ctrl.$scope.items", &uri_b));
+        });
+        store.add_reference(make_reference("UserService", &uri_a));
+
+        assert!(store.has_definition("Ctrl.$scope.items"));
+        let refs = store.get_references("Ctrl.$scope.items");
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].uri, uri_b);
+        // 抑止はクロージャの間だけ
+        assert_eq!(store.get_references("UserService").len(), 1);
+    }
+
     #[test]
     fn clear_document_removes_empty_definition_keys() {
         let store = DefinitionStore::new();
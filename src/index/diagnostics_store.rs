@@ -1,30 +1,67 @@
 use dashmap::DashMap;
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{Diagnostic, Url};
 
-use crate::model::DiArityIssue;
+use crate::model::{
+    AnalysisFailure, DiArityIssue, DiOrderIssue, ExpressionAssignment, HtmlSyntaxError, InjectedService, InjectionUsage,
+    TemplatePathUsage,
+};
 
 /// アナライザーが収集した診断補助情報を保持するストア。
 ///
-/// 解析処理の中でしか取れない情報 (AST 由来の DI arity 不一致など) を
+/// 解析処理の中でしか取れない情報 (AST 由来の DI arity 不一致や注入サービスの位置など) を
 /// `DiagnosticsHandler` から読み出せるよう中継する。
 pub struct DiagnosticsStore {
     /// URI ごとの DI arity 不一致リスト
     di_arity_issues: DashMap<Url, Vec<DiArityIssue>>,
+    /// URI ごとの DI 位置ずれリスト
+    di_order_issues: DashMap<Url, Vec<DiOrderIssue>>,
+    /// URI ごとの注入サービス (DI 配列 / `$inject` の文字列要素)
+    injected_services: DashMap<Url, Vec<InjectedService>>,
+    /// URI ごとの DI 引数と関数本体での識別子の使用状況
+    injection_usages: DashMap<Url, Vec<InjectionUsage>>,
+    /// URI ごとのテンプレートパス (`templateUrl` / `ng-include`)
+    template_paths: DashMap<Url, Vec<TemplatePathUsage>>,
+    /// URI ごとの HTML 構文エラー
+    html_syntax_errors: DashMap<Url, Vec<HtmlSyntaxError>>,
+    /// URI ごとの式中の代入 (値を読むだけの属性のもの)
+    expression_assignments: DashMap<Url, Vec<ExpressionAssignment>>,
+    /// typescript-language-server から届いた URI ごとの診断 (未フィルタ)
+    ///
+    /// tsserver は自分のタイミングで publishDiagnostics を送ってくるため、
+    /// AngularJS 側の再解析 (`clear_document`) では消さない。
+    ts_diagnostics: DashMap<Url, Vec<Diagnostic>>,
+    /// 直近の解析に失敗した URI と理由
+    ///
+    /// 失敗時は解析自体が走らず `clear_document` も呼ばれないので、
+    /// パースの成否は差分解析 (`analyze_document_incremental`) が、
+    /// panic は `Backend` が明示的に記録する。
+    analysis_failures: DashMap<Url, AnalysisFailure>,
 }
 
 impl DiagnosticsStore {
     pub fn new() -> Self {
         Self {
             di_arity_issues: DashMap::new(),
+            di_order_issues: DashMap::new(),
+            injected_services: DashMap::new(),
+            injection_usages: DashMap::new(),
+            template_paths: DashMap::new(),
+            html_syntax_errors: DashMap::new(),
+            expression_assignments: DashMap::new(),
+            ts_diagnostics: DashMap::new(),
+            analysis_failures: DashMap::new(),
         }
     }
 
     /// DI arity 不一致を登録する
+    ///
+    /// ワークスペーススキャンでは同じ JS を追記モードで 2 回解析するため、
+    /// 同一位置の登録は重複させない (以下の add_* も同様)。
     pub fn add_di_arity_issue(&self, issue: DiArityIssue) {
-        self.di_arity_issues
-            .entry(issue.uri.clone())
-            .or_default()
-            .push(issue);
+        let mut entry = self.di_arity_issues.entry(issue.uri.clone()).or_default();
+        if !entry.iter().any(|i| i.span == issue.span) {
+            entry.push(issue);
+        }
     }
 
     /// 指定 URI の DI arity 不一致リストを取得する
@@ -35,14 +72,157 @@ impl DiagnosticsStore {
             .unwrap_or_default()
     }
 
+    /// DI 位置ずれを登録する
+    pub fn add_di_order_issue(&self, issue: DiOrderIssue) {
+        let mut entry = self.di_order_issues.entry(issue.uri.clone()).or_default();
+        if !entry.iter().any(|i| i.span == issue.span) {
+            entry.push(issue);
+        }
+    }
+
+    /// 指定 URI の DI 位置ずれリストを取得する
+    pub fn get_di_order_issues(&self, uri: &Url) -> Vec<DiOrderIssue> {
+        self.di_order_issues
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 注入サービスを登録する
+    pub fn add_injected_service(&self, service: InjectedService) {
+        let mut entry = self.injected_services.entry(service.uri.clone()).or_default();
+        if !entry.iter().any(|s| s.span == service.span) {
+            entry.push(service);
+        }
+    }
+
+    /// 指定 URI の注入サービスリストを取得する
+    pub fn get_injected_services(&self, uri: &Url) -> Vec<InjectedService> {
+        self.injected_services
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// DI 引数の使用状況を登録する
+    pub fn add_injection_usage(&self, usage: InjectionUsage) {
+        let mut entry = self.injection_usages.entry(usage.uri.clone()).or_default();
+        if !entry.iter().any(|u| u.span == usage.span) {
+            entry.push(usage);
+        }
+    }
+
+    /// 指定 URI の DI 引数の使用状況リストを取得する
+    pub fn get_injection_usages(&self, uri: &Url) -> Vec<InjectionUsage> {
+        self.injection_usages
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// テンプレートパスを登録する
+    pub fn add_template_path(&self, usage: TemplatePathUsage) {
+        let mut entry = self.template_paths.entry(usage.uri.clone()).or_default();
+        if !entry.iter().any(|u| u.span == usage.span) {
+            entry.push(usage);
+        }
+    }
+
+    /// 指定 URI のテンプレートパスリストを取得する
+    pub fn get_template_paths(&self, uri: &Url) -> Vec<TemplatePathUsage> {
+        self.template_paths
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// HTML 構文エラーを登録する
+    pub fn add_html_syntax_error(&self, error: HtmlSyntaxError) {
+        let mut entry = self.html_syntax_errors.entry(error.uri.clone()).or_default();
+        if !entry.iter().any(|e| e.span == error.span) {
+            entry.push(error);
+        }
+    }
+
+    /// 指定 URI の HTML 構文エラーリストを取得する
+    pub fn get_html_syntax_errors(&self, uri: &Url) -> Vec<HtmlSyntaxError> {
+        self.html_syntax_errors
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 式中の代入を登録する
+    pub fn add_expression_assignment(&self, assignment: ExpressionAssignment) {
+        let mut entry = self.expression_assignments.entry(assignment.uri.clone()).or_default();
+        if !entry.iter().any(|a| a.span == assignment.span) {
+            entry.push(assignment);
+        }
+    }
+
+    /// 指定 URI の式中の代入リストを取得する
+    pub fn get_expression_assignments(&self, uri: &Url) -> Vec<ExpressionAssignment> {
+        self.expression_assignments
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// tsserver の診断を置き換える
+    pub fn set_ts_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>) {
+        self.ts_diagnostics.insert(uri, diagnostics);
+    }
+
+    /// 指定 URI の tsserver の診断を取得する
+    pub fn get_ts_diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        self.ts_diagnostics
+            .get(uri)
+            .map(|v| v.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 指定 URI の tsserver の診断を破棄する (ファイルを閉じたとき)
+    pub fn remove_ts_diagnostics(&self, uri: &Url) {
+        self.ts_diagnostics.remove(uri);
+    }
+
+    /// 解析の失敗を記録する
+    pub fn set_analysis_failure(&self, uri: Url, failure: AnalysisFailure) {
+        self.analysis_failures.insert(uri, failure);
+    }
+
+    /// 指定 URI の直近の解析の失敗 (成功していれば `None`)
+    pub fn get_analysis_failure(&self, uri: &Url) -> Option<AnalysisFailure> {
+        self.analysis_failures.get(uri).map(|v| *v.value())
+    }
+
+    /// 解析に成功したので失敗の記録を消す
+    pub fn clear_analysis_failure(&self, uri: &Url) {
+        self.analysis_failures.remove(uri);
+    }
+
     /// 指定 URI の情報をクリアする
     pub fn clear_document(&self, uri: &Url) {
         self.di_arity_issues.remove(uri);
+        self.di_order_issues.remove(uri);
+        self.injected_services.remove(uri);
+        self.injection_usages.remove(uri);
+        self.template_paths.remove(uri);
+        self.html_syntax_errors.remove(uri);
+        self.expression_assignments.remove(uri);
     }
 
     /// 全データをクリアする
     pub fn clear_all(&self) {
         self.di_arity_issues.clear();
+        self.di_order_issues.clear();
+        self.injected_services.clear();
+        self.injection_usages.clear();
+        self.template_paths.clear();
+        self.html_syntax_errors.clear();
+        self.expression_assignments.clear();
+        self.ts_diagnostics.clear();
+        self.analysis_failures.clear();
     }
 }
 
@@ -11,8 +11,9 @@
 use tower_lsp::lsp_types::{Position, Url};
 
 use super::Index;
+use crate::analyzer::html::filters::is_builtin_filter;
 use crate::model::{
-    HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable, HtmlUiSrefReference,
+    HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable, HtmlUiSrefReference, SymbolKind,
 };
 
 /// HTML 上のカーソル位置に対応する解決結果。
@@ -20,12 +21,14 @@ use crate::model::{
 /// 解決優先順位 (高い順):
 /// 1. `UiSref`               — `ui-sref="state"` の state 名
 /// 2. `Directive`            — カスタムディレクティブ / コンポーネント参照
-/// 3. `LocalVarDef`          — `ng-init` / `ng-repeat` ローカル変数の定義位置
-/// 4. `LocalVarRef`          — ローカル変数の参照 (定義済み)
-/// 5. `FormBindingDef`       — `<form name="x">` の name 属性値
-/// 6. `InheritedFormBinding` — 親テンプレートで定義されたフォーム名への参照
-/// 7. `InheritedLocalVar`    — 親テンプレートで定義されたローカル変数への参照
-/// 8. `Scope`                — `$scope` プロパティ参照 (controller as alias 含む)
+/// 3. `Filter`               — 式中のパイプ後のフィルター名 (`x | myFilter`)
+/// 4. `Controller`           — `ng-controller="UserController"` のコントローラー名
+/// 5. `LocalVarDef`          — `ng-init` / `ng-repeat` ローカル変数の定義位置
+/// 6. `LocalVarRef`          — ローカル変数の参照 (定義済み)
+/// 7. `FormBindingDef`       — `<form name="x">` の name 属性値
+/// 8. `InheritedFormBinding` — 親テンプレートで定義されたフォーム名への参照
+/// 9. `InheritedLocalVar`    — 親テンプレートで定義されたローカル変数への参照
+/// 10. `Scope`               — `$scope` プロパティ参照 (controller as alias 含む)
 ///
 /// `Scope` の後段処理 (`$scope.X` → `controller.X` (alias) → `$rootScope.X` →
 /// ng-model 暗黙的 → 失敗) は各ハンドラ側で実装する。これらの fallback chain は
@@ -35,6 +38,10 @@ use crate::model::{
 pub enum HtmlResolution {
     UiSref(HtmlUiSrefReference),
     Directive(HtmlDirectiveReference),
+    /// フィルター名 (`.filter('name', ...)` の定義名、または組み込みフィルター名)
+    Filter(String),
+    /// `ng-controller` 属性値のコントローラー名 (定義済みのもの)
+    Controller(String),
     LocalVarDef(HtmlLocalVariable),
     /// 参照位置から解決した「変数定義」を保持する。後段処理は `LocalVarDef` と同じ
     /// (=定義位置にジャンプ / hover で var の情報を表示) のため、共通の payload。
@@ -83,6 +90,27 @@ impl Index {
             return Some(HtmlResolution::Directive(directive_ref));
         }
 
+        // 0c. フィルター参照 (`{{ x | myFilter }}`)
+        if let Some(name) = self
+            .definitions
+            .find_symbol_at_position(uri, position.line, position.character)
+            .filter(|name| {
+                self.definitions.has_definition_of_kind(name, SymbolKind::Filter)
+                    || is_builtin_filter(name)
+            })
+        {
+            return Some(HtmlResolution::Filter(name));
+        }
+
+        // 0d. ng-controller 属性値のコントローラー名
+        if let Some(name) = self
+            .definitions
+            .find_symbol_at_position(uri, position.line, position.character)
+            .filter(|name| self.definitions.has_definition_of_kind(name, SymbolKind::Controller))
+        {
+            return Some(HtmlResolution::Controller(name));
+        }
+
         // 1. ローカル変数の「定義位置」にカーソルがあるか
         if let Some(var_def) = self
             .html
@@ -142,6 +170,7 @@ impl Index {
             let (controllers, property_path, is_alias) = self.resolve_scope_target(
                 uri,
                 position.line,
+                position.character,
                 &html_ref.property_path,
             );
 
@@ -180,8 +209,14 @@ impl Index {
         &self,
         uri: &Url,
         line: u32,
+        col: u32,
         property_path: &str,
     ) -> (Vec<String>, String, bool) {
+        if let Some((controllers, prop)) =
+            self.resolve_parent_scope_path(uri, line, col, property_path)
+        {
+            return (controllers, prop, false);
+        }
         if let Some((alias, prop)) = property_path.split_once('.') {
             if let Some(controller) = self.resolve_controller_by_alias(uri, line, alias) {
                 return (vec![controller], prop.to_string(), true);
@@ -1,6 +1,8 @@
 use dashmap::DashMap;
 use tower_lsp::lsp_types::Url;
 
+use crate::analyzer::html::directives::normalize_directive_attr;
+use crate::config::ExpressionAttributeMode;
 use crate::model::{
     HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable, HtmlLocalVariableReference,
     HtmlNgModelTarget, HtmlScopeReference, HtmlUiSrefReference,
@@ -26,6 +28,9 @@ pub struct HtmlStore {
     /// HTML 内の ui-router `ui-sref="state"` 参照 (URI -> Vec<HtmlUiSrefReference>)
     /// state 名 → state 定義へのジャンプ・ホバー解決に使う
     ui_sref_references: DashMap<Url, Vec<HtmlUiSrefReference>>,
+    /// ajsconfig.json の `expression_attributes` (属性名 -> 解析方法)。
+    /// 解析結果ではなく設定値なので `clear_all` では消さない
+    expression_attributes: DashMap<String, ExpressionAttributeMode>,
 }
 
 impl HtmlStore {
@@ -38,9 +43,31 @@ impl HtmlStore {
             html_directive_references: DashMap::new(),
             ng_model_targets: DashMap::new(),
             ui_sref_references: DashMap::new(),
+            expression_attributes: DashMap::new(),
         }
     }
 
+    // ========== 式評価属性 (設定) ==========
+
+    /// ajsconfig.json の `expression_attributes` を設定する (既存の設定は置き換え)
+    pub fn set_expression_attributes(
+        &self,
+        attributes: impl IntoIterator<Item = (String, ExpressionAttributeMode)>,
+    ) {
+        self.expression_attributes.clear();
+        for (name, mode) in attributes {
+            self.expression_attributes
+                .insert(normalize_directive_attr(&name).into_owned(), mode);
+        }
+    }
+
+    /// ユーザー設定の式評価属性なら解析方法を返す (`data-` 接頭辞などの表記ゆれは無視)
+    pub fn expression_attribute_mode(&self, attr_name: &str) -> Option<ExpressionAttributeMode> {
+        self.expression_attributes
+            .get(normalize_directive_attr(attr_name).as_ref())
+            .map(|mode| *mode)
+    }
+
     // ========== スコープ参照 ==========
 
     pub fn add_html_scope_reference(&self, reference: HtmlScopeReference) {
@@ -6,7 +6,10 @@ pub mod export_store;
 pub mod html_resolve;
 pub mod html_store;
 pub mod interpolate_store;
+pub mod module_graph;
 mod query;
+pub mod status;
+pub mod symbol_dump;
 pub mod template_store;
 
 pub use html_resolve::HtmlResolution;
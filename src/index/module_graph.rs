@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use super::Index;
+use crate::model::SymbolKind;
+
+/// `angular.module('a', ['b', 'c'])` から集めたモジュール依存グラフ
+///
+/// `angularjs-lsp.showModuleGraph` コマンドの応答としてそのまま JSON 化する
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ModuleGraph {
+    /// モジュール名 → 依存モジュール名 (宣言順。同名モジュールの複数定義は統合)
+    pub modules: BTreeMap<String, Vec<String>>,
+    /// 循環依存の経路 (`["a", "b", "a"]` のように先頭のモジュールに戻る)
+    pub cycles: Vec<Vec<String>>,
+    /// ワークスペースに定義のないモジュール名 → それに依存しているモジュール
+    pub undefined: BTreeMap<String, Vec<String>>,
+}
+
+impl Index {
+    /// 全モジュール定義とその依存配列からモジュール依存グラフを組み立てる
+    pub fn module_graph(&self) -> ModuleGraph {
+        let mut modules: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for symbol in self.definitions.get_all_definitions() {
+            if symbol.kind != SymbolKind::Module {
+                continue;
+            }
+            let deps = modules.entry(symbol.name).or_default();
+            for dep in symbol.dependencies.unwrap_or_default() {
+                if !deps.contains(&dep) {
+                    deps.push(dep);
+                }
+            }
+        }
+
+        let mut undefined: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (module, deps) in &modules {
+            for dep in deps.iter().filter(|dep| !modules.contains_key(*dep)) {
+                undefined.entry(dep.clone()).or_default().push(module.clone());
+            }
+        }
+
+        let cycles = find_cycles(&modules);
+        ModuleGraph {
+            modules,
+            cycles,
+            undefined,
+        }
+    }
+}
+
+/// 深さ優先探索で後退辺を見つけ、その経路を循環依存として返す
+///
+/// 同じ循環を別の起点から見つけた場合 (`a → b → a` と `b → a → b`) は 1 つにまとめる
+fn find_cycles(modules: &BTreeMap<String, Vec<String>>) -> Vec<Vec<String>> {
+    let mut cycles = Vec::new();
+    let mut seen_cycles: HashSet<Vec<String>> = HashSet::new();
+    let mut finished: HashSet<&str> = HashSet::new();
+    let mut path: Vec<&str> = Vec::new();
+
+    for start in modules.keys() {
+        visit(
+            start,
+            modules,
+            &mut path,
+            &mut finished,
+            &mut seen_cycles,
+            &mut cycles,
+        );
+    }
+    cycles
+}
+
+fn visit<'a>(
+    module: &'a str,
+    modules: &'a BTreeMap<String, Vec<String>>,
+    path: &mut Vec<&'a str>,
+    finished: &mut HashSet<&'a str>,
+    seen_cycles: &mut HashSet<Vec<String>>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    if finished.contains(module) {
+        return;
+    }
+    if let Some(pos) = path.iter().position(|m| *m == module) {
+        let cycle: Vec<String> = path[pos..].iter().map(|m| m.to_string()).collect();
+        if seen_cycles.insert(normalize_cycle(&cycle)) {
+            let mut route = cycle;
+            route.push(module.to_string());
+            cycles.push(route);
+        }
+        return;
+    }
+    let Some(deps) = modules.get(module) else {
+        return;
+    };
+
+    path.push(module);
+    for dep in deps {
+        visit(dep, modules, path, finished, seen_cycles, cycles);
+    }
+    path.pop();
+    finished.insert(module);
+}
+
+/// 循環を最小のモジュール名から始まるように回転する (重複判定用)
+fn normalize_cycle(cycle: &[String]) -> Vec<String> {
+    let min = cycle
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, name)| *name)
+        .map_or(0, |(i, _)| i);
+    cycle[min..].iter().chain(&cycle[..min]).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SymbolBuilder;
+    use tower_lsp::lsp_types::Url;
+
+    fn add_module(index: &Index, name: &str, deps: &[&str]) {
+        let uri = Url::parse(&format!("file:///{}.js", name)).unwrap();
+        index.definitions.add_definition(
+            SymbolBuilder::new(name, SymbolKind::Module, uri)
+                .dependencies(deps.iter().map(|d| d.to_string()).collect())
+                .build(),
+        );
+    }
+
+    #[test]
+    fn graph_lists_dependencies_and_undefined_modules() {
+        let index = Index::new();
+        add_module(&index, "app", &["app.core", "ngRoute"]);
+        add_module(&index, "app.core", &[]);
+
+        let graph = index.module_graph();
+        assert_eq!(graph.modules["app"], vec!["app.core", "ngRoute"]);
+        assert!(graph.modules["app.core"].is_empty());
+        assert_eq!(graph.undefined["ngRoute"], vec!["app"]);
+        assert!(graph.cycles.is_empty());
+    }
+
+    #[test]
+    fn graph_reports_each_cycle_once() {
+        let index = Index::new();
+        add_module(&index, "a", &["b"]);
+        add_module(&index, "b", &["c"]);
+        add_module(&index, "c", &["a"]);
+        add_module(&index, "d", &["d"]);
+
+        let graph = index.module_graph();
+        assert_eq!(
+            graph.cycles,
+            vec![
+                vec!["a".to_string(), "b".to_string(), "c".to_string(), "a".to_string()],
+                vec!["d".to_string(), "d".to_string()],
+            ]
+        );
+    }
+}
@@ -4,7 +4,7 @@ use tower_lsp::lsp_types::Url;
 
 use super::Index;
 use crate::model::{
-    HtmlFormBinding, HtmlLocalVariable, Span, Symbol, SymbolKind,
+    DirectiveUsageType, HtmlFormBinding, HtmlLocalVariable, Span, Symbol, SymbolKind,
     SymbolReference,
 };
 
@@ -18,11 +18,13 @@ impl Index {
             return self.get_html_references_for_root_scope(&property_path, symbol_name);
         }
 
-        let (controller_name, property_path) =
-            if let Some(parsed) = self.parse_scope_symbol_name(symbol_name) {
-                parsed
-            } else if let Some(parsed) = self.parse_controller_method_name(symbol_name) {
-                parsed
+        // `Ctrl.$scope.x` は `{{ x }}`、`Ctrl.x` (controller as のインスタンスメンバー) は
+        // `{{ vm.x }}` で参照される
+        let (controller_name, property_path, is_scope_symbol) =
+            if let Some((controller, property)) = self.parse_scope_symbol_name(symbol_name) {
+                (controller, property, true)
+            } else if let Some((controller, property)) = self.parse_controller_method_name(symbol_name) {
+                (controller, property, false)
             } else {
                 return Vec::new();
             };
@@ -34,9 +36,20 @@ impl Index {
             let html_refs = entry.value();
 
             for html_ref in html_refs {
-                let direct_match = html_ref.property_path == property_path;
+                let direct_match = is_scope_symbol && html_ref.property_path == property_path;
 
-                let alias_match = if html_ref.property_path.contains('.') {
+                // `$parent.x` は x を定義している最も近い親コントローラーの $scope.x だけを指す
+                let parent_match = is_scope_symbol
+                    && self
+                        .resolve_parent_scope_symbol(
+                            uri,
+                            html_ref.start_line,
+                            html_ref.start_col,
+                            &html_ref.property_path,
+                        )
+                        .is_some_and(|symbol| symbol == symbol_name);
+
+                let alias_match = if !is_scope_symbol && html_ref.property_path.contains('.') {
                     let parts: Vec<&str> = html_ref.property_path.splitn(2, '.').collect();
                     if parts.len() == 2 {
                         let alias = parts[0];
@@ -55,13 +68,16 @@ impl Index {
                     false
                 };
 
-                if !direct_match && !alias_match {
+                if !direct_match && !alias_match && !parent_match {
                     continue;
                 }
 
                 let controllers =
                     self.resolve_controllers_for_html(uri, html_ref.start_line);
-                if controllers.contains(&controller_name.to_string()) || alias_match {
+                if (direct_match && controllers.contains(&controller_name.to_string()))
+                    || alias_match
+                    || parent_match
+                {
                     references.push(SymbolReference {
                         name: symbol_name.to_string(),
                         uri: uri.clone(),
@@ -91,12 +107,25 @@ impl Index {
             let html_refs = entry.value();
 
             for html_ref in html_refs {
-                if html_ref.property_path != property_path {
+                // `$root.x` / `$parent.x` は辿った先のコントローラーだけを見る
+                let parent = self.resolve_parent_scope_path(
+                    uri,
+                    html_ref.start_line,
+                    html_ref.start_col,
+                    &html_ref.property_path,
+                );
+                let prop = parent
+                    .as_ref()
+                    .map_or(html_ref.property_path.as_str(), |(_, prop)| prop.as_str());
+                if prop != property_path {
                     continue;
                 }
 
-                let controllers =
-                    self.resolve_controllers_for_html(uri, html_ref.start_line);
+                let controllers = match parent {
+                    Some((controllers, _)) => controllers,
+                    None => self.resolve_controllers_for_html(uri, html_ref.start_line),
+                };
+
                 let has_scope_property = controllers.iter().any(|ctrl| {
                     let scope_symbol = format!("{}.$scope.{}", ctrl, property_path);
                     self.definitions.has_definition(&scope_symbol)
@@ -123,9 +152,25 @@ impl Index {
     }
 
     /// JS参照とHTML参照を合わせて取得
+    ///
+    /// `.component('userList', ...)` の要素使用 (`<user-list>`) は HTML のディレクティブ
+    /// 参照としてのみ登録されているので、ここでコンポーネントの参照に含める
     pub fn get_all_references(&self, name: &str) -> Vec<SymbolReference> {
         let mut refs = self.definitions.get_references(name);
         refs.extend(self.get_html_references_for_symbol(name));
+        if self.definitions.has_definition_of_kind(name, SymbolKind::Component) {
+            refs.extend(
+                self.html
+                    .get_html_directive_references(name)
+                    .into_iter()
+                    .filter(|r| r.usage_type == DirectiveUsageType::Element)
+                    .map(|r| SymbolReference {
+                        name: name.to_string(),
+                        span: r.span(),
+                        uri: r.uri,
+                    }),
+            );
+        }
         refs
     }
 
@@ -250,6 +295,75 @@ impl Index {
         controllers
     }
 
+    /// `$parent.x` / `$parent.$parent.x` / `$root.x` を `(候補コントローラー, x)` に分解する
+    ///
+    /// `$parent` 1 つごとに、その位置を囲むスコープ (ng-controller と ng-if / ng-repeat /
+    /// ng-include / ng-switch-when などの子スコープ) の最も内側を 1 つ外す。候補は残った
+    /// スコープのコントローラーを、スコープ継承で先に見つかる内側から外側への順に並べたもの。
+    /// `$root.x` は `$rootScope` だけを参照するので候補は空。
+    /// `$parent` / `$root` で始まらないパスや、辿った先にコントローラーがない場合は `None`。
+    ///
+    /// HTML 上の位置 (`col` は UTF-16) でのみ解決する。JS の `$scope.$parent.x` は
+    /// 親がコントローラーを置いた HTML 次第で決まらないため対象外
+    pub fn resolve_parent_scope_path(
+        &self,
+        uri: &Url,
+        line: u32,
+        col: u32,
+        property_path: &str,
+    ) -> Option<(Vec<String>, String)> {
+        if let Some(prop) = property_path.strip_prefix("$root.") {
+            return Some((Vec::new(), prop.to_string()));
+        }
+
+        let mut prop = property_path;
+        let mut depth = 0;
+        while let Some(rest) = prop.strip_prefix("$parent.") {
+            prop = rest;
+            depth += 1;
+        }
+        if depth == 0 {
+            return None;
+        }
+
+        // 外側から内側への順のスコープ (`None` はコントローラーを持たない子スコープ)
+        let local = self.controllers.get_html_scopes_at(uri, line, col);
+        let mut scopes: Vec<Option<String>> = self
+            .resolve_controllers_for_html(uri, line)
+            .into_iter()
+            .filter(|controller| !local.contains(&Some(controller.clone())))
+            .map(Some)
+            .collect();
+        // ng-include / ng-view で取り込まれたテンプレートは、取り込んだ要素の子スコープにある
+        if !self.templates.get_inherited_controllers_for_template(uri).is_empty() {
+            scopes.push(None);
+        }
+        scopes.extend(local);
+
+        scopes.truncate(scopes.len().saturating_sub(depth));
+        let controllers: Vec<String> = scopes.into_iter().rev().flatten().collect();
+        if controllers.is_empty() {
+            return None;
+        }
+        Some((controllers, prop.to_string()))
+    }
+
+    /// `$parent.x` / `$root.x` が解決できるシンボル名 (`Ctrl.$scope.x` または `$rootScope` の x)
+    pub fn resolve_parent_scope_symbol(
+        &self,
+        uri: &Url,
+        line: u32,
+        col: u32,
+        property_path: &str,
+    ) -> Option<String> {
+        let (controllers, prop) = self.resolve_parent_scope_path(uri, line, col, property_path)?;
+        controllers
+            .iter()
+            .map(|controller| format!("{}.$scope.{}", controller, prop))
+            .find(|symbol| self.definitions.has_definition(symbol))
+            .or_else(|| self.definitions.find_root_scope_symbol_name_by_property(&prop))
+    }
+
     /// aliasに対応するコントローラー名を解決（ng-controller + コンポーネントテンプレート）
     pub fn resolve_controller_by_alias(
         &self,
@@ -286,6 +400,9 @@ impl Index {
                 uri: v.uri,
                 scope_start_line: v.scope_start_line,
                 scope_end_line: v.scope_end_line,
+                // 子テンプレート全体で有効
+                scope_start_byte: 0,
+                scope_end_byte: usize::MAX,
                 name_start_line: v.name_start_line,
                 name_start_col: v.name_start_col,
                 name_end_line: v.name_end_line,
@@ -317,6 +434,8 @@ impl Index {
                 name_start_col: b.name_start_col,
                 name_end_line: b.name_end_line,
                 name_end_col: b.name_end_col,
+                field_names: b.field_names,
+                nested_form_names: b.nested_form_names,
             })
     }
 
@@ -346,17 +465,43 @@ impl Index {
         self.templates.resolve_template_uri(template_path)
     }
 
-    /// コントローラー名からバインドされているHTMLテンプレートのパスを取得
-    pub fn get_templates_for_controller(&self, controller_name: &str) -> Vec<String> {
-        let mut templates = self.templates.get_templates_for_controller(controller_name);
-        let html_templates = self
-            .controllers
-            .get_html_templates_for_controller(controller_name);
-        for t in html_templates {
-            if !templates.contains(&t) {
-                templates.push(t);
-            }
+    /// コントローラーがバインドされている HTML テンプレートを (URI, 行) で取得
+    ///
+    /// - `$routeProvider` / `$stateProvider` / `$uibModal.open` 等のテンプレートバインディング
+    ///   (ファイル先頭)
+    /// - `ng-controller` を書いている HTML (最初の ng-controller の行)
+    /// - `include_inherited` が true なら ng-include / ng-view でコントローラーを
+    ///   継承している子テンプレート (ファイル先頭)
+    ///
+    /// 同じファイルは最初に見つかったものだけを返す
+    pub fn get_templates_for_controller(
+        &self,
+        controller_name: &str,
+        include_inherited: bool,
+    ) -> Vec<(Url, u32)> {
+        let mut templates: Vec<(Url, u32)> = self
+            .templates
+            .get_templates_for_controller(controller_name)
+            .iter()
+            .filter_map(|path| self.resolve_template_uri(path))
+            .map(|uri| (uri, 0))
+            .collect();
+        templates.extend(
+            self.controllers
+                .get_html_templates_for_controller(controller_name),
+        );
+        if include_inherited {
+            templates.extend(
+                self.templates
+                    .get_inheriting_templates_for_controller(controller_name)
+                    .into_iter()
+                    .map(|uri| (uri, 0)),
+            );
         }
+
+        let mut seen = HashSet::new();
+        templates.retain(|(uri, _)| seen.insert(uri.clone()));
+        templates.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
         templates
     }
 
@@ -379,6 +524,10 @@ impl Index {
                 ),
                 docs: Some("ng-controller".to_string()),
                 parameters: None,
+                module: None,
+                restrict: None,
+                binding_type: None,
+                dependencies: None,
             });
         }
 
@@ -392,6 +541,10 @@ impl Index {
                 name_span: Span::new(r.start_line, r.start_col, r.end_line, r.end_col),
                 docs: None,
                 parameters: None,
+                module: None,
+                restrict: None,
+                binding_type: None,
+                dependencies: None,
             });
         }
 
@@ -405,6 +558,10 @@ impl Index {
                 name_span: Span::new(r.start_line, r.start_col, r.end_line, r.end_col),
                 docs: None,
                 parameters: None,
+                module: None,
+                restrict: None,
+                binding_type: None,
+                dependencies: None,
             });
         }
 
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::Index;
+use crate::model::SymbolKind;
+
+/// インデックスに登録されている情報の件数
+///
+/// `angularjs-lsp.showStatus` コマンドの応答の一部として JSON 化する
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct IndexStatus {
+    /// 定義の総数
+    pub definitions: usize,
+    /// 種類 (`SymbolKind::as_str`) ごとの定義数
+    pub definitions_by_kind: BTreeMap<&'static str, usize>,
+    /// コントローラーの定義数
+    pub controllers: usize,
+    /// 解析済みの HTML ファイル数
+    pub analyzed_html_files: usize,
+}
+
+impl Index {
+    /// 各ストアの件数を集計する
+    pub fn status(&self) -> IndexStatus {
+        let definitions = self.definitions.get_all_definitions();
+        let mut definitions_by_kind = BTreeMap::new();
+        for symbol in &definitions {
+            *definitions_by_kind.entry(symbol.kind.as_str()).or_insert(0) += 1;
+        }
+        IndexStatus {
+            definitions: definitions.len(),
+            controllers: definitions_by_kind
+                .get(SymbolKind::Controller.as_str())
+                .copied()
+                .unwrap_or(0),
+            definitions_by_kind,
+            analyzed_html_files: self.templates.analyzed_html_uris().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::js::AngularJsAnalyzer;
+    use std::sync::Arc;
+    use tower_lsp::lsp_types::Url;
+
+    #[test]
+    fn counts_definitions_by_kind() {
+        let index = Arc::new(Index::new());
+        let uri = Url::parse("file:///app.js").unwrap();
+        AngularJsAnalyzer::new(Arc::clone(&index)).analyze_document(
+            &uri,
+            r#"
+angular.module('app', [])
+    .controller('MainCtrl', function() {})
+    .controller('SubCtrl', function() {})
+    .service('UserService', function() {});
+"#,
+        );
+        index.templates.mark_html_analyzed(&Url::parse("file:///index.html").unwrap());
+
+        let status = index.status();
+        assert_eq!(status.controllers, 2);
+        assert_eq!(status.definitions_by_kind.get("module"), Some(&1));
+        assert_eq!(status.definitions_by_kind.get("service"), Some(&1));
+        assert_eq!(status.definitions, status.definitions_by_kind.values().sum::<usize>());
+        assert_eq!(status.analyzed_html_files, 1);
+    }
+}
@@ -0,0 +1,113 @@
+use serde::Serialize;
+use tower_lsp::lsp_types::Url;
+
+use super::Index;
+
+/// 指定した名前のシンボルについてインデックスが持っている情報
+///
+/// `angularjs-lsp.dumpSymbol` コマンドの応答としてそのまま JSON 化する。
+/// 行・列は LSP と同じ 0 始まり
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct SymbolDump {
+    pub name: String,
+    pub definitions: Vec<DumpedDefinition>,
+    /// JS 内の参照
+    pub references: Vec<DumpedReference>,
+    /// 名前の末尾 (`Ctrl.$scope.user` なら `user`) と一致する HTML スコープ参照
+    pub html_scope_references: Vec<DumpedHtmlScopeReference>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DumpedDefinition {
+    pub uri: Url,
+    pub line: u32,
+    pub col: u32,
+    pub kind: &'static str,
+    /// `Ctrl.$scope.x` / `Ctrl.x` 形式の名前から取り出したコントローラー名
+    pub controller: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DumpedReference {
+    pub uri: Url,
+    pub line: u32,
+    pub col: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DumpedHtmlScopeReference {
+    pub uri: Url,
+    pub line: u32,
+    pub col: u32,
+    pub property_path: String,
+    /// 参照位置で解決されたコントローラー (外側から内側への順)
+    pub controllers: Vec<String>,
+    /// このシンボルの参照として解決されたか (Find References に出るか)
+    pub resolved: bool,
+}
+
+impl Index {
+    /// シンボル名の全定義・全参照を集める (定義ジャンプが効かない原因の切り分け用)
+    pub fn dump_symbol(&self, name: &str) -> SymbolDump {
+        let controller = self
+            .parse_scope_symbol_name(name)
+            .or_else(|| self.parse_controller_method_name(name))
+            .map(|(controller, _)| controller);
+
+        let definitions = self
+            .definitions
+            .get_definitions(name)
+            .into_iter()
+            .map(|symbol| DumpedDefinition {
+                uri: symbol.uri,
+                line: symbol.name_span.start_line,
+                col: symbol.name_span.start_col,
+                kind: symbol.kind.as_str(),
+                controller: controller.clone(),
+            })
+            .collect();
+
+        let references = self
+            .definitions
+            .get_references(name)
+            .into_iter()
+            .map(|reference| DumpedReference {
+                uri: reference.uri,
+                line: reference.span.start_line,
+                col: reference.span.start_col,
+            })
+            .collect();
+
+        let resolved = self.get_html_references_for_symbol(name);
+        let leaf = name.rsplit('.').next().unwrap_or(name);
+        let mut html_scope_references = Vec::new();
+        for entry in self.html.iter_all_html_scope_references() {
+            let uri = entry.key();
+            for html_ref in entry.value() {
+                if html_ref.property_path.rsplit('.').next() != Some(leaf) {
+                    continue;
+                }
+                html_scope_references.push(DumpedHtmlScopeReference {
+                    uri: uri.clone(),
+                    line: html_ref.start_line,
+                    col: html_ref.start_col,
+                    property_path: html_ref.property_path.clone(),
+                    controllers: self.resolve_controllers_for_html(uri, html_ref.start_line),
+                    resolved: resolved
+                        .iter()
+                        .any(|r| &r.uri == uri && r.span == html_ref.span()),
+                });
+            }
+        }
+        html_scope_references.sort_by(|a, b| {
+            (a.uri.as_str(), a.line, a.col).cmp(&(b.uri.as_str(), b.line, b.col))
+        });
+
+        SymbolDump {
+            name: name.to_string(),
+            definitions,
+            references,
+            html_scope_references,
+        }
+    }
+}
@@ -2,10 +2,10 @@ use dashmap::{DashMap, DashSet};
 use tower_lsp::lsp_types::Url;
 
 use crate::model::{
-    BindingSource, InheritedFormBinding, InheritedLocalVariable, NgIncludeBinding, NgViewBinding,
-    TemplateBinding,
+    BindingSource, InheritedFormBinding, InheritedLocalVariable, InlineTemplate, NgIncludeBinding,
+    NgViewBinding, TemplateBinding,
 };
-use crate::util::normalize_template_path;
+use crate::util::{normalize_template_path, resolve_relative_full_path, resolve_relative_path};
 
 /// テンプレートバインディング・ng-include/ng-view の管理ストア
 pub struct TemplateStore {
@@ -25,6 +25,10 @@ pub struct TemplateStore {
     pending_reanalysis: DashSet<Url>,
     /// 解析済みのHTMLファイルのURI
     analyzed_html_files: DashSet<Url>,
+    /// ワークスペーススキャンで見つかったテンプレートファイルのファイル名
+    workspace_file_names: DashSet<String>,
+    /// JS 内のインラインテンプレート（定義元 JS の URI -> テンプレート）
+    inline_templates: DashMap<Url, Vec<InlineTemplate>>,
 }
 
 impl TemplateStore {
@@ -38,6 +42,8 @@ impl TemplateStore {
             route_provider_templates: DashSet::new(),
             pending_reanalysis: DashSet::new(),
             analyzed_html_files: DashSet::new(),
+            workspace_file_names: DashSet::new(),
+            inline_templates: DashMap::new(),
         }
     }
 
@@ -276,6 +282,14 @@ impl TemplateStore {
         self.ng_view_bindings.insert(key, binding);
     }
 
+    /// 全ng-viewバインディングを取得（キャッシュ用）
+    pub fn get_all_ng_view_bindings(&self) -> Vec<NgViewBinding> {
+        self.ng_view_bindings
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
     fn queue_child_for_reanalysis(&self, resolved_filename: &str, normalized_path: &str) {
         for uri in self.analyzed_html_files.iter() {
             let uri_path = uri.path();
@@ -385,6 +399,24 @@ impl TemplateStore {
         controllers
     }
 
+    /// ng-include / ng-view 経由でコントローラーを継承している子テンプレートを取得
+    pub fn get_inheriting_templates_for_controller(&self, controller_name: &str) -> Vec<Url> {
+        let mut templates = Vec::new();
+        for entry in self.ng_include_bindings.iter() {
+            let binding = entry.value();
+            if !binding.inherited_controllers.iter().any(|c| c == controller_name) {
+                continue;
+            }
+            let template_path = Self::extract_template_path_from_key(entry.key());
+            for uri in self.resolve_ng_include_targets(&binding.parent_uri, template_path) {
+                if !templates.contains(&uri) {
+                    templates.push(uri);
+                }
+            }
+        }
+        templates
+    }
+
     /// ng-includeで継承されるローカル変数リストを取得
     pub fn get_inherited_local_variables_for_template(
         &self,
@@ -539,6 +571,69 @@ impl TemplateStore {
         None
     }
 
+    /// ng-include のテンプレートパスを解析済み HTML ファイルの URI に解決
+    ///
+    /// 同名ファイルが複数ある場合に備え、親ファイルのディレクトリからの相対解決と
+    /// 完全一致するファイルを最優先し、なければ [`Self::resolve_template_targets`]
+    /// と同じ規則で探す。見つからなければ空を返す
+    pub fn resolve_ng_include_targets(&self, parent_uri: &Url, template_path: &str) -> Vec<Url> {
+        let full_path = resolve_relative_full_path(parent_uri, template_path);
+        let filename = resolve_relative_path(parent_uri, template_path);
+
+        let exact = self
+            .analyzed_html_files
+            .iter()
+            .find(|uri| uri.path() == full_path)
+            .map(|uri| uri.clone());
+        if let Some(exact) = exact {
+            return vec![exact];
+        }
+        self.resolve_template_targets_by_filename(template_path, &filename)
+    }
+
+    /// templateUrl などのテンプレートパスを解析済み HTML ファイルの URI に解決
+    ///
+    /// 1. 正規化パス (`../` や先頭 `/`、クエリパラメータを除去) でのパス末尾一致
+    ///    （プロジェクトルート相対）
+    /// 2. ファイル名のみ一致し、候補が1件
+    ///
+    /// 見つからなければ空を返す
+    pub fn resolve_template_targets(&self, template_path: &str) -> Vec<Url> {
+        let normalized_path = normalize_template_path(template_path);
+        let filename = normalized_path
+            .rsplit('/')
+            .next()
+            .unwrap_or(&normalized_path)
+            .to_string();
+        self.resolve_template_targets_by_filename(template_path, &filename)
+    }
+
+    fn resolve_template_targets_by_filename(&self, template_path: &str, filename: &str) -> Vec<Url> {
+        let normalized_path = normalize_template_path(template_path);
+        let candidates: Vec<Url> = self
+            .analyzed_html_files
+            .iter()
+            .filter(|uri| uri.path().rsplit('/').next() == Some(filename))
+            .map(|uri| uri.clone())
+            .collect();
+
+        let suffix = format!("/{}", normalized_path);
+        let mut by_path: Vec<Url> = candidates
+            .iter()
+            .filter(|uri| uri.path().ends_with(&suffix))
+            .cloned()
+            .collect();
+        if !by_path.is_empty() {
+            by_path.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+            return by_path;
+        }
+
+        if candidates.len() == 1 {
+            return candidates;
+        }
+        Vec::new()
+    }
+
     /// 全ての$routeProviderテンプレートに対してng-view継承を適用
     pub fn apply_all_ng_view_inheritances(&self) {
         let templates: Vec<String> = self
@@ -702,6 +797,44 @@ impl TemplateStore {
         self.analyzed_html_files.iter().map(|r| r.clone()).collect()
     }
 
+    /// ワークスペースに存在するファイルとして登録する
+    pub fn add_workspace_file(&self, uri: &Url) {
+        if let Some(name) = uri.path_segments().and_then(|mut s| s.next_back()) {
+            self.workspace_file_names.insert(name.to_string());
+        }
+    }
+
+    /// ワークスペーススキャンでファイル一覧が登録済みか
+    pub fn has_workspace_files(&self) -> bool {
+        !self.workspace_file_names.is_empty()
+    }
+
+    /// 指定ファイル名のファイルがワークスペースに存在するか
+    pub fn workspace_file_exists(&self, file_name: &str) -> bool {
+        self.workspace_file_names.contains(file_name)
+    }
+
+    // ========== インラインテンプレート ==========
+
+    /// インラインテンプレートを登録（JS の 2 パス目で同じ位置が再登録されても重複させない）
+    pub fn add_inline_template(&self, template: InlineTemplate) {
+        let mut templates = self.inline_templates.entry(template.uri.clone()).or_default();
+        if !templates
+            .iter()
+            .any(|t| t.line == template.line && t.col == template.col)
+        {
+            templates.push(template);
+        }
+    }
+
+    /// 指定 JS ファイル内のインラインテンプレートを取得
+    pub fn get_inline_templates(&self, uri: &Url) -> Vec<InlineTemplate> {
+        self.inline_templates
+            .get(uri)
+            .map(|templates| templates.value().clone())
+            .unwrap_or_default()
+    }
+
     pub fn clear_ng_include_bindings_for_parent(&self, parent_uri: &Url) {
         let entries_to_remove: Vec<(String, String, String)> = self
             .ng_include_bindings
@@ -730,6 +863,7 @@ impl TemplateStore {
     pub fn clear_document(&self, uri: &Url) {
         self.clear_ng_include_bindings_for_parent(uri);
         self.ng_view_bindings.remove(&uri.to_string());
+        self.inline_templates.remove(uri);
     }
 
     pub fn clear_all(&self) {
@@ -741,6 +875,8 @@ impl TemplateStore {
         self.route_provider_templates.clear();
         self.pending_reanalysis.clear();
         self.analyzed_html_files.clear();
+        self.workspace_file_names.clear();
+        self.inline_templates.clear();
     }
 }
 
@@ -12,6 +12,10 @@ pub struct SymbolBuilder {
     name_span: Span,
     docs: Option<String>,
     parameters: Option<Vec<String>>,
+    module: Option<String>,
+    restrict: Option<String>,
+    dependencies: Option<Vec<String>>,
+    binding_type: Option<String>,
 }
 
 impl SymbolBuilder {
@@ -24,6 +28,10 @@ impl SymbolBuilder {
             name_span: Span::default(),
             docs: None,
             parameters: None,
+            module: None,
+            restrict: None,
+            dependencies: None,
+            binding_type: None,
         }
     }
 
@@ -47,6 +55,26 @@ impl SymbolBuilder {
         self
     }
 
+    pub fn module(mut self, module: impl Into<String>) -> Self {
+        self.module = Some(module.into());
+        self
+    }
+
+    pub fn restrict(mut self, restrict: impl Into<String>) -> Self {
+        self.restrict = Some(restrict.into());
+        self
+    }
+
+    pub fn dependencies(mut self, dependencies: Vec<String>) -> Self {
+        self.dependencies = Some(dependencies);
+        self
+    }
+
+    pub fn binding_type(mut self, binding_type: impl Into<String>) -> Self {
+        self.binding_type = Some(binding_type.into());
+        self
+    }
+
     pub fn build(self) -> Symbol {
         Symbol {
             name: self.name,
@@ -56,6 +84,10 @@ impl SymbolBuilder {
             name_span: self.name_span,
             docs: self.docs,
             parameters: self.parameters,
+            module: self.module,
+            restrict: self.restrict,
+            dependencies: self.dependencies,
+            binding_type: self.binding_type,
         }
     }
 }
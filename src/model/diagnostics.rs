@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Url;
 
@@ -21,3 +23,159 @@ pub struct DiArityIssue {
     /// 警告の表示位置 (関数本体または class 全体)
     pub span: Span,
 }
+
+/// DI 配列 (または `$inject`) と関数引数の位置ずれを表す診断情報
+///
+/// 認識パターン:
+/// ```javascript
+/// // position = 0, service_name = "$scope", param_name = "UserService" → 警告
+/// .controller('Ctrl', ['$scope', 'UserService', function(UserService, $scope) {}])
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiOrderIssue {
+    /// この診断を出すドキュメント
+    pub uri: Url,
+    /// 食い違っている位置 (0 始まり)
+    pub position: usize,
+    /// その位置に注入されるサービス名
+    pub service_name: String,
+    /// その位置の引数名
+    pub param_name: String,
+    /// 警告の表示位置 (引数の識別子)
+    pub span: Span,
+}
+
+/// DI 配列 / `$inject` で注入されているサービス名とその文字列リテラルの位置
+///
+/// 定義の存在確認は他ファイルの解析結果に依存するため、解析時には記録だけ行い
+/// 診断時に照合する。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedService {
+    /// 注入しているドキュメント
+    pub uri: Url,
+    /// 注入されているサービス名 (例: `UserService`)
+    pub name: String,
+    /// 文字列リテラルの位置
+    pub span: Span,
+}
+
+/// `templateUrl` / `ng-include` に書かれたテンプレートパスとその位置
+///
+/// ファイルの存在確認はワークスペーススキャンの結果に依存するため、
+/// 解析時には記録だけ行い診断時に照合する。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplatePathUsage {
+    /// パスが書かれているドキュメント
+    pub uri: Url,
+    /// 文字列リテラル部分のパス (動的連結の場合は先頭のリテラルのみ)
+    pub path: String,
+    /// 文字列リテラル (HTML では属性値) の位置
+    pub span: Span,
+}
+
+/// ドキュメントの AngularJS 解析を行えなかった理由
+///
+/// 失敗したドキュメントのインデックスは前回の解析結果のまま残るので、補完などが
+/// 古い情報で動く (または効かない) 理由を info 診断としてユーザーに知らせる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisFailure {
+    /// tree-sitter がツリーを返さなかった (パースのキャンセル / タイムアウトなど)
+    ParseFailed,
+    /// 解析中に panic した
+    Panicked,
+}
+
+impl AnalysisFailure {
+    pub fn reason(self) -> &'static str {
+        match self {
+            AnalysisFailure::ParseFailed => "parse failed",
+            AnalysisFailure::Panicked => "analyzer panicked",
+        }
+    }
+}
+
+/// HTML の構文エラー (tree-sitter のパースで ERROR / MISSING ノードになった箇所)
+///
+/// 閉じタグの欠落や属性値のクォート不一致など。AngularJS の解析自体は
+/// エラー回復後のツリーで続けるので、診断として位置を知らせるだけに使う。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtmlSyntaxError {
+    /// エラーのあるドキュメント
+    pub uri: Url,
+    /// 欠けているトークン (MISSING ノードの種類)。不正な記述 (ERROR ノード) は `None`
+    pub missing: Option<String>,
+    /// エラーの位置 (複数行にわたる ERROR ノードは開始行の末尾まで)
+    pub span: Span,
+}
+
+/// DI で受け取る引数と、関数本体で使われている識別子の集合
+///
+/// 認識パターン:
+/// ```javascript
+/// // UserService は本体で使われていない → 未使用の注入として hint
+/// .controller('Ctrl', ['$scope', 'UserService', function($scope, UserService) {
+///     $scope.name = '';
+/// }])
+/// ```
+///
+/// 除外するサービス (`$scope` など) の判断や、使われているのに注入されていない
+/// サービスの判定は他ファイルの定義に依存するため、解析時には引数と使用識別子を
+/// そのまま記録し診断時に照合する。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectionUsage {
+    /// 関数のあるドキュメント
+    pub uri: Url,
+    /// DI で受け取る引数 (注入順)
+    pub params: Vec<InjectedParam>,
+    /// 関数本体 (class は class 本体全体) で使われている識別子と最初の出現位置
+    pub used_identifiers: HashMap<String, Span>,
+    /// 本体で宣言されている名前 (ローカル変数・関数名・内側の関数の引数) と、
+    /// 関数を囲むスコープで宣言されている名前 (外側の変数・引数・`import` / `require`)
+    pub declared_identifiers: HashSet<String>,
+    /// DI 配列 / `$inject` に名前を足す位置 (暗黙 DI では `None`)
+    pub dependency_insert: Option<InsertPoint>,
+    /// 引数を足す位置 (`x => ...` のように括弧のない引数や、DI 配列と引数の数が
+    /// 合わない場合は `None`)
+    pub param_insert: Option<InsertPoint>,
+    /// 関数 (または class) 全体の位置
+    pub span: Span,
+}
+
+/// DI 配列や引数リストに要素を足す位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InsertPoint {
+    pub line: u32,
+    pub col: u32,
+    /// 既存の要素の後ろに足すので前に `, ` が要る
+    pub leading_comma: bool,
+    /// 既存の要素の前に足すので後ろに `, ` が要る
+    pub trailing_comma: bool,
+}
+
+/// DI で受け取る 1 つの引数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedParam {
+    /// 注入されるサービス名 (暗黙 DI では引数名と同じ)
+    pub service: String,
+    /// 引数名
+    pub name: String,
+    /// 引数の識別子の位置
+    pub span: Span,
+}
+
+/// 値を読むだけの式の中にある代入 (`==` の書き間違い)
+///
+/// 認識パターン:
+/// ```html
+/// <!-- attribute = "ng-if" → 警告 -->
+/// <div ng-if="status = 'active'"></div>
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpressionAssignment {
+    /// 代入のあるドキュメント
+    pub uri: Url,
+    /// 代入を含む属性名 (`data-ng-if` など HTML に書かれたまま。診断メッセージに使う)
+    pub attribute: String,
+    /// `=` 演算子の位置
+    pub span: Span,
+}
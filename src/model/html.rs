@@ -32,6 +32,9 @@ pub enum HtmlLocalVariableSource {
     /// ng-repeat スコープで暗黙に利用可能な特殊変数
     /// ($index, $first, $last, $middle, $odd, $even)
     NgRepeatSpecial,
+    /// ng-repeat="item in items | filter:q as filtered" -> "filtered"
+    /// (フィルター適用後のコレクション。ng-repeat 要素の親スコープに公開される)
+    NgRepeatAlias,
 }
 
 impl HtmlLocalVariableSource {
@@ -41,6 +44,7 @@ impl HtmlLocalVariableSource {
             HtmlLocalVariableSource::NgRepeatIterator => "ng-repeat",
             HtmlLocalVariableSource::NgRepeatKeyValue => "ng-repeat",
             HtmlLocalVariableSource::NgRepeatSpecial => "ng-repeat (special)",
+            HtmlLocalVariableSource::NgRepeatAlias => "ng-repeat (alias)",
         }
     }
 }
@@ -55,6 +59,10 @@ pub struct HtmlLocalVariable {
     pub scope_start_line: u32,
     /// スコープの終了行（定義要素の終了）
     pub scope_end_line: u32,
+    /// スコープとなる要素の開始バイト位置（スコープのネスト判定用）
+    pub scope_start_byte: usize,
+    /// スコープとなる要素の終了バイト位置（排他的。スコープのネスト判定用）
+    pub scope_end_byte: usize,
     /// 変数名の定義位置（正確な位置）
     pub name_start_line: u32,
     pub name_start_col: u32,
@@ -108,6 +116,12 @@ pub struct HtmlFormBinding {
     pub name_start_col: u32,
     pub name_end_line: u32,
     pub name_end_col: u32,
+    /// フォーム配下のコントロール名 (`<input name="email">` の `email` 等)
+    #[serde(default)]
+    pub field_names: Vec<String>,
+    /// `field_names` のうち入れ子フォーム (`<div ng-form name="address">`) の名前
+    #[serde(default)]
+    pub nested_form_names: Vec<String>,
 }
 
 impl HtmlFormBinding {
@@ -150,6 +164,10 @@ pub struct InheritedFormBinding {
     pub name_start_col: u32,
     pub name_end_line: u32,
     pub name_end_col: u32,
+    #[serde(default)]
+    pub field_names: Vec<String>,
+    #[serde(default)]
+    pub nested_form_names: Vec<String>,
 }
 
 /// HTML内でのディレクティブ使用タイプ
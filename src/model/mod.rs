@@ -9,7 +9,10 @@ pub mod symbol;
 pub mod template;
 
 pub use builder::SymbolBuilder;
-pub use diagnostics::DiArityIssue;
+pub use diagnostics::{
+    AnalysisFailure, DiArityIssue, DiOrderIssue, ExpressionAssignment, HtmlSyntaxError, InjectedParam,
+    InjectedService, InjectionUsage, InsertPoint, TemplatePathUsage,
+};
 pub use export::{ExportInfo, ExportedComponentObject};
 pub use html::{
     DirectiveUsageType, HtmlDirectiveReference, HtmlFormBinding, HtmlLocalVariable,
@@ -17,7 +20,7 @@ pub use html::{
     HtmlUiSrefReference, InheritedFormBinding, InheritedLocalVariable,
 };
 pub use inheritance::{NgIncludeBinding, NgViewBinding};
-pub use scope::{ControllerScope, HtmlControllerScope};
+pub use scope::{ControllerScope, HtmlChildScope, HtmlControllerScope, ServiceAlias};
 pub use span::Span;
 pub use symbol::{Symbol, SymbolKind, SymbolReference};
-pub use template::{BindingSource, ComponentTemplateUrl, TemplateBinding};
+pub use template::{BindingSource, ComponentTemplateUrl, InlineTemplate, TemplateBinding};
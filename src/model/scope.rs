@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Url;
 
+use super::span::Span;
+
 /// コントローラーのスコープ情報（JSファイル側）
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ControllerScope {
@@ -12,17 +14,55 @@ pub struct ControllerScope {
     pub injected_services: Vec<String>,
 }
 
+/// `$injector.get('X')` の戻り値を受けた変数 (JSファイル側)
+///
+/// ```javascript
+/// var svc = $injector.get('UserService');  // name: "svc", service: "UserService"
+/// ```
+///
+/// `start_line`〜`end_line` は代入を含む関数本体の範囲 (関数外ならファイル全体)。
+#[derive(Clone, Debug)]
+pub struct ServiceAlias {
+    pub name: String,
+    pub service: String,
+    pub uri: Url,
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
 /// HTML内のng-controllerスコープ
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HtmlControllerScope {
     pub controller_name: String,
     /// "controller as alias"構文で指定されたalias名（例: "formCustomItem"）
     pub alias: Option<String>,
+    /// ng-controller 属性値内の alias 名の位置（alias 指定時のみ）
+    pub alias_span: Option<Span>,
     pub uri: Url,
     pub start_line: u32,
     pub end_line: u32,
 }
 
+/// HTML内で子スコープを作るディレクティブ (ng-if / ng-repeat / ng-include など) の要素
+///
+/// 位置はどちらも UTF-16。ディレクティブ自身の式は外側のスコープで評価されるので、
+/// `expression_span` 内は子スコープに含めない
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HtmlChildScope {
+    pub uri: Url,
+    /// 要素全体の範囲
+    pub span: Span,
+    /// ディレクティブの属性値の範囲
+    pub expression_span: Span,
+}
+
+impl HtmlChildScope {
+    /// 指定位置がこの子スコープ内か
+    pub fn contains(&self, line: u32, col: u32) -> bool {
+        self.span.contains(line, col) && !self.expression_span.contains(line, col)
+    }
+}
+
 /// DIスコープ（アナライザーコンテキスト用）
 #[derive(Clone, Debug, Default)]
 pub struct DiScope {
@@ -31,8 +31,12 @@ pub enum SymbolKind {
     ExportedComponent,
     /// コンポーネントのbindingsプロパティ（'<', '=', '@', '&'）
     ComponentBinding,
+    /// ディレクティブの isolate scope バインディング（`scope: { userName: '=' }`）
+    DirectiveBinding,
     /// ui-router の state ($stateProvider.state('name', ...) で登録される名前)
     UiRouterState,
+    /// `$scope.$on('name', ...)` で購読されるイベント名 (`$emit` / `$broadcast` が参照)
+    Event,
 }
 
 impl SymbolKind {
@@ -56,7 +60,9 @@ impl SymbolKind {
             SymbolKind::FormBinding => "form binding",
             SymbolKind::ExportedComponent => "exported component",
             SymbolKind::ComponentBinding => "component binding",
+            SymbolKind::DirectiveBinding => "directive binding",
             SymbolKind::UiRouterState => "ui-router state",
+            SymbolKind::Event => "event",
         }
     }
 
@@ -80,7 +86,9 @@ impl SymbolKind {
             SymbolKind::FormBinding => lsp_types::SymbolKind::VARIABLE,
             SymbolKind::ExportedComponent => lsp_types::SymbolKind::CLASS,
             SymbolKind::ComponentBinding => lsp_types::SymbolKind::PROPERTY,
+            SymbolKind::DirectiveBinding => lsp_types::SymbolKind::PROPERTY,
             SymbolKind::UiRouterState => lsp_types::SymbolKind::EVENT,
+            SymbolKind::Event => lsp_types::SymbolKind::EVENT,
         }
     }
 }
@@ -97,6 +105,14 @@ pub struct Symbol {
     pub docs: Option<String>,
     /// 関数パラメータ（ScopeMethodやMethodなどの場合）
     pub parameters: Option<Vec<String>>,
+    /// 登録先モジュール名（`angular.module('app').filter(...)` の `app`）
+    pub module: Option<String>,
+    /// ディレクティブの `restrict` 値（`'EA'` など。未指定なら `None`）
+    pub restrict: Option<String>,
+    /// モジュールの依存モジュール名（`angular.module('app', ['dep1', 'dep2'])` の `dep1`, `dep2`）
+    pub dependencies: Option<Vec<String>>,
+    /// バインディングの値（`'<'`, `'=?'`, `'&onSelected'` など。値が文字列でなければ `None`）
+    pub binding_type: Option<String>,
 }
 
 impl Symbol {
@@ -13,6 +13,8 @@ pub enum BindingSource {
     MdToast,
     MdPanel,
     NgDialog,
+    /// `.directive()` の定義オブジェクトの `templateUrl`
+    Directive,
 }
 
 impl BindingSource {
@@ -29,6 +31,7 @@ impl BindingSource {
             BindingSource::MdToast => "$mdToast",
             BindingSource::MdPanel => "$mdPanel",
             BindingSource::NgDialog => "ngDialog",
+            BindingSource::Directive => "directive",
         }
     }
 }
@@ -61,3 +64,24 @@ pub struct ComponentTemplateUrl {
     /// controllerAsエイリアス（デフォルト: "$ctrl"）
     pub controller_as: String,
 }
+
+/// JS 内のインラインテンプレート (`template: '<div ng-click="onClick()"></div>'`)
+///
+/// `source` は文字列リテラルの中身 (クォートを除く)。テンプレートリテラルの `${...}` は
+/// 位置がずれないよう同じ長さの空白に置き換えてある
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InlineTemplate {
+    /// 定義元のURI（JSファイル）
+    pub uri: Url,
+    pub source: String,
+    /// 中身の開始行（JSファイル内）
+    pub line: u32,
+    /// 中身の開始列（JSファイル内、バイト単位）
+    pub col: u32,
+    /// テンプレートが `$scope` を参照するコントローラー / ディレクティブ名
+    pub scope_name: Option<String>,
+    /// `controllerAs` で参照するコントローラー名
+    pub controller_name: Option<String>,
+    /// controllerAs エイリアス（component はデフォルト "$ctrl"）
+    pub controller_as: Option<String>,
+}
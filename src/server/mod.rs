@@ -3,7 +3,8 @@ pub mod workspace;
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -12,25 +13,40 @@ use tokio::sync::RwLock;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
+use tree_sitter::Tree;
 
 use crate::analyzer::html::HtmlAngularJsAnalyzer;
+use crate::analyzer::html::ng_include::find_ng_include_path_at;
 use crate::analyzer::html::parser::HtmlParser;
 use crate::analyzer::html::EmbeddedScript;
-use crate::analyzer::js::AngularJsAnalyzer;
-use crate::cache::{CacheLoader, CacheWriter};
-use crate::config::{AjsConfig, DiagnosticsConfig, PathMatcher};
+use crate::analyzer::js::builtin_service_methods::{builtin_service_methods, is_service_injected_at};
+use crate::analyzer::js::{AngularJsAnalyzer, JsParser};
+use crate::analyzer::incremental::SyntaxTreeCache;
+use crate::cache::{resolve_cache_dir, CacheLoader, CacheWriter};
+use crate::config::{AjsConfig, CacheAutosave, DiagnosticsConfig, FileLimits, PathMatcher};
 use crate::handler::{
-    new_js_tree_cache, CodeLensHandler, CompletionHandler, DefinitionHandler,
-    DiagnosticsHandler, DocumentHighlightHandler, DocumentSymbolHandler, HoverHandler,
-    InlayHintsHandler, JsTreeCache, ReferencesHandler, RenameHandler,
-    SemanticTokensHandler, SignatureHelpHandler, WorkspaceSymbolHandler,
+    angularjs_completion_symbol, locate_symbol_at, CallHierarchyHandler, CodeActionHandler,
+    CodeLensHandler, CompletionHandler, DefinitionHandler, DiagnosticsHandler,
+    DocumentColorHandler, DocumentHighlightHandler, DocumentSymbolHandler, FoldingRangeHandler,
+    HoverHandler, InlayHintsHandler, LinkedEditingRangeHandler, LocatedSymbol,
+    OnTypeFormattingHandler, ReferencesHandler, RenameHandler, SelectionRangeHandler,
+    SemanticTokensHandler, SignatureHelpHandler, WorkspaceSymbolHandler, FIRST_TRIGGER_CHARACTER,
+    MORE_TRIGGER_CHARACTERS,
 };
 use crate::index::Index;
-use crate::ts_proxy::TsProxy;
-use crate::util::{is_html_file, is_js_file};
+use crate::model::AnalysisFailure;
+use crate::ts_proxy::{TsProxy, TsProxyEvent, TsServerOptions};
+use crate::util::{apply_content_changes, is_html_file, is_js_file, utf16_col_to_byte_col};
 
-use progress::{begin_progress, end_progress, report_progress};
-use workspace::{collect_file_metadata, collect_files, find_tsconfig_root, get_service_prefix_at_cursor};
+use progress::{
+    begin_cancellable_progress, begin_progress, end_progress, is_cancel_for, report_progress,
+    INDEXING_TOKEN,
+};
+use workspace::{
+    collect_file_metadata, collect_files, file_metadata, find_tsconfig_root,
+    get_member_chain_at_cursor, get_module_dependency_context, get_template_path_context,
+    is_definitions_only, list_template_path_entries, retain_files_within_limits, MemberChain,
+};
 
 pub struct Backend {
     client: Client,
@@ -41,8 +57,11 @@ pub struct Backend {
     /// `tokio::spawn` 内のデバウンス済みタスクからも参照する必要があるため Arc 化。
     ts_proxy: Arc<RwLock<Option<TsProxy>>>,
     documents: Arc<DashMap<Url, String>>,
-    ts_opened_files: DashMap<Url, bool>,
+    /// tsserver 再起動時にイベント処理タスクからクリアするため Arc 化。
+    ts_opened_files: Arc<DashMap<Url, bool>>,
     path_matcher: RwLock<Option<PathMatcher>>,
+    /// ajsconfig.json の `max_file_size_bytes` / `max_file_lines`
+    file_limits: RwLock<FileLimits>,
     diagnostics_config: Arc<RwLock<DiagnosticsConfig>>,
     debounce_versions: Arc<DashMap<Url, u64>>,
     /// URI ごとに「tsserver に最後に flush した debounce_versions の値」。
@@ -50,9 +69,22 @@ pub struct Backend {
     /// 待ちの did_change が積まれている)。`ensure_ts_synced` で同期済みかを
     /// 判定し、必要なら request 直前に flush する。
     ts_synced_versions: Arc<DashMap<Url, u64>>,
-    /// Inlay hint 用の JS Tree キャッシュ (URI -> 直近パース結果)。
+    /// JS の tree-sitter Tree キャッシュ (analyzer の差分パース用と共有、inlay hints で再利用)。
     /// `did_close` でエントリを破棄する。
-    inlay_hint_js_tree_cache: Arc<JsTreeCache>,
+    js_tree_cache: Arc<SyntaxTreeCache>,
+    /// 現在適用中の ajsconfig.json (変更検知時にどの設定が変わったかの判定用)
+    ajs_config: RwLock<AjsConfig>,
+    /// 保存されたファイル → 書き込み先の CacheWriter。デバウンス解析の完了後に
+    /// そのファイル分だけキャッシュを書き換える (`cache.autosave: "on_save"`)
+    pending_cache_saves: Arc<DashMap<Url, CacheWriter>>,
+    /// `angularjs-lsp.ignoreFolder` で除外したフォルダ (セッション内のみ保持)
+    ignored_folders: RwLock<Vec<PathBuf>>,
+    /// ワークスペーススキャンの進捗がクライアントからキャンセルされたか。
+    /// `scan_workspace` の開始時に下ろし、各 Phase の区切りと Phase 1 / 2 の
+    /// ファイルごとの解析ループで参照する
+    scan_cancelled: Arc<AtomicBool>,
+    /// クライアントが `workspace/didChangeWatchedFiles` の動的登録に対応しているか
+    watched_files_dynamic_registration: AtomicBool,
 }
 
 async fn publish_html_diagnostics(
@@ -77,12 +109,168 @@ async fn publish_js_diagnostics(
 ) {
     let config = diagnostics_config.read().await.clone();
     let handler = DiagnosticsHandler::new(Arc::clone(index), config);
-    let diagnostics = handler.diagnose_js(uri);
+    let mut diagnostics = handler.diagnose_js(uri);
+    // tsserver から中継された診断をマージする (publishDiagnostics は URI 単位で
+    // 全置換なので、片方だけ送ると他方が消える)
+    diagnostics.extend(filter_ts_diagnostics(
+        index,
+        index.diagnostics.get_ts_diagnostics(uri),
+    ));
     client
         .publish_diagnostics(uri.clone(), diagnostics, None)
         .await;
 }
 
+/// 解析の失敗を記録し、クライアントのログにも出す
+///
+/// パース失敗は analyzer が記録済みだが、panic はここで記録する。記録は次に解析が成功したとき
+/// (`analyze_document_incremental` 内) に消える。診断の再発行は呼び出し側で行う
+async fn report_analysis_failure(client: &Client, index: &Index, uri: &Url, failure: AnalysisFailure) {
+    tracing::warn!("AngularJS analysis skipped for {}: {}", uri, failure.reason());
+    index.diagnostics.set_analysis_failure(uri.clone(), failure);
+    client
+        .log_message(
+            MessageType::WARNING,
+            format!("AngularJS analysis skipped for {}: {}", uri, failure.reason()),
+        )
+        .await;
+}
+
+/// tsserver の診断のうち、AngularJS 側で解決済みのシンボルに対するものを除外する。
+///
+/// 除外対象: `Cannot find name 'X'` 系 (TS2304 / TS2552) で、`X` が `angular`
+/// グローバル、またはインデックスに定義のあるシンボル (service / controller 等) の場合。
+fn filter_ts_diagnostics(index: &Index, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics
+        .into_iter()
+        .filter(|d| {
+            let is_cannot_find_name = matches!(
+                d.code,
+                Some(NumberOrString::Number(2304)) | Some(NumberOrString::Number(2552))
+            );
+            if !is_cannot_find_name {
+                return true;
+            }
+            match d.message.split('\'').nth(1) {
+                Some(name) => name != "angular" && !index.definitions.has_definition(name),
+                None => true,
+            }
+        })
+        .collect()
+}
+
+/// 保存待ちのファイルならキャッシュのそのファイル分を書き換える
+///
+/// キャッシュがまだ作られていない (初回の全体保存前) 場合などは書き込まない
+fn save_pending_cache(pending_cache_saves: &DashMap<Url, CacheWriter>, index: &Index, uri: &Url) {
+    let Some((_, writer)) = pending_cache_saves.remove(uri) else {
+        return;
+    };
+    let Some(meta) = uri.to_file_path().ok().and_then(|path| file_metadata(&path)) else {
+        return;
+    };
+    if let Err(e) = writer.save_incremental(uri, index, &meta) {
+        tracing::debug!("Skipped incremental cache save for {}: {}", uri, e);
+    }
+}
+
+/// カーソル下が AngularJS のシンボルか ([`LocatedSymbol::is_angularjs`])
+///
+/// 自前のハンドラが結果を返さなくても、AngularJS のシンボル上では tsserver に
+/// フォールバックしない (DI 名や `$scope` のプロパティを tsserver は正しく解決できない)
+fn is_angularjs_symbol(located: Option<&LocatedSymbol>) -> bool {
+    located.is_some_and(|located| located.is_angularjs)
+}
+
+/// `cancelled` が立つまで `items` を順に `f` で処理する
+///
+/// スキャンのファイルごとの解析ループ用。キャンセルは次の要素に進む前に確認する
+fn for_each_until_cancelled<I: IntoIterator>(
+    items: I,
+    cancelled: &AtomicBool,
+    mut f: impl FnMut(I::Item),
+) {
+    for item in items {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        f(item);
+    }
+}
+
+/// `angularjs-lsp.ignoreFolder` の引数 (URI または パス) をフォルダの絶対パスにする
+///
+/// 相対パスはワークスペースルート基準
+fn resolve_folder_argument(arg: &str, root_path: Option<&Path>) -> Option<PathBuf> {
+    if let Some(uri) = Url::parse(arg).ok().filter(|uri| uri.scheme() == "file") {
+        return uri.to_file_path().ok();
+    }
+    let path = PathBuf::from(arg.trim_end_matches(['/', '\\']));
+    if path.is_absolute() {
+        return Some(path);
+    }
+    root_path.map(|root| root.join(path))
+}
+
+/// ワークスペーススキャン Phase 4 (HTML 参照収集) で進捗を報告する単位のファイル数
+const HTML_REFERENCE_BATCH_SIZE: usize = 200;
+
+/// tsserver の再起動を試みる最大回数
+const TS_RESTART_MAX_ATTEMPTS: u32 = 5;
+/// 再起動バックオフの初期待ち時間 (試行ごとに倍になる)
+const TS_RESTART_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+/// この時間以上動いていたプロキシが落ちた場合は試行回数をリセットする
+const TS_RESTART_STABLE_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// 試行回数が `attempts` のときの次の再起動までの待ち時間 (試行ごとに倍になる)。
+/// `TS_RESTART_MAX_ATTEMPTS` に達していれば `None`
+fn ts_restart_delay(attempts: u32) -> Option<std::time::Duration> {
+    (attempts < TS_RESTART_MAX_ATTEMPTS).then(|| TS_RESTART_BASE_DELAY * 2u32.pow(attempts))
+}
+
+/// `uptime` 動いたプロキシが落ちたときの試行回数。
+/// 十分長く動いていたなら別のクラッシュとみなして 0 に戻す
+fn ts_restart_attempts_after(attempts: u32, uptime: std::time::Duration) -> u32 {
+    if uptime >= TS_RESTART_STABLE_DURATION {
+        0
+    } else {
+        attempts
+    }
+}
+
+/// 指数バックオフで typescript-language-server を再起動し、`ts_proxy` を差し替える。
+///
+/// `attempts` は呼び出しをまたいで累積する試行回数。`TS_RESTART_MAX_ATTEMPTS` に
+/// 達しても起動できなければ `false` を返す。
+async fn restart_ts_proxy(
+    client: &Client,
+    ts_proxy: &Arc<RwLock<Option<TsProxy>>>,
+    ts_root_uri: Option<&Url>,
+    ts_options: &TsServerOptions,
+    events: &tokio::sync::mpsc::UnboundedSender<TsProxyEvent>,
+    attempts: &mut u32,
+) -> bool {
+    while let Some(delay) = ts_restart_delay(*attempts) {
+        *attempts += 1;
+        tokio::time::sleep(delay).await;
+
+        if let Some(proxy) = TsProxy::start(ts_root_uri, ts_options, events.clone()).await {
+            *ts_proxy.write().await = Some(proxy);
+            client
+                .log_message(
+                    MessageType::INFO,
+                    format!(
+                        "typescript-language-server restarted (attempt {})",
+                        attempts
+                    ),
+                )
+                .await;
+            return true;
+        }
+    }
+    false
+}
+
 async fn republish_all_js_diagnostics(
     client: &Client,
     index: &Arc<Index>,
@@ -120,6 +308,19 @@ enum CompletionDecision {
     FallbackToTsProxy,
 }
 
+/// `completion` の計算に必要な、ドキュメント/インデックス以外の環境情報。
+/// `spawn_blocking` に渡すため `Backend` の RwLock から clone して作る。
+struct CompletionEnv {
+    /// JS の構文木キャッシュ (組み込みサービスの DI 判定に使う)
+    js_tree_cache: Arc<SyntaxTreeCache>,
+    /// ワークスペースルート (テンプレートパス補完の基点)
+    workspace_root: Option<PathBuf>,
+    /// ajsconfig.json の include/exclude
+    path_matcher: Option<PathMatcher>,
+    /// クライアントが送ってきた trigger character
+    trigger_character: Option<String>,
+}
+
 /// `completion` の CPU-bound な計算 (HTML/JS の AngularJS 補完抽出) を行う。
 ///
 /// `spawn_blocking` 内で動かすため `&self` ではなく必要なものを Arc clone で
@@ -131,15 +332,80 @@ fn compute_completion_decision(
     uri: Url,
     line: u32,
     col: u32,
+    env: CompletionEnv,
 ) -> CompletionDecision {
+    let is_html = is_html_file(&uri);
+
+    // `col` は LSP の UTF-16 の列。テキストベースの文脈判定 (workspace.rs) には
+    // バイト列 (`byte_col` / `js_col`) を渡す
+    let byte_col = documents
+        .get(&uri)
+        .map_or(col, |doc| utf16_col_to_byte_col(doc.value(), line, col));
+
+    // HTML 内の <script> は script ローカルの位置で JS として補完する。
+    // インデックス (コントローラーのスコープなど) は解析時に line_offset を足した
+    // HTML 上の行で記録されているので、参照には元の `line` を使う
+    let embedded = documents.get(&uri).filter(|_| is_html).and_then(|doc| {
+        let cached_tree = html_analyzer.tree_for_source(&uri, doc.value());
+        HtmlAngularJsAnalyzer::script_at(doc.value(), cached_tree.as_ref(), line, col)
+    });
+    let in_template = is_html && embedded.is_none();
+    let (js_source, js_line, js_col) = match embedded {
+        Some((script, local_line, local_col)) => (Some(script.source), local_line, local_col),
+        None => (documents.get(&uri).map(|doc| doc.value().clone()), line, byte_col),
+    };
+
+    // Template path completion (`templateUrl: '<ここ>'` / `ng-include="'<ここ>'"`)
+    if let Some(typed) = js_source
+        .as_deref()
+        .and_then(|source| get_template_path_context(source, js_line, js_col, in_template))
+    {
+        let Some(root) = env.workspace_root.as_deref() else {
+            return CompletionDecision::NoResult;
+        };
+        // 現在ファイルからの相対 → ワークスペースルートからの順で探す
+        let mut base_dirs = Vec::new();
+        if let Some(dir) = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+        {
+            base_dirs.push(dir);
+        }
+        base_dirs.push(root.to_path_buf());
+
+        let entries =
+            list_template_path_entries(&base_dirs, &typed, root, env.path_matcher.as_ref());
+        // 置換範囲は LSP の UTF-16 の列で、入力中のファイル名部分
+        let file_part = &typed[typed.rfind('/').map_or(0, |pos| pos + 1)..];
+        let file_part_len = file_part.encode_utf16().count() as u32;
+        let replace_range = Range {
+            start: Position::new(line, col.saturating_sub(file_part_len)),
+            end: Position::new(line, col),
+        };
+        let handler = CompletionHandler::new(Arc::clone(&index));
+        return CompletionDecision::Resolved(CompletionResponse::Array(
+            handler.complete_template_paths(&entries, replace_range),
+        ));
+    }
+
+    // `/` はパス補完専用のトリガー。それ以外の位置では AngularJS 補完を出さない
+    if env.trigger_character.as_deref() == Some("/") {
+        return if in_template {
+            CompletionDecision::NoResult
+        } else {
+            CompletionDecision::FallbackToTsProxy
+        };
+    }
+
     // HTML file completion
-    if is_html_file(&uri) {
+    if in_template {
         if let Some(doc) = documents.get(&uri) {
             let source = doc.value();
 
             // Directive completion context
             if let Some((prefix, is_tag_name, element_tag_name)) =
-                html_analyzer.get_directive_completion_context_with_tag(source, line, col)
+                html_analyzer.get_directive_completion_context_with_tag(source, line, byte_col)
             {
                 let handler = CompletionHandler::new(Arc::clone(&index));
                 let mut items: Vec<CompletionItem> = Vec::new();
@@ -151,6 +417,13 @@ fn compute_completion_decision(
                     }
                 }
 
+                // 属性名位置 + `ng-` 入力中 → 組み込み ng-* 属性を提案
+                if let Some((ng_prefix, existing_attrs)) =
+                    html_analyzer.get_ng_attribute_completion_context(source, line, byte_col)
+                {
+                    items.extend(handler.complete_ng_attributes(&ng_prefix, &existing_attrs));
+                }
+
                 // 既存のディレクティブ補完（ng-* など）も併せて返す
                 if let Some(CompletionResponse::Array(directive_items)) =
                     handler.complete_directives(&prefix, is_tag_name)
@@ -169,10 +442,42 @@ fn compute_completion_decision(
                 }
             }
 
+            // Filter name completion (`{{ x | <ここ> }}`)
+            if html_analyzer.is_in_filter_position(source, line, byte_col) {
+                let handler = CompletionHandler::new(Arc::clone(&index));
+                return CompletionDecision::Resolved(CompletionResponse::Array(
+                    handler.complete_filters(),
+                ));
+            }
+
             // Angular context completion
-            if html_analyzer.is_in_angular_context(source, line, col) {
+            if html_analyzer.is_in_angular_context(source, line, byte_col) {
                 let handler = CompletionHandler::new(Arc::clone(&index));
-                let items = handler.complete_in_html_angular_context(&uri, line);
+
+                // `vm.user.` / `user.` / `myForm.email.` のメンバーチェーン → 子プロパティ
+                match get_member_chain_at_cursor(source, line, byte_col) {
+                    Some(MemberChain::Path(chain)) => {
+                        let items = handler.complete_html_member_chain(&uri, line, &chain);
+                        if !items.is_empty() {
+                            return CompletionDecision::Resolved(CompletionResponse::Array(items));
+                        }
+                    }
+                    // `vm.load().` / `items[0].` の型は分からない
+                    Some(MemberChain::Dynamic) => return CompletionDecision::NoResult,
+                    None => {}
+                }
+
+                // イベントディレクティブでは `$event` / `$index` を先頭に出す
+                let mut items = if html_analyzer.event_directive_at(source, line, col).is_some() {
+                    handler.complete_event_locals(&uri, line)
+                } else {
+                    Vec::new()
+                };
+                for item in handler.complete_in_html_angular_context(&uri, line) {
+                    if !items.iter().any(|existing| existing.label == item.label) {
+                        items.push(item);
+                    }
+                }
                 if !items.is_empty() {
                     return CompletionDecision::Resolved(CompletionResponse::Array(items));
                 }
@@ -181,20 +486,91 @@ fn compute_completion_decision(
         return CompletionDecision::NoResult;
     }
 
+    // Module dependency completion (`angular.module('app', ['<ここ>'])`)
+    if let Some((prefix, excluded)) = js_source
+        .as_deref()
+        .and_then(|source| get_module_dependency_context(source, js_line, js_col))
+    {
+        let handler = CompletionHandler::new(Arc::clone(&index));
+        return CompletionDecision::Resolved(CompletionResponse::Array(
+            handler.complete_module_dependencies(&prefix, &excluded),
+        ));
+    }
+
+    // HTML は tsserver に開かせないので、<script> 内で解決できなければ補完なし
+    let fallback = || {
+        if is_html {
+            CompletionDecision::NoResult
+        } else {
+            CompletionDecision::FallbackToTsProxy
+        }
+    };
+
     // JS file completion
-    let service_prefix = documents
-        .get(&uri)
-        .and_then(|doc| get_service_prefix_at_cursor(doc.value(), line, col));
+    let chain = js_source
+        .as_deref()
+        .and_then(|source| get_member_chain_at_cursor(source, js_line, js_col));
+    // `UserService.getAll().then(...)` の `.then` など、メソッドの戻り値や添字アクセスの
+    // メンバーは AngularJS 側で型を追えないので tsserver に任せる
+    if chain == Some(MemberChain::Dynamic) {
+        return fallback();
+    }
+
+    // `$injector.get('X')` を受けた変数はサービス名に読み替える
+    let service_prefix = match chain {
+        Some(MemberChain::Path(path)) => Some(path),
+        _ => None,
+    }
+    .map(|prefix| {
+        let (head, rest) = match prefix.split_once('.') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (prefix.as_str(), None),
+        };
+        match (index.controllers.resolve_service_alias(&uri, head, line), rest) {
+            (Some(service), Some(rest)) => format!("{}.{}", service, rest),
+            (Some(service), None) => service,
+            (None, _) => prefix,
+        }
+    });
+
+    let mut injected_services = index.controllers.get_injected_services_at(&uri, line);
+
+    // 組み込みサービス (`$http.`) は ControllerScope に記録されないので、
+    // カーソルを囲む関数の DI から注入済みか判定する
+    let injected_builtin = service_prefix
+        .as_deref()
+        .filter(|prefix| builtin_service_methods(prefix).is_some())
+        .filter(|prefix| {
+            let Some(source) = js_source.as_deref().filter(|_| !in_template) else {
+                return false;
+            };
+            // 埋め込み <script> の Tree はキャッシュしていないのでその場でパースする
+            let tree = if is_html {
+                JsParser::new().parse(source)
+            } else {
+                env.js_tree_cache
+                    .tree_for_source(&uri, source)
+                    .or_else(|| JsParser::for_uri(&uri).parse(source))
+            };
+            tree.is_some_and(|tree| is_service_injected_at(&tree, source, js_line, js_col, prefix))
+        });
+    if let Some(service) = injected_builtin {
+        injected_services.push(service.to_string());
+    }
 
     // Non-AngularJS object pattern -> fallback to TypeScript
+    // (`$scope.user` / `UserService.config` のようなチェーンは先頭で判定する)
     if let Some(ref prefix) = service_prefix {
-        if prefix != "$scope" && !index.definitions.is_service_or_factory(prefix) {
-            return CompletionDecision::FallbackToTsProxy;
+        let head = prefix.split('.').next().unwrap_or(prefix);
+        if head != "$scope"
+            && !index.definitions.is_service_or_factory(head)
+            && injected_builtin.is_none()
+        {
+            return fallback();
         }
     }
 
     let controller_name = index.controllers.get_controller_at(&uri, line);
-    let injected_services = index.controllers.get_injected_services_at(&uri, line);
 
     let handler = CompletionHandler::new(Arc::clone(&index));
     if let Some(completions) = handler.complete_with_context(
@@ -205,7 +581,7 @@ fn compute_completion_decision(
         return CompletionDecision::Resolved(completions);
     }
 
-    CompletionDecision::FallbackToTsProxy
+    fallback()
 }
 
 /// `pending_reanalysis` キューが空になるまでドレインし、各 URI を `analyze_one` で
@@ -510,6 +886,33 @@ fn collect_affected_html_uris(
     affected
 }
 
+/// ajsconfig.json の変更内容に応じて必要になる再処理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigChange {
+    /// 解析結果に影響しない (cache / tsserver / codelens / workspace_symbol 設定のみ、または変更なし)
+    None,
+    /// 診断設定のみ変わった: 開いているファイルの診断を再発行する
+    Diagnostics,
+    /// include/exclude・ファイルの上限・式評価属性が変わった: 解析対象・HTML の
+    /// 解析結果が変わるので index を作り直す
+    Rescan,
+}
+
+fn classify_config_change(previous: &AjsConfig, current: &AjsConfig) -> ConfigChange {
+    if previous.include != current.include
+        || previous.exclude != current.exclude
+        || previous.definitions_only != current.definitions_only
+        || previous.file_limits() != current.file_limits()
+        || previous.expression_attributes != current.expression_attributes
+    {
+        ConfigChange::Rescan
+    } else if previous.diagnostics != current.diagnostics {
+        ConfigChange::Diagnostics
+    } else {
+        ConfigChange::None
+    }
+}
+
 impl Backend {
     pub fn new(client: Client) -> Self {
         let index = Arc::new(Index::new());
@@ -519,6 +922,8 @@ impl Backend {
             Arc::clone(&analyzer),
         ));
 
+        let js_tree_cache = analyzer.tree_cache();
+
         Self {
             client,
             analyzer,
@@ -527,15 +932,110 @@ impl Backend {
             root_uri: RwLock::new(None),
             ts_proxy: Arc::new(RwLock::new(None)),
             documents: Arc::new(DashMap::new()),
-            ts_opened_files: DashMap::new(),
+            ts_opened_files: Arc::new(DashMap::new()),
             path_matcher: RwLock::new(None),
+            file_limits: RwLock::new(FileLimits::default()),
             diagnostics_config: Arc::new(RwLock::new(DiagnosticsConfig::default())),
             debounce_versions: Arc::new(DashMap::new()),
             ts_synced_versions: Arc::new(DashMap::new()),
-            inlay_hint_js_tree_cache: new_js_tree_cache(),
+            js_tree_cache,
+            ajs_config: RwLock::new(AjsConfig::default()),
+            pending_cache_saves: Arc::new(DashMap::new()),
+            ignored_folders: RwLock::new(Vec::new()),
+            scan_cancelled: Arc::new(AtomicBool::new(false)),
+            watched_files_dynamic_registration: AtomicBool::new(false),
         }
     }
 
+    /// typescript-language-server からのイベントを処理するタスクを起動する
+    ///
+    /// `textDocument/publishDiagnostics` は JS ファイルの分だけインデックスに保持し、
+    /// 開いているファイルなら AngularJS 側の診断とマージして再発行する。
+    /// `Exited` を受けたらプロキシを再起動する (`restart_ts_proxy` 参照)。
+    fn spawn_ts_event_handler(
+        &self,
+        mut events: tokio::sync::mpsc::UnboundedReceiver<TsProxyEvent>,
+        events_tx: tokio::sync::mpsc::UnboundedSender<TsProxyEvent>,
+        ts_root_uri: Option<Url>,
+        ts_options: TsServerOptions,
+    ) {
+        let client = self.client.clone();
+        let index = Arc::clone(&self.index);
+        let documents = Arc::clone(&self.documents);
+        let diagnostics_config = Arc::clone(&self.diagnostics_config);
+        let ts_proxy = Arc::clone(&self.ts_proxy);
+        let ts_opened_files = Arc::clone(&self.ts_opened_files);
+        let ts_synced_versions = Arc::clone(&self.ts_synced_versions);
+
+        tokio::spawn(async move {
+            let mut restart_attempts = 0u32;
+            let mut started_at = std::time::Instant::now();
+
+            while let Some(event) = events.recv().await {
+                match event {
+                    TsProxyEvent::Notification { method, params }
+                        if method == "textDocument/publishDiagnostics" =>
+                    {
+                        let Ok(params) = serde_json::from_value::<PublishDiagnosticsParams>(params)
+                        else {
+                            continue;
+                        };
+                        if !is_js_file(&params.uri) {
+                            continue;
+                        }
+                        index
+                            .diagnostics
+                            .set_ts_diagnostics(params.uri.clone(), params.diagnostics);
+                        if documents.contains_key(&params.uri) {
+                            publish_js_diagnostics(&client, &index, &diagnostics_config, &params.uri)
+                                .await;
+                        }
+                    }
+                    TsProxyEvent::Notification { .. } => {}
+                    TsProxyEvent::Exited => {
+                        // 落ちたプロキシは即座に外し、フォールバックを素通りさせる
+                        *ts_proxy.write().await = None;
+                        // 新しいプロセスには何も開かれていないので、次回アクセス時に
+                        // `ensure_ts_file_opened` で開き直させる
+                        ts_opened_files.clear();
+                        ts_synced_versions.clear();
+
+                        restart_attempts =
+                            ts_restart_attempts_after(restart_attempts, started_at.elapsed());
+
+                        let restarted = restart_ts_proxy(
+                            &client,
+                            &ts_proxy,
+                            ts_root_uri.as_ref(),
+                            &ts_options,
+                            &events_tx,
+                            &mut restart_attempts,
+                        )
+                        .await;
+                        if restarted {
+                            started_at = std::time::Instant::now();
+                        } else {
+                            tracing::warn!(
+                                "typescript-language-server could not be restarted; TypeScript fallback is disabled"
+                            );
+                            client
+                                .log_message(
+                                    MessageType::WARNING,
+                                    format!(
+                                        "typescript-language-server crashed and could not be restarted after {} attempts; TypeScript fallback is disabled",
+                                        TS_RESTART_MAX_ATTEMPTS
+                                    ),
+                                )
+                                .await;
+                            // フォールバック無効化後はイベント元のプロキシが存在しないのでタスクを終える
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
     async fn publish_diagnostics_for_html(&self, uri: &Url) {
         publish_html_diagnostics(&self.client, &self.index, &self.diagnostics_config, uri).await;
     }
@@ -582,16 +1082,28 @@ impl Backend {
         }
 
         // 1. buffer 内容で再解析 (CPU work は spawn_blocking)
+        let file_limits = *self.file_limits.read().await;
         for (uri, text) in &open_files {
+            if file_limits.exceeds(text) {
+                continue;
+            }
+            let ignored = match uri.to_file_path() {
+                Ok(path) => self.is_ignored_path(&path).await,
+                Err(_) => false,
+            };
+            if ignored {
+                continue;
+            }
             if is_html_file(uri) {
                 let analyzer = Arc::clone(&self.analyzer);
                 let html_analyzer = Arc::clone(&self.html_analyzer);
                 let index = Arc::clone(&self.index);
                 let bl_uri = uri.clone();
                 let bl_text = text.clone();
-                let _ = tokio::task::spawn_blocking(move || {
+                let result = tokio::task::spawn_blocking(move || {
                     let scripts = html_analyzer
-                        .analyze_document_and_extract_scripts(&bl_uri, &bl_text);
+                        .analyze_document_incremental(&bl_uri, &bl_text)
+                        .ok_or(AnalysisFailure::ParseFailed)?;
                     index.templates.mark_html_analyzed(&bl_uri);
                     for script in scripts {
                         analyzer.analyze_embedded_script(
@@ -600,16 +1112,30 @@ impl Backend {
                             script.line_offset,
                         );
                     }
+                    Ok(())
                 })
-                .await;
+                .await
+                .unwrap_or(Err(AnalysisFailure::Panicked));
+                if let Err(failure) = result {
+                    report_analysis_failure(&self.client, &self.index, uri, failure).await;
+                }
             } else if is_js_file(uri) {
                 let analyzer = Arc::clone(&self.analyzer);
+                let html_analyzer = Arc::clone(&self.html_analyzer);
                 let bl_uri = uri.clone();
                 let bl_text = text.clone();
-                let _ = tokio::task::spawn_blocking(move || {
-                    analyzer.analyze_document(&bl_uri, &bl_text);
+                let result = tokio::task::spawn_blocking(move || {
+                    if !analyzer.analyze_document_incremental(&bl_uri, &bl_text) {
+                        return Err(AnalysisFailure::ParseFailed);
+                    }
+                    html_analyzer.analyze_inline_templates(&bl_uri);
+                    Ok(())
                 })
-                .await;
+                .await
+                .unwrap_or(Err(AnalysisFailure::Panicked));
+                if let Err(failure) = result {
+                    report_analysis_failure(&self.client, &self.index, uri, failure).await;
+                }
             }
         }
 
@@ -634,9 +1160,222 @@ impl Backend {
         tokio::join!(diagnostics, refresh_signals);
     }
 
+    /// ajsconfig.json の診断設定・include/exclude・式評価属性を反映する
+    async fn apply_config(&self, config: &AjsConfig) {
+        *self.diagnostics_config.write().await = config.diagnostics.clone();
+        *self.file_limits.write().await = config.file_limits();
+        self.index.html.set_expression_attributes(
+            config
+                .expression_attributes
+                .iter()
+                .map(|attr| (attr.name().to_string(), attr.mode())),
+        );
+
+        match config.create_path_matcher() {
+            Ok(matcher) => {
+                *self.path_matcher.write().await = Some(matcher);
+            }
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Invalid path patterns: {}", e),
+                    )
+                    .await;
+            }
+        }
+
+        *self.ajs_config.write().await = config.clone();
+    }
+
+    /// `cache_dir` / `cache.compression` / 解析設定のハッシュを反映した CacheWriter
+    async fn cache_writer(&self, root_path: &Path) -> CacheWriter {
+        let config = self.ajs_config.read().await;
+        let cache_dir = resolve_cache_dir(root_path, config.cache_dir.as_deref());
+        CacheWriter::with_dir(cache_dir)
+            .with_compression(config.cache.compression())
+            .with_config_hash(config.analysis_settings_hash())
+    }
+
+    /// `cache_dir` / 解析設定のハッシュを反映した CacheLoader
+    async fn cache_loader(&self, root_path: &Path) -> CacheLoader {
+        let config = self.ajs_config.read().await;
+        CacheLoader::with_dir(resolve_cache_dir(root_path, config.cache_dir.as_deref()))
+            .with_config_hash(config.analysis_settings_hash())
+    }
+
+    /// `ajsconfig.json` の変更監視を `client/registerCapability` で登録する
+    async fn register_config_watcher(&self) {
+        if !self.watched_files_dynamic_registration.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/ajsconfig.json".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "angularjs-lsp/ajsconfig-watcher".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Failed to watch ajsconfig.json: {}", e),
+                )
+                .await;
+        }
+    }
+
+    /// ajsconfig.json を読み直して、変わった設定に応じて再解析・診断再発行する
+    /// (判定は [`classify_config_change`])
+    ///
+    /// interpolate 記号は JS の `$interpolateProvider` から解決するため、
+    /// 記号の変更は JS ファイルの再解析で HTML の再解析が走る (設定のリロードは不要)。
+    async fn reload_config(&self) {
+        let Some(root_path) = self
+            .root_uri
+            .read()
+            .await
+            .as_ref()
+            .and_then(|uri| uri.to_file_path().ok())
+        else {
+            return;
+        };
+
+        let config = AjsConfig::load_from_dir(&root_path);
+        let previous = self.ajs_config.read().await.clone();
+        if config == previous {
+            return;
+        }
+
+        self.client
+            .log_message(MessageType::INFO, "ajsconfig.json changed, reloading")
+            .await;
+        self.apply_config(&config).await;
+
+        match classify_config_change(&previous, &config) {
+            ConfigChange::Rescan => {
+                self.index.clear_all();
+                self.scan_workspace().await;
+                self.republish_open_files_after_init().await;
+            }
+            ConfigChange::Diagnostics => {
+                let open_files: Vec<Url> =
+                    self.documents.iter().map(|e| e.key().clone()).collect();
+                for uri in &open_files {
+                    if is_html_file(uri) {
+                        self.publish_diagnostics_for_html(uri).await;
+                    } else if is_js_file(uri) {
+                        self.publish_diagnostics_for_js(uri).await;
+                    }
+                }
+            }
+            ConfigChange::None => {}
+        }
+
+        if config.codelens != previous.codelens {
+            let _ = self.client.code_lens_refresh().await;
+        }
+    }
+
+    /// フォルダを除外リストに加え、配下のファイルをインデックスから外す
+    ///
+    /// 戻り値はインデックスから外したファイル数
+    async fn ignore_folder(&self, folder: PathBuf, root_path: Option<&Path>) -> usize {
+        {
+            let mut ignored_folders = self.ignored_folders.write().await;
+            if !ignored_folders.contains(&folder) {
+                ignored_folders.push(folder.clone());
+            }
+        }
+
+        // スキャン対象になり得るファイル (ディスク上) と開いているファイル
+        let mut files = HashMap::new();
+        let path_matcher = self.path_matcher.read().await;
+        collect_file_metadata(
+            &folder,
+            root_path.unwrap_or(&folder),
+            path_matcher.as_ref(),
+            &mut files,
+        );
+        drop(path_matcher);
+        let mut uris: HashSet<Url> = files
+            .keys()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .collect();
+        let open_uris: Vec<Url> = self
+            .documents
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|uri| uri.to_file_path().is_ok_and(|path| path.starts_with(&folder)))
+            .collect();
+        uris.extend(open_uris);
+
+        for uri in &uris {
+            self.index.clear_document(uri);
+            self.analyzer.forget_tree(uri);
+            self.html_analyzer.forget_tree(uri);
+        }
+
+        // 除外したファイルの定義を参照していた他ファイルの診断・トークンを更新する
+        self.republish_open_files_after_init().await;
+        uris.len()
+    }
+
+    /// `angularjs-lsp.ignoreFolder` で除外したフォルダ配下のパスか
+    async fn is_ignored_path(&self, path: &Path) -> bool {
+        self.ignored_folders
+            .read()
+            .await
+            .iter()
+            .any(|folder| path.starts_with(folder))
+    }
+
+    /// 除外フォルダ配下のドキュメントは解析せず、以前の解析結果も破棄する
+    async fn skip_ignored_document(&self, uri: &Url) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return false;
+        };
+        if !self.is_ignored_path(&path).await {
+            return false;
+        }
+        self.index.clear_document(uri);
+        self.analyzer.forget_tree(uri);
+        self.html_analyzer.forget_tree(uri);
+        true
+    }
+
+    /// 上限 (`max_file_size_bytes` / `max_file_lines`) を超える開いたドキュメントは
+    /// 自前の解析をスキップし、JS なら tsserver へのフォールバックのみ行う。
+    /// 上限を超えた時点で以前の解析結果は破棄する。
+    async fn skip_oversized_document(&self, uri: &Url, text: &str) -> bool {
+        if !self.file_limits.read().await.exceeds(text) {
+            return false;
+        }
+        self.index.clear_document(uri);
+        self.analyzer.forget_tree(uri);
+        self.html_analyzer.forget_tree(uri);
+        true
+    }
+
     async fn on_change(&self, uri: Url, text: String) {
         self.documents.insert(uri.clone(), text.clone());
 
+        if self.skip_ignored_document(&uri).await
+            || self.skip_oversized_document(&uri, &text).await
+        {
+            // tsserver へはデバウンスせず、リクエスト直前の `ensure_ts_synced` で同期する
+            if is_js_file(&uri) {
+                *self.debounce_versions.entry(uri.clone()).or_insert(0) += 1;
+            }
+            return;
+        }
+
         if is_html_file(&uri) {
             // Increment version counter for debounce
             let ver = {
@@ -653,6 +1392,7 @@ impl Backend {
             let documents = Arc::clone(&self.documents);
             let diagnostics_config = Arc::clone(&self.diagnostics_config);
             let debounce_versions = Arc::clone(&self.debounce_versions);
+            let pending_cache_saves = Arc::clone(&self.pending_cache_saves);
             let spawn_uri = uri.clone();
 
             tokio::spawn(async move {
@@ -673,7 +1413,7 @@ impl Backend {
 
                 // Run CPU-intensive analysis on the blocking thread pool
                 //
-                // 戻り値: Some((before_snapshot, after_snapshot))
+                // 戻り値: Some(Ok((before_snapshot, after_snapshot)))
                 //   解析前後の HtmlChangeSnapshot ペアを返す。各種 cross-file
                 //   dependency (HTML scope refs / 埋め込み defs/refs / ng-include /
                 //   ng-controller / 埋め込み template_bindings / component templateUrl)
@@ -688,8 +1428,11 @@ impl Backend {
                     // before スナップショット: 解析後に clear されてしまうので先に取得
                     let before = HtmlChangeSnapshot::capture(&bl_index, &bl_uri);
 
-                    let scripts = bl_html_analyzer
-                        .analyze_document_and_extract_scripts(&bl_uri, &latest_text);
+                    let Some(scripts) = bl_html_analyzer
+                        .analyze_document_incremental(&bl_uri, &latest_text)
+                    else {
+                        return Some(Err(AnalysisFailure::ParseFailed));
+                    };
                     bl_index.templates.mark_html_analyzed(&bl_uri);
                     for script in scripts {
                         bl_analyzer.analyze_embedded_script(
@@ -714,13 +1457,19 @@ impl Backend {
                     // after スナップショット
                     let after = HtmlChangeSnapshot::capture(&bl_index, &bl_uri);
 
-                    Some((before, after))
+                    save_pending_cache(&pending_cache_saves, &bl_index, &bl_uri);
+
+                    Some(Ok((before, after)))
                 })
                 .await
-                .ok()
-                .flatten();
+                .unwrap_or(Some(Err(AnalysisFailure::Panicked)));
+
+                if let Some(Err(failure)) = analysis_result {
+                    report_analysis_failure(&client, &index, &uri, failure).await;
+                    publish_html_diagnostics(&client, &index, &diagnostics_config, &uri).await;
+                }
 
-                if let Some((before, after)) = analysis_result {
+                if let Some(Ok((before, after))) = analysis_result {
                     publish_html_diagnostics(&client, &index, &diagnostics_config, &uri).await;
 
                     // この HTML 変更で診断結果が変わり得る開いている JS だけ
@@ -763,12 +1512,14 @@ impl Backend {
 
             let client = self.client.clone();
             let analyzer = Arc::clone(&self.analyzer);
+            let html_analyzer = Arc::clone(&self.html_analyzer);
             let index = Arc::clone(&self.index);
             let documents = Arc::clone(&self.documents);
             let diagnostics_config = Arc::clone(&self.diagnostics_config);
             let debounce_versions = Arc::clone(&self.debounce_versions);
             let ts_proxy = Arc::clone(&self.ts_proxy);
             let ts_synced_versions = Arc::clone(&self.ts_synced_versions);
+            let pending_cache_saves = Arc::clone(&self.pending_cache_saves);
             let spawn_uri = uri.clone();
 
             tokio::spawn(async move {
@@ -781,10 +1532,11 @@ impl Backend {
 
                 let bl_uri = uri.clone();
                 let bl_analyzer = Arc::clone(&analyzer);
+                let bl_html_analyzer = Arc::clone(&html_analyzer);
                 let bl_documents = Arc::clone(&documents);
                 let bl_index = Arc::clone(&index);
 
-                // 戻り値: Some((before_snapshot, after_snapshot))
+                // 戻り値: Some(Ok((before_snapshot, after_snapshot)))
                 //   解析前後の JsChangeSnapshot ペア。defined symbols /
                 //   template_bindings / component templateUrl の cross-file dep を
                 //   全部捕捉する。
@@ -796,15 +1548,19 @@ impl Backend {
 
                     let before = JsChangeSnapshot::capture(&bl_index, &bl_uri);
 
-                    bl_analyzer.analyze_document(&bl_uri, &latest_text);
+                    if !bl_analyzer.analyze_document_incremental(&bl_uri, &latest_text) {
+                        return Some(Err(AnalysisFailure::ParseFailed));
+                    }
+                    bl_html_analyzer.analyze_inline_templates(&bl_uri);
 
                     let after = JsChangeSnapshot::capture(&bl_index, &bl_uri);
 
-                    Some((before, after))
+                    save_pending_cache(&pending_cache_saves, &bl_index, &bl_uri);
+
+                    Some(Ok((before, after)))
                 })
                 .await
-                .ok()
-                .flatten();
+                .unwrap_or(Some(Err(AnalysisFailure::Panicked)));
 
                 // tsserver にも debounce 後に最新テキストを送る (毎キーストローク
                 // の IPC 送信を避ける)。`debounce_versions` の version check で
@@ -828,7 +1584,12 @@ impl Backend {
                     }
                 }
 
-                if let Some((before, after)) = analysis_result {
+                if let Some(Err(failure)) = analysis_result {
+                    report_analysis_failure(&client, &index, &uri, failure).await;
+                    publish_js_diagnostics(&client, &index, &diagnostics_config, &uri).await;
+                }
+
+                if let Some(Ok((before, after))) = analysis_result {
                     publish_js_diagnostics(&client, &index, &diagnostics_config, &uri).await;
 
                     // この JS の変更で診断結果が変わり得る HTML ファイルを特定して
@@ -861,7 +1622,20 @@ impl Backend {
     async fn on_open(&self, uri: Url, text: String) {
         self.documents.insert(uri.clone(), text.clone());
 
-        if is_html_file(&uri) {
+        if self.skip_ignored_document(&uri).await {
+            self.debounce_versions.insert(uri.clone(), 0);
+        } else if self.skip_oversized_document(&uri, &text).await {
+            self.debounce_versions.insert(uri.clone(), 0);
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!(
+                        "Skipping analysis of {}: exceeds max_file_size_bytes / max_file_lines",
+                        uri
+                    ),
+                )
+                .await;
+        } else if is_html_file(&uri) {
             self.debounce_versions.insert(uri.clone(), 0);
 
             let bl_uri = uri.clone();
@@ -870,10 +1644,16 @@ impl Backend {
             let bl_index = Arc::clone(&self.index);
             let bl_documents = Arc::clone(&self.documents);
             let bl_text = text.clone();
-            tokio::task::spawn_blocking(move || {
-                let scripts =
-                    bl_html_analyzer.analyze_document_and_extract_scripts(&bl_uri, &bl_text);
+            let result = tokio::task::spawn_blocking(move || {
+                let scripts = bl_html_analyzer
+                    .analyze_document_incremental(&bl_uri, &bl_text)
+                    .ok_or(AnalysisFailure::ParseFailed)?;
                 bl_index.templates.mark_html_analyzed(&bl_uri);
+                // スキャン後に新規作成されたテンプレートもファイル一覧に加える
+                // (未スキャン時は一覧自体を持たないので、存在判定を有効化しない)
+                if bl_index.templates.has_workspace_files() {
+                    bl_index.templates.add_workspace_file(&bl_uri);
+                }
                 for script in scripts {
                     bl_analyzer
                         .analyze_embedded_script(&bl_uri, &script.source, script.line_offset);
@@ -886,9 +1666,13 @@ impl Backend {
                         bl_html_analyzer.analyze_document(child_uri, doc.value());
                     }
                 });
+                Ok(())
             })
             .await
-            .unwrap_or(());
+            .unwrap_or(Err(AnalysisFailure::Panicked));
+            if let Err(failure) = result {
+                report_analysis_failure(&self.client, &self.index, &uri, failure).await;
+            }
 
             // 解析完了後、診断発行と semantic_tokens / code_lens の refresh signal を
             // 並列に走らせる。診断発行は IPC + (republish 内では) CPU 解析を含むので
@@ -912,12 +1696,20 @@ impl Backend {
 
             let bl_uri = uri.clone();
             let bl_analyzer = Arc::clone(&self.analyzer);
+            let bl_html_analyzer = Arc::clone(&self.html_analyzer);
             let bl_text = text.clone();
-            tokio::task::spawn_blocking(move || {
-                bl_analyzer.analyze_document(&bl_uri, &bl_text);
+            let result = tokio::task::spawn_blocking(move || {
+                if !bl_analyzer.analyze_document_incremental(&bl_uri, &bl_text) {
+                    return Err(AnalysisFailure::ParseFailed);
+                }
+                bl_html_analyzer.analyze_inline_templates(&bl_uri);
+                Ok(())
             })
             .await
-            .unwrap_or(());
+            .unwrap_or(Err(AnalysisFailure::Panicked));
+            if let Err(failure) = result {
+                report_analysis_failure(&self.client, &self.index, &uri, failure).await;
+            }
 
             // (HTML側と同じ理由で並列化) refresh signal と診断発行は互いに独立
             let refresh_signals = async {
@@ -991,7 +1783,63 @@ impl Backend {
         });
     }
 
-    async fn scan_workspace(&self) {
+    /// `window/workDoneProgress/cancel` 通知 (tower-lsp の `LanguageServer` に
+    /// 未定義のため `custom_method` で登録する)
+    pub async fn work_done_progress_cancel(&self, params: WorkDoneProgressCancelParams) {
+        if is_cancel_for(&params, INDEXING_TOKEN) {
+            self.scan_cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 保留中の通知を処理させてから、スキャンのキャンセル要求を確認する
+    ///
+    /// スキャンは通知ハンドラ内で同期的に進むため、明示的に譲らないと
+    /// キャンセル通知が処理されない
+    async fn scan_cancel_requested(&self) -> bool {
+        tokio::task::yield_now().await;
+        self.scan_cancelled.load(Ordering::Relaxed)
+    }
+
+    /// キャンセルされたスキャンの進捗を終了する。解析済みの分はインデックスに残す
+    async fn end_cancelled_scan(&self, token: &NumberOrString, phase: &str) {
+        self.client
+            .log_message(
+                MessageType::INFO,
+                format!("Workspace scan cancelled during {}; keeping partial index", phase),
+            )
+            .await;
+        self.republish_diagnostics_for_open_js_files().await;
+        end_progress(
+            &self.client,
+            token,
+            format!("Indexing cancelled during {} (partial index)", phase),
+        )
+        .await;
+    }
+
+    /// 解析スレッドが panic したスキャンの進捗を終了する。解析済みの分はインデックスに残す
+    async fn end_failed_scan(&self, token: &NumberOrString, phase: &str) {
+        self.client
+            .log_message(
+                MessageType::WARNING,
+                format!("Workspace scan aborted: analysis panicked during {}", phase),
+            )
+            .await;
+        self.republish_diagnostics_for_open_js_files().await;
+        end_progress(
+            &self.client,
+            token,
+            format!("Indexing failed during {} (partial index)", phase),
+        )
+        .await;
+    }
+
+    /// ワークスペース全体をスキャンする
+    ///
+    /// 進捗がクライアントからキャンセルされた場合は `false` を返す。その時点までの
+    /// インデックスは保持されるが不完全なので、呼び出し側はキャッシュに保存しないこと
+    async fn scan_workspace(&self) -> bool {
+        self.scan_cancelled.store(false, Ordering::Relaxed);
         let root_uri = self.root_uri.read().await;
         let path_matcher = self.path_matcher.read().await;
         if let Some(ref uri) = *root_uri {
@@ -1000,30 +1848,75 @@ impl Backend {
                     .log_message(MessageType::INFO, format!("Scanning workspace: {:?}", path))
                     .await;
 
-                let token = begin_progress(
+                let token = begin_cancellable_progress(
                     &self.client,
-                    "angularjs-indexing",
+                    INDEXING_TOKEN,
                     "Indexing AngularJS",
                     Some("Collecting files...".to_string()),
                 )
                 .await;
 
+                let file_limits = *self.file_limits.read().await;
+
+                // angularjs-lsp.ignoreFolder で除外したフォルダ配下は解析しない
+                let ignored_folders = self.ignored_folders.read().await.clone();
+                let is_ignored = |uri: &Url| {
+                    uri.to_file_path()
+                        .is_ok_and(|p| ignored_folders.iter().any(|folder| p.starts_with(folder)))
+                };
+
                 // Collect JS files
                 let mut js_files: Vec<(Url, String)> = Vec::new();
-                collect_files(&path, &path, path_matcher.as_ref(), &["js"], &mut js_files);
+                let mut skipped_count = collect_files(
+                    &path,
+                    &path,
+                    path_matcher.as_ref(),
+                    &file_limits,
+                    &["js", "ts", "tsx"],
+                    &mut js_files,
+                );
+                js_files.retain(|(uri, _)| !is_ignored(uri));
                 let js_count = js_files.len();
 
                 // Collect HTML files
                 let mut html_files: Vec<(Url, String)> = Vec::new();
-                collect_files(
+                skipped_count += collect_files(
                     &path,
                     &path,
                     path_matcher.as_ref(),
+                    &file_limits,
                     &["html", "htm"],
                     &mut html_files,
                 );
+                html_files.retain(|(uri, _)| !is_ignored(uri));
                 let html_count = html_files.len();
 
+                if skipped_count > 0 {
+                    self.client
+                        .log_message(
+                            MessageType::INFO,
+                            format!(
+                                "Skipped {} files exceeding max_file_size_bytes / max_file_lines",
+                                skipped_count
+                            ),
+                        )
+                        .await;
+                }
+
+                // 存在しないテンプレートファイルの診断用にファイル一覧を保持
+                for (uri, _) in &html_files {
+                    self.index.templates.add_workspace_file(uri);
+                }
+
+                // definitions_only にマッチするファイルは参照を収集しない
+                let definitions_only: HashSet<Url> = js_files
+                    .iter()
+                    .chain(&html_files)
+                    .map(|(uri, _)| uri)
+                    .filter(|uri| is_definitions_only(&path, path_matcher.as_ref(), uri))
+                    .cloned()
+                    .collect();
+
                 // Extract embedded scripts from HTML
                 let html_scripts: Vec<(Url, Vec<EmbeddedScript>)> = html_files
                     .iter()
@@ -1038,16 +1931,20 @@ impl Backend {
 
                 // Pre-parse HTML files (HtmlParser is !Send, must be done on one thread)
                 let mut parser = HtmlParser::new();
-                let parsed_html_files: Vec<_> = html_files
-                    .iter()
+                let parsed_html_files: Vec<(Url, String, Tree)> = html_files
+                    .into_iter()
                     .filter_map(|(uri, content)| {
-                        parser
-                            .parse(content)
-                            .map(|tree| (uri, content.as_str(), tree))
+                        let tree = parser.parse(&content)?;
+                        Some((uri, content, tree))
                     })
                     .collect();
                 let parsed_count = parsed_html_files.len();
 
+                if self.scan_cancel_requested().await {
+                    self.end_cancelled_scan(&token, "file collection").await;
+                    return false;
+                }
+
                 // Phase 1: JS Pass 1 (definitions) ∥ HTML Pass 1 (ng-controller)
                 report_progress(
                     &self.client,
@@ -1057,40 +1954,81 @@ impl Backend {
                 )
                 .await;
 
-                std::thread::scope(|s| {
-                    s.spawn(|| {
-                        // JS Pass 1: definitions
-                        for (uri, content) in js_files.iter() {
-                            self.analyzer
-                                .analyze_document_with_options(uri, content, true);
-                        }
-                        for (uri, scripts) in html_scripts.iter() {
-                            let mut first = true;
-                            for script in scripts {
-                                if first {
-                                    self.index.clear_document(uri);
-                                    first = false;
-                                }
-                                self.analyzer.analyze_embedded_script(
-                                    uri,
-                                    &script.source,
-                                    script.line_offset,
-                                );
+                // Phase 1 / 2 は spawn_blocking で動かし、その間もキャンセル通知を
+                // 処理できるようにする。各ワーカーはファイルごとに scan_cancelled を確認する
+                let js_files = Arc::new(js_files);
+                let html_scripts = Arc::new(html_scripts);
+                let parsed_html_files = Arc::new(parsed_html_files);
+                let definitions_only = Arc::new(definitions_only);
+
+                let phase1 = {
+                    let analyzer = Arc::clone(&self.analyzer);
+                    let html_analyzer = Arc::clone(&self.html_analyzer);
+                    let index = Arc::clone(&self.index);
+                    let cancelled = Arc::clone(&self.scan_cancelled);
+                    let js_files = Arc::clone(&js_files);
+                    let html_scripts = Arc::clone(&html_scripts);
+                    let parsed_html_files = Arc::clone(&parsed_html_files);
+                    let definitions_only = Arc::clone(&definitions_only);
+                    tokio::task::spawn_blocking(move || {
+                        // definitions_only のファイルは参照を記録せずに解析する
+                        let analyze = |uri: &Url, f: &dyn Fn()| {
+                            if definitions_only.contains(uri) {
+                                index.definitions.without_references(uri, f);
+                            } else {
+                                f();
                             }
-                        }
-                    });
-                    s.spawn(|| {
-                        // HTML Pass 1: ng-controller scopes
-                        for (uri, content, tree) in &parsed_html_files {
-                            self.html_analyzer
-                                .collect_controller_scopes_only_with_tree(uri, content, tree);
-                        }
+                        };
+                        let analyze_js = |(uri, content): &(Url, String)| {
+                            analyze(uri, &|| {
+                                analyzer.analyze_document_with_options(uri, content, true);
+                            });
+                        };
+                        let analyze_scripts = |(uri, scripts): &(Url, Vec<EmbeddedScript>)| {
+                            index.clear_document(uri);
+                            analyze(uri, &|| {
+                                for script in scripts {
+                                    analyzer.analyze_embedded_script(
+                                        uri,
+                                        &script.source,
+                                        script.line_offset,
+                                    );
+                                }
+                            });
+                        };
                         // 全HTMLファイルを解析済みとして登録
-                        for (uri, _content, _tree) in &parsed_html_files {
-                            self.index.templates.mark_html_analyzed(uri);
-                        }
-                    });
-                });
+                        let collect_scopes = |(uri, content, tree): &(Url, String, Tree)| {
+                            analyze(uri, &|| {
+                                html_analyzer
+                                    .collect_controller_scopes_only_with_tree(uri, content, tree);
+                            });
+                            index.templates.mark_html_analyzed(uri);
+                        };
+                        std::thread::scope(|s| {
+                            s.spawn(|| {
+                                // JS Pass 1: definitions
+                                for_each_until_cancelled(js_files.iter(), &cancelled, analyze_js);
+                                for_each_until_cancelled(
+                                    html_scripts.iter(),
+                                    &cancelled,
+                                    analyze_scripts,
+                                );
+                            });
+                            s.spawn(|| {
+                                // HTML Pass 1: ng-controller scopes
+                                for_each_until_cancelled(
+                                    parsed_html_files.iter(),
+                                    &cancelled,
+                                    collect_scopes,
+                                );
+                            });
+                        });
+                    })
+                };
+                if phase1.await.is_err() {
+                    self.end_failed_scan(&token, "Phase 1").await;
+                    return false;
+                }
 
                 report_progress(
                     &self.client,
@@ -1100,6 +2038,11 @@ impl Backend {
                 )
                 .await;
 
+                if self.scan_cancel_requested().await {
+                    self.end_cancelled_scan(&token, "Phase 1").await;
+                    return false;
+                }
+
                 // Phase 2: JS Pass 2 (references) ∥ HTML Pass 1.5 (ng-include)
                 report_progress(
                     &self.client,
@@ -1109,31 +2052,57 @@ impl Backend {
                 )
                 .await;
 
-                std::thread::scope(|s| {
-                    s.spawn(|| {
-                        // JS Pass 2: references
-                        for (uri, content) in js_files.iter() {
-                            self.analyzer
-                                .analyze_document_with_options(uri, content, false);
-                        }
-                        for (uri, scripts) in html_scripts.iter() {
+                let phase2 = {
+                    let analyzer = Arc::clone(&self.analyzer);
+                    let html_analyzer = Arc::clone(&self.html_analyzer);
+                    let cancelled = Arc::clone(&self.scan_cancelled);
+                    let js_files = Arc::clone(&js_files);
+                    let html_scripts = Arc::clone(&html_scripts);
+                    let parsed_html_files = Arc::clone(&parsed_html_files);
+                    let definitions_only = Arc::clone(&definitions_only);
+                    tokio::task::spawn_blocking(move || {
+                        let analyze_js = |(uri, content): &(Url, String)| {
+                            analyzer.analyze_document_with_options(uri, content, false);
+                        };
+                        let analyze_scripts = |(uri, scripts): &(Url, Vec<EmbeddedScript>)| {
                             for script in scripts {
-                                self.analyzer.analyze_embedded_script(
+                                analyzer.analyze_embedded_script(
                                     uri,
                                     &script.source,
                                     script.line_offset,
                                 );
                             }
-                        }
-                    });
-                    s.spawn(|| {
-                        // HTML Pass 1.5: ng-include bindings
-                        for (uri, content, tree) in &parsed_html_files {
-                            self.html_analyzer
-                                .collect_ng_include_bindings_with_tree(uri, content, tree);
-                        }
-                    });
-                });
+                        };
+                        let collect_includes = |(uri, content, tree): &(Url, String, Tree)| {
+                            html_analyzer.collect_ng_include_bindings_with_tree(uri, content, tree);
+                        };
+                        std::thread::scope(|s| {
+                            s.spawn(|| {
+                                // JS Pass 2: references
+                                let js_files = js_files
+                                    .iter()
+                                    .filter(|(uri, _)| !definitions_only.contains(uri));
+                                for_each_until_cancelled(js_files, &cancelled, analyze_js);
+                                let html_scripts = html_scripts
+                                    .iter()
+                                    .filter(|(uri, _)| !definitions_only.contains(uri));
+                                for_each_until_cancelled(html_scripts, &cancelled, analyze_scripts);
+                            });
+                            s.spawn(|| {
+                                // HTML Pass 1.5: ng-include bindings
+                                for_each_until_cancelled(
+                                    parsed_html_files.iter(),
+                                    &cancelled,
+                                    collect_includes,
+                                );
+                            });
+                        });
+                    })
+                };
+                if phase2.await.is_err() {
+                    self.end_failed_scan(&token, "Phase 2").await;
+                    return false;
+                }
 
                 report_progress(
                     &self.client,
@@ -1143,6 +2112,11 @@ impl Backend {
                 )
                 .await;
 
+                if self.scan_cancel_requested().await {
+                    self.end_cancelled_scan(&token, "Phase 2").await;
+                    return false;
+                }
+
                 self.client
                     .log_message(
                         MessageType::INFO,
@@ -1180,6 +2154,10 @@ impl Backend {
                             pct,
                         )
                         .await;
+                        if self.scan_cancel_requested().await {
+                            self.end_cancelled_scan(&token, "Phase 3").await;
+                            return false;
+                        }
                     }
                 }
 
@@ -1192,25 +2170,41 @@ impl Backend {
                 )
                 .await;
 
-                for (i, (uri, content, tree)) in parsed_html_files.iter().enumerate() {
+                // ファイル単位で独立しているので並列に処理する。進捗は
+                // バッチごとに処理済みカウンタを読んで報告する
+                let reference_files: Vec<_> = parsed_html_files
+                    .iter()
+                    .filter(|(uri, _, _)| !definitions_only.contains(uri))
+                    .map(|(uri, content, tree)| (uri, content.as_str(), tree.clone()))
+                    .collect();
+                let reference_count = reference_files.len();
+                let processed = AtomicUsize::new(0);
+                for batch in reference_files.chunks(HTML_REFERENCE_BATCH_SIZE) {
                     self.html_analyzer
-                        .analyze_document_references_only_with_tree(uri, content, tree);
-                    if i % 10 == 0 || i == parsed_count - 1 {
-                        let pct = 90 + ((i + 1) * 10 / parsed_count.max(1)) as u32;
-                        report_progress(
-                            &self.client,
-                            &token,
-                            format!(
-                                "Phase 4: HTML references ({}/{} files)",
-                                i + 1,
-                                parsed_count
-                            ),
-                            pct,
-                        )
-                        .await;
+                        .analyze_references_parallel(batch, &processed);
+                    let done = processed.load(Ordering::Relaxed);
+                    let pct = 90 + (done * 10 / reference_count.max(1)) as u32;
+                    report_progress(
+                        &self.client,
+                        &token,
+                        format!("Phase 4: HTML references ({}/{} files)", done, reference_count),
+                        pct,
+                    )
+                    .await;
+                    if self.scan_cancel_requested().await {
+                        self.end_cancelled_scan(&token, "Phase 4").await;
+                        return false;
                     }
                 }
 
+                // JS 内のインラインテンプレートも HTML と同じく全定義が揃ってから解析する
+                for (uri, _) in js_files
+                    .iter()
+                    .filter(|(uri, _)| !definitions_only.contains(uri))
+                {
+                    self.html_analyzer.analyze_inline_templates(uri);
+                }
+
                 self.client
                     .log_message(
                         MessageType::INFO,
@@ -1228,32 +2222,94 @@ impl Backend {
                 .await;
             }
         }
+        true
+    }
+
+    /// `files` のうち `definitions_only` にマッチするファイル
+    async fn definitions_only_files(&self, files: &[PathBuf]) -> HashSet<PathBuf> {
+        let root_path = self
+            .root_uri
+            .read()
+            .await
+            .as_ref()
+            .and_then(|uri| uri.to_file_path().ok());
+        let path_matcher = self.path_matcher.read().await;
+        let Some(root_path) = root_path else {
+            return HashSet::new();
+        };
+        files
+            .iter()
+            .filter(|path| {
+                Url::from_file_path(path).is_ok_and(|uri| {
+                    is_definitions_only(&root_path, path_matcher.as_ref(), &uri)
+                })
+            })
+            .cloned()
+            .collect()
     }
 
     async fn scan_js_files_only(&self, files: &[PathBuf]) {
+        let file_limits = *self.file_limits.read().await;
+        let definitions_only = self.definitions_only_files(files).await;
+        let mut skipped_count = 0;
+        let mut inline_template_uris = Vec::new();
+
         for file_path in files {
             if let Ok(uri) = Url::from_file_path(file_path) {
                 if let Ok(content) = fs::read_to_string(file_path) {
-                    if is_js_file(&uri) {
-                        self.analyzer.analyze_document(&uri, &content);
-                    } else if is_html_file(&uri) {
-                        let scripts = self
-                            .html_analyzer
-                            .analyze_document_and_extract_scripts(&uri, &content);
-                        for script in scripts {
-                            self.analyzer.analyze_embedded_script(
-                                &uri,
-                                &script.source,
-                                script.line_offset,
-                            );
+                    if file_limits.exceeds(&content) {
+                        skipped_count += 1;
+                    } else {
+                        // HTML 自体の解析は scan_html_files_only で行う
+                        let analyze = || {
+                            if is_js_file(&uri) {
+                                self.analyzer.analyze_document(&uri, &content);
+                            } else if is_html_file(&uri) {
+                                self.index.clear_document(&uri);
+                                for script in HtmlAngularJsAnalyzer::extract_scripts(&content) {
+                                    self.analyzer.analyze_embedded_script(
+                                        &uri,
+                                        &script.source,
+                                        script.line_offset,
+                                    );
+                                }
+                            }
+                        };
+                        // definitions_only のファイルは参照を記録せずに解析する
+                        if definitions_only.contains(file_path) {
+                            self.index.definitions.without_references(&uri, analyze);
+                        } else {
+                            analyze();
+                            if is_js_file(&uri) {
+                                inline_template_uris.push(uri);
+                            }
                         }
                     }
                 }
             }
         }
+
+        // JS 内のインラインテンプレートは全ファイルの定義が揃ってから解析する
+        for uri in &inline_template_uris {
+            self.html_analyzer.analyze_inline_templates(uri);
+        }
+
+        if skipped_count > 0 {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!(
+                        "Skipped {} changed files exceeding max_file_size_bytes / max_file_lines",
+                        skipped_count
+                    ),
+                )
+                .await;
+        }
     }
 
     async fn scan_html_files_only(&self, files: &[PathBuf]) {
+        let file_limits = *self.file_limits.read().await;
+        let definitions_only = self.definitions_only_files(files).await;
         let mut parser = HtmlParser::new();
         let mut html_files: Vec<(Url, String)> = Vec::new();
 
@@ -1261,7 +2317,10 @@ impl Backend {
             if let Ok(uri) = Url::from_file_path(file_path) {
                 if is_html_file(&uri) {
                     if let Ok(content) = fs::read_to_string(file_path) {
-                        html_files.push((uri, content));
+                        // 上限超過のファイル数は scan_js_files_only でログ済み
+                        if !file_limits.exceeds(&content) {
+                            html_files.push((uri, content));
+                        }
                     }
                 }
             }
@@ -1271,6 +2330,10 @@ impl Backend {
             return;
         }
 
+        let is_definitions_only_uri = |uri: &Url| {
+            uri.to_file_path()
+                .is_ok_and(|path| definitions_only.contains(&path))
+        };
         let parsed_html_files: Vec<_> = html_files
             .iter()
             .filter_map(|(uri, content)| {
@@ -1284,8 +2347,15 @@ impl Backend {
         std::thread::scope(|s| {
             s.spawn(|| {
                 for (uri, content, tree) in &parsed_html_files {
-                    self.html_analyzer
-                        .collect_controller_scopes_only_with_tree(uri, content, tree);
+                    let collect = || {
+                        self.html_analyzer
+                            .collect_controller_scopes_only_with_tree(uri, content, tree);
+                    };
+                    if is_definitions_only_uri(uri) {
+                        self.index.definitions.without_references(uri, collect);
+                    } else {
+                        collect();
+                    }
                 }
                 // 全HTMLファイルを解析済みとして登録
                 for (uri, _content, _tree) in &parsed_html_files {
@@ -1309,8 +2379,11 @@ impl Backend {
                 .collect_form_bindings_only_with_tree(uri, content, tree);
         }
 
-        // Pass 3
-        for (uri, content, tree) in &parsed_html_files {
+        // Pass 3 (definitions_only のファイルは参照を収集しない)
+        for (uri, content, tree) in parsed_html_files
+            .iter()
+            .filter(|(uri, _, _)| !is_definitions_only_uri(uri))
+        {
             self.html_analyzer
                 .analyze_document_references_only_with_tree(uri, content, tree);
         }
@@ -1332,6 +2405,16 @@ impl LanguageServer for Backend {
 
         *self.root_uri.write().await = root;
 
+        let watched_files_dynamic_registration = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.did_change_watched_files.as_ref())
+            .and_then(|d| d.dynamic_registration)
+            .unwrap_or(false);
+        self.watched_files_dynamic_registration
+            .store(watched_files_dynamic_registration, Ordering::Relaxed);
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: "angularjs-lsp".to_string(),
@@ -1339,18 +2422,31 @@ impl LanguageServer for Backend {
             }),
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Left(true)),
                 document_highlight_provider: Some(OneOf::Left(true)),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(true)),
+                color_provider: Some(ColorProviderCapability::Simple(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: FIRST_TRIGGER_CHARACTER.to_string(),
+                    more_trigger_character: Some(
+                        MORE_TRIGGER_CHARACTERS.iter().map(|c| c.to_string()).collect(),
+                    ),
+                }),
                 definition_provider: Some(OneOf::Left(true)),
                 completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string()]),
+                    trigger_characters: Some(vec![".".to_string(), "/".to_string()]),
+                    resolve_provider: Some(true),
                     ..Default::default()
                 }),
                 signature_help_provider: Some(SignatureHelpOptions {
-                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string(), ":".to_string()]),
                     retrigger_characters: None,
                     work_done_progress_options: Default::default(),
                 }),
@@ -1375,7 +2471,14 @@ impl LanguageServer for Backend {
                 ),
                 inlay_hint_provider: Some(OneOf::Left(true)),
                 execute_command_provider: Some(ExecuteCommandOptions {
-                    commands: vec!["angularjs-lsp.refreshIndex".to_string()],
+                    commands: vec![
+                        "angularjs-lsp.refreshIndex".to_string(),
+                        "angularjs-lsp.gotoTemplateController".to_string(),
+                        "angularjs-lsp.ignoreFolder".to_string(),
+                        "angularjs-lsp.showModuleGraph".to_string(),
+                        "angularjs-lsp.showStatus".to_string(),
+                        "angularjs-lsp.dumpSymbol".to_string(),
+                    ],
                     work_done_progress_options: Default::default(),
                 }),
                 ..Default::default()
@@ -1394,15 +2497,20 @@ impl LanguageServer for Backend {
         // Load ajsconfig.json
         let root_uri = self.root_uri.read().await.clone();
         let mut cache_enabled = false;
+        let mut ts_options = TsServerOptions::default();
 
         if let Some(ref uri) = root_uri {
             if let Ok(path) = uri.to_file_path() {
                 let config = AjsConfig::load_from_dir(&path);
-                cache_enabled = config.cache;
+                cache_enabled = config.cache.enabled();
+                ts_options = TsServerOptions {
+                    path: config.tsserver_path.clone(),
+                    args: config.tsserver_args.clone(),
+                };
 
                 // interpolate 記号は JS の `$interpolateProvider.startSymbol/endSymbol`
                 // から動的に解決する (ajsconfig.json 経由の設定経路は撤廃済み)。
-                *self.diagnostics_config.write().await = config.diagnostics.clone();
+                self.apply_config(&config).await;
 
                 if !config.include.is_empty() {
                     self.client
@@ -1424,23 +2532,11 @@ impl LanguageServer for Backend {
                         .log_message(MessageType::INFO, "Cache enabled")
                         .await;
                 }
-
-                match config.create_path_matcher() {
-                    Ok(matcher) => {
-                        *self.path_matcher.write().await = Some(matcher);
-                    }
-                    Err(e) => {
-                        self.client
-                            .log_message(
-                                MessageType::ERROR,
-                                format!("Invalid path patterns: {}", e),
-                            )
-                            .await;
-                    }
-                }
             }
         }
 
+        self.register_config_watcher().await;
+
         // Start typescript-language-server
         let ts_root_uri = find_tsconfig_root(&root_uri).or(root_uri.clone());
 
@@ -1453,7 +2549,16 @@ impl LanguageServer for Backend {
                 .await;
         }
 
-        if let Some(proxy) = TsProxy::start(ts_root_uri.as_ref()).await {
+        let (ts_events_tx, ts_events_rx) = tokio::sync::mpsc::unbounded_channel();
+        self.spawn_ts_event_handler(
+            ts_events_rx,
+            ts_events_tx.clone(),
+            ts_root_uri.clone(),
+            ts_options.clone(),
+        );
+
+        if let Some(proxy) = TsProxy::start(ts_root_uri.as_ref(), &ts_options, ts_events_tx).await
+        {
             *self.ts_proxy.write().await = Some(proxy);
             self.client
                 .log_message(
@@ -1465,7 +2570,7 @@ impl LanguageServer for Backend {
             self.client
                 .log_message(
                     MessageType::WARNING,
-                    "typescript-language-server not found, fallback disabled",
+                    "typescript-language-server could not be started, fallback disabled (set \"tsserver_path\" in ajsconfig.json)",
                 )
                 .await;
         }
@@ -1482,8 +2587,32 @@ impl LanguageServer for Backend {
                         path_matcher.as_ref(),
                         &mut file_metadata,
                     );
+                    // 上限を超えるファイルはキャッシュがあっても読み込まない
+                    let file_limits = *self.file_limits.read().await;
+                    let skipped_count = retain_files_within_limits(&mut file_metadata, &file_limits);
+                    if skipped_count > 0 {
+                        self.client
+                            .log_message(
+                                MessageType::INFO,
+                                format!(
+                                    "Skipped {} files exceeding max_file_size_bytes / max_file_lines",
+                                    skipped_count
+                                ),
+                            )
+                            .await;
+                    }
+
+                    // 存在しないテンプレートファイルの診断用にファイル一覧を保持
+                    // (キャッシュ読み込み失敗時の scan_workspace でも再登録される)
+                    let html_uris = file_metadata
+                        .keys()
+                        .filter(|p| p.extension().is_some_and(|e| e == "html" || e == "htm"))
+                        .filter_map(|p| Url::from_file_path(p).ok());
+                    for uri in html_uris {
+                        self.index.templates.add_workspace_file(&uri);
+                    }
 
-                    let loader = CacheLoader::new(&root_path);
+                    let loader = self.cache_loader(&root_path).await;
                     let files_for_validation: Vec<_> = file_metadata
                         .iter()
                         .map(|(p, m)| (p.clone(), m.mtime, m.size))
@@ -1531,7 +2660,24 @@ impl LanguageServer for Backend {
                                         )
                                         .await;
                                     drop(path_matcher);
-                                    self.scan_workspace().await;
+                                    // 壊れた / 古いスキーマのキャッシュは作り直す
+                                    // (SchemaMismatch 等で途中まで読めたデータも破棄する)
+                                    self.index.clear_all();
+                                    // キャンセルされたスキャンの不完全なインデックスはキャッシュに保存しない
+                                    if self.scan_workspace().await {
+                                        let writer = self.cache_writer(&root_path).await;
+                                        if let Err(e) = writer
+                                            .save_full(&self.index, &file_metadata)
+                                            .map_err(|e| e.to_string())
+                                        {
+                                            self.client
+                                                .log_message(
+                                                    MessageType::WARNING,
+                                                    format!("Cache save failed: {}", e),
+                                                )
+                                                .await;
+                                        }
+                                    }
                                 } else if !validation.invalid_files.is_empty() {
                                     let invalid_files: Vec<_> =
                                         validation.invalid_files.into_iter().collect();
@@ -1585,7 +2731,7 @@ impl LanguageServer for Backend {
                                     )
                                     .await;
 
-                                    let writer = CacheWriter::new(&root_path);
+                                    let writer = self.cache_writer(&root_path).await;
                                     if let Err(e) = writer
                                         .save_full(&self.index, &file_metadata)
                                         .map_err(|e| e.to_string())
@@ -1647,9 +2793,41 @@ impl LanguageServer for Backend {
                                 }
                             } else {
                                 drop(path_matcher);
-                                self.scan_workspace().await;
-
-                                let writer = CacheWriter::new(&root_path);
+                                // キャンセルされたスキャンの不完全なインデックスはキャッシュに保存しない
+                                if self.scan_workspace().await {
+                                    let writer = self.cache_writer(&root_path).await;
+                                    if let Err(e) = writer
+                                        .save_full(&self.index, &file_metadata)
+                                        .map_err(|e| e.to_string())
+                                    {
+                                        self.client
+                                            .log_message(
+                                                MessageType::WARNING,
+                                                format!("Cache save failed: {}", e),
+                                            )
+                                            .await;
+                                    } else {
+                                        self.client
+                                            .log_message(MessageType::INFO, "Cache saved")
+                                            .await;
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            self.client
+                                .log_message(
+                                    MessageType::INFO,
+                                    format!(
+                                        "Cache not available: {:?}, performing full scan",
+                                        e
+                                    ),
+                                )
+                                .await;
+                            drop(path_matcher);
+                            // キャンセルされたスキャンの不完全なインデックスはキャッシュに保存しない
+                            if self.scan_workspace().await {
+                                let writer = self.cache_writer(&root_path).await;
                                 if let Err(e) = writer
                                     .save_full(&self.index, &file_metadata)
                                     .map_err(|e| e.to_string())
@@ -1667,36 +2845,6 @@ impl LanguageServer for Backend {
                                 }
                             }
                         }
-                        Err(e) => {
-                            self.client
-                                .log_message(
-                                    MessageType::INFO,
-                                    format!(
-                                        "Cache not available: {:?}, performing full scan",
-                                        e
-                                    ),
-                                )
-                                .await;
-                            drop(path_matcher);
-                            self.scan_workspace().await;
-
-                            let writer = CacheWriter::new(&root_path);
-                            if let Err(e) = writer
-                                .save_full(&self.index, &file_metadata)
-                                .map_err(|e| e.to_string())
-                            {
-                                self.client
-                                    .log_message(
-                                        MessageType::WARNING,
-                                        format!("Cache save failed: {}", e),
-                                    )
-                                    .await;
-                            } else {
-                                self.client
-                                    .log_message(MessageType::INFO, "Cache saved")
-                                    .await;
-                            }
-                        }
                     }
                 } else {
                     self.scan_workspace().await;
@@ -1725,9 +2873,9 @@ impl LanguageServer for Backend {
                     .await;
 
                 self.index.clear_all();
-                self.scan_workspace().await;
+                let completed = self.scan_workspace().await;
 
-                // Save cache
+                // Save cache (キャンセルされたスキャンの不完全なインデックスは保存しない)
                 if let Some(ref uri) = *self.root_uri.read().await {
                     if let Ok(root_path) = uri.to_file_path() {
                         let config_path = root_path.join("ajsconfig.json");
@@ -1735,13 +2883,13 @@ impl LanguageServer for Backend {
                             fs::read_to_string(&config_path)
                                 .ok()
                                 .and_then(|s| serde_json::from_str::<AjsConfig>(&s).ok())
-                                .map(|c| c.cache)
+                                .map(|c| c.cache.enabled())
                                 .unwrap_or(true)
                         } else {
                             true
                         };
 
-                        if cache_enabled {
+                        if cache_enabled && completed {
                             let path_matcher = self.path_matcher.read().await;
                             let mut file_metadata = HashMap::new();
                             collect_file_metadata(
@@ -1750,8 +2898,14 @@ impl LanguageServer for Backend {
                                 path_matcher.as_ref(),
                                 &mut file_metadata,
                             );
-
-                            let cache_writer = CacheWriter::new(&root_path);
+                            // 除外フォルダ配下はキャッシュ上も未解析として扱う
+                            let ignored_folders = self.ignored_folders.read().await;
+                            file_metadata.retain(|path, _| {
+                                !ignored_folders.iter().any(|folder| path.starts_with(folder))
+                            });
+                            drop(ignored_folders);
+
+                            let cache_writer = self.cache_writer(&root_path).await;
                             if let Err(e) = cache_writer
                                 .save_full(&self.index, &file_metadata)
                                 .map_err(|e| e.to_string())
@@ -1775,7 +2929,95 @@ impl LanguageServer for Backend {
                     .log_message(MessageType::INFO, "AngularJS index refreshed")
                     .await;
 
-                Ok(Some(serde_json::json!({ "success": true })))
+                Ok(Some(serde_json::json!({ "success": true, "cancelled": !completed })))
+            }
+            // 引数: [テンプレート HTML の URI]。バインドされたコントローラー定義の
+            // Location 配列を返す (クライアント側でジャンプ先を選ぶ)
+            "angularjs-lsp.gotoTemplateController" => {
+                let Some(uri) = params
+                    .arguments
+                    .first()
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Url::parse(s).ok())
+                else {
+                    return Ok(None);
+                };
+                let locations = match DefinitionHandler::new(Arc::clone(&self.index))
+                    .goto_template_controllers(&uri)
+                {
+                    Some(GotoDefinitionResponse::Array(locations)) => locations,
+                    _ => Vec::new(),
+                };
+                Ok(serde_json::to_value(locations).ok())
+            }
+            // 引数: [フォルダの URI またはワークスペースルートからの相対パス]。
+            // 配下のファイルをインデックスから外し、以後の解析・スキャンでも除外する
+            "angularjs-lsp.ignoreFolder" => {
+                let Some(arg) = params.arguments.first().and_then(|v| v.as_str()) else {
+                    return Ok(None);
+                };
+                let root_path = self
+                    .root_uri
+                    .read()
+                    .await
+                    .as_ref()
+                    .and_then(|uri| uri.to_file_path().ok());
+                let Some(folder) = resolve_folder_argument(arg, root_path.as_deref()) else {
+                    return Ok(None);
+                };
+                let cleared = self.ignore_folder(folder.clone(), root_path.as_deref()).await;
+
+                self.client
+                    .log_message(
+                        MessageType::INFO,
+                        format!("Ignoring {:?} ({} files removed from index)", folder, cleared),
+                    )
+                    .await;
+                Ok(Some(serde_json::json!({ "success": true, "cleared": cleared })))
+            }
+            "angularjs-lsp.showModuleGraph" => {
+                // 可視化はクライアント側で行うので、グラフデータだけを返す
+                let graph = self.index.module_graph();
+                Ok(serde_json::to_value(graph).ok())
+            }
+            // デバッグ・バグ報告用にインデックスの件数とキャッシュ / tsserver の状態を返す
+            "angularjs-lsp.showStatus" => {
+                let index_status = self.index.status();
+                let root_path = self
+                    .root_uri
+                    .read()
+                    .await
+                    .as_ref()
+                    .and_then(|uri| uri.to_file_path().ok());
+                let cache = match root_path {
+                    Some(root_path) => {
+                        let loader = self.cache_loader(&root_path).await;
+                        serde_json::json!({
+                            "dir": loader.cache_dir(),
+                            "exists": loader.exists(),
+                        })
+                    }
+                    None => serde_json::Value::Null,
+                };
+                let tsserver_running = self.ts_proxy.read().await.is_some();
+                Ok(Some(serde_json::json!({
+                    "index": index_status,
+                    "cache": cache,
+                    "tsserver": { "running": tsserver_running },
+                })))
+            }
+            // 引数: [シンボル名 (`MainCtrl` / `MainCtrl.$scope.user` など)]。
+            // 全定義・全参照と参照位置のコントローラーを返す
+            "angularjs-lsp.dumpSymbol" => {
+                let Some(name) = params.arguments.first().and_then(|v| v.as_str()) else {
+                    return Ok(None);
+                };
+                let index = Arc::clone(&self.index);
+                let name = name.to_string();
+                let dump = tokio::task::spawn_blocking(move || index.dump_symbol(&name))
+                    .await
+                    .ok();
+                Ok(dump.and_then(|dump| serde_json::to_value(dump).ok()))
             }
             _ => {
                 self.client
@@ -1798,7 +3040,7 @@ impl LanguageServer for Backend {
                     fs::read_to_string(&config_path)
                         .ok()
                         .and_then(|s| serde_json::from_str::<AjsConfig>(&s).ok())
-                        .map(|c| c.cache)
+                        .map(|c| c.cache.enabled())
                         .unwrap_or(true)
                 } else {
                     true
@@ -1813,8 +3055,14 @@ impl LanguageServer for Backend {
                         path_matcher.as_ref(),
                         &mut file_metadata,
                     );
+                    // 除外フォルダ配下はキャッシュ上も未解析として扱う
+                    let ignored_folders = self.ignored_folders.read().await;
+                    file_metadata.retain(|path, _| {
+                        !ignored_folders.iter().any(|folder| path.starts_with(folder))
+                    });
+                    drop(ignored_folders);
 
-                    let writer = CacheWriter::new(&root_path);
+                    let writer = self.cache_writer(&root_path).await;
                     if let Err(e) = writer.save_full(&self.index, &file_metadata) {
                         tracing::warn!("Failed to save cache on shutdown: {}", e);
                     } else {
@@ -1831,81 +3079,325 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        // ワークスペース直下の ajsconfig.json だけを対象にする
+        let Some(config_uri) = self
+            .root_uri
+            .read()
+            .await
+            .as_ref()
+            .and_then(|uri| uri.to_file_path().ok())
+            .and_then(|root| Url::from_file_path(root.join("ajsconfig.json")).ok())
+        else {
+            return;
+        };
+
+        if params.changes.iter().any(|change| change.uri == config_uri) {
+            self.reload_config().await;
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let uri = params.text_document.uri;
         let text = params.text_document.text;
         self.on_open(uri, text).await;
     }
 
-    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let current = self.documents.get(&uri).map(|doc| doc.value().clone());
+        // 未オープンのドキュメントには差分を当てられないので、全文の変更から始まる場合だけ受け付ける
+        let base = match current {
+            Some(current) => current,
+            None if params.content_changes.first().is_some_and(|c| c.range.is_none()) => String::new(),
+            None => return,
+        };
+        let text = apply_content_changes(&base, &params.content_changes);
+        self.on_change(uri, text).await;
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+
+        // 保存内容の解析完了後にそのファイル分だけキャッシュを更新する。
+        // デバウンス中の解析があり得るので、ここで再解析を予約してその完了時に書き込む
+        let cache = self.ajs_config.read().await.cache.clone();
+        let root_path = self
+            .root_uri
+            .read()
+            .await
+            .as_ref()
+            .and_then(|root| root.to_file_path().ok());
+        let ignored = match uri.to_file_path() {
+            Ok(path) => self.is_ignored_path(&path).await,
+            Err(_) => false,
+        };
+        let autosave_root = root_path.filter(|_| {
+            cache.enabled() && cache.autosave() == CacheAutosave::OnSave && !ignored
+        });
+
+        let text = match autosave_root {
+            Some(root_path) => {
+                let writer = self.cache_writer(&root_path).await;
+                self.pending_cache_saves.insert(uri.clone(), writer);
+                params
+                    .text
+                    .or_else(|| self.documents.get(&uri).map(|doc| doc.value().clone()))
+            }
+            None => params.text,
+        };
+        let Some(text) = text else {
+            self.pending_cache_saves.remove(&uri);
+            return;
+        };
+        self.on_change(uri, text).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = &params.text_document.uri;
+        // 実際に tsserver に開いたファイルだけを close する
+        // (HTML 等、tsserver に渡していないファイルに did_close を送る必要はない)
+        if self.ts_opened_files.remove(uri).is_some() {
+            if let Some(ref proxy) = *self.ts_proxy.read().await {
+                proxy.did_close(uri).await;
+            }
+        }
+        // 閉じられたファイルの sync 状態は不要、エントリを削除する
+        // (再 open 時に ensure_ts_file_opened が新しく初期化する)
+        self.ts_synced_versions.remove(uri);
+        // 閉じたファイルの tsserver 診断は次に開いたとき再送されるので破棄する
+        self.index.diagnostics.remove_ts_diagnostics(uri);
+        // 差分パース / inlay hint 用の Tree キャッシュも閉じたファイル分は破棄
+        // (再 open 時のソースは別物の可能性があり、また長期蓄積を避ける)
+        self.analyzer.forget_tree(uri);
+        self.html_analyzer.forget_tree(uri);
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri.clone();
+        let position = params.text_document_position.position;
+        let index = Arc::clone(&self.index);
+        let params_for_blocking = params.clone();
+        let blocking_uri = uri.clone();
+        let (local_refs, is_angularjs) = tokio::task::spawn_blocking(move || {
+            let located =
+                locate_symbol_at(&index, &blocking_uri, position.line, position.character, None);
+            let refs = ReferencesHandler::new(Arc::clone(&index))
+                .find_references_with_located(params_for_blocking, located.as_ref());
+            (refs, is_angularjs_symbol(located.as_ref()))
+        })
+        .await
+        .unwrap_or_default();
+        if let Some(refs) = local_refs {
+            return Ok(Some(refs));
+        }
+        if is_angularjs {
+            return Ok(None);
+        }
+
+        self.ensure_ts_file_opened(&uri).await;
+        self.ensure_ts_synced(&uri).await;
+        if let Some(ref proxy) = *self.ts_proxy.read().await {
+            return Ok(proxy.references(&params).await);
+        }
+
+        Ok(None)
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let index = Arc::clone(&self.index);
+        let params_for_blocking = params.clone();
+        let local = tokio::task::spawn_blocking(move || {
+            DocumentHighlightHandler::new(index).document_highlight(params_for_blocking)
+        })
+        .await
+        .ok()
+        .flatten();
+        // tsserver にフォールバックしない: documentHighlight は同ファイル内の
+        // 即時ハイライト用途で、AngularJS 固有のシンボルが解決できなかった場合は
+        // クライアント側のデフォルト挙動 (なし or text-based highlight) に任せる。
+        Ok(local)
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let uri = params.text_document.uri;
+        let source = match self.documents.get(&uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(None),
+        };
+
+        // デバウンス解析で更新された Tree をソースが一致する限り再利用する
+        let cached_tree = if is_html_file(&uri) {
+            self.html_analyzer.tree_for_source(&uri, &source)
+        } else {
+            self.js_tree_cache.tree_for_source(&uri, &source)
+        };
+        let index = Arc::clone(&self.index);
+        Ok(tokio::task::spawn_blocking(move || {
+            SelectionRangeHandler::new(index).selection_ranges(
+                &uri,
+                &source,
+                &params.positions,
+                cached_tree,
+            )
+        })
+        .await
+        .ok()
+        .flatten())
+    }
+
+    async fn folding_range(
+        &self,
+        params: FoldingRangeParams,
+    ) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri;
+        let source = match self.documents.get(&uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(None),
+        };
+
+        // デバウンス解析で更新された Tree をソースが一致する限り再利用する
+        let cached_tree = if is_html_file(&uri) {
+            self.html_analyzer.tree_for_source(&uri, &source)
+        } else {
+            self.js_tree_cache.tree_for_source(&uri, &source)
+        };
+        Ok(tokio::task::spawn_blocking(move || {
+            FoldingRangeHandler::new().folding_ranges(&uri, &source, cached_tree)
+        })
+        .await
+        .ok()
+        .flatten())
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let source = match self.documents.get(&uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(None),
+        };
+
+        let cached_tree = self.html_analyzer.tree_for_source(&uri, &source);
+        Ok(tokio::task::spawn_blocking(move || {
+            LinkedEditingRangeHandler::new().linked_editing_ranges(&uri, &source, position, cached_tree)
+        })
+        .await
+        .ok()
+        .flatten())
+    }
+
+    async fn document_color(&self, params: DocumentColorParams) -> Result<Vec<ColorInformation>> {
         let uri = params.text_document.uri;
-        if let Some(change) = params.content_changes.into_iter().next() {
-            self.on_change(uri, change.text).await;
-        }
+        let source = match self.documents.get(&uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(Vec::new()),
+        };
+
+        let cached_tree = self.html_analyzer.tree_for_source(&uri, &source);
+        Ok(tokio::task::spawn_blocking(move || {
+            DocumentColorHandler::new().document_colors(&uri, &source, cached_tree)
+        })
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default())
     }
 
-    async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        if let Some(text) = params.text {
-            self.on_change(params.text_document.uri, text).await;
-        }
+    async fn color_presentation(
+        &self,
+        params: ColorPresentationParams,
+    ) -> Result<Vec<ColorPresentation>> {
+        let source = match self.documents.get(&params.text_document.uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(Vec::new()),
+        };
+        Ok(DocumentColorHandler::new().color_presentations(&source, params.color, params.range))
     }
 
-    async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let uri = &params.text_document.uri;
-        // 実際に tsserver に開いたファイルだけを close する
-        // (HTML 等、tsserver に渡していないファイルに did_close を送る必要はない)
-        if self.ts_opened_files.remove(uri).is_some() {
-            if let Some(ref proxy) = *self.ts_proxy.read().await {
-                proxy.did_close(uri).await;
-            }
-        }
-        // 閉じられたファイルの sync 状態は不要、エントリを削除する
-        // (再 open 時に ensure_ts_file_opened が新しく初期化する)
-        self.ts_synced_versions.remove(uri);
-        // Inlay hint Tree キャッシュも閉じたファイル分は破棄 (再 open 時の
-        // ソースは別物の可能性があり、また長期蓄積を避ける)
-        self.inlay_hint_js_tree_cache.remove(uri);
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let source = match self.documents.get(&uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(None),
+        };
+
+        Ok(OnTypeFormattingHandler::new(Arc::clone(&self.index)).on_type_formatting(
+            &uri,
+            &source,
+            position,
+            &params.ch,
+        ))
     }
 
-    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-        let uri = params.text_document_position.text_document.uri.clone();
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
         let index = Arc::clone(&self.index);
-        let params_for_blocking = params.clone();
-        let local_refs = tokio::task::spawn_blocking(move || {
-            ReferencesHandler::new(index).find_references(params_for_blocking)
+        let documents = Arc::clone(&self.documents);
+        let tree_cache = Arc::clone(&self.js_tree_cache);
+        Ok(tokio::task::spawn_blocking(move || {
+            CallHierarchyHandler::new(index, documents, tree_cache).prepare(params)
         })
         .await
         .ok()
-        .flatten();
-        if let Some(refs) = local_refs {
-            return Ok(Some(refs));
-        }
-
-        self.ensure_ts_file_opened(&uri).await;
-        self.ensure_ts_synced(&uri).await;
-        if let Some(ref proxy) = *self.ts_proxy.read().await {
-            return Ok(proxy.references(&params).await);
-        }
+        .flatten())
+    }
 
-        Ok(None)
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let index = Arc::clone(&self.index);
+        let documents = Arc::clone(&self.documents);
+        let tree_cache = Arc::clone(&self.js_tree_cache);
+        Ok(tokio::task::spawn_blocking(move || {
+            CallHierarchyHandler::new(index, documents, tree_cache).incoming_calls(params)
+        })
+        .await
+        .ok()
+        .flatten())
     }
 
-    async fn document_highlight(
+    async fn outgoing_calls(
         &self,
-        params: DocumentHighlightParams,
-    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
         let index = Arc::clone(&self.index);
-        let params_for_blocking = params.clone();
-        let local = tokio::task::spawn_blocking(move || {
-            DocumentHighlightHandler::new(index).document_highlight(params_for_blocking)
+        let documents = Arc::clone(&self.documents);
+        let tree_cache = Arc::clone(&self.js_tree_cache);
+        Ok(tokio::task::spawn_blocking(move || {
+            CallHierarchyHandler::new(index, documents, tree_cache).outgoing_calls(params)
         })
         .await
         .ok()
-        .flatten();
-        // tsserver にフォールバックしない: documentHighlight は同ファイル内の
-        // 即時ハイライト用途で、AngularJS 固有のシンボルが解決できなかった場合は
-        // クライアント側のデフォルト挙動 (なし or text-based highlight) に任せる。
-        Ok(local)
+        .flatten())
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let index = Arc::clone(&self.index);
+        let documents = Arc::clone(&self.documents);
+        let tree_cache = Arc::clone(&self.js_tree_cache);
+        Ok(tokio::task::spawn_blocking(move || {
+            CodeActionHandler::new(index, documents, tree_cache).code_actions(&params)
+        })
+        .await
+        .ok()
+        .flatten())
     }
 
     async fn goto_definition(
@@ -1916,15 +3408,24 @@ impl LanguageServer for Backend {
         let pos = params.text_document_position_params.position;
 
         let source = self.documents.get(&uri).map(|s| s.value().clone());
+        // ng-include のパス文字列上では、解決できなくても tsserver には流さない
+        let in_ng_include_path = is_html_file(&uri)
+            && source
+                .as_deref()
+                .and_then(|s| find_ng_include_path_at(s, pos.line, pos.character))
+                .is_some();
         let index = Arc::clone(&self.index);
         let params_for_blocking = params.clone();
-        let local_def = tokio::task::spawn_blocking(move || {
-            DefinitionHandler::new(index)
-                .goto_definition_with_source(params_for_blocking, source.as_deref())
+        let blocking_uri = uri.clone();
+        let (local_def, is_angularjs) = tokio::task::spawn_blocking(move || {
+            let source = source.as_deref();
+            let located = locate_symbol_at(&index, &blocking_uri, pos.line, pos.character, source);
+            let definition = DefinitionHandler::new(Arc::clone(&index))
+                .goto_definition_with_located(params_for_blocking, source, located.as_ref());
+            (definition, is_angularjs_symbol(located.as_ref()))
         })
         .await
-        .ok()
-        .flatten();
+        .unwrap_or_default();
 
         if let Some(def) = local_def {
             self.client
@@ -1939,6 +3440,10 @@ impl LanguageServer for Backend {
             return Ok(Some(def));
         }
 
+        if in_ng_include_path || is_angularjs {
+            return Ok(None);
+        }
+
         self.client
             .log_message(
                 MessageType::INFO,
@@ -1960,17 +3465,25 @@ impl LanguageServer for Backend {
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = params.text_document_position_params.text_document.uri.clone();
+        let position = params.text_document_position_params.position;
         let index = Arc::clone(&self.index);
         let params_for_blocking = params.clone();
-        let local_hover = tokio::task::spawn_blocking(move || {
-            HoverHandler::new(index).hover(params_for_blocking)
+        let blocking_uri = uri.clone();
+        let (local_hover, is_angularjs) = tokio::task::spawn_blocking(move || {
+            let located =
+                locate_symbol_at(&index, &blocking_uri, position.line, position.character, None);
+            let hover = HoverHandler::new(Arc::clone(&index))
+                .hover_with_located(params_for_blocking, located.as_ref());
+            (hover, is_angularjs_symbol(located.as_ref()))
         })
         .await
-        .ok()
-        .flatten();
+        .unwrap_or_default();
         if let Some(hover) = local_hover {
             return Ok(Some(hover));
         }
+        if is_angularjs {
+            return Ok(None);
+        }
 
         self.ensure_ts_file_opened(&uri).await;
         self.ensure_ts_synced(&uri).await;
@@ -1994,13 +3507,16 @@ impl LanguageServer for Backend {
         };
 
         let index = Arc::clone(&self.index);
+        let html_analyzer = Arc::clone(&self.html_analyzer);
         let blocking_uri = uri.clone();
         let local_sig = tokio::task::spawn_blocking(move || {
+            let cached_tree = html_analyzer.tree_for_source(&blocking_uri, &source);
             SignatureHelpHandler::new(index).signature_help(
                 &blocking_uri,
                 position.line,
                 position.character,
                 &source,
+                cached_tree.as_ref(),
             )
         })
         .await
@@ -2025,11 +3541,22 @@ impl LanguageServer for Backend {
     ) -> Result<Option<DocumentSymbolResponse>> {
         let uri = params.text_document.uri.clone();
         let index = Arc::clone(&self.index);
+        let html_source = is_html_file(&uri)
+            .then(|| self.documents.get(&uri).map(|doc| doc.value().clone()))
+            .flatten();
+        let cached_tree = html_source
+            .as_deref()
+            .and_then(|source| self.html_analyzer.tree_for_source(&uri, source));
         // CPU-bound work を blocking スレッドに退避し、tokio worker を解放する。
         // (多数ファイル open 時に handler が tokio worker を占有して他 LSP リクエストが
         //  詰まる問題を回避するため。以下の handler 群でも同様)
         let result = tokio::task::spawn_blocking(move || {
-            DocumentSymbolHandler::new(index).document_symbols(&uri)
+            let handler = DocumentSymbolHandler::new(index);
+            if is_html_file(&uri) {
+                handler.document_symbols_html(&uri, html_source.as_deref()?, cached_tree)
+            } else {
+                handler.document_symbols(&uri)
+            }
         })
         .await
         .ok()
@@ -2051,6 +3578,20 @@ impl LanguageServer for Backend {
         let html_analyzer = Arc::clone(&self.html_analyzer);
         let documents = Arc::clone(&self.documents);
         let blocking_uri = uri.clone();
+        let env = CompletionEnv {
+            js_tree_cache: Arc::clone(&self.js_tree_cache),
+            workspace_root: self
+                .root_uri
+                .read()
+                .await
+                .as_ref()
+                .and_then(|u| u.to_file_path().ok()),
+            path_matcher: self.path_matcher.read().await.clone(),
+            trigger_character: params
+                .context
+                .as_ref()
+                .and_then(|c| c.trigger_character.clone()),
+        };
         let decision = tokio::task::spawn_blocking(move || {
             compute_completion_decision(
                 index,
@@ -2059,6 +3600,7 @@ impl LanguageServer for Backend {
                 blocking_uri,
                 line,
                 col,
+                env,
             )
         })
         .await
@@ -2078,19 +3620,49 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn completion_resolve(&self, item: CompletionItem) -> Result<CompletionItem> {
+        // AngularJS 由来の候補はインデックスから JSDoc を埋める
+        if angularjs_completion_symbol(&item).is_some() {
+            return Ok(CompletionHandler::new(Arc::clone(&self.index)).resolve(item));
+        }
+
+        // それ以外は tsserver フォールバック補完の候補なので tsserver に解決させる
+        let resolved = match *self.ts_proxy.read().await {
+            Some(ref proxy) => proxy.completion_resolve(&item).await,
+            None => None,
+        };
+        Ok(resolved.unwrap_or(item))
+    }
+
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
         let uri = params.text_document_position.text_document.uri.clone();
+
+        // フィルター / ディレクティブ名として不正な新名は tsserver に回さずエラーにする
+        if let Err(message) = RenameHandler::new(Arc::clone(&self.index), Arc::clone(&self.documents))
+            .validate_new_name(&params) {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(message));
+        }
+
+        let position = params.text_document_position.position;
         let index = Arc::clone(&self.index);
+        let documents = Arc::clone(&self.documents);
         let params_for_blocking = params.clone();
-        let local_edit = tokio::task::spawn_blocking(move || {
-            RenameHandler::new(index).rename(params_for_blocking)
+        let blocking_uri = uri.clone();
+        let (local_edit, is_angularjs) = tokio::task::spawn_blocking(move || {
+            let located =
+                locate_symbol_at(&index, &blocking_uri, position.line, position.character, None);
+            let edit = RenameHandler::new(Arc::clone(&index), documents)
+                .rename_with_located(params_for_blocking, located.as_ref());
+            (edit, is_angularjs_symbol(located.as_ref()))
         })
         .await
-        .ok()
-        .flatten();
+        .unwrap_or_default();
         if let Some(edit) = local_edit {
             return Ok(Some(edit));
         }
+        if is_angularjs {
+            return Ok(None);
+        }
 
         self.ensure_ts_file_opened(&uri).await;
         self.ensure_ts_synced(&uri).await;
@@ -2105,9 +3677,22 @@ impl LanguageServer for Backend {
         &self,
         params: TextDocumentPositionParams,
     ) -> Result<Option<PrepareRenameResponse>> {
+        let source = match self.documents.get(&params.text_document.uri) {
+            Some(doc) => doc.value().clone(),
+            None => return Ok(None),
+        };
+
+        let uri = &params.text_document.uri;
+        let cached_tree = if is_html_file(uri) {
+            self.html_analyzer.tree_for_source(uri, &source)
+        } else {
+            self.js_tree_cache.tree_for_source(uri, &source)
+        };
+
         let index = Arc::clone(&self.index);
+        let documents = Arc::clone(&self.documents);
         let result = tokio::task::spawn_blocking(move || {
-            RenameHandler::new(index).prepare_rename(params)
+            RenameHandler::new(index, documents).prepare_rename(params, &source, cached_tree)
         })
         .await
         .ok()
@@ -2118,8 +3703,9 @@ impl LanguageServer for Backend {
     async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
         let uri = params.text_document.uri.clone();
         let index = Arc::clone(&self.index);
+        let config = self.ajs_config.read().await.codelens.clone();
         let result = tokio::task::spawn_blocking(move || {
-            CodeLensHandler::new(index).code_lens(&uri)
+            CodeLensHandler::new(index, config).code_lens(&uri)
         })
         .await
         .ok()
@@ -2150,7 +3736,7 @@ impl LanguageServer for Backend {
         let range = Some(params.range);
         let index = Arc::clone(&self.index);
         let documents = Arc::clone(&self.documents);
-        let tree_cache = Arc::clone(&self.inlay_hint_js_tree_cache);
+        let tree_cache = Arc::clone(&self.js_tree_cache);
         let hints = tokio::task::spawn_blocking(move || {
             InlayHintsHandler::new(index, documents, tree_cache).inlay_hints(&uri, range)
         })
@@ -2165,8 +3751,9 @@ impl LanguageServer for Backend {
         params: WorkspaceSymbolParams,
     ) -> Result<Option<Vec<SymbolInformation>>> {
         let index = Arc::clone(&self.index);
+        let config = self.ajs_config.read().await.workspace_symbol.clone();
         let symbols = tokio::task::spawn_blocking(move || {
-            WorkspaceSymbolHandler::new(index).handle(&params.query)
+            WorkspaceSymbolHandler::new(index, config).handle(&params.query)
         })
         .await
         .unwrap_or_default();
@@ -2709,6 +4296,7 @@ mod change_snapshot_tests {
         index.controllers.add_html_controller_scope(HtmlControllerScope {
             controller_name: name.to_string(),
             alias: alias.map(String::from),
+            alias_span: None,
             uri: uri.clone(),
             start_line: 0,
             end_line: 100,
@@ -3085,3 +4673,401 @@ mod drain_pending_reanalysis_tests {
         assert_eq!(visited, vec![b]);
     }
 }
+
+#[cfg(test)]
+mod classify_config_change_tests {
+    use super::*;
+    use crate::config::ajs_config::{CacheConfig, ExpressionAttribute};
+
+    #[test]
+    fn unchanged_config_needs_nothing() {
+        let config = AjsConfig::default();
+        assert_eq!(classify_config_change(&config, &config.clone()), ConfigChange::None);
+    }
+
+    #[test]
+    fn cache_flag_change_needs_nothing() {
+        let previous = AjsConfig::default();
+        let current = AjsConfig { cache: CacheConfig::Enabled(true), ..AjsConfig::default() };
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::None);
+    }
+
+    #[test]
+    fn exclude_change_triggers_rescan() {
+        let previous = AjsConfig::default();
+        let mut current = AjsConfig::default();
+        current.exclude.push("**/vendor/**".to_string());
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Rescan);
+    }
+
+    #[test]
+    fn cancel_request_matches_only_indexing_token() {
+        let cancel = |token: NumberOrString| WorkDoneProgressCancelParams { token };
+        assert!(is_cancel_for(
+            &cancel(NumberOrString::String(INDEXING_TOKEN.to_string())),
+            INDEXING_TOKEN
+        ));
+        assert!(!is_cancel_for(
+            &cancel(NumberOrString::String("angularjs-lsp/cache".to_string())),
+            INDEXING_TOKEN
+        ));
+        assert!(!is_cancel_for(&cancel(NumberOrString::Number(1)), INDEXING_TOKEN));
+    }
+
+    #[test]
+    fn folder_argument_accepts_uri_and_paths() {
+        let root = Path::new("/work/app");
+        assert_eq!(
+            resolve_folder_argument("file:///work/app/vendor", Some(root)),
+            Some(PathBuf::from("/work/app/vendor"))
+        );
+        assert_eq!(
+            resolve_folder_argument("vendor/lib/", Some(root)),
+            Some(PathBuf::from("/work/app/vendor/lib"))
+        );
+        assert_eq!(
+            resolve_folder_argument("/opt/shared", Some(root)),
+            Some(PathBuf::from("/opt/shared"))
+        );
+        assert_eq!(resolve_folder_argument("vendor", None), None);
+    }
+
+    #[test]
+    fn definitions_only_change_triggers_rescan() {
+        let previous = AjsConfig::default();
+        let current = AjsConfig {
+            definitions_only: vec!["legacy/**".to_string()],
+            ..AjsConfig::default()
+        };
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Rescan);
+    }
+
+    #[test]
+    fn file_limit_change_triggers_rescan() {
+        let previous = AjsConfig::default();
+        let current = AjsConfig {
+            max_file_lines: Some(10_000),
+            ..AjsConfig::default()
+        };
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Rescan);
+    }
+
+    #[test]
+    fn expression_attributes_change_triggers_rescan() {
+        let previous = AjsConfig::default();
+        let current = AjsConfig {
+            expression_attributes: vec![ExpressionAttribute::Name("my-validate".to_string())],
+            ..AjsConfig::default()
+        };
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Rescan);
+    }
+
+    #[test]
+    fn include_change_wins_over_diagnostics_change() {
+        let previous = AjsConfig::default();
+        let mut current = AjsConfig::default();
+        current.include.push("src/**".to_string());
+        current.diagnostics.enabled = false;
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Rescan);
+    }
+
+    #[test]
+    fn diagnostics_change_republishes_diagnostics() {
+        let previous = AjsConfig::default();
+        let mut current = AjsConfig::default();
+        current.diagnostics.severity = "error".to_string();
+        assert_eq!(classify_config_change(&previous, &current), ConfigChange::Diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod ts_diagnostics_tests {
+    use super::*;
+    use crate::model::{SymbolBuilder, SymbolKind};
+
+    fn cannot_find_name(name: &str) -> Diagnostic {
+        Diagnostic {
+            code: Some(NumberOrString::Number(2304)),
+            message: format!("Cannot find name '{}'.", name),
+            ..Diagnostic::default()
+        }
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| d.message.as_str()).collect()
+    }
+
+    #[test]
+    fn drops_cannot_find_name_for_angularjs_symbols() {
+        let index = Index::new();
+        let uri = Url::parse("file:///app.js").unwrap();
+        index.definitions.add_definition(
+            SymbolBuilder::new("UserService".to_string(), SymbolKind::Service, uri).build(),
+        );
+        let unused = Diagnostic {
+            code: Some(NumberOrString::Number(6133)),
+            message: "'UserService' is declared but its value is never read.".to_string(),
+            ..Diagnostic::default()
+        };
+
+        let kept = filter_ts_diagnostics(
+            &index,
+            vec![
+                cannot_find_name("UserService"),
+                cannot_find_name("angular"),
+                cannot_find_name("undefinedThing"),
+                unused,
+            ],
+        );
+        assert_eq!(
+            messages(&kept),
+            vec![
+                "Cannot find name 'undefinedThing'.",
+                "'UserService' is declared but its value is never read.",
+            ]
+        );
+    }
+
+    #[test]
+    fn ts_diagnostics_survive_reanalysis_until_removed() {
+        let index = Index::new();
+        let uri = Url::parse("file:///app.js").unwrap();
+        index
+            .diagnostics
+            .set_ts_diagnostics(uri.clone(), vec![cannot_find_name("foo")]);
+
+        // AngularJS 側の再解析では tsserver の診断は消えない
+        index.diagnostics.clear_document(&uri);
+        assert_eq!(
+            messages(&index.diagnostics.get_ts_diagnostics(&uri)),
+            vec!["Cannot find name 'foo'."]
+        );
+
+        index.diagnostics.remove_ts_diagnostics(&uri);
+        assert!(index.diagnostics.get_ts_diagnostics(&uri).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod ts_restart_tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn delay_doubles_until_max_attempts() {
+        let delays: Vec<Option<Duration>> = (0..=TS_RESTART_MAX_ATTEMPTS)
+            .map(ts_restart_delay)
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(500)),
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(8)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn attempts_reset_only_after_stable_run() {
+        assert_eq!(ts_restart_attempts_after(3, Duration::from_secs(5)), 3);
+        assert_eq!(
+            ts_restart_attempts_after(TS_RESTART_MAX_ATTEMPTS, TS_RESTART_STABLE_DURATION),
+            0
+        );
+        // 上限まで使い切っても、安定稼働後のクラッシュなら再び再起動できる
+        assert!(ts_restart_delay(ts_restart_attempts_after(
+            TS_RESTART_MAX_ATTEMPTS,
+            Duration::from_secs(120)
+        ))
+        .is_some());
+    }
+}
+
+#[cfg(test)]
+mod completion_decision_tests {
+    use super::*;
+
+    fn decide(path: &str, source: &str, line: u32, col: u32) -> CompletionDecision {
+        decide_in(None, path, source, line, col)
+    }
+
+    fn decide_in(
+        workspace_root: Option<PathBuf>,
+        path: &str,
+        source: &str,
+        line: u32,
+        col: u32,
+    ) -> CompletionDecision {
+        let index = Arc::new(Index::new());
+        let analyzer = Arc::new(AngularJsAnalyzer::new(Arc::clone(&index)));
+        let html_analyzer = Arc::new(HtmlAngularJsAnalyzer::new(Arc::clone(&index), Arc::clone(&analyzer)));
+        let uri = Url::parse(&format!("file://{}", path)).unwrap();
+        analyzer.analyze_document(&uri, source);
+        let documents = Arc::new(DashMap::new());
+        documents.insert(uri.clone(), source.to_string());
+        let env = CompletionEnv {
+            js_tree_cache: analyzer.tree_cache(),
+            workspace_root,
+            path_matcher: None,
+            trigger_character: None,
+        };
+        compute_completion_decision(index, html_analyzer, documents, uri, line, col, env)
+    }
+
+    #[test]
+    fn array_member_while_typing_falls_back_to_tsserver() {
+        let source = "angular.module('app', []).controller('ListCtrl', function($scope) {\n    $scope.items = [];\n    $scope.items.pu\n});\n";
+        let col = source.lines().nth(2).unwrap().len() as u32;
+        // `$scope.items` に AngularJS 側の子プロパティはないので push などは tsserver が出す
+        assert!(matches!(
+            decide("/app.js", source, 2, col),
+            CompletionDecision::FallbackToTsProxy
+        ));
+    }
+
+    #[test]
+    fn template_path_replace_range_uses_utf16_columns() {
+        let root = std::env::temp_dir().join(format!("ajs-template-path-{}", std::process::id()));
+        fs::create_dir_all(root.join("views")).unwrap();
+        fs::write(root.join("views").join("日本語.html"), "").unwrap();
+
+        let source = "angular.module('app', []).component('a', {\n    templateUrl: 'views/日本'\n});\n";
+        let line_text = "    templateUrl: 'views/日本";
+        let col = line_text.encode_utf16().count() as u32;
+        let decision = decide_in(Some(root.clone()), &root.join("app.js").to_string_lossy(), source, 1, col);
+        fs::remove_dir_all(&root).unwrap();
+
+        let CompletionDecision::Resolved(CompletionResponse::Array(items)) = decision else {
+            panic!("template path completion expected");
+        };
+        let item = items.iter().find(|item| item.label == "日本語.html").unwrap();
+        let Some(CompletionTextEdit::Edit(edit)) = &item.text_edit else {
+            panic!("text edit expected");
+        };
+        // 入力中の「日本」(UTF-16 で 2) だけを置き換える
+        assert_eq!(edit.range, Range::new(Position::new(1, col - 2), Position::new(1, col)));
+    }
+}
+
+#[cfg(test)]
+mod scan_cancellation_tests {
+    use super::*;
+
+    /// Phase 1 の途中でキャンセルされたら、残りのファイルは解析しない
+    #[test]
+    fn cancel_during_phase_stops_before_next_file() {
+        let index = Arc::new(Index::new());
+        let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+        let js_files: Vec<(Url, String)> = (0..5)
+            .map(|i| {
+                (
+                    Url::parse(&format!("file:///ctrl{}.js", i)).unwrap(),
+                    format!("angular.module('app').controller('Ctrl{}', function() {{}});", i),
+                )
+            })
+            .collect();
+
+        let cancelled = AtomicBool::new(false);
+        let mut analyzed = 0;
+        for_each_until_cancelled(js_files.iter(), &cancelled, |(uri, content)| {
+            analyzer.analyze_document_with_options(uri, content, true);
+            analyzed += 1;
+            // 2 ファイル目の解析中にキャンセル通知が届いた想定
+            if analyzed == 2 {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        });
+
+        assert_eq!(analyzed, 2);
+        assert!(index.definitions.has_definition("Ctrl0"));
+        assert!(index.definitions.has_definition("Ctrl1"));
+        assert!(!index.definitions.has_definition("Ctrl2"));
+        assert!(!index.definitions.has_definition("Ctrl4"));
+    }
+}
+
+#[cfg(test)]
+mod ignore_folder_tests {
+    use super::*;
+    use futures::StreamExt;
+    use tempfile::TempDir;
+    use tower_lsp::LspService;
+
+    /// `root` をワークスペースにした Backend (クライアントへの通知は読み捨てる)
+    async fn backend_for(root: &Path) -> LspService<Backend> {
+        let (service, socket) = LspService::new(Backend::new);
+        tokio::spawn(socket.for_each(|_| async {}));
+        *service.inner().root_uri.write().await = Some(Url::from_file_path(root).unwrap());
+        service
+    }
+
+    fn controller_source(name: &str) -> String {
+        format!("angular.module('app').controller('{}', function() {{}});", name)
+    }
+
+    /// `src/app.js` (MainCtrl) と `legacy/old.js` (LegacyCtrl) を置いたワークスペース
+    fn workspace() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        for (dir, file, name) in [("src", "app.js", "MainCtrl"), ("legacy", "old.js", "LegacyCtrl")] {
+            fs::create_dir_all(tmp.path().join(dir)).unwrap();
+            fs::write(tmp.path().join(dir).join(file), controller_source(name)).unwrap();
+        }
+        tmp
+    }
+
+    #[tokio::test]
+    async fn ignore_folder_clears_index_and_rescan_skips_it() {
+        let tmp = workspace();
+        let service = backend_for(tmp.path()).await;
+        let backend = service.inner();
+        assert!(backend.scan_workspace().await);
+        assert!(backend.index.definitions.has_definition("LegacyCtrl"));
+
+        let removed = backend
+            .ignore_folder(tmp.path().join("legacy"), Some(tmp.path()))
+            .await;
+        assert_eq!(removed, 1);
+        assert!(!backend.index.definitions.has_definition("LegacyCtrl"));
+        assert!(backend.index.definitions.has_definition("MainCtrl"));
+
+        backend.index.clear_all();
+        assert!(backend.scan_workspace().await);
+        assert!(!backend.index.definitions.has_definition("LegacyCtrl"));
+        assert!(backend.index.definitions.has_definition("MainCtrl"));
+    }
+
+    #[tokio::test]
+    async fn edits_in_ignored_folder_are_not_analyzed() {
+        let tmp = workspace();
+        let service = backend_for(tmp.path()).await;
+        let backend = service.inner();
+        backend
+            .ignore_folder(tmp.path().join("legacy"), Some(tmp.path()))
+            .await;
+
+        let ignored_uri = Url::from_file_path(tmp.path().join("legacy/old.js")).unwrap();
+        backend
+            .on_open(ignored_uri.clone(), controller_source("OpenedCtrl"))
+            .await;
+        assert!(!backend.index.definitions.has_definition("OpenedCtrl"));
+
+        // 変更はデバウンス後に解析されるので、後から変更した除外外のファイルの解析を待つ
+        backend
+            .on_change(ignored_uri, controller_source("EditedCtrl"))
+            .await;
+        let uri = Url::from_file_path(tmp.path().join("src/app.js")).unwrap();
+        backend.on_change(uri, controller_source("ChangedCtrl")).await;
+        for _ in 0..100 {
+            if backend.index.definitions.has_definition("ChangedCtrl") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(backend.index.definitions.has_definition("ChangedCtrl"));
+        assert!(!backend.index.definitions.has_definition("EditedCtrl"));
+    }
+}
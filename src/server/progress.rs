@@ -13,12 +13,35 @@ pub async fn send_progress(client: &Client, token: &NumberOrString, value: WorkD
         .await;
 }
 
+/// Progress token of the workspace scan (also matched against cancel requests)
+pub const INDEXING_TOKEN: &str = "angularjs-indexing";
+
 /// Create a progress token and send begin notification
 pub async fn begin_progress(
     client: &Client,
     token_name: &str,
     title: &str,
     message: Option<String>,
+) -> NumberOrString {
+    begin_progress_with(client, token_name, title, message, false).await
+}
+
+/// Create a progress token the client may cancel via `window/workDoneProgress/cancel`
+pub async fn begin_cancellable_progress(
+    client: &Client,
+    token_name: &str,
+    title: &str,
+    message: Option<String>,
+) -> NumberOrString {
+    begin_progress_with(client, token_name, title, message, true).await
+}
+
+async fn begin_progress_with(
+    client: &Client,
+    token_name: &str,
+    title: &str,
+    message: Option<String>,
+    cancellable: bool,
 ) -> NumberOrString {
     let token = NumberOrString::String(token_name.to_string());
     let _ = client
@@ -32,7 +55,7 @@ pub async fn begin_progress(
         &token,
         WorkDoneProgress::Begin(WorkDoneProgressBegin {
             title: title.to_string(),
-            cancellable: Some(false),
+            cancellable: Some(cancellable),
             message,
             percentage: Some(0),
         }),
@@ -43,6 +66,8 @@ pub async fn begin_progress(
 }
 
 /// Send progress report
+///
+/// `cancellable` is left unset so the value sent with the begin notification stays in effect
 pub async fn report_progress(
     client: &Client,
     token: &NumberOrString,
@@ -53,7 +78,7 @@ pub async fn report_progress(
         client,
         token,
         WorkDoneProgress::Report(WorkDoneProgressReport {
-            cancellable: Some(false),
+            cancellable: None,
             message: Some(message),
             percentage: Some(percentage),
         }),
@@ -72,3 +97,8 @@ pub async fn end_progress(client: &Client, token: &NumberOrString, message: Stri
     )
     .await;
 }
+
+/// Whether a `window/workDoneProgress/cancel` notification targets the given token
+pub fn is_cancel_for(params: &WorkDoneProgressCancelParams, token_name: &str) -> bool {
+    matches!(&params.token, NumberOrString::String(token) if token == token_name)
+}
@@ -2,19 +2,24 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{Position, Url};
 
 use crate::cache::FileMetadata;
-use crate::config::PathMatcher;
+use crate::config::{FileLimits, PathMatcher};
+use crate::util::position_to_offset;
 
 /// Collect files with given extensions from workspace directory
+///
+/// `limits` を超えるファイルは収集せず、その数を返す
 pub fn collect_files(
     dir: &Path,
     root: &Path,
     path_matcher: Option<&PathMatcher>,
+    limits: &FileLimits,
     extensions: &[&str],
     files: &mut Vec<(Url, String)>,
-) {
+) -> usize {
+    let mut skipped = 0;
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
@@ -36,7 +41,7 @@ pub fn collect_files(
                         }
                     }
                 }
-                collect_files(&path, root, path_matcher, extensions, files);
+                skipped += collect_files(&path, root, path_matcher, limits, extensions, files);
             } else {
                 let ext_match = path
                     .extension()
@@ -50,7 +55,16 @@ pub fn collect_files(
                             continue;
                         }
                     }
+                    // 読み込む前にサイズで足切り (巨大ファイルを読まない)
+                    if entry.metadata().is_ok_and(|m| limits.exceeds_size(m.len())) {
+                        skipped += 1;
+                        continue;
+                    }
                     if let Ok(content) = fs::read_to_string(&path) {
+                        if limits.exceeds(&content) {
+                            skipped += 1;
+                            continue;
+                        }
                         if let Ok(uri) = Url::from_file_path(&path) {
                             files.push((uri, content));
                         }
@@ -59,6 +73,7 @@ pub fn collect_files(
             }
         }
     }
+    skipped
 }
 
 /// Collect file metadata for caching
@@ -92,27 +107,15 @@ pub fn collect_file_metadata(
                 collect_file_metadata(&path, root, path_matcher, metadata);
             } else {
                 let ext = path.extension().and_then(|e| e.to_str());
-                if ext == Some("js") || ext == Some("html") || ext == Some("htm") {
+                if matches!(ext, Some("js" | "ts" | "tsx" | "html" | "htm")) {
                     if let Some(matcher) = path_matcher {
                         if !matcher.should_include(relative_path) {
                             continue;
                         }
                     }
 
-                    if let Ok(meta) = fs::metadata(&path) {
-                        let mtime = meta
-                            .modified()
-                            .ok()
-                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                            .map(|d| d.as_secs())
-                            .unwrap_or(0);
-                        metadata.insert(
-                            path,
-                            FileMetadata {
-                                mtime,
-                                size: meta.len(),
-                            },
-                        );
+                    if let Some(meta) = file_metadata(&path) {
+                        metadata.insert(path, meta);
                     }
                 }
             }
@@ -120,6 +123,48 @@ pub fn collect_file_metadata(
     }
 }
 
+/// `limits` を超えるファイルを `metadata` から除き、その数を返す
+///
+/// キャッシュから読み込む場合もフルスキャン (`collect_files`) と同じ上限を適用する。
+/// サイズは記録済みの値で判定し、行数の上限があるときだけ内容を読む
+pub fn retain_files_within_limits(
+    metadata: &mut HashMap<PathBuf, FileMetadata>,
+    limits: &FileLimits,
+) -> usize {
+    let before = metadata.len();
+    metadata.retain(|path, meta| {
+        if limits.exceeds_size(meta.size) {
+            return false;
+        }
+        limits.max_file_lines.is_none()
+            || fs::read_to_string(path).is_ok_and(|content| !limits.exceeds(&content))
+    });
+    before - metadata.len()
+}
+
+/// キャッシュ検証用のファイルの更新時刻とサイズ
+pub fn file_metadata(path: &Path) -> Option<FileMetadata> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Some(FileMetadata {
+        mtime,
+        size: meta.len(),
+    })
+}
+
+/// `definitions_only` にマッチし、参照を収集しないファイルかどうか
+pub fn is_definitions_only(root: &Path, path_matcher: Option<&PathMatcher>, uri: &Url) -> bool {
+    let (Some(matcher), Ok(path)) = (path_matcher, uri.to_file_path()) else {
+        return false;
+    };
+    matcher.is_definitions_only(path.strip_prefix(root).unwrap_or(&path))
+}
+
 /// Find tsconfig.json in workspace
 pub fn find_tsconfig_root(root_uri: &Option<Url>) -> Option<Url> {
     let root_uri = root_uri.as_ref()?;
@@ -155,36 +200,363 @@ fn find_tsconfig_in_dir(dir: &Path) -> Option<Url> {
     None
 }
 
-/// Extract service prefix at cursor position ("ServiceName." pattern)
-pub fn get_service_prefix_at_cursor(text: &str, line: u32, col: u32) -> Option<String> {
-    let lines: Vec<&str> = text.lines().collect();
-    if line as usize >= lines.len() {
+/// カーソル直前のメンバーアクセス (`X.` / `X.ab` の補完位置) の解析結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemberChain {
+    /// 識別子とプロパティだけで辿れるチェーン (`$scope.user.` → `$scope.user`)
+    Path(String),
+    /// 呼び出し結果・添字アクセス・リテラルのメンバー
+    /// (`UserService.getAll().then(` の `then` や `data['users'].`)。
+    /// 戻り値や要素の型は解析できないので AngularJS 側では補完しない
+    Dynamic,
+}
+
+/// カーソル直前のメンバーチェーンを遡る
+///
+/// 入力中のプロパティ名 (`$scope.us|`) は除いて、その前の `.` / `?.` までを見る。
+/// `.` の直後でなければ `None`
+pub fn get_member_chain_at_cursor(text: &str, line: u32, col: u32) -> Option<MemberChain> {
+    let line_text = text.lines().nth(line as usize)?;
+    let before_cursor = line_text.get(..col as usize)?;
+
+    let typing = before_cursor.trim_end_matches(is_ident_char);
+    let receiver = typing.strip_suffix('.')?;
+    let receiver = receiver.strip_suffix('?').unwrap_or(receiver);
+    if let Some(service_name) = injector_get_service_before(receiver) {
+        return Some(MemberChain::Path(service_name));
+    }
+
+    // 識別子を `.` / `?.` 区切りで遡る。`.` の前が識別子でなければ、
+    // チェーンの根元は識別子以外の式 (`getAll()` / `data['users']` / `'str'`)
+    let mut rest = receiver;
+    let mut segments = Vec::new();
+    let root_is_expression = loop {
+        let segment_start = rest.trim_end_matches(is_ident_char).len();
+        let segment = &rest[segment_start..];
+        if segment.is_empty() || segment.starts_with(|c: char| c.is_ascii_digit()) {
+            break true;
+        }
+        segments.push(segment);
+        rest = &rest[..segment_start];
+        match rest.strip_suffix('.') {
+            Some(before_dot) => rest = before_dot.strip_suffix('?').unwrap_or(before_dot),
+            None => break false,
+        }
+    };
+
+    if root_is_expression {
+        return rest
+            .ends_with([')', ']', '\'', '"', '`'])
+            .then_some(MemberChain::Dynamic);
+    }
+    segments.reverse();
+    Some(MemberChain::Path(segments.join(".")))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// `$injector.get('X')` で終わるテキストなら `X` を返す (`$injector.get('X').` のチェーン補完用)
+fn injector_get_service_before(text: &str) -> Option<String> {
+    let inner = text.trim_end().strip_suffix(')')?.trim_end();
+    let quote = inner.chars().last().filter(|c| *c == '\'' || *c == '"')?;
+    let inner = &inner[..inner.len() - 1];
+    let open = inner.rfind(quote)?;
+    let service = &inner[open + 1..];
+    let call = inner[..open].trim_end().strip_suffix('(')?.trim_end();
+    if call.ends_with("$injector.get") && !service.is_empty() {
+        Some(service.to_string())
+    } else {
+        None
+    }
+}
+
+/// `angular.module('app', [...])` の依存配列内、文字列リテラル上のカーソル位置を判定する
+///
+/// 戻り値: Some((prefix, excluded))
+/// - prefix: 入力中の文字列リテラルのカーソルまでの内容
+/// - excluded: 定義中のモジュール名と、配列に既に列挙済みの依存モジュール名
+///   （入力中のリテラル自身は含まない）
+///
+/// 複数行にまたがる依存配列にも対応するため、カーソル位置から遡って最後の
+/// `angular.module(` を起点に文字単位で走査する。
+pub fn get_module_dependency_context(text: &str, line: u32, col: u32) -> Option<(String, Vec<String>)> {
+    // `col` はバイト列なので、行頭だけ UTF-16 の位置から求めて足す
+    let line_start = position_to_offset(text, Position::new(line, 0));
+    let offset = line_start + col as usize;
+    text.get(line_start..offset)
+        .filter(|before| !before.contains('\n'))?;
+    let call_start = text[..offset].rfind("angular.module(")? + "angular.module(".len();
+
+    // 依存配列内で完結した文字列リテラルを集める。入力中のリテラルは prefix に入る
+    let mut excluded: Vec<String> = Vec::new();
+    let mut module_name: Option<String> = None;
+    let mut paren_depth = 0u32;
+    let mut bracket_depth = 0u32;
+    let mut in_deps = false;
+    let mut seen_comma = false;
+    let mut quote: Option<char> = None;
+    let mut literal = String::new();
+
+    for c in text[call_start..offset].chars() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+                let value = std::mem::take(&mut literal);
+                if paren_depth == 0 && !seen_comma && module_name.is_none() {
+                    module_name = Some(value);
+                } else if in_deps && bracket_depth == 1 {
+                    excluded.push(value);
+                }
+            } else {
+                literal.push(c);
+            }
+            continue;
+        }
+
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '(' => paren_depth += 1,
+            ')' => {
+                if paren_depth == 0 {
+                    // angular.module( ... ) の呼び出しが閉じた
+                    return None;
+                }
+                paren_depth -= 1;
+            }
+            ',' if paren_depth == 0 && bracket_depth == 0 => seen_comma = true,
+            '[' => {
+                if paren_depth == 0 && bracket_depth == 0 && seen_comma {
+                    in_deps = true;
+                }
+                bracket_depth += 1;
+            }
+            ']' => {
+                bracket_depth = bracket_depth.saturating_sub(1);
+                if bracket_depth == 0 && in_deps {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // カーソル位置: 依存配列の直下で文字列リテラル内でなければ対象外
+    let current_quote = quote?;
+    if !in_deps || bracket_depth != 1 {
         return None;
     }
+    excluded.extend(scan_remaining_deps(&text[offset..], current_quote));
+    excluded.extend(module_name);
+
+    Some((literal, excluded))
+}
+
+/// カーソル以降の依存配列から、完結した文字列リテラルを集める
+/// (`text` はカーソル位置から始まり、入力中リテラルの残りを含む)
+fn scan_remaining_deps(text: &str, current_quote: char) -> Vec<String> {
+    let mut deps = Vec::new();
+    let mut chars = text.chars();
 
-    let line_text = lines[line as usize];
-    let col = col as usize;
-    if col > line_text.len() {
+    // 入力中リテラルの残りを読み飛ばす
+    for c in chars.by_ref() {
+        if c == current_quote || c == '\n' {
+            break;
+        }
+    }
+
+    let mut quote: Option<char> = None;
+    let mut literal = String::new();
+    for c in chars {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+                deps.push(std::mem::take(&mut literal));
+            } else {
+                literal.push(c);
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => quote = Some(c),
+            ']' | ')' => break,
+            _ => {}
+        }
+    }
+
+    deps
+}
+
+/// `templateUrl: '...'` / `ng-include="'...'"` のパス文字列内にカーソルがあれば、
+/// 文字列の開始からカーソルまでの入力済みパスを返す
+///
+/// `is_html` が true なら `ng-include` 属性値、false なら JS の `templateUrl`
+/// プロパティ値を対象にする。クエリパラメータ部 (`?v=123`) の入力中は補完しない
+pub fn get_template_path_context(text: &str, line: u32, col: u32, is_html: bool) -> Option<String> {
+    let line_text = text.split('\n').nth(line as usize)?;
+    let before = line_text.get(..col as usize)?;
+
+    let typed = if is_html {
+        template_path_in_ng_include(before)?
+    } else {
+        template_path_in_template_url(before)?
+    };
+
+    if typed.contains('?') {
+        return None;
+    }
+    Some(typed.to_string())
+}
+
+/// JS: カーソル直前が `templateUrl: '<入力中>` の形なら入力中部分を返す
+fn template_path_in_template_url(before: &str) -> Option<&str> {
+    // 行頭からクォート状態を追い、カーソル位置で開いている文字列の開始を探す
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (i, c) in before.char_indices() {
+        match open {
+            Some((_, q)) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    open = None;
+                }
+            }
+            None if c == '\'' || c == '"' || c == '`' => open = Some((i, c)),
+            None => {}
+        }
+    }
+    let (quote_pos, _) = open?;
+
+    let key_part = before[..quote_pos].trim_end().strip_suffix(':')?.trim_end();
+    let key = key_part.trim_end_matches(['\'', '"']);
+    if !key.ends_with("templateUrl") {
+        return None;
+    }
+    let key_start = key.len() - "templateUrl".len();
+    if key[..key_start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+    {
+        return None;
+    }
+    Some(&before[quote_pos + 1..])
+}
+
+/// HTML: カーソル直前が `ng-include="'<入力中>` (属性形式) または
+/// `<ng-include src="'<入力中>` (要素形式) の形なら入力中部分を返す
+fn template_path_in_ng_include(before: &str) -> Option<&str> {
+    let attr_pos = before.rfind("ng-include")?;
+    let after_name = &before[attr_pos + "ng-include".len()..];
+    let value = match after_name.trim_start().strip_prefix('=') {
+        Some(value) => value,
+        // 要素形式: 同じタグ内の src 属性
+        None if before[..attr_pos].ends_with('<') && !after_name.contains('>') => {
+            let src_pos = after_name.rfind("src")?;
+            after_name[src_pos + "src".len()..].trim_start().strip_prefix('=')?
+        }
+        None => return None,
+    };
+
+    let mut chars = value.trim_start().chars();
+    let outer = chars.next().filter(|c| *c == '"' || *c == '\'')?;
+    let inner = chars.next().filter(|c| (*c == '"' || *c == '\'') && *c != outer)?;
+    let typed = chars.as_str();
+    if typed.contains(outer) || typed.contains(inner) {
         return None;
     }
+    Some(typed)
+}
 
-    let before_cursor = &line_text[..col];
+/// テンプレートパス補完の候補を列挙する
+///
+/// `typed` の最後の `/` までをディレクトリ部分とみなし、各 `base_dirs` からの
+/// 相対ディレクトリ直下にある `.html` / `.htm` ファイルとサブディレクトリを返す。
+/// 戻り値は (名前, ディレクトリか) のリストで、名前の重複は除去済み
+pub fn list_template_path_entries(
+    base_dirs: &[PathBuf],
+    typed: &str,
+    root: &Path,
+    path_matcher: Option<&PathMatcher>,
+) -> Vec<(String, bool)> {
+    let dir_part = match typed.rfind('/') {
+        Some(pos) => &typed[..pos + 1],
+        None => "",
+    };
+
+    // 先頭 `/` はワークスペースルートからの絶対指定とみなす
+    let (bases, dir_part): (Vec<&Path>, &str) = match dir_part.strip_prefix('/') {
+        Some(stripped) => (vec![root], stripped),
+        None => (base_dirs.iter().map(|p| p.as_path()).collect(), dir_part),
+    };
 
-    if before_cursor.ends_with('.') {
-        let without_dot = &before_cursor[..before_cursor.len() - 1];
-        let service_name: String = without_dot
-            .chars()
-            .rev()
-            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
-            .collect::<String>()
-            .chars()
-            .rev()
-            .collect();
+    let mut seen = std::collections::HashSet::new();
+    let mut entries = Vec::new();
+    for base in bases {
+        let dir = base.join(dir_part);
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let relative_path = path.strip_prefix(root).ok();
 
-        if !service_name.is_empty() {
-            return Some(service_name);
+            if path.is_dir() {
+                let traverse = match (path_matcher, relative_path) {
+                    (Some(matcher), Some(rel)) => matcher.should_traverse_dir(rel),
+                    _ => !(name.starts_with('.') || name == "node_modules"),
+                };
+                if traverse && seen.insert(format!("{}/", name)) {
+                    entries.push((name.to_string(), true));
+                }
+            } else {
+                let is_html = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e == "html" || e == "htm");
+                if !is_html {
+                    continue;
+                }
+                let included = match (path_matcher, relative_path) {
+                    (Some(matcher), Some(rel)) => matcher.should_include(rel),
+                    _ => true,
+                };
+                if included && seen.insert(name.to_string()) {
+                    entries.push((name.to_string(), false));
+                }
+            }
         }
     }
+    entries.sort();
+    entries
+}
 
-    None
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retain_files_within_limits_drops_oversized_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut metadata = HashMap::new();
+        for (name, content) in [("small.js", "a\n"), ("long.js", "a\nb\nc\n"), ("big.html", "0123456789")] {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            metadata.insert(path.clone(), file_metadata(&path).unwrap());
+        }
+
+        let limits = FileLimits {
+            max_file_size_bytes: Some(8),
+            max_file_lines: Some(2),
+        };
+        assert_eq!(retain_files_within_limits(&mut metadata, &limits), 2);
+        assert_eq!(metadata.keys().collect::<Vec<_>>(), vec![&dir.path().join("small.js")]);
+    }
 }
@@ -1,35 +1,64 @@
 mod transport;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::Arc;
 
 use dashmap::DashMap;
 use serde_json::{json, Value};
 use tokio::process::{Child, Command};
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tower_lsp::lsp_types::*;
 use tracing::{error, info, warn};
 
 use transport::{LspReader, LspWriter};
 
+/// typescript-language-server から `Backend` へ中継するイベント
+#[derive(Debug)]
+pub enum TsProxyEvent {
+    /// サーバーからの通知 (id を持たないメッセージ)
+    Notification { method: String, params: Value },
+    /// 子プロセスとの通信が途絶えた (クラッシュ等)。`shutdown` による終了では送らない
+    Exited,
+}
+
+/// typescript-language-server の起動設定 (ajsconfig.json の `tsserver_path` / `tsserver_args`)
+#[derive(Debug, Clone, Default)]
+pub struct TsServerOptions {
+    /// 実行ファイルのパス。相対パスはルートディレクトリ基準で解決する
+    pub path: Option<String>,
+    /// `--stdio` の後に渡す追加引数
+    pub args: Vec<String>,
+}
+
 /// Proxy to typescript-language-server
 pub struct TsProxy {
     writer: Arc<Mutex<LspWriter>>,
     pending_requests: Arc<DashMap<i64, oneshot::Sender<Value>>>,
     next_id: AtomicI64,
+    /// `shutdown` 済みかどうか。意図した終了を `Exited` として通知しないために使う
+    shutting_down: Arc<AtomicBool>,
     _child: Child,
 }
 
 impl TsProxy {
-    pub async fn start(root_uri: Option<&Url>) -> Option<Self> {
-        let tsserver_path = find_tsserver()?;
+    /// typescript-language-server を起動して initialize まで済ませる
+    ///
+    /// サーバーから届いた通知は `events` に送る。
+    pub async fn start(
+        root_uri: Option<&Url>,
+        options: &TsServerOptions,
+        events: mpsc::UnboundedSender<TsProxyEvent>,
+    ) -> Option<Self> {
+        let root_dir = root_uri.and_then(|u| u.to_file_path().ok());
+        let tsserver_path = find_tsserver(options.path.as_deref(), root_dir.as_deref())?;
 
         info!("Starting typescript-language-server: {:?}", tsserver_path);
 
         let mut child = Command::new(&tsserver_path)
             .arg("--stdio")
+            .args(&options.args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
@@ -43,11 +72,13 @@ impl TsProxy {
         let mut reader = LspReader::new(stdout);
         let pending_requests: Arc<DashMap<i64, oneshot::Sender<Value>>> =
             Arc::new(DashMap::new());
+        let shutting_down = Arc::new(AtomicBool::new(false));
 
         let proxy = Self {
             writer: Arc::clone(&writer),
             pending_requests: Arc::clone(&pending_requests),
             next_id: AtomicI64::new(1),
+            shutting_down: Arc::clone(&shutting_down),
             _child: child,
         };
 
@@ -61,10 +92,22 @@ impl TsProxy {
                             if let Some((_, sender)) = pending_clone.remove(&id) {
                                 let _ = sender.send(response);
                             }
+                        } else if let Some(method) =
+                            response.get("method").and_then(|v| v.as_str())
+                        {
+                            let _ = events.send(TsProxyEvent::Notification {
+                                method: method.to_string(),
+                                params: response.get("params").cloned().unwrap_or(Value::Null),
+                            });
                         }
                     }
                     Err(e) => {
-                        error!("Error reading from tsserver: {}", e);
+                        // 応答待ちのリクエストは sender を破棄してタイムアウトを待たせない
+                        pending_clone.clear();
+                        if !shutting_down.load(Ordering::SeqCst) {
+                            error!("Error reading from tsserver: {}", e);
+                            let _ = events.send(TsProxyEvent::Exited);
+                        }
                         break;
                     }
                 }
@@ -78,7 +121,11 @@ impl TsProxy {
             "rootUri": root_uri_str,
             "capabilities": {
                 "textDocument": {
+                    "publishDiagnostics": { "relatedInformation": true },
                     "hover": { "contentFormat": ["markdown", "plaintext"] },
+                    "completion": {
+                        "completionItem": { "documentationFormat": ["markdown", "plaintext"] }
+                    },
                     "definition": { "linkSupport": true },
                     "references": {},
                     "signatureHelp": {
@@ -148,7 +195,7 @@ impl TsProxy {
         let params = json!({
             "textDocument": {
                 "uri": uri.to_string(),
-                "languageId": "javascript",
+                "languageId": language_id(uri),
                 "version": 1,
                 "text": text
             }
@@ -266,6 +313,21 @@ impl TsProxy {
         serde_json::from_value(result.clone()).ok()
     }
 
+    pub async fn completion_resolve(&self, item: &CompletionItem) -> Option<CompletionItem> {
+        let request_params = serde_json::to_value(item).ok()?;
+
+        let response = self
+            .send_request("completionItem/resolve", request_params)
+            .await?;
+        let result = response.get("result")?;
+
+        if result.is_null() {
+            return None;
+        }
+
+        serde_json::from_value(result.clone()).ok()
+    }
+
     pub async fn rename(&self, params: &RenameParams) -> Option<WorkspaceEdit> {
         let uri = &params.text_document_position.text_document.uri;
         let pos = &params.text_document_position.position;
@@ -310,27 +372,89 @@ impl TsProxy {
     }
 
     pub async fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
         let _ = self.send_request("shutdown", json!(null)).await;
         self.send_notification("exit", json!(null)).await;
     }
 }
 
-/// Find typescript-language-server in PATH
-fn find_tsserver() -> Option<PathBuf> {
-    let output = std::process::Command::new("which")
-        .arg("typescript-language-server")
-        .output()
-        .ok()?;
+/// ファイル拡張子から LSP の languageId を決める
+fn language_id(uri: &Url) -> &'static str {
+    let path = uri.path().to_lowercase();
+    if path.ends_with(".tsx") {
+        "typescriptreact"
+    } else if path.ends_with(".ts") {
+        "typescript"
+    } else {
+        "javascript"
+    }
+}
+
+/// typescript-language-server の実行ファイルを探す
+///
+/// 探索順: 設定値 (`tsserver_path`) → PATH (`which` / Windows は `where`) →
+/// `<root>/node_modules/.bin`。見つからなければ探索したパスを含めて警告する。
+fn find_tsserver(configured: Option<&str>, root_dir: Option<&Path>) -> Option<PathBuf> {
+    if let Some(configured) = configured {
+        let path = match root_dir {
+            Some(root) => root.join(configured),
+            None => PathBuf::from(configured),
+        };
+        if path.is_file() {
+            return Some(path);
+        }
+        warn!(
+            "tsserver_path {:?} does not exist, falling back to automatic lookup",
+            path
+        );
+    }
+
+    if let Some(path) = find_in_path("typescript-language-server") {
+        return Some(path);
+    }
 
-    if output.status.success() {
-        let path = String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .to_string();
-        if !path.is_empty() {
-            return Some(PathBuf::from(path));
+    let local = root_dir.map(|root| {
+        let bin = if cfg!(windows) {
+            "typescript-language-server.cmd"
+        } else {
+            "typescript-language-server"
+        };
+        root.join("node_modules").join(".bin").join(bin)
+    });
+    if let Some(ref path) = local {
+        if path.is_file() {
+            return Some(path.clone());
         }
     }
 
-    warn!("typescript-language-server not found in PATH");
+    match local {
+        Some(path) => warn!(
+            "typescript-language-server not found in PATH or at {:?}; set \"tsserver_path\" in ajsconfig.json",
+            path
+        ),
+        None => warn!(
+            "typescript-language-server not found in PATH; set \"tsserver_path\" in ajsconfig.json"
+        ),
+    }
     None
 }
+
+/// `which` (Windows は `where`) でコマンドを探す
+fn find_in_path(command: &str) -> Option<PathBuf> {
+    let finder = if cfg!(windows) { "where" } else { "which" };
+    let output = std::process::Command::new(finder)
+        .arg(command)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // `where` は候補を複数行で返すので先頭を使う
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(PathBuf::from)
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::io;
 
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{ChildStdin, ChildStdout};
 
 /// LSP write transport
@@ -28,12 +28,12 @@ impl LspWriter {
 }
 
 /// LSP read transport
-pub struct LspReader {
-    stdout: BufReader<ChildStdout>,
+pub struct LspReader<R = ChildStdout> {
+    stdout: BufReader<R>,
 }
 
-impl LspReader {
-    pub fn new(stdout: ChildStdout) -> Self {
+impl<R: AsyncRead + Unpin> LspReader<R> {
+    pub fn new(stdout: R) -> Self {
         Self {
             stdout: BufReader::new(stdout),
         }
@@ -45,7 +45,13 @@ impl LspReader {
 
         loop {
             line.clear();
-            self.stdout.read_line(&mut line).await?;
+            // 0 バイト読み込みは子プロセスの stdout が閉じた (= 終了した) ことを示す
+            if self.stdout.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "tsserver stdout closed",
+                ));
+            }
 
             if line == "\r\n" || line == "\n" {
                 break;
@@ -69,3 +75,19 @@ impl LspReader {
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_message_returns_eof_error_when_stream_closes() {
+        let input = b"Content-Length: 8\r\n\r\n{\"id\":1}";
+        let mut reader = LspReader::new(&input[..]);
+        assert_eq!(reader.read_message().await.unwrap(), serde_json::json!({ "id": 1 }));
+
+        // 閉じた stdout の 0 バイト読み込みでループせずにエラーを返す
+        let err = reader.read_message().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,4 +1,4 @@
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent, Url};
 
 /// ファイルがHTMLかどうか判定
 pub fn is_html_file(uri: &Url) -> bool {
@@ -6,9 +6,15 @@ pub fn is_html_file(uri: &Url) -> bool {
     path.ends_with(".html") || path.ends_with(".htm")
 }
 
-/// ファイルがJSかどうか判定
+/// ファイルがJS (TypeScript を含む) かどうか判定
 pub fn is_js_file(uri: &Url) -> bool {
-    uri.path().ends_with(".js")
+    uri.path().ends_with(".js") || is_typescript_file(uri)
+}
+
+/// ファイルがTypeScript (`.ts` / `.tsx`) かどうか判定
+pub fn is_typescript_file(uri: &Url) -> bool {
+    let path = uri.path();
+    path.ends_with(".ts") || path.ends_with(".tsx")
 }
 
 /// camelCaseをkebab-caseに変換
@@ -64,6 +70,18 @@ pub fn normalize_template_path(path: &str) -> String {
 
 /// 親URIを起点として相対パスを解決し、ファイル名を取得
 pub fn resolve_relative_path(parent_uri: &Url, template_path: &str) -> String {
+    let resolved = resolve_relative_full_path(parent_uri, template_path);
+    resolved
+        .rsplit('/')
+        .next()
+        .unwrap_or(&resolved)
+        .to_string()
+}
+
+/// 親URIを起点として相対パスを解決し、絶対パス（URI の path 部分）を取得
+///
+/// 先頭が `/` のパスはそのまま返す。クエリパラメータは除去する
+pub fn resolve_relative_full_path(parent_uri: &Url, template_path: &str) -> String {
     let template_path = template_path.split('?').next().unwrap_or(template_path);
     let parent_path = parent_uri.path();
     let parent_dir = if let Some(last_slash) = parent_path.rfind('/') {
@@ -72,7 +90,7 @@ pub fn resolve_relative_path(parent_uri: &Url, template_path: &str) -> String {
         ""
     };
 
-    let resolved = if template_path.starts_with('/') {
+    if template_path.starts_with('/') {
         template_path.to_string()
     } else {
         let mut parts: Vec<&str> = parent_dir.split('/').filter(|s| !s.is_empty()).collect();
@@ -86,18 +104,181 @@ pub fn resolve_relative_path(parent_uri: &Url, template_path: &str) -> String {
             }
         }
         format!("/{}", parts.join("/"))
-    };
+    }
+}
 
-    resolved
-        .rsplit('/')
-        .next()
-        .unwrap_or(&resolved)
-        .to_string()
+/// 2 つの文字列の編集距離 (Levenshtein 距離、文字単位)
+pub fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// `textDocument/didChange` の content change を順に適用したテキストを返す
+///
+/// `range` のない変更は全文置換 (FULL 同期)、ある変更はその範囲の差分置換
+/// (INCREMENTAL 同期)。位置は LSP の (line, UTF-16 character)
+pub fn apply_content_changes(text: &str, changes: &[TextDocumentContentChangeEvent]) -> String {
+    let mut text = text.to_string();
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = position_to_offset(&text, range.start);
+                let end = position_to_offset(&text, range.end).max(start);
+                text.replace_range(start..end, &change.text);
+            }
+            None => text = change.text.clone(),
+        }
+    }
+    text
+}
+
+/// LSP の (line, UTF-16 character) をバイトオフセットに変換する
+///
+/// 行末 (改行の手前) やテキスト末尾を超える位置はそこに丸める
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |newline| line_start + newline);
+    let line = text[line_start..line_end].trim_end_matches('\r');
+
+    let mut utf16 = 0;
+    for (byte, c) in line.char_indices() {
+        if utf16 >= position.character as usize {
+            return line_start + byte;
+        }
+        utf16 += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// LSP の UTF-16 の列を、同じ行のバイト列に変換する (行末を超える列は行末に丸める)
+///
+/// テキストベースの補完・シグネチャヘルプの文脈判定はバイト列で行単位の slice を取る
+pub fn utf16_col_to_byte_col(text: &str, line: u32, character: u32) -> u32 {
+    let offset = position_to_offset(text, Position::new(line, character));
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    (offset - line_start) as u32
+}
+
+/// 行内のバイト列を LSP の UTF-16 の列に変換する (`utf16_col_to_byte_col` の逆)
+///
+/// JS のインデックスの列 (tree-sitter のバイト列) をクライアントに返すときに使う。
+/// 行末を超える列は行末に丸める
+pub fn byte_col_to_utf16_col(text: &str, line: u32, byte_col: u32) -> u32 {
+    let line_start = position_to_offset(text, Position::new(line, 0));
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |newline| line_start + newline);
+    let mut offset = (line_start + byte_col as usize).min(line_end);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset_to_position(text, offset).character
+}
+
+/// バイトオフセットを LSP の (line, UTF-16 character) に変換する (`position_to_offset` の逆)
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() as u32;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position::new(line, before[line_start..].encode_utf16().count() as u32)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tower_lsp::lsp_types::Range;
+
+    fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(start.0, start.1),
+                Position::new(end.0, end.1),
+            )),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_incremental_changes_match_full_text() {
+        let original = "angular.module('app')\n  .controller('MainCtrl', function($scope) {\n  });\n";
+        let changes = [
+            // 関数本体に 1 行挿入
+            change((1, 44), (1, 44), "\n    $scope.name = '日本語😀';"),
+            // 挿入した行のサロゲートペアの後ろを書き換え (UTF-16 の列)
+            change((2, 24), (2, 26), "!';"),
+            // 複数行にまたがる削除
+            change((0, 21), (1, 2), ""),
+            // 末尾への追加
+            change((3, 0), (3, 0), "// end\n"),
+        ];
+        let expected = "angular.module('app').controller('MainCtrl', function($scope) {\n    $scope.name = '日本語😀!';\n  });\n// end\n";
+        assert_eq!(apply_content_changes(original, &changes), expected);
+
+        // FULL の変更 (range なし) は全文置換で、後続の差分はその上に適用される
+        let full = TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: expected.to_string(),
+        };
+        let changes = [full, change((3, 0), (4, 0), "")];
+        assert_eq!(
+            apply_content_changes("", &changes),
+            expected.trim_end_matches("// end\n")
+        );
+    }
+
+    #[test]
+    fn test_incremental_changes_clamp_positions_and_keep_crlf() {
+        let original = "<div>\r\n  {{ a }}\r\n</div>";
+        // 行末を超える列は改行 (`\r\n`) の手前に丸める
+        let changes = [change((1, 99), (1, 99), "{{ b }}")];
+        assert_eq!(
+            apply_content_changes(original, &changes),
+            "<div>\r\n  {{ a }}{{ b }}\r\n</div>"
+        );
+        // 最終行を超える位置はテキスト末尾
+        let changes = [change((9, 0), (9, 0), "\r\n")];
+        assert_eq!(apply_content_changes(original, &changes), format!("{}\r\n", original));
+    }
+
+    #[test]
+    fn test_byte_col_to_utf16_col() {
+        let text = "a\n/* 名前 */ x\n";
+        // `名前` は 1 文字 3 バイト、UTF-16 では 1 コードユニット
+        assert_eq!(byte_col_to_utf16_col(text, 1, 13), 9);
+        assert_eq!(utf16_col_to_byte_col(text, 1, 9), 13);
+        // 行末を超える列は行末に丸める
+        assert_eq!(byte_col_to_utf16_col(text, 0, 99), 1);
+    }
+
+    #[test]
+    fn test_is_js_file_includes_typescript() {
+        let uri = |path: &str| Url::parse(&format!("file://{}", path)).unwrap();
+        assert!(is_js_file(&uri("/app/ctrl.js")));
+        assert!(is_js_file(&uri("/app/ctrl.ts")));
+        assert!(is_js_file(&uri("/app/view.tsx")));
+        assert!(!is_js_file(&uri("/app/view.html")));
+        assert!(is_typescript_file(&uri("/app/ctrl.ts")));
+        assert!(!is_typescript_file(&uri("/app/ctrl.js")));
+    }
 
     #[test]
     fn test_camel_to_kebab() {
@@ -114,6 +295,15 @@ mod tests {
         assert_eq!(kebab_to_camel("simple"), "simple");
     }
 
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein_distance("userName", "userName"), 0);
+        assert_eq!(levenshtein_distance("usrName", "userName"), 1);
+        assert_eq!(levenshtein_distance("userNmae", "userName"), 2);
+        assert_eq!(levenshtein_distance("", "abc"), 3);
+        assert_eq!(levenshtein_distance("名前", "名"), 1);
+    }
+
     #[test]
     fn test_normalize_template_path() {
         assert_eq!(
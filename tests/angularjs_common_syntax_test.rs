@@ -4,11 +4,14 @@
 ///! LSPのアナライザーが各パターンを正しく認識できるか検証する。
 
 use std::sync::Arc;
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{SemanticTokenType, Url};
 
 use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
 use angularjs_lsp::analyzer::html::HtmlAngularJsAnalyzer;
-use angularjs_lsp::handler::WorkspaceSymbolHandler;
+use angularjs_lsp::config::{CodeLensConfig, ExpressionAttributeMode, WorkspaceSymbolConfig};
+use angularjs_lsp::handler::{
+    CodeLensHandler, DocumentSymbolHandler, SemanticTokensHandler, WorkspaceSymbolHandler,
+};
 use angularjs_lsp::index::Index;
 use angularjs_lsp::model::SymbolKind;
 
@@ -274,6 +277,69 @@ angular.module('app', []).service('DataService', DataService);
         "class-basedサービスのメソッドが認識されるべき");
 }
 
+#[test]
+fn test_service_prototype_methods() {
+    // TypeScript のトランスパイル結果のようなプロトタイプ定義
+    let source = r#"
+var UserService = (function () {
+    function UserService($http) {
+        this.$http = $http;
+    }
+    /** 全件取得 */
+    UserService.prototype.getAll = function () { return this.$http.get('/api/users'); };
+    UserService.prototype.getById = function (id) { return this.$http.get('/api/users/' + id); };
+    return UserService;
+}());
+function OtherThing() {}
+OtherThing.prototype.unrelated = function () {};
+ConfigService.prototype = {
+    load: function (key) {},
+    reset() {}
+};
+function ConfigService() {}
+angular.module('app', [])
+    .service('UserService', ['$http', UserService])
+    .service('ConfigService', ConfigService);
+"#;
+    let index = analyze_js(source);
+    assert!(has_definition(&index, "UserService.getAll", SymbolKind::Method),
+        "prototype 代入のメソッドが認識されるべき");
+    assert!(has_definition(&index, "UserService.getById", SymbolKind::Method));
+    let get_by_id = &index.definitions.get_definitions("UserService.getById")[0];
+    assert_eq!(get_by_id.parameters.as_deref(), Some(&["id".to_string()][..]));
+    let get_all = &index.definitions.get_definitions("UserService.getAll")[0];
+    assert!(get_all.docs.as_deref().is_some_and(|d| d.contains("全件取得")));
+    assert!(has_definition(&index, "ConfigService.load", SymbolKind::Method),
+        "prototype へのオブジェクト代入のメソッドが認識されるべき");
+    assert!(has_definition(&index, "ConfigService.reset", SymbolKind::Method));
+    assert!(!index.definitions.has_definition("UserService.unrelated"),
+        "登録されていないコンストラクタのプロトタイプは対象外");
+    assert!(!index.definitions.has_definition("OtherThing.unrelated"));
+}
+
+#[test]
+fn test_service_object_assign_this() {
+    let source = r#"
+angular.module('app', []).service('ApiService', ['$http', function($http) {
+    var vm = this;
+    function fetch(url) { return $http.get(url); }
+    Object.assign(this, {
+        list: function(page) {},
+        fetch,
+        remove(id) {}
+    });
+    Object.assign(vm, { reload: function() {} });
+    Object.assign({}, { notAMethod: 1 });
+}]);
+"#;
+    let index = analyze_js(source);
+    for method in ["list", "fetch", "remove", "reload"] {
+        assert!(has_definition(&index, &format!("ApiService.{}", method), SymbolKind::Method),
+            "Object.assign(this, ...) のメソッド {} が認識されるべき", method);
+    }
+    assert!(!index.definitions.has_definition("ApiService.notAMethod"));
+}
+
 #[test]
 fn test_service_implicit_injection() {
     let source = r#"
@@ -1973,7 +2039,7 @@ angular.module('myApp', [])
     });
 "#;
     let index = analyze_js(source);
-    let handler = WorkspaceSymbolHandler::new(index);
+    let handler = WorkspaceSymbolHandler::new(index, WorkspaceSymbolConfig::default());
     let symbols = handler.handle("");
 
     let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
@@ -1995,7 +2061,7 @@ angular.module('myApp', [])
     .service('UserService', function() {});
 "#;
     let index = analyze_js(source);
-    let handler = WorkspaceSymbolHandler::new(index);
+    let handler = WorkspaceSymbolHandler::new(index, WorkspaceSymbolConfig::default());
     let symbols = handler.handle("User");
 
     let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
@@ -2011,13 +2077,43 @@ angular.module('myApp', [])
     .controller('UserController', function() {});
 "#;
     let index = analyze_js(source);
-    let handler = WorkspaceSymbolHandler::new(index);
+    let handler = WorkspaceSymbolHandler::new(index, WorkspaceSymbolConfig::default());
     let symbols = handler.handle("user");
 
     let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
     assert!(names.contains(&"UserController"), "大文字小文字を区別せずにマッチすべき");
 }
 
+#[test]
+fn test_workspace_symbol_kind_prefix_and_fuzzy_ranking() {
+    let source = r#"
+angular.module('myApp', [])
+    .controller('UserListController', function() {})
+    .controller('UserController', function() {})
+    .controller('AdminController', function() {})
+    .service('UserService', function() {});
+"#;
+    let index = analyze_js(source);
+    let handler = WorkspaceSymbolHandler::new(index.clone(), WorkspaceSymbolConfig::default());
+
+    // 種別で絞り込み
+    let names: Vec<String> = handler
+        .handle("controller:User")
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, vec!["UserController", "UserListController"]);
+
+    // ファジーマッチ (部分文字シーケンス) は単語境界の一致が上位
+    let names: Vec<String> = handler.handle("ulc").into_iter().map(|s| s.name).collect();
+    assert_eq!(names.first().map(String::as_str), Some("UserListController"));
+    assert!(!names.contains(&"AdminController".to_string()));
+
+    // 上位 N 件に制限
+    let handler = WorkspaceSymbolHandler::new(index, WorkspaceSymbolConfig { max_results: Some(2) });
+    assert_eq!(handler.handle("controller:").len(), 2);
+}
+
 #[test]
 fn test_workspace_symbol_excludes_scope_properties() {
     let source = r#"
@@ -2028,7 +2124,7 @@ angular.module('myApp', [])
     });
 "#;
     let index = analyze_js(source);
-    let handler = WorkspaceSymbolHandler::new(index);
+    let handler = WorkspaceSymbolHandler::new(index, WorkspaceSymbolConfig::default());
     let symbols = handler.handle("");
 
     let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
@@ -2127,6 +2223,106 @@ angular.module('app', []).component('userCard', {
     );
 }
 
+#[test]
+fn test_component_class_controller_constructor_properties_resolve_via_alias() {
+    // ES6 classコントローラーの constructor 内 this.X も $ctrl.X / vm.X から解決できること
+    let js = r#"
+class UserListCtrl {
+    constructor() {
+        this.users = [];
+    }
+    load() {}
+}
+angular.module('app', []).component('userList', {
+    templateUrl: 'views/user-list.html',
+    controller: UserListCtrl
+});
+"#;
+    let html = r#"<div ng-click="$ctrl.load()">{{ $ctrl.users }}</div>"#;
+    let index = analyze_component_with_template(js, html, "file:///views/user-list.html");
+    let html_uri = Url::parse("file:///views/user-list.html").unwrap();
+
+    let resolved = index.resolve_controller_by_alias(&html_uri, 0, "$ctrl");
+    assert_eq!(resolved, Some("UserListCtrl".to_string()));
+    assert!(
+        has_definition(&index, "UserListCtrl.users", SymbolKind::Method),
+        "constructor内の this.users が UserListCtrl.users として登録されるべき"
+    );
+    assert!(has_definition(&index, "UserListCtrl.load", SymbolKind::Method));
+}
+
+#[test]
+fn test_directive_isolate_scope_bindings_resolve_in_template() {
+    use angularjs_lsp::analyzer::html::directives::is_directive_attribute;
+
+    let js = r#"
+angular.module('app', []).directive('userCard', function() {
+    return {
+        scope: {
+            userName: '=',
+            onSave: '&'
+        },
+        templateUrl: 'views/user-card.html'
+    };
+});
+"#;
+    let html = r#"<div ng-click="onSave()">{{ userName }}</div>"#;
+    let index = analyze_component_with_template(js, html, "file:///views/user-card.html");
+    let html_uri = Url::parse("file:///views/user-card.html").unwrap();
+    let js_uri = Url::parse("file:///test.js").unwrap();
+
+    assert!(has_definition(&index, "userCard.$scope.userName", SymbolKind::DirectiveBinding));
+    assert!(has_definition(&index, "userCard.$scope.onSave", SymbolKind::DirectiveBinding));
+
+    // テンプレート内の userName / onSave は isolate scope のバインディングへジャンプする
+    let locations = goto_definition_at(index.clone(), &html_uri, html, 0, 32).unwrap();
+    assert_eq!(locations, vec![(js_uri.clone(), 4)]);
+    let locations = goto_definition_at(index.clone(), &html_uri, html, 0, 17).unwrap();
+    assert_eq!(locations, vec![(js_uri, 5)]);
+
+    // 使用側の属性値は親スコープの式として解析される
+    assert!(is_directive_attribute("user-name", Some("user-card"), &index));
+    assert!(!is_directive_attribute("user-name", Some("div"), &index));
+}
+
+#[test]
+fn test_directive_bind_to_controller_and_require() {
+    let js = r#"
+angular.module('app', [])
+.directive('tabs', function() {
+    return { controller: function() {} };
+})
+.directive('tabPane', function() {
+    return {
+        require: { tabsCtrl: '^^tabs' },
+        scope: {},
+        bindToController: { title: '<' },
+        controller: function() {},
+        controllerAs: 'pane'
+    };
+})
+.directive('tabLink', function() {
+    return { require: ['^tabs', '?ngModel'] };
+})
+.directive('tabBadge', function() {
+    return { require: '?^tabs' };
+});
+"#;
+    let index = analyze_js(js);
+
+    // bindToController は controller (無名なのでディレクティブ名) 上のプロパティ
+    assert!(has_definition(&index, "tabPane.title", SymbolKind::DirectiveBinding));
+    assert!(!has_definition(&index, "tabPane.$scope.title", SymbolKind::DirectiveBinding));
+
+    // require はオブジェクト/配列/文字列のいずれでも依存ディレクティブへの参照になる
+    let refs = index.definitions.get_references("tabs");
+    let lines: Vec<u32> = refs.iter().map(|r| r.span.start_line).collect();
+    assert_eq!(lines.len(), 3, "refs: {:?}", lines);
+    assert!(lines.contains(&7));
+    assert!(lines.contains(&15));
+    assert!(lines.contains(&18));
+}
+
 #[test]
 fn test_component_template_completion_for_alias_prefix_returns_methods() {
     // complete_with_context(Some("lcComp"), ...) が this.X Method を返すことを確認
@@ -2171,6 +2367,51 @@ angular.module('app', []).component('lcComp', {
     );
 }
 
+#[test]
+fn test_completion_resolve_fills_jsdoc_for_angularjs_items() {
+    // AngularJS 由来の補完候補は data に由来を記録し、ドキュメントは resolve で埋める
+    use angularjs_lsp::handler::{angularjs_completion_symbol, CompletionHandler};
+    use tower_lsp::lsp_types::{CompletionItem, CompletionResponse, Documentation};
+
+    let js = r#"
+/**
+ * ユーザー管理サービス
+ */
+angular.module('app', []).service('DocService', ['$http', function($http) {
+    this.getAll = function() { return $http.get('/api/users'); };
+}]);
+"#;
+    let index = analyze_js(js);
+    let handler = CompletionHandler::new(index);
+
+    let items = match handler.complete_with_context(None, None, &[]) {
+        Some(CompletionResponse::Array(items)) => items,
+        _ => panic!("Array response 期待"),
+    };
+    let item = items
+        .into_iter()
+        .find(|i| i.label == "DocService")
+        .expect("DocService が補完候補に含まれるべき");
+    assert_eq!(angularjs_completion_symbol(&item), Some("DocService"));
+    assert!(item.documentation.is_none(), "ドキュメントは resolve まで遅延するべき");
+
+    let resolved = handler.resolve(item);
+    match resolved.documentation {
+        Some(Documentation::MarkupContent(content)) => {
+            assert!(content.value.contains("ユーザー管理サービス"), "{}", content.value)
+        }
+        other => panic!("resolve で JSDoc が埋まるべき: {:?}", other),
+    }
+
+    // AngularJS 由来でない候補 (tsserver 由来など) はそのまま返す
+    let foreign = CompletionItem {
+        label: "toString".to_string(),
+        ..Default::default()
+    };
+    assert!(angularjs_completion_symbol(&foreign).is_none());
+    assert!(handler.resolve(foreign).documentation.is_none());
+}
+
 #[test]
 fn test_html_completion_in_component_template_includes_ctrl_alias_and_methods() {
     // component templateで補完を呼ぶと:
@@ -2288,6 +2529,182 @@ angular.module('app', []).controller('FooCtrl', ['$scope', function($scope) {
     );
 }
 
+#[test]
+fn test_nested_member_chain_completion() {
+    use angularjs_lsp::handler::CompletionHandler;
+    use angularjs_lsp::server::workspace::{get_member_chain_at_cursor, MemberChain};
+    use tower_lsp::lsp_types::CompletionResponse;
+
+    let js = r#"
+angular.module('app', []).controller('FooCtrl', ['$scope', function($scope) {
+    var vm = this;
+    $scope.user = { name: '', email: '', address: { city: '' } };
+    vm.profile = { nickname: '', update: function(force) {} };
+}]);
+"#;
+    let html = r#"
+<div ng-controller="FooCtrl as vm">
+    {{ }}
+</div>
+"#;
+    let index = analyze_html(js, html);
+    assert!(has_definition(&index, "FooCtrl.$scope.user.address.city", SymbolKind::ScopeProperty));
+    assert!(has_definition(&index, "FooCtrl.profile.nickname", SymbolKind::Method));
+
+    let handler = CompletionHandler::new(index);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let labels = |items: Vec<tower_lsp::lsp_types::CompletionItem>| {
+        let mut labels: Vec<String> = items.into_iter().map(|i| i.label).collect();
+        labels.sort();
+        labels
+    };
+
+    // HTML: $scope プロパティのチェーンは 1 階層ずつ
+    assert_eq!(
+        labels(handler.complete_html_member_chain(&html_uri, 2, "user")),
+        vec!["address", "email", "name"]
+    );
+    assert_eq!(
+        labels(handler.complete_html_member_chain(&html_uri, 2, "user.address")),
+        vec!["city"]
+    );
+    // HTML: エイリアス経由 (controller の this.profile)
+    assert_eq!(
+        labels(handler.complete_html_member_chain(&html_uri, 2, "vm.profile")),
+        vec!["nickname", "update"]
+    );
+    // エイリアス単体は通常の補完に任せる
+    assert!(handler.complete_html_member_chain(&html_uri, 2, "vm").is_empty());
+
+    // JS: `$scope.user.` → FooCtrl.$scope.user の子
+    let Some(CompletionResponse::Array(items)) =
+        handler.complete_with_context(Some("$scope.user"), Some("FooCtrl"), &[])
+    else {
+        panic!("completion expected");
+    };
+    assert_eq!(labels(items), vec!["address", "email", "name"]);
+
+    // controller 直下の候補にネストプロパティは混ざらない
+    let Some(CompletionResponse::Array(items)) =
+        handler.complete_with_context(Some("FooCtrl"), None, &[])
+    else {
+        panic!("completion expected");
+    };
+    assert_eq!(labels(items), vec!["profile"]);
+
+    // 子プロパティを持たないメンバー (文字列・配列など) は tsserver に任せる
+    assert!(handler
+        .complete_with_context(Some("$scope.user.name"), Some("FooCtrl"), &[])
+        .is_none());
+
+    assert_eq!(
+        get_member_chain_at_cursor("    $scope.user.address.", 0, 24),
+        Some(MemberChain::Path("$scope.user.address".to_string()))
+    );
+}
+
+#[test]
+fn test_builtin_service_method_completion_requires_injection() {
+    use angularjs_lsp::analyzer::js::builtin_service_methods::is_service_injected_at;
+    use angularjs_lsp::analyzer::js::JsParser;
+    use angularjs_lsp::handler::CompletionHandler;
+    use tower_lsp::lsp_types::CompletionResponse;
+
+    let js = r#"
+angular.module('app', [])
+.controller('ArrayCtrl', ['$scope', '$http', function($scope, http) {
+    $http.
+}])
+.controller('ParamCtrl', function($q) {
+    $q.
+    $http.
+})
+.controller('NamedCtrl', ['$http', function($http) {
+    $http.
+}]);
+"#;
+    let tree = JsParser::new().parse(js).unwrap();
+    let col = |line: usize| js.lines().nth(line).unwrap().len() as u32;
+    // DI 配列の '$http' は `http` で受けているので `$http.` は注入済みではない
+    assert!(!is_service_injected_at(&tree, js, 3, col(3), "$http"));
+    assert!(is_service_injected_at(&tree, js, 6, col(6), "$q"));
+    assert!(!is_service_injected_at(&tree, js, 7, col(7), "$http"));
+    assert!(is_service_injected_at(&tree, js, 10, col(10), "$http"));
+
+    let handler = CompletionHandler::new(analyze_js(js));
+    let labels = |prefix: &str, injected: &[String]| -> Vec<String> {
+        let Some(CompletionResponse::Array(items)) =
+            handler.complete_with_context(Some(prefix), None, injected)
+        else {
+            panic!("completion expected");
+        };
+        items.into_iter().map(|i| i.label).collect()
+    };
+
+    let http = labels("$http", &["$http".to_string()]);
+    for expected in ["get", "post", "put", "delete"] {
+        assert!(http.contains(&expected.to_string()), "{} (labels: {:?})", expected, http);
+    }
+    let q = labels("$q", &["$q".to_string()]);
+    for expected in ["defer", "all", "resolve"] {
+        assert!(q.contains(&expected.to_string()), "{} (labels: {:?})", expected, q);
+    }
+    // 注入されていなければ候補は出さない
+    assert!(labels("$http", &[]).is_empty());
+}
+
+#[test]
+fn test_form_control_state_completion() {
+    use angularjs_lsp::handler::CompletionHandler;
+
+    let js = r#"
+angular.module('app', []).controller('FormCtrl', ['$scope', function($scope) {
+    $scope.user = {};
+}]);
+"#;
+    let html = r#"
+<div ng-controller="FormCtrl">
+  <form name="myForm">
+    <input name="email" ng-model="user.email">
+    <div><select name="role" ng-model="user.role"></select></div>
+    <div ng-form name="address">
+      <input name="city" ng-model="user.city">
+    </div>
+  </form>
+  {{ }}
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let handler = CompletionHandler::new(index);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let labels = |chain: &str| -> Vec<String> {
+        handler
+            .complete_html_member_chain(&html_uri, 9, chain)
+            .into_iter()
+            .map(|i| i.label)
+            .collect()
+    };
+
+    // `myForm.` → コントロール名 (入れ子フォームの内側は含まない) + フォーム状態
+    let form_labels = labels("myForm");
+    assert_eq!(form_labels[..3], ["email", "role", "address"]);
+    assert!(!form_labels.contains(&"city".to_string()));
+    assert!(form_labels.contains(&"$submitted".to_string()));
+
+    // `myForm.email.` → ngModel の状態プロパティ
+    let field_labels = labels("myForm.email");
+    for expected in ["$valid", "$invalid", "$error", "$touched", "$dirty"] {
+        assert!(field_labels.contains(&expected.to_string()), "{} (labels: {:?})", expected, field_labels);
+    }
+    assert!(!field_labels.contains(&"$submitted".to_string()));
+
+    // 入れ子フォーム (`myForm.address.`) は ngModel ではないので状態プロパティを出さない
+    assert!(labels("myForm.address").is_empty());
+
+    // 存在しないコントロール名には何も出さない
+    assert!(labels("myForm.unknown").is_empty());
+}
+
 // ============================================================
 // ng-repeat 特殊変数 ($index, $first, $last, $middle, $odd, $even)
 // ============================================================
@@ -2533,6 +2950,51 @@ angular.module('app', [])
     );
 }
 
+#[test]
+fn test_component_bindings_completion_with_named_controller() {
+    use angularjs_lsp::handler::CompletionHandler;
+
+    // controller 指定ありでも component 名から bindings を引ける
+    let js = r#"
+angular.module('app', []).component('userList', {
+    controller: 'UserListController',
+    bindings: {
+        users: '<',
+        title: '@?',
+        onSelect: '&',
+        showDetails: '&onDetails'
+    }
+});
+"#;
+    let index = analyze_js(js);
+    assert_eq!(
+        index.components.get_bindings("userList"),
+        vec![
+            ("users".to_string(), "<".to_string()),
+            ("title".to_string(), "@?".to_string()),
+            ("onSelect".to_string(), "&".to_string()),
+            ("showDetails".to_string(), "&onDetails".to_string()),
+        ]
+    );
+
+    let handler = CompletionHandler::new(index);
+    let items = handler.complete_component_bindings("user-list", "");
+    let detail_of = |label: &str| {
+        items
+            .iter()
+            .find(|i| i.label == label)
+            .and_then(|i| i.detail.clone())
+            .unwrap_or_else(|| panic!("'{}' が候補に含まれるべき", label))
+    };
+
+    assert!(detail_of("users").contains("one-way binding"));
+    assert!(detail_of("title").contains("[optional]"));
+    assert!(detail_of("on-select").contains("(expression callback)"));
+    // 別名指定がある場合は別名側が属性名になる
+    assert!(detail_of("on-details").contains("(expression callback)"));
+    assert!(!items.iter().any(|i| i.label == "show-details"));
+}
+
 #[test]
 fn test_directive_completion_context_returns_element_tag_name() {
     use std::sync::Arc;
@@ -2568,69 +3030,596 @@ fn test_directive_completion_context_returns_element_tag_name() {
 }
 
 // ============================================================
-// $mdDialog.show のテンプレート/コントローラーバインディング
+// 組み込み ng-* 属性名補完
 // ============================================================
 
 #[test]
-fn test_md_dialog_template_binding() {
-    use angularjs_lsp::model::BindingSource;
+fn test_ng_attribute_completion_context_collects_existing_attrs() {
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer);
 
-    let source = r#"
-angular.module('app', []).controller('DialogCtrl', ['$mdDialog', function($mdDialog) {
-    $mdDialog.show({
-        controller: 'EditDialogCtrl',
-        templateUrl: 'templates/edit-dialog.html'
-    });
-}]);
-"#;
-    let index = analyze_js(source);
-    let bindings = index.templates.get_all_template_bindings();
-    let dialog_binding = bindings
-        .iter()
-        .find(|b| b.template_path.contains("edit-dialog.html"))
-        .expect("$mdDialog.show のテンプレートバインディングが登録されるべき");
+    let html = r#"<input type="text" ng-model="vm.name" ng- disabled>"#;
+    let col = html.find("ng- ").unwrap() + "ng-".len();
 
-    assert_eq!(dialog_binding.controller_name, "EditDialogCtrl");
-    assert_eq!(
-        dialog_binding.source,
-        BindingSource::MdDialog,
-        "BindingSource は MdDialog であるべき"
-    );
+    let (prefix, existing) = html_analyzer
+        .get_ng_attribute_completion_context(html, 0, col as u32)
+        .expect("`ng-` 入力中の属性名位置で context が返るべき");
+    assert_eq!(prefix, "ng-");
+    assert_eq!(existing, vec!["type", "ng-model", "disabled"]);
+
+    // `ng-` で始まらない属性名では発火しない
+    let col_type = html.find("type").unwrap() + 2;
+    assert!(html_analyzer
+        .get_ng_attribute_completion_context(html, 0, col_type as u32)
+        .is_none());
+
+    // 属性値の中では発火しない
+    let col_value = html.find("vm.name").unwrap() + 2;
+    assert!(html_analyzer
+        .get_ng_attribute_completion_context(html, 0, col_value as u32)
+        .is_none());
+
+    // 列はバイト列。前の属性値のマルチバイト文字で位置がずれない
+    let html = r#"<div title="日本" ng- hidden>"#;
+    let col = html.find("ng- ").unwrap() + "ng-".len();
+    let (prefix, existing) = html_analyzer
+        .get_ng_attribute_completion_context(html, 0, col as u32)
+        .expect("マルチバイト文字の後でも context が返るべき");
+    assert_eq!(prefix, "ng-");
+    assert_eq!(existing, vec!["title", "hidden"]);
 }
 
 #[test]
-fn test_md_dialog_controller_reference_registered() {
-    let source = r#"
-angular.module('app', []).controller('PageCtrl', ['$mdDialog', function($mdDialog) {
-    $mdDialog.show({
-        controller: 'ConfirmDialogCtrl',
-        templateUrl: 'templates/confirm.html'
-    });
-}]);
-"#;
-    let index = analyze_js(source);
+fn test_ng_attribute_completion_excludes_existing_attrs() {
+    use angularjs_lsp::handler::CompletionHandler;
+
+    let handler = CompletionHandler::new(Arc::new(Index::new()));
+
+    let items = handler.complete_ng_attributes("ng-", &["data-ng-model".to_string()]);
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"ng-click"), "labels: {:?}", labels);
+    assert!(labels.contains(&"ng-repeat"), "labels: {:?}", labels);
     assert!(
-        has_reference(&index, "ConfirmDialogCtrl"),
-        "$mdDialog.show の controller 参照が登録されるべき"
+        !labels.contains(&"ng-model"),
+        "data-ng-model が既にある場合 ng-model は提示しないべき (labels: {:?})",
+        labels
+    );
+    assert!(
+        labels.iter().all(|l| l.starts_with("ng-")),
+        "`ng-` 入力中は data- 版を混ぜないべき (labels: {:?})",
+        labels
     );
+
+    let data_items = handler.complete_ng_attributes("data-ng-cl", &[]);
+    let data_labels: Vec<&str> = data_items.iter().map(|i| i.label.as_str()).collect();
+    assert!(data_labels.contains(&"data-ng-click"), "labels: {:?}", data_labels);
+    assert!(data_labels.contains(&"data-ng-class"), "labels: {:?}", data_labels);
+    assert!(data_labels.contains(&"data-ng-cloak"), "labels: {:?}", data_labels);
 }
 
+// ============================================================
+// フィルター名補完
+// ============================================================
+
 #[test]
-fn test_md_dialog_show_without_template_does_not_register() {
-    // $mdDialog.confirm() / $mdDialog.alert() などプリセットビルダーは
-    // show() ではないため対象外。show() でもオブジェクト引数を取らないものは無視。
-    let source = r#"
-angular.module('app', []).controller('PageCtrl', ['$mdDialog', function($mdDialog) {
-    $mdDialog.show($mdDialog.confirm().title('確認'));
-}]);
-"#;
-    let index = analyze_js(source);
-    let bindings = index.templates.get_all_template_bindings();
-    assert!(
-        bindings.is_empty(),
-        "オブジェクト引数を取らない $mdDialog.show は無視されるべき (got: {:?})",
-        bindings.iter().map(|b| &b.template_path).collect::<Vec<_>>()
-    );
+fn test_filter_position_detection() {
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer);
+
+    let at_end = |html: &str| html_analyzer.is_in_filter_position(html, 0, html.len() as u32);
+
+    assert!(at_end("<p>{{ amount | "));
+    assert!(at_end("<p>{{ amount | curr"));
+    assert!(at_end("<li ng-repeat=\"x in items | order"));
+    assert!(!at_end("<p>{{ amount | currency : "), "フィルター引数位置では発火しない");
+    assert!(!at_end("<p>{{ a || "), "`||` は論理 OR なので発火しない");
+    assert!(!at_end("<p>{{ amount "), "パイプが無ければ発火しない");
+    assert!(!at_end("<p>a | "), "Angular コンテキスト外では発火しない");
+    // 列はバイト列。カーソル前のマルチバイト文字で位置がずれない
+    assert!(at_end("<p title=\"日本語\">{{ amount | "));
+}
+
+#[test]
+fn test_filter_completion_lists_user_and_builtin_filters() {
+    use angularjs_lsp::handler::CompletionHandler;
+
+    let js = r#"
+angular.module('shop', [])
+    .controller('CartCtrl', function() {})
+    .filter('price', function() { return function(x) { return x; }; });
+"#;
+    let index = analyze_js(js);
+    let handler = CompletionHandler::new(index);
+
+    let items = handler.complete_filters();
+    let price = items
+        .iter()
+        .find(|i| i.label == "price")
+        .expect("ユーザー定義フィルター 'price' が候補に含まれるべき");
+    assert_eq!(price.detail.as_deref(), Some("shop (filter)"));
+
+    let currency = items
+        .iter()
+        .find(|i| i.label == "currency")
+        .expect("組み込みフィルター 'currency' が候補に含まれるべき");
+    assert_eq!(currency.detail.as_deref(), Some("builtin filter"));
+    assert!(items.iter().any(|i| i.label == "orderBy"));
+}
+
+// ============================================================
+// angular.module 依存モジュール名補完
+// ============================================================
+
+#[test]
+fn test_module_dependency_context_inside_deps_array() {
+    use angularjs_lsp::server::workspace::get_module_dependency_context;
+
+    let js = "angular.module('app', ['ngRoute', 'sh', 'common.ui']);";
+    let col = js.find("'sh'").unwrap() + "'sh".len();
+
+    let (prefix, excluded) = get_module_dependency_context(js, 0, col as u32)
+        .expect("依存配列の文字列リテラル内では context が返るべき");
+    assert_eq!(prefix, "sh");
+    assert!(excluded.contains(&"ngRoute".to_string()), "excluded: {:?}", excluded);
+    assert!(excluded.contains(&"common.ui".to_string()), "excluded: {:?}", excluded);
+    assert!(excluded.contains(&"app".to_string()), "定義中のモジュール自身も除外: {:?}", excluded);
+    assert!(!excluded.contains(&"sh".to_string()), "入力中のリテラルは除外しない");
+}
+
+#[test]
+fn test_module_dependency_context_multiline_and_negative_cases() {
+    use angularjs_lsp::server::workspace::get_module_dependency_context;
+
+    let js = "angular.module('app', [\n    'ngAnimate',\n    'ng\n]);";
+    let (prefix, excluded) = get_module_dependency_context(js, 2, 7)
+        .expect("複数行の依存配列でも context が返るべき");
+    assert_eq!(prefix, "ng");
+    assert!(excluded.contains(&"ngAnimate".to_string()));
+
+    // モジュール名 (第1引数) の位置では発火しない
+    let getter = "angular.module('ap";
+    assert!(get_module_dependency_context(getter, 0, getter.len() as u32).is_none());
+
+    // 文字列リテラル外では発火しない
+    let outside = "angular.module('app', [";
+    assert!(get_module_dependency_context(outside, 0, outside.len() as u32).is_none());
+
+    // 呼び出しが閉じた後の別の配列では発火しない
+    let closed = "angular.module('app', []).constant('X', ['a";
+    assert!(get_module_dependency_context(closed, 0, closed.len() as u32).is_none());
+}
+
+#[test]
+fn test_module_dependency_completion_excludes_listed_modules() {
+    use angularjs_lsp::handler::CompletionHandler;
+
+    let js = r#"
+angular.module('shared', []);
+angular.module('admin', []);
+"#;
+    let index = analyze_js(js);
+    let handler = CompletionHandler::new(index);
+
+    let items = handler.complete_module_dependencies("", &["admin".to_string()]);
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert!(labels.contains(&"shared"), "labels: {:?}", labels);
+    assert!(labels.contains(&"ngRoute"), "labels: {:?}", labels);
+    assert!(!labels.contains(&"admin"), "列挙済みは除外されるべき (labels: {:?})", labels);
+
+    let ng_items = handler.complete_module_dependencies("ngA", &[]);
+    let ng_labels: Vec<&str> = ng_items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(ng_labels, vec!["ngAnimate", "ngAria"]);
+}
+
+// ============================================================
+// templateUrl / ng-include パス補完
+// ============================================================
+
+#[test]
+fn test_template_path_context_detection() {
+    use angularjs_lsp::server::workspace::get_template_path_context;
+
+    let js = "    templateUrl: 'views/us";
+    assert_eq!(
+        get_template_path_context(js, 0, js.len() as u32, false).as_deref(),
+        Some("views/us")
+    );
+
+    let js_quoted_key = "    \"templateUrl\": \"";
+    assert_eq!(
+        get_template_path_context(js_quoted_key, 0, js_quoted_key.len() as u32, false).as_deref(),
+        Some("")
+    );
+
+    // templateUrl 以外のプロパティ、クエリパラメータ入力中は対象外
+    let other = "    myTemplateUrl: 'views/";
+    assert!(get_template_path_context(other, 0, other.len() as u32, false).is_none());
+    let query = "    templateUrl: 'views/user.html?v=1";
+    assert!(get_template_path_context(query, 0, query.len() as u32, false).is_none());
+
+    // 既存値にクエリパラメータがあってもカーソル前のパスだけ返す
+    let existing = "    templateUrl: 'views/user.html?v=123',";
+    let col = existing.find("user").unwrap() as u32;
+    assert_eq!(
+        get_template_path_context(existing, 0, col, false).as_deref(),
+        Some("views/")
+    );
+
+    let html = r#"<div ng-include="'partials/he"#;
+    assert_eq!(
+        get_template_path_context(html, 0, html.len() as u32, true).as_deref(),
+        Some("partials/he")
+    );
+    let element = r#"<ng-include src="'partials/"#;
+    assert_eq!(
+        get_template_path_context(element, 0, element.len() as u32, true).as_deref(),
+        Some("partials/")
+    );
+    // 変数式 (文字列リテラルでない) は対象外
+    let expr = r#"<div ng-include="vm.tpl"#;
+    assert!(get_template_path_context(expr, 0, expr.len() as u32, true).is_none());
+}
+
+#[test]
+fn test_template_path_entries_are_listed_per_directory() {
+    use angularjs_lsp::server::workspace::list_template_path_entries;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("app/views/admin")).unwrap();
+    std::fs::create_dir_all(root.join("node_modules/lib")).unwrap();
+    std::fs::write(root.join("app/views/user.html"), "").unwrap();
+    std::fs::write(root.join("app/views/user.js"), "").unwrap();
+    std::fs::write(root.join("app/views/admin/panel.html"), "").unwrap();
+    std::fs::write(root.join("index.html"), "").unwrap();
+
+    let base_dirs = vec![root.join("app"), root.to_path_buf()];
+
+    let top = list_template_path_entries(&base_dirs, "", root, None);
+    assert!(top.contains(&("views".to_string(), true)), "top: {:?}", top);
+    assert!(top.contains(&("index.html".to_string(), false)), "top: {:?}", top);
+    assert!(!top.iter().any(|(n, _)| n == "node_modules"), "top: {:?}", top);
+
+    let views = list_template_path_entries(&base_dirs, "views/us", root, None);
+    assert_eq!(
+        views,
+        vec![("admin".to_string(), true), ("user.html".to_string(), false)]
+    );
+
+    let absolute = list_template_path_entries(&base_dirs, "/app/views/admin/", root, None);
+    assert_eq!(absolute, vec![("panel.html".to_string(), false)]);
+}
+
+// ============================================================
+// ng-include パス文字列からの定義ジャンプ
+// ============================================================
+
+/// 定義ジャンプ先の (URI, 開始行) 一覧
+fn goto_definition_at(
+    index: Arc<Index>,
+    uri: &Url,
+    source: &str,
+    line: u32,
+    character: u32,
+) -> Option<Vec<(Url, u32)>> {
+    use angularjs_lsp::handler::DefinitionHandler;
+    use tower_lsp::lsp_types::{
+        GotoDefinitionParams, GotoDefinitionResponse, Position, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let params = GotoDefinitionParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line, character },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    match DefinitionHandler::new(index).goto_definition_with_source(params, Some(source))? {
+        GotoDefinitionResponse::Array(locations) => Some(
+            locations
+                .into_iter()
+                .map(|l| (l.uri, l.range.start.line))
+                .collect(),
+        ),
+        GotoDefinitionResponse::Scalar(l) => Some(vec![(l.uri, l.range.start.line)]),
+        GotoDefinitionResponse::Link(links) => Some(
+            links
+                .into_iter()
+                .map(|l| (l.target_uri, l.target_selection_range.start.line))
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn test_ng_include_path_goto_definition_prefers_relative_file() {
+    let index = Arc::new(Index::new());
+    let parent = Url::parse("file:///app/views/index.html").unwrap();
+    let relative = Url::parse("file:///app/views/partials/child.html").unwrap();
+    let other = Url::parse("file:///app/admin/partials/child.html").unwrap();
+    for uri in [&parent, &relative, &other] {
+        index.mark_html_analyzed(uri);
+    }
+
+    let html = r#"<div ng-include="'partials/child.html?v=1'"></div>"#;
+    let col = html.find("child").unwrap() as u32;
+    let targets = goto_definition_at(index.clone(), &parent, html, 0, col)
+        .expect("ng-include のパス上では対象ファイルに解決されるべき");
+    assert_eq!(targets, vec![(relative.clone(), 0)]);
+
+    // 要素形式 + プロジェクトルート相対パス
+    let element = r#"<ng-include src="'app/admin/partials/child.html'"></ng-include>"#;
+    let col = element.find("admin").unwrap() as u32;
+    let targets = goto_definition_at(index.clone(), &parent, element, 0, col)
+        .expect("ルート相対パスでも解決されるべき");
+    assert_eq!(targets, vec![(other, 0)]);
+}
+
+#[test]
+fn test_ng_include_path_goto_definition_missing_file() {
+    let index = Arc::new(Index::new());
+    let parent = Url::parse("file:///app/index.html").unwrap();
+    index.mark_html_analyzed(&parent);
+
+    let html = r#"<div ng-include="'views/missing.html'"></div>"#;
+    let col = html.find("missing").unwrap() as u32;
+    assert!(goto_definition_at(index, &parent, html, 0, col).is_none());
+
+    use angularjs_lsp::analyzer::html::ng_include::find_ng_include_path_at;
+    assert_eq!(
+        find_ng_include_path_at(html, 0, col).as_deref(),
+        Some("views/missing.html")
+    );
+    // 属性名の上はパス文字列ではない
+    assert!(find_ng_include_path_at(html, 0, 6).is_none());
+}
+
+// ============================================================
+// templateUrl ⇔ テンプレート HTML ⇔ コントローラーの相互ジャンプ
+// ============================================================
+
+#[test]
+fn test_template_url_goto_definition_opens_template() {
+    let js = r#"angular.module('app', []).config(function($routeProvider) {
+    $routeProvider.when('/users', {
+        templateUrl: 'views/users.html?v=2',
+        controller: 'UsersCtrl'
+    });
+});
+"#;
+    let index = analyze_js(js);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let template_uri = Url::parse("file:///app/views/users.html").unwrap();
+    index.mark_html_analyzed(&template_uri);
+    index.mark_html_analyzed(&Url::parse("file:///app/views/admin.html").unwrap());
+
+    let line = 2;
+    let col = js.lines().nth(2).unwrap().find("users.html").unwrap() as u32;
+    let targets = goto_definition_at(index.clone(), &js_uri, js, line, col)
+        .expect("templateUrl の文字列上ではテンプレートへジャンプできるべき");
+    assert_eq!(targets, vec![(template_uri.clone(), 0)]);
+
+    // templateUrl のキー部分では発火しない
+    assert!(goto_definition_at(index.clone(), &js_uri, js, line, 10).is_none());
+
+    // 列は UTF-16。行内のマルチバイト文字の後ろでもパス文字列を拾う
+    let js = "angular.module('app').component('users', { /* 一覧 */ templateUrl: 'views/users.html' });";
+    let line_text = js.lines().next().unwrap();
+    let col = line_text[..line_text.find("views").unwrap()].encode_utf16().count() as u32;
+    let targets = goto_definition_at(index, &js_uri, js, 0, col)
+        .expect("マルチバイト文字の後ろの templateUrl でもジャンプできるべき");
+    assert_eq!(targets, vec![(template_uri, 0)]);
+}
+
+#[test]
+fn test_template_goto_definition_lists_bound_controllers() {
+    let js = r#"angular.module('app', [])
+.controller('UsersCtrl', function($scope) {})
+.controller('UserModalCtrl', function($scope) {})
+.config(function($routeProvider) {
+    $routeProvider.when('/users', {
+        templateUrl: 'views/users.html',
+        controller: 'UsersCtrl'
+    });
+})
+.run(function($uibModal) {
+    $uibModal.open({
+        templateUrl: 'views/users.html',
+        controller: 'UserModalCtrl'
+    });
+});
+"#;
+    let index = analyze_js(js);
+    let template_uri = Url::parse("file:///app/views/users.html").unwrap();
+    let html = "<div>\n  <span>users</span>\n</div>\n";
+
+    let targets = goto_definition_at(index.clone(), &template_uri, html, 0, 1)
+        .expect("テンプレート先頭からバインド元コントローラーへジャンプできるべき");
+    let lines: Vec<u32> = targets.iter().map(|(_, line)| *line).collect();
+    assert_eq!(targets.len(), 2, "route と modal の両方を返すべき: {:?}", targets);
+    assert!(lines.contains(&1) && lines.contains(&2), "targets: {:?}", targets);
+
+    // 先頭行以外では発火しない
+    assert!(goto_definition_at(index, &template_uri, html, 1, 4).is_none());
+}
+
+// ============================================================
+// カスタムディレクティブ使用箇所からの定義ジャンプ
+// ============================================================
+
+#[test]
+fn test_directive_usage_goto_definition_element_and_attribute() {
+    let js = r#"angular.module('app', [])
+.directive('userCard', function() {
+    return { restrict: 'E' };
+})
+.directive('myHighlight', function() {
+    return { restrict: 'A' };
+})
+.component('heroDetail', {
+    bindings: { hero: '<' }
+});
+"#;
+    let html = r#"<user-card></user-card>
+<div data-my-highlight="'red'"></div>
+<hero-detail hero="vm.hero"></hero-detail>
+<div hero-detail></div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let js_uri = Url::parse("file:///test.js").unwrap();
+
+    // 要素 (開始タグ / 閉じタグ) → directive
+    let open_tag = goto_definition_at(index.clone(), &html_uri, html, 0, 3)
+        .expect("<user-card> から directive 定義へジャンプできるべき");
+    assert_eq!(open_tag, vec![(js_uri.clone(), 1)]);
+    let close_tag = goto_definition_at(index.clone(), &html_uri, html, 0, 15)
+        .expect("</user-card> から directive 定義へジャンプできるべき");
+    assert_eq!(close_tag, vec![(js_uri.clone(), 1)]);
+
+    // data- 付き属性 → directive
+    let attr = goto_definition_at(index.clone(), &html_uri, html, 1, 10)
+        .expect("data-my-highlight 属性から directive 定義へジャンプできるべき");
+    assert_eq!(attr, vec![(js_uri.clone(), 4)]);
+
+    // 要素 → component
+    let component = goto_definition_at(index.clone(), &html_uri, html, 2, 3)
+        .expect("<hero-detail> から component 定義へジャンプできるべき");
+    assert_eq!(component.len(), 1);
+    assert_eq!(component[0].0, js_uri);
+
+    // component は属性としては使えないので解決しない
+    assert!(goto_definition_at(index, &html_uri, html, 3, 8).is_none());
+}
+
+// ============================================================
+// フィルター使用箇所の参照検索と定義ジャンプ
+// ============================================================
+
+#[test]
+fn test_filter_usages_resolve_to_definition_and_references() {
+    use angularjs_lsp::handler::ReferencesHandler;
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let js = r#"angular.module('app', [])
+.filter('shortName', function() {
+    return function(input) { return input; };
+});
+"#;
+    let html = r#"<p>{{ user.name | shortName }}</p>
+<li ng-repeat="u in users | filter:query | orderBy:'name'">{{ u.name | shortName:10 }}</li>
+<p title="{{ a || b | shortName }}">{{ '|x' }}</p>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let js_uri = Url::parse("file:///test.js").unwrap();
+
+    let refs = index.definitions.get_references("shortName");
+    let mut positions: Vec<(u32, u32)> = refs
+        .iter()
+        .filter(|r| r.uri == html_uri)
+        .map(|r| (r.span.start_line, r.span.start_col))
+        .collect();
+    positions.sort();
+    assert_eq!(positions, vec![(0, 18), (1, 71), (2, 22)]);
+    // `||` と文字列リテラル内の `|` はフィルターとして拾わない
+    assert!(index.definitions.get_references("b").is_empty());
+    assert!(index.definitions.get_references("x").is_empty());
+    // 組み込みフィルターも参照として登録される
+    assert_eq!(index.definitions.get_references("orderBy").len(), 1);
+
+    // 使用箇所 → 定義
+    let targets = goto_definition_at(index.clone(), &html_uri, html, 0, 20)
+        .expect("フィルター名から定義へジャンプできるべき");
+    assert_eq!(targets, vec![(js_uri.clone(), 1)]);
+
+    // 定義 → 全使用箇所
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: js_uri },
+            position: Position { line: 1, character: 10 },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext { include_declaration: false },
+    };
+    let locations = ReferencesHandler::new(index)
+        .find_references(params)
+        .expect("フィルター定義から参照検索できるべき");
+    assert_eq!(locations.iter().filter(|l| l.uri == html_uri).count(), 3);
+}
+
+// ============================================================
+// $mdDialog.show のテンプレート/コントローラーバインディング
+// ============================================================
+
+#[test]
+fn test_md_dialog_template_binding() {
+    use angularjs_lsp::model::BindingSource;
+
+    let source = r#"
+angular.module('app', []).controller('DialogCtrl', ['$mdDialog', function($mdDialog) {
+    $mdDialog.show({
+        controller: 'EditDialogCtrl',
+        templateUrl: 'templates/edit-dialog.html'
+    });
+}]);
+"#;
+    let index = analyze_js(source);
+    let bindings = index.templates.get_all_template_bindings();
+    let dialog_binding = bindings
+        .iter()
+        .find(|b| b.template_path.contains("edit-dialog.html"))
+        .expect("$mdDialog.show のテンプレートバインディングが登録されるべき");
+
+    assert_eq!(dialog_binding.controller_name, "EditDialogCtrl");
+    assert_eq!(
+        dialog_binding.source,
+        BindingSource::MdDialog,
+        "BindingSource は MdDialog であるべき"
+    );
+}
+
+#[test]
+fn test_md_dialog_controller_reference_registered() {
+    let source = r#"
+angular.module('app', []).controller('PageCtrl', ['$mdDialog', function($mdDialog) {
+    $mdDialog.show({
+        controller: 'ConfirmDialogCtrl',
+        templateUrl: 'templates/confirm.html'
+    });
+}]);
+"#;
+    let index = analyze_js(source);
+    assert!(
+        has_reference(&index, "ConfirmDialogCtrl"),
+        "$mdDialog.show の controller 参照が登録されるべき"
+    );
+}
+
+#[test]
+fn test_md_dialog_show_without_template_does_not_register() {
+    // $mdDialog.confirm() / $mdDialog.alert() などプリセットビルダーは
+    // show() ではないため対象外。show() でもオブジェクト引数を取らないものは無視。
+    let source = r#"
+angular.module('app', []).controller('PageCtrl', ['$mdDialog', function($mdDialog) {
+    $mdDialog.show($mdDialog.confirm().title('確認'));
+}]);
+"#;
+    let index = analyze_js(source);
+    let bindings = index.templates.get_all_template_bindings();
+    assert!(
+        bindings.is_empty(),
+        "オブジェクト引数を取らない $mdDialog.show は無視されるべき (got: {:?})",
+        bindings.iter().map(|b| &b.template_path).collect::<Vec<_>>()
+    );
 }
 
 #[test]
@@ -3157,6 +4146,56 @@ angular.module('app', []).controller('Ctrl', ['$scope', function($scope) {
     );
 }
 
+#[test]
+fn test_configured_expression_attributes_are_parsed() {
+    // ajsconfig.json の expression_attributes で登録した属性:
+    // - mode 省略 ("expression") は属性値全体を式として解析
+    // - mode "literal" は補間 ({{ }}) のみ解析
+    let js = r#"
+angular.module('app', []).controller('Ctrl', ['$scope', function($scope) {
+    $scope.user = {};
+    $scope.title = '';
+}]);
+"#;
+    let html = r#"
+<div ng-controller="Ctrl">
+    <input data-my-validate="user.email">
+    <span my-label="user.name {{ title }}">label</span>
+</div>
+"#;
+    let index = Arc::new(Index::new());
+    index.html.set_expression_attributes([
+        ("my-validate".to_string(), ExpressionAttributeMode::Expression),
+        ("my-label".to_string(), ExpressionAttributeMode::Literal),
+    ]);
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+    js_analyzer.analyze_document(&Url::parse("file:///test.js").unwrap(), js);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    html_analyzer.analyze_document(&html_uri, html);
+
+    let refs = index.html.get_html_scope_references(&html_uri);
+    let lines: Vec<(&str, u32)> = refs
+        .iter()
+        .map(|r| (r.property_path.as_str(), r.start_line))
+        .collect();
+    assert!(
+        lines.contains(&("user", 2)),
+        "expression モードの属性値は式として解析されるべき (refs: {:?})",
+        lines
+    );
+    assert!(
+        lines.contains(&("title", 3)),
+        "literal モードでも補間は解析されるべき (refs: {:?})",
+        lines
+    );
+    assert!(
+        !lines.contains(&("user", 3)),
+        "literal モードの属性値は式として解析されるべきでない (refs: {:?})",
+        lines
+    );
+}
+
 #[test]
 fn test_component_binding_attribute_value_is_parsed_as_expression() {
     // .component('userCard', { bindings: { user: '<', onSelect: '&' } })
@@ -3434,9 +4473,54 @@ angular.module('app', []).controller('PaletteCtrl', ['$scope', function($scope)
 }
 
 #[test]
-fn test_ng_pattern_value_is_not_treated_as_scope_reference() {
-    // `ng-pattern="/^\d+$/"` のような正規表現リテラルは scope 参照ではなく、
-    // インライン正規表現として AngularJS が `$eval` する。tree-sitter-javascript
+fn test_ng_switch_when_variable_is_registered_as_scope_reference() {
+    // `ng-switch-when="activeStatus"` のようにスコープ変数を指す場合は参照として登録し、
+    // `'active'` のような文字列リテラルや未定義の case ラベルは登録しない
+    let js = r#"
+angular.module('app', []).controller('StatusCtrl', ['$scope', function($scope) {
+    $scope.status = 'active';
+    $scope.activeStatus = 'active';
+}]);
+"#;
+    let html = r#"
+<div ng-controller="StatusCtrl" ng-switch="status">
+    <div ng-switch-when="activeStatus">Active</div>
+    <div ng-switch-when="'inactive'">Inactive</div>
+    <div ng-switch-when="pending">Pending</div>
+    <div ng-switch-default>Unknown</div>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let names: Vec<&str> = scope_refs.iter().map(|r| r.property_path.as_str()).collect();
+    assert!(names.contains(&"status"), "ng-switch の値は参照 (refs: {:?})", names);
+    assert!(
+        names.contains(&"activeStatus"),
+        "定義済みのスコープ変数は参照として登録されるべき (refs: {:?})",
+        names
+    );
+    for literal in &["inactive", "'inactive'", "pending"] {
+        assert!(
+            !names.contains(literal),
+            "ng-switch-when=\"{}\" は参照として登録されてはいけない (refs: {:?})",
+            literal,
+            names
+        );
+    }
+
+    let active = scope_refs
+        .iter()
+        .find(|r| r.property_path == "activeStatus")
+        .unwrap();
+    assert_eq!((active.start_line, active.start_col, active.end_col), (2, 25, 37));
+}
+
+#[test]
+fn test_ng_pattern_value_is_not_treated_as_scope_reference() {
+    // `ng-pattern="/^\d+$/"` のような正規表現リテラルは scope 参照ではなく、
+    // インライン正規表現として AngularJS が `$eval` する。tree-sitter-javascript
     // が regex literal を解釈するので false positive は出にくいが、念のため
     // 文字列フォーム `ng-pattern="^[a-z]+$"` のようなパターンも literal として
     // 扱い scope ref にしない。
@@ -3521,6 +4605,180 @@ angular.module('app', []).controller('GalleryCtrl', ['$scope', function($scope)
     }
 }
 
+#[test]
+fn test_ng_bind_attributes_register_scope_references() {
+    // ng-bind / ng-bind-html は式、ng-bind-template は補間テンプレートとして解析される
+    let js = r#"
+angular.module('app', []).controller('ProfileCtrl', ['$scope', function($scope) {
+    $scope.userName = 'mochi';
+    $scope.htmlContent = '<b>hi</b>';
+    $scope.firstName = 'a';
+    $scope.lastName = 'b';
+}]);
+"#;
+    let html = r#"
+<div ng-controller="ProfileCtrl">
+    <span ng-bind="userName"></span>
+    <span data-ng-bind-html="htmlContent"></span>
+    <span ng-bind-template="{{firstName}} {{lastName}}"></span>
+    <span data-ng-bind-template="Hello {{userName}}"></span>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let positions = |name: &str| -> Vec<(u32, u32)> {
+        scope_refs
+            .iter()
+            .filter(|r| r.property_path == name)
+            .map(|r| (r.start_line, r.start_col))
+            .collect()
+    };
+    assert_eq!(positions("userName"), vec![(2, 19), (5, 41)]);
+    assert_eq!(positions("htmlContent"), vec![(3, 29)]);
+    assert_eq!(positions("firstName"), vec![(4, 30)]);
+    assert_eq!(positions("lastName"), vec![(4, 44)]);
+    assert!(
+        !scope_refs.iter().any(|r| r.property_path.contains('{') || r.property_path == "Hello"),
+        "ng-bind-template のテンプレート文字列が式として解析されてはいけない"
+    );
+}
+
+#[test]
+fn test_one_time_binding_prefix_is_stripped_from_references() {
+    // `::` ワンタイムバインディングは式の一部ではないので、直後の識別子を参照として登録する
+    let js = r#"
+angular.module('app', []).controller('OnceCtrl', ['$scope', function($scope) {
+    $scope.userName = 'mochi';
+    $scope.isReady = true;
+    $scope.items = [];
+}]);
+"#;
+    let html = r#"
+<div ng-controller="OnceCtrl as vm">
+    <span>{{ ::userName }}</span>
+    <div ng-if="::isReady"></div>
+    <div ng-show="::isReady && userName.length"></div>
+    <li ng-repeat="item in ::items">{{::item}}</li>
+    <span>{{ :: vm.userName | uppercase }}</span>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let positions = |name: &str| -> Vec<(u32, u32, u32)> {
+        scope_refs
+            .iter()
+            .filter(|r| r.property_path == name)
+            .map(|r| (r.start_line, r.start_col, r.end_col))
+            .collect()
+    };
+    assert_eq!(positions("userName"), vec![(2, 15, 23), (4, 31, 39)]);
+    assert_eq!(positions("isReady"), vec![(3, 18, 25), (4, 20, 27)]);
+    assert_eq!(positions("items"), vec![(5, 29, 34)]);
+    assert_eq!(positions("vm.userName"), vec![(6, 19, 27)]);
+    assert!(
+        !scope_refs.iter().any(|r| r.property_path.contains(':')),
+        "`::` を含む名前で登録されてはいけない"
+    );
+}
+
+#[test]
+fn test_filter_arguments_are_registered_as_scope_references() {
+    // フィルター引数は式として評価され、フィルター名自体はスコープ参照にならない
+    let js = r#"
+angular.module('app', []).controller('ListCtrl', ['$scope', function($scope) {
+    $scope.items = [];
+    $scope.searchText = '';
+    $scope.sortKey = 'name';
+    $scope.reverse = false;
+    $scope.limit = 10;
+}]);
+"#;
+    let html = r#"
+<div ng-controller="ListCtrl">
+    <span>{{ items | filter: searchText | orderBy: sortKey : reverse }}</span>
+    <li ng-repeat="item in items | filter: {name: searchText} | limitTo: limit">{{ item }}</li>
+    <span>{{ items | date: 'short' | orderBy:(sortKey || 'id') }}</span>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let positions = |name: &str| -> Vec<(u32, u32, u32)> {
+        let mut positions: Vec<_> = scope_refs
+            .iter()
+            .filter(|r| r.property_path == name)
+            .map(|r| (r.start_line, r.start_col, r.end_col))
+            .collect();
+        positions.sort();
+        positions
+    };
+    assert_eq!(positions("searchText"), vec![(2, 29, 39), (3, 50, 60)]);
+    assert_eq!(positions("sortKey"), vec![(2, 51, 58), (4, 46, 53)]);
+    assert_eq!(positions("reverse"), vec![(2, 61, 68)]);
+    assert_eq!(positions("limit"), vec![(3, 73, 78)]);
+    for filter_name in &["filter", "orderBy", "limitTo", "date", "short", "name"] {
+        assert!(
+            positions(filter_name).is_empty(),
+            "'{}' はスコープ参照として登録されてはいけない",
+            filter_name
+        );
+    }
+}
+
+#[test]
+fn test_ng_repeat_alias_filter_and_track_by_are_classified() {
+    // `item` / `filtered` はローカル変数、`items` / `q` はスコープ参照、
+    // `track by item.id` の `item` はローカル変数参照として扱う
+    use angularjs_lsp::model::HtmlLocalVariableSource;
+
+    let js = r#"
+angular.module('app', []).controller('SearchCtrl', ['$scope', function($scope) {
+    $scope.items = [];
+    $scope.q = '';
+}]);
+"#;
+    let html = r#"
+<div ng-controller="SearchCtrl">
+    <ul>
+        <li ng-repeat="item in items | filter:q as filtered track by item.id">{{ $index }}</li>
+        <li ng-if="filtered.length === 0">No results</li>
+    </ul>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let names: Vec<&str> = scope_refs.iter().map(|r| r.property_path.as_str()).collect();
+    assert!(names.contains(&"items"), "refs: {:?}", names);
+    assert!(names.contains(&"q"), "refs: {:?}", names);
+    for local in &["item", "item.id", "filtered", "filtered.length", "$index"] {
+        assert!(!names.contains(local), "'{}' はスコープ参照ではない (refs: {:?})", local, names);
+    }
+
+    let locals = index.html.get_all_local_variables(&html_uri);
+    let item = locals.iter().find(|v| v.name == "item").expect("item");
+    assert_eq!((item.scope_start_line, item.scope_end_line), (3, 3));
+    let filtered = locals.iter().find(|v| v.name == "filtered").expect("filtered");
+    assert_eq!(filtered.source, HtmlLocalVariableSource::NgRepeatAlias);
+    assert_eq!((filtered.name_start_line, filtered.name_start_col), (3, 51));
+    // `as` エイリアスは ng-repeat 要素の親 (<ul>) のスコープで有効
+    assert_eq!((filtered.scope_start_line, filtered.scope_end_line), (2, 5));
+
+    let item_refs = index.html.get_local_variable_references(&html_uri, "item", 3, 3);
+    let item_cols: Vec<u32> = item_refs.iter().map(|r| r.start_col).collect();
+    assert_eq!(item_cols, vec![69], "track by の item は参照");
+    let filtered_refs = index.html.get_local_variable_references(&html_uri, "filtered", 2, 5);
+    let filtered_positions: Vec<(u32, u32)> =
+        filtered_refs.iter().map(|r| (r.start_line, r.start_col)).collect();
+    assert_eq!(filtered_positions, vec![(4, 19)]);
+}
+
 #[test]
 fn test_ng_src_interpolation_is_still_extracted() {
     // `ng-src="{{vm.imageUrl}}"` のように補間が含まれる場合は、補間内の
@@ -3548,6 +4806,94 @@ angular.module('app', []).controller('GalleryCtrl', ['$scope', function($scope)
     );
 }
 
+#[test]
+fn test_expression_and_interpolation_ng_attributes_are_classified() {
+    // 式全体を評価する属性 (`ng-disabled` / `ng-class-even` / `ng-model-options` 等) は
+    // 値を式として、補間テキスト属性 (`ng-href` / `ng-srcset` / `ng-attr-*`) は
+    // `{{ }}` の中だけをスコープ参照として抽出する
+    let js = r#"
+angular.module('app', []).controller('ListCtrl', ['$scope', function($scope) {
+    $scope.isDisabled = false;
+    $scope.evenClass = 'even';
+    $scope.oddClass = 'odd';
+    $scope.delay = 500;
+    $scope.minValue = 1;
+    $scope.userId = 1;
+    $scope.imageUrl = '';
+    $scope.width = 10;
+}]);
+"#;
+    let html = r#"
+<div ng-controller="ListCtrl">
+    <p ng-class-even="evenClass" ng-class-odd="oddClass"></p>
+    <input ng-model="query" ng-model-options="{ debounce: delay }" ng-disabled="isDisabled" ng-min="minValue">
+    <a ng-href="/users/{{ userId }}">user</a>
+    <img ng-srcset="{{ imageUrl }} 2x" ng-attr-width="{{ width }}">
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scope_refs = index.html.get_html_scope_references(&html_uri);
+    let mut names: Vec<&str> = scope_refs.iter().map(|r| r.property_path.as_str()).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "delay", "evenClass", "imageUrl", "isDisabled", "minValue", "oddClass", "query",
+            "userId", "width",
+        ]
+    );
+
+    // 組み込み属性は custom directive の参照として登録しない
+    let directive_refs = index.html.get_all_directive_references_for_uri(&html_uri);
+    let builtin = ["ngClassEven", "ngModelOptions", "ngMin", "ngHref", "ngSrcset", "ngAttrWidth"];
+    assert!(
+        directive_refs.iter().all(|r| !builtin.contains(&r.directive_name.as_str())),
+        "ng-* 属性が directive 参照になっている: {:?}",
+        directive_refs
+    );
+}
+
+#[test]
+fn test_ng_style_object_and_ng_attr_interpolation_references() {
+    // `ng-style` のオブジェクト式は値 (省略記法のキーを含む) だけ、`ng-attr-*` は補間の中身だけを
+    // 参照として抽出し、キー名や文字列リテラル (`'font-size'` の `size`)、実属性名 (`title`) は除く
+    let js = r#"
+angular.module('app', []).controller('StyleCtrl', ['$scope', function($scope) {
+    $scope.textColor = 'red';
+    $scope.size = 12;
+    $scope.height = 10;
+    $scope.tooltip = '';
+}]);
+"#;
+    let html = r#"
+<div ng-controller="StyleCtrl">
+    <p ng-style="{ color: textColor, 'font-size': size + 'px', height }" ng-attr-title="{{ tooltip }}"></p>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let line = html.lines().nth(2).unwrap();
+    let mut refs: Vec<(String, u32)> = index
+        .html
+        .get_html_scope_references(&html_uri)
+        .into_iter()
+        .map(|r| (r.property_path, r.start_col))
+        .collect();
+    refs.sort_by_key(|(_, col)| *col);
+    assert_eq!(
+        refs,
+        vec![
+            ("textColor".to_string(), line.find("textColor").unwrap() as u32),
+            ("size".to_string(), line.find("size +").unwrap() as u32),
+            ("height".to_string(), line.find("height").unwrap() as u32),
+            ("tooltip".to_string(), line.find("tooltip").unwrap() as u32),
+        ]
+    );
+}
+
 // ====================================================================
 // PR #42: ng-model implicit \$scope definition
 // (diagnostic suppression + goto-definition / hover fallback)
@@ -3697,6 +5043,65 @@ angular.module('app', [])
     );
 }
 
+#[test]
+fn test_undefined_scope_reference_severity_is_configurable() {
+    // 未定義 $scope 参照だけ専用の severity で出し、ローカル変数の診断は従来の
+    // `severity` のまま。コントローラー未解決のテンプレートでは何も出さない。
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+    use std::sync::Arc;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    let js = r#"
+angular.module('app', []).controller('UserCtrl', ['$scope', function($scope) {
+    $scope.users = [];
+}]);
+"#;
+    let html = r#"
+<div ng-controller="UserCtrl">
+    <p>{{ userName }}</p>
+    <li ng-repeat="u in users">{{ u.name }}</li>
+</div>
+<div ng-controller="UnknownCtrl">
+    <p>{{ anything }}</p>
+</div>
+"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let config = DiagnosticsConfig {
+        undefined_scope_reference_severity: Some("error".to_string()),
+        ..DiagnosticsConfig::default()
+    };
+    let diagnostics = DiagnosticsHandler::new(Arc::clone(&index), config).diagnose_html(&html_uri);
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+    let user_name = diagnostics
+        .iter()
+        .find(|d| d.message.contains("'userName'"))
+        .expect("未定義の userName に診断が出るべき");
+    assert_eq!(user_name.severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(user_name.range.start.line, 2);
+    assert!(
+        !messages.iter().any(|m| m.contains("'u'") || m.contains("'name'")),
+        "ng-repeat のローカル変数は除外されるべき: {:?}",
+        messages
+    );
+    assert!(
+        !messages.iter().any(|m| m.contains("'anything'")),
+        "コントローラーが解決できない範囲はスキップされるべき: {:?}",
+        messages
+    );
+
+    // 未指定なら従来どおり `severity` が使われる
+    let diagnostics = DiagnosticsHandler::new(index, DiagnosticsConfig::default())
+        .diagnose_html(&html_uri);
+    assert!(diagnostics
+        .iter()
+        .filter(|d| d.message.contains("'userName'"))
+        .all(|d| d.severity == Some(DiagnosticSeverity::WARNING)));
+}
+
 #[test]
 fn test_goto_definition_falls_back_to_ng_model_target() {
     // controller で明示的に \$scope.X を定義していない場合、`{{ X }}` への
@@ -4131,6 +5536,35 @@ fn test_document_highlight_js_symbol() {
     );
 }
 
+#[test]
+fn test_document_highlight_controller_as_alias() {
+    // `controller as vm` 経由の `vm.userName` 参照も同 URI 内で全件 READ になる。
+    use tower_lsp::lsp_types::DocumentHighlightKind;
+
+    let js = r#"angular.module('app', []).controller('UserCtrl', function() {
+    var vm = this;
+    vm.userName = 'alice';
+});
+"#;
+    let html = r#"<div ng-controller="UserCtrl as vm">
+    <input ng-model="vm.userName">
+    <p>{{ vm.userName }}</p>
+</div>"#;
+    let index = analyze_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // 行 2: "    <p>{{ vm.userName }}</p>" の userName 内
+    let highlights = run_document_highlight(std::sync::Arc::clone(&index), html_uri, 2, 16)
+        .expect("alias 経由の参照がハイライトされるべき");
+
+    let mut lines: Vec<u32> = highlights.iter().map(|h| h.range.start.line).collect();
+    lines.sort();
+    assert_eq!(lines, vec![1, 2]);
+    assert!(highlights
+        .iter()
+        .all(|h| h.kind == Some(DocumentHighlightKind::READ)));
+}
+
 // ====================================================================
 // Issue #65: DI 配列の要素数と関数の引数数の不一致を警告
 // ====================================================================
@@ -4419,205 +5853,2909 @@ angular.module('app', []).controller('MainCtrl', ['$scope', '$timeout', function
     );
 }
 
-// ============================================================
-// Rename refactoring (#68)
-// ============================================================
-
-/// rename 用ヘルパー: JS と HTML の双方を解析して Index を返す
-fn analyze_js_and_html(js_source: &str, html_source: &str) -> Arc<Index> {
-    analyze_html(js_source, html_source)
+#[test]
+fn test_di_order_mismatch_warns_when_params_are_swapped() {
+    let js = r#"
+angular.module('app', []).controller('MainCtrl', ['$scope', 'UserService', '$q',
+    function(UserService, $scope, $http) {}]);
+"#;
+    let diagnostics = diagnose_js_for_test(js);
+    let mut order: Vec<(u32, u32)> = diagnostics
+        .iter()
+        .filter(|d| d.message.contains("dependency order"))
+        .map(|d| (d.range.start.line, d.range.start.character))
+        .collect();
+    order.sort();
+    // UserService / $scope は入れ替わり、$http は '$q' 位置の `$` 同士の食い違い
+    assert_eq!(order, vec![(2, 13), (2, 26), (2, 34)]);
 }
 
-fn make_rename_params(uri: &Url, line: u32, character: u32, new_name: &str) -> tower_lsp::lsp_types::RenameParams {
-    use tower_lsp::lsp_types::{Position, RenameParams, TextDocumentIdentifier, TextDocumentPositionParams, WorkDoneProgressParams};
-    RenameParams {
-        text_document_position: TextDocumentPositionParams {
-            text_document: TextDocumentIdentifier { uri: uri.clone() },
-            position: Position { line, character },
-        },
-        new_name: new_name.to_string(),
-        work_done_progress_params: WorkDoneProgressParams::default(),
-    }
+#[test]
+fn test_di_order_mismatch_ignores_renamed_params() {
+    // ミニファイ対策で引数名を変えているだけなら警告しない
+    let js = r#"
+angular.module('app', []).controller('MainCtrl', ['$scope', 'UserService',
+    function($scope, users) {}]);
+"#;
+    let diagnostics = diagnose_js_for_test(js);
+    assert!(
+        diagnostics.iter().all(|d| !d.message.contains("dependency order")),
+        "別名の引数は位置ずれとみなさない (got: {:?})",
+        diagnostics
+    );
 }
 
-fn edit_texts_in(edit: &tower_lsp::lsp_types::WorkspaceEdit, uri: &Url) -> Vec<String> {
-    edit.changes
-        .as_ref()
-        .and_then(|m| m.get(uri))
-        .map(|edits| edits.iter().map(|e| e.new_text.clone()).collect())
-        .unwrap_or_default()
+#[test]
+fn test_di_arity_and_order_checked_for_inject_pattern() {
+    let js = r#"
+function MainCtrl($scope) {}
+MainCtrl.$inject = ['$scope', 'UserService'];
+
+class OtherCtrl {
+    constructor(UserService, $scope) {}
 }
+OtherCtrl.$inject = ['$scope', 'UserService'];
 
-#[test]
-fn test_rename_controller_updates_js_definition_and_html_references() {
-    // JS の controller 定義の文字列リテラルにカーソルを置いて rename すると、
-    // JS 側の登録名と HTML 側の ng-controller 値の両方が同時に書き換わるべき
-    use angularjs_lsp::handler::RenameHandler;
+angular.module('app', [])
+    .controller('MainCtrl', MainCtrl)
+    .controller('OtherCtrl', OtherCtrl);
+"#;
+    let diagnostics = diagnose_js_for_test(js);
+    let arity: Vec<u32> = diagnostics
+        .iter()
+        .filter(|d| d.message.contains("DI array has 2"))
+        .map(|d| d.range.start.line)
+        .collect();
+    assert_eq!(arity, vec![1], "$inject の要素数不一致は関数宣言に出る");
 
-    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {}]);"#;
-    let html = r#"<div ng-controller="MainCtrl as vm">{{ vm.x }}</div>"#;
+    let order = diagnostics
+        .iter()
+        .filter(|d| d.message.contains("dependency order"))
+        .count();
+    assert_eq!(order, 2, "class constructor の入れ替えも検出する: {:?}", diagnostics);
+}
 
-    let index = analyze_js_and_html(js, html);
-    let handler = RenameHandler::new(index);
+// ====================================================================
+// $watch の文字列式と $on / $emit / $broadcast のイベント名
+// ====================================================================
 
-    let js_uri = Url::parse("file:///test.js").unwrap();
-    let html_uri = Url::parse("file:///test.html").unwrap();
+#[test]
+fn test_scope_watch_expression_references_scope_property() {
+    let js = r#"
+angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {
+    $scope.user = { name: '' };
+    $scope.$watch('user.name', function() {});
+    $scope.$watchGroup(['user', 'missing'], function() {});
+}]);
+"#;
+    let index = analyze_js(js);
 
-    // 'MainCtrl' の "M" 付近 (line 0, controller('MainCtrl' の中身) にカーソル
-    // `angular.module('app', []).controller('MainCtrl'` の "MainCtrl" は
-    // 0-index で `controller('` の後 → 文字位置を計算
-    let needle = "controller('";
-    let col = js.find(needle).unwrap() + needle.len() + 1; // 'M' の 1 文字内
-    let params = make_rename_params(&js_uri, 0, col as u32, "UserCtrl");
-    let edit = handler.rename(params).expect("rename は WorkspaceEdit を返すべき");
+    let mut refs: Vec<(u32, u32, u32)> = index
+        .definitions
+        .get_references("MainCtrl.$scope.user")
+        .iter()
+        .map(|r| (r.span.start_line, r.span.start_col, r.span.end_col))
+        .collect();
+    refs.sort();
+    assert_eq!(refs, vec![(3, 19, 23), (4, 25, 29)], "文字列内の先頭識別子を参照にする");
+    assert_eq!(index.definitions.get_references("MainCtrl.$scope.missing").len(), 1);
+}
 
-    let js_edits = edit_texts_in(&edit, &js_uri);
-    let html_edits = edit_texts_in(&edit, &html_uri);
+#[test]
+fn test_root_scope_properties_are_completed_and_referenced_in_all_templates() {
+    use angularjs_lsp::handler::{CompletionHandler, ReferencesHandler};
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
 
-    assert!(
-        !js_edits.is_empty(),
-        "JS 側に少なくとも 1 件の編集が必要 (got {:?})",
-        js_edits
+    let js = r#"angular.module('app', [])
+.run(['$rootScope', function($rootScope) {
+    $rootScope.isLoggedIn = false;
+    $rootScope.logout = function() {};
+}])
+.controller('FooCtrl', ['$scope', function($scope) {
+    $scope.title = '';
+}]);
+"#;
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+    js_analyzer.analyze_document(&Url::parse("file:///test.js").unwrap(), js);
+
+    // コントローラー配下のテンプレートと、コントローラーのないテンプレート
+    let page_uri = Url::parse("file:///page.html").unwrap();
+    html_analyzer.analyze_document(
+        &page_uri,
+        "<div ng-controller=\"FooCtrl\">\n  <p ng-if=\"isLoggedIn\">{{ title }}</p>\n</div>\n",
     );
-    assert!(
-        js_edits.iter().all(|t| t == "UserCtrl"),
-        "全ての編集 new_text は 'UserCtrl' であるべき (got {:?})",
-        js_edits
+    let header_uri = Url::parse("file:///header.html").unwrap();
+    html_analyzer.analyze_document(&header_uri, "<nav>\n  <a ng-show=\"isLoggedIn\" ng-click=\"logout()\"></a>\n</nav>\n");
+
+    let handler = CompletionHandler::new(index.clone());
+    for uri in [&page_uri, &header_uri] {
+        let items = handler.complete_in_html_angular_context(uri, 1);
+        let logged_in = items
+            .iter()
+            .find(|i| i.label == "isLoggedIn")
+            .expect("$rootScope プロパティが補完候補に含まれるべき");
+        assert_eq!(logged_in.detail.as_deref(), Some("app ($rootScope)"));
+        assert!(items.iter().any(|i| i.label == "logout"));
+    }
+
+    // HTML の参照から全テンプレート横断で参照検索できる
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: header_uri.clone() },
+            position: Position { line: 1, character: 15 },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext { include_declaration: true },
+    };
+    let mut locations: Vec<(String, u32)> = ReferencesHandler::new(index)
+        .find_references(params)
+        .expect("$rootScope プロパティの参照検索ができるべき")
+        .iter()
+        .map(|l| (l.uri.path().to_string(), l.range.start.line))
+        .collect();
+    locations.sort();
+    assert_eq!(
+        locations,
+        vec![
+            ("/header.html".to_string(), 1),
+            ("/page.html".to_string(), 1),
+            ("/test.js".to_string(), 2),
+        ]
     );
+}
+
+#[test]
+fn test_scope_events_cross_reference_between_on_and_broadcast() {
+    use angularjs_lsp::handler::ReferencesHandler;
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let js = r#"angular.module('app', [])
+.controller('ListCtrl', ['$scope', function($scope) {
+    $scope.$on('userUpdated', function(event, user) {});
+}])
+.controller('EditCtrl', ['$scope', '$rootScope', function($scope, $rootScope) {
+    $rootScope.$broadcast('userUpdated', {});
+    $scope.$emit('userUpdated');
+    $scope.$on('$destroy', function() {});
+    $rootScope.$on('$routeChangeSuccess', function() {});
+    $rootScope.$on('$stateChangeStart', function() {});
+}]);
+"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+
+    assert!(has_definition(&index, "userUpdated", SymbolKind::Event));
+    // `$` で始まる組み込みイベントはユーザー定義のイベントとして登録しない
+    for builtin in ["$destroy", "$routeChangeSuccess", "$stateChangeStart"] {
+        assert!(index.definitions.get_definitions(builtin).is_empty(), "{}", builtin);
+    }
+
+    // $broadcast → $on へジャンプ
+    let targets = goto_definition_at(index.clone(), &uri, js, 5, 30)
+        .expect("送信側から購読側へジャンプできるべき");
+    assert_eq!(targets, vec![(uri.clone(), 2)]);
+
+    // $on から送信箇所を横断検索
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line: 2, character: 16 },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext { include_declaration: true },
+    };
+    let mut lines: Vec<u32> = ReferencesHandler::new(index)
+        .find_references(params)
+        .expect("イベント名から参照検索できるべき")
+        .iter()
+        .map(|l| l.range.start.line)
+        .collect();
+    lines.sort();
+    lines.dedup();
+    assert_eq!(lines, vec![2, 5, 6]);
+}
+
+// ====================================================================
+// $injector.get による動的注入
+// ====================================================================
+
+#[test]
+fn test_injector_get_registers_service_and_alias_references() {
+    let js = r#"
+angular.module('app', [])
+.service('UserService', function() {
+    this.load = function() {};
+})
+.controller('MainCtrl', ['$injector', function($injector) {
+    var users = $injector.get('UserService');
+    users.load();
+    $injector.get('UserService').load();
+}])
+.run(['$injector', function(inj) {
+    inj.get('UserService').load();
+}]);
+"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+
+    let service_refs: Vec<u32> = index
+        .definitions
+        .get_references("UserService")
+        .iter()
+        .map(|r| r.span.start_line)
+        .collect();
     assert!(
-        !html_edits.is_empty(),
-        "HTML 側 ng-controller の参照も書き換え対象になるべき (got {:?})",
-        html_edits
+        service_refs.contains(&6) && service_refs.contains(&8) && service_refs.contains(&11),
+        "$injector.get の文字列がサービス参照になるべき: {:?}",
+        service_refs
+    );
+
+    let mut method_refs: Vec<u32> = index
+        .definitions
+        .get_references("UserService.load")
+        .iter()
+        .map(|r| r.span.start_line)
+        .collect();
+    method_refs.sort();
+    method_refs.dedup();
+    assert_eq!(method_refs, vec![7, 8, 11], "別名・チェーン経由のメソッド参照");
+
+    assert_eq!(
+        index.controllers.resolve_service_alias(&uri, "users", 7).as_deref(),
+        Some("UserService"),
+        "補完用に変数 → サービスの対応が記録されるべき"
     );
+    assert_eq!(index.controllers.resolve_service_alias(&uri, "users", 11), None);
+}
+
+// ====================================================================
+// TypeScript (.ts) ファイルの解析
+// ====================================================================
+
+fn analyze_ts(source: &str) -> (Arc<Index>, Url) {
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(index.clone());
+    let uri = Url::parse("file:///test.ts").unwrap();
+    analyzer.analyze_document(&uri, source);
+    (index, uri)
+}
+
+#[test]
+fn test_typescript_class_controller_with_typed_constructor_params() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let ts = r#"
+class UserCtrl {
+    users: string[] = [];
+    constructor(private $scope: ng.IScope, private UserService: UserService) {
+        $scope.selected = null;
+    }
+    refresh(): void {
+        this.UserService.load();
+    }
+}
+UserCtrl.$inject = ['$scope', 'UserService'];
+
+class SwappedCtrl {
+    constructor(public UserService: UserService, $scope: ng.IScope) {}
+}
+SwappedCtrl.$inject = ['$scope', 'UserService'];
+
+angular.module('app', [])
+    .controller('UserCtrl', UserCtrl)
+    .controller('SwappedCtrl', SwappedCtrl);
+"#;
+    let (index, uri) = analyze_ts(ts);
+    assert!(has_definition(&index, "UserCtrl", SymbolKind::Controller),
+        "TypeScript の class コントローラーが認識されるべき");
+    assert!(has_definition(&index, "UserCtrl.$scope.selected", SymbolKind::ScopeProperty),
+        "型注釈付き $scope 引数から $scope プロパティを追跡するべき");
+    assert!(has_definition(&index, "UserCtrl.refresh", SymbolKind::Method),
+        "戻り値型付きメソッドが Method として認識されるべき");
+
+    let diagnostics =
+        DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default()).diagnose_js(&uri);
+    let order: Vec<u32> = diagnostics
+        .iter()
+        .filter(|d| d.message.contains("dependency order"))
+        .map(|d| d.range.start.line)
+        .collect();
+    assert_eq!(order.len(), 2, "アクセス修飾子付き引数の入れ替えを検出する: {:?}", diagnostics);
+    assert!(order.iter().all(|line| *line == 13), "SwappedCtrl の引数に出る: {:?}", order);
     assert!(
-        html_edits.iter().all(|t| t == "UserCtrl"),
-        "HTML 側の new_text も 'UserCtrl' であるべき (got {:?})",
-        html_edits
+        diagnostics.iter().all(|d| !d.message.contains("DI array has")),
+        "型注釈付き引数も arity に数える: {:?}",
+        diagnostics
+    );
+}
+
+// ====================================================================
+// HTML 参照収集 (Pass 3) の並列化
+// ====================================================================
+
+/// ワークスペーススキャンと同じ Pass 1〜3 を実行し、参照系インデックスの内容を返す
+fn scan_html_references(html_files: &[(Url, String)], parallel: bool) -> Vec<String> {
+    use angularjs_lsp::analyzer::html::parser::HtmlParser;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let js = r#"
+angular.module('app', [])
+.controller('MainCtrl', function($scope) { $scope.items = []; $scope.save = function() {}; })
+.directive('userCard', function() { return { scope: { user: '=' } }; });
+"#;
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+    js_analyzer.analyze_document(&Url::parse("file:///app.js").unwrap(), js);
+
+    let mut parser = HtmlParser::new();
+    let parsed: Vec<_> = html_files
+        .iter()
+        .map(|(uri, source)| (uri, source.as_str(), parser.parse(source).unwrap()))
+        .collect();
+    for (uri, source, tree) in &parsed {
+        html_analyzer.collect_controller_scopes_only_with_tree(uri, source, tree);
+    }
+    for (uri, source, tree) in &parsed {
+        html_analyzer.collect_ng_include_bindings_with_tree(uri, source, tree);
+    }
+    for (uri, source, tree) in &parsed {
+        html_analyzer.collect_form_bindings_only_with_tree(uri, source, tree);
+    }
+
+    if parallel {
+        let processed = AtomicUsize::new(0);
+        html_analyzer.analyze_references_parallel(&parsed, &processed);
+        assert_eq!(processed.load(Ordering::Relaxed), parsed.len());
+    } else {
+        for (uri, source, tree) in &parsed {
+            html_analyzer.analyze_document_references_only_with_tree(uri, source, tree);
+        }
+    }
+
+    let mut snapshot: Vec<String> = Vec::new();
+    snapshot.extend(index.html.get_all_html_scope_references_for_cache().iter().map(|r| format!("{:?}", r)));
+    snapshot.extend(index.html.get_all_html_local_variables_for_cache().iter().map(|r| format!("{:?}", r)));
+    snapshot.extend(
+        index
+            .html
+            .get_all_html_local_variable_references_for_cache()
+            .iter()
+            .map(|r| format!("{:?}", r)),
+    );
+    snapshot.extend(index.html.get_all_html_directive_references_for_cache().iter().map(|r| format!("{:?}", r)));
+    snapshot.extend(index.html.get_all_ng_model_targets_for_cache().iter().map(|r| format!("{:?}", r)));
+    snapshot.extend(index.html.get_all_ui_sref_references_for_cache().iter().map(|r| format!("{:?}", r)));
+    snapshot.sort();
+    snapshot
+}
+
+#[test]
+fn test_parallel_html_reference_pass_matches_serial_pass() {
+    let html_files: Vec<(Url, String)> = (0..64)
+        .map(|i| {
+            let uri = Url::parse(&format!("file:///views/page{}.html", i)).unwrap();
+            let source = format!(
+                r#"<div ng-controller="MainCtrl">
+  <form name="form{i}"><input ng-model="query{i}"></form>
+  <ul><li ng-repeat="item in items" ng-click="save(item, form{i})">{{{{ item.name }}}}</li></ul>
+  <user-card user="items[{i}]"></user-card>
+  <a ui-sref="page{i}">link</a>
+</div>"#
+            );
+            (uri, source)
+        })
+        .collect();
+
+    let serial = scan_html_references(&html_files, false);
+    let parallel = scan_html_references(&html_files, true);
+    assert!(!serial.is_empty());
+    assert_eq!(serial, parallel);
+}
+
+// ====================================================================
+// コントローラーメソッドの参照数 CodeLens
+// ====================================================================
+
+/// 指定行の CodeLens のタイトル一覧
+fn code_lens_titles_at(index: &Arc<Index>, config: CodeLensConfig, line: u32) -> Vec<String> {
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    CodeLensHandler::new(index.clone(), config)
+        .code_lens(&js_uri)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|lens| lens.range.start.line == line)
+        .filter_map(|lens| lens.command.map(|c| c.title))
+        .collect()
+}
+
+#[test]
+fn test_controller_method_reference_count_code_lens() {
+    let js = r#"
+angular.module('app', [])
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.save = function() {};
+        $scope.reset = function() {};
+        this.load = function() {};
+    }]);
+"#;
+    let html = r#"
+<div ng-controller="MainCtrl as vm">
+    <button ng-click="save()">Save</button>
+    <button ng-click="vm.load()">Load</button>
+    <form ng-submit="save()"></form>
+</div>
+"#;
+    let index = analyze_html(js, html);
+
+    assert_eq!(code_lens_titles_at(&index, CodeLensConfig::default(), 3), vec!["2 references"]);
+    assert_eq!(code_lens_titles_at(&index, CodeLensConfig::default(), 5), vec!["1 reference"]);
+    // 参照 0 件は既定では表示しない
+    assert!(code_lens_titles_at(&index, CodeLensConfig::default(), 4).is_empty());
+
+    let show_unused = CodeLensConfig {
+        show_unused: true,
+        ..CodeLensConfig::default()
+    };
+    assert_eq!(code_lens_titles_at(&index, show_unused, 4), vec!["unused"]);
+
+    // クリックで参照一覧を開くコマンドに参照位置が渡される
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let lens = CodeLensHandler::new(index.clone(), CodeLensConfig::default())
+        .code_lens(&js_uri)
+        .unwrap()
+        .into_iter()
+        .find(|lens| lens.range.start.line == 3)
+        .unwrap();
+    let command = lens.command.unwrap();
+    assert_eq!(command.command, "angularjs.showReferences");
+    let arguments = command.arguments.unwrap();
+    assert_eq!(arguments[0], serde_json::json!("file:///test.js"));
+    assert_eq!(arguments[2].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_controller_used_in_templates_code_lens() {
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+
+    let js_uri = Url::parse("file:///app/app.js").unwrap();
+    let js = r#"
+angular.module('app', ['ngRoute'])
+    .controller('UserCtrl', ['$scope', function($scope) {
+        $scope.user = {};
+    }])
+    .config(['$routeProvider', function($routeProvider) {
+        $routeProvider.when('/user', {
+            templateUrl: 'views/user.html',
+            controller: 'UserCtrl'
+        });
+    }]);
+"#;
+    js_analyzer.analyze_document(&js_uri, js);
+
+    let html = [
+        (
+            "file:///app/index.html",
+            "<div>\n<div ng-controller=\"UserCtrl\">\n<div ng-include=\"'views/partial.html'\"></div>\n</div>\n</div>",
+        ),
+        ("file:///app/views/user.html", "<p>{{ user }}</p>"),
+        ("file:///app/views/partial.html", "<p>{{ user }}</p>"),
+    ];
+    for (uri, source) in html {
+        html_analyzer.analyze_document(&Url::parse(uri).unwrap(), source);
+    }
+
+    let lens_at_controller = |config: CodeLensConfig| {
+        CodeLensHandler::new(index.clone(), config)
+            .code_lens(&js_uri)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|lens| lens.range.start.line == 2)
+            .and_then(|lens| lens.command)
+            .unwrap()
+    };
+
+    // route / ng-controller / ng-include 継承の 3 テンプレート
+    let command = lens_at_controller(CodeLensConfig::default());
+    assert_eq!(command.title, "Used in 3 templates");
+    assert_eq!(command.command, "angularjs.showReferences");
+    let locations = command.arguments.unwrap()[2].clone();
+    let uris: Vec<&str> = locations
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| l["uri"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        uris,
+        vec![
+            "file:///app/index.html",
+            "file:///app/views/partial.html",
+            "file:///app/views/user.html",
+        ]
+    );
+    // ng-controller は該当行を指す
+    assert_eq!(locations[0]["range"]["start"]["line"], 1);
+
+    // 継承経由を含めない設定
+    let command = lens_at_controller(CodeLensConfig {
+        include_inherited_templates: false,
+        ..CodeLensConfig::default()
+    });
+    assert_eq!(command.title, "Used in 2 templates");
+
+    // ファイルに解決できない templateUrl は "(not resolved)" として示す
+    let missing_uri = Url::parse("file:///app/missing.js").unwrap();
+    let missing = r#"
+angular.module('app')
+    .controller('MissingCtrl', function() {})
+    .config(['$routeProvider', function($routeProvider) {
+        $routeProvider.when('/missing', {
+            templateUrl: 'views/missing.html',
+            controller: 'MissingCtrl'
+        });
+    }]);
+"#;
+    js_analyzer.analyze_document(&missing_uri, missing);
+    let command = CodeLensHandler::new(index.clone(), CodeLensConfig::default())
+        .code_lens(&missing_uri)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|lens| lens.range.start.line == 2)
+        .and_then(|lens| lens.command)
+        .unwrap();
+    assert_eq!(command.title, "Used in 1 template (not resolved)");
+    assert!(command.arguments.is_none());
+}
+
+// ====================================================================
+// フィルターのセマンティックトークン
+// ====================================================================
+
+/// セマンティックトークンを (行, 列, 長さ, type, modifiers) の絶対位置に戻す
+fn decode_semantic_tokens(index: &Arc<Index>, uri: &Url) -> Vec<(u32, u32, u32, u32, u32)> {
+    let tokens = SemanticTokensHandler::new(index.clone())
+        .semantic_tokens_full(uri)
+        .unwrap()
+        .data;
+
+    let mut line = 0;
+    let mut col = 0;
+    let mut decoded = Vec::new();
+    for token in &tokens {
+        if token.delta_line > 0 {
+            line += token.delta_line;
+            col = token.delta_start;
+        } else {
+            col += token.delta_start;
+        }
+        decoded.push((line, col, token.length, token.token_type, token.token_modifiers_bitset));
+    }
+    decoded
+}
+
+fn semantic_token_type(token_type: SemanticTokenType) -> u32 {
+    SemanticTokensHandler::legend()
+        .token_types
+        .iter()
+        .position(|t| *t == token_type)
+        .unwrap() as u32
+}
+
+fn semantic_token_modifier(modifier: &str) -> u32 {
+    1 << SemanticTokensHandler::legend()
+        .token_modifiers
+        .iter()
+        .position(|m| m.as_str() == modifier)
+        .unwrap()
+}
+
+#[test]
+fn test_filter_semantic_tokens() {
+    let js = r#"
+angular.module('app', [])
+    .filter('myFilter', function() { return function(x) { return x; }; })
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.price = 1;
+    }]);
+"#;
+    let html = r#"<div ng-controller="MainCtrl">
+<span>{{ price | myFilter | currency }}</span>
+</div>"#;
+    let index = analyze_html(js, html);
+    let decoded = decode_semantic_tokens(&index, &Url::parse("file:///test.html").unwrap());
+
+    let function_type = semantic_token_type(SemanticTokenType::FUNCTION);
+    let property_type = semantic_token_type(SemanticTokenType::PROPERTY);
+    let default_library = semantic_token_modifier("defaultLibrary");
+
+    // `price` は scope property、フィルター名は function トークン
+    assert!(decoded.contains(&(1, 9, 5, property_type, 0)), "{:?}", decoded);
+    assert!(decoded.contains(&(1, 17, 8, function_type, 0)), "{:?}", decoded);
+    assert!(decoded.contains(&(1, 28, 8, function_type, default_library)), "{:?}", decoded);
+}
+
+#[test]
+fn test_local_variable_semantic_tokens() {
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+
+    let js = r#"
+angular.module('app', [])
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.items = [];
+        $scope.user = {};
+    }]);
+"#;
+    js_analyzer.analyze_document(&Url::parse("file:///app/app.js").unwrap(), js);
+
+    let parent_uri = Url::parse("file:///app/index.html").unwrap();
+    let parent = r#"<div ng-controller="MainCtrl">
+<li ng-repeat="item in items">{{ item }} {{ user }}
+<div ng-include="'row.html'"></div>
+</li>
+</div>"#;
+    html_analyzer.analyze_document(&parent_uri, parent);
+    let child_uri = Url::parse("file:///app/row.html").unwrap();
+    html_analyzer.analyze_document(&child_uri, "<span>{{ item }}</span>");
+
+    let variable_type = semantic_token_type(SemanticTokenType::VARIABLE);
+    let property_type = semantic_token_type(SemanticTokenType::PROPERTY);
+    let local = semantic_token_modifier("local");
+    let readonly = semantic_token_modifier("readonly");
+    let declaration = semantic_token_modifier("declaration");
+
+    let decoded = decode_semantic_tokens(&index, &parent_uri);
+    // ng-repeat の定義、参照、scope プロパティ
+    assert!(decoded.contains(&(1, 15, 4, variable_type, local | declaration)), "{:?}", decoded);
+    assert!(decoded.contains(&(1, 33, 4, variable_type, local)), "{:?}", decoded);
+    assert!(decoded.contains(&(1, 44, 4, property_type, 0)), "{:?}", decoded);
+
+    // ng-include 先では継承ローカル変数として readonly が付く
+    let decoded = decode_semantic_tokens(&index, &child_uri);
+    assert!(decoded.contains(&(0, 9, 4, variable_type, local | readonly)), "{:?}", decoded);
+}
+
+// ====================================================================
+// ドキュメントアウトラインの階層化
+// ====================================================================
+
+#[test]
+fn test_document_symbols_are_nested() {
+    use tower_lsp::lsp_types::{DocumentSymbol, DocumentSymbolResponse};
+
+    let js = r#"
+angular.module('app', [])
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.user = {};
+        $scope.save = function() {};
+        this.load = function() {};
+    }])
+    .service('UserService', ['$http', function($http) {
+        this.getAll = function() {};
+    }])
+    .filter('myFilter', function() { return function(x) { return x; }; });
+"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let Some(DocumentSymbolResponse::Nested(symbols)) =
+        DocumentSymbolHandler::new(index).document_symbols(&uri)
+    else {
+        panic!("nested document symbols expected");
+    };
+
+    fn names(symbols: &[DocumentSymbol]) -> Vec<&str> {
+        symbols.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    // モジュール → 登録コンポーネント
+    assert_eq!(names(&symbols), vec!["app"]);
+    let module = &symbols[0];
+    let registered = module.children.as_ref().unwrap();
+    assert_eq!(names(registered), vec!["MainCtrl", "UserService", "myFilter"]);
+
+    // コントローラー → $scope / this メンバー
+    let controller = &registered[0];
+    assert_eq!(
+        names(controller.children.as_ref().unwrap()),
+        vec!["$scope.user", "$scope.save", "load"]
+    );
+    // サービス → メソッド
+    assert_eq!(names(registered[1].children.as_ref().unwrap()), vec!["getAll"]);
+
+    // 子の range は親の range に含まれる
+    for child in registered {
+        assert!(module.range.start <= child.range.start && child.range.end <= module.range.end);
+    }
+}
+
+#[test]
+fn test_html_document_symbols() {
+    use tower_lsp::lsp_types::{DocumentSymbol, DocumentSymbolResponse, Position};
+
+    let js = r#"
+angular.module('app', [])
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.items = [];
+    }]);
+"#;
+    let html = r#"<div ng-controller="MainCtrl as vm">
+  <form name="editForm">
+    <input ng-model="vm.name">
+  </form>
+  <ul>
+    <li ng-repeat="(key, value) in items">
+      {{ key }}
+    </li>
+  </ul>
+  <span ng-repeat="item in items">{{ item }}</span>
+</div>"#;
+    let index = analyze_html(js, html);
+    let uri = Url::parse("file:///test.html").unwrap();
+    let Some(DocumentSymbolResponse::Nested(symbols)) =
+        DocumentSymbolHandler::new(index).document_symbols_html(&uri, html, None)
+    else {
+        panic!("nested document symbols expected");
+    };
+
+    fn names(symbols: &[DocumentSymbol]) -> Vec<&str> {
+        symbols.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    assert_eq!(names(&symbols), vec!["MainCtrl as vm"]);
+    let controller = &symbols[0];
+    assert_eq!(controller.detail.as_deref(), Some("ng-controller"));
+    let children = controller.children.as_ref().unwrap();
+    assert_eq!(names(children), vec!["editForm", "(key, value)", "item"]);
+    assert_eq!(children[1].detail.as_deref(), Some("ng-repeat"));
+
+    // range は要素の開始タグから終了タグの末尾まで
+    assert_eq!(controller.range.start, Position::new(0, 0));
+    assert_eq!(controller.range.end, Position::new(10, 6));
+    assert_eq!(children[1].range.start, Position::new(5, 4));
+    assert_eq!(children[1].range.end, Position::new(7, 9));
+
+    // 1 行の要素でも range が selection_range を含む
+    for child in &children[1..] {
+        let (range, selection) = (child.range, child.selection_range);
+        assert!(range.start <= selection.start && selection.end <= range.end, "{:?}", child);
+    }
+    assert_eq!(children[2].range.start, Position::new(9, 2));
+    assert_eq!(children[2].range.end, Position::new(9, 51));
+}
+
+// ====================================================================
+// フィルターのホバー
+// ====================================================================
+
+fn hover_markdown_at(index: &Arc<Index>, uri: &Url, line: u32, character: u32) -> Option<String> {
+    use angularjs_lsp::handler::HoverHandler;
+    use tower_lsp::lsp_types::{
+        HoverContents, HoverParams, Position, TextDocumentIdentifier, TextDocumentPositionParams,
+        WorkDoneProgressParams,
+    };
+
+    let params = HoverParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line, character },
+        },
+        work_done_progress_params: WorkDoneProgressParams::default(),
+    };
+    match HoverHandler::new(index.clone()).hover(params)?.contents {
+        HoverContents::Markup(m) => Some(m.value),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_hover_on_filters() {
+    let js = r#"
+angular.module('app', [])
+    /**
+     * 文字列を指定の長さで切り詰める
+     */
+    .filter('truncate', function() {
+        return function(input, length, suffix) {
+            return input.slice(0, length) + suffix;
+        };
+    })
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.title = '';
+        $scope.amount = 0;
+    }]);
+"#;
+    let html = r#"<div ng-controller="MainCtrl">
+<p>{{ title | truncate:10:'...' }}</p>
+<p>{{ amount | currency }}</p>
+</div>"#;
+    let index = analyze_html(js, html);
+    let uri = Url::parse("file:///test.html").unwrap();
+
+    // ユーザー定義: 引数からの書式・JSDoc・定義位置
+    let value = hover_markdown_at(&index, &uri, 1, 16).expect("truncate の hover");
+    assert!(value.contains("**truncate** (*filter*)"), "{}", value);
+    assert!(value.contains("{{ input | truncate:length:suffix }}"), "{}", value);
+    assert!(value.contains("文字列を指定の長さで切り詰める"), "{}", value);
+    assert!(value.contains("Defined in: `test.js:"), "{}", value);
+
+    // 組み込み: 静的な説明
+    let value = hover_markdown_at(&index, &uri, 2, 18).expect("currency の hover");
+    assert!(value.contains("**currency** (*built-in filter*)"), "{}", value);
+    assert!(value.contains("currency[:symbol[:fractionSize]]"), "{}", value);
+}
+
+// ====================================================================
+// コントローラーのホバーでの注入サービス一覧
+// ====================================================================
+
+#[test]
+fn test_hover_on_controller_lists_injected_services() {
+    let js = r#"
+angular.module('app', [])
+    .service('UserService', function() {})
+    /**
+     * ユーザー一覧画面
+     */
+    .controller('UserController', ['$scope', '$http', 'UserService', 'MissingService', function($scope, $http, UserService, MissingService) {
+        $scope.users = [];
+    }]);
+"#;
+    let html = r#"<div ng-controller="UserController">
+</div>"#;
+    let index = analyze_html(js, html);
+
+    let assert_services = |value: &str| {
+        assert!(value.contains("Injected services:"), "{}", value);
+        assert!(value.contains("[`UserService`](file:///test.js#L3) (*service*)"), "{}", value);
+        assert!(!value.contains("$http"), "{}", value);
+        assert!(value.contains("`MissingService` (*not found in workspace*)"), "{}", value);
+        assert!(value.contains("ユーザー一覧画面"), "{}", value);
+    };
+
+    // HTML: ng-controller の属性値
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let value = hover_markdown_at(&index, &html_uri, 0, 22).expect("ng-controller の hover");
+    assert_services(&value);
+
+    // JS: .controller('UserController', ...) の名前
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let value = hover_markdown_at(&index, &js_uri, 6, 18).expect("controller 定義の hover");
+    assert_services(&value);
+}
+
+// ====================================================================
+// ディレクティブのホバーでの restrict / scope 表示
+// ====================================================================
+
+#[test]
+fn test_hover_on_directive_shows_definition_object() {
+    let js = r#"
+angular.module('app', [])
+    .directive('userCard', function() {
+        return {
+            restrict: 'EA',
+            scope: { userName: '=', onSave: '&', label: '@title' },
+            templateUrl: 'views/user-card.html'
+        };
+    })
+    .directive('autoFocus', function() {
+        return { restrict: 'A', link: function() {} };
+    });
+"#;
+    let html = r#"<user-card user-name="name"></user-card>
+<input auto-focus>"#;
+    let index = analyze_html(js, html);
+    let uri = Url::parse("file:///test.html").unwrap();
+
+    let value = hover_markdown_at(&index, &uri, 0, 3).expect("user-card の hover");
+    assert!(value.contains("Restrict: `EA` — element, attribute"), "{}", value);
+    assert!(value.contains("Usable as both an element and an attribute"), "{}", value);
+    assert!(value.contains("| `onSave` | `&` |"), "{}", value);
+    assert!(value.contains("| `userName` | `=` |"), "{}", value);
+    assert!(value.contains("| `label` | `@title` |"), "{}", value);
+    assert!(value.contains("Template: `views/user-card.html`"), "{}", value);
+
+    let value = hover_markdown_at(&index, &uri, 1, 10).expect("auto-focus の hover");
+    assert!(value.contains("Restrict: `A` — attribute"), "{}", value);
+    assert!(!value.contains("Usable as both"), "{}", value);
+    assert!(!value.contains("| Binding |"), "{}", value);
+}
+
+// ====================================================================
+// 定義の見つからない注入サービスの診断
+// ====================================================================
+
+#[test]
+fn test_undefined_injected_service_warns() {
+    let js = r#"
+angular.module('app', [])
+.service('UserService', function() {})
+.constant('API_URL', '/api')
+.provider('Auth', function() { this.$get = function() {}; })
+.controller('MainCtrl', ['$scope', '$http', 'UserService', 'API_URL', 'MissingService',
+    function($scope, $http, UserService, API_URL, MissingService) {}])
+.config(['$httpProvider', 'AuthProvider', function($httpProvider, AuthProvider) {}]);
+
+function OtherCtrl(OtherMissing, $state) {}
+OtherCtrl.$inject = ['OtherMissing', '$state'];
+"#;
+    let diagnostics = diagnose_js_for_test(js);
+    let mut undefined: Vec<(String, u32)> = diagnostics
+        .iter()
+        .filter(|d| d.message.contains("is not defined in the workspace"))
+        .map(|d| (d.message.clone(), d.range.start.line))
+        .collect();
+    undefined.sort_by_key(|(_, line)| *line);
+
+    assert_eq!(
+        undefined,
+        vec![
+            (
+                "Injected service 'MissingService' is not defined in the workspace".to_string(),
+                5
+            ),
+            (
+                "Injected service 'OtherMissing' is not defined in the workspace".to_string(),
+                10
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_undefined_injected_service_respects_known_services() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let js = r#"
+angular.module('app', []).controller('MainCtrl', ['toastr', function(toastr) { toastr.info('ready'); }]);
+"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let config = DiagnosticsConfig {
+        known_services: vec!["toastr".to_string()],
+        ..DiagnosticsConfig::default()
+    };
+    let diagnostics = DiagnosticsHandler::new(index, config).diagnose_js(&uri);
+    assert!(
+        diagnostics.iter().all(|d| !d.message.contains("'toastr'")),
+        "known_services に登録したサービスは警告しない (got: {:?})",
+        diagnostics
+    );
+}
+
+// ====================================================================
+// 同名コントローラー / サービスの重複定義の診断
+// ====================================================================
+
+#[test]
+fn test_duplicate_definitions_across_files() {
+    use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let a = r#"angular.module('app').controller('MainCtrl', function() {});
+angular.module('app').service('UserService', function() {});
+"#;
+    let b = r#"angular.module('admin').controller('MainCtrl', function() {});
+angular.module('app').factory('UserService', function() {});
+angular.module('app').controller('OnlyHere', function() {});
+"#;
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(index.clone());
+    let uri_a = Url::parse("file:///a.js").unwrap();
+    let uri_b = Url::parse("file:///b.js").unwrap();
+    analyzer.analyze_document(&uri_a, a);
+    analyzer.analyze_document(&uri_b, b);
+
+    let duplicates = |config: DiagnosticsConfig| {
+        let mut found: Vec<(u32, String)> = DiagnosticsHandler::new(index.clone(), config)
+            .diagnose_js(&uri_a)
+            .into_iter()
+            .filter(|d| d.message.contains("times in the workspace"))
+            .map(|d| {
+                let related = d.related_information.unwrap_or_default();
+                assert_eq!(related.len(), 1);
+                assert_eq!(related[0].location.uri, uri_b);
+                (d.range.start.line, related[0].message.clone())
+            })
+            .collect();
+        found.sort();
+        found
+    };
+
+    assert_eq!(
+        duplicates(DiagnosticsConfig::default()),
+        vec![
+            (0, "Other definition: b.js:1".to_string()),
+            (1, "Other definition: b.js:2".to_string()),
+        ]
+    );
+
+    // モジュールが異なる MainCtrl は除外され、同じ app モジュールの service/factory だけ残る
+    let config = DiagnosticsConfig {
+        allow_cross_module_duplicates: true,
+        ..DiagnosticsConfig::default()
+    };
+    assert_eq!(
+        duplicates(config),
+        vec![(1, "Other definition: b.js:2".to_string())]
+    );
+}
+
+// ====================================================================
+// 存在しない templateUrl / ng-include ファイルの診断
+// ====================================================================
+
+#[test]
+fn test_missing_template_files_are_reported() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let js = r#"angular.module('app', [])
+.component('userCard', { templateUrl: 'views/user-card.html' })
+.directive('missing', function() { return { templateUrl: 'views/missing.html' }; })
+.component('versioned', { templateUrl: 'views/gone.html?v=' + version })
+.component('dynamic', { templateUrl: 'views/' + name + '.html' });
+"#;
+    let html = r#"<div ng-include="'partials/header.html'"></div>
+<div ng-include="'partials/nope.html'"></div>
+<div ng-include="vm.templatePath"></div>
+"#;
+    let index = analyze_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let messages = |uri: &Url| -> Vec<(u32, String)> {
+        let handler = DiagnosticsHandler::new(index.clone(), DiagnosticsConfig::default());
+        let diagnostics = if uri.path().ends_with(".html") {
+            handler.diagnose_html(uri)
+        } else {
+            handler.diagnose_js(uri)
+        };
+        diagnostics
+            .into_iter()
+            .filter(|d| d.message.starts_with("Template file"))
+            .map(|d| (d.range.start.line, d.message))
+            .collect()
+    };
+
+    // ファイル一覧が無い (未スキャン) 間は判定しない
+    assert!(messages(&js_uri).is_empty());
+
+    for path in ["/views/user-card.html", "/partials/header.html", "/test.html"] {
+        let uri = Url::parse(&format!("file://{}", path)).unwrap();
+        index.templates.add_workspace_file(&uri);
+    }
+
+    assert_eq!(
+        messages(&js_uri),
+        vec![
+            (2, "Template file 'views/missing.html' was not found in the workspace".to_string()),
+            (3, "Template file 'views/gone.html?v=' was not found in the workspace".to_string()),
+        ]
+    );
+    assert_eq!(
+        messages(&html_uri),
+        vec![(1, "Template file 'partials/nope.html' was not found in the workspace".to_string())]
+    );
+}
+
+// ============================================================
+// Rename refactoring (#68)
+// ============================================================
+
+/// rename 用ヘルパー: JS と HTML の双方を解析して Index を返す
+fn analyze_js_and_html(js_source: &str, html_source: &str) -> Arc<Index> {
+    analyze_html(js_source, html_source)
+}
+
+fn make_rename_params(uri: &Url, line: u32, character: u32, new_name: &str) -> tower_lsp::lsp_types::RenameParams {
+    use tower_lsp::lsp_types::{Position, RenameParams, TextDocumentIdentifier, TextDocumentPositionParams, WorkDoneProgressParams};
+    RenameParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line, character },
+        },
+        new_name: new_name.to_string(),
+        work_done_progress_params: WorkDoneProgressParams::default(),
+    }
+}
+
+fn edit_texts_in(edit: &tower_lsp::lsp_types::WorkspaceEdit, uri: &Url) -> Vec<String> {
+    edit.changes
+        .as_ref()
+        .and_then(|m| m.get(uri))
+        .map(|edits| edits.iter().map(|e| e.new_text.clone()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_rename_controller_updates_js_definition_and_html_references() {
+    // JS の controller 定義の文字列リテラルにカーソルを置いて rename すると、
+    // JS 側の登録名と HTML 側の ng-controller 値の両方が同時に書き換わるべき
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {}]);"#;
+    let html = r#"<div ng-controller="MainCtrl as vm">{{ vm.x }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // 'MainCtrl' の "M" 付近 (line 0, controller('MainCtrl' の中身) にカーソル
+    // `angular.module('app', []).controller('MainCtrl'` の "MainCtrl" は
+    // 0-index で `controller('` の後 → 文字位置を計算
+    let needle = "controller('";
+    let col = js.find(needle).unwrap() + needle.len() + 1; // 'M' の 1 文字内
+    let params = make_rename_params(&js_uri, 0, col as u32, "UserCtrl");
+    let edit = handler.rename(params).expect("rename は WorkspaceEdit を返すべき");
+
+    let js_edits = edit_texts_in(&edit, &js_uri);
+    let html_edits = edit_texts_in(&edit, &html_uri);
+
+    assert!(
+        !js_edits.is_empty(),
+        "JS 側に少なくとも 1 件の編集が必要 (got {:?})",
+        js_edits
+    );
+    assert!(
+        js_edits.iter().all(|t| t == "UserCtrl"),
+        "全ての編集 new_text は 'UserCtrl' であるべき (got {:?})",
+        js_edits
+    );
+    assert!(
+        !html_edits.is_empty(),
+        "HTML 側 ng-controller の参照も書き換え対象になるべき (got {:?})",
+        html_edits
+    );
+    assert!(
+        html_edits.iter().all(|t| t == "UserCtrl"),
+        "HTML 側の new_text も 'UserCtrl' であるべき (got {:?})",
+        html_edits
+    );
+}
+
+#[test]
+fn test_rename_controller_with_padded_ng_controller_value() {
+    // 属性値の先頭に空白があっても、HTML 側の編集範囲はコントローラー名そのもの
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).controller('MainCtrl', function() {});"#;
+    let html = r#"<div ng-controller=" MainCtrl as vm">{{ vm.x }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let col = js.find("MainCtrl").unwrap() + 1;
+    let params = make_rename_params(&js_uri, 0, col as u32, "UserCtrl");
+    let edit = handler.rename(params).expect("rename は WorkspaceEdit を返すべき");
+
+    let start = html.find("MainCtrl").unwrap() as u32;
+    let ranges: Vec<(u32, u32)> = edit.changes.as_ref().unwrap()[&html_uri]
+        .iter()
+        .map(|e| (e.range.start.character, e.range.end.character))
+        .collect();
+    assert_eq!(ranges, vec![(start, start + "MainCtrl".len() as u32)]);
+}
+
+#[test]
+fn test_rename_controller_alias_uses_alias_token_position() {
+    // alias の位置は 3 番目のトークンから求める (後ろに同じ文字列があってもずれない)
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).controller('MainCtrl', function() {});"#;
+    let html = r#"<div ng-controller="MainCtrl as ctrl ctrlx">{{ ctrl.x }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let alias_start = html.find("ctrl ").unwrap() as u32;
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 0, alias_start + 1, "vm"))
+        .expect("alias 定義からの rename");
+    let reference_start = html.find("ctrl.x").unwrap() as u32;
+    assert_eq!(
+        edit_ranges_in(&edit, &html_uri),
+        vec![
+            (0, alias_start, alias_start + 4, "vm".to_string()),
+            (0, reference_start, reference_start + 4, "vm".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_rename_scope_property_in_html_updates_js_and_html() {
+    // HTML 内の {{ foo }} にカーソルを置いて rename すると
+    // JS 側 $scope.foo と HTML 内の参照両方が書き換わるべき
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {
+    $scope.foo = 1;
+}]);"#;
+    let html = r#"<div ng-controller="MainCtrl">{{ foo }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // HTML 0 行目 `{{ foo }}` の foo (col index)
+    let foo_col = html.find("{{ foo").unwrap() + 3; // '{', '{', ' ' の後
+    let params = make_rename_params(&html_uri, 0, foo_col as u32, "bar");
+    let edit = handler
+        .rename(params)
+        .expect("scope property rename は WorkspaceEdit を返すべき");
+
+    let html_edits = edit_texts_in(&edit, &html_uri);
+    let js_edits = edit_texts_in(&edit, &js_uri);
+
+    assert!(
+        !html_edits.is_empty(),
+        "HTML 側の参照を書き換える必要がある (got {:?})",
+        html_edits
+    );
+    assert!(
+        !js_edits.is_empty(),
+        "JS 側の $scope.foo 定義も書き換える必要がある (got {:?})",
+        js_edits
+    );
+    assert!(
+        html_edits.iter().chain(js_edits.iter()).all(|t| t == "bar"),
+        "全ての new_text は 'bar' であるべき"
+    );
+}
+
+#[test]
+fn test_rename_ng_repeat_local_variable_is_scope_limited() {
+    // ng-repeat の `item in items` の `item` をリネームしても、
+    // ng-repeat スコープ外で同名の参照は書き換えられてはいけない
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = "";
+    let html = r#"<div>
+<ul>
+<li ng-repeat="item in items">{{ item.name }}</li>
+</ul>
+<span>{{ item }}</span>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // 2 行目 (0-index) の ng-repeat 内 'item' にカーソルを置く
+    // line 2: `<li ng-repeat="item in items">{{ item.name }}</li>`
+    let line = 2u32;
+    let line_text = "<li ng-repeat=\"item in items\">{{ item.name }}</li>";
+    let item_col = line_text.find("\"item").unwrap() + 1; // 'i' の位置
+
+    let params = make_rename_params(&html_uri, line, item_col as u32, "row");
+    let edit = handler
+        .rename(params)
+        .expect("ng-repeat ローカル変数の rename は WorkspaceEdit を返すべき");
+
+    let edits = edit
+        .changes
+        .as_ref()
+        .and_then(|m| m.get(&html_uri))
+        .cloned()
+        .unwrap_or_default();
+
+    assert!(!edits.is_empty(), "rename 編集が少なくとも 1 件必要");
+    assert!(
+        edits.iter().all(|e| e.new_text == "row"),
+        "new_text 全件が 'row' であるべき (got {:?})",
+        edits.iter().map(|e| &e.new_text).collect::<Vec<_>>()
+    );
+
+    // スコープ外の `<span>{{ item }}</span>` (line 4) は書き換えられないこと
+    let outer_line = 4u32;
+    assert!(
+        edits.iter().all(|e| e.range.start.line != outer_line),
+        "ng-repeat スコープ外 (line {}) の `item` は書き換え対象外であるべき (got ranges: {:?})",
+        outer_line,
+        edits.iter().map(|e| e.range).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_prepare_rename_returns_range_for_controller_literal() {
+    // prepareRename: controller の文字列リテラルにカーソルを置くと書き換え可能範囲が返る
+    use angularjs_lsp::handler::RenameHandler;
+    use tower_lsp::lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams};
+
+    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {}]);"#;
+    let html = r#"<div ng-controller="MainCtrl">{{ }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let js_uri = Url::parse("file:///test.js").unwrap();
+
+    let needle = "controller('";
+    let col = js.find(needle).unwrap() + needle.len() + 1;
+    let params = TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier { uri: js_uri.clone() },
+        position: Position { line: 0, character: col as u32 },
+    };
+
+    let response = handler
+        .prepare_rename(params, js, None)
+        .expect("controller 名上では prepareRename が範囲を返すべき");
+    // クォートを除いた名前がプレースホルダーになる
+    match response {
+        tower_lsp::lsp_types::PrepareRenameResponse::RangeWithPlaceholder { placeholder, .. } => {
+            assert_eq!(placeholder, "MainCtrl");
+        }
+        other => panic!("RangeWithPlaceholder レスポンスが返るべき: {:?}", other),
+    }
+}
+
+/// rename 結果を (行, 開始列, 終了列, new_text) の一覧にする
+fn edit_ranges_in(edit: &tower_lsp::lsp_types::WorkspaceEdit, uri: &Url) -> Vec<(u32, u32, u32, String)> {
+    let mut edits: Vec<_> = edit
+        .changes
+        .as_ref()
+        .and_then(|m| m.get(uri))
+        .map(|edits| {
+            edits
+                .iter()
+                .map(|e| (e.range.start.line, e.range.start.character, e.range.end.character, e.new_text.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    edits.sort();
+    edits
+}
+
+#[test]
+fn test_rename_controller_alias_respects_nested_shadowing() {
+    // `vm` の rename は外側 ng-controller の定義とそのスコープ内の `vm.xxx` のみを更新し、
+    // 内側で同名エイリアスを再定義したスコープ内の参照は書き換えない
+    use angularjs_lsp::handler::RenameHandler;
+    use tower_lsp::lsp_types::{Position, PrepareRenameResponse, TextDocumentIdentifier, TextDocumentPositionParams};
+
+    let js = r#"angular.module('app', [])
+    .controller('UserController', function() { this.name = ''; })
+    .controller('ChildController', function() { this.child = ''; });"#;
+    let html = r#"<div ng-controller="UserController as vm">
+  <p>{{ vm.name }}</p>
+  <input ng-model="vm.email">
+  <div ng-controller="ChildController as vm">
+    <span>{{ vm.child }}</span>
+  </div>
+  <span ng-click="vm.save()">{{ vm.count }}</span>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let expected = vec![
+        (0, 38, 40, "user".to_string()),
+        (1, 8, 10, "user".to_string()),
+        (2, 19, 21, "user".to_string()),
+        (6, 18, 20, "user".to_string()),
+        (6, 32, 34, "user".to_string()),
+    ];
+
+    // 参照側 (`{{ vm.name }}` の vm) から
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 1, 9, "user"))
+        .expect("alias rename は WorkspaceEdit を返すべき");
+    assert_eq!(edit_ranges_in(&edit, &html_uri), expected);
+
+    // 定義側 (ng-controller 属性内の vm) から
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 0, 39, "user"))
+        .expect("alias 定義からの rename");
+    assert_eq!(edit_ranges_in(&edit, &html_uri), expected);
+
+    // 内側スコープの vm は内側の定義だけを更新
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 4, 14, "child"))
+        .expect("内側 alias の rename");
+    assert_eq!(
+        edit_ranges_in(&edit, &html_uri),
+        vec![(3, 41, 43, "child".to_string()), (4, 13, 15, "child".to_string())]
+    );
+
+    // 不正な識別子は拒否
+    let params = make_rename_params(&html_uri, 1, 9, "my-vm");
+    assert!(handler.validate_new_name(&params).is_err());
+
+    // prepareRename はエイリアス部分のみ
+    let response = handler.prepare_rename(TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier { uri: html_uri.clone() },
+        position: Position { line: 1, character: 9 },
+    }, html, None);
+    match response {
+        Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }) => {
+            assert_eq!((range.start.line, range.start.character, range.end.character), (1, 8, 10));
+            assert_eq!(placeholder, "vm");
+        }
+        other => panic!("RangeWithPlaceholder レスポンスが返るべき: {:?}", other),
+    }
+}
+
+#[test]
+fn test_rename_module_updates_setter_getter_and_dependency_arrays() {
+    // setter / getter の両形式と依存配列内の参照を更新し、
+    // 前方一致するだけの別モジュール (`app.users.admin`) は置換しない
+    use angularjs_lsp::handler::RenameHandler;
+
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(index.clone());
+    let users_uri = Url::parse("file:///users.js").unwrap();
+    let service_uri = Url::parse("file:///user-service.js").unwrap();
+    let app_uri = Url::parse("file:///app.js").unwrap();
+    analyzer.analyze_document(&users_uri, "angular.module('app.users', []);\n");
+    analyzer.analyze_document(
+        &service_uri,
+        "angular.module('app.users').service('UserService', function() {});\n",
+    );
+    analyzer.analyze_document(
+        &app_uri,
+        "angular.module('app.users.admin', []);\nangular.module('app', ['app.users', 'app.users.admin']);\n",
+    );
+    let handler = RenameHandler::new(index, Arc::default());
+
+    // getter 形式のモジュール名から rename
+    let edit = handler
+        .rename(make_rename_params(&service_uri, 0, 18, "app.accounts"))
+        .expect("module rename は WorkspaceEdit を返すべき");
+
+    assert_eq!(
+        edit_ranges_in(&edit, &users_uri),
+        vec![(0, 16, 25, "app.accounts".to_string())]
+    );
+    assert_eq!(
+        edit_ranges_in(&edit, &service_uri),
+        vec![(0, 16, 25, "app.accounts".to_string())]
+    );
+    assert_eq!(
+        edit_ranges_in(&edit, &app_uri),
+        vec![(1, 24, 33, "app.accounts".to_string())]
+    );
+
+    // 依存配列内の参照からも rename できる
+    let edit = handler
+        .rename(make_rename_params(&app_uri, 1, 26, "app.accounts"))
+        .expect("依存配列からの module rename");
+    assert_eq!(edit.changes.as_ref().map(|c| c.len()), Some(3));
+
+    // 空白やクォートを含む名前は拒否
+    let params = make_rename_params(&service_uri, 0, 18, "app users");
+    assert!(handler.validate_new_name(&params).is_err());
+}
+
+#[test]
+fn test_rename_filter_updates_definition_and_html_pipes() {
+    // HTML の `| truncate` から rename すると JS の登録名 (クォートの内側) と
+    // 全 HTML のパイプ使用箇所が書き換わる
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).filter('truncate', function() { return function(s) { return s; }; });"#;
+    let html = r#"<p>{{ title | truncate }}</p>
+<p>{{ body | truncate:10 }}</p>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let col = html.find("truncate").unwrap() as u32 + 2;
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 0, col, "shorten"))
+        .expect("filter rename は WorkspaceEdit を返すべき");
+
+    let name_start = js.find("truncate").unwrap() as u32;
+    assert_eq!(
+        edit_ranges_in(&edit, &js_uri),
+        vec![(0, name_start, name_start + 8, "shorten".to_string())]
+    );
+    assert_eq!(
+        edit_ranges_in(&edit, &html_uri),
+        vec![
+            (0, 14, 22, "shorten".to_string()),
+            (1, 13, 21, "shorten".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_rename_directive_converts_between_camel_and_kebab_case() {
+    use angularjs_lsp::handler::RenameHandler;
+    use tower_lsp::lsp_types::{Position, PrepareRenameResponse, TextDocumentIdentifier, TextDocumentPositionParams};
+
+    let js = r#"angular.module('app', [])
+    .directive('userCard', function() { return { restrict: 'EA' }; })
+    .directive('cardList', function() { return { require: '^userCard' }; });"#;
+    let html = r#"<user-card></user-card>
+<div data-user-card></div>
+<span x-user-card></span>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    // 属性名の接頭辞は HTML の内容から判断する
+    let documents = Arc::new(dashmap::DashMap::new());
+    documents.insert(html_uri.clone(), html.to_string());
+    let handler = RenameHandler::new(index, documents);
+
+    // JS の登録名から camelCase で rename → HTML は kebab-case (data- / x- 接頭辞は維持)
+    let line1 = "    .directive('userCard', function() { return { restrict: 'EA' }; })";
+    let col = line1.find("userCard").unwrap() as u32 + 1;
+    let edit = handler
+        .rename(make_rename_params(&js_uri, 1, col, "profileCard"))
+        .expect("directive rename は WorkspaceEdit を返すべき");
+    let line2 = "    .directive('cardList', function() { return { require: '^userCard' }; });";
+    let def_col = line1.find("userCard").unwrap() as u32;
+    let req_col = line2.find("userCard").unwrap() as u32;
+    assert_eq!(
+        edit_ranges_in(&edit, &js_uri),
+        vec![
+            (1, def_col, def_col + 8, "profileCard".to_string()),
+            (2, req_col, req_col + 8, "profileCard".to_string()),
+        ]
+    );
+    assert_eq!(
+        edit_ranges_in(&edit, &html_uri),
+        vec![
+            (0, 1, 10, "profile-card".to_string()),
+            (0, 13, 22, "profile-card".to_string()),
+            (1, 5, 19, "data-profile-card".to_string()),
+            (2, 6, 17, "x-profile-card".to_string()),
+        ]
+    );
+
+    // HTML の要素名から kebab-case で rename しても同じ結果
+    let edit = handler
+        .rename(make_rename_params(&html_uri, 0, 3, "profile-card"))
+        .expect("HTML からの directive rename");
+    assert_eq!(edit_texts_in(&edit, &js_uri), vec!["profileCard", "profileCard"]);
+
+    // 不正な新名は拒否
+    for invalid in ["Profile-Card", "profile--card", "1card", "profile card"] {
+        let params = make_rename_params(&html_uri, 0, 3, invalid);
+        assert!(handler.validate_new_name(&params).is_err(), "{} は不正", invalid);
+        assert!(handler.rename(params).is_none(), "{} では編集しない", invalid);
+    }
+
+    // prepareRename は data- を除いた kebab-case 部分
+    let response = handler.prepare_rename(TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier { uri: html_uri.clone() },
+        position: Position { line: 1, character: 12 },
+    }, html, None);
+    match response {
+        Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }) => {
+            assert_eq!((range.start.character, range.end.character), (10, 19));
+            assert_eq!(placeholder, "user-card");
+        }
+        other => panic!("RangeWithPlaceholder レスポンスが返るべき: {:?}", other),
+    }
+}
+
+#[test]
+fn test_prepare_rename_returns_segment_under_cursor_with_placeholder() {
+    // `vm.userName` はカーソルが `vm` 上ならエイリアス、`userName` 上ならプロパティだけを返し、
+    // 組み込みディレクティブ名や式中の文字列リテラルでは None
+    use angularjs_lsp::handler::RenameHandler;
+    use tower_lsp::lsp_types::{Position, PrepareRenameResponse, TextDocumentIdentifier, TextDocumentPositionParams};
+
+    let js = r#"angular.module('app', [])
+    .controller('UserController', function() { this.userName = ''; this.mode = ''; });"#;
+    let html = r#"<div ng-controller="UserController as vm">
+  <p>{{ vm.userName }}</p>
+  <form name="userForm"><input name="email" ng-model="vm.userName"></form>
+  <span ng-if="vm.mode == 'vm'">{{ userForm.email.$invalid }}</span>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let prepare = |line: u32, character: u32| {
+        handler
+            .prepare_rename(
+                TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: html_uri.clone() },
+                    position: Position { line, character },
+                },
+                html,
+                None,
+            )
+            .map(|response| match response {
+                PrepareRenameResponse::RangeWithPlaceholder { range, placeholder } => {
+                    (range.start.line, range.start.character, range.end.character, placeholder)
+                }
+                other => panic!("RangeWithPlaceholder レスポンスが返るべき: {:?}", other),
+            })
+    };
+
+    assert_eq!(prepare(1, 9), Some((1, 8, 10, "vm".to_string())));
+    assert_eq!(prepare(1, 14), Some((1, 11, 19, "userName".to_string())));
+    assert_eq!(prepare(3, 36), Some((3, 35, 43, "userForm".to_string())));
+    // フォームのフィールド名、組み込みディレクティブ名、文字列リテラル
+    assert_eq!(prepare(3, 45), None);
+    assert_eq!(prepare(3, 9), None);
+    assert_eq!(prepare(3, 28), None);
+}
+
+#[test]
+fn test_prepare_rename_handles_crlf_and_js_string_literals() {
+    // CRLF の HTML でも式中の文字列リテラルを判定でき、
+    // JS の文字列リテラルはリテラルの中身だけが範囲になる
+    use angularjs_lsp::handler::RenameHandler;
+    use dashmap::DashMap;
+    use tower_lsp::lsp_types::{Position, PrepareRenameResponse, TextDocumentIdentifier, TextDocumentPositionParams};
+
+    let js = r#"angular.module('app', []).controller("MainCtrl", function() { this.mode = ''; });"#;
+    let html = "<div ng-controller=\"MainCtrl as vm\">\r\n  <p>{{ vm.mode }}</p>\r\n  <p>{{ vm.mode }}</p>\r\n  <span ng-if=\"'vm'||vm.mode\">x</span>\r\n</div>";
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let documents = Arc::new(DashMap::new());
+    documents.insert(js_uri.clone(), js.to_string());
+    documents.insert(html_uri.clone(), html.to_string());
+    let handler = RenameHandler::new(index, documents);
+
+    let prepare = |uri: &Url, source: &str, line: u32, character: u32| {
+        handler.prepare_rename(
+            TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character },
+            },
+            source,
+            None,
+        )
+    };
+
+    // `'vm'` の開きクォート直後
+    let literal_col = html.lines().nth(3).unwrap().find("'vm'").unwrap() as u32 + 1;
+    assert_eq!(prepare(&html_uri, html, 3, literal_col), None);
+    assert!(prepare(&html_uri, html, 3, literal_col + 5).is_some(), "リテラル直後の vm はリネームできる");
+
+    let col = js.find("MainCtrl").unwrap() as u32 + 2;
+    match prepare(&js_uri, js, 0, col) {
+        Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }) => {
+            assert_eq!(placeholder, "MainCtrl");
+            assert_eq!(range.start.character, col - 2);
+        }
+        other => panic!("RangeWithPlaceholder レスポンスが返るべき: {:?}", other),
+    }
+
+    let edit = handler
+        .rename(make_rename_params(&js_uri, 0, col, "UserCtrl"))
+        .expect("controller 名の rename");
+    assert_eq!(apply_edits_in(&edit, &js_uri, js), js.replace("MainCtrl", "UserCtrl"));
+}
+
+/// WorkspaceEdit の `uri` 向け編集を `source` に適用した結果 (1 行内の ASCII 範囲のみ想定)
+fn apply_edits_in(edit: &tower_lsp::lsp_types::WorkspaceEdit, uri: &Url, source: &str) -> String {
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    let mut edits = edit
+        .changes
+        .as_ref()
+        .and_then(|m| m.get(uri))
+        .cloned()
+        .unwrap_or_default();
+    // 後ろから適用して前の編集位置をずらさない
+    edits.sort_by_key(|e| std::cmp::Reverse((e.range.start.line, e.range.start.character)));
+    for e in edits {
+        let line = &mut lines[e.range.start.line as usize];
+        line.replace_range(e.range.start.character as usize..e.range.end.character as usize, &e.new_text);
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn test_rename_controller_as_property_replaces_only_property_segment() {
+    // `vm.userName.first` の rename は HTML / JS の両方で `userName` だけを書き換え、
+    // `vm` や `first`、同名の `$scope.userName` は壊さない
+    use angularjs_lsp::handler::RenameHandler;
+
+    let js = r#"angular.module('app', []).controller('UserCtrl', function($scope) {
+  var vm = this;
+  vm.userName = { first: '' };
+  vm.show = function() { return vm.userName.first + this.userName.first; };
+  $scope.userName = '';
+});"#;
+    let html = r#"<div ng-controller="UserCtrl as vm">
+  <p title="{{ vm.userName.first }}">{{ vm.userName.first | uppercase }}</p>
+  <input ng-model="vm.userName.first" ng-class="{ on: vm.userName.first }">
+  <span>{{ userName }}</span>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let handler = RenameHandler::new(index, Arc::default());
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let expected_js = js
+        .replace("vm.userName", "vm.fullName")
+        .replace("this.userName", "this.fullName");
+    let expected_html = html.replace("vm.userName", "vm.fullName");
+
+    // HTML の `vm.userName.first` の userName 上から / JS の定義から、どちらでも同じ結果
+    let html_col = html.lines().nth(1).unwrap().find("userName").unwrap() as u32 + 2;
+    for params in [
+        make_rename_params(&html_uri, 1, html_col, "fullName"),
+        make_rename_params(&js_uri, 2, 6, "fullName"),
+    ] {
+        let edit = handler.rename(params).expect("controller as プロパティの rename");
+        assert_eq!(apply_edits_in(&edit, &js_uri, js), expected_js);
+        assert_eq!(apply_edits_in(&edit, &html_uri, html), expected_html);
+    }
+}
+
+#[test]
+fn test_component_element_usages_are_component_references() {
+    // `<user-list>` は component 定義 `userList` の参照として登録され、
+    // 参照検索・rename では HTML の要素名が重複なく 1 回ずつ現れる
+    use angularjs_lsp::handler::{ReferencesHandler, RenameHandler};
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let js = r#"angular.module('app', [])
+.component('userList', {
+    bindings: { users: '<' },
+    template: '<ul></ul>'
+})
+.directive('userCard', function() {
+    return { restrict: 'E' };
+});"#;
+    let html = r#"<div ng-controller="MainCtrl as vm">
+  <user-list users="vm.users"></user-list>
+  <user-card></user-card>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // 開始タグ・終了タグの要素名が component の参照になる
+    let mut refs: Vec<(u32, u32)> = index
+        .get_all_references("userList")
+        .iter()
+        .filter(|r| r.uri == html_uri)
+        .map(|r| (r.span.start_line, r.span.start_col))
+        .collect();
+    refs.sort();
+    assert_eq!(refs, vec![(1, 3), (1, 32)]);
+    // 要素使用は HTML のディレクティブ参照として 1 回だけ登録される
+    assert!(!has_reference(&index, "userList"));
+    // 同じ形で使われていてもディレクティブは component テーブルにないので含めない
+    assert!(index.get_all_references("userCard").is_empty());
+
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: js_uri.clone() },
+            position: Position { line: 1, character: 13 },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext { include_declaration: false },
+    };
+    let locations = ReferencesHandler::new(index.clone())
+        .find_references(params)
+        .expect("component の参照");
+    let html_locations: Vec<_> = locations.iter().filter(|l| l.uri == html_uri).collect();
+    assert_eq!(html_locations.len(), 2);
+
+    let edit = RenameHandler::new(index, Arc::default())
+        .rename(make_rename_params(&js_uri, 1, 13, "memberList"))
+        .expect("component の rename");
+    assert_eq!(
+        apply_edits_in(&edit, &html_uri, html),
+        html.replace("user-list", "member-list")
+    );
+    assert_eq!(
+        apply_edits_in(&edit, &js_uri, js),
+        js.replace("userList", "memberList")
+    );
+}
+
+#[test]
+fn test_component_binding_attributes_are_parent_scope_expressions() {
+    // `<` / `=` / `&` バインディングの属性値は親スコープの式、`@` は補間テキストとして扱う
+    use angularjs_lsp::analyzer::html::directives::is_directive_attribute;
+
+    let js = r#"angular.module('app', [])
+.component('userList', {
+    bindings: { users: '<', onSelect: '&onPicked', title: '@', model: '=?' },
+    controller: 'UserListCtrl'
+});"#;
+    let html = r#"<div ng-controller="MainCtrl as vm">
+  <user-list users="vm.users" on-picked="vm.select(item)" model="vm.current"
+             title="vm.caption {{ vm.heading }}"></user-list>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    assert!(is_directive_attribute("users", Some("user-list"), &index));
+    assert!(is_directive_attribute("on-picked", Some("user-list"), &index));
+    assert!(!is_directive_attribute("on-select", Some("user-list"), &index));
+    assert!(!is_directive_attribute("title", Some("user-list"), &index));
+
+    // キャッシュ読み込み直後 (ComponentStore が空) でも定義シンボルから同じ判定になる
+    index.components.clear_all();
+    assert!(is_directive_attribute("users", Some("user-list"), &index));
+    assert!(is_directive_attribute("on-picked", Some("user-list"), &index));
+    assert!(!is_directive_attribute("on-select", Some("user-list"), &index));
+    assert!(!is_directive_attribute("title", Some("user-list"), &index));
+
+    let paths: Vec<String> = index
+        .html
+        .get_html_scope_references(&html_uri)
+        .into_iter()
+        .map(|r| r.property_path)
+        .collect();
+    for expected in ["vm.users", "vm.select", "vm.current", "vm.heading"] {
+        assert!(paths.iter().any(|p| p == expected), "{} が参照として抽出されるべき: {:?}", expected, paths);
+    }
+    // `@` バインディングの補間外の文字列は式として評価しない
+    assert!(!paths.iter().any(|p| p == "vm.caption"), "{:?}", paths);
+}
+
+#[test]
+fn test_ng_controller_name_is_reference_to_controller_definition() {
+    // `ng-controller="UserController as vm"` のコントローラー名から定義へジャンプでき、
+    // JS / HTML どちらからの参照検索にも HTML 側の使用箇所が含まれる
+    use angularjs_lsp::handler::ReferencesHandler;
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let js = r#"angular.module('app', [])
+.controller('UserController', function() {
+    this.name = '';
+});"#;
+    let html = r#"<div ng-controller="UserController as vm">
+  <section data-ng-controller="UserController">{{ vm.name }}</section>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let targets = goto_definition_at(index.clone(), &html_uri, html, 0, 22)
+        .expect("ng-controller のコントローラー名から定義へジャンプできるべき");
+    assert_eq!(targets, vec![(js_uri.clone(), 1)]);
+
+    let find = |uri: &Url, line: u32, character: u32| {
+        let params = ReferenceParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext { include_declaration: false },
+        };
+        let mut found: Vec<(String, u32, u32)> = ReferencesHandler::new(index.clone())
+            .find_references(params)
+            .expect("コントローラーの参照")
+            .into_iter()
+            .map(|l| (l.uri.path().to_string(), l.range.start.line, l.range.start.character))
+            .collect();
+        found.sort();
+        found
+    };
+    let expected = vec![
+        ("/test.html".to_string(), 0, 20),
+        ("/test.html".to_string(), 1, 31),
+    ];
+    assert_eq!(find(&js_uri, 1, 15), expected);
+    assert_eq!(find(&html_uri, 1, 35), expected);
+}
+
+#[test]
+fn test_inline_templates_register_scope_references_at_js_positions() {
+    // `template: '...'` / テンプレートリテラルの中身を HTML として解析し、
+    // 参照位置は元の JS ファイル内の位置になる
+    use angularjs_lsp::handler::ReferencesHandler;
+    use tower_lsp::lsp_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams,
+    };
+
+    let js = r#"angular.module('app', [])
+.directive('clickBox', function() {
+    return {
+        template: '<div ng-click="onClick()">{{ label }}</div>'
+    };
+})
+.component('userList', {
+    template: `
+      <ul class="${cls}">
+        <li ng-repeat="user in $ctrl.users" ng-click="$ctrl.select(user)">{{ user.name }}</li>
+      </ul>`,
+    controller: 'UserListCtrl'
+})
+.controller('UserListCtrl', function() {
+    this.users = [];
+    this.select = function(user) {};
+});"#;
+
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    js_analyzer.analyze_document(&js_uri, js);
+    html_analyzer.analyze_inline_templates(&js_uri);
+
+    let position_of = |needle: &str| {
+        let (line, text) = js.lines().enumerate().find(|(_, l)| l.contains(needle)).unwrap();
+        (line as u32, text.find(needle).unwrap() as u32)
+    };
+    let refs: Vec<(String, u32, u32)> = index
+        .html
+        .get_html_scope_references(&js_uri)
+        .into_iter()
+        .map(|r| (r.property_path, r.start_line, r.start_col))
+        .collect();
+    let has_ref = |path: &str, needle: &str| {
+        let (line, col) = position_of(needle);
+        refs.iter().any(|r| r.0 == path && r.1 == line && r.2 == col)
+    };
+    assert!(has_ref("onClick", "onClick()"), "{:?}", refs);
+    assert!(has_ref("label", "label }}"), "{:?}", refs);
+    assert!(has_ref("$ctrl.users", "users\" ng-click"), "{:?}", refs);
+    assert!(has_ref("$ctrl.select", "select(user)"), "{:?}", refs);
+    // `${...}` の中身や ng-repeat のローカル変数は参照にしない
+    assert!(!refs.iter().any(|r| r.0 == "cls" || r.0 == "user"), "{:?}", refs);
+
+    // controllerAs (`$ctrl`) 経由でコントローラーのメンバーから参照検索できる
+    let (line, col) = position_of("select = ");
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: js_uri.clone() },
+            position: Position { line, character: col },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext { include_declaration: false },
+    };
+    let locations = ReferencesHandler::new(index.clone())
+        .find_references(params)
+        .expect("インラインテンプレートからの参照");
+    let (line, col) = position_of("select(user)");
+    assert!(
+        locations
+            .iter()
+            .any(|l| l.uri == js_uri && l.range.start == Position::new(line, col)),
+        "{:?}",
+        locations
+    );
+}
+
+#[test]
+fn test_parent_and_root_scope_access_resolves_to_ancestor_scope() {
+    // `$parent.x` は 1 つ外側、`$parent.$parent.x` は 2 つ外側のコントローラーの $scope、
+    // `$root.x` は $rootScope に解決し、解決できないものは参照として登録しない
+    let js = r#"angular.module('app', [])
+.controller('OuterCtrl', function($scope) { $scope.userName = ''; })
+.controller('InnerCtrl', function($scope) { $scope.userName = ''; })
+.controller('LeafCtrl', function($scope) {})
+.run(function($rootScope) { $rootScope.appName = 'demo'; });"#;
+    let html = r#"<div ng-controller="OuterCtrl">
+  <div ng-controller="InnerCtrl">
+    <div ng-controller="LeafCtrl">
+      <input ng-model="$parent.userName">
+      <span>{{ $parent.$parent.userName }} {{ $parent.missing }} {{ $root.appName }}</span>
+    </div>
+  </div>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let paths: Vec<String> = index
+        .html
+        .get_html_scope_references(&html_uri)
+        .into_iter()
+        .map(|r| r.property_path)
+        .collect();
+    assert!(paths.contains(&"$parent.userName".to_string()), "{:?}", paths);
+    assert!(paths.contains(&"$parent.$parent.userName".to_string()), "{:?}", paths);
+    assert!(paths.contains(&"$root.appName".to_string()), "{:?}", paths);
+    assert!(!paths.iter().any(|p| p.contains("missing") || p == "$parent"), "{:?}", paths);
+
+    let col_of = |line: usize, needle: &str| html.lines().nth(line).unwrap().find(needle).unwrap() as u32;
+    let goto = |line: u32, col: u32| goto_definition_at(index.clone(), &html_uri, html, line, col);
+    assert_eq!(goto(3, col_of(3, "userName") + 1), Some(vec![(js_uri.clone(), 2)]));
+    assert_eq!(goto(4, col_of(4, "userName") + 1), Some(vec![(js_uri.clone(), 1)]));
+    assert_eq!(goto(4, col_of(4, "appName") + 1), Some(vec![(js_uri.clone(), 4)]));
+
+    // 参照検索は辿った先のコントローラーのシンボルにだけ含まれる
+    let lines = |symbol: &str| -> Vec<u32> {
+        index
+            .get_html_references_for_symbol(symbol)
+            .iter()
+            .map(|r| r.span.start_line)
+            .collect()
+    };
+    assert_eq!(lines("OuterCtrl.$scope.userName"), vec![4]);
+    assert_eq!(lines("InnerCtrl.$scope.userName"), vec![3]);
+}
+
+#[test]
+fn test_parent_scope_access_counts_child_scopes() {
+    // ng-if / ng-repeat も 1 階層のスコープとして数える。
+    // ディレクティブ自身の式 (`ng-if="..."`) は外側のスコープで評価される
+    let js = r#"angular.module('app', [])
+.controller('OuterCtrl', function($scope) { $scope.userName = ''; })
+.controller('InnerCtrl', function($scope) { $scope.userName = ''; });"#;
+    let html = r#"<div ng-controller="OuterCtrl">
+  <div ng-controller="InnerCtrl">
+    <div ng-if="$parent.userName">
+      <span>{{ $parent.userName }}</span>
+      <p ng-repeat="item in items">{{ $parent.$parent.userName }}</p>
+    </div>
+  </div>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let col_of = |line: usize, needle: &str| html.lines().nth(line).unwrap().find(needle).unwrap() as u32;
+    let goto = |line: u32, col: u32| goto_definition_at(index.clone(), &html_uri, html, line, col);
+    assert_eq!(goto(2, col_of(2, "userName") + 1), Some(vec![(js_uri.clone(), 1)]));
+    assert_eq!(goto(3, col_of(3, "userName") + 1), Some(vec![(js_uri.clone(), 2)]));
+    assert_eq!(goto(4, col_of(4, "userName") + 1), Some(vec![(js_uri.clone(), 2)]));
+
+    let lines = |symbol: &str| -> Vec<u32> {
+        let mut lines: Vec<u32> = index
+            .get_html_references_for_symbol(symbol)
+            .iter()
+            .map(|r| r.span.start_line)
+            .collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(lines("OuterCtrl.$scope.userName"), vec![2]);
+    assert_eq!(lines("InnerCtrl.$scope.userName"), vec![3, 4]);
+}
+
+#[test]
+fn test_locate_symbol_at_reports_kind_name_controllers_and_range() {
+    use angularjs_lsp::handler::locate_symbol_at;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    let js = r#"angular.module('app', [])
+.service('UserService', function() {})
+.controller('UserCtrl', function($scope, UserService, MissingService) {
+    $scope.userName = '';
+});"#;
+    let html = r#"<div ng-controller="UserCtrl">
+  <span>{{ userName }}</span>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    // JS: DI 引数の登録済みサービス
+    let col = js.lines().nth(2).unwrap().find("UserService").unwrap() as u32;
+    let located = locate_symbol_at(&index, &js_uri, 2, col + 1, None).unwrap();
+    assert_eq!(located.name, "UserService");
+    assert_eq!(located.kind, Some(SymbolKind::Service));
+    assert!(located.is_angularjs);
+    assert_eq!(
+        located.range,
+        Some(Range::new(Position::new(2, col), Position::new(2, col + 11)))
+    );
+    assert!(located.html.is_none());
+
+    // JS: 定義のないサービスは tsserver に任せる
+    let col = js.lines().nth(2).unwrap().find("MissingService").unwrap() as u32;
+    assert!(locate_symbol_at(&index, &js_uri, 2, col + 1, None).is_none_or(|l| !l.is_angularjs));
+
+    // HTML: スコープ参照は解決済みのシンボル名とコントローラーを持つ
+    let col = html.lines().nth(1).unwrap().find("userName").unwrap() as u32;
+    let located = locate_symbol_at(&index, &html_uri, 1, col + 1, Some(html)).unwrap();
+    assert_eq!(located.name, "UserCtrl.$scope.userName");
+    assert_eq!(located.kind, Some(SymbolKind::ScopeProperty));
+    assert_eq!(located.controllers, vec!["UserCtrl".to_string()]);
+    assert_eq!(
+        located.range,
+        Some(Range::new(Position::new(1, col), Position::new(1, col + 8)))
+    );
+    assert!(located.is_angularjs);
+
+    // HTML: ng-controller のコントローラー名
+    let located = locate_symbol_at(&index, &html_uri, 0, 22, Some(html)).unwrap();
+    assert_eq!(located.name, "UserCtrl");
+    assert_eq!(located.kind, Some(SymbolKind::Controller));
+}
+
+#[test]
+fn test_directive_attribute_spellings_are_normalized() {
+    use angularjs_lsp::analyzer::html::directives::normalize_directive_attr;
+
+    for name in ["ng-controller", "data-ng-controller", "x-ng-controller", "ng:controller", "ng_controller", "NG-Controller"] {
+        assert_eq!(normalize_directive_attr(name), "ng-controller", "{}", name);
+    }
+    assert_eq!(normalize_directive_attr("x-user-card"), "user-card");
+
+    // レガシーな表記ゆれのテンプレートでも同じように解析される
+    let js = r#"angular.module('app', [])
+.controller('UserCtrl', function() {
+    this.users = [];
+    this.save = function() {};
+});"#;
+    let html = r#"<div x-ng-controller="UserCtrl as vm">
+  <li ng:repeat="user in vm.users">{{ user.name }}</li>
+  <button NG-CLICK="vm.save()">Save</button>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let scopes = index.controllers.get_all_html_controller_scopes(&html_uri);
+    assert_eq!(scopes.len(), 1);
+    assert_eq!(scopes[0].controller_name, "UserCtrl");
+    assert_eq!(scopes[0].alias.as_deref(), Some("vm"));
+
+    let vars: Vec<String> = index
+        .html
+        .get_all_local_variables(&html_uri)
+        .into_iter()
+        .map(|v| v.name)
+        .collect();
+    assert!(vars.contains(&"user".to_string()), "{:?}", vars);
+
+    let paths: Vec<String> = index
+        .html
+        .get_html_scope_references(&html_uri)
+        .into_iter()
+        .map(|r| r.property_path)
+        .collect();
+    assert!(paths.contains(&"vm.users".to_string()), "{:?}", paths);
+    assert!(paths.contains(&"vm.save".to_string()), "{:?}", paths);
+}
+
+#[test]
+fn test_html_syntax_errors_are_reported_as_hints() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    let js = r#"angular.module('app', []).controller('Ctrl', function($scope) { $scope.name = ''; });"#;
+    let html = r#"<div ng-controller="Ctrl">
+  <span>{{ name }}</span
+  <p>ok</p>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+
+    let syntax: Vec<_> = DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default())
+        .diagnose_html(&html_uri)
+        .into_iter()
+        .filter(|d| d.message.starts_with("HTML syntax error"))
+        .collect();
+    assert!(!syntax.is_empty());
+    assert!(syntax.iter().all(|d| d.severity == Some(DiagnosticSeverity::HINT)));
+    assert!(syntax.iter().all(|d| d.range.start.line == 1), "{:?}", syntax);
+
+    // ルールを無効にすると出ない
+    let config = DiagnosticsConfig {
+        html_syntax: false,
+        ..DiagnosticsConfig::default()
+    };
+    let diagnostics = DiagnosticsHandler::new(Arc::clone(&index), config).diagnose_html(&html_uri);
+    assert!(diagnostics.iter().all(|d| !d.message.starts_with("HTML syntax error")));
+
+    // 正しい HTML では出ない
+    let index = analyze_js_and_html(js, "<div ng-controller=\"Ctrl\">{{ name }}</div>");
+    let diagnostics = DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default())
+        .diagnose_html(&html_uri);
+    assert!(diagnostics.iter().all(|d| !d.message.starts_with("HTML syntax error")));
+}
+
+#[test]
+fn test_unused_injected_services_are_reported_as_hints() {
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    let js = r#"
+angular.module('app', [])
+.controller('ArrayCtrl', ['$scope', '$http', 'UserService', 'LogService', function($scope, http, UserService, log) {
+    $scope.load = function() { return http.get('/users'); };
+    return { log };
+}])
+.controller('ImplicitCtrl', function($scope, $timeout, OrderService) {
+    $timeout(function() {});
+})
+.controller('ArgsCtrl', ['CacheService', function(CacheService) {
+    console.log(arguments);
+}]);
+
+function InjectCtrl($q, TokenService) {
+    this.token = TokenService.get();
+}
+InjectCtrl.$inject = ['$q', 'TokenService'];
+"#;
+
+    let unused: Vec<_> = diagnose_js_for_test(js)
+        .into_iter()
+        .filter(|d| d.message.ends_with("is never used"))
+        .collect();
+    assert!(unused.iter().all(|d| d.severity == Some(DiagnosticSeverity::HINT)));
+    let mut messages: Vec<_> = unused.iter().map(|d| d.message.as_str()).collect();
+    messages.sort();
+    // $scope は除外、引数名が異なる DI (http / log) は引数名で、arguments を使う関数は対象外
+    assert_eq!(
+        messages,
+        vec![
+            "Injected service '$q' is never used",
+            "Injected service 'OrderService' is never used",
+            "Injected service 'UserService' is never used",
+        ]
+    );
+}
+
+#[test]
+fn test_unused_injection_can_be_disabled() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let js = r#"angular.module('app', []).controller('Ctrl', function($scope, UserService) {});"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let config = DiagnosticsConfig {
+        unused_injection: false,
+        ..DiagnosticsConfig::default()
+    };
+    let diagnostics = DiagnosticsHandler::new(Arc::clone(&index), config).diagnose_js(&uri);
+    assert!(diagnostics.iter().all(|d| !d.message.ends_with("is never used")));
+}
+
+#[test]
+fn test_ng_repeat_shadowing_is_reported_when_enabled() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    let js = r#"
+angular.module('app', []).controller('Ctrl', function($scope) {
+    $scope.groups = [];
+    $scope.key = 'id';
+});
+"#;
+    let html = r#"<div ng-controller="Ctrl">
+  <ul ng-repeat="item in groups">
+    <li ng-repeat="item in item.children">{{ item }}</li>
+    <li ng-repeat="child in item.children">{{ child }}</li>
+  </ul>
+  <p ng-repeat="(key, value) in groups">{{ key }}: {{ value }}</p>
+  <i ng-repeat="tag in groups">{{ tag }}</i><b ng-repeat="tag in groups">{{ tag }}</b>
+</div>"#;
+
+    let index = analyze_js_and_html(js, html);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let is_shadowing = |message: &str| message.starts_with("ng-repeat variable");
+
+    // 既定では無効
+    let diagnostics = DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default())
+        .diagnose_html(&html_uri);
+    assert!(diagnostics.iter().all(|d| !is_shadowing(&d.message)));
+
+    let config = DiagnosticsConfig {
+        ng_repeat_shadowing: true,
+        ..DiagnosticsConfig::default()
+    };
+    let mut shadowing: Vec<_> = DiagnosticsHandler::new(Arc::clone(&index), config)
+        .diagnose_html(&html_uri)
+        .into_iter()
+        .filter(|d| is_shadowing(&d.message))
+        .collect();
+    shadowing.sort_by_key(|d| d.range.start.line);
+    assert!(shadowing.iter().all(|d| d.severity == Some(DiagnosticSeverity::HINT)));
+
+    let found: Vec<_> = shadowing
+        .iter()
+        .map(|d| (d.range.start.line, d.message.as_str()))
+        .collect();
+    // 同じ行に並んだ兄弟の ng-repeat (`tag`) は入れ子ではないので報告しない
+    assert_eq!(
+        found,
+        vec![
+            (2, "ng-repeat variable 'item' shadows the ng-repeat variable on line 2"),
+            (5, "ng-repeat variable 'key' hides the $scope property 'key' of controller 'Ctrl'"),
+        ]
     );
 }
 
 #[test]
-fn test_rename_scope_property_in_html_updates_js_and_html() {
-    // HTML 内の {{ foo }} にカーソルを置いて rename すると
-    // JS 側 $scope.foo と HTML 内の参照両方が書き換わるべき
-    use angularjs_lsp::handler::RenameHandler;
-
-    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {
-    $scope.foo = 1;
-}]);"#;
-    let html = r#"<div ng-controller="MainCtrl">{{ foo }}</div>"#;
+fn test_assignment_in_read_only_expression_is_warned() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+    use tower_lsp::lsp_types::{DiagnosticSeverity, Position};
+
+    let js = r#"angular.module('app', []).controller('Ctrl', function($scope) { $scope.status = ''; $scope.count = 0; });"#;
+    let html = r#"<div ng-controller="Ctrl" ng-init="count = 1">
+  <p ng-if="status = 'active'">active</p>
+  <p data-ng-show="status == 'a=b' || count >= 1 && count !== 2">ok</p>
+  <button ng-click="status = 'done'">done</button>
+  <span uib-tooltip="Total = {{ count }}">?</span>
+</div>"#;
 
     let index = analyze_js_and_html(js, html);
-    let handler = RenameHandler::new(index);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let assignments: Vec<_> = DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default())
+        .diagnose_html(&html_uri)
+        .into_iter()
+        .filter(|d| d.message.starts_with("Assignment in"))
+        .collect();
 
-    let js_uri = Url::parse("file:///test.js").unwrap();
+    // ng-init / ng-click の代入、比較演算子・文字列内の `=`、
+    // 読み取り専用でないディレクティブ (uib-tooltip) は対象外
+    assert_eq!(assignments.len(), 1, "{:?}", assignments);
+    let diagnostic = &assignments[0];
+    assert_eq!(diagnostic.message, "Assignment in 'ng-if' expression; did you mean '=='?");
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostic.range.start, Position::new(1, 19));
+    assert_eq!(diagnostic.range.end, Position::new(1, 20));
+}
+
+#[test]
+fn test_undefined_scope_reference_suggests_similar_property() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+
+    let js = r#"
+angular.module('app', [])
+.controller('Ctrl', function($scope) {
+    $scope.userName = '';
+})
+.controller('VmCtrl', function() {
+    this.totalPrice = 0;
+})
+.controller('OtherCtrl', function($scope) {
+    $scope.title = '';
+});
+"#;
+    let html = r#"<div ng-controller="Ctrl">
+  <p>{{ usrName }}</p>
+  <p>{{ USERNAME }}</p>
+  <p>{{ somethingElse }}</p>
+  <p>{{ titel }}</p>
+</div>
+<div ng-controller="VmCtrl as vm">{{ vm.totlPrice }}</div>"#;
+
+    let index = analyze_js_and_html(js, html);
     let html_uri = Url::parse("file:///test.html").unwrap();
+    let mut diagnostics: Vec<_> = DiagnosticsHandler::new(Arc::clone(&index), DiagnosticsConfig::default())
+        .diagnose_html(&html_uri)
+        .into_iter()
+        .filter(|d| d.message.contains("is not defined"))
+        .collect();
+    diagnostics.sort_by_key(|d| d.range.start.line);
+
+    let suggestion = |line: u32| {
+        let d = diagnostics.iter().find(|d| d.range.start.line == line).unwrap();
+        d.data
+            .as_ref()
+            .and_then(|data| data["didYouMean"].as_str())
+            .map(|s| s.to_string())
+    };
+    assert_eq!(suggestion(1).as_deref(), Some("userName"));
+    assert_eq!(suggestion(2).as_deref(), Some("userName"));
+    assert_eq!(suggestion(3), None);
+    // 別のコントローラーのプロパティは候補にしない
+    assert_eq!(suggestion(4), None);
+    assert_eq!(suggestion(6).as_deref(), Some("totalPrice"));
+
+    let typo = diagnostics.iter().find(|d| d.range.start.line == 1).unwrap();
+    assert!(typo.message.ends_with("did you mean 'userName'?"), "{}", typo.message);
+    let related = typo.related_information.as_ref().unwrap();
+    assert_eq!(related[0].location.uri, Url::parse("file:///test.js").unwrap());
+    assert_eq!(related[0].location.range.start.line, 3);
+}
+
+/// テスト用ヘルパー：診断の quick fix を取得し、`uri` への編集を適用したソースとタイトルを返す
+fn apply_quick_fixes(
+    index: &Arc<Index>,
+    uri: &Url,
+    diagnostic: &tower_lsp::lsp_types::Diagnostic,
+    documents: Vec<(Url, String)>,
+) -> Vec<(String, String)> {
+    apply_code_actions(index, uri, diagnostic.range, vec![diagnostic.clone()], documents)
+}
+
+/// テスト用ヘルパー：`range` の code action を取得し、編集を適用したソースとタイトルを返す
+fn apply_code_actions(
+    index: &Arc<Index>,
+    uri: &Url,
+    range: tower_lsp::lsp_types::Range,
+    diagnostics: Vec<tower_lsp::lsp_types::Diagnostic>,
+    documents: Vec<(Url, String)>,
+) -> Vec<(String, String)> {
+    use angularjs_lsp::analyzer::incremental::SyntaxTreeCache;
+    use angularjs_lsp::handler::CodeActionHandler;
+    use tower_lsp::lsp_types::{
+        CodeActionContext, CodeActionOrCommand, CodeActionParams, PartialResultParams,
+        TextDocumentIdentifier, WorkDoneProgressParams,
+    };
 
-    // HTML 0 行目 `{{ foo }}` の foo (col index)
-    let foo_col = html.find("{{ foo").unwrap() + 3; // '{', '{', ' ' の後
-    let params = make_rename_params(&html_uri, 0, foo_col as u32, "bar");
-    let edit = handler
-        .rename(params)
-        .expect("scope property rename は WorkspaceEdit を返すべき");
+    let documents: Arc<dashmap::DashMap<Url, String>> = Arc::new(documents.into_iter().collect());
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range,
+        context: CodeActionContext {
+            diagnostics,
+            only: None,
+            trigger_kind: None,
+        },
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+    };
+    let tree_cache = Arc::new(SyntaxTreeCache::new());
+    let actions = CodeActionHandler::new(Arc::clone(index), Arc::clone(&documents), tree_cache)
+        .code_actions(&params)
+        .unwrap_or_default();
 
-    let html_edits = edit_texts_in(&edit, &html_uri);
-    let js_edits = edit_texts_in(&edit, &js_uri);
+    actions
+        .into_iter()
+        .filter_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => Some(action),
+            CodeActionOrCommand::Command(_) => None,
+        })
+        .map(|action| {
+            let changes = action.edit.unwrap().changes.unwrap();
+            let (target, edits) = changes.into_iter().next().unwrap();
+            let mut source = documents.get(&target).unwrap().clone();
+            let mut edits = edits;
+            edits.sort_by_key(|e| std::cmp::Reverse((e.range.start.line, e.range.start.character)));
+            for edit in edits {
+                let start = angularjs_lsp::util::position_to_offset(&source, edit.range.start);
+                let end = angularjs_lsp::util::position_to_offset(&source, edit.range.end);
+                source.replace_range(start..end, &edit.new_text);
+            }
+            (action.title, source)
+        })
+        .collect()
+}
 
-    assert!(
-        !html_edits.is_empty(),
-        "HTML 側の参照を書き換える必要がある (got {:?})",
-        html_edits
-    );
-    assert!(
-        !js_edits.is_empty(),
-        "JS 側の $scope.foo 定義も書き換える必要がある (got {:?})",
-        js_edits
-    );
-    assert!(
-        html_edits.iter().chain(js_edits.iter()).all(|t| t == "bar"),
-        "全ての new_text は 'bar' であるべき"
+#[test]
+fn test_missing_injection_is_reported_with_quick_fix() {
+    let js = r#"
+angular.module('app', [])
+.factory('UserService', function() { return {}; })
+.factory('OrderService', function() { return {}; })
+.controller('ArrayCtrl', ['$scope', function($scope) {
+    UserService.load();
+    var OrderService = {};
+    OrderService.load();
+}])
+.controller('ImplicitCtrl', function() {
+    $scope.user = UserService.current;
+})
+.controller('NestedCtrl', function($scope) {
+    [1].forEach(function(OrderService) { return OrderService; });
+});
+"#;
+
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let mut missing: Vec<_> = diagnose_js_for_test(js)
+        .into_iter()
+        .filter(|d| d.message.ends_with("is used but not injected"))
+        .collect();
+    missing.sort_by_key(|d| d.range.start.line);
+    // 本体で宣言した変数・ネストした関数の引数は対象外
+    let lines: Vec<_> = missing.iter().map(|d| (d.range.start.line, d.message.as_str())).collect();
+    assert_eq!(
+        lines,
+        vec![
+            (5, "Service 'UserService' is used but not injected"),
+            (10, "Service 'UserService' is used but not injected"),
+        ]
     );
+
+    let fixed = apply_quick_fixes(&index, &uri, &missing[0], vec![(uri.clone(), js.to_string())]);
+    assert_eq!(fixed[0].0, "Inject 'UserService'");
+    assert!(fixed[0].1.contains(
+        "['$scope', 'UserService', function($scope, UserService) {"
+    ));
+
+    let fixed = apply_quick_fixes(&index, &uri, &missing[1], vec![(uri.clone(), js.to_string())]);
+    assert!(fixed[0].1.contains(".controller('ImplicitCtrl', function(UserService) {"));
 }
 
 #[test]
-fn test_rename_ng_repeat_local_variable_is_scope_limited() {
-    // ng-repeat の `item in items` の `item` をリネームしても、
-    // ng-repeat スコープ外で同名の参照は書き換えられてはいけない
-    use angularjs_lsp::handler::RenameHandler;
+fn test_missing_injection_ignores_names_bound_in_enclosing_scopes() {
+    let js = r#"
+import UserService from './user-service';
+import { OrderService as Orders } from './order-service';
+const AuthService = require('./auth-service');
+var CartService = {};
+angular.module('app', [])
+.factory('UserService', function() { return {}; })
+.factory('Orders', function() { return {}; })
+.factory('AuthService', function() { return {}; })
+.factory('CartService', function() { return {}; })
+.factory('PriceService', function() { return {}; })
+.controller('ImportCtrl', function($scope) {
+    UserService.load();
+    Orders.load();
+    AuthService.login();
+    CartService.clear();
+});
+(function(PriceService) {
+    angular.module('app').controller('ClosureCtrl', function($scope) {
+        PriceService.total();
+    });
+})(window.PriceService);
+"#;
 
-    let js = "";
-    let html = r#"<div>
-<ul>
-<li ng-repeat="item in items">{{ item.name }}</li>
-</ul>
-<span>{{ item }}</span>
+    let missing: Vec<_> = diagnose_js_for_test(js)
+        .into_iter()
+        .filter(|d| d.message.ends_with("is used but not injected"))
+        .collect();
+    assert!(missing.is_empty(), "unexpected diagnostics: {:?}", missing);
+}
+
+#[test]
+fn test_missing_injection_quick_fix_with_non_ascii_before_insert_point() {
+    let js = r#"
+angular.module('app', [])
+.factory('UserService', function() { return {}; })
+.controller('ÜberCtrl', ['$scope', /* 名前 */ '$http', function($scope, /* 引数 */ $http) {
+    UserService.load($http);
+}]);
+"#;
+
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let missing = diagnose_js_for_test(js)
+        .into_iter()
+        .find(|d| d.message == "Service 'UserService' is used but not injected")
+        .unwrap();
+    let fixed = apply_quick_fixes(&index, &uri, &missing, vec![(uri.clone(), js.to_string())]);
+    assert!(fixed[0].1.contains(
+        "['$scope', /* 名前 */ '$http', 'UserService', function($scope, /* 引数 */ $http, UserService) {"
+    ));
+}
+
+#[test]
+fn test_undefined_scope_reference_quick_fixes() {
+    let js = r#"
+angular.module('app', [])
+.controller('Ctrl', function($scope) {
+    $scope.userName = '';
+});
+"#;
+    let html = r#"<div ng-controller="Ctrl">
+  <p>{{ usrName }}</p>
 </div>"#;
 
     let index = analyze_js_and_html(js, html);
-    let handler = RenameHandler::new(index);
+    let js_uri = Url::parse("file:///test.js").unwrap();
     let html_uri = Url::parse("file:///test.html").unwrap();
+    let diagnostic = angularjs_lsp::handler::DiagnosticsHandler::new(
+        Arc::clone(&index),
+        angularjs_lsp::config::DiagnosticsConfig::default(),
+    )
+    .diagnose_html(&html_uri)
+    .into_iter()
+    .find(|d| d.message.contains("is not defined"))
+    .unwrap();
+
+    let fixes = apply_quick_fixes(
+        &index,
+        &html_uri,
+        &diagnostic,
+        vec![(js_uri, js.to_string()), (html_uri.clone(), html.to_string())],
+    );
+    let titles: Vec<_> = fixes.iter().map(|(title, _)| title.as_str()).collect();
+    assert_eq!(
+        titles,
+        vec!["Change to 'userName'", "Add '$scope.usrName' to controller 'Ctrl'"]
+    );
+    assert!(fixes[0].1.contains("<p>{{ userName }}</p>"));
+    assert!(fixes[1].1.contains(
+        "function($scope) {\n    $scope.usrName = null;\n    $scope.userName = '';"
+    ));
 
-    // 2 行目 (0-index) の ng-repeat 内 'item' にカーソルを置く
-    // line 2: `<li ng-repeat="item in items">{{ item.name }}</li>`
-    let line = 2u32;
-    let line_text = "<li ng-repeat=\"item in items\">{{ item.name }}</li>";
-    let item_col = line_text.find("\"item").unwrap() + 1; // 'i' の位置
+    // `$scope` を別名の引数で受けている場合はその名前で追加する
+    let js = r#"
+angular.module('app', [])
+.controller('ShortCtrl', ['$http', '$scope', function(http, s) {
+    s.items = [];
+}]);
+"#;
+    let html = r#"<div ng-controller="ShortCtrl">
+  <p>{{ total }}</p>
+</div>"#;
+    let index = analyze_js_and_html(js, html);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    let diagnostic = angularjs_lsp::handler::DiagnosticsHandler::new(
+        Arc::clone(&index),
+        angularjs_lsp::config::DiagnosticsConfig::default(),
+    )
+    .diagnose_html(&html_uri)
+    .into_iter()
+    .find(|d| d.message.contains("is not defined"))
+    .unwrap();
+    let fixes = apply_quick_fixes(
+        &index,
+        &html_uri,
+        &diagnostic,
+        vec![(js_uri, js.to_string()), (html_uri.clone(), html.to_string())],
+    );
+    let add = fixes
+        .iter()
+        .find(|(title, _)| title.starts_with("Add "))
+        .expect("$scope への追加");
+    assert!(add.1.contains("function(http, s) {\n    s.total = null;\n    s.items = [];"), "{}", add.1);
+}
 
-    let params = make_rename_params(&html_uri, line, item_col as u32, "row");
-    let edit = handler
-        .rename(params)
-        .expect("ng-repeat ローカル変数の rename は WorkspaceEdit を返すべき");
+#[test]
+fn test_di_annotation_code_actions() {
+    use tower_lsp::lsp_types::{Position, Range};
 
-    let edits = edit
-        .changes
-        .as_ref()
-        .and_then(|m| m.get(&html_uri))
-        .cloned()
-        .unwrap_or_default();
+    let js = r#"angular.module('app', [])
+.controller('ImplicitCtrl', function($scope, UserService) {
+    $scope.users = UserService.all();
+})
+.controller('PartialCtrl', ['$scope', function($scope, OrderService) {
+    $scope.orders = OrderService.all();
+}])
+.controller('DeclCtrl', DeclCtrl)
+.controller('InjectCtrl', InjectCtrl);
 
-    assert!(!edits.is_empty(), "rename 編集が少なくとも 1 件必要");
-    assert!(
-        edits.iter().all(|e| e.new_text == "row"),
-        "new_text 全件が 'row' であるべき (got {:?})",
-        edits.iter().map(|e| &e.new_text).collect::<Vec<_>>()
-    );
+function DeclCtrl($http, $q) {
+    return $http.get('/').then($q.resolve);
+}
 
-    // スコープ外の `<span>{{ item }}</span>` (line 4) は書き換えられないこと
-    let outer_line = 4u32;
+function InjectCtrl($timeout, TokenService) {
+    $timeout(TokenService.refresh);
+}
+InjectCtrl.$inject = ['$timeout'];
+"#;
+    let index = analyze_js(js);
+    let uri = Url::parse("file:///test.js").unwrap();
+    let actions_at = |line: u32, character: u32| {
+        let position = Position::new(line, character);
+        apply_code_actions(
+            &index,
+            &uri,
+            Range::new(position, position),
+            Vec::new(),
+            vec![(uri.clone(), js.to_string())],
+        )
+    };
+
+    // 本体の中のカーソルからも外側の DI 関数を探す
+    let actions = actions_at(2, 10);
+    assert_eq!(actions[0].0, "Convert to DI array");
+    assert!(actions[0].1.contains(
+        ".controller('ImplicitCtrl', ['$scope', 'UserService', function($scope, UserService) {"
+    ));
+    assert!(actions[0].1.contains("    $scope.users = UserService.all();\n}])"));
+
+    let actions = actions_at(5, 10);
+    assert_eq!(actions[0].0, "Sync DI array with parameters");
+    assert!(actions[0].1.contains("['$scope', 'OrderService', function($scope, OrderService) {"));
+
+    let actions = actions_at(11, 5);
+    assert_eq!(actions[0].0, "Add $inject annotation");
+    assert!(actions[0].1.contains("}\nDeclCtrl.$inject = ['$http', '$q'];\n"));
+
+    let actions = actions_at(15, 5);
+    assert_eq!(actions[0].0, "Sync $inject with parameters");
+    assert!(actions[0].1.contains("InjectCtrl.$inject = ['$timeout', 'TokenService'];"));
+
+    // DI 関数の外ではアクションを出さない
+    let outside = actions_at(0, 2);
+    assert!(outside.is_empty(), "{:?}", outside);
+
+    // DI 値として登録されていない関数宣言には出さない。
+    // 列は UTF-16 で受け取り、挿入位置も UTF-16 で返す
+    let js = r#"function helper(a, b) {
+    return a + b;
+}
+var label = '日本語'; angular.module('app').controller('WideCtrl', function($scope) {});
+"#;
+    let index = analyze_js(js);
+    let actions_at = |line: u32, character: u32| {
+        let position = Position::new(line, character);
+        apply_code_actions(
+            &index,
+            &uri,
+            Range::new(position, position),
+            Vec::new(),
+            vec![(uri.clone(), js.to_string())],
+        )
+    };
+    let helper = actions_at(1, 5);
+    assert!(helper.is_empty(), "{:?}", helper);
+
+    let line = js.lines().nth(3).unwrap();
+    let character = line[..line.find("function").unwrap()].encode_utf16().count() as u32 + 2;
+    let actions = actions_at(3, character);
+    assert_eq!(actions[0].0, "Convert to DI array");
     assert!(
-        edits.iter().all(|e| e.range.start.line != outer_line),
-        "ng-repeat スコープ外 (line {}) の `item` は書き換え対象外であるべき (got ranges: {:?})",
-        outer_line,
-        edits.iter().map(|e| e.range).collect::<Vec<_>>()
+        actions[0].1.contains(".controller('WideCtrl', ['$scope', function($scope) {}]);"),
+        "{}",
+        actions[0].1
     );
 }
 
 #[test]
-fn test_prepare_rename_returns_range_for_controller_literal() {
-    // prepareRename: controller の文字列リテラルにカーソルを置くと書き換え可能範囲が返る
-    use angularjs_lsp::handler::RenameHandler;
-    use tower_lsp::lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams};
+fn test_event_directive_completion_offers_event_locals() {
+    use angularjs_lsp::handler::CompletionHandler;
 
-    let js = r#"angular.module('app', []).controller('MainCtrl', ['$scope', function($scope) {}]);"#;
-    let html = r#"<div ng-controller="MainCtrl">{{ }}</div>"#;
+    let js = r#"
+angular.module('app', []).controller('ListCtrl', ['$scope', function($scope) {
+    $scope.select = function(item, event) {};
+}]);
+"#;
+    let html = r#"<ul ng-controller="ListCtrl">
+  <li ng-repeat="item in items" ng-click="select(item, )" ng-change="select(item, )">{{ item }}</li>
+</ul>
+<button ng-click="select(null, )" title="x">ok</button>"#;
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(index.clone()));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(index.clone(), js_analyzer.clone());
+    js_analyzer.analyze_document(&Url::parse("file:///test.js").unwrap(), js);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    html_analyzer.analyze_document(&html_uri, html);
 
-    let index = analyze_js_and_html(js, html);
-    let handler = RenameHandler::new(index);
-    let js_uri = Url::parse("file:///test.js").unwrap();
+    // ng-click (ng-repeat 内) → $event と $index
+    assert_eq!(html_analyzer.event_directive_at(html, 1, 55).as_deref(), Some("ng-click"));
+    let handler = CompletionHandler::new(index);
+    let labels: Vec<String> = handler
+        .complete_event_locals(&html_uri, 1)
+        .into_iter()
+        .map(|item| item.label)
+        .collect();
+    assert_eq!(labels, vec!["$event", "$index"]);
 
-    let needle = "controller('";
-    let col = js.find(needle).unwrap() + needle.len() + 1;
-    let params = TextDocumentPositionParams {
-        text_document: TextDocumentIdentifier { uri: js_uri.clone() },
-        position: Position { line: 0, character: col as u32 },
+    // ng-change はイベントオブジェクトを渡さない
+    assert_eq!(html_analyzer.event_directive_at(html, 1, 82), None);
+
+    // ng-repeat の外 → $event のみ
+    assert_eq!(html_analyzer.event_directive_at(html, 3, 31).as_deref(), Some("ng-click"));
+    let labels: Vec<String> = handler
+        .complete_event_locals(&html_uri, 3)
+        .into_iter()
+        .map(|item| item.label)
+        .collect();
+    assert_eq!(labels, vec!["$event"]);
+    // 閉じた後の別の属性値の中は対象外
+    assert_eq!(html_analyzer.event_directive_at(html, 3, 41), None);
+
+    // 列は UTF-16。複数行の属性値は前の行の開きクォートまで遡る
+    let html = "<button title=\"保存\" ng-click=\"save(\n  item, )\">ok</button>";
+    let col = "<button title=\"保存\" ng-click=\"".encode_utf16().count() as u32;
+    assert_eq!(html_analyzer.event_directive_at(html, 0, col).as_deref(), Some("ng-click"));
+    assert_eq!(html_analyzer.event_directive_at(html, 1, 8).as_deref(), Some("ng-click"));
+}
+
+#[test]
+fn test_member_chain_at_cursor_handles_calls_and_brackets() {
+    use angularjs_lsp::server::workspace::{get_member_chain_at_cursor, MemberChain};
+
+    let chain = |text: &str| get_member_chain_at_cursor(text, 0, text.len() as u32);
+    let path = |p: &str| Some(MemberChain::Path(p.to_string()));
+
+    assert_eq!(chain("    UserService."), path("UserService"));
+    assert_eq!(chain("    $scope.user.address."), path("$scope.user.address"));
+    // 入力中のプロパティ名は除く
+    assert_eq!(chain("    $scope.user.na"), path("$scope.user"));
+    assert_eq!(chain("    vm?.profile."), path("vm.profile"));
+    // 引数の中のチェーンは、その引数だけを見る
+    assert_eq!(chain("    save(items[0], UserService."), path("UserService"));
+    assert_eq!(chain("    var svc = $injector.get('UserService')."), path("UserService"));
+
+    // 呼び出し結果・添字・リテラルのメンバーは型が分からない
+    assert_eq!(chain("    UserService.getAll()."), Some(MemberChain::Dynamic));
+    assert_eq!(chain("    UserService.getAll().th"), Some(MemberChain::Dynamic));
+    assert_eq!(chain("    UserService.getAll().then(fn).catch."), Some(MemberChain::Dynamic));
+    assert_eq!(chain("    data['users']."), Some(MemberChain::Dynamic));
+    assert_eq!(chain("    'abc'."), Some(MemberChain::Dynamic));
+
+    // `.` の直後でない位置
+    assert_eq!(chain("    UserService.getAll().then("), None);
+    assert_eq!(chain("    var total = 1."), None);
+    assert_eq!(chain("    Use"), None);
+}
+
+#[test]
+fn test_analysis_failure_is_reported_as_info_diagnostic() {
+    use angularjs_lsp::config::DiagnosticsConfig;
+    use angularjs_lsp::handler::DiagnosticsHandler;
+    use angularjs_lsp::model::AnalysisFailure;
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    let js_source = r#"angular.module('app', []).controller('MainCtrl', function($scope) {});"#;
+    let index = analyze_js(js_source);
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    assert!(AngularJsAnalyzer::new(Arc::clone(&index)).analyze_document(&js_uri, js_source));
+
+    let skipped = |index: &Arc<Index>| {
+        DiagnosticsHandler::new(Arc::clone(index), DiagnosticsConfig::default())
+            .diagnose_js(&js_uri)
+            .into_iter()
+            .filter(|d| d.message == "AngularJS analysis skipped: parse failed")
+            .collect::<Vec<_>>()
     };
+    assert!(skipped(&index).is_empty());
 
-    let response = handler
-        .prepare_rename(params)
-        .expect("controller 名上では prepareRename が範囲を返すべき");
-    // Range が返ること自体を確認 (具体的な値はスキップ)
-    match response {
-        tower_lsp::lsp_types::PrepareRenameResponse::Range(_) => {}
-        _ => panic!("Range レスポンスが返るべき"),
-    }
+    // 失敗を記録すると info 診断が出て、前回の解析結果は残る
+    index.diagnostics.set_analysis_failure(js_uri.clone(), AnalysisFailure::ParseFailed);
+    let diagnostics = skipped(&index);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::INFORMATION));
+    assert!(has_definition(&index, "MainCtrl", SymbolKind::Controller));
+
+    // 次の解析が成功したら消える
+    index.diagnostics.clear_analysis_failure(&js_uri);
+    assert!(skipped(&index).is_empty());
+}
+
+#[test]
+fn test_dump_symbol_lists_definitions_and_html_references_with_controllers() {
+    let js_source = r#"
+angular.module('app', [])
+    .controller('MainCtrl', ['$scope', function($scope) {
+        $scope.user = {};
+    }]);
+"#;
+    let html_source = r#"<div ng-controller="MainCtrl">
+  <p>{{ user }}</p>
+</div>
+<p>{{ user }}</p>
+"#;
+    let index = analyze_html(js_source, html_source);
+
+    let dump = index.dump_symbol("MainCtrl.$scope.user");
+    assert_eq!(dump.definitions.len(), 1);
+    assert_eq!(dump.definitions[0].kind, "scope property");
+    assert_eq!(dump.definitions[0].controller.as_deref(), Some("MainCtrl"));
+
+    let html_refs: Vec<(u32, &[String], bool)> = dump
+        .html_scope_references
+        .iter()
+        .map(|r| (r.line, r.controllers.as_slice(), r.resolved))
+        .collect();
+    // コントローラーの外にある参照は解決されない
+    assert_eq!(
+        html_refs,
+        vec![(1, &["MainCtrl".to_string()][..], true), (3, &[][..], false)]
+    );
+
+    assert!(index.dump_symbol("UnknownCtrl").definitions.is_empty());
 }
@@ -0,0 +1,151 @@
+//! call hierarchy ハンドラの統合テスト。
+//!
+//! コントローラーメソッドからサービスメソッドへの DI 越しの呼び出しが
+//! incoming / outgoing の両方向で解決できるかを検証する。
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    CallHierarchyIncomingCallsParams, CallHierarchyItem, CallHierarchyOutgoingCallsParams,
+    CallHierarchyPrepareParams, Position, TextDocumentIdentifier, TextDocumentPositionParams,
+    Url,
+};
+
+use angularjs_lsp::analyzer::html::HtmlAngularJsAnalyzer;
+use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
+use angularjs_lsp::handler::CallHierarchyHandler;
+use angularjs_lsp::index::Index;
+
+const SOURCE: &str = r#"angular.module('app', [])
+.service('UserService', function($http) {
+    this.getAll = function() {
+        return $http.get('/api/users');
+    };
+})
+.controller('MainCtrl', ['$scope', 'UserService', function($scope, UserService) {
+    $scope.load = function() {
+        return UserService.getAll();
+    };
+    UserService.getAll();
+}]);
+"#;
+
+fn handler() -> (CallHierarchyHandler, Url) {
+    let index = Arc::new(Index::new());
+    let analyzer = AngularJsAnalyzer::new(Arc::clone(&index));
+    let uri = Url::parse("file:///test.js").unwrap();
+    analyzer.analyze_document(&uri, SOURCE);
+
+    let documents = Arc::new(DashMap::new());
+    documents.insert(uri.clone(), SOURCE.to_string());
+    (CallHierarchyHandler::new(index, documents, analyzer.tree_cache()), uri)
+}
+
+fn position_of(needle: &str, occurrence: usize) -> Position {
+    let offset = SOURCE.match_indices(needle).nth(occurrence).unwrap().0;
+    let before = &SOURCE[..offset];
+    let line = before.matches('\n').count() as u32;
+    let col = (offset - before.rfind('\n').map_or(0, |i| i + 1)) as u32;
+    Position::new(line, col)
+}
+
+fn prepare(handler: &CallHierarchyHandler, uri: &Url, position: Position) -> Vec<CallHierarchyItem> {
+    handler
+        .prepare(CallHierarchyPrepareParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position,
+            },
+            work_done_progress_params: Default::default(),
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn prepare_resolves_service_method_from_call_site() {
+    let (handler, uri) = handler();
+    let items = prepare(&handler, &uri, position_of("getAll()", 0));
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "UserService.getAll");
+    assert_eq!(items[0].selection_range.start, position_of("getAll =", 0));
+}
+
+#[test]
+fn incoming_calls_are_rounded_to_calling_method() {
+    let (handler, uri) = handler();
+    let item = prepare(&handler, &uri, position_of("getAll =", 0)).remove(0);
+
+    let calls = handler
+        .incoming_calls(CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let callers: Vec<&str> = calls.iter().map(|c| c.from.name.as_str()).collect();
+    assert_eq!(callers, vec!["MainCtrl.$scope.load", "MainCtrl"]);
+    assert_eq!(calls[0].from_ranges[0].start, position_of("getAll()", 0));
+    assert_eq!(calls[1].from_ranges[0].start, position_of("getAll()", 1));
+}
+
+#[test]
+fn outgoing_calls_cross_di_boundary() {
+    let (handler, uri) = handler();
+    let item = prepare(&handler, &uri, position_of("load", 0)).remove(0);
+    assert_eq!(item.name, "MainCtrl.$scope.load");
+
+    let calls = handler
+        .outgoing_calls(CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].to.name, "UserService.getAll");
+    assert_eq!(calls[0].from_ranges[0].start, position_of("getAll()", 0));
+}
+
+#[test]
+fn incoming_calls_include_html_template_callers() {
+    // 列は JS がバイト、HTML とクライアントが UTF-16。
+    // マルチバイト文字の後ろにある位置でも揃えて比較する
+    let js = "angular.module('app', [])\n.controller('FormCtrl', function() {\n    /* 保存 */ this.save = function() {};\n});\n";
+    let html = "<div ng-controller=\"FormCtrl as vm\">\n  <button title=\"保存\" ng-click=\"vm.save()\">ok</button>\n</div>\n";
+
+    let index = Arc::new(Index::new());
+    let analyzer = Arc::new(AngularJsAnalyzer::new(Arc::clone(&index)));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(Arc::clone(&index), Arc::clone(&analyzer));
+    let js_uri = Url::parse("file:///form.js").unwrap();
+    let html_uri = Url::parse("file:///form.html").unwrap();
+    analyzer.analyze_document(&js_uri, js);
+    html_analyzer.analyze_document(&html_uri, html);
+
+    let documents = Arc::new(DashMap::new());
+    documents.insert(js_uri.clone(), js.to_string());
+    documents.insert(html_uri.clone(), html.to_string());
+    let handler = CallHierarchyHandler::new(index, documents, analyzer.tree_cache());
+
+    let utf16_position = |source: &str, line: u32, needle: &str| {
+        let text = source.lines().nth(line as usize).unwrap();
+        Position::new(line, text[..text.find(needle).unwrap()].encode_utf16().count() as u32)
+    };
+    let items = prepare(&handler, &js_uri, utf16_position(js, 2, "save"));
+    assert_eq!(items.len(), 1, "{:?}", items);
+    assert_eq!(items[0].name, "FormCtrl.save");
+
+    let calls = handler
+        .incoming_calls(CallHierarchyIncomingCallsParams {
+            item: items[0].clone(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let html_call = calls
+        .iter()
+        .find(|call| call.from.uri == html_uri)
+        .expect("HTML の呼び出し元");
+    assert_eq!(html_call.from.name, "form.html");
+    assert_eq!(html_call.from_ranges[0].start, utf16_position(html, 1, "save"));
+}
@@ -6,6 +6,8 @@
 use std::sync::Arc;
 use tower_lsp::lsp_types::{ParameterLabel, Url};
 
+use angularjs_lsp::analyzer::html::HtmlAngularJsAnalyzer;
+use angularjs_lsp::analyzer::html::parser::HtmlParser;
 use angularjs_lsp::analyzer::js::AngularJsAnalyzer;
 use angularjs_lsp::handler::SignatureHelpHandler;
 use angularjs_lsp::index::Index;
@@ -44,7 +46,7 @@ function callIt() {
 
     let handler = SignatureHelpHandler::new(index);
     let help = handler
-        .signature_help(&uri, 7, paren_pos, source)
+        .signature_help(&uri, 7, paren_pos, source, None)
         .expect("signature help が返るべき (service method)");
 
     assert_eq!(help.signatures.len(), 1, "signature が 1 つ");
@@ -88,7 +90,7 @@ function callIt() {
 
     let handler = SignatureHelpHandler::new(index);
     let help = handler
-        .signature_help(&uri, 7, comma_pos, source)
+        .signature_help(&uri, 7, comma_pos, source, None)
         .expect("signature help が返るべき");
 
     assert_eq!(help.active_parameter, Some(1), "第2引数位置を示す");
@@ -112,7 +114,7 @@ function caller() {
     let paren_pos = line_text.find('(').unwrap() as u32 + 1;
 
     let handler = SignatureHelpHandler::new(index);
-    let help = handler.signature_help(&uri, 4, paren_pos, source);
+    let help = handler.signature_help(&uri, 4, paren_pos, source, None);
 
     assert!(
         help.is_none(),
@@ -133,7 +135,218 @@ angular.module('app', [])
 
     // 1 行目 (空行) の col 0 にカーソル
     let handler = SignatureHelpHandler::new(index);
-    let help = handler.signature_help(&uri, 0, 0, source);
+    let help = handler.signature_help(&uri, 0, 0, source, None);
 
     assert!(help.is_none(), "呼び出し外では signatureHelp は None");
 }
+
+#[test]
+fn signature_help_resolves_callee_like_hover() {
+    // `vm.save` は名前だけでは引けないが、解析時に記録された参照
+    // (`FormCtrl.save`) から hover / definition と同じシンボルに解決される
+    let source = r#"
+angular.module('app', [])
+.controller('FormCtrl', function() {
+    var vm = this;
+    vm.save = function(form, options) {};
+    vm.submit = function() {
+        vm.save(vm.form, {});
+    };
+});
+"#;
+    let (index, uri) = analyze_js_source(source);
+
+    let line_text = "        vm.save(vm.form, {});";
+    let comma_pos = line_text.find(',').unwrap() as u32 + 1;
+
+    let handler = SignatureHelpHandler::new(index);
+    let help = handler
+        .signature_help(&uri, 6, comma_pos, source, None)
+        .expect("signature help が返るべき (controller as の this メソッド)");
+
+    assert_eq!(parameter_names(&help), vec!["form", "options"]);
+    assert_eq!(help.active_parameter, Some(1));
+}
+
+/// 1 番目のシグネチャのパラメータ名一覧
+fn parameter_names(help: &tower_lsp::lsp_types::SignatureHelp) -> Vec<String> {
+    help.signatures[0]
+        .parameters
+        .as_ref()
+        .expect("parameters があるべき")
+        .iter()
+        .map(|p| match &p.label {
+            ParameterLabel::Simple(s) => s.clone(),
+            ParameterLabel::LabelOffsets(_) => String::new(),
+        })
+        .collect()
+}
+
+#[test]
+fn signature_help_for_arrow_function_parameters() {
+    // アロー関数・デフォルト引数・分割代入・残余引数でも引数名が並ぶ
+    let js = r#"
+angular.module('app', [])
+.controller('UserCtrl', function($scope) {
+    var vm = this;
+    $scope.save = (user, opts = {}) => {};
+    $scope.remove = user => {};
+    vm.update = ({ id, name }, [first], ...rest) => {};
+    $scope.save();
+});
+"#;
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(Arc::clone(&index)));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(Arc::clone(&index), Arc::clone(&js_analyzer));
+    let js_uri = Url::parse("file:///test.js").unwrap();
+    js_analyzer.analyze_document(&js_uri, js);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let html = "<div ng-controller=\"UserCtrl as vm\">\n  <button ng-click=\"remove(); vm.update(\"></button>\n</div>\n";
+    html_analyzer.analyze_document(&html_uri, html);
+    let handler = SignatureHelpHandler::new(index);
+
+    // JS 内の $scope.save(
+    let line_text = "    $scope.save();";
+    let col = line_text.find('(').unwrap() as u32 + 1;
+    let help = handler.signature_help(&js_uri, 7, col, js, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["user", "opts"]);
+
+    // 括弧なしの単一引数
+    let line = html.lines().nth(1).unwrap();
+    let col = line.find("remove(").unwrap() as u32 + "remove(".len() as u32;
+    let help = handler.signature_help(&html_uri, 1, col, html, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["user"]);
+
+    // controller as のメソッド
+    let col = line.find("vm.update(").unwrap() as u32 + "vm.update(".len() as u32;
+    let help = handler.signature_help(&html_uri, 1, col, html, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["{ id, name }", "[first]", "...rest"]);
+}
+
+#[test]
+fn signature_help_for_user_defined_filter_arguments_in_html() {
+    // フィルター関数の第1引数 (入力値) を除いた引数が JSDoc @param の説明付きで出る
+    let js = r#"
+angular.module('app', [])
+/**
+ * 文字列を切り詰める
+ * @param {string} input 入力値
+ * @param {number} length 最大文字数
+ * @param {string} [suffix='...'] 末尾に付ける文字列
+ */
+.filter('truncate', function() {
+    return function(input, length, suffix) {
+        return input.slice(0, length) + suffix;
+    };
+});
+"#;
+    let (index, _) = analyze_js_source(js);
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let html = "<p>{{ title | truncate:10:'...' }}</p>\n";
+    let handler = SignatureHelpHandler::new(index);
+
+    // `truncate:` の直後 → 1 番目の引数 (length)
+    let col = html.find("truncate:").unwrap() as u32 + "truncate:".len() as u32;
+    let help = handler
+        .signature_help(&html_uri, 0, col, html, None)
+        .expect("フィルター引数で signature help が返るべき");
+    assert_eq!(parameter_names(&help), vec!["length", "suffix"]);
+    assert_eq!(help.active_parameter, Some(0));
+    assert_eq!(help.signatures[0].label, "input | truncate:length:suffix");
+    let param_doc = help.signatures[0].parameters.as_ref().unwrap()[0]
+        .documentation
+        .clone();
+    assert!(
+        matches!(param_doc, Some(tower_lsp::lsp_types::Documentation::MarkupContent(ref m)) if m.value == "最大文字数"),
+        "@param の説明が付く: {:?}",
+        param_doc
+    );
+
+    // 2 つ目の `:` の後 → 2 番目の引数 (suffix)
+    let col = html.find("'...'").unwrap() as u32;
+    let help = handler.signature_help(&html_uri, 0, col, html, None).unwrap();
+    assert_eq!(help.active_parameter, Some(1));
+
+    // フィルター名の入力中 (`:` の前) は対象外
+    let col = html.find("truncate").unwrap() as u32 + 3;
+    assert!(handler.signature_help(&html_uri, 0, col, html, None).is_none());
+}
+
+#[test]
+fn signature_help_for_builtin_filter_arguments_in_html() {
+    let (index, _) = analyze_js_source("angular.module('app', []);\n");
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    let html = r#"<li ng-repeat="item in items | orderBy:'name':true">{{ item.at | date: }}</li>"#;
+    let handler = SignatureHelpHandler::new(index);
+
+    // 属性値中の組み込みフィルター (2 番目の引数)
+    let col = html.find("true").unwrap() as u32;
+    let help = handler.signature_help(&html_uri, 0, col, html, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["expression", "reverse", "comparator"]);
+    assert_eq!(help.active_parameter, Some(1));
+
+    // 補間内の `date:` 直後
+    let col = html.find("date:").unwrap() as u32 + "date:".len() as u32;
+    let help = handler.signature_help(&html_uri, 0, col, html, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["format", "timezone"]);
+    assert_eq!(help.active_parameter, Some(0));
+
+    // `||` は論理和でありパイプではない
+    let html = "<p ng-show=\"a || b:\"></p>";
+    let col = html.find("b:").unwrap() as u32 + 2;
+    assert!(handler.signature_help(&html_uri, 0, col, html, None).is_none());
+}
+
+#[test]
+fn signature_help_in_embedded_script_uses_script_local_position() {
+    let html = r#"<div ng-controller="FormCtrl">
+  <script>var first = 1;</script>
+  <p>{{ save(item, ) }}</p>
+  <script>
+    angular.module('app', []).controller('FormCtrl', function($scope) {
+      $scope.save = function(item, force) {};
+      $scope.save(item, );
+    });
+  </script>
+</div>
+"#;
+    let index = Arc::new(Index::new());
+    let js_analyzer = Arc::new(AngularJsAnalyzer::new(Arc::clone(&index)));
+    let html_analyzer = HtmlAngularJsAnalyzer::new(Arc::clone(&index), Arc::clone(&js_analyzer));
+    let html_uri = Url::parse("file:///test.html").unwrap();
+    for script in html_analyzer.analyze_document_and_extract_scripts(&html_uri, html) {
+        js_analyzer.analyze_embedded_script(&html_uri, &script.source, script.line_offset);
+    }
+
+    // カーソルを含む 2 つ目の script を選び、script ローカルの位置に変換する
+    let line_text = "      $scope.save(item, );";
+    let col = line_text.find(", )").unwrap() as u32 + 2;
+    let tree = HtmlParser::new().parse(html).unwrap();
+    let (script, local_line, local_col) =
+        HtmlAngularJsAnalyzer::script_at(html, Some(&tree), 6, col).unwrap();
+    assert_eq!(script.line_offset, 3);
+    assert_eq!((local_line, local_col), (3, col));
+    // 先頭行は `<script>` の後ろからの列
+    let (_, local_line, local_col) = HtmlAngularJsAnalyzer::script_at(html, None, 1, 14).unwrap();
+    assert_eq!((local_line, local_col), (0, 4));
+    assert!(HtmlAngularJsAnalyzer::script_at(html, None, 2, 10).is_none());
+
+    // 列は LSP の UTF-16 で受け取り、script ローカルのバイト列で返す
+    let html_ja = "<script>var 名前 = f(1, );</script>\n";
+    let utf16_col = "<script>var 名前 = f(1, ".encode_utf16().count() as u32;
+    let (_, local_line, local_col) =
+        HtmlAngularJsAnalyzer::script_at(html_ja, None, 0, utf16_col).unwrap();
+    assert_eq!((local_line, local_col), (0, "var 名前 = f(1, ".len() as u32));
+
+    let handler = SignatureHelpHandler::new(index);
+    let help = handler
+        .signature_help(&html_uri, 6, col, html, None)
+        .expect("script 内の $scope メソッド呼び出しで signature help が返るべき");
+    assert_eq!(parameter_names(&help), vec!["item", "force"]);
+    assert_eq!(help.active_parameter, Some(1));
+
+    // script の外 (テンプレートの式) は従来どおりコントローラーから解決する
+    let col = "  <p>{{ save(item, ".len() as u32;
+    let help = handler.signature_help(&html_uri, 2, col, html, None).unwrap();
+    assert_eq!(parameter_names(&help), vec!["item", "force"]);
+}
@@ -77,6 +77,16 @@ export async function activate(context: vscode.ExtensionContext) {
         context.subscriptions.push(openLocationDisposable);
     }
 
+    const showReferencesDisposable = await safeRegisterCommand(
+        'angularjs.showReferences',
+        async (uri: string, position: LspLocation['range']['start'], locations: LspLocation[]) => {
+            await handleShowReferences(uri, position, locations);
+        }
+    );
+    if (showReferencesDisposable) {
+        context.subscriptions.push(showReferencesDisposable);
+    }
+
     const restartDisposable = await safeRegisterCommand(
         'angularjs.restartServer',
         async () => {
@@ -117,6 +127,16 @@ export async function activate(context: vscode.ExtensionContext) {
         context.subscriptions.push(refreshCacheDisposable);
     }
 
+    const ignoreFolderDisposable = await safeRegisterCommand(
+        'angularjs.ignoreFolder',
+        async (folder?: vscode.Uri) => {
+            await ignoreFolderCommand(outputChannel, folder);
+        }
+    );
+    if (ignoreFolderDisposable) {
+        context.subscriptions.push(ignoreFolderDisposable);
+    }
+
     // Ensure dependencies are installed (non-blocking for dialogs)
     await ensureDependencies(context, outputChannel);
 
@@ -504,6 +524,8 @@ async function startServer(
     const clientOptions: LanguageClientOptions = {
         documentSelector: [
             { scheme: 'file', language: 'javascript' },
+            { scheme: 'file', language: 'typescript' },
+            { scheme: 'file', language: 'typescriptreact' },
             { scheme: 'file', language: 'html' },
             { scheme: 'file', language: 'django-html' },
             { scheme: 'file', language: 'jinja-html' },
@@ -596,6 +618,28 @@ async function handleOpenLocation(
     }
 }
 
+/**
+ * Open the references peek view for a "N references" CodeLens
+ */
+async function handleShowReferences(
+    uri: string,
+    position: LspLocation['range']['start'],
+    locations: LspLocation[]
+): Promise<void> {
+    const toRange = (range: LspLocation['range']) =>
+        new vscode.Range(
+            new vscode.Position(range.start.line, range.start.character),
+            new vscode.Position(range.end.line, range.end.character)
+        );
+
+    await vscode.commands.executeCommand(
+        'editor.action.showReferences',
+        vscode.Uri.parse(uri),
+        new vscode.Position(position.line, position.character),
+        locations.map((loc) => new vscode.Location(vscode.Uri.parse(loc.uri), toRange(loc.range)))
+    );
+}
+
 async function openSingleLocation(location: LspLocation): Promise<void> {
     const uri = vscode.Uri.parse(location.uri);
     const range = new vscode.Range(
@@ -681,6 +725,52 @@ async function refreshCacheCommand(
     }
 }
 
+/**
+ * Command to remove a folder from the AngularJS index for this session
+ */
+async function ignoreFolderCommand(
+    outputChannel: vscode.OutputChannel,
+    folder?: vscode.Uri
+): Promise<void> {
+    if (!client) {
+        vscode.window.showWarningMessage('AngularJS LSP: Language server is not running');
+        return;
+    }
+
+    if (!folder) {
+        const picked = await vscode.window.showOpenDialog({
+            canSelectFiles: false,
+            canSelectFolders: true,
+            canSelectMany: false,
+            openLabel: 'Ignore',
+        });
+        folder = picked?.[0];
+    }
+    if (!folder) {
+        return;
+    }
+
+    try {
+        const result = await client.sendRequest<{ cleared?: number } | null>(
+            'workspace/executeCommand',
+            {
+                command: 'angularjs-lsp.ignoreFolder',
+                arguments: [folder.toString()],
+            }
+        );
+        const cleared = result?.cleared ?? 0;
+        outputChannel.appendLine(
+            `AngularJS LSP: Ignoring ${folder.fsPath} (${cleared} files removed from index)`
+        );
+        vscode.window.showInformationMessage(
+            `AngularJS: ignoring ${vscode.workspace.asRelativePath(folder)} until restart`
+        );
+    } catch (error) {
+        outputChannel.appendLine(`AngularJS LSP: Failed to ignore folder: ${error}`);
+        vscode.window.showErrorMessage(`Failed to ignore folder: ${error}`);
+    }
+}
+
 export async function deactivate(): Promise<void> {
     await stopServer();
 }